@@ -1,19 +1,39 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{StdResult, Storage};
+use cosmwasm_std::{Addr, CosmosMsg, Decimal, StdResult, Storage, Uint128};
 use cw_storage_plus::Item;
-use rujira_rs::bow::{InstantiateMsg, Strategies, Strategy};
+use rujira_rs::bow::{GaugeMsg, InstantiateMsg, PokeConfig, Strategies, Strategy};
 
 use crate::ContractError;
 
 #[cw_serde]
 pub struct Config {
     pub strategy: Strategies,
+    /// The maximum change in share redemption value permitted when the strategy is changed
+    #[serde(default = "default_migration_epsilon")]
+    pub migration_epsilon: Decimal,
+    /// Oracles and bounty for permissionless re-quoting of the strategy
+    #[serde(default)]
+    pub poke: Option<PokeConfig>,
+    /// Swaps, deposits and pokes are paused. Set when a strategy invariant check fails
+    #[serde(default)]
+    pub paused: bool,
+    /// The gauge that LP share changes are reported to, for external incentives
+    #[serde(default)]
+    pub gauge: Option<Addr>,
+}
+
+fn default_migration_epsilon() -> Decimal {
+    Decimal::bps(1)
 }
 
 impl From<InstantiateMsg> for Config {
     fn from(v: InstantiateMsg) -> Self {
         Self {
             strategy: v.strategy,
+            migration_epsilon: default_migration_epsilon(),
+            poke: None,
+            paused: false,
+            gauge: None,
         }
     }
 }
@@ -24,12 +44,43 @@ impl Config {
     }
 
     pub fn validate(&self) -> Result<(), ContractError> {
+        if self.migration_epsilon > Decimal::one() {
+            return Err(ContractError::Invalid("migration_epsilon".to_string()));
+        }
+        if let Some(poke) = &self.poke {
+            if poke.threshold > Decimal::one() {
+                return Err(ContractError::Invalid("poke threshold".to_string()));
+            }
+            if poke.bounty > Decimal::one() {
+                return Err(ContractError::Invalid("poke bounty".to_string()));
+            }
+        }
         Ok(self.strategy.validate()?)
     }
 
     pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
         Item::new("config").save(storage, self)
     }
+
+    /// Reports the shares minted to or burned by `owner` to the gauge, if one is set
+    pub fn checkpoint(
+        &self,
+        owner: &Addr,
+        minted: Uint128,
+        burned: Uint128,
+    ) -> StdResult<Option<CosmosMsg>> {
+        self.gauge
+            .as_ref()
+            .map(|gauge| {
+                GaugeMsg::Checkpoint {
+                    owner: owner.to_string(),
+                    minted,
+                    burned,
+                }
+                .call(gauge)
+            })
+            .transpose()
+    }
 }
 
 #[cfg(test)]
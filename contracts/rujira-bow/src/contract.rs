@@ -1,25 +1,33 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coin, coins, to_json_binary, BankMsg, Binary, CosmosMsg, Deps, DepsMut, Empty, Env,
-    MessageInfo, Response,
+    coin, coins, ensure, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps,
+    DepsMut, Empty, Env, MessageInfo, Response, Uint128,
 };
 use cw2::set_contract_version;
 use cw_utils::{must_pay, one_coin, NativeBalance};
 use rujira_rs::bow::{
-    ExecuteMsg, InstantiateMsg, QueryMsg, Strategies, Strategy, StrategyResponse, StrategyState,
-    SudoMsg,
+    ExecuteMsg, GaugeMsg, InstantiateMsg, LadderCursor, LadderLevel, LadderResponse, QueryMsg,
+    QuoteRequest, QuoteResponse, Strategies, Strategy, StrategyError, StrategyResponse,
+    StrategyState, SudoMsg, Xyk, LADDER_LEVELS,
 };
-use rujira_rs::TokenFactory;
+use rujira_rs::{Oracle, TokenFactory};
 
 use crate::config::Config;
 use crate::error::ContractError;
-use crate::events::{event_deposit, event_swap, event_withdraw};
+use crate::events::{
+    event_circuit_breaker, event_deposit, event_poke, event_set_strategy, event_set_strategy_param,
+    event_swap, event_withdraw, event_zap,
+};
 
 // version info for migration info
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// LP shares minted to the contract on the first deposit and never redeemed, so that no depositor
+/// can hold the entire supply and inflate the share price
+pub const MINIMUM_LIQUIDITY: Uint128 = Uint128::new(1000);
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -52,7 +60,9 @@ pub fn execute(
             to,
             callback,
         } => {
+            ensure!(!config.paused, ContractError::Paused {});
             let offer = one_coin(&info)?;
+            let before = state.clone();
             let (fee, surplus) =
                 config
                     .strategy
@@ -60,8 +70,11 @@ pub fn execute(
             let to_address = to
                 .map(|x| deps.api.addr_validate(x.as_str()))
                 .transpose()?
-                .unwrap_or(info.sender);
+                .unwrap_or(info.sender.clone());
 
+            if let Err(err) = config.strategy.check_invariants(&before, &state) {
+                return trip(deps, config, "swap", err, info.sender, vec![offer]);
+            }
             config.strategy.commit_state(deps, &state)?;
             let event = event_swap(offer, min_return.clone(), fee, surplus);
 
@@ -81,24 +94,46 @@ pub fn execute(
             min_return,
             callback,
         } => {
+            ensure!(!config.paused, ContractError::Paused {});
             let t = TokenFactory::new(&env, config.strategy.denom().as_str());
-            let minted = config
+            let initial = config.strategy.shares(&state).is_zero();
+            let before = state.clone();
+            let mut minted = config
                 .strategy
-                .deposit(&mut state, NativeBalance(info.funds))?;
+                .deposit(&mut state, NativeBalance(info.funds.clone()))?;
 
+            if let Err(err) = config.strategy.check_invariants(&before, &state) {
+                return trip(deps, config, "deposit", err, info.sender, info.funds);
+            }
             config.strategy.commit_state(deps, &state)?;
 
+            let mut response = Response::default();
+            if initial {
+                minted = minted
+                    .checked_sub(MINIMUM_LIQUIDITY)
+                    .ok()
+                    .filter(|x| !x.is_zero())
+                    .ok_or(ContractError::InsufficientInitialDeposit {
+                        min: MINIMUM_LIQUIDITY,
+                    })?;
+                response = response
+                    .add_message(t.mint_msg(MINIMUM_LIQUIDITY, env.contract.address.clone()));
+            }
+
             if let Some(min) = min_return {
                 if minted.lt(&min) {
                     return Err(ContractError::InsufficientFunds {});
                 };
             }
 
+            let response =
+                response.add_messages(config.checkpoint(&info.sender, minted, Uint128::zero())?);
+
             match callback {
-                None => Ok(Response::default()
+                None => Ok(response
                     .add_message(t.mint_msg(minted, info.sender.clone()))
                     .add_event(event_deposit(info.sender, coin(minted.u128(), t.denom())))),
-                Some(cb) => Ok(Response::default()
+                Some(cb) => Ok(response
                     .add_message(t.mint_msg(minted, env.contract.address))
                     .add_message(cb.to_message(
                         &info.sender,
@@ -108,21 +143,54 @@ pub fn execute(
                     .add_event(event_deposit(info.sender, coin(minted.u128(), t.denom())))),
             }
         }
+        ExecuteMsg::Zap { min_shares } => {
+            ensure!(!config.paused, ContractError::Paused {});
+            let t = TokenFactory::new(&env, config.strategy.denom().as_str());
+            let offer = one_coin(&info)?;
+            let before = state.clone();
+            let (fee, minted) = config.strategy.zap(&mut state, offer.clone())?;
+
+            if let Err(err) = config.strategy.check_invariants(&before, &state) {
+                return trip(deps, config, "zap", err, info.sender, vec![offer]);
+            }
+            config.strategy.commit_state(deps, &state)?;
+
+            if let Some(min) = min_shares {
+                if minted.lt(&min) {
+                    return Err(ContractError::InsufficientFunds {});
+                };
+            }
+
+            Ok(Response::default()
+                .add_messages(config.checkpoint(&info.sender, minted, Uint128::zero())?)
+                .add_message(t.mint_msg(minted, info.sender.clone()))
+                .add_event(event_zap(
+                    info.sender,
+                    offer,
+                    fee,
+                    coin(minted.u128(), t.denom()),
+                )))
+        }
         ExecuteMsg::Withdraw { callback } => {
             let t = TokenFactory::new(&env, config.strategy.denom().as_str());
             let balance = must_pay(&info, t.denom().as_str())?;
             let withdrawn = config.strategy.withdraw(&mut state, balance)?;
             config.strategy.commit_state(deps, &state)?;
+            let response = Response::default().add_messages(config.checkpoint(
+                &info.sender,
+                Uint128::zero(),
+                balance,
+            )?);
 
             match callback {
-                None => Ok(Response::default()
+                None => Ok(response
                     .add_message(CosmosMsg::Bank(BankMsg::Send {
                         to_address: info.sender.to_string(),
                         amount: withdrawn.clone().into_vec(),
                     }))
                     .add_message(t.burn_msg(balance))
                     .add_event(event_withdraw(info.sender, coin(balance.u128(), t.denom())))),
-                Some(cb) => Ok(Response::default()
+                Some(cb) => Ok(response
                     .add_message(cb.to_message(
                         &info.sender,
                         Empty {},
@@ -132,21 +200,217 @@ pub fn execute(
                     .add_event(event_withdraw(info.sender, coin(balance.u128(), t.denom())))),
             }
         }
+        ExecuteMsg::Poke {} => {
+            ensure!(!config.paused, ContractError::Paused {});
+            let poke = config.poke.clone().ok_or(ContractError::PokeDisabled {})?;
+            let price = poke.oracles.oracle_price(deps.querier)?;
+            let current = config.strategy.price(&state);
+            ensure!(
+                !current.is_zero(),
+                ContractError::Invalid("strategy".to_string())
+            );
+            let deviation = current.abs_diff(price).checked_div(price)?;
+
+            // Fees are retained by the contract outside of the strategy's assets
+            let mut fees = NativeBalance::default();
+            for c in config.strategy.balances(&state).into_vec() {
+                let held = deps
+                    .querier
+                    .query_balance(env.contract.address.as_str(), &c.denom)?;
+                fees += coin(held.amount.saturating_sub(c.amount).u128(), c.denom);
+            }
+            fees.normalize();
+
+            let mut bounty = NativeBalance::default();
+            if deviation.ge(&poke.threshold) {
+                for c in fees.clone().into_vec() {
+                    bounty += coin(c.amount.mul_floor(poke.bounty).u128(), c.denom);
+                }
+                bounty.normalize();
+                fees = fees.sub_saturating(bounty.clone())?;
+            }
+
+            let before = state.clone();
+            let added = config.strategy.poke(&mut state, price, &fees)?;
+            if let Err(err) = config.strategy.check_invariants(&before, &state) {
+                return trip(deps, config, "poke", err, info.sender, vec![]);
+            }
+            config.strategy.commit_state(deps, &state)?;
+
+            let event = event_poke(info.sender.clone(), price, deviation, &added, &bounty);
+            if bounty.is_empty() {
+                return Ok(Response::default().add_event(event));
+            }
+            Ok(Response::default()
+                .add_message(CosmosMsg::Bank(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: bounty.into_vec(),
+                }))
+                .add_event(event))
+        }
+        ExecuteMsg::ClaimRewards {} => {
+            let gauge = config.gauge.ok_or(ContractError::GaugeDisabled {})?;
+            Ok(Response::default().add_message(
+                GaugeMsg::Claim {
+                    owner: info.sender.to_string(),
+                }
+                .call(&gauge)?,
+            ))
+        }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+pub fn sudo(mut deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
     let mut config = Config::load(deps.storage)?;
 
     match msg {
         SudoMsg::SetStrategy(strategy) => {
+            let state = config.strategy.load_state(deps.as_ref(), env.clone())?;
+            let balances = config.strategy.balances(&state);
+            let shares = config.strategy.shares(&state);
+
+            // The receipt token must be retained so that existing shares can be redeemed
+            ensure!(
+                strategy.denom() == config.strategy.denom(),
+                ContractError::Invalid("denom".to_string())
+            );
+
+            let migrated = strategy.migrate_state(&balances, shares)?;
+            let migrated_balances = strategy.balances(&migrated);
+
+            // The new strategy can only allocate assets that the contract holds
+            for c in migrated_balances.clone().into_vec() {
+                let held = deps
+                    .querier
+                    .query_balance(env.contract.address.as_str(), &c.denom)?;
+                ensure!(
+                    held.amount.ge(&c.amount),
+                    ContractError::InsufficientFunds {}
+                );
+            }
+
+            if !shares.is_zero() {
+                let migrated_shares = strategy.shares(&migrated);
+                for c in balances.clone().into_vec() {
+                    let before = Decimal::from_ratio(c.amount, shares);
+                    let after = migrated_balances
+                        .clone()
+                        .into_vec()
+                        .iter()
+                        .find(|x| x.denom == c.denom)
+                        .map(|x| Decimal::checked_from_ratio(x.amount, migrated_shares))
+                        .transpose()?
+                        .unwrap_or_default();
+                    let delta = before.abs_diff(after).checked_div(before)?;
+                    ensure!(
+                        delta.le(&config.migration_epsilon),
+                        ContractError::ShareValueChanged {
+                            denom: c.denom,
+                            delta,
+                            max: config.migration_epsilon,
+                        }
+                    );
+                }
+            }
+
             config.strategy = strategy;
             config.validate()?;
+            config.strategy.commit_state(deps.branch(), &migrated)?;
+            config.save(deps.storage)?;
+            Ok(Response::default().add_event(event_set_strategy(
+                config.strategy.denom(),
+                &migrated_balances,
+                shares,
+            )))
+        }
+        SudoMsg::SetMigrationEpsilon(epsilon) => {
+            config.migration_epsilon = epsilon;
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetPoke(poke) => {
+            config.poke = poke;
+            config.validate()?;
             config.save(deps.storage)?;
             Ok(Response::default())
         }
+        SudoMsg::SetGauge(gauge) => {
+            config.gauge = gauge.map(|x| deps.api.addr_validate(&x)).transpose()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetPaused(paused) => {
+            config.paused = paused;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetFee(fee) => {
+            set_param(deps, config, "fee", fee.to_string(), |x| x.with_fee(fee))
+        }
+        SudoMsg::SetFeeBid(fee) => set_param(
+            deps,
+            config,
+            "fee_bid",
+            fee.map(|x| x.to_string()).unwrap_or_default(),
+            |x| x.with_fee_bid(fee),
+        ),
+        SudoMsg::SetFeeAsk(fee) => set_param(
+            deps,
+            config,
+            "fee_ask",
+            fee.map(|x| x.to_string()).unwrap_or_default(),
+            |x| x.with_fee_ask(fee),
+        ),
+        SudoMsg::SetSkew(step) => set_param(deps, config, "step", step.to_string(), |x| {
+            x.with_step(step)
+        }),
+        SudoMsg::SetMinQuote(min_quote) => {
+            set_param(deps, config, "min_quote", min_quote.to_string(), |x| {
+                x.with_min_quote(min_quote)
+            })
+        }
+    }
+}
+
+/// Updates a parameter of the strategy in place. Parameters only affect pricing, so the
+/// state is retained as-is
+fn set_param(
+    deps: DepsMut,
+    mut config: Config,
+    key: &str,
+    value: String,
+    update: impl FnOnce(Xyk) -> Xyk,
+) -> Result<Response, ContractError> {
+    config.strategy = match config.strategy {
+        Strategies::Xyk(x) => Strategies::Xyk(update(x)),
+    };
+    config.validate()?;
+    config.save(deps.storage)?;
+    Ok(Response::default().add_event(event_set_strategy_param(key, value)))
+}
+
+/// Pauses the pool when a transition fails the strategy's invariant checks. The state is
+/// discarded and any funds sent are returned, so that LP positions are left untouched
+fn trip(
+    deps: DepsMut,
+    mut config: Config,
+    action: &str,
+    err: StrategyError,
+    sender: Addr,
+    refund: Vec<Coin>,
+) -> Result<Response, ContractError> {
+    config.paused = true;
+    config.save(deps.storage)?;
+    let response = Response::default().add_event(event_circuit_breaker(action, err.to_string()));
+    if refund.is_empty() {
+        return Ok(response);
     }
+    Ok(response.add_message(BankMsg::Send {
+        to_address: sender.to_string(),
+        amount: refund,
+    }))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -155,9 +419,34 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractErro
     let state = config.strategy.load_state(deps, env)?;
     match msg {
         QueryMsg::Quote(req) => {
+            // A paused pool offers no liquidity
+            if config.paused {
+                return Ok(to_json_binary(&None::<QuoteResponse>)?);
+            }
             let q = config.strategy.quote(&state, req)?;
             Ok(to_json_binary(&q)?)
         }
+        QueryMsg::Ladder {
+            offer_denom,
+            ask_denom,
+            levels,
+            after,
+        } => {
+            if config.paused {
+                return Ok(to_json_binary(&LadderResponse {
+                    levels: vec![],
+                    next: None,
+                })?);
+            }
+            Ok(to_json_binary(&ladder(
+                &config.strategy,
+                &state,
+                offer_denom,
+                ask_denom,
+                levels.min(LADDER_LEVELS),
+                after,
+            )?)?)
+        }
         QueryMsg::Strategy {} => match (config.strategy, state) {
             (Strategies::Xyk(strategy), StrategyState::Xyk(state)) => {
                 Ok(to_json_binary(&StrategyResponse::Xyk((strategy, state)))?)
@@ -166,6 +455,50 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractErro
     }
 }
 
+/// Quotes the strategy as FIN's market maker iterator does, stopping where it would: when the
+/// strategy has nothing more to offer, or quotes a price that isn't strictly worse than the last
+fn ladder(
+    strategy: &Strategies,
+    state: &StrategyState,
+    offer_denom: String,
+    ask_denom: String,
+    levels: u16,
+    after: Option<LadderCursor>,
+) -> Result<LadderResponse, ContractError> {
+    let (mut min_price, mut data) = match after {
+        Some(cursor) => (Some(cursor.min_price), cursor.data),
+        None => (None, None),
+    };
+    let mut res = LadderResponse {
+        levels: vec![],
+        next: None,
+    };
+    while res.levels.len() < levels as usize {
+        let quote = strategy.quote(
+            state,
+            QuoteRequest {
+                min_price,
+                offer_denom: offer_denom.clone(),
+                ask_denom: ask_denom.clone(),
+                data: data.clone(),
+            },
+        )?;
+        match quote {
+            Some(q) if min_price.map_or(true, |x| q.price < x) => {
+                res.levels.push(LadderLevel {
+                    price: q.price,
+                    size: q.size,
+                });
+                min_price = Some(q.price);
+                data = q.data;
+            }
+            _ => return Ok(res),
+        }
+    }
+    res.next = min_price.map(|min_price| LadderCursor { min_price, data });
+    Ok(res)
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
@@ -176,13 +509,13 @@ pub fn migrate(deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractE
 mod tests {
 
     use super::*;
-    use cosmwasm_std::{Decimal, Event, Uint128};
+    use cosmwasm_std::{Addr, Decimal, Event, StdResult, Uint128};
     use cw_multi_test::{ContractWrapper, Executor};
     use rujira_rs::{
-        bow::{Strategies, Xyk},
-        TokenMetadata,
+        bow::{PokeConfig, Strategies, Xyk},
+        Layer1Asset, TokenMetadata,
     };
-    use rujira_rs_testing::mock_rujira_app;
+    use rujira_rs_testing::{mock_rujira_app, RujiraApp};
 
     #[test]
     fn instantiation() {
@@ -223,9 +556,11 @@ mod tests {
         let mut app = mock_rujira_app();
         let addr = app.api().addr_make("depositor");
         app.init_modules(|router, _api, storage| {
-            router
-                .bank
-                .init_balance(storage, &addr, vec![coin(1000, "ruji"), coin(1000, "usdc")])
+            router.bank.init_balance(
+                storage,
+                &addr,
+                vec![coin(10_000, "ruji"), coin(10_000, "usdc")],
+            )
         })
         .unwrap();
         let owner = app.api().addr_make("owner");
@@ -263,10 +598,10 @@ mod tests {
             addr.clone(),
             contract.clone(),
             &ExecuteMsg::Deposit {
-                min_return: Some(Uint128::from(1001u128)),
+                min_return: Some(Uint128::from(9001u128)),
                 callback: None,
             },
-            &[coin(1000, "ruji"), coin(1000, "usdc")],
+            &[coin(10_000, "ruji"), coin(10_000, "usdc")],
         )
         .unwrap_err();
 
@@ -275,24 +610,31 @@ mod tests {
                 addr.clone(),
                 contract.clone(),
                 &ExecuteMsg::Deposit {
-                    min_return: Some(Uint128::from(1000u128)),
+                    min_return: Some(Uint128::from(9000u128)),
                     callback: None,
                 },
-                &[coin(1000, "ruji"), coin(1000, "usdc")],
+                &[coin(10_000, "ruji"), coin(10_000, "usdc")],
             )
             .unwrap();
 
         res.assert_event(
             &Event::new("wasm-rujira-bow/deposit")
-                .add_attributes(vec![("minted", "1000x/bow-xyk-ruji-usdc")]),
+                .add_attributes(vec![("minted", "9000x/bow-xyk-ruji-usdc")]),
         );
 
         res.assert_event(&Event::new("mint").add_attributes(vec![
-            ("amount", "1000"),
+            ("amount", "9000"),
             ("denom", "x/bow-xyk-ruji-usdc"),
             ("recipient", addr.as_str()),
         ]));
 
+        // The minimum liquidity is locked in the contract
+        res.assert_event(&Event::new("mint").add_attributes(vec![
+            ("amount", "1000"),
+            ("denom", "x/bow-xyk-ruji-usdc"),
+            ("recipient", contract.as_str()),
+        ]));
+
         let res = app
             .execute_contract(
                 addr.clone(),
@@ -344,4 +686,900 @@ mod tests {
                 .add_attributes(vec![("amount", "200"), ("denom", "x/bow-xyk-ruji-usdc")]),
         );
     }
+
+    #[test]
+    fn zap() {
+        let mut app = mock_rujira_app();
+        let addr = app.api().addr_make("depositor");
+        app.init_modules(|router, _api, storage| {
+            router.bank.init_balance(
+                storage,
+                &addr,
+                vec![coin(20_000, "ruji"), coin(20_000, "usdc")],
+            )
+        })
+        .unwrap();
+        let owner = app.api().addr_make("owner");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query));
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner,
+                &InstantiateMsg {
+                    metadata: TokenMetadata {
+                        description: "RUJI-USDC XYK Liquidity Pool Token".to_string(),
+                        display: "LP RUJI-USDC".to_string(),
+                        name: "LP RUJI-USDC".to_string(),
+                        symbol: "LP/RUJI-USDC".to_string(),
+                        uri: None,
+                        uri_hash: None,
+                    },
+                    strategy: Strategies::Xyk(Xyk::new(
+                        "ruji".to_string(),
+                        "usdc".to_string(),
+                        Decimal::permille(1u64),
+                        Uint128::from(Xyk::MIN_MIN_QUOTE),
+                        Decimal::zero(),
+                    )),
+                },
+                &[],
+                "template",
+                None,
+            )
+            .unwrap();
+
+        // The pool must be opened with a deposit of both assets
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::Zap { min_shares: None },
+            &[coin(1_000, "ruji")],
+        )
+        .unwrap_err();
+
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::Deposit {
+                min_return: None,
+                callback: None,
+            },
+            &[coin(10_000, "ruji"), coin(10_000, "usdc")],
+        )
+        .unwrap();
+
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::Zap {
+                min_shares: Some(Uint128::from(489u128)),
+            },
+            &[coin(1_000, "ruji")],
+        )
+        .unwrap_err();
+
+        // 488 ruji is swapped for 465 usdc, and deposited with the remaining 512 ruji
+        let res = app
+            .execute_contract(
+                addr.clone(),
+                contract.clone(),
+                &ExecuteMsg::Zap {
+                    min_shares: Some(Uint128::from(488u128)),
+                },
+                &[coin(1_000, "ruji")],
+            )
+            .unwrap();
+
+        res.assert_event(&Event::new("wasm-rujira-bow/zap").add_attributes(vec![
+            ("offer", "1000ruji"),
+            ("fee", "0usdc"),
+            ("minted", "488x/bow-xyk-ruji-usdc"),
+        ]));
+        res.assert_event(&Event::new("mint").add_attributes(vec![
+            ("amount", "488"),
+            ("denom", "x/bow-xyk-ruji-usdc"),
+            ("recipient", addr.as_str()),
+        ]));
+
+        let res: StrategyResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Strategy {})
+            .unwrap();
+        let StrategyResponse::Xyk((_, state)) = res;
+        assert_eq!(
+            state.reserves(),
+            (Uint128::from(11_000u128), Uint128::from(10_000u128))
+        );
+    }
+
+    #[test]
+    fn set_strategy() {
+        let mut app = mock_rujira_app();
+        let addr = app.api().addr_make("depositor");
+        app.init_modules(|router, _api, storage| {
+            router.bank.init_balance(
+                storage,
+                &addr,
+                vec![coin(10_000, "ruji"), coin(10_000, "usdc")],
+            )
+        })
+        .unwrap();
+        let owner = app.api().addr_make("owner");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo));
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner,
+                &InstantiateMsg {
+                    metadata: TokenMetadata {
+                        description: "RUJI-USDC XYK Liquidity Pool Token".to_string(),
+                        display: "LP RUJI-USDC".to_string(),
+                        name: "LP RUJI-USDC".to_string(),
+                        symbol: "LP/RUJI-USDC".to_string(),
+                        uri: None,
+                        uri_hash: None,
+                    },
+                    strategy: Strategies::Xyk(Xyk::new(
+                        "ruji".to_string(),
+                        "usdc".to_string(),
+                        Decimal::permille(1u64),
+                        Uint128::from(Xyk::MIN_MIN_QUOTE),
+                        Decimal::zero(),
+                    )),
+                },
+                &[],
+                "template",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::Deposit {
+                min_return: None,
+                callback: None,
+            },
+            &[coin(10_000, "ruji"), coin(10_000, "usdc")],
+        )
+        .unwrap();
+
+        // A different pair can't redeem the existing shares
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetStrategy(Strategies::Xyk(Xyk::new(
+                "ruji".to_string(),
+                "usdt".to_string(),
+                Decimal::permille(1u64),
+                Uint128::from(Xyk::MIN_MIN_QUOTE),
+                Decimal::zero(),
+            ))),
+        )
+        .unwrap_err();
+
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetMigrationEpsilon(Decimal::percent(101)),
+        )
+        .unwrap_err();
+
+        let res = app
+            .wasm_sudo(
+                contract.clone(),
+                &SudoMsg::SetStrategy(Strategies::Xyk(Xyk::new(
+                    "ruji".to_string(),
+                    "usdc".to_string(),
+                    Decimal::permille(2u64),
+                    Uint128::from(Xyk::MIN_MIN_QUOTE),
+                    Decimal::bps(30),
+                ))),
+            )
+            .unwrap();
+
+        res.assert_event(
+            &Event::new("wasm-rujira-bow/set_strategy").add_attributes(vec![
+                ("denom", "bow-xyk-ruji-usdc"),
+                ("balances", "10000ruji,10000usdc"),
+                ("shares", "10000"),
+            ]),
+        );
+
+        // Shares remain redeemable for the same underlying assets
+        let res = app
+            .execute_contract(
+                addr.clone(),
+                contract.clone(),
+                &ExecuteMsg::Withdraw { callback: None },
+                &[coin(9000, "x/bow-xyk-ruji-usdc")],
+            )
+            .unwrap();
+
+        res.assert_event(&Event::new("transfer").add_attributes(vec![
+            ("amount", "9000ruji,9000usdc"),
+            ("recipient", addr.as_str()),
+            ("sender", contract.as_str()),
+        ]));
+    }
+
+    #[test]
+    fn set_params() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo));
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner,
+                &InstantiateMsg {
+                    metadata: TokenMetadata {
+                        description: "RUJI-USDC XYK Liquidity Pool Token".to_string(),
+                        display: "LP RUJI-USDC".to_string(),
+                        name: "LP RUJI-USDC".to_string(),
+                        symbol: "LP/RUJI-USDC".to_string(),
+                        uri: None,
+                        uri_hash: None,
+                    },
+                    strategy: Strategies::Xyk(Xyk::new(
+                        "ruji".to_string(),
+                        "usdc".to_string(),
+                        Decimal::permille(1u64),
+                        Uint128::from(Xyk::MIN_MIN_QUOTE),
+                        Decimal::zero(),
+                    )),
+                },
+                &[],
+                "template",
+                None,
+            )
+            .unwrap();
+
+        let err = app
+            .wasm_sudo(contract.clone(), &SudoMsg::SetFee(Decimal::percent(11)))
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Invalid Config fee"));
+        let err = app
+            .wasm_sudo(contract.clone(), &SudoMsg::SetSkew(Decimal::zero()))
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Invalid Config step"));
+        let err = app
+            .wasm_sudo(
+                contract.clone(),
+                &SudoMsg::SetMinQuote(Uint128::from(Xyk::MIN_MIN_QUOTE - 1)),
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Invalid Config min_quote"));
+
+        let res = app
+            .wasm_sudo(contract.clone(), &SudoMsg::SetFee(Decimal::bps(30)))
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-bow/set_strategy_param")
+                .add_attributes(vec![("key", "fee"), ("value", "0.003")]),
+        );
+        let res = app
+            .wasm_sudo(
+                contract.clone(),
+                &SudoMsg::SetFeeBid(Some(Decimal::bps(100))),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-bow/set_strategy_param")
+                .add_attributes(vec![("key", "fee_bid"), ("value", "0.01")]),
+        );
+        let err = app
+            .wasm_sudo(
+                contract.clone(),
+                &SudoMsg::SetFeeAsk(Some(Decimal::bps(1001))),
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Invalid Config fee_ask"));
+        let res = app
+            .wasm_sudo(contract.clone(), &SudoMsg::SetSkew(Decimal::permille(5)))
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-bow/set_strategy_param")
+                .add_attributes(vec![("key", "step"), ("value", "0.005")]),
+        );
+        let res = app
+            .wasm_sudo(
+                contract.clone(),
+                &SudoMsg::SetMinQuote(Uint128::from(5000u128)),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-bow/set_strategy_param")
+                .add_attributes(vec![("key", "min_quote"), ("value", "5000")]),
+        );
+
+        let res: StrategyResponse = app
+            .wrap()
+            .query_wasm_smart(contract, &QueryMsg::Strategy {})
+            .unwrap();
+        let StrategyResponse::Xyk((xyk, _)) = res;
+        assert_eq!(xyk.fee(), Decimal::bps(30));
+        assert_eq!(xyk.fee_bid(), Decimal::bps(100));
+        assert_eq!(xyk.fee_ask(), Decimal::bps(30));
+        assert_eq!(xyk.step(), Decimal::permille(5));
+        assert_eq!(xyk.min_quote(), Uint128::from(5000u128));
+    }
+
+    #[test]
+    fn minimum_liquidity() {
+        let mut app = mock_rujira_app();
+        let attacker = app.api().addr_make("attacker");
+        let victim = app.api().addr_make("victim");
+        app.init_modules(|router, _api, storage| {
+            router.bank.init_balance(
+                storage,
+                &attacker,
+                vec![coin(2001, "ruji"), coin(2001, "usdc")],
+            )?;
+            router.bank.init_balance(
+                storage,
+                &victim,
+                vec![coin(1000, "ruji"), coin(1000, "usdc")],
+            )
+        })
+        .unwrap();
+        let owner = app.api().addr_make("owner");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query));
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner,
+                &InstantiateMsg {
+                    metadata: TokenMetadata {
+                        description: "RUJI-USDC XYK Liquidity Pool Token".to_string(),
+                        display: "LP RUJI-USDC".to_string(),
+                        name: "LP RUJI-USDC".to_string(),
+                        symbol: "LP/RUJI-USDC".to_string(),
+                        uri: None,
+                        uri_hash: None,
+                    },
+                    strategy: Strategies::Xyk(Xyk::new(
+                        "ruji".to_string(),
+                        "usdc".to_string(),
+                        Decimal::permille(1u64),
+                        Uint128::from(Xyk::MIN_MIN_QUOTE),
+                        Decimal::zero(),
+                    )),
+                },
+                &[],
+                "template",
+                None,
+            )
+            .unwrap();
+
+        let deposit = |app: &mut RujiraApp, sender: &Addr, amount: u128| {
+            app.execute_contract(
+                sender.clone(),
+                contract.clone(),
+                &ExecuteMsg::Deposit {
+                    min_return: None,
+                    callback: None,
+                },
+                &[coin(amount, "ruji"), coin(amount, "usdc")],
+            )
+        };
+
+        // The first deposit must mint more than the locked minimum
+        deposit(&mut app, &attacker, 1000).unwrap_err();
+
+        // The attacker can take a tiny share of the pool, but never all of it
+        let res = deposit(&mut app, &attacker, 1001).unwrap();
+        res.assert_event(&Event::new("mint").add_attributes(vec![
+            ("amount", "1"),
+            ("denom", "x/bow-xyk-ruji-usdc"),
+            ("recipient", attacker.as_str()),
+        ]));
+        let locked = app
+            .wrap()
+            .query_balance(&contract, "x/bow-xyk-ruji-usdc")
+            .unwrap();
+        assert_eq!(locked.amount, MINIMUM_LIQUIDITY);
+
+        // So a subsequent deposit is issued its fair share, and redeemable in full
+        let res = deposit(&mut app, &victim, 1000).unwrap();
+        res.assert_event(&Event::new("mint").add_attributes(vec![
+            ("amount", "1000"),
+            ("denom", "x/bow-xyk-ruji-usdc"),
+            ("recipient", victim.as_str()),
+        ]));
+        let res = app
+            .execute_contract(
+                victim.clone(),
+                contract.clone(),
+                &ExecuteMsg::Withdraw { callback: None },
+                &[coin(1000, "x/bow-xyk-ruji-usdc")],
+            )
+            .unwrap();
+        res.assert_event(&Event::new("transfer").add_attributes(vec![
+            ("amount", "1000ruji,1000usdc"),
+            ("recipient", victim.as_str()),
+            ("sender", contract.as_str()),
+        ]));
+    }
+
+    #[test]
+    fn poke() {
+        let mut app = mock_rujira_app();
+        let addr = app.api().addr_make("depositor");
+        let keeper = app.api().addr_make("keeper");
+        app.init_modules(|router, _api, storage| {
+            router
+                .stargate
+                .with_prices(vec![("RUJI", Decimal::one()), ("USDC", Decimal::one())]);
+            router.bank.init_balance(
+                storage,
+                &addr,
+                vec![coin(2_000_000, "ruji"), coin(2_000_000, "usdc")],
+            )
+        })
+        .unwrap();
+        let owner = app.api().addr_make("owner");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo));
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner,
+                &InstantiateMsg {
+                    metadata: TokenMetadata {
+                        description: "RUJI-USDC XYK Liquidity Pool Token".to_string(),
+                        display: "LP RUJI-USDC".to_string(),
+                        name: "LP RUJI-USDC".to_string(),
+                        symbol: "LP/RUJI-USDC".to_string(),
+                        uri: None,
+                        uri_hash: None,
+                    },
+                    strategy: Strategies::Xyk(Xyk::new(
+                        "ruji".to_string(),
+                        "usdc".to_string(),
+                        Decimal::permille(1u64),
+                        Uint128::from(Xyk::MIN_MIN_QUOTE),
+                        Decimal::percent(10),
+                    )),
+                },
+                &[],
+                "template",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::Deposit {
+                min_return: None,
+                callback: None,
+            },
+            &[coin(1_000_000, "ruji"), coin(1_000_000, "usdc")],
+        )
+        .unwrap();
+
+        app.execute_contract(keeper.clone(), contract.clone(), &ExecuteMsg::Poke {}, &[])
+            .unwrap_err();
+
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetPoke(Some(PokeConfig {
+                oracles: [
+                    Layer1Asset::new("THOR", "RUJI"),
+                    Layer1Asset::new("ETH", "USDC-0XA0B86991C6218B36C1D19D4A2E9EB0CE3606EB48"),
+                ],
+                threshold: Decimal::percent(5),
+                bounty: Decimal::percent(10),
+            })),
+        )
+        .unwrap();
+
+        // 9090 usdc retained as fees, pool price moves to 0.826
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::Swap {
+                min_return: coin(81_819, "usdc"),
+                to: None,
+                callback: None,
+            },
+            &[coin(100_000, "ruji")],
+        )
+        .unwrap();
+
+        let res = app
+            .execute_contract(keeper.clone(), contract.clone(), &ExecuteMsg::Poke {}, &[])
+            .unwrap();
+
+        res.assert_event(
+            &Event::new("wasm-rujira-bow/poke")
+                .add_attributes(vec![("added", "8181usdc"), ("bounty", "909usdc")]),
+        );
+
+        // Fees have been used, nothing left to pay out
+        app.execute_contract(keeper.clone(), contract.clone(), &ExecuteMsg::Poke {}, &[])
+            .unwrap();
+
+        let balance = app.wrap().query_balance(&keeper, "usdc").unwrap();
+        assert_eq!(balance.amount, Uint128::from(909u128));
+    }
+
+    #[test]
+    fn circuit_breaker() {
+        let mut app = mock_rujira_app();
+        let addr = app.api().addr_make("depositor");
+        app.init_modules(|router, _api, storage| {
+            router.bank.init_balance(
+                storage,
+                &addr,
+                vec![coin(2_000_000, "ruji"), coin(2_000_000, "usdc")],
+            )
+        })
+        .unwrap();
+        let owner = app.api().addr_make("owner");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo));
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner,
+                &InstantiateMsg {
+                    metadata: TokenMetadata {
+                        description: "RUJI-USDC XYK Liquidity Pool Token".to_string(),
+                        display: "LP RUJI-USDC".to_string(),
+                        name: "LP RUJI-USDC".to_string(),
+                        symbol: "LP/RUJI-USDC".to_string(),
+                        uri: None,
+                        uri_hash: None,
+                    },
+                    strategy: Strategies::Xyk(Xyk::new(
+                        "ruji".to_string(),
+                        "usdc".to_string(),
+                        Decimal::permille(1u64),
+                        Uint128::from(Xyk::MIN_MIN_QUOTE),
+                        Decimal::percent(1),
+                    )),
+                },
+                &[],
+                "template",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::Deposit {
+                min_return: None,
+                callback: None,
+            },
+            &[coin(1_000_000, "ruji"), coin(1_000_000, "usdc")],
+        )
+        .unwrap();
+
+        app.wasm_sudo(contract.clone(), &SudoMsg::SetPaused(true))
+            .unwrap();
+
+        let swap = ExecuteMsg::Swap {
+            min_return: coin(1_000, "usdc"),
+            to: None,
+            callback: None,
+        };
+        let err = app
+            .execute_contract(
+                addr.clone(),
+                contract.clone(),
+                &swap,
+                &[coin(2_000, "ruji")],
+            )
+            .unwrap_err();
+        assert!(err.root_cause().to_string().contains("Paused"));
+
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::Deposit {
+                min_return: None,
+                callback: None,
+            },
+            &[coin(1_000, "ruji"), coin(1_000, "usdc")],
+        )
+        .unwrap_err();
+
+        // No liquidity is offered to FIN while paused
+        let quote: Option<QuoteResponse> = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Quote(rujira_rs::bow::QuoteRequest {
+                    min_price: None,
+                    offer_denom: "ruji".to_string(),
+                    ask_denom: "usdc".to_string(),
+                    data: None,
+                }),
+            )
+            .unwrap();
+        assert_eq!(quote, None);
+
+        // Withdrawals are never paused
+        let res = app
+            .execute_contract(
+                addr.clone(),
+                contract.clone(),
+                &ExecuteMsg::Withdraw { callback: None },
+                &[coin(99_000, "x/bow-xyk-ruji-usdc")],
+            )
+            .unwrap();
+        res.assert_event(&Event::new("transfer").add_attributes(vec![
+            ("amount", "99000ruji,99000usdc"),
+            ("recipient", addr.as_str()),
+        ]));
+
+        app.wasm_sudo(contract.clone(), &SudoMsg::SetPaused(false))
+            .unwrap();
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &swap,
+            &[coin(2_000, "ruji")],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn ladder() {
+        let mut app = mock_rujira_app();
+        let addr = app.api().addr_make("depositor");
+        app.init_modules(|router, _api, storage| {
+            router.bank.init_balance(
+                storage,
+                &addr,
+                vec![coin(1_000_000, "ruji"), coin(1_000_000, "usdc")],
+            )
+        })
+        .unwrap();
+        let owner = app.api().addr_make("owner");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo));
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner,
+                &InstantiateMsg {
+                    metadata: TokenMetadata {
+                        description: "RUJI-USDC XYK Liquidity Pool Token".to_string(),
+                        display: "LP RUJI-USDC".to_string(),
+                        name: "LP RUJI-USDC".to_string(),
+                        symbol: "LP/RUJI-USDC".to_string(),
+                        uri: None,
+                        uri_hash: None,
+                    },
+                    strategy: Strategies::Xyk(Xyk::new(
+                        "ruji".to_string(),
+                        "usdc".to_string(),
+                        Decimal::permille(1u64),
+                        Uint128::from(Xyk::MIN_MIN_QUOTE),
+                        Decimal::percent(1),
+                    )),
+                },
+                &[],
+                "template",
+                None,
+            )
+            .unwrap();
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::Deposit {
+                min_return: None,
+                callback: None,
+            },
+            &[coin(1_000_000, "ruji"), coin(1_000_000, "usdc")],
+        )
+        .unwrap();
+
+        // The quotes a FIN market maker iterator would consume
+        let mut quotes = vec![];
+        let mut req = rujira_rs::bow::QuoteRequest {
+            min_price: None,
+            offer_denom: "usdc".to_string(),
+            ask_denom: "ruji".to_string(),
+            data: None,
+        };
+        for _ in 0..6 {
+            let quote: QuoteResponse = app
+                .wrap()
+                .query_wasm_smart(contract.clone(), &QueryMsg::Quote(req.clone()))
+                .unwrap();
+            req.min_price = Some(quote.price);
+            req.data = quote.data;
+            quotes.push(LadderLevel {
+                price: quote.price,
+                size: quote.size,
+            });
+        }
+
+        let ladder = |after: Option<LadderCursor>| -> LadderResponse {
+            app.wrap()
+                .query_wasm_smart(
+                    contract.clone(),
+                    &QueryMsg::Ladder {
+                        offer_denom: "usdc".to_string(),
+                        ask_denom: "ruji".to_string(),
+                        levels: 3,
+                        after,
+                    },
+                )
+                .unwrap()
+        };
+        let first = ladder(None);
+        assert_eq!(first.levels, quotes[..3]);
+        let second = ladder(first.next);
+        assert_eq!(second.levels, quotes[3..]);
+        assert!(second.next.is_some());
+
+        app.wasm_sudo(contract.clone(), &SudoMsg::SetPaused(true))
+            .unwrap();
+        let paused: LadderResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Ladder {
+                    offer_denom: "usdc".to_string(),
+                    ask_denom: "ruji".to_string(),
+                    levels: 3,
+                    after: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(paused.levels, vec![]);
+        assert_eq!(paused.next, None);
+    }
+
+    #[test]
+    fn gauge() {
+        let mut app = mock_rujira_app();
+        let addr = app.api().addr_make("depositor");
+        app.init_modules(|router, _api, storage| {
+            router.bank.init_balance(
+                storage,
+                &addr,
+                vec![coin(10_000, "ruji"), coin(10_000, "usdc")],
+            )
+        })
+        .unwrap();
+        let owner = app.api().addr_make("owner");
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo));
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    metadata: TokenMetadata {
+                        description: "RUJI-USDC XYK Liquidity Pool Token".to_string(),
+                        display: "LP RUJI-USDC".to_string(),
+                        name: "LP RUJI-USDC".to_string(),
+                        symbol: "LP/RUJI-USDC".to_string(),
+                        uri: None,
+                        uri_hash: None,
+                    },
+                    strategy: Strategies::Xyk(Xyk::new(
+                        "ruji".to_string(),
+                        "usdc".to_string(),
+                        Decimal::permille(1u64),
+                        Uint128::from(Xyk::MIN_MIN_QUOTE),
+                        Decimal::zero(),
+                    )),
+                },
+                &[],
+                "template",
+                None,
+            )
+            .unwrap();
+
+        // Records each checkpoint and claim in an event
+        let code = ContractWrapper::new(
+            |_, _, _, msg: GaugeMsg| -> StdResult<Response> {
+                let event = match msg {
+                    GaugeMsg::Checkpoint {
+                        owner,
+                        minted,
+                        burned,
+                    } => Event::new("checkpoint")
+                        .add_attribute("owner", owner)
+                        .add_attribute("minted", minted.to_string())
+                        .add_attribute("burned", burned.to_string()),
+                    GaugeMsg::Claim { owner } => Event::new("claim").add_attribute("owner", owner),
+                };
+                Ok(Response::default().add_event(event))
+            },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::default()) },
+            |_, _, _: Empty| -> StdResult<Binary> { Ok(Binary::default()) },
+        );
+        let code_id = app.store_code(Box::new(code));
+        let gauge = app
+            .instantiate_contract(code_id, owner, &Empty {}, &[], "gauge", None)
+            .unwrap();
+
+        app.execute_contract(
+            addr.clone(),
+            contract.clone(),
+            &ExecuteMsg::ClaimRewards {},
+            &[],
+        )
+        .unwrap_err();
+
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetGauge(Some(gauge.to_string())),
+        )
+        .unwrap();
+
+        let res = app
+            .execute_contract(
+                addr.clone(),
+                contract.clone(),
+                &ExecuteMsg::Deposit {
+                    min_return: None,
+                    callback: None,
+                },
+                &[coin(10_000, "ruji"), coin(10_000, "usdc")],
+            )
+            .unwrap();
+        // The minimum liquidity isn't attributed to an LP
+        res.assert_event(&Event::new("wasm-checkpoint").add_attributes(vec![
+            ("owner", addr.as_str()),
+            ("minted", "9000"),
+            ("burned", "0"),
+        ]));
+
+        let res = app
+            .execute_contract(
+                addr.clone(),
+                contract.clone(),
+                &ExecuteMsg::Withdraw { callback: None },
+                &[coin(200, "x/bow-xyk-ruji-usdc")],
+            )
+            .unwrap();
+        res.assert_event(&Event::new("wasm-checkpoint").add_attributes(vec![
+            ("owner", addr.as_str()),
+            ("minted", "0"),
+            ("burned", "200"),
+        ]));
+
+        let res = app
+            .execute_contract(
+                addr.clone(),
+                contract.clone(),
+                &ExecuteMsg::ClaimRewards {},
+                &[],
+            )
+            .unwrap();
+        res.assert_event(&Event::new("wasm-claim").add_attributes(vec![("owner", addr.as_str())]));
+
+        // Without a gauge, nothing is reported
+        app.wasm_sudo(contract.clone(), &SudoMsg::SetGauge(None))
+            .unwrap();
+        let res = app
+            .execute_contract(
+                addr.clone(),
+                contract.clone(),
+                &ExecuteMsg::Withdraw { callback: None },
+                &[coin(200, "x/bow-xyk-ruji-usdc")],
+            )
+            .unwrap();
+        assert!(!res.events.iter().any(|x| x.ty == "wasm-checkpoint"));
+    }
 }
@@ -1,6 +1,6 @@
-use cosmwasm_std::{CheckedFromRatioError, CoinsError, StdError};
+use cosmwasm_std::{CheckedFromRatioError, CoinsError, Decimal, StdError, Uint128};
 use cw_utils::PaymentError;
-use rujira_rs::{bow::StrategyError, SharePoolError};
+use rujira_rs::{bow::StrategyError, OracleError, SharePoolError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -23,6 +23,9 @@ pub enum ContractError {
     #[error("{0}")]
     Coins(#[from] CoinsError),
 
+    #[error("{0}")]
+    Oracle(#[from] OracleError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -31,6 +34,25 @@ pub enum ContractError {
 
     #[error("Invalid: {0}")]
     Invalid(String),
+
+    #[error("InsufficientInitialDeposit: must mint more than {min} shares")]
+    InsufficientInitialDeposit { min: Uint128 },
+
+    #[error("PokeDisabled")]
+    PokeDisabled {},
+
+    #[error("GaugeDisabled")]
+    GaugeDisabled {},
+
+    #[error("Paused")]
+    Paused {},
+
+    #[error("Share value of {denom} changed by {delta}, max {max}")]
+    ShareValueChanged {
+        denom: String,
+        delta: Decimal,
+        max: Decimal,
+    },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
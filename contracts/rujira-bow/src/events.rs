@@ -1,4 +1,5 @@
-use cosmwasm_std::{Addr, Coin, Event};
+use cosmwasm_std::{Addr, Coin, Decimal, Event, Uint128};
+use cw_utils::NativeBalance;
 
 pub fn event_deposit(owner: Addr, minted: Coin) -> Event {
     Event::new(format!("{}/deposit", env!("CARGO_PKG_NAME")))
@@ -6,6 +7,14 @@ pub fn event_deposit(owner: Addr, minted: Coin) -> Event {
         .add_attribute("minted", minted.to_string())
 }
 
+pub fn event_zap(owner: Addr, offer: Coin, fee: Coin, minted: Coin) -> Event {
+    Event::new(format!("{}/zap", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner.clone())
+        .add_attribute("offer", offer.to_string())
+        .add_attribute("fee", fee.to_string())
+        .add_attribute("minted", minted.to_string())
+}
+
 pub fn event_withdraw(owner: Addr, share: Coin) -> Event {
     Event::new(format!("{}/withdraw", env!("CARGO_PKG_NAME")))
         .add_attribute("owner", owner.clone())
@@ -19,3 +28,37 @@ pub fn event_swap(offer: Coin, ask: Coin, fee: Coin, surplus: Coin) -> Event {
         .add_attribute("fee", fee.to_string())
         .add_attribute("surplus", surplus.to_string())
 }
+
+pub fn event_set_strategy(denom: String, balances: &NativeBalance, shares: Uint128) -> Event {
+    Event::new(format!("{}/set_strategy", env!("CARGO_PKG_NAME")))
+        .add_attribute("denom", denom)
+        .add_attribute("balances", balances.to_string())
+        .add_attribute("shares", shares.to_string())
+}
+
+pub fn event_set_strategy_param(key: &str, value: String) -> Event {
+    Event::new(format!("{}/set_strategy_param", env!("CARGO_PKG_NAME")))
+        .add_attribute("key", key)
+        .add_attribute("value", value)
+}
+
+pub fn event_circuit_breaker(action: &str, reason: String) -> Event {
+    Event::new(format!("{}/circuit_breaker", env!("CARGO_PKG_NAME")))
+        .add_attribute("action", action)
+        .add_attribute("reason", reason)
+}
+
+pub fn event_poke(
+    caller: Addr,
+    price: Decimal,
+    deviation: Decimal,
+    added: &NativeBalance,
+    bounty: &NativeBalance,
+) -> Event {
+    Event::new(format!("{}/poke", env!("CARGO_PKG_NAME")))
+        .add_attribute("caller", caller)
+        .add_attribute("price", price.to_string())
+        .add_attribute("deviation", deviation.to_string())
+        .add_attribute("added", added.to_string())
+        .add_attribute("bounty", bounty.to_string())
+}
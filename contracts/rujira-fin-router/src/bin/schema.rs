@@ -0,0 +1,11 @@
+use cosmwasm_schema::write_api;
+
+use rujira_rs::fin_router;
+
+fn main() {
+    write_api! {
+        instantiate: fin_router::InstantiateMsg,
+        execute: fin_router::ExecuteMsg,
+        query: fin_router::QueryMsg,
+    }
+}
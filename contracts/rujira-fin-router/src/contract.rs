@@ -0,0 +1,476 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    ensure, to_json_binary, Addr, Api, BankMsg, Binary, Coin, Deps, DepsMut, Empty, Env,
+    MessageInfo, Response, WasmMsg,
+};
+use cw2::set_contract_version;
+use cw_utils::NativeBalance;
+use rujira_rs::{
+    fin::{self, OrderCallback, OrderTarget},
+    fin_router::{ExecuteMsg, InstantiateMsg, OrderResponse, PairOrders, PairResponse, QueryMsg},
+    CallbackData, CallbackMsg,
+};
+
+use crate::error::ContractError;
+use crate::events::event_order;
+
+const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Order { pairs, callback } => {
+            let (hops, refund) = allocate(deps.api, pairs, info.funds)?;
+            let result = summarize(&hops, &refund);
+            let mut response = Response::default();
+            for (fin, orders, funds) in hops {
+                let event = event_order(&info.sender, &fin, orders.len(), &funds);
+                // Placed as a trusted caller, so that the orders are owned by the sender. The
+                // sender's callback is sent once the last pair is done, rather than by each pair
+                let order = OrderCallback {
+                    for_account: info.sender.to_string(),
+                    orders: (orders, None),
+                };
+                let msg = fin::ExecuteMsg::Callback(CallbackMsg::new(
+                    Empty {},
+                    CallbackData::Msg(to_json_binary(&order)?),
+                )?);
+                response = response
+                    .add_message(WasmMsg::Execute {
+                        contract_addr: fin.to_string(),
+                        msg: to_json_binary(&msg)?,
+                        funds,
+                    })
+                    .add_event(event);
+            }
+            match callback {
+                Some(callback) => {
+                    Ok(response.add_message(callback.to_message(&info.sender, result, refund)?))
+                }
+                None if refund.is_empty() => Ok(response),
+                None => Ok(response.add_message(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: refund,
+                })),
+            }
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    match msg {
+        QueryMsg::Simulate { pairs, funds } => {
+            let (hops, refund) = allocate(deps.api, pairs, funds)?;
+            Ok(to_json_binary(&summarize(&hops, &refund))?)
+        }
+    }
+}
+
+/// A pair, the orders placed on it and the funds allocated to them
+type Hop = (Addr, Vec<OrderTarget>, Vec<Coin>);
+
+/// Allocates `funds` to the orders of each pair, returning the hops and the funds left over
+fn allocate(
+    api: &dyn Api,
+    pairs: Vec<PairOrders>,
+    funds: Vec<Coin>,
+) -> Result<(Vec<Hop>, Vec<Coin>), ContractError> {
+    ensure!(
+        !pairs.is_empty(),
+        ContractError::Invalid("pairs".to_string())
+    );
+    let mut remaining = NativeBalance(funds);
+    remaining.normalize();
+    let mut hops = vec![];
+    for pair in pairs {
+        let fin = api.addr_validate(&pair.fin)?;
+        let mut funds = NativeBalance(pair.funds);
+        funds.normalize();
+        for coin in funds.clone().into_vec() {
+            remaining = (remaining - coin).map_err(|_| ContractError::InsufficientFunds {})?;
+        }
+        hops.push((fin, pair.orders, funds.into_vec()));
+    }
+    remaining.normalize();
+    Ok((hops, remaining.into_vec()))
+}
+
+fn summarize(hops: &[Hop], refund: &[Coin]) -> OrderResponse {
+    OrderResponse {
+        pairs: hops
+            .iter()
+            .map(|(fin, orders, funds)| PairResponse {
+                fin: fin.to_string(),
+                orders: orders.len(),
+                funds: funds.clone(),
+            })
+            .collect(),
+        refund: refund.to_vec(),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use cosmwasm_std::{
+        coin, coins, from_json, to_json_vec, Addr, Decimal, Event, StdResult, Uint128,
+    };
+    use cw_multi_test::{ContractWrapper, Executor};
+    use rujira_fin::mock::Fin;
+    use rujira_rs::{
+        fin::{Denoms, Price, Side, SudoMsg},
+        fin_router::PairOrders,
+        PageRequest,
+    };
+    use rujira_rs_testing::{mock_rujira_app, RujiraApp};
+
+    fn orders(app: &RujiraApp, fin: &Fin, owner: &Addr) -> Vec<Uint128> {
+        fin.query_orders(app, owner.as_str(), None, PageRequest::default())
+            .unwrap()
+            .orders
+            .into_iter()
+            .map(|x| x.remaining)
+            .collect()
+    }
+
+    #[test]
+    fn order() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let fees = app.api().addr_make("fees");
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &user, coins(10_000, "eth-usdc"))
+                .unwrap();
+        });
+        let btc = Fin::create(
+            &mut app,
+            &owner,
+            &fees,
+            &[],
+            Denoms::new("btc-btc", "eth-usdc"),
+            None,
+        );
+        let eth = Fin::create(
+            &mut app,
+            &owner,
+            &fees,
+            &[],
+            Denoms::new("eth-eth", "eth-usdc"),
+            None,
+        );
+
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query));
+        let code_id = app.store_code(code);
+        let router = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {},
+                &[],
+                "router",
+                None,
+            )
+            .unwrap();
+
+        let bid = |price: &str, amount: u128| {
+            (
+                Side::Quote,
+                Price::Fixed(Decimal::from_str(price).unwrap()),
+                Some(Uint128::from(amount)),
+            )
+        };
+        let msg = ExecuteMsg::Order {
+            pairs: vec![
+                PairOrders {
+                    fin: btc.addr().to_string(),
+                    orders: vec![bid("90000", 1_000), bid("89000", 2_000)],
+                    funds: coins(3_000, "eth-usdc"),
+                },
+                PairOrders {
+                    fin: eth.addr().to_string(),
+                    orders: vec![bid("3000", 1_500)],
+                    funds: coins(1_500, "eth-usdc"),
+                },
+            ],
+            callback: None,
+        };
+
+        // The router must be trusted by every pair, and a failing pair reverts the batch
+        app.wasm_sudo(
+            btc.addr().clone(),
+            &SudoMsg::SetTrustedCaller {
+                address: router.to_string(),
+                trusted: true,
+            },
+        )
+        .unwrap();
+        let err = app
+            .execute_contract(
+                user.clone(),
+                router.clone(),
+                &msg,
+                &coins(5_000, "eth-usdc"),
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Unauthorized"));
+        assert!(orders(&app, &btc, &user).is_empty());
+
+        app.wasm_sudo(
+            eth.addr().clone(),
+            &SudoMsg::SetTrustedCaller {
+                address: router.to_string(),
+                trusted: true,
+            },
+        )
+        .unwrap();
+
+        // Funds allocated must have been sent
+        let err = app
+            .execute_contract(
+                user.clone(),
+                router.clone(),
+                &msg,
+                &coins(4_000, "eth-usdc"),
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("InsufficientFunds"));
+
+        let res = app
+            .execute_contract(
+                user.clone(),
+                router.clone(),
+                &msg,
+                &coins(5_000, "eth-usdc"),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin-router/order").add_attributes(vec![
+                ("owner", user.as_str()),
+                ("fin", btc.addr().as_str()),
+                ("orders", "2"),
+                ("funds", "3000eth-usdc"),
+            ]),
+        );
+
+        // The orders are owned by the sender, and unallocated funds refunded
+        assert_eq!(
+            orders(&app, &btc, &user),
+            vec![Uint128::from(2_000u128), Uint128::from(1_000u128)]
+        );
+        assert_eq!(orders(&app, &eth, &user), vec![Uint128::from(1_500u128)]);
+        assert!(orders(&app, &btc, &router).is_empty());
+        assert_eq!(
+            app.wrap().query_balance(&user, "eth-usdc").unwrap(),
+            coin(10_000 - 4_500, "eth-usdc")
+        );
+        assert_eq!(
+            app.wrap().query_balance(&router, "eth-usdc").unwrap(),
+            coin(0, "eth-usdc")
+        );
+
+        // The router can only place or increase the sender's orders
+        app.execute_contract(
+            user.clone(),
+            router.clone(),
+            &ExecuteMsg::Order {
+                pairs: vec![PairOrders {
+                    fin: eth.addr().to_string(),
+                    orders: vec![bid("3000", 0)],
+                    funds: vec![],
+                }],
+                callback: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn callback() {
+        #[cosmwasm_schema::cw_serde]
+        enum ReceiverMsg {
+            Route { router: String, msg: ExecuteMsg },
+            Callback(rujira_rs::CallbackMsg),
+        }
+
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let fees = app.api().addr_make("fees");
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &user, coins(10_000, "eth-usdc"))
+                .unwrap();
+        });
+        let pairs: Vec<Fin> = ["btc-btc", "eth-eth"]
+            .into_iter()
+            .map(|base| {
+                Fin::create(
+                    &mut app,
+                    &owner,
+                    &fees,
+                    &[],
+                    Denoms::new(base, "eth-usdc"),
+                    None,
+                )
+            })
+            .collect();
+        let code = Box::new(ContractWrapper::new(execute, instantiate, query));
+        let code_id = app.store_code(code);
+        let router = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {},
+                &[],
+                "router",
+                None,
+            )
+            .unwrap();
+        for fin in pairs.iter() {
+            app.wasm_sudo(
+                fin.addr().clone(),
+                &SudoMsg::SetTrustedCaller {
+                    address: router.to_string(),
+                    trusted: true,
+                },
+            )
+            .unwrap();
+        }
+
+        // Routes orders through the router, and records each callback with the funds sent
+        let code = ContractWrapper::new(
+            |deps, _, info, msg: ReceiverMsg| -> StdResult<Response> {
+                match msg {
+                    ReceiverMsg::Route { router, msg } => {
+                        Ok(Response::default().add_message(WasmMsg::Execute {
+                            contract_addr: router,
+                            msg: to_json_binary(&msg)?,
+                            funds: info.funds,
+                        }))
+                    }
+                    ReceiverMsg::Callback(cb) => {
+                        let mut received: Vec<(OrderResponse, Vec<Coin>)> = deps
+                            .storage
+                            .get(b"received")
+                            .as_deref()
+                            .map(from_json)
+                            .transpose()?
+                            .unwrap_or_default();
+                        received.push((from_json(cb.data)?, info.funds));
+                        deps.storage.set(b"received", &to_json_vec(&received)?);
+                        Ok(Response::default())
+                    }
+                }
+            },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::default()) },
+            |deps, _, _: Empty| -> StdResult<Binary> {
+                Ok(Binary::from(
+                    deps.storage.get(b"received").unwrap_or_default(),
+                ))
+            },
+        );
+        let code_id = app.store_code(Box::new(code));
+        let receiver = app
+            .instantiate_contract(code_id, owner.clone(), &Empty {}, &[], "receiver", None)
+            .unwrap();
+
+        let pair_orders: Vec<PairOrders> = pairs
+            .iter()
+            .map(|fin| PairOrders {
+                fin: fin.addr().to_string(),
+                orders: vec![(
+                    Side::Quote,
+                    Price::Fixed(Decimal::from_str("1000").unwrap()),
+                    Some(Uint128::from(1_000u128)),
+                )],
+                funds: coins(1_000, "eth-usdc"),
+            })
+            .collect();
+        let expected = OrderResponse {
+            pairs: pairs
+                .iter()
+                .map(|fin| PairResponse {
+                    fin: fin.addr().to_string(),
+                    orders: 1,
+                    funds: coins(1_000, "eth-usdc"),
+                })
+                .collect(),
+            refund: coins(500, "eth-usdc"),
+        };
+        let simulation: OrderResponse = app
+            .wrap()
+            .query_wasm_smart(
+                router.clone(),
+                &QueryMsg::Simulate {
+                    pairs: pair_orders.clone(),
+                    funds: coins(2_500, "eth-usdc"),
+                },
+            )
+            .unwrap();
+        assert_eq!(simulation, expected);
+        app.wrap()
+            .query_wasm_smart::<OrderResponse>(
+                router.clone(),
+                &QueryMsg::Simulate {
+                    pairs: pair_orders.clone(),
+                    funds: coins(1_500, "eth-usdc"),
+                },
+            )
+            .unwrap_err();
+
+        // A single callback once every pair has placed its orders, with the refund
+        app.execute_contract(
+            user.clone(),
+            receiver.clone(),
+            &ReceiverMsg::Route {
+                router: router.to_string(),
+                msg: ExecuteMsg::Order {
+                    pairs: pair_orders,
+                    callback: Some(CallbackData::Msg(Binary::default())),
+                },
+            },
+            &coins(2_500, "eth-usdc"),
+        )
+        .unwrap();
+        let received: Vec<(OrderResponse, Vec<Coin>)> = app
+            .wrap()
+            .query_wasm_smart(receiver.clone(), &Empty {})
+            .unwrap();
+        assert_eq!(received, vec![(expected, coins(500, "eth-usdc"))]);
+        for fin in pairs.iter() {
+            assert_eq!(orders(&app, fin, &receiver), vec![Uint128::from(1_000u128)]);
+        }
+    }
+}
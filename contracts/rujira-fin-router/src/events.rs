@@ -0,0 +1,19 @@
+use cosmwasm_std::{Addr, Coin, Event};
+
+pub fn event_order(owner: &Addr, fin: &Addr, orders: usize, funds: &[Coin]) -> Event {
+    let event = Event::new(format!("{}/order", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner)
+        .add_attribute("fin", fin)
+        .add_attribute("orders", orders.to_string());
+    if funds.is_empty() {
+        return event;
+    }
+    event.add_attribute(
+        "funds",
+        funds
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+            .join(","),
+    )
+}
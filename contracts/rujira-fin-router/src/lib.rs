@@ -0,0 +1,5 @@
+pub mod contract;
+mod error;
+mod events;
+
+pub use crate::error::ContractError;
@@ -0,0 +1,88 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coin, ensure, to_json_binary, Addr, Coin, CosmosMsg, Decimal, Event, QuerierWrapper, Storage,
+    WasmMsg,
+};
+use cw_storage_plus::Item;
+use rujira_rs::{
+    fin::{ExecuteMsg, OrderTarget, SwapRequest},
+    CallbackData, Oracle,
+};
+
+use crate::{config::Config, events::event_aux_quote_convert, ContractError};
+
+/// The most auxiliary quote assets a pair can accept
+pub const MAX_AUX_QUOTES: usize = 5;
+
+type Orders = (Vec<OrderTarget>, Option<CallbackData>);
+
+// The nonce of the last conversion
+static NONCE: Item<u64> = Item::new("aux-quote-nonce");
+
+// The conversion in progress: its nonce, and the owner and orders it's resumed with
+static PENDING: Item<(u64, Addr, Orders)> = Item::new("aux-quote-pending");
+
+/// The callback of the swap of an auxiliary quote asset into the quote asset. The request it resumes
+/// is held by the pair, so that a swap on the aux pair can't place orders on behalf of anyone
+#[cw_serde]
+pub enum AuxQuoteCallback {
+    /// The quote asset returned for the orders of the conversion with `nonce`
+    Order { nonce: u64 },
+}
+
+/// Swaps `funds` into the quote asset on the pair of their [rujira_rs::fin::AuxQuote], when they are
+/// a single auxiliary quote asset. The swap must return the oracle value of `funds` less the
+/// `max_slippage` of the aux quote, or the request fails. The orders of `owner` are resumed with
+/// [resume] once the pair returns the quote asset
+pub fn convert(
+    q: QuerierWrapper,
+    storage: &mut dyn Storage,
+    config: &Config,
+    funds: &[Coin],
+    owner: &Addr,
+    orders: &Orders,
+) -> Result<Option<(CosmosMsg, Event)>, ContractError> {
+    let ([offer], Some([_, quote])) = (funds, &config.oracles) else {
+        return Ok(None);
+    };
+    let Some(aux) = config.aux_quotes.iter().find(|x| x.denom == offer.denom) else {
+        return Ok(None);
+    };
+    let rate = aux.oracle.tor_price(q)?.checked_div(quote.tor_price(q)?)?;
+    let min_return = offer
+        .amount
+        .mul_floor(rate * (Decimal::one() - aux.max_slippage));
+    let nonce = NONCE.may_load(storage)?.unwrap_or_default() + 1;
+    NONCE.save(storage, &nonce)?;
+    PENDING.save(storage, &(nonce, owner.clone(), orders.clone()))?;
+    let msg = WasmMsg::Execute {
+        contract_addr: aux.pair.to_string(),
+        msg: to_json_binary(&ExecuteMsg::Swap(SwapRequest::Min {
+            min_return,
+            to: None,
+            callback: Some(CallbackData::Msg(to_json_binary(
+                &AuxQuoteCallback::Order { nonce },
+            )?)),
+            max_levels: None,
+        }))?,
+        funds: funds.to_vec(),
+    };
+    Ok(Some((
+        msg.into(),
+        event_aux_quote_convert(
+            &aux.pair,
+            offer,
+            &coin(min_return.u128(), config.denoms.quote()),
+        ),
+    )))
+}
+
+/// Takes the owner and orders of the conversion in progress, which must be `nonce`
+pub fn resume(storage: &mut dyn Storage, nonce: u64) -> Result<(Addr, Orders), ContractError> {
+    let (pending, owner, orders) = PENDING
+        .may_load(storage)?
+        .ok_or(ContractError::Unauthorized {})?;
+    ensure!(pending == nonce, ContractError::Unauthorized {});
+    PENDING.remove(storage);
+    Ok((owner, orders))
+}
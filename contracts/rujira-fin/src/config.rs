@@ -1,12 +1,24 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Api, Decimal, Deps, DepsMut, StdResult, Storage};
+use cosmwasm_std::{
+    Addr, Api, BankMsg, CosmosMsg, Decimal, Deps, DepsMut, QuerierWrapper, StdResult, Storage,
+    Uint128,
+};
 use cw_storage_plus::Item;
+use cw_utils::NativeBalance;
 use rujira_rs::{
-    fin::{ConfigResponse, Denoms, InstantiateMsg, Tick},
+    fin::{
+        AuxQuote, ConfigResponse, DecimalDelta, Denoms, FeeConversion, InstantiateMsg,
+        MarketMakerLimit, OracleFallback, OrderFlowAuction, PremiumBounds, Session, Side,
+        SwapRequest, SweepBounty, Tick, WrapAdapter,
+    },
     Layer1Asset, Oracle,
 };
+use std::collections::BTreeMap;
 
-use crate::{market_makers::MarketMakers, ContractError};
+use crate::{
+    aux_quote::MAX_AUX_QUOTES, market_makers::MarketMakers, order_flow_auction::MAX_BIDDERS,
+    ContractError,
+};
 
 pub static CONFIG: Item<Config> = Item::new("config");
 
@@ -19,6 +31,45 @@ pub struct Config {
     pub fee_maker: Decimal,
     pub fee_taker: Decimal,
     pub fee_address: Addr,
+    #[serde(default)]
+    pub premium_bounds: PremiumBounds,
+    #[serde(default)]
+    pub trusted_callers: Vec<Addr>,
+    #[serde(default)]
+    pub decimal_delta: DecimalDelta,
+    /// Wraps the underlying of a receipt token paid to swaps and orders
+    #[serde(default)]
+    pub wrap: Option<WrapAdapter>,
+    /// Reserves a share of fees to pay callers of [rujira_rs::fin::ExecuteMsg::Sweep]
+    #[serde(default)]
+    pub sweep_bounty: Option<SweepBounty>,
+    /// Suspends continuous trading during recurring windows, settled at the oracle price
+    #[serde(default)]
+    pub session: Option<Session>,
+    /// How oracle priced orders are treated while the oracle is unavailable
+    #[serde(default)]
+    pub oracle_fallback: OracleFallback,
+    /// Sells fees collected in the base asset for the quote asset
+    #[serde(default)]
+    pub fee_conversion: Option<FeeConversion>,
+    /// Receives fees in the base asset in place of `fee_address`
+    #[serde(default)]
+    pub fee_address_base: Option<Addr>,
+    /// Receives fees in the quote asset in place of `fee_address`
+    #[serde(default)]
+    pub fee_address_quote: Option<Addr>,
+    /// Denoms accepted in place of the quote asset to fund orders
+    #[serde(default)]
+    pub aux_quotes: Vec<AuxQuote>,
+    /// Bounds the commitment of each market maker in one pass over the book
+    #[serde(default)]
+    pub market_maker_limit: Option<MarketMakerLimit>,
+    /// The difference from the target amount of an order under which it is left as it is
+    #[serde(default)]
+    pub target_epsilon: Uint128,
+    /// Auctions large market swaps to bidders before they reach the book
+    #[serde(default)]
+    pub order_flow_auction: Option<OrderFlowAuction>,
 }
 
 impl Config {
@@ -31,6 +82,20 @@ impl Config {
             fee_taker: value.fee_taker,
             fee_maker: value.fee_maker,
             fee_address: api.addr_validate(value.fee_address.as_str())?,
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
         })
     }
 
@@ -47,9 +112,173 @@ impl Config {
             return Err(ContractError::Invalid("fee_take >= 1".into()));
         }
         self.tick.validate()?;
+        if !self.premium_bounds.is_valid() {
+            return Err(ContractError::Invalid("premium_bounds".into()));
+        }
+        if !self.decimal_delta.is_valid() {
+            return Err(ContractError::Invalid("decimal_delta".into()));
+        }
+        if let Some(wrap) = &self.wrap {
+            let denoms = [self.denoms.base(), self.denoms.quote()];
+            if !denoms.contains(&wrap.receipt.as_str())
+                || denoms.contains(&wrap.underlying.as_str())
+            {
+                return Err(ContractError::Invalid("wrap".into()));
+            }
+        }
+        if let Some(bounty) = &self.sweep_bounty {
+            if bounty.share > Decimal::one() || bounty.rate > Decimal::one() {
+                return Err(ContractError::Invalid("sweep_bounty".into()));
+            }
+        }
+        if let Some(session) = &self.session {
+            if self.oracles.is_none()
+                || session.duration == 0
+                || session.duration >= session.interval
+            {
+                return Err(ContractError::Invalid("session".into()));
+            }
+        }
+        if self.oracles.is_none() && self.oracle_fallback != OracleFallback::Freeze {
+            return Err(ContractError::Invalid("oracle_fallback".into()));
+        }
+        if let Some(conversion) = &self.fee_conversion {
+            if conversion.max_slippage >= Decimal::one() {
+                return Err(ContractError::Invalid("fee_conversion".into()));
+            }
+        }
+        if self.aux_quotes.len() > MAX_AUX_QUOTES {
+            return Err(ContractError::Invalid("aux_quotes".into()));
+        }
+        for aux in &self.aux_quotes {
+            let denoms = [self.denoms.base(), self.denoms.quote()];
+            if self.oracles.is_none()
+                || aux.max_slippage >= Decimal::one()
+                || denoms.contains(&aux.denom.as_str())
+                || self
+                    .wrap
+                    .as_ref()
+                    .is_some_and(|x| x.underlying == aux.denom)
+            {
+                return Err(ContractError::Invalid("aux_quote".into()));
+            }
+            aux.oracle.tor_price(deps.querier)?;
+        }
+        if self.market_maker_limit.is_some() && self.oracles.is_none() {
+            return Err(ContractError::Invalid("market_maker_limit".into()));
+        }
+        if let Some(auction) = &self.order_flow_auction {
+            let denoms = [self.denoms.base(), self.denoms.quote()];
+            if auction.bidders.is_empty()
+                || auction.bidders.len() > MAX_BIDDERS
+                || auction
+                    .min_offer
+                    .iter()
+                    .any(|x| x.amount.is_zero() || !denoms.contains(&x.denom.as_str()))
+            {
+                return Err(ContractError::Invalid("order_flow_auction".into()));
+            }
+        }
         Ok(())
     }
 
+    /// The USD price of the quote asset, used to display rates in USD alongside the pair rate
+    pub fn quote_usd(&self, q: QuerierWrapper) -> Option<Decimal> {
+        self.oracles
+            .as_ref()
+            .and_then(|[_, quote]| quote.tor_price(q).ok())
+    }
+
+    /// The USD value of atomic amounts of the base and quote assets, when both oracles are available
+    pub fn value_usd(&self, q: QuerierWrapper, base: Uint128, quote: Uint128) -> Option<Decimal> {
+        let [base_oracle, quote_oracle] = self.oracles.as_ref()?;
+        base_oracle
+            .tor_price(q)
+            .ok()?
+            .checked_mul(Decimal::from_ratio(base, Uint128::one()))
+            .ok()?
+            .checked_add(
+                quote_oracle
+                    .tor_price(q)
+                    .ok()?
+                    .checked_mul(Decimal::from_ratio(quote, Uint128::one()))
+                    .ok()?,
+            )
+            .ok()
+    }
+
+    /// The most of `denom` that each market maker can be committed to pay in one pass over the book,
+    /// when [Config::market_maker_limit] is set. Nothing while the oracle price can't be read
+    pub fn market_maker_limit(&self, q: QuerierWrapper, denom: &str) -> Option<Uint128> {
+        let limit = self.market_maker_limit.as_ref()?;
+        let price = self.oracles.as_ref().and_then(|[base, quote]| {
+            let oracle = if denom == self.denoms.base() {
+                base
+            } else {
+                quote
+            };
+            oracle.tor_price(q).ok()
+        });
+        Some(
+            price
+                .and_then(|x| limit.max_value.checked_div(x).ok())
+                .map(|x| x.to_uint_floor())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// The address that fees in `denom` are sent to
+    pub fn fee_sink(&self, denom: &str) -> &Addr {
+        let sink = if denom == self.denoms.base() {
+            self.fee_address_base.as_ref()
+        } else if denom == self.denoms.quote() {
+            self.fee_address_quote.as_ref()
+        } else {
+            None
+        };
+        sink.unwrap_or(&self.fee_address)
+    }
+
+    /// Sends `fees` to their fee addresses, one message per address
+    pub fn fee_msgs(&self, fees: NativeBalance) -> Vec<CosmosMsg> {
+        let mut sinks: BTreeMap<&Addr, NativeBalance> = BTreeMap::new();
+        for c in fees.into_vec().into_iter().filter(|x| !x.amount.is_zero()) {
+            *sinks.entry(self.fee_sink(&c.denom)).or_default() += c;
+        }
+        sinks
+            .into_iter()
+            .map(|(addr, amount)| {
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: addr.to_string(),
+                    amount: amount.into_vec(),
+                })
+            })
+            .collect()
+    }
+
+    /// Converts the limit price of `req`, quoted in human units of the offer asset, to an atomic rate
+    pub fn scale_request(&self, req: SwapRequest, side: &Side) -> SwapRequest {
+        match req {
+            SwapRequest::Limit {
+                price,
+                min_fill,
+                to,
+                callback,
+                max_levels,
+            } => SwapRequest::Limit {
+                price: match side {
+                    Side::Base => self.decimal_delta.to_atomic(price),
+                    Side::Quote => self.decimal_delta.to_human(price),
+                },
+                min_fill,
+                to,
+                callback,
+                max_levels,
+            },
+            req => req,
+        }
+    }
+
     pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
         CONFIG.save(storage, self)
     }
@@ -105,6 +334,20 @@ impl Config {
             fee_maker: legacy.fee_maker,
             fee_taker: legacy.fee_taker,
             fee_address: legacy.fee_address,
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
         }
         .save(deps.storage)
     }
@@ -125,6 +368,24 @@ impl From<Config> for ConfigResponse {
             fee_maker: value.fee_maker,
             fee_taker: value.fee_taker,
             fee_address: value.fee_address.to_string(),
+            premium_bounds: value.premium_bounds,
+            trusted_callers: value
+                .trusted_callers
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            decimal_delta: value.decimal_delta,
+            wrap: value.wrap,
+            sweep_bounty: value.sweep_bounty,
+            session: value.session,
+            oracle_fallback: value.oracle_fallback,
+            fee_conversion: value.fee_conversion,
+            fee_address_base: value.fee_address_base.map(|x| x.to_string()),
+            fee_address_quote: value.fee_address_quote.map(|x| x.to_string()),
+            aux_quotes: value.aux_quotes,
+            market_maker_limit: value.market_maker_limit,
+            target_epsilon: value.target_epsilon,
+            order_flow_auction: value.order_flow_auction,
         }
     }
 }
@@ -1,28 +1,60 @@
+use crate::aux_quote::{self, AuxQuoteCallback};
 use crate::config::{Config, CONFIG};
 use crate::error::ContractError;
+use crate::events::{
+    event_arb, event_cancel_grid, event_cancel_twap, event_close_maker_session, event_crank_twap,
+    event_create_grid, event_create_twap, event_expire_maker_session, event_fill_callback_failed,
+    event_heartbeat, event_import_order, event_market_maker_dropped, event_market_maker_fallback,
+    event_migrate, event_open_maker_session, event_order_callback,
+    event_order_flow_auction_fallback, event_order_flow_auction_fill, event_reprice_grid,
+    event_rescue, event_set_fill_callback, event_set_withdraw_whitelist, event_settle, event_sweep,
+    event_trade_summary,
+};
+use crate::fees;
+use crate::fill_callback::{FillCallback, FILL_CALLBACK_REPLY_ID};
+use crate::grid::Grid;
+use crate::legacy;
+use crate::lock::Lock;
+use crate::maker_session::MakerSession;
+use crate::market_maker_fallback::{self, FallbackPayload, MARKET_MAKER_FALLBACK_REPLY_ID};
 use crate::market_makers::MarketMakers;
-use crate::order::Order;
+use crate::migration;
+use crate::oracle;
+use crate::order::{self, Order};
+use crate::order_flow_auction::{self, ORDER_FLOW_AUCTION_REPLY_ID};
 use crate::order_manager::OrderManager;
 use crate::pool::Pool;
+use crate::rounding;
+use crate::session;
+use crate::stats;
 use crate::swap_iter::SwapIter;
+use crate::sweep;
+use crate::trade::Trade;
+use crate::twap::Twap;
+use crate::withdraw_whitelist;
+use crate::wrap::{self, WrapCallback};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coin, ensure_eq, to_json_binary, BankMsg, Binary, CosmosMsg, Deps, DepsMut, Empty, Env,
-    MessageInfo, Response, WasmMsg,
+    coin, ensure, ensure_eq, from_json, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg,
+    Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult, Storage, SubMsg,
+    SubMsgResult, Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
-use cw_utils::{one_coin, NativeBalance};
+use cw2::{get_contract_version, set_contract_version};
+use cw_utils::{nonpayable, one_coin, NativeBalance};
 use rujira_rs::exchange::{Arber, Swappable, Swapper};
 use rujira_rs::fin::{
-    BookItemResponse, BookResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, OrderResponse,
-    OrdersResponse, Price, QueryMsg, Side, SimulationResponse, SudoMsg, SwapRequest,
+    ArbOpportunityResponse, AuxQuote, BidderMsg, BookCursor, BookItemResponse, BookResponse,
+    ConfigResponse, DecimalDelta, ExecuteMsg, GridResponse, InstantiateMsg, MakerSessionResponse,
+    OrderCallback, OrderResponse, OrderTarget, OrdersResponse, PoolOrdersResponse, Price, QueryMsg,
+    Scaled, Side, SimulationResponse, SudoMsg, SwapRequest, TickerResponse, WrapAdapter,
 };
-use rujira_rs::{Oracle, Premiumable};
+use rujira_rs::{CallbackData, PageResponse, Premiumable};
 
 // version info for migration info
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const BOOK_LIMIT: u8 = 100;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -45,39 +77,408 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    migration::ensure_complete(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
-    let oracle = config
-        .oracles
-        .clone()
-        .and_then(|x| x.tor_price(deps.querier).ok());
+    let (price, fallback) = oracle::observe(deps.storage, deps.querier, &config, env.block.time)?;
+    let oracle = Scaled {
+        oracle: price,
+        decimal_delta: config.decimal_delta,
+        time: env.block.time,
+    };
+    // Applied to atomic rates by swaps and orders
+    let quote_usd = config
+        .quote_usd(deps.querier)
+        .map(|x| config.decimal_delta.to_human(x));
     let mut messages: Vec<CosmosMsg> = vec![];
     let mut fees = NativeBalance::default();
-    let swap_iter = SwapIter::new(deps.querier, &config);
+    let swap_iter = SwapIter::new(deps.querier, &config)
+        .without_market_makers(market_maker_fallback::is_excluded(deps.storage)?);
+    // Funds deposited by contracts called mid-sequence, e.g. the bidder filling an order flow auction,
+    // would count towards the balance the sequence measures, and could be reclaimed afterwards
+    if !info.funds.is_empty() && info.sender != env.contract.address {
+        Lock::ensure_released(deps.storage)?;
+    }
 
-    match msg {
+    let response = match msg {
         ExecuteMsg::Swap(req) => {
+            withdraw_whitelist::recipient(
+                deps.storage,
+                deps.api,
+                env.block.time,
+                &info.sender,
+                req.to(),
+            )?;
+            if let Some(msg) = wrap::deposit(&config, &info.funds, || {
+                WrapCallback::Swap((info.sender.clone(), req.clone()))
+            })? {
+                return Ok(Response::default().add_message(msg));
+            }
+            if let Some((msg, event)) = order_flow_auction::run(
+                deps.querier,
+                deps.storage,
+                &env,
+                &config,
+                &swap_iter,
+                &oracle,
+                &info.sender,
+                &info.funds,
+                &req,
+            )? {
+                Lock::acquire(deps.storage)?;
+                return Ok(Response::default().add_submessage(msg).add_event(event));
+            }
+            let msg = swap_msg(deps.storage, &env, &config, info.sender, info.funds, req)?;
+            Ok(Response::default().add_submessage(msg))
+        }
+        ExecuteMsg::Order(req) => {
+            if let Some(msg) = wrap::deposit(&config, &info.funds, || {
+                WrapCallback::Order((info.sender.clone(), req.clone()))
+            })? {
+                return Ok(Response::default().add_message(msg));
+            }
+            if let Some((msg, event)) = aux_quote::convert(
+                deps.querier,
+                deps.storage,
+                &config,
+                &info.funds,
+                &info.sender,
+                &req,
+            )? {
+                return Ok(Response::default().add_message(msg).add_event(event));
+            }
+            let msg = order_msg(deps.storage, &env, info.sender, info.funds, req)?;
+            Ok(Response::default().add_message(msg))
+        }
+        ExecuteMsg::Callback(cb)
+            if config.wrap.as_ref().is_some_and(|x| x.vault == info.sender) =>
+        {
+            match cb.deserialize_callback()? {
+                WrapCallback::Swap((sender, req)) => {
+                    wrap::resume(deps.storage)?;
+                    let msg = swap_msg(deps.storage, &env, &config, sender, info.funds, req)?;
+                    Ok(Response::default().add_submessage(msg))
+                }
+                WrapCallback::Order((owner, req)) => {
+                    wrap::resume(deps.storage)?;
+                    let msg = order_msg(deps.storage, &env, owner, info.funds, req)?;
+                    Ok(Response::default().add_message(msg))
+                }
+                WrapCallback::Payout {
+                    recipient,
+                    callback,
+                    funds,
+                } => {
+                    let mut funds = NativeBalance(funds);
+                    for coin in info.funds {
+                        funds += coin;
+                    }
+                    funds.normalize();
+                    Ok(Response::default().add_messages(wrap::send(
+                        &recipient,
+                        callback,
+                        funds.into_vec(),
+                    )?))
+                }
+            }
+        }
+        ExecuteMsg::Callback(cb) if config.aux_quotes.iter().any(|x| x.pair == info.sender) => {
+            match cb.deserialize_callback()? {
+                AuxQuoteCallback::Order { nonce } => {
+                    let (owner, req) = aux_quote::resume(deps.storage, nonce)?;
+                    let msg = order_msg(deps.storage, &env, owner, info.funds, req)?;
+                    Ok(Response::default().add_message(msg))
+                }
+            }
+        }
+        ExecuteMsg::Callback(cb) => {
+            ensure!(
+                config.trusted_callers.contains(&info.sender),
+                ContractError::Unauthorized {}
+            );
+            let OrderCallback {
+                for_account,
+                orders,
+            } = cb.deserialize_callback()?;
+            let owner = deps.api.addr_validate(&for_account)?;
+            // The caller funds the account's orders, it can't retract, reduce or claim them
+            for (side, price, target) in orders.0.iter() {
+                let target = target.ok_or_else(|| {
+                    ContractError::Invalid("trusted callers can only increase orders".to_string())
+                })?;
+                let pool = Pool::load(deps.storage, price, side, &oracle);
+                let remaining = match pool.load_order(deps.storage, &owner) {
+                    Ok(order) => order.amount(),
+                    Err(ContractError::NotFound {}) => Uint128::zero(),
+                    Err(err) => return Err(err),
+                };
+                ensure!(
+                    target >= remaining,
+                    ContractError::Invalid(format!(
+                        "trusted callers can only increase orders, {target} below {remaining}"
+                    ))
+                );
+            }
+            let msg = order_msg(deps.storage, &env, owner.clone(), info.funds, orders)?;
+            Ok(Response::default()
+                .add_message(msg)
+                .add_event(event_order_callback(&info.sender, &owner)))
+        }
+        ExecuteMsg::Grid {
+            side_range,
+            levels,
+            total,
+            side,
+        } => {
+            let grid = Grid::create(
+                deps.storage,
+                &config.tick,
+                info.sender.clone(),
+                side,
+                side_range,
+                levels,
+                total,
+            )?;
+            let msg = order_msg(
+                deps.storage,
+                &env,
+                info.sender,
+                info.funds,
+                (grid.targets(), None),
+            )?;
+            Ok(Response::default()
+                .add_message(msg)
+                .add_event(event_create_grid(&grid)))
+        }
+        ExecuteMsg::RepriceGrid { id, side_range } => {
+            let mut grid = Grid::load_owned(deps.storage, id, &info.sender)?;
+            let targets = grid.reprice(&config.tick, side_range)?;
+            grid.save(deps.storage)?;
+            let msg = order_msg(deps.storage, &env, info.sender, info.funds, (targets, None))?;
+            Ok(Response::default()
+                .add_message(msg)
+                .add_event(event_reprice_grid(&grid)))
+        }
+        ExecuteMsg::CancelGrid { id } => {
+            let grid = Grid::load_owned(deps.storage, id, &info.sender)?;
+            grid.remove(deps.storage);
+            let msg = order_msg(
+                deps.storage,
+                &env,
+                info.sender,
+                info.funds,
+                (grid.cancel_targets(), None),
+            )?;
+            Ok(Response::default()
+                .add_message(msg)
+                .add_event(event_cancel_grid(&grid)))
+        }
+        ExecuteMsg::Twap(req) => {
+            let funds = one_coin(&info)?;
+            let side = config.denoms.ask_side(&funds)?;
+            let twap = Twap::create(
+                deps.storage,
+                info.sender,
+                side,
+                funds.amount,
+                env.block.time,
+                req,
+            )?;
+            Ok(Response::default().add_event(event_create_twap(&twap)))
+        }
+        ExecuteMsg::SetFillCallback {
+            side,
+            price,
+            callback,
+        } => {
+            nonpayable(&info)?;
+            // Callbacks can only be received by contracts
+            deps.querier
+                .query_wasm_contract_info(&info.sender)
+                .map_err(|_| ContractError::Unauthorized {})?;
+            let pool = Pool::load(deps.storage, &price, &side, &oracle);
+            let order = pool.load_order(deps.storage, &info.sender)?;
+            let set = callback.is_some();
+            match callback {
+                Some(callback) => FillCallback::set(deps.storage, &order, &side, &price, callback)?,
+                None => FillCallback::remove(deps.storage, &info.sender, &side, &price),
+            }
+            Ok(Response::default().add_event(event_set_fill_callback(
+                &info.sender,
+                &side,
+                &price,
+                set,
+            )))
+        }
+        ExecuteMsg::SetWithdrawWhitelist { addresses } => {
+            nonpayable(&info)?;
+            let effective = withdraw_whitelist::set(
+                deps.storage,
+                deps.api,
+                env.block.time,
+                &info.sender,
+                addresses.clone(),
+            )?;
+            Ok(Response::default().add_event(event_set_withdraw_whitelist(
+                &info.sender,
+                &addresses,
+                effective,
+            )))
+        }
+        ExecuteMsg::CrankFill { owner, side, price } => {
+            nonpayable(&info)?;
+            let owner = deps.api.addr_validate(&owner)?;
+            let pool = Pool::load(deps.storage, &price, &side, &oracle);
+            let order = pool.load_order(deps.storage, &owner)?;
+            let msg = FillCallback::crank(deps.storage, &pool, &order, env.block.time)?;
+            Ok(Response::default().add_submessage(msg))
+        }
+        ExecuteMsg::CrankTwap { id } => {
+            nonpayable(&info)?;
+            let twap = Twap::load(deps.storage, id)?;
+            ensure!(
+                !twap.due(env.block.time).is_zero(),
+                ContractError::TwapNotDue {}
+            );
+            Lock::acquire(deps.storage)?;
             let msg = WasmMsg::Execute {
                 contract_addr: env.contract.address.to_string(),
                 msg: to_json_binary(&ExecuteMsg::Arb {
-                    then: Some(to_json_binary(&ExecuteMsg::DoSwap((info.sender, req)))?),
+                    then: Some(to_json_binary(&ExecuteMsg::DoTwap(id))?),
                 })?,
-                funds: info.funds,
+                funds: vec![],
             };
 
             Ok(Response::default().add_message(msg))
         }
-        ExecuteMsg::Order(req) => {
-            let msg = WasmMsg::Execute {
-                contract_addr: env.contract.address.to_string(),
-                msg: to_json_binary(&ExecuteMsg::Arb {
-                    then: Some(to_json_binary(&ExecuteMsg::DoOrder((info.sender, req)))?),
-                })?,
-                funds: info.funds,
-            };
+        ExecuteMsg::CancelTwap { id } => {
+            let twap = Twap::load(deps.storage, id)?;
+            ensure_eq!(twap.owner, info.sender, ContractError::Unauthorized {});
+            twap.remove(deps.storage)?;
 
+            Ok(Response::default()
+                .add_message(BankMsg::Send {
+                    to_address: twap.owner.to_string(),
+                    amount: vec![coin(twap.remaining.u128(), config.denoms.ask(&twap.side))],
+                })
+                .add_event(event_cancel_twap(&twap)))
+        }
+        ExecuteMsg::OpenMakerSession { ttl } => {
+            let session =
+                MakerSession::open(deps.storage, info.sender, ttl, env.block.time, info.funds)?;
+            Ok(Response::default().add_event(event_open_maker_session(&session)))
+        }
+        ExecuteMsg::Heartbeat { id } => {
+            nonpayable(&info)?;
+            let mut session =
+                MakerSession::load_live(deps.storage, id, &info.sender, env.block.time)?;
+            session.heartbeat(env.block.time);
+            session.save(deps.storage)?;
+            Ok(Response::default().add_event(event_heartbeat(&session)))
+        }
+        ExecuteMsg::MakerSessionOrder { id, orders } => {
+            let mut session =
+                MakerSession::load_live(deps.storage, id, &info.sender, env.block.time)?;
+            session.tag(deps.storage, &orders.0)?;
+            session.save(deps.storage)?;
+            let msg = order_msg(deps.storage, &env, info.sender, info.funds, orders)?;
             Ok(Response::default().add_message(msg))
         }
+        ExecuteMsg::CloseMakerSession { id } => {
+            nonpayable(&info)?;
+            let session = MakerSession::load_live(deps.storage, id, &info.sender, env.block.time)?;
+            session.remove(deps.storage)?;
+            let mut response = Response::default().add_event(event_close_maker_session(&session));
+            if !session.bounty.is_empty() {
+                response = response.add_message(BankMsg::Send {
+                    to_address: session.owner.to_string(),
+                    amount: session.bounty,
+                });
+            }
+            Ok(response)
+        }
+        ExecuteMsg::ExpireMakerSession { id } => {
+            nonpayable(&info)?;
+            let session = MakerSession::load(deps.storage, id)?;
+            ensure!(
+                session.expired(env.block.time),
+                ContractError::Invalid("maker session not expired".to_string())
+            );
+            session.remove(deps.storage)?;
+            let targets = session.cancel_targets(deps.storage, &oracle);
+            let mut response = Response::default().add_event(event_expire_maker_session(
+                &session,
+                &info.sender,
+                targets.len(),
+            ));
+            if !targets.is_empty() {
+                response = response.add_message(order_msg(
+                    deps.storage,
+                    &env,
+                    session.owner.clone(),
+                    vec![],
+                    (targets, None),
+                )?);
+            }
+            if !session.bounty.is_empty() {
+                response = response.add_message(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: session.bounty,
+                });
+            }
+            Ok(response)
+        }
+        ExecuteMsg::Sweep { keys, limit } => {
+            nonpayable(&info)?;
+            let (removed, bounty) = sweep::sweep(deps.storage, &config, keys, limit)?;
+            let bounty = bounty.into_vec();
+            let event = event_sweep(&info.sender, removed, &bounty);
+            if bounty.is_empty() {
+                return Ok(Response::default().add_event(event));
+            }
+            Ok(Response::default()
+                .add_message(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: bounty,
+                })
+                .add_event(event))
+        }
+        ExecuteMsg::Settle {} => {
+            nonpayable(&info)?;
+            Lock::ensure_released(deps.storage)?;
+            // Sessions only settle at a live oracle price
+            ensure!(
+                oracle.oracle.is_some() && fallback.is_none(),
+                ContractError::Invalid("oracle price unavailable".to_string())
+            );
+            let rate = Price::Oracle(0).to_rate(&oracle);
+            let settlement = session::settle(deps.storage, &config, env.block.time, &oracle, rate)?;
+            if !settlement.base.is_zero() {
+                Trade::new(rate, env.block.time).save(deps.storage)?;
+            }
+            let event = event_settle(&settlement);
+            if settlement.surplus.is_empty() {
+                return Ok(Response::default().add_event(event));
+            }
+            Ok(Response::default()
+                .add_messages(config.fee_msgs(settlement.surplus))
+                .add_event(event))
+        }
         ExecuteMsg::Arb { then } => {
+            // Permissionless arbs can't run mid-sequence, and only the contract itself can chain a follow-up
+            if info.sender != env.contract.address {
+                ensure!(then.is_none(), ContractError::Unauthorized {});
+                Lock::ensure_released(deps.storage)?;
+            }
+            // Crossing orders rest until the session window is settled
+            if session::is_auction(deps.storage, &config, env.block.time)? {
+                return Ok(
+                    Response::default().add_messages(then.map(|msg| WasmMsg::Execute {
+                        contract_addr: env.contract.address.to_string(),
+                        msg,
+                        funds: info.funds,
+                    })),
+                );
+            }
             let mut arb = Arber::default();
             let base_iter = swap_iter.iter(deps.storage, &Side::Base, &oracle);
             let quote_iter = swap_iter.iter(deps.storage, &Side::Quote, &oracle);
@@ -90,11 +491,19 @@ pub fn execute(
             fees += coin(res.profit_base.u128(), config.denoms.bid(&Side::Base));
             fees.normalize();
 
+            let mut events = vec![];
             if !fees.is_empty() {
-                messages.push(CosmosMsg::Bank(BankMsg::Send {
-                    to_address: config.fee_address.to_string(),
-                    amount: fees.into_vec(),
-                }))
+                let usd = config.value_usd(deps.querier, res.profit_base, res.profit_quote);
+                stats::record_arb(
+                    deps.storage,
+                    &[
+                        (config.denoms.base(), res.profit_base),
+                        (config.denoms.quote(), res.profit_quote),
+                    ],
+                    usd,
+                )?;
+                events.push(event_arb(&fees.clone().into_vec(), usd));
+                messages.extend(config.fee_msgs(fees));
             }
 
             if let Some(msg) = then {
@@ -105,7 +514,9 @@ pub fn execute(
                 }))
             }
 
-            Ok(Response::default().add_messages(messages))
+            Ok(Response::default()
+                .add_messages(messages)
+                .add_events(events))
         }
 
         ExecuteMsg::DoSwap((sender, req)) => {
@@ -114,54 +525,225 @@ pub fn execute(
                 env.contract.address,
                 ContractError::Unauthorized {}
             );
-            let to = req.to().map(|x| deps.api.addr_validate(&x)).transpose()?;
+            ensure!(
+                !session::is_auction(deps.storage, &config, env.block.time)?,
+                ContractError::SessionActive {}
+            );
+            let recipient = withdraw_whitelist::recipient(
+                deps.storage,
+                deps.api,
+                env.block.time,
+                &sender,
+                req.to(),
+            )?;
             let funds = one_coin(&info)?;
             let side = config.denoms.ask_side(&funds)?;
             let mut swapper = Swapper::new(
                 env!("CARGO_PKG_NAME"),
                 funds.amount,
-                req.clone(),
+                config.scale_request(req.clone(), &side),
                 config.fee_taker,
-            );
+            )
+            .with_quote_usd(quote_usd);
             let res = {
                 let mut iter = swap_iter.iter(deps.storage, &side, &oracle);
-                swapper.swap(&mut iter)?
+                swapper.swap(deps.storage, &mut iter)?
             };
+            rounding::check_fee(&res, config.fee_taker)?;
             let commit = swapper.commit(deps.storage)?;
             messages.append(&mut commit.to_msgs(&config.denoms, &side)?);
-            let mut funds = NativeBalance(vec![
+            if let Some(rate) = res.last_rate {
+                Trade::new(rate, env.block.time).save(deps.storage)?;
+            }
+            Lock::release(deps.storage);
+            market_maker_fallback::clear(deps.storage);
+            let funds = NativeBalance(vec![
                 coin(res.return_amount.u128(), config.denoms.bid(&side)),
                 coin(res.remaining_offer.u128(), config.denoms.ask(&side)),
             ]);
 
-            let recipient = to.unwrap_or(sender);
+            messages.append(&mut wrap::payout(
+                deps.storage,
+                &config,
+                &recipient,
+                req.callback(),
+                funds,
+            )?);
 
-            funds.normalize();
-            if !funds.is_empty() {
-                match req.callback() {
-                    None => messages.push(CosmosMsg::Bank(BankMsg::Send {
-                        to_address: recipient.to_string(),
-                        amount: funds.into_vec(),
-                    })),
-                    Some(cb) => messages.push(
-                        cb.to_message(&recipient, Empty {}, funds.into_vec())?
-                            .into(),
-                    ),
+            fees += coin(res.fee_amount.u128(), config.denoms.bid(&side));
+            fees.normalize();
+            sweep::reserve(deps.storage, &config, &mut fees)?;
+            let (mut conversion, conversion_events) = fees::convert(
+                deps.storage,
+                &config,
+                &swap_iter,
+                &oracle,
+                env.block.time,
+                &mut fees,
+            )?;
+            messages.append(&mut conversion);
+            messages.extend(config.fee_msgs(fees));
+
+            Ok(Response::default()
+                .add_messages(messages)
+                .add_events(res.events)
+                .add_events(conversion_events))
+        }
+        ExecuteMsg::DoAuctionFill(fill) => {
+            ensure_eq!(
+                info.sender,
+                env.contract.address,
+                ContractError::Unauthorized {}
+            );
+            let offer = one_coin(&info)?;
+            let ask_denom = config.denoms.bid(&config.denoms.ask_side(&offer)?);
+            let before = deps
+                .querier
+                .query_balance(&env.contract.address, ask_denom)?;
+            order_flow_auction::save_pending(deps.storage, &fill, &before)?;
+            // The bidder returns the ask denom within its execution, and the return is measured after it
+            Ok(Response::default()
+                .add_message(
+                    BidderMsg::Fill {
+                        ask_denom: ask_denom.to_string(),
+                        bid: fill.bid,
+                    }
+                    .call(&fill.bidder, info.funds)?,
+                )
+                .add_message(WasmMsg::Execute {
+                    contract_addr: env.contract.address.to_string(),
+                    msg: to_json_binary(&ExecuteMsg::SettleAuction {})?,
+                    funds: vec![],
+                }))
+        }
+        ExecuteMsg::SettleAuction {} => {
+            ensure_eq!(
+                info.sender,
+                env.contract.address,
+                ContractError::Unauthorized {}
+            );
+            let (fill, before) = order_flow_auction::take_pending(deps.storage)?;
+            let balance = deps
+                .querier
+                .query_balance(&env.contract.address, &before.denom)?;
+            let returned = coin(
+                balance.amount.saturating_sub(before.amount).u128(),
+                &before.denom,
+            );
+            ensure!(
+                returned.amount >= fill.bid,
+                ContractError::InsufficientReturn {
+                    requested: coin(fill.bid.u128(), &before.denom),
+                    returned,
                 }
-            };
+            );
+            let fee = coin(
+                returned.amount.mul_ceil(config.fee_taker).u128(),
+                &before.denom,
+            );
+            Lock::release(deps.storage);
+            let recipient = withdraw_whitelist::recipient(
+                deps.storage,
+                deps.api,
+                env.block.time,
+                &fill.sender,
+                fill.req.to(),
+            )?;
+            messages.append(&mut wrap::payout(
+                deps.storage,
+                &config,
+                &recipient,
+                fill.req.callback(),
+                NativeBalance(vec![coin(
+                    (returned.amount - fee.amount).u128(),
+                    &before.denom,
+                )]),
+            )?);
 
-            fees += coin(res.fee_amount.u128(), config.denoms.bid(&side));
+            fees += fee.clone();
             fees.normalize();
-            if !fees.is_empty() {
+            sweep::reserve(deps.storage, &config, &mut fees)?;
+            let (mut conversion, conversion_events) = fees::convert(
+                deps.storage,
+                &config,
+                &swap_iter,
+                &oracle,
+                env.block.time,
+                &mut fees,
+            )?;
+            messages.append(&mut conversion);
+            messages.extend(config.fee_msgs(fees));
+
+            Ok(Response::default()
+                .add_messages(messages)
+                .add_event(event_order_flow_auction_fill(&fill.bidder, &returned, &fee))
+                .add_events(conversion_events))
+        }
+        ExecuteMsg::DoTwap(id) => {
+            ensure_eq!(
+                info.sender,
+                env.contract.address,
+                ContractError::Unauthorized {}
+            );
+            ensure!(
+                !session::is_auction(deps.storage, &config, env.block.time)?,
+                ContractError::SessionActive {}
+            );
+            let mut twap = Twap::load(deps.storage, id)?;
+            let mut swapper = Swapper::new(
+                env!("CARGO_PKG_NAME"),
+                twap.due(env.block.time),
+                config.scale_request(twap.swap_request(), &twap.side),
+                config.fee_taker,
+            )
+            .with_quote_usd(quote_usd);
+            let res = {
+                let mut iter = swap_iter.iter(deps.storage, &twap.side, &oracle);
+                swapper.swap(deps.storage, &mut iter)?
+            };
+            rounding::check_fee(&res, config.fee_taker)?;
+            let commit = swapper.commit(deps.storage)?;
+            messages.append(&mut commit.to_msgs(&config.denoms, &twap.side)?);
+            if let Some(rate) = res.last_rate {
+                Trade::new(rate, env.block.time).save(deps.storage)?;
+            }
+            Lock::release(deps.storage);
+            twap.record(res.consumed_offer, res.return_amount)?;
+            twap.save(deps.storage)?;
+
+            if !res.return_amount.is_zero() {
                 messages.push(CosmosMsg::Bank(BankMsg::Send {
-                    to_address: config.fee_address.to_string(),
-                    amount: fees.into_vec(),
+                    to_address: twap.owner.to_string(),
+                    amount: vec![coin(
+                        res.return_amount.u128(),
+                        config.denoms.bid(&twap.side),
+                    )],
                 }))
             }
 
+            fees += coin(res.fee_amount.u128(), config.denoms.bid(&twap.side));
+            fees.normalize();
+            sweep::reserve(deps.storage, &config, &mut fees)?;
+            let (mut conversion, conversion_events) = fees::convert(
+                deps.storage,
+                &config,
+                &swap_iter,
+                &oracle,
+                env.block.time,
+                &mut fees,
+            )?;
+            messages.append(&mut conversion);
+            messages.extend(config.fee_msgs(fees));
+
             Ok(Response::default()
                 .add_messages(messages)
-                .add_events(res.events))
+                .add_events(res.events)
+                .add_events(conversion_events)
+                .add_event(event_crank_twap(
+                    &twap,
+                    &res.consumed_offer,
+                    &res.return_amount,
+                )))
         }
         ExecuteMsg::DoOrder((recipient, (vec, callback))) => {
             ensure_eq!(
@@ -170,48 +752,99 @@ pub fn execute(
                 ContractError::Unauthorized {}
             );
 
+            let auction = session::is_auction(deps.storage, &config, env.block.time)?;
             let mut e = OrderManager::new(
                 &config,
                 recipient.clone(),
                 env.block.time,
                 NativeBalance(info.funds),
-            );
+            )
+            .with_quote_usd(quote_usd)
+            .with_auction(auction);
 
             let mut res = e.execute_orders(deps.storage, &swap_iter, vec, &oracle)?;
+            Lock::release(deps.storage);
             fees += res.fees;
             messages.append(&mut res.messages);
 
-            if !res.withdraw.is_empty() {
-                match callback {
-                    None => messages.push(CosmosMsg::Bank(BankMsg::Send {
-                        to_address: recipient.to_string(),
-                        amount: res.withdraw.into_vec(),
-                    })),
-                    Some(cb) => messages.push(
-                        cb.to_message(&recipient, Empty {}, res.withdraw.into_vec())?
-                            .into(),
-                    ),
-                }
-            }
+            messages.append(&mut wrap::payout(
+                deps.storage,
+                &config,
+                &recipient,
+                callback,
+                res.withdraw,
+            )?);
 
             fees.normalize();
+            sweep::reserve(deps.storage, &config, &mut fees)?;
+            // The book isn't traded against while a session window is unsettled
+            let (mut conversion, conversion_events) = match auction {
+                true => (vec![], vec![]),
+                false => fees::convert(
+                    deps.storage,
+                    &config,
+                    &swap_iter,
+                    &oracle,
+                    env.block.time,
+                    &mut fees,
+                )?,
+            };
+            messages.append(&mut conversion);
 
-            if !fees.is_empty() {
-                messages.push(CosmosMsg::Bank(BankMsg::Send {
-                    to_address: config.fee_address.to_string(),
-                    amount: fees.into_vec(),
-                }))
-            }
+            messages.extend(config.fee_msgs(fees));
 
             Ok(Response::default()
                 .add_messages(messages)
-                .add_events(res.events))
+                .add_submessages(res.sub_msgs)
+                .add_events(res.events)
+                .add_events(conversion_events))
+        }
+    };
+    let response = response?;
+    let summary = event_trade_summary(&response.events);
+    Ok(response
+        .add_events(summary)
+        .add_events(fallback)
+        .add_events(swap_iter.dropped().iter().map(event_market_maker_dropped)))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match (msg.result, msg.id) {
+        // A failing fill callback doesn't revert the settlement it was sent from
+        (SubMsgResult::Err(err), FILL_CALLBACK_REPLY_ID) => {
+            Ok(Response::default().add_event(event_fill_callback_failed(err)))
+        }
+        // A market maker that fails to settle doesn't brick swaps. The swap is retried against the
+        // book alone, still holding the lock it took
+        (SubMsgResult::Err(err), MARKET_MAKER_FALLBACK_REPLY_ID) => {
+            let (sender, funds, req): FallbackPayload = from_json(&msg.payload)?;
+            market_maker_fallback::exclude(deps.storage)?;
+            Ok(Response::default()
+                .add_message(do_swap_msg(&env, sender, funds, req)?)
+                .add_event(event_market_maker_fallback(err)))
         }
+        // A winning bidder that fails to fill doesn't fail the swap. It is matched against the book
+        // instead, still holding the lock it took
+        (SubMsgResult::Err(err), ORDER_FLOW_AUCTION_REPLY_ID) => {
+            let (sender, funds, req): FallbackPayload = from_json(&msg.payload)?;
+            let config = CONFIG.load(deps.storage)?;
+            let msg = do_swap_msg(&env, sender.clone(), funds.clone(), req.clone())?;
+            Ok(Response::default()
+                .add_submessage(market_maker_fallback::guard(
+                    &config,
+                    msg,
+                    &(sender, funds, req),
+                )?)
+                .add_event(event_order_flow_auction_fallback(err)))
+        }
+        (SubMsgResult::Ok(_), _) => Ok(Response::default()),
+        _ => Err(ContractError::Unauthorized {}),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
     match msg {
         SudoMsg::UpdateConfig {
             tick,
@@ -220,6 +853,8 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
             fee_address,
             market_makers,
             oracles,
+            premium_bounds,
+            decimal_delta,
         } => {
             let fee_address = fee_address
                 .map(|x| deps.api.addr_validate(&x))
@@ -238,21 +873,250 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
                 fee_address,
                 oracles,
             );
+            if let Some(premium_bounds) = premium_bounds {
+                config.premium_bounds = premium_bounds;
+            }
+            if let Some(decimal_delta) = decimal_delta {
+                // Orders are stored at atomic prices, converted from the human prices they were
+                // placed at with the delta, so it can't change under them
+                ensure!(
+                    decimal_delta == config.decimal_delta || stats::open_orders(deps.storage)? == 0,
+                    ContractError::Invalid(
+                        "decimal_delta can only be changed with no open orders".to_string()
+                    )
+                );
+                config.decimal_delta = decimal_delta;
+            }
+            config.validate(deps.as_ref())?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetTrustedCaller { address, trusted } => {
+            let address = deps.api.addr_validate(&address)?;
+            let mut config = CONFIG.load(deps.storage)?;
+            config.trusted_callers.retain(|x| x != address);
+            if trusted {
+                config.trusted_callers.push(address);
+            }
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetWrapAdapter {
+            vault,
+            underlying,
+            receipt,
+        } => {
+            let mut config = CONFIG.load(deps.storage)?;
+            config.wrap = vault
+                .map(|x| -> StdResult<WrapAdapter> {
+                    Ok(WrapAdapter {
+                        vault: deps.api.addr_validate(&x)?,
+                        underlying,
+                        receipt,
+                    })
+                })
+                .transpose()?;
+            config.validate(deps.as_ref())?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetSweepBounty { bounty } => {
+            let mut config = CONFIG.load(deps.storage)?;
+            config.sweep_bounty = bounty;
+            config.validate(deps.as_ref())?;
+            config.save(deps.storage)?;
+            if config.sweep_bounty.is_some() {
+                return Ok(Response::default());
+            }
+            // Fees reserved for bounties are released to the fee address
+            let reserve = sweep::release(deps.storage)?;
+            if reserve.is_empty() {
+                return Ok(Response::default());
+            }
+            Ok(Response::default().add_messages(config.fee_msgs(reserve)))
+        }
+        SudoMsg::SetSession { session } => {
+            let mut config = CONFIG.load(deps.storage)?;
+            config.session = session;
+            config.validate(deps.as_ref())?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetOracleFallback { fallback } => {
+            let mut config = CONFIG.load(deps.storage)?;
+            config.oracle_fallback = fallback;
+            config.validate(deps.as_ref())?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetFeeConversion { conversion } => {
+            let mut config = CONFIG.load(deps.storage)?;
+            config.fee_conversion = conversion;
+            config.validate(deps.as_ref())?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetMarketMakerLimit { limit } => {
+            let mut config = CONFIG.load(deps.storage)?;
+            config.market_maker_limit = limit;
+            config.validate(deps.as_ref())?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetOrderFlowAuction { auction } => {
+            for bidder in auction.iter().flat_map(|x| &x.bidders) {
+                deps.api.addr_validate(bidder.as_str())?;
+            }
+            let mut config = CONFIG.load(deps.storage)?;
+            config.order_flow_auction = auction;
+            config.validate(deps.as_ref())?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetTargetEpsilon { epsilon } => {
+            let mut config = CONFIG.load(deps.storage)?;
+            config.target_epsilon = epsilon;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetFeeAddresses { base, quote } => {
+            let mut config = CONFIG.load(deps.storage)?;
+            config.fee_address_base = base.map(|x| deps.api.addr_validate(&x)).transpose()?;
+            config.fee_address_quote = quote.map(|x| deps.api.addr_validate(&x)).transpose()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetAuxQuote {
+            denom,
+            pair,
+            oracle,
+            max_slippage,
+        } => {
+            let mut config = CONFIG.load(deps.storage)?;
+            config.aux_quotes.retain(|x| x.denom != denom);
+            if let Some(pair) = pair {
+                config.aux_quotes.push(AuxQuote {
+                    denom,
+                    pair: deps.api.addr_validate(&pair)?,
+                    oracle,
+                    max_slippage,
+                });
+            }
             config.validate(deps.as_ref())?;
             config.save(deps.storage)?;
             Ok(Response::default())
         }
+        SudoMsg::Rescue { denom, to } => {
+            // The liabilities are incomplete until the backfill of their totals completes
+            migration::ensure_complete(deps.storage)?;
+            let config = CONFIG.load(deps.storage)?;
+            let to = deps.api.addr_validate(&to)?;
+            let mut liabilities = stats::liabilities(deps.storage, &config.denoms)?;
+            for c in sweep::reserved(deps.storage)?.into_vec() {
+                liabilities += c;
+            }
+            let owed = liabilities
+                .0
+                .iter()
+                .find(|x| x.denom == denom)
+                .map(|x| x.amount)
+                .unwrap_or_default();
+            let balance = deps
+                .querier
+                .query_balance(&env.contract.address, &denom)?
+                .amount;
+            let surplus = coin(balance.saturating_sub(owed).u128(), denom);
+            ensure!(
+                !surplus.amount.is_zero(),
+                ContractError::Invalid(format!("no surplus of {} to rescue", surplus.denom))
+            );
+            Ok(Response::default()
+                .add_message(BankMsg::Send {
+                    to_address: to.to_string(),
+                    amount: vec![surplus.clone()],
+                })
+                .add_event(event_rescue(&to, &surplus)))
+        }
+        SudoMsg::ImportLegacyOrders { orders } => {
+            migration::ensure_complete(deps.storage)?;
+            let config = CONFIG.load(deps.storage)?;
+            // Legacy orders are all fixed price, the oracle is never consulted
+            let oracle = Scaled {
+                oracle: None,
+                decimal_delta: config.decimal_delta,
+                time: env.block.time,
+            };
+            let mut response = Response::default();
+            for legacy in orders {
+                if !legacy::mark_imported(deps.storage, legacy.idx)? {
+                    continue;
+                }
+                let owner = deps.api.addr_validate(&legacy.owner)?;
+                let side = if legacy.offer_denom == config.denoms.base() {
+                    Side::Base
+                } else if legacy.offer_denom == config.denoms.quote() {
+                    Side::Quote
+                } else {
+                    return Err(ContractError::Invalid(format!(
+                        "offer_denom {}",
+                        legacy.offer_denom
+                    )));
+                };
+                config
+                    .tick
+                    .validate_price_scaled(&legacy.quote_price, &config.decimal_delta)?;
+                let price = Price::Fixed(legacy.quote_price);
+                let mut pool = Pool::load(deps.storage, &price, &side, &oracle);
+
+                // Merge with an order the owner already has at this price
+                let order = match pool.load_order(deps.storage, &owner) {
+                    Ok(mut order) => {
+                        pool.increase_order(
+                            deps.storage,
+                            &mut order,
+                            &env.block.time,
+                            legacy.offer_amount,
+                        )?;
+                        order
+                    }
+                    Err(ContractError::NotFound {}) => pool.create_order(
+                        deps.storage,
+                        &legacy.created_at,
+                        &owner,
+                        legacy.offer_amount,
+                    )?,
+                    Err(err) => return Err(err),
+                };
+
+                // Filled amounts are settled immediately rather than carried into the bid pool
+                if !legacy.filled_amount.is_zero() {
+                    response = response.add_message(BankMsg::Send {
+                        to_address: owner.to_string(),
+                        amount: vec![coin(legacy.filled_amount.u128(), config.denoms.ask(&side))],
+                    });
+                }
+                response = response.add_event(event_import_order(
+                    &pool,
+                    &order,
+                    &legacy.idx,
+                    &legacy.filled_amount,
+                ));
+            }
+            Ok(response)
+        }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    let oracle = config
-        .oracles
-        .clone()
-        .map(|x| x.tor_price(deps.querier))
-        .transpose()?;
+    let oracle = Scaled {
+        oracle: oracle::query(deps.storage, deps.querier, &config, env.block.time)?,
+        decimal_delta: config.decimal_delta,
+        time: env.block.time,
+    };
+    let delta = config.decimal_delta;
+    let quote_usd = config.quote_usd(deps.querier);
     let swap_iter = SwapIter::new(deps.querier, &config);
 
     match msg {
@@ -261,53 +1125,151 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             let addr = deps.api.addr_validate(&owner)?;
             let pool = Pool::load(deps.storage, &price, &side, &oracle);
             let order = pool.load_order(deps.storage, &addr)?;
-            to_json_binary(&order_response(&order, &pool.side, &pool.price, &oracle))
+            to_json_binary(&order_response(
+                &order,
+                &pool.side,
+                &pool.price,
+                &oracle,
+                &delta,
+                quote_usd,
+            ))
         }
-        QueryMsg::Orders {
-            owner,
-            side,
-            offset,
-            limit,
-        } => {
+        QueryMsg::Orders { owner, side, page } => {
             let addr = deps.api.addr_validate(owner.as_str())?;
-            let orders: Result<Vec<OrderResponse>, ContractError> =
-                Order::by_owner(deps.storage, &addr, side, offset, limit)?
-                    .iter_mut()
-                    .map(|(k, order)| {
-                        let pool = Pool::load(deps.storage, &k.price, &k.side, &oracle);
-                        pool.sync_order(deps.storage, order)?;
-                        Ok(order_response(order, &k.side, &k.price, &oracle))
-                    })
-                    .collect();
+            let limit = page.limit(order::DEFAULT_LIMIT, order::MAX_LIMIT);
+            let mut orders = Order::by_owner(deps.storage, &addr, side, page.start_after, limit)?;
+            let page =
+                PageResponse::new(&orders, limit, |(k, _)| (k.side.clone(), k.price.clone()));
+            let orders: Result<Vec<OrderResponse>, ContractError> = orders
+                .iter_mut()
+                .map(|(k, order)| {
+                    let pool = Pool::load(deps.storage, &k.price, &k.side, &oracle);
+                    pool.sync_order(deps.storage, order)?;
+                    Ok(order_response(
+                        order, &k.side, &k.price, &oracle, &delta, quote_usd,
+                    ))
+                })
+                .collect();
 
-            to_json_binary(&OrdersResponse { orders: orders? })
+            to_json_binary(&OrdersResponse {
+                orders: orders?,
+                page,
+            })
         }
-        QueryMsg::Book { limit, offset } => {
-            let limit = limit.unwrap_or(100);
-            let offset = offset.unwrap_or(0);
+        QueryMsg::PoolOrders { side, price, page } => {
+            let limit = page.limit(order::DEFAULT_LIMIT, order::MAX_LIMIT);
+            let page = page.try_map(|x| deps.api.addr_validate(&x))?;
+            let pool = Pool::load(deps.storage, &price, &side, &oracle);
+            let mut orders = Order::by_pool(deps.storage, &side, &price, page.start_after, limit)?;
+            let page = PageResponse::new(&orders, limit, |x| x.owner.to_string());
+            let orders: Result<Vec<OrderResponse>, ContractError> = orders
+                .iter_mut()
+                .map(|order| {
+                    pool.sync_order(deps.storage, order)?;
+                    Ok(order_response(
+                        order, &side, &price, &oracle, &delta, quote_usd,
+                    ))
+                })
+                .collect();
 
-            to_json_binary(&BookResponse {
-                base: swap_iter
-                    .iter(deps.storage, &Side::Base, &oracle)
-                    .skip(offset as usize)
-                    .take(limit as usize)
-                    .map(|v| BookItemResponse {
-                        price: v.rate(),
-                        total: v.total(),
-                    })
-                    .collect(),
-                quote: swap_iter
-                    .iter(deps.storage, &Side::Quote, &oracle)
-                    .skip(offset as usize)
-                    .take(limit as usize)
-                    .map(|v| BookItemResponse {
-                        price: v.rate(),
-                        total: v.total(),
-                    })
-                    .collect(),
+            to_json_binary(&PoolOrdersResponse {
+                orders: orders?,
+                page,
             })
         }
-        QueryMsg::Simulate(offer) => {
+        QueryMsg::Book { page, group_by } => {
+            let limit = page.limit(BOOK_LIMIT, BOOK_LIMIT);
+            let cursor = page.start_after.unwrap_or_default();
+            if group_by.is_some_and(|x| x.is_zero()) {
+                return Err(ContractError::Invalid("group_by".to_string()));
+            }
+
+            // Levels are returned best rate first, so the cursor excludes every rate up to and
+            // including the last rate of the previous page
+            let base = book_levels(
+                swap_iter
+                    .iter(deps.storage, &Side::Base, &oracle)
+                    .map(|v| book_item(&v, &delta, quote_usd))
+                    .skip_while(|v| cursor.base.is_some_and(|c| v.price <= c)),
+                &Side::Base,
+                group_by,
+                limit,
+            );
+            let quote = book_levels(
+                swap_iter
+                    .iter(deps.storage, &Side::Quote, &oracle)
+                    .map(|v| book_item(&v, &delta, quote_usd))
+                    .skip_while(|v| cursor.quote.is_some_and(|c| v.price >= c)),
+                &Side::Quote,
+                group_by,
+                limit,
+            );
+
+            let page = if base.len() < limit && quote.len() < limit {
+                PageResponse::default()
+            } else {
+                // A grouped page ends at the bound of its last bucket, so that the next
+                // page starts with the following bucket
+                PageResponse {
+                    next: Some(BookCursor {
+                        base: base
+                            .last()
+                            .map(|x| x.bucket.unwrap_or(x.price))
+                            .or(cursor.base),
+                        quote: quote
+                            .last()
+                            .map(|x| x.bucket.unwrap_or(x.price))
+                            .or(cursor.quote),
+                    }),
+                }
+            };
+
+            to_json_binary(&BookResponse { base, quote, page })
+        }
+        QueryMsg::Ticker {} => {
+            let best_ask = swap_iter
+                .iter(deps.storage, &Side::Base, &oracle)
+                .next()
+                .map(|v| delta.to_human(v.rate()));
+            let best_bid = swap_iter
+                .iter(deps.storage, &Side::Quote, &oracle)
+                .next()
+                .map(|v| delta.to_human(v.rate()));
+            let (mid, spread) = match (best_bid, best_ask) {
+                (Some(bid), Some(ask)) => (
+                    Some((bid + ask) * Decimal::percent(50)),
+                    Some(ask.abs_diff(bid)),
+                ),
+                _ => (None, None),
+            };
+            let last = Trade::last(deps.storage)?;
+
+            to_json_binary(&TickerResponse {
+                best_bid,
+                best_ask,
+                mid,
+                spread,
+                last_price: last.as_ref().map(|x| delta.to_human(x.price)),
+                last_time: last.map(|x| x.timestamp),
+            })
+        }
+        QueryMsg::Twap { id } => {
+            to_json_binary(&Twap::load(deps.storage, id)?.to_response(env.block.time))
+        }
+        QueryMsg::Stats {} => to_json_binary(&stats::load(deps.storage)?),
+        QueryMsg::WithdrawWhitelist { owner } => to_json_binary(
+            &withdraw_whitelist::load(
+                deps.storage,
+                env.block.time,
+                &deps.api.addr_validate(&owner)?,
+            )?
+            .map(|x| x.iter().map(|x| x.to_string()).collect::<Vec<_>>()),
+        ),
+        QueryMsg::Grid { id } => to_json_binary(&GridResponse::from(Grid::load(deps.storage, id)?)),
+        QueryMsg::MakerSession { id } => to_json_binary(&MakerSessionResponse::from(
+            MakerSession::load(deps.storage, id)?,
+        )),
+        QueryMsg::Simulate(offer) => {
             let side = config.denoms.ask_side(&offer)?;
             let mut swapper = Swapper::new(
                 env!("CARGO_PKG_NAME"),
@@ -315,25 +1277,113 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
                 SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
                 config.fee_taker,
             );
             let mut iter = swap_iter.iter(deps.storage, &side, &oracle);
-            let res = swapper.swap(&mut iter)?;
+            let res = swapper.swap(deps.storage, &mut iter)?;
             to_json_binary(&SimulationResponse {
                 returned: res.return_amount,
                 fee: res.fee_amount,
             })
         }
+        QueryMsg::ArbOpportunity {} => {
+            // Crossing orders rest until the session window is settled
+            if session::is_auction(deps.storage, &config, env.block.time)? {
+                return Ok(to_json_binary(&ArbOpportunityResponse {
+                    crossed: false,
+                    size: vec![],
+                    profit: vec![],
+                    profit_usd: None,
+                })?);
+            }
+            let base_iter = swap_iter.iter(deps.storage, &Side::Base, &oracle);
+            let quote_iter = swap_iter.iter(deps.storage, &Side::Quote, &oracle);
+            let res = Arber::default().run(base_iter, quote_iter)?;
+            let mut size = NativeBalance::default();
+            size += coin(res.size_base.u128(), config.denoms.base());
+            size += coin(res.size_quote.u128(), config.denoms.quote());
+            size.normalize();
+            let mut profit = NativeBalance::default();
+            profit += coin(res.profit_base.u128(), config.denoms.base());
+            profit += coin(res.profit_quote.u128(), config.denoms.quote());
+            profit.normalize();
+            to_json_binary(&ArbOpportunityResponse {
+                crossed: !size.is_empty(),
+                profit_usd: match profit.is_empty() {
+                    true => None,
+                    false => config.value_usd(deps.querier, res.profit_base, res.profit_quote),
+                },
+                size: size.into_vec(),
+                profit: profit.into_vec(),
+            })
+        }
     }
     .map_err(ContractError::Std)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractError> {
+pub fn migrate(mut deps: DepsMut, env: Env, _msg: ()) -> Result<Response, ContractError> {
+    let previous = get_contract_version(deps.storage)?;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    Config::migrate(deps)?;
-    Ok(Response::default())
+    Config::migrate(deps.branch())?;
+    let config = CONFIG.load(deps.storage)?;
+    let (price, _) = oracle::observe(deps.storage, deps.querier, &config, env.block.time)?;
+    let oracle = Scaled {
+        oracle: price,
+        decimal_delta: config.decimal_delta,
+        time: env.block.time,
+    };
+    // A large book is backfilled over several calls, re-running the migration until it completes
+    migration::start(deps.storage, &previous.version)?;
+    let complete = migration::step(deps.storage, &oracle, migration::LIMIT)?;
+    Ok(Response::default().add_event(event_migrate(complete)))
+}
+
+/// Starts the Swap -> Arb -> DoSwap sequence for `req`, on behalf of `sender`
+fn swap_msg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &Config,
+    sender: Addr,
+    funds: Vec<Coin>,
+    req: SwapRequest,
+) -> Result<SubMsg, ContractError> {
+    Lock::acquire(storage)?;
+    let msg = do_swap_msg(env, sender.clone(), funds.clone(), req.clone())?;
+    Ok(market_maker_fallback::guard(
+        config,
+        msg,
+        &(sender, funds, req),
+    )?)
+}
+
+fn do_swap_msg(env: &Env, sender: Addr, funds: Vec<Coin>, req: SwapRequest) -> StdResult<WasmMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        msg: to_json_binary(&ExecuteMsg::Arb {
+            then: Some(to_json_binary(&ExecuteMsg::DoSwap((sender, req)))?),
+        })?,
+        funds,
+    })
+}
+
+fn order_msg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    owner: Addr,
+    funds: Vec<Coin>,
+    req: (Vec<OrderTarget>, Option<CallbackData>),
+) -> Result<WasmMsg, ContractError> {
+    Lock::acquire(storage)?;
+    Ok(WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        msg: to_json_binary(&ExecuteMsg::Arb {
+            then: Some(to_json_binary(&ExecuteMsg::DoOrder((owner, req)))?),
+        })?,
+        funds,
+    })
 }
 
 fn order_response(
@@ -341,12 +1391,16 @@ fn order_response(
     side: &Side,
     price: &Price,
     oracle: &impl Premiumable,
+    delta: &DecimalDelta,
+    quote_usd: Option<Decimal>,
 ) -> OrderResponse {
+    let rate = delta.to_human(price.to_rate(oracle));
     OrderResponse {
         owner: order.owner.to_string(),
         side: side.clone(),
         price: price.clone(),
-        rate: price.to_rate(oracle),
+        rate,
+        rate_usd: quote_usd.map(|x| rate * x),
         updated_at: order.updated_at,
         offer: order.offer,
         remaining: order.bid.amount().try_into().unwrap(),
@@ -354,17 +1408,65 @@ fn order_response(
     }
 }
 
+fn book_item(
+    v: &impl Swappable,
+    delta: &DecimalDelta,
+    quote_usd: Option<Decimal>,
+) -> BookItemResponse {
+    let price = delta.to_human(v.rate());
+    BookItemResponse {
+        price,
+        total: v.total(),
+        rate_usd: quote_usd.map(|x| price * x),
+        bucket: None,
+    }
+}
+
+/// Takes up to `limit` levels, or buckets of levels when grouped. Levels arrive best price first,
+/// so the first level of each bucket holds its best price
+fn book_levels(
+    levels: impl Iterator<Item = BookItemResponse>,
+    side: &Side,
+    group_by: Option<Decimal>,
+    limit: usize,
+) -> Vec<BookItemResponse> {
+    let Some(group_by) = group_by else {
+        return levels.take(limit).collect();
+    };
+    let mut buckets: Vec<BookItemResponse> = vec![];
+    for level in levels {
+        let multiple = level.price / group_by;
+        let bucket = match side {
+            Side::Base => multiple.ceil(),
+            Side::Quote => multiple.floor(),
+        } * group_by;
+        match buckets.last_mut() {
+            Some(last) if last.bucket == Some(bucket) => last.total += level.total,
+            _ if buckets.len() == limit => break,
+            _ => buckets.push(BookItemResponse {
+                bucket: Some(bucket),
+                ..level
+            }),
+        }
+    }
+    buckets
+}
+
 #[cfg(test)]
 mod tests {
 
     use std::str::FromStr;
 
     use super::*;
-    use cosmwasm_std::{coin, coins, Addr, Decimal, Event, Uint128};
+    use cosmwasm_std::{coin, coins, Addr, Decimal, Empty, Event, Timestamp, Uint128};
     use cw_multi_test::{ContractWrapper, Executor};
+    use rujira_ghost_vault::mock::GhostVault;
     use rujira_rs::{
-        fin::{Denoms, SwapRequest, Tick},
-        Layer1Asset,
+        fin::{
+            Denoms, FeeConversion, LegacyOrder, PremiumBounds, Session, StatsResponse, SwapRequest,
+            SweepBounty, Tick, TwapRequest, TwapResponse,
+        },
+        CallbackMsg, Layer1Asset, PageRequest,
     };
     use rujira_rs_testing::{mock_rujira_app, RujiraApp};
 
@@ -373,7 +1475,11 @@ mod tests {
 
         let owner = app.api().addr_make("owner");
 
-        let code = Box::new(ContractWrapper::new(execute, instantiate, query).with_sudo(sudo));
+        let code = Box::new(
+            ContractWrapper::new(execute, instantiate, query)
+                .with_sudo(sudo)
+                .with_reply(reply),
+        );
         let code_id = app.store_code(code);
         let contract = app
             .instantiate_contract(
@@ -466,8 +1572,8 @@ mod tests {
             .query_wasm_smart(
                 contract.clone(),
                 &QueryMsg::Book {
-                    limit: None,
-                    offset: None,
+                    page: PageRequest::default(),
+                    group_by: None,
                 },
             )
             .unwrap();
@@ -574,10 +1680,10 @@ mod tests {
         let book: BookResponse = app
             .wrap()
             .query_wasm_smart(
-                contract,
+                contract.clone(),
                 &QueryMsg::Book {
-                    limit: None,
-                    offset: None,
+                    page: PageRequest::default(),
+                    group_by: None,
                 },
             )
             .unwrap();
@@ -599,6 +1705,81 @@ mod tests {
         let entry = book.quote[0].clone();
         assert_eq!(entry.price, Decimal::from_str("87900").unwrap());
         assert_eq!(entry.total, Uint128::from(5100000u128));
+        // USDC is priced at 1.001
+        assert_eq!(entry.rate_usd, Some(Decimal::from_str("87987.9").unwrap()));
+        assert_eq!(book.page.next, None);
+
+        let page: BookResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Book {
+                    page: PageRequest::new(None, Some(2)),
+                    group_by: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(page.base, book.base[..2].to_vec());
+        assert_eq!(page.quote, book.quote);
+        let page: BookResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Book {
+                    page: PageRequest::new(page.page.next, Some(2)),
+                    group_by: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(page.base, book.base[2..].to_vec());
+        assert!(page.quote.is_empty());
+
+        let grouped: BookResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Book {
+                    page: PageRequest::new(None, Some(2)),
+                    group_by: Some(Decimal::from_str("1000").unwrap()),
+                },
+            )
+            .unwrap();
+        assert_eq!(grouped.base.len(), 2);
+        assert_eq!(grouped.base[0].price, Decimal::from_str("91219").unwrap());
+        assert_eq!(
+            grouped.base[0].bucket,
+            Some(Decimal::from_str("92000").unwrap())
+        );
+        assert_eq!(grouped.base[0].total, Uint128::from(5099977u128));
+        // 93219 and 93317 share a bucket, with the lower ask as its best price
+        assert_eq!(grouped.base[1].price, Decimal::from_str("93219").unwrap());
+        assert_eq!(
+            grouped.base[1].bucket,
+            Some(Decimal::from_str("94000").unwrap())
+        );
+        assert_eq!(grouped.base[1].total, Uint128::from(3100000u128));
+        assert_eq!(grouped.quote.len(), 1);
+        assert_eq!(grouped.quote[0].price, Decimal::from_str("87900").unwrap());
+        assert_eq!(
+            grouped.quote[0].bucket,
+            Some(Decimal::from_str("87000").unwrap())
+        );
+        let grouped: BookResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract,
+                &QueryMsg::Book {
+                    page: PageRequest::new(grouped.page.next, Some(2)),
+                    group_by: Some(Decimal::from_str("1000").unwrap()),
+                },
+            )
+            .unwrap();
+        assert_eq!(grouped.base.len(), 1);
+        assert_eq!(
+            grouped.base[0].bucket,
+            Some(Decimal::from_str("100000").unwrap())
+        );
+        assert!(grouped.quote.is_empty());
 
         #[allow(deprecated)]
         let balance = app.wrap().query_all_balances(owner.to_string()).unwrap();
@@ -613,6 +1794,254 @@ mod tests {
         );
     }
 
+    #[test]
+    fn free_tick() {
+        let (mut app, contract) = setup();
+        let owner = app.api().addr_make("owner");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(2_000, "btc-btc"))
+                .unwrap();
+        });
+        let prices = [
+            Decimal::from_str("0.000000000123456789").unwrap(),
+            Decimal::from_str("0.000000000123456788").unwrap(),
+        ];
+        let order = |app: &mut RujiraApp| {
+            app.execute_contract(
+                owner.clone(),
+                contract.clone(),
+                &ExecuteMsg::Order((
+                    prices
+                        .iter()
+                        .map(|x| (Side::Base, Price::Fixed(*x), Some(Uint128::from(1_000u128))))
+                        .collect(),
+                    None,
+                )),
+                &coins(2_000, "btc-btc"),
+            )
+        };
+        let err = order(&mut app).unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("InvalidPrice"));
+
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::UpdateConfig {
+                tick: Some(Tick::free()),
+                fee_taker: None,
+                fee_maker: None,
+                fee_address: None,
+                market_makers: None,
+                oracles: None,
+                premium_bounds: None,
+                decimal_delta: None,
+            },
+        )
+        .unwrap();
+        order(&mut app).unwrap();
+
+        // Levels are keyed by their exact price
+        let book: BookResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Book {
+                    page: PageRequest::default(),
+                    group_by: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(book.base.len(), 2);
+        assert_eq!(book.base[0].price, prices[1]);
+        assert_eq!(book.base[0].total, Uint128::from(1_000u128));
+        assert_eq!(book.base[1].price, prices[0]);
+        assert_eq!(book.base[1].total, Uint128::from(1_000u128));
+    }
+
+    #[test]
+    fn decimal_delta() {
+        let (mut app, contract) = setup();
+        let owner = app.api().addr_make("owner");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc-btc"))
+                .unwrap();
+        });
+        // 8 decimal base, 6 decimal quote
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::UpdateConfig {
+                tick: None,
+                fee_taker: None,
+                fee_maker: None,
+                fee_address: None,
+                market_makers: None,
+                oracles: None,
+                premium_bounds: None,
+                decimal_delta: Some(DecimalDelta(-2)),
+            },
+        )
+        .unwrap();
+
+        let price = Price::Fixed(Decimal::from_str("91200").unwrap());
+        app.execute_contract(
+            owner.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Base,
+                    price.clone(),
+                    Some(Uint128::from(1_000_000u128)),
+                )],
+                None,
+            )),
+            &coins(1_000_000, "btc-btc"),
+        )
+        .unwrap();
+
+        // Prices are reported in human units
+        let book: BookResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Book {
+                    page: PageRequest::default(),
+                    group_by: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(book.base[0].price, Decimal::from_str("91200").unwrap());
+        let order: OrderResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Order((owner.to_string(), Side::Base, price)),
+            )
+            .unwrap();
+        assert_eq!(order.rate, Decimal::from_str("91200").unwrap());
+
+        // And settled at the atomic rate of 912
+        let res: SimulationResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Simulate(coin(9120, "eth-usdc")),
+            )
+            .unwrap();
+        assert_eq!(res.returned, Uint128::from(10u128));
+
+        // The delta can't change while orders are open
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::UpdateConfig {
+                tick: None,
+                fee_taker: None,
+                fee_maker: None,
+                fee_address: None,
+                market_makers: None,
+                oracles: None,
+                premium_bounds: None,
+                decimal_delta: Some(DecimalDelta(0)),
+            },
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn query_ticker() {
+        let (mut app, contract) = setup();
+        let owner = app.api().addr_make("owner");
+        let funds = vec![
+            coin(500_000_000_000_000, "btc-btc"),
+            coin(500_000_000_000_000, "eth-usdc"),
+        ];
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, funds.clone())
+                .unwrap();
+        });
+
+        let ticker: TickerResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Ticker {})
+            .unwrap();
+        assert_eq!(
+            ticker,
+            TickerResponse {
+                best_bid: None,
+                best_ask: None,
+                mid: None,
+                spread: None,
+                last_price: None,
+                last_time: None,
+            }
+        );
+
+        app.execute_contract(
+            owner.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![
+                    (
+                        Side::Base,
+                        Price::Fixed(Decimal::from_str("93219").unwrap()),
+                        Some(Uint128::from(21000u128)),
+                    ),
+                    (
+                        Side::Base,
+                        Price::Fixed(Decimal::from_str("91219").unwrap()),
+                        Some(Uint128::from(51000u128)),
+                    ),
+                    (
+                        Side::Quote,
+                        Price::Fixed(Decimal::from_str("90000").unwrap()),
+                        Some(Uint128::from(1250000000u128)),
+                    ),
+                    (
+                        Side::Quote,
+                        Price::Fixed(Decimal::from_str("87900").unwrap()),
+                        Some(Uint128::from(5100000000u128)),
+                    ),
+                ],
+                None,
+            )),
+            &funds,
+        )
+        .unwrap();
+
+        let ticker: TickerResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Ticker {})
+            .unwrap();
+        assert_eq!(ticker.best_ask, Some(Decimal::from_str("91219").unwrap()));
+        assert_eq!(ticker.best_bid, Some(Decimal::from_str("90000").unwrap()));
+        assert_eq!(ticker.mid, Some(Decimal::from_str("90609.5").unwrap()));
+        assert_eq!(ticker.spread, Some(Decimal::from_str("1219").unwrap()));
+        assert_eq!(ticker.last_price, None);
+
+        app.execute_contract(
+            owner.clone(),
+            contract.clone(),
+            &ExecuteMsg::Swap(SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            &coins(100_000, "eth-usdc"),
+        )
+        .unwrap();
+
+        let ticker: TickerResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Ticker {})
+            .unwrap();
+        assert_eq!(ticker.last_price, Some(Decimal::from_str("91219").unwrap()));
+        assert_eq!(ticker.last_time, Some(app.block_info().time));
+    }
+
     #[test]
     fn swap() {
         let (mut app, contract) = setup();
@@ -689,6 +2118,7 @@ mod tests {
                 &ExecuteMsg::Swap(SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 }),
                 &[swap_amount],
             )
@@ -701,6 +2131,7 @@ mod tests {
             ("offer", "100000"),
             ("bid", "1"),
             ("price", "fixed:91219"),
+            ("rate_usd", "91310.219"),
         ]));
         res.assert_event(&Event::new("transfer").add_attributes(vec![
             ("recipient", owner.as_str()),
@@ -717,6 +2148,7 @@ mod tests {
                 &ExecuteMsg::Swap(SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 }),
                 &coins(30_000, "btc-btc"),
             )
@@ -749,6 +2181,7 @@ mod tests {
                 &ExecuteMsg::Swap(SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 }),
                 &coins(100000, "btc-btc"),
             )
@@ -769,11 +2202,104 @@ mod tests {
     }
 
     #[test]
-    fn update_config() {
+    fn book_stats() {
         let (mut app, contract) = setup();
-        app.wasm_sudo(
+        let owner = app.api().addr_make("owner");
+        let funds = vec![coin(1_000_000, "btc-btc"), coin(10_000_000, "eth-usdc")];
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, funds.clone())
+                .unwrap();
+        });
+        let stats = |app: &RujiraApp| -> StatsResponse {
+            app.wrap()
+                .query_wasm_smart(contract.clone(), &QueryMsg::Stats {})
+                .unwrap()
+        };
+        let order = |app: &mut RujiraApp, side: Side, price: &str, target: u128, funds: &[Coin]| {
+            app.execute_contract(
+                owner.clone(),
+                contract.clone(),
+                &ExecuteMsg::Order((
+                    vec![(
+                        side,
+                        Price::Fixed(Decimal::from_str(price).unwrap()),
+                        Some(Uint128::from(target)),
+                    )],
+                    None,
+                )),
+                funds,
+            )
+            .unwrap();
+        };
+
+        order(
+            &mut app,
+            Side::Base,
+            "100000",
+            10_000,
+            &coins(10_000, "btc-btc"),
+        );
+        order(
+            &mut app,
+            Side::Base,
+            "110000",
+            5_000,
+            &coins(5_000, "btc-btc"),
+        );
+        order(
+            &mut app,
+            Side::Quote,
+            "90000",
+            900_000,
+            &coins(900_000, "eth-usdc"),
+        );
+        let res = stats(&app);
+        assert_eq!(res.base.resting, Uint128::from(15_000u128));
+        assert_eq!(res.base.orders, 2);
+        assert_eq!(res.quote.resting, Uint128::from(900_000u128));
+        assert_eq!(res.quote.orders, 1);
+
+        // Retracting reduces the resting total
+        order(&mut app, Side::Quote, "90000", 450_000, &[]);
+        let res = stats(&app);
+        assert_eq!(res.quote.resting, Uint128::from(450_000u128));
+        assert_eq!(res.quote.orders, 1);
+
+        // Filled orders no longer rest on the book, but stay open until withdrawn
+        app.execute_contract(
+            owner.clone(),
             contract.clone(),
-            &SudoMsg::UpdateConfig {
+            &ExecuteMsg::Swap(SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            &coins(10, "btc-btc"),
+        )
+        .unwrap();
+        let res = stats(&app);
+        assert_eq!(res.quote.resting, Uint128::zero());
+        assert_eq!(res.quote.orders, 1);
+        assert_eq!(res.quote.unclaimed, Uint128::from(5u128));
+        assert_eq!(res.base.resting, Uint128::from(15_000u128));
+
+        order(&mut app, Side::Quote, "90000", 0, &[]);
+        order(&mut app, Side::Base, "110000", 0, &[]);
+        let res = stats(&app);
+        assert_eq!(res.quote.orders, 0);
+        assert_eq!(res.quote.unclaimed, Uint128::zero());
+        assert_eq!(res.base.resting, Uint128::from(10_000u128));
+        assert_eq!(res.base.orders, 1);
+    }
+
+    #[test]
+    fn update_config() {
+        let (mut app, contract) = setup();
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::UpdateConfig {
                 tick: Some(Tick::new(8)),
                 fee_taker: Some(Decimal::from_ratio(1u128, 100u128)),
                 fee_maker: Some(Decimal::from_ratio(2u128, 100u128)),
@@ -784,6 +2310,8 @@ mod tests {
                         .unwrap(),
                     Layer1Asset::try_from("BTC.BTC").unwrap(),
                 ]),
+                premium_bounds: Some(PremiumBounds::new(-1000, 2000)),
+                decimal_delta: None,
             },
         )
         .unwrap();
@@ -797,6 +2325,7 @@ mod tests {
         assert_eq!(config.fee_taker, Decimal::from_ratio(1u128, 100u128));
         assert_eq!(config.fee_maker, Decimal::from_ratio(2u128, 100u128));
         assert_eq!(config.fee_address, app.api().addr_make("fees2").to_string());
+        assert_eq!(config.premium_bounds, PremiumBounds::new(-1000, 2000));
         assert_eq!(
             config.market_makers[0],
             app.api().addr_make("mm2").to_string()
@@ -902,8 +2431,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: owner.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -919,8 +2447,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: user2.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -935,8 +2462,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: user3.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -953,6 +2479,7 @@ mod tests {
                 &ExecuteMsg::Swap(SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 }),
                 &[swap_amount],
             )
@@ -973,8 +2500,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: owner.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -990,8 +2516,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: user2.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -1006,8 +2531,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: user3.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -1110,6 +2634,7 @@ mod tests {
                 &ExecuteMsg::Swap(SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 }),
                 &[swap_amount],
             )
@@ -1130,8 +2655,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: owner.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -1147,8 +2671,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: user2.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -1163,8 +2686,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: user3.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -1182,6 +2704,7 @@ mod tests {
                 &ExecuteMsg::Swap(SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 }),
                 &[swap_amount],
             )
@@ -1202,8 +2725,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: owner.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -1219,8 +2741,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: user2.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -1235,8 +2756,7 @@ mod tests {
                 &QueryMsg::Orders {
                     owner: user3.clone().to_string(),
                     side: None,
-                    offset: None,
-                    limit: None,
+                    page: PageRequest::default(),
                 },
             )
             .unwrap();
@@ -1244,4 +2764,2489 @@ mod tests {
         assert_eq!(order.remaining, Uint128::zero());
         assert_eq!(order.filled, Uint128::from(500000u128));
     }
+
+    #[test]
+    fn reentrancy_lock() {
+        let (mut app, contract) = setup();
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &user,
+                    vec![coin(1_000_000, "btc-btc"), coin(1_000_000, "eth-usdc")],
+                )
+                .unwrap();
+        });
+        let order = ExecuteMsg::Order((
+            vec![(
+                Side::Base,
+                Price::Fixed(Decimal::from_str("100000").unwrap()),
+                Some(Uint128::from(1000u128)),
+            )],
+            None,
+        ));
+
+        // The bidder of an order flow auction is called whilst the swap holds the lock, and re-enters
+        // with the message it's instantiated with, forwarding the offer when `funds` is set
+        #[cosmwasm_schema::cw_serde]
+        struct Reenter {
+            msg: ExecuteMsg,
+            funds: bool,
+        }
+        let code = ContractWrapper::new(
+            |deps: DepsMut, _, info: MessageInfo, _: BidderMsg| -> StdResult<Response> {
+                let reenter: Reenter = from_json(deps.storage.get(b"reenter").unwrap_or_default())?;
+                Ok(Response::default().add_message(WasmMsg::Execute {
+                    contract_addr: info.sender.to_string(),
+                    msg: to_json_binary(&reenter.msg)?,
+                    funds: if reenter.funds { info.funds } else { vec![] },
+                }))
+            },
+            |deps: DepsMut, _, _, msg: Reenter| -> StdResult<Response> {
+                deps.storage
+                    .set(b"reenter", to_json_binary(&msg)?.as_slice());
+                Ok(Response::default())
+            },
+            |_, _, msg: rujira_rs::fin::BidderQueryMsg| -> StdResult<Binary> {
+                let rujira_rs::fin::BidderQueryMsg::Bid { book, .. } = msg;
+                to_json_binary(&Some(book.multiply_ratio(11u128, 10u128)))
+            },
+        );
+        let code_id = app.store_code(Box::new(code));
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Base,
+                    Price::Fixed(Decimal::from_str("2").unwrap()),
+                    Some(Uint128::from(1_000u128)),
+                )],
+                None,
+            )),
+            &coins(1_000, "btc-btc"),
+        )
+        .unwrap();
+        let swap = ExecuteMsg::Swap(SwapRequest::Yolo {
+            to: None,
+            callback: None,
+            max_levels: None,
+        });
+        let bid = ExecuteMsg::Order((
+            vec![(
+                Side::Quote,
+                Price::Fixed(Decimal::one()),
+                Some(Uint128::from(100u128)),
+            )],
+            None,
+        ));
+        for (msg, funds) in [
+            (swap.clone(), true),
+            (bid, true),
+            (ExecuteMsg::Arb { then: None }, false),
+        ] {
+            let bidder = app
+                .instantiate_contract(
+                    code_id,
+                    user.clone(),
+                    &Reenter { msg, funds },
+                    &[],
+                    "bidder",
+                    None,
+                )
+                .unwrap();
+            app.wasm_sudo(
+                contract.clone(),
+                &SudoMsg::SetOrderFlowAuction {
+                    auction: Some(rujira_rs::fin::OrderFlowAuction {
+                        min_offer: coins(100, "eth-usdc"),
+                        bidders: vec![bidder.clone()],
+                        min_improvement: Decimal::from_str("0.05").unwrap(),
+                    }),
+                },
+            )
+            .unwrap();
+            let before = app.wrap().query_balance(&user, "btc-btc").unwrap().amount;
+            let res = app
+                .execute_contract(
+                    user.clone(),
+                    contract.clone(),
+                    &swap,
+                    &coins(100, "eth-usdc"),
+                )
+                .unwrap();
+
+            // The re-entry is rejected, failing the bidder, and the swap falls back to the book
+            assert!(res
+                .events
+                .iter()
+                .any(|x| x.ty == "wasm-rujira-fin/order_flow_auction.fallback"));
+            assert_eq!(
+                app.wrap().query_balance(&user, "btc-btc").unwrap().amount - before,
+                Uint128::from(50u128)
+            );
+            let orders: OrdersResponse = app
+                .wrap()
+                .query_wasm_smart(
+                    contract.clone(),
+                    &QueryMsg::Orders {
+                        owner: bidder.to_string(),
+                        side: None,
+                        page: PageRequest::default(),
+                    },
+                )
+                .unwrap();
+            assert!(orders.orders.is_empty());
+            assert!(!Lock::is_locked(app.contract_storage(&contract).as_ref()).unwrap());
+        }
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetOrderFlowAuction { auction: None },
+        )
+        .unwrap();
+
+        // Only the contract can chain a follow-up onto an arb
+        let err = app
+            .execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Arb {
+                    then: Some(
+                        to_json_binary(&ExecuteMsg::DoOrder((user.clone(), (vec![], None))))
+                            .unwrap(),
+                    ),
+                },
+                &coins(1000, "btc-btc"),
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Unauthorized"));
+
+        // A full sequence releases the lock on completion
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &order,
+            &coins(1000, "btc-btc"),
+        )
+        .unwrap();
+        assert!(!Lock::is_locked(app.contract_storage(&contract).as_ref()).unwrap());
+
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::Arb { then: None },
+            &[],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn grid() {
+        let (mut app, contract) = setup();
+        let user = app.api().addr_make("user");
+        let other = app.api().addr_make("other");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &user, coins(1_000_000, "eth-usdc"))
+                .unwrap();
+        });
+        let fixed = |x: &str| Price::Fixed(Decimal::from_str(x).unwrap());
+        let remaining = |app: &RujiraApp| -> Vec<(Price, Uint128)> {
+            let res: OrdersResponse = app
+                .wrap()
+                .query_wasm_smart(
+                    contract.clone(),
+                    &QueryMsg::Orders {
+                        owner: user.to_string(),
+                        side: Some(Side::Quote),
+                        page: PageRequest::default(),
+                    },
+                )
+                .unwrap();
+            res.orders
+                .into_iter()
+                .map(|x| (x.price, x.remaining))
+                .collect()
+        };
+
+        let res = app
+            .execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Grid {
+                    side_range: (fixed("100000"), fixed("90000")),
+                    levels: 3,
+                    total: Uint128::from(3000u128),
+                    side: Side::Quote,
+                },
+                &coins(3000, "eth-usdc"),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/grid.create")
+                .add_attribute("id", "0")
+                .add_attribute("levels", "3"),
+        );
+        assert_eq!(
+            remaining(&app),
+            vec![
+                (fixed("90000"), Uint128::from(1000u128)),
+                (fixed("95000"), Uint128::from(1000u128)),
+                (fixed("100000"), Uint128::from(1000u128)),
+            ]
+        );
+
+        // Only the owner can manage the grid
+        let err = app
+            .execute_contract(
+                other.clone(),
+                contract.clone(),
+                &ExecuteMsg::CancelGrid { id: 0 },
+                &[],
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Unauthorized"));
+
+        // Retracted levels fund the new ones
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::RepriceGrid {
+                id: 0,
+                side_range: (fixed("90000"), fixed("80000")),
+            },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            remaining(&app),
+            vec![
+                (fixed("80000"), Uint128::from(1000u128)),
+                (fixed("85000"), Uint128::from(1000u128)),
+                (fixed("90000"), Uint128::from(1000u128)),
+            ]
+        );
+        let grid: GridResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Grid { id: 0 })
+            .unwrap();
+        assert_eq!(grid.prices.len(), 3);
+        assert_eq!(grid.side_range, (fixed("90000"), fixed("80000")));
+
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::CancelGrid { id: 0 },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(remaining(&app), vec![]);
+        assert_eq!(
+            app.wrap().query_balance(&user, "eth-usdc").unwrap().amount,
+            Uint128::from(1_000_000u128)
+        );
+        app.wrap()
+            .query_wasm_smart::<GridResponse>(contract.clone(), &QueryMsg::Grid { id: 0 })
+            .unwrap_err();
+    }
+
+    #[test]
+    fn maker_session() {
+        let (mut app, contract) = setup();
+        let user = app.api().addr_make("user");
+        let keeper = app.api().addr_make("keeper");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &user, coins(1_000_000, "eth-usdc"))
+                .unwrap();
+        });
+        let run = |app: &mut RujiraApp, sender: &Addr, msg: ExecuteMsg, funds: &[Coin]| {
+            app.execute_contract(sender.clone(), contract.clone(), &msg, funds)
+        };
+        let advance = |app: &mut RujiraApp, seconds: u64| {
+            app.update_block(|b| b.time = b.time.plus_seconds(seconds))
+        };
+        let price = Price::Fixed(Decimal::from_str("90000").unwrap());
+
+        let res = run(
+            &mut app,
+            &user,
+            ExecuteMsg::OpenMakerSession { ttl: 60 },
+            &coins(10, "eth-usdc"),
+        )
+        .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/maker_session.open")
+                .add_attribute("id", "0")
+                .add_attribute("ttl", "60"),
+        );
+        run(
+            &mut app,
+            &user,
+            ExecuteMsg::MakerSessionOrder {
+                id: 0,
+                orders: (
+                    vec![(Side::Quote, price.clone(), Some(Uint128::from(1000u128)))],
+                    None,
+                ),
+            },
+            &coins(1000, "eth-usdc"),
+        )
+        .unwrap();
+        let session: MakerSessionResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::MakerSession { id: 0 })
+            .unwrap();
+        assert_eq!(session.orders, vec![(Side::Quote, price.clone())]);
+        assert_eq!(session.bounty, coins(10, "eth-usdc"));
+
+        // Kept alive by heartbeats, which only the owner can send
+        advance(&mut app, 50);
+        run(&mut app, &keeper, ExecuteMsg::Heartbeat { id: 0 }, &[]).unwrap_err();
+        run(&mut app, &user, ExecuteMsg::Heartbeat { id: 0 }, &[]).unwrap();
+        advance(&mut app, 50);
+        let err = run(
+            &mut app,
+            &keeper,
+            ExecuteMsg::ExpireMakerSession { id: 0 },
+            &[],
+        )
+        .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("maker session not expired"));
+
+        // Once expired the owner can no longer heartbeat, and anyone can retract its orders
+        advance(&mut app, 10);
+        run(&mut app, &user, ExecuteMsg::Heartbeat { id: 0 }, &[]).unwrap_err();
+        let res = run(
+            &mut app,
+            &keeper,
+            ExecuteMsg::ExpireMakerSession { id: 0 },
+            &[],
+        )
+        .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/maker_session.expire")
+                .add_attribute("caller", keeper.to_string())
+                .add_attribute("orders", "1"),
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(&keeper, "eth-usdc")
+                .unwrap()
+                .amount,
+            Uint128::from(10u128)
+        );
+        assert_eq!(
+            app.wrap().query_balance(&user, "eth-usdc").unwrap().amount,
+            Uint128::from(999_990u128)
+        );
+        app.wrap()
+            .query_wasm_smart::<OrderResponse>(
+                contract.clone(),
+                &QueryMsg::Order((user.to_string(), Side::Quote, price.clone())),
+            )
+            .unwrap_err();
+        app.wrap()
+            .query_wasm_smart::<MakerSessionResponse>(
+                contract.clone(),
+                &QueryMsg::MakerSession { id: 0 },
+            )
+            .unwrap_err();
+
+        // Closing returns the bounty and leaves the orders in place
+        run(
+            &mut app,
+            &user,
+            ExecuteMsg::OpenMakerSession { ttl: 60 },
+            &coins(10, "eth-usdc"),
+        )
+        .unwrap();
+        run(
+            &mut app,
+            &user,
+            ExecuteMsg::MakerSessionOrder {
+                id: 1,
+                orders: (
+                    vec![(Side::Quote, price.clone(), Some(Uint128::from(1000u128)))],
+                    None,
+                ),
+            },
+            &coins(1000, "eth-usdc"),
+        )
+        .unwrap();
+        run(
+            &mut app,
+            &user,
+            ExecuteMsg::CloseMakerSession { id: 1 },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap().query_balance(&user, "eth-usdc").unwrap().amount,
+            Uint128::from(998_990u128)
+        );
+        app.wrap()
+            .query_wasm_smart::<OrderResponse>(
+                contract.clone(),
+                &QueryMsg::Order((user.to_string(), Side::Quote, price.clone())),
+            )
+            .unwrap();
+
+        // The bounty of an open session is held as a liability, and can't be rescued
+        run(
+            &mut app,
+            &user,
+            ExecuteMsg::OpenMakerSession { ttl: 60 },
+            &coins(10, "eth-usdc"),
+        )
+        .unwrap();
+        run(
+            &mut app,
+            &user,
+            ExecuteMsg::MakerSessionOrder {
+                id: 2,
+                orders: (vec![(Side::Quote, price.clone(), None)], None),
+            },
+            &[],
+        )
+        .unwrap();
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::Rescue {
+                denom: "eth-usdc".to_string(),
+                to: keeper.to_string(),
+            },
+        )
+        .unwrap_err();
+
+        // An order retracted outside of the session is untagged from it
+        run(
+            &mut app,
+            &user,
+            ExecuteMsg::Order((
+                vec![(Side::Quote, price.clone(), Some(Uint128::zero()))],
+                None,
+            )),
+            &[],
+        )
+        .unwrap();
+        let session: MakerSessionResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::MakerSession { id: 2 })
+            .unwrap();
+        assert!(session.orders.is_empty());
+    }
+
+    #[test]
+    fn twap() {
+        let (mut app, contract) = setup();
+        let owner = app.api().addr_make("owner");
+        let user = app.api().addr_make("user");
+        let other = app.api().addr_make("other");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(1_000_000, "eth-usdc"))
+                .unwrap();
+        });
+        app.execute_contract(
+            owner.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Base,
+                    Price::Fixed(Decimal::from_str("100000").unwrap()),
+                    Some(Uint128::from(10u128)),
+                )],
+                None,
+            )),
+            &coins(10, "btc-btc"),
+        )
+        .unwrap();
+
+        let res = app
+            .execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Twap(TwapRequest {
+                    duration: 300,
+                    interval: 100,
+                    limit: None,
+                }),
+                &coins(300_000, "eth-usdc"),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/twap.create")
+                .add_attribute("id", "0")
+                .add_attribute("slices", "3"),
+        );
+
+        let crank = |app: &mut RujiraApp| {
+            app.execute_contract(
+                other.clone(),
+                contract.clone(),
+                &ExecuteMsg::CrankTwap { id: 0 },
+                &[],
+            )
+        };
+        let res = crank(&mut app).unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/twap.crank")
+                .add_attribute("offer", "100000")
+                .add_attribute("returned", "1")
+                .add_attribute("remaining", "200000"),
+        );
+        assert_eq!(
+            app.wrap().query_balance(&user, "btc-btc").unwrap().amount,
+            Uint128::one()
+        );
+
+        // Only the slices due can be swapped
+        let err = crank(&mut app).unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("TwapNotDue"));
+
+        app.update_block(|b| b.time = b.time.plus_seconds(100));
+        crank(&mut app).unwrap();
+        let twap: TwapResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Twap { id: 0 })
+            .unwrap();
+        assert_eq!(twap.remaining, Uint128::from(100_000u128));
+        assert_eq!(twap.returned, Uint128::from(2u128));
+        assert_eq!(twap.due, Uint128::zero());
+
+        // The remaining offer is refunded to the owner on cancel
+        app.execute_contract(
+            other.clone(),
+            contract.clone(),
+            &ExecuteMsg::CancelTwap { id: 0 },
+            &[],
+        )
+        .unwrap_err();
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::CancelTwap { id: 0 },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap().query_balance(&user, "eth-usdc").unwrap().amount,
+            Uint128::from(800_000u128)
+        );
+        crank(&mut app).unwrap_err();
+    }
+
+    #[test]
+    fn order_callback() {
+        let (mut app, contract) = setup();
+        let vault = app.api().addr_make("vault");
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &vault, coins(1_000, "eth-usdc"))
+                .unwrap();
+        });
+        let msg = |target: Option<Uint128>| {
+            ExecuteMsg::Callback(CallbackMsg {
+                data: to_json_binary(&Empty {}).unwrap(),
+                callback: CallbackData::Msg(
+                    to_json_binary(&OrderCallback {
+                        for_account: user.to_string(),
+                        orders: (
+                            vec![(
+                                Side::Quote,
+                                Price::Fixed(Decimal::from_str("90000").unwrap()),
+                                target,
+                            )],
+                            None,
+                        ),
+                    })
+                    .unwrap(),
+                ),
+                payload: None,
+            })
+        };
+
+        // Only trusted callers can place orders for another account
+        let err = app
+            .execute_contract(
+                vault.clone(),
+                contract.clone(),
+                &msg(Some(Uint128::from(1_000u128))),
+                &coins(1_000, "eth-usdc"),
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Unauthorized"));
+
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetTrustedCaller {
+                address: vault.to_string(),
+                trusted: true,
+            },
+        )
+        .unwrap();
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Config {})
+            .unwrap();
+        assert_eq!(config.trusted_callers, vec![vault.to_string()]);
+
+        let res = app
+            .execute_contract(
+                vault.clone(),
+                contract.clone(),
+                &msg(Some(Uint128::from(1_000u128))),
+                &coins(1_000, "eth-usdc"),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/order.callback")
+                .add_attribute("caller", vault.as_str())
+                .add_attribute("owner", user.as_str()),
+        );
+
+        // The order is owned by the account, not the caller
+        let res: OrdersResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Orders {
+                    owner: user.to_string(),
+                    side: None,
+                    page: PageRequest::default(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.orders.len(), 1);
+        assert_eq!(res.orders[0].remaining, Uint128::from(1_000u128));
+        let res: OrdersResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Orders {
+                    owner: vault.to_string(),
+                    side: None,
+                    page: PageRequest::default(),
+                },
+            )
+            .unwrap();
+        assert!(res.orders.is_empty());
+
+        // The caller can't retract, reduce or claim the account's order
+        for target in [None, Some(Uint128::zero()), Some(Uint128::from(500u128))] {
+            let err = app
+                .execute_contract(vault.clone(), contract.clone(), &msg(target), &[])
+                .unwrap_err();
+            assert!(format!("{:?}", err.root_cause()).contains("only increase orders"));
+        }
+    }
+
+    #[test]
+    fn maker_attribution() {
+        let (mut app, contract) = setup();
+        let alice = app.api().addr_make("alice");
+        let bob = app.api().addr_make("bob");
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &alice, coins(10, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &bob, coins(5, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(2_000_000, "eth-usdc"))
+                .unwrap();
+        });
+        for (maker, amount) in [(&alice, 10u128), (&bob, 5u128)] {
+            app.execute_contract(
+                maker.clone(),
+                contract.clone(),
+                &ExecuteMsg::Order((
+                    vec![(
+                        Side::Base,
+                        Price::Fixed(Decimal::from_str("100000").unwrap()),
+                        Some(Uint128::from(amount)),
+                    )],
+                    None,
+                )),
+                &coins(amount, "btc-btc"),
+            )
+            .unwrap();
+        }
+        let swap = |app: &mut RujiraApp, amount: u128| {
+            app.execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }),
+                &coins(amount, "eth-usdc"),
+            )
+            .unwrap()
+        };
+
+        // Orders are only attributed once the pool is fully consumed
+        let res = swap(&mut app, 600_000);
+        assert!(!res
+            .events
+            .iter()
+            .any(|e| e.attributes.iter().any(|a| a.key == "maker")));
+
+        let res = swap(&mut app, 900_000);
+        res.assert_event(&Event::new("wasm-rujira-fin/trade").add_attributes(vec![
+            ("maker", format!("{alice}:6")),
+            ("maker", format!("{bob}:3")),
+        ]));
+    }
+
+    #[test]
+    fn sweep() {
+        let (mut app, contract) = setup();
+        let alice = app.api().addr_make("alice");
+        let user = app.api().addr_make("user");
+        let other = app.api().addr_make("other");
+        let fee = app.api().addr_make("fee");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &alice, coins(1_000, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(100_000, "eth-usdc"))
+                .unwrap();
+        });
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::UpdateConfig {
+                tick: None,
+                fee_taker: Some(Decimal::from_ratio(1u128, 10u128)),
+                fee_maker: None,
+                fee_address: None,
+                market_makers: None,
+                oracles: None,
+                premium_bounds: None,
+                decimal_delta: None,
+            },
+        )
+        .unwrap();
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetSweepBounty {
+                bounty: Some(SweepBounty {
+                    share: Decimal::percent(200),
+                    rate: Decimal::one(),
+                }),
+            },
+        )
+        .unwrap_err();
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetSweepBounty {
+                bounty: Some(SweepBounty {
+                    share: Decimal::percent(50),
+                    rate: Decimal::one(),
+                }),
+            },
+        )
+        .unwrap();
+
+        let price = Price::Fixed(Decimal::from_str("100").unwrap());
+        let order = |app: &mut RujiraApp, amount: u128, funds: &[Coin]| {
+            app.execute_contract(
+                alice.clone(),
+                contract.clone(),
+                &ExecuteMsg::Order((
+                    vec![(Side::Base, price.clone(), Some(Uint128::from(amount)))],
+                    None,
+                )),
+                funds,
+            )
+            .unwrap();
+        };
+        order(&mut app, 1_000, &coins(1_000, "btc-btc"));
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::Swap(SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            &coins(100_000, "eth-usdc"),
+        )
+        .unwrap();
+        // Half of the taker fee is reserved for bounties
+        assert_eq!(
+            app.wrap().query_balance(&fee, "btc-btc").unwrap().amount,
+            Uint128::from(50u128)
+        );
+
+        let sweep = |app: &mut RujiraApp| {
+            app.execute_contract(
+                other.clone(),
+                contract.clone(),
+                &ExecuteMsg::Sweep {
+                    keys: vec![(Side::Base, price.clone())],
+                    limit: None,
+                },
+                &[],
+            )
+            .unwrap()
+        };
+
+        // The pool is empty, but the filled order is still reachable
+        let res = sweep(&mut app);
+        res.assert_event(&Event::new("wasm-rujira-fin/sweep").add_attribute("removed", "0"));
+
+        order(&mut app, 0, &[]);
+        let res = sweep(&mut app);
+        res.assert_event(&Event::new("wasm-rujira-fin/sweep").add_attribute("bounty", "50btc-btc"));
+        assert_eq!(
+            app.wrap().query_balance(&other, "btc-btc").unwrap().amount,
+            Uint128::from(50u128)
+        );
+
+        let res = sweep(&mut app);
+        res.assert_event(&Event::new("wasm-rujira-fin/sweep").add_attribute("removed", "0"));
+    }
+
+    #[test]
+    fn fee_conversion() {
+        let (mut app, contract) = setup();
+        let alice = app.api().addr_make("alice");
+        let bob = app.api().addr_make("bob");
+        let user = app.api().addr_make("user");
+        let fee = app.api().addr_make("fee");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &alice, coins(1_000, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &bob, coins(99_000, "eth-usdc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(100_000, "eth-usdc"))
+                .unwrap();
+        });
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::UpdateConfig {
+                tick: None,
+                fee_taker: Some(Decimal::from_ratio(1u128, 10u128)),
+                fee_maker: None,
+                fee_address: None,
+                market_makers: None,
+                oracles: None,
+                premium_bounds: None,
+                decimal_delta: None,
+            },
+        )
+        .unwrap();
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetFeeConversion {
+                conversion: Some(FeeConversion {
+                    max_slippage: Decimal::one(),
+                }),
+            },
+        )
+        .unwrap_err();
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetFeeConversion {
+                conversion: Some(FeeConversion {
+                    max_slippage: Decimal::percent(5),
+                }),
+            },
+        )
+        .unwrap();
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Config {})
+            .unwrap();
+        assert_eq!(
+            config.fee_conversion,
+            Some(FeeConversion {
+                max_slippage: Decimal::percent(5)
+            })
+        );
+
+        let order = |app: &mut RujiraApp, owner: &Addr, side: Side, price: &str, funds: Coin| {
+            app.execute_contract(
+                owner.clone(),
+                contract.clone(),
+                &ExecuteMsg::Order((
+                    vec![(
+                        side,
+                        Price::Fixed(Decimal::from_str(price).unwrap()),
+                        Some(funds.amount),
+                    )],
+                    None,
+                )),
+                &[funds],
+            )
+            .unwrap();
+        };
+        order(&mut app, &alice, Side::Base, "100", coin(1_000, "btc-btc"));
+        order(&mut app, &bob, Side::Quote, "99", coin(99_000, "eth-usdc"));
+
+        // The taker fee of 100 btc-btc is sold to bob's bid
+        let res = app
+            .execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }),
+                &coins(100_000, "eth-usdc"),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/fee.convert")
+                .add_attribute("sold", "100btc-btc")
+                .add_attribute("bought", "9900eth-usdc"),
+        );
+        assert_eq!(
+            app.wrap().query_balance(&fee, "btc-btc").unwrap().amount,
+            Uint128::zero()
+        );
+        assert_eq!(
+            app.wrap().query_balance(&fee, "eth-usdc").unwrap().amount,
+            Uint128::from(9_900u128)
+        );
+        assert_eq!(
+            app.wrap().query_balance(&user, "btc-btc").unwrap().amount,
+            Uint128::from(900u128)
+        );
+    }
+
+    #[test]
+    fn fee_addresses() {
+        let (mut app, contract) = setup();
+        let alice = app.api().addr_make("alice");
+        let user = app.api().addr_make("user");
+        let fee = app.api().addr_make("fee");
+        let buyback = app.api().addr_make("buyback");
+        let treasury = app.api().addr_make("treasury");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &alice,
+                    vec![coin(1_000, "btc-btc"), coin(1_000, "eth-usdc")],
+                )
+                .unwrap();
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &user,
+                    vec![coin(1_000, "btc-btc"), coin(1_000, "eth-usdc")],
+                )
+                .unwrap();
+        });
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::UpdateConfig {
+                tick: None,
+                fee_taker: Some(Decimal::from_ratio(1u128, 10u128)),
+                fee_maker: None,
+                fee_address: None,
+                market_makers: None,
+                oracles: None,
+                premium_bounds: None,
+                decimal_delta: None,
+            },
+        )
+        .unwrap();
+        let set = |app: &mut RujiraApp, base: Option<&Addr>, quote: Option<&Addr>| {
+            app.wasm_sudo(
+                contract.clone(),
+                &SudoMsg::SetFeeAddresses {
+                    base: base.map(|x| x.to_string()),
+                    quote: quote.map(|x| x.to_string()),
+                },
+            )
+            .unwrap();
+        };
+        set(&mut app, Some(&buyback), Some(&treasury));
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Config {})
+            .unwrap();
+        assert_eq!(config.fee_address_base, Some(buyback.to_string()));
+        assert_eq!(config.fee_address_quote, Some(treasury.to_string()));
+
+        app.execute_contract(
+            alice.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![
+                    (
+                        Side::Base,
+                        Price::Fixed(Decimal::from_str("2").unwrap()),
+                        Some(Uint128::from(1_000u128)),
+                    ),
+                    (
+                        Side::Quote,
+                        Price::Fixed(Decimal::one()),
+                        Some(Uint128::from(1_000u128)),
+                    ),
+                ],
+                None,
+            )),
+            &[coin(1_000, "btc-btc"), coin(1_000, "eth-usdc")],
+        )
+        .unwrap();
+        let swap = |app: &mut RujiraApp, funds: Coin| {
+            app.execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }),
+                &[funds],
+            )
+            .unwrap();
+        };
+        let balances = |app: &RujiraApp, addr: &Addr| app.wrap().query_all_balances(addr).unwrap();
+
+        // Base fees to the buyback contract, quote fees to the treasury
+        swap(&mut app, coin(200, "eth-usdc"));
+        swap(&mut app, coin(100, "btc-btc"));
+        assert_eq!(balances(&app, &buyback), coins(10, "btc-btc"));
+        assert_eq!(balances(&app, &treasury), coins(10, "eth-usdc"));
+        assert_eq!(balances(&app, &fee), vec![]);
+
+        // Unset, the fee address receives the base fees
+        set(&mut app, None, Some(&treasury));
+        swap(&mut app, coin(200, "eth-usdc"));
+        assert_eq!(balances(&app, &buyback), coins(10, "btc-btc"));
+        assert_eq!(balances(&app, &fee), coins(10, "btc-btc"));
+    }
+
+    #[test]
+    fn trade_summary() {
+        let (mut app, contract) = setup();
+        let alice = app.api().addr_make("alice");
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &alice, coins(200, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(300, "eth-usdc"))
+                .unwrap();
+        });
+
+        // Resting orders don't trade, and have no summary
+        let res = app
+            .execute_contract(
+                alice.clone(),
+                contract.clone(),
+                &ExecuteMsg::Order((
+                    vec![
+                        (
+                            Side::Base,
+                            Price::Fixed(Decimal::from_str("1").unwrap()),
+                            Some(Uint128::from(100u128)),
+                        ),
+                        (
+                            Side::Base,
+                            Price::Fixed(Decimal::from_str("2").unwrap()),
+                            Some(Uint128::from(100u128)),
+                        ),
+                    ],
+                    None,
+                )),
+                &coins(200, "btc-btc"),
+            )
+            .unwrap();
+        assert!(!res
+            .events
+            .iter()
+            .any(|x| x.ty == "wasm-rujira-fin/trade.summary"));
+
+        // One swap filling both levels
+        let res = app
+            .execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }),
+                &coins(300, "eth-usdc"),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/trade.summary")
+                .add_attribute("open", "1")
+                .add_attribute("high", "2")
+                .add_attribute("low", "1")
+                .add_attribute("close", "2")
+                .add_attribute("volume_base", "200")
+                .add_attribute("volume_quote", "300")
+                .add_attribute("fills", "2"),
+        );
+    }
+
+    #[test]
+    fn rescue() {
+        let (mut app, contract) = setup();
+        let alice = app.api().addr_make("alice");
+        let bob = app.api().addr_make("bob");
+        let treasury = app.api().addr_make("treasury");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &alice, coins(1_000, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &bob,
+                    vec![coin(100, "btc-btc"), coin(1_000, "eth-usdc")],
+                )
+                .unwrap();
+        });
+        let rescue = |app: &mut RujiraApp, denom: &str| {
+            app.wasm_sudo(
+                contract.clone(),
+                &SudoMsg::Rescue {
+                    denom: denom.to_string(),
+                    to: treasury.to_string(),
+                },
+            )
+        };
+
+        app.execute_contract(
+            alice.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Base,
+                    Price::Fixed(Decimal::one()),
+                    Some(Uint128::from(1_000u128)),
+                )],
+                None,
+            )),
+            &coins(1_000, "btc-btc"),
+        )
+        .unwrap();
+        // Fills 500 eth-usdc to alice's order, awaiting withdrawal
+        app.execute_contract(
+            bob.clone(),
+            contract.clone(),
+            &ExecuteMsg::Swap(SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            &coins(500, "eth-usdc"),
+        )
+        .unwrap();
+        rescue(&mut app, "btc-btc").unwrap_err();
+        rescue(&mut app, "eth-usdc").unwrap_err();
+
+        // Funds sent directly to the contract are rescued, and nothing more
+        app.send_tokens(
+            bob.clone(),
+            contract.clone(),
+            &[coin(77, "btc-btc"), coin(33, "eth-usdc")],
+        )
+        .unwrap();
+        let res = rescue(&mut app, "btc-btc").unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/rescue")
+                .add_attribute("to", treasury.to_string())
+                .add_attribute("amount", "77btc-btc"),
+        );
+        rescue(&mut app, "eth-usdc").unwrap();
+        rescue(&mut app, "btc-btc").unwrap_err();
+        rescue(&mut app, "eth-usdc").unwrap_err();
+        assert_eq!(
+            app.wrap().query_all_balances(&treasury).unwrap(),
+            vec![coin(77, "btc-btc"), coin(33, "eth-usdc")]
+        );
+
+        // The liabilities are still covered in full
+        app.execute_contract(
+            alice.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Base,
+                    Price::Fixed(Decimal::one()),
+                    Some(Uint128::zero()),
+                )],
+                None,
+            )),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(app.wrap().query_all_balances(&contract).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn session() {
+        let (mut app, contract) = setup();
+        let alice = app.api().addr_make("alice");
+        let bob = app.api().addr_make("bob");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &alice, coins(11, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &bob, coins(2_000_000, "eth-usdc"))
+                .unwrap();
+        });
+        let start = app.block_info().time;
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetSession {
+                session: Some(Session {
+                    start,
+                    interval: 3600,
+                    duration: 600,
+                }),
+            },
+        )
+        .unwrap();
+
+        // The oracle is at 100000. Both orders cross it, and each other, but only rest during the window
+        let order = |app: &mut RujiraApp, owner: &Addr, side: Side, price: &str, amount: u128| {
+            let funds = match (amount, &side) {
+                (0, _) => vec![],
+                (_, Side::Base) => coins(amount, "btc-btc"),
+                (_, Side::Quote) => coins(amount, "eth-usdc"),
+            };
+            app.execute_contract(
+                owner.clone(),
+                contract.clone(),
+                &ExecuteMsg::Order((
+                    vec![(
+                        side,
+                        Price::Fixed(Decimal::from_str(price).unwrap()),
+                        Some(Uint128::from(amount)),
+                    )],
+                    None,
+                )),
+                &funds,
+            )
+            .unwrap();
+        };
+        order(&mut app, &alice, Side::Base, "99000", 10);
+        order(&mut app, &bob, Side::Quote, "101000", 2_000_000);
+        let book: BookResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Book {
+                    page: PageRequest::default(),
+                    group_by: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(book.base[0].total, Uint128::from(10u128));
+        assert_eq!(book.quote[0].total, Uint128::from(2_000_000u128));
+
+        let swap = |app: &mut RujiraApp| {
+            app.execute_contract(
+                alice.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }),
+                &coins(1, "btc-btc"),
+            )
+        };
+        let settle = |app: &mut RujiraApp| {
+            app.execute_contract(bob.clone(), contract.clone(), &ExecuteMsg::Settle {}, &[])
+        };
+        let err = swap(&mut app).unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("SessionActive"));
+        settle(&mut app).unwrap_err();
+
+        // Swaps remain suspended until the closed window is settled
+        app.update_block(|b| b.time = b.time.plus_seconds(600));
+        swap(&mut app).unwrap_err();
+        let res = settle(&mut app).unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/session.settle")
+                .add_attribute("window", "0")
+                .add_attribute("rate", "100000")
+                .add_attribute("base", "10")
+                .add_attribute("quote", "1000000"),
+        );
+        settle(&mut app).unwrap_err();
+
+        // Alice is paid the oracle price rather than her limit
+        order(&mut app, &alice, Side::Base, "99000", 0);
+        assert_eq!(
+            app.wrap().query_balance(&alice, "eth-usdc").unwrap().amount,
+            Uint128::from(1_000_000u128)
+        );
+        swap(&mut app).unwrap();
+    }
+
+    #[test]
+    fn session_pagination() {
+        let (mut app, contract) = setup();
+        let alice = app.api().addr_make("alice");
+        let bob = app.api().addr_make("bob");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &alice, coins(100, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &bob, coins(10_000_000, "eth-usdc"))
+                .unwrap();
+        });
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetSession {
+                session: Some(Session {
+                    start: app.block_info().time,
+                    interval: 3600,
+                    duration: 600,
+                }),
+            },
+        )
+        .unwrap();
+
+        // More asks cross the oracle than are matched by one settle
+        let asks = (0..=session::MAX_POOLS)
+            .map(|i| {
+                (
+                    Side::Base,
+                    Price::Fixed(Decimal::from_str(&(99_000 - i * 10).to_string()).unwrap()),
+                    Some(Uint128::one()),
+                )
+            })
+            .collect::<Vec<_>>();
+        app.execute_contract(
+            alice.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((asks, None)),
+            &coins(session::MAX_POOLS as u128 + 1, "btc-btc"),
+        )
+        .unwrap();
+        app.execute_contract(
+            bob.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Quote,
+                    Price::Fixed(Decimal::from_str("101000").unwrap()),
+                    Some(Uint128::from(10_000_000u128)),
+                )],
+                None,
+            )),
+            &coins(10_000_000, "eth-usdc"),
+        )
+        .unwrap();
+
+        app.update_block(|b| b.time = b.time.plus_seconds(600));
+        let settle = |app: &mut RujiraApp| {
+            app.execute_contract(bob.clone(), contract.clone(), &ExecuteMsg::Settle {}, &[])
+        };
+        settle(&mut app).unwrap().assert_event(
+            &Event::new("wasm-rujira-fin/session.settle")
+                .add_attribute("base", session::MAX_POOLS.to_string())
+                .add_attribute("complete", "false"),
+        );
+        settle(&mut app).unwrap().assert_event(
+            &Event::new("wasm-rujira-fin/session.settle")
+                .add_attribute("base", "1")
+                .add_attribute("complete", "true"),
+        );
+        settle(&mut app).unwrap_err();
+    }
+
+    #[test]
+    fn fill_callback() {
+        #[cosmwasm_schema::cw_serde]
+        enum ReceiverMsg {
+            Forward { contract: String, msg: ExecuteMsg },
+            Callback(CallbackMsg),
+        }
+
+        let (mut app, contract) = setup();
+        let owner = app.api().addr_make("owner");
+        let user = app.api().addr_make("user");
+        let other = app.api().addr_make("other");
+
+        // Forwards messages to the contract, and fails the fill callback if its data is `true`
+        let code = ContractWrapper::new(
+            |_, _, info, msg: ReceiverMsg| -> cosmwasm_std::StdResult<Response> {
+                match msg {
+                    ReceiverMsg::Forward { contract, msg } => {
+                        Ok(Response::default().add_message(WasmMsg::Execute {
+                            contract_addr: contract,
+                            msg: to_json_binary(&msg)?,
+                            funds: info.funds,
+                        }))
+                    }
+                    ReceiverMsg::Callback(cb) => {
+                        let fill: rujira_rs::fin::OrderFill = cosmwasm_std::from_json(&cb.data)?;
+                        let fail: bool = cb.deserialize_callback()?;
+                        if fail {
+                            return Err(cosmwasm_std::StdError::generic_err("callback failed"));
+                        }
+                        Ok(Response::default().add_attribute("filled", fill.filled))
+                    }
+                }
+            },
+            |_, _, _, _: Empty| -> cosmwasm_std::StdResult<Response> { Ok(Response::default()) },
+            |_, _, _: Empty| -> cosmwasm_std::StdResult<Binary> { Ok(Binary::default()) },
+        );
+        let code_id = app.store_code(Box::new(code));
+        let receiver = app
+            .instantiate_contract(code_id, owner.clone(), &Empty {}, &[], "receiver", None)
+            .unwrap();
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &receiver, coins(10, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(1_000_000, "eth-usdc"))
+                .unwrap();
+        });
+        let price = Price::Fixed(Decimal::from_str("100000").unwrap());
+        let forward = |app: &mut RujiraApp, msg: ExecuteMsg, funds: &[Coin]| {
+            app.execute_contract(
+                owner.clone(),
+                receiver.clone(),
+                &ReceiverMsg::Forward {
+                    contract: contract.to_string(),
+                    msg,
+                },
+                funds,
+            )
+        };
+        let set_callback = |fail: bool| ExecuteMsg::SetFillCallback {
+            side: Side::Base,
+            price: price.clone(),
+            callback: Some(CallbackData::Msg(to_json_binary(&fail).unwrap())),
+        };
+        forward(
+            &mut app,
+            ExecuteMsg::Order((
+                vec![(Side::Base, price.clone(), Some(Uint128::from(10u128)))],
+                None,
+            )),
+            &coins(10, "btc-btc"),
+        )
+        .unwrap();
+
+        // Only contracts can register a callback
+        app.execute_contract(owner.clone(), contract.clone(), &set_callback(false), &[])
+            .unwrap_err();
+        forward(&mut app, set_callback(false), &[]).unwrap();
+
+        let swap = |app: &mut RujiraApp, amount: u128| {
+            app.execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }),
+                &coins(amount, "eth-usdc"),
+            )
+            .unwrap();
+        };
+        let crank = |app: &mut RujiraApp| {
+            app.execute_contract(
+                other.clone(),
+                contract.clone(),
+                &ExecuteMsg::CrankFill {
+                    owner: receiver.to_string(),
+                    side: Side::Base,
+                    price: price.clone(),
+                },
+                &[],
+            )
+        };
+        swap(&mut app, 500_000);
+        let res = crank(&mut app).unwrap();
+        res.assert_event(&Event::new("wasm").add_attribute("filled", "500000"));
+
+        // Cranks are rate limited, and only sent for a new fill
+        let err = crank(&mut app).unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("rate limited"));
+        app.update_block(|b| b.time = b.time.plus_seconds(60));
+        crank(&mut app).unwrap_err();
+
+        // A failing callback doesn't block the withdrawal
+        forward(&mut app, set_callback(true), &[]).unwrap();
+        swap(&mut app, 200_000);
+        let res = forward(
+            &mut app,
+            ExecuteMsg::Order((vec![(Side::Base, price.clone(), None)], None)),
+            &[],
+        )
+        .unwrap();
+        res.assert_event(&Event::new("wasm-rujira-fin/fill_callback.failed"));
+        assert_eq!(
+            app.wrap()
+                .query_balance(&receiver, "eth-usdc")
+                .unwrap()
+                .amount,
+            Uint128::from(700_000u128)
+        );
+    }
+
+    #[test]
+    fn import_legacy_orders() {
+        let (mut app, contract) = setup();
+        let alice = app.api().addr_make("alice");
+        let bob = app.api().addr_make("bob");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &contract,
+                    vec![coin(15, "btc-btc"), coin(1_000, "eth-usdc")],
+                )
+                .unwrap();
+        });
+
+        let orders = vec![
+            LegacyOrder {
+                idx: Uint128::from(1u128),
+                owner: alice.to_string(),
+                quote_price: Decimal::from_str("90000").unwrap(),
+                offer_denom: "eth-usdc".to_string(),
+                original_offer_amount: Uint128::from(1_500u128),
+                offer_amount: Uint128::from(1_000u128),
+                filled_amount: Uint128::from(5u128),
+                created_at: Timestamp::from_seconds(1),
+            },
+            LegacyOrder {
+                idx: Uint128::from(2u128),
+                owner: bob.to_string(),
+                quote_price: Decimal::from_str("100000").unwrap(),
+                offer_denom: "btc-btc".to_string(),
+                original_offer_amount: Uint128::from(10u128),
+                offer_amount: Uint128::from(10u128),
+                filled_amount: Uint128::zero(),
+                created_at: Timestamp::from_seconds(2),
+            },
+        ];
+
+        let res = app
+            .wasm_sudo(
+                contract.clone(),
+                &SudoMsg::ImportLegacyOrders {
+                    orders: orders.clone(),
+                },
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/order.import").add_attributes(vec![
+                ("idx", "1"),
+                ("owner", alice.as_str()),
+                ("side", "quote"),
+                ("offer", "1000"),
+                ("filled", "5"),
+            ]),
+        );
+        assert_eq!(
+            app.wrap().query_balance(&alice, "btc-btc").unwrap().amount,
+            Uint128::from(5u128)
+        );
+
+        let order: OrderResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Order((
+                    bob.to_string(),
+                    Side::Base,
+                    Price::Fixed(Decimal::from_str("100000").unwrap()),
+                )),
+            )
+            .unwrap();
+        assert_eq!(order.remaining, Uint128::from(10u128));
+        assert_eq!(order.updated_at, Timestamp::from_seconds(2));
+
+        // Re-importing is a no-op
+        let res = app
+            .wasm_sudo(contract.clone(), &SudoMsg::ImportLegacyOrders { orders })
+            .unwrap();
+        assert!(!res
+            .events
+            .iter()
+            .any(|e| e.ty == "wasm-rujira-fin/order.import"));
+        assert_eq!(
+            app.wrap().query_balance(&alice, "btc-btc").unwrap().amount,
+            Uint128::from(5u128)
+        );
+        let res: OrdersResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Orders {
+                    owner: alice.to_string(),
+                    side: None,
+                    page: PageRequest::default(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.orders.len(), 1);
+        assert_eq!(res.orders[0].remaining, Uint128::from(1_000u128));
+
+        // Imported orders trade like any other
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &alice, coins(1_000_000, "eth-usdc"))
+                .unwrap();
+        });
+        app.execute_contract(
+            alice.clone(),
+            contract.clone(),
+            &ExecuteMsg::Swap(SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            &coins(1_000_000, "eth-usdc"),
+        )
+        .unwrap();
+        let order: OrderResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Order((
+                    bob.to_string(),
+                    Side::Base,
+                    Price::Fixed(Decimal::from_str("100000").unwrap()),
+                )),
+            )
+            .unwrap();
+        assert_eq!(order.remaining, Uint128::zero());
+
+        let err = app
+            .wasm_sudo(
+                contract.clone(),
+                &SudoMsg::ImportLegacyOrders {
+                    orders: vec![LegacyOrder {
+                        idx: Uint128::from(3u128),
+                        owner: bob.to_string(),
+                        quote_price: Decimal::from_str("100000").unwrap(),
+                        offer_denom: "eth-eth".to_string(),
+                        original_offer_amount: Uint128::from(10u128),
+                        offer_amount: Uint128::from(10u128),
+                        filled_amount: Uint128::zero(),
+                        created_at: Timestamp::from_seconds(2),
+                    }],
+                },
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("offer_denom"));
+    }
+
+    #[test]
+    fn wrap_receipt() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000, "btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(200_000, "usdc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "usdc");
+        let receipt = "x/ghost-vault/usdc";
+
+        let code = Box::new(
+            ContractWrapper::new(execute, instantiate, query)
+                .with_sudo(sudo)
+                .with_reply(reply),
+        );
+        let code_id = app.store_code(code);
+        let contract = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    denoms: Denoms::new("btc", receipt),
+                    market_makers: vec![],
+                    oracles: None,
+                    tick: Tick::new(6u8),
+                    fee_taker: Decimal::zero(),
+                    fee_maker: Decimal::zero(),
+                    fee_address: app.api().addr_make("fee").to_string(),
+                },
+                &[],
+                "template",
+                None,
+            )
+            .unwrap();
+
+        // The receipt token must be one of the pair's denoms
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetWrapAdapter {
+                vault: Some(vault.addr().to_string()),
+                underlying: "usdc".to_string(),
+                receipt: "x/ghost-vault/btc".to_string(),
+            },
+        )
+        .unwrap_err();
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetWrapAdapter {
+                vault: Some(vault.addr().to_string()),
+                underlying: "usdc".to_string(),
+                receipt: receipt.to_string(),
+            },
+        )
+        .unwrap();
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Config {})
+            .unwrap();
+        assert_eq!(
+            config.wrap,
+            Some(WrapAdapter {
+                vault: vault.addr().clone(),
+                underlying: "usdc".to_string(),
+                receipt: receipt.to_string(),
+            })
+        );
+
+        app.execute_contract(
+            owner.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Base,
+                    Price::Fixed(Decimal::from_str("100").unwrap()),
+                    Some(Uint128::from(1_000u128)),
+                )],
+                None,
+            )),
+            &coins(1_000, "btc"),
+        )
+        .unwrap();
+
+        // 110,000 usdc is wrapped, 100,000 of the receipt token buys the book and the rest is unwrapped
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::Swap(SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            &coins(110_000, "usdc"),
+        )
+        .unwrap();
+        #[allow(deprecated)]
+        let balances = app.wrap().query_all_balances(&user).unwrap();
+        assert_eq!(balances, vec![coin(1_000, "btc"), coin(100_000, "usdc")]);
+
+        // Wrapped orders rest in the receipt token
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Quote,
+                    Price::Fixed(Decimal::from_str("90").unwrap()),
+                    Some(Uint128::from(9_000u128)),
+                )],
+                None,
+            )),
+            &coins(9_000, "usdc"),
+        )
+        .unwrap();
+        let order: OrderResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Order((
+                    user.to_string(),
+                    Side::Quote,
+                    Price::Fixed(Decimal::from_str("90").unwrap()),
+                )),
+            )
+            .unwrap();
+        assert_eq!(order.remaining, Uint128::from(9_000u128));
+        assert_eq!(
+            app.wrap().query_balance(&user, "usdc").unwrap().amount,
+            Uint128::from(91_000u128)
+        );
+
+        // Funds in the pair's denoms are untouched
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::Swap(SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            &coins(10, "btc"),
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap().query_balance(&user, receipt).unwrap().amount,
+            Uint128::from(900u128)
+        );
+    }
+
+    #[test]
+    fn aux_quote() {
+        let (mut app, contract) = setup();
+        let owner = app.api().addr_make("owner");
+        let maker = app.api().addr_make("maker");
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &maker, coins(1_000, "eth-usdc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(1_000, "eth-usdt"))
+                .unwrap();
+        });
+        let code = Box::new(
+            ContractWrapper::new(execute, instantiate, query)
+                .with_sudo(sudo)
+                .with_reply(reply),
+        );
+        let code_id = app.store_code(code);
+        let pair = app
+            .instantiate_contract(
+                code_id,
+                owner,
+                &InstantiateMsg {
+                    denoms: Denoms::new("eth-usdt", "eth-usdc"),
+                    market_makers: vec![],
+                    oracles: None,
+                    tick: Tick::new(6u8),
+                    fee_taker: Decimal::zero(),
+                    fee_maker: Decimal::zero(),
+                    fee_address: app.api().addr_make("fee").to_string(),
+                },
+                &[],
+                "pair",
+                None,
+            )
+            .unwrap();
+        app.execute_contract(
+            maker.clone(),
+            pair.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Quote,
+                    Price::Fixed(Decimal::from_str("0.99").unwrap()),
+                    Some(Uint128::from(1_000u128)),
+                )],
+                None,
+            )),
+            &coins(1_000, "eth-usdc"),
+        )
+        .unwrap();
+
+        // The mock has no USDT pool, so it is priced at par with USDC
+        let set = |app: &mut RujiraApp, denom: &str, max_slippage: Decimal| {
+            app.wasm_sudo(
+                contract.clone(),
+                &SudoMsg::SetAuxQuote {
+                    denom: denom.to_string(),
+                    pair: Some(pair.to_string()),
+                    oracle: Layer1Asset::try_from(
+                        "ETH.USDC-0XA0B86991C6218B36C1D19D4A2E9EB0CE3606EB48",
+                    )
+                    .unwrap(),
+                    max_slippage,
+                },
+            )
+        };
+        set(&mut app, "eth-usdc", Decimal::percent(2)).unwrap_err();
+        set(&mut app, "eth-usdt", Decimal::one()).unwrap_err();
+        set(&mut app, "eth-usdt", Decimal::percent(2)).unwrap();
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(contract.clone(), &QueryMsg::Config {})
+            .unwrap();
+        assert_eq!(config.aux_quotes.len(), 1);
+        assert_eq!(config.aux_quotes[0].pair, pair);
+
+        let order = |app: &mut RujiraApp| {
+            app.execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Order((
+                    vec![(
+                        Side::Quote,
+                        Price::Fixed(Decimal::one()),
+                        Some(Uint128::from(400u128)),
+                    )],
+                    None,
+                )),
+                &coins(500, "eth-usdt"),
+            )
+        };
+
+        // 500 USDT returns 495 USDC at 0.99, within 2% of the oracle value
+        let res = order(&mut app).unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/aux_quote.convert").add_attributes(vec![
+                ("pair", pair.as_str()),
+                ("offer", "500eth-usdt"),
+                ("min_return", "490eth-usdc"),
+            ]),
+        );
+        let res: OrderResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Order((user.to_string(), Side::Quote, Price::Fixed(Decimal::one()))),
+            )
+            .unwrap();
+        assert_eq!(res.offer, Uint128::from(400u128));
+        assert_eq!(
+            app.wrap().query_balance(&user, "eth-usdc").unwrap().amount,
+            Uint128::from(95u128)
+        );
+        assert_eq!(
+            app.wrap().query_balance(&user, "eth-usdt").unwrap().amount,
+            Uint128::from(500u128)
+        );
+
+        // The same swap is more than 0.5% below the oracle value
+        set(&mut app, "eth-usdt", Decimal::permille(5)).unwrap();
+        order(&mut app).unwrap_err();
+        assert_eq!(
+            app.wrap().query_balance(&user, "eth-usdt").unwrap().amount,
+            Uint128::from(500u128)
+        );
+
+        // A swap on the aux pair can't resume a conversion that the pair didn't start
+        app.execute_contract(
+            user.clone(),
+            pair.clone(),
+            &ExecuteMsg::Swap(SwapRequest::Yolo {
+                to: Some(contract.to_string()),
+                callback: Some(CallbackData::Msg(
+                    to_json_binary(&AuxQuoteCallback::Order { nonce: 1 }).unwrap(),
+                )),
+                max_levels: None,
+            }),
+            &coins(10, "eth-usdt"),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn market_maker_fallback() {
+        let (mut app, contract) = setup();
+        let owner = app.api().addr_make("owner");
+        let maker = app.api().addr_make("maker");
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &maker, coins(1_000, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(1_000, "eth-usdc"))
+                .unwrap();
+        });
+
+        // Quotes a better price than the book, and fails to settle it
+        let code = ContractWrapper::new(
+            |_, _, _, _: Empty| -> StdResult<Response> {
+                Err(cosmwasm_std::StdError::generic_err("insufficient funds"))
+            },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::default()) },
+            |_, _, msg: rujira_rs::bow::QueryMsg| -> StdResult<Binary> {
+                match msg {
+                    rujira_rs::bow::QueryMsg::Quote(req) if req.min_price.is_none() => {
+                        to_json_binary(&Some(rujira_rs::bow::QuoteResponse {
+                            price: Decimal::one(),
+                            size: Uint128::from(1_000u128),
+                            data: None,
+                        }))
+                    }
+                    _ => to_json_binary(&None::<rujira_rs::bow::QuoteResponse>),
+                }
+            },
+        );
+        let code_id = app.store_code(Box::new(code));
+        let mm = app
+            .instantiate_contract(code_id, owner, &Empty {}, &[], "mm", None)
+            .unwrap();
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::UpdateConfig {
+                tick: None,
+                fee_taker: None,
+                fee_maker: None,
+                fee_address: None,
+                market_makers: Some(vec![mm.to_string()]),
+                oracles: None,
+                premium_bounds: None,
+                decimal_delta: None,
+            },
+        )
+        .unwrap();
+        app.execute_contract(
+            maker.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Base,
+                    Price::Fixed(Decimal::from_str("2").unwrap()),
+                    Some(Uint128::from(1_000u128)),
+                )],
+                None,
+            )),
+            &coins(1_000, "btc-btc"),
+        )
+        .unwrap();
+
+        // The swap is retried against the book alone
+        let res = app
+            .execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }),
+                &coins(100, "eth-usdc"),
+            )
+            .unwrap();
+        assert!(res
+            .events
+            .iter()
+            .any(|x| x.ty == "wasm-rujira-fin/market_maker.fallback"));
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/market_maker.dropped")
+                .add_attribute("contract", mm.to_string()),
+        );
+        assert_eq!(
+            app.wrap().query_balance(&user, "btc-btc").unwrap().amount,
+            Uint128::from(50u128)
+        );
+        assert!(!Lock::is_locked(app.contract_storage(&contract).as_ref()).unwrap());
+        assert!(
+            !market_maker_fallback::is_excluded(app.contract_storage(&contract).as_ref()).unwrap()
+        );
+
+        // Bounded by its balance of the asset it pays, the market maker isn't traded against
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetMarketMakerLimit {
+                limit: Some(rujira_rs::fin::MarketMakerLimit {
+                    max_value: Decimal::from_str("1000000000").unwrap(),
+                }),
+            },
+        )
+        .unwrap();
+        let res = app
+            .execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }),
+                &coins(100, "eth-usdc"),
+            )
+            .unwrap();
+        assert!(!res
+            .events
+            .iter()
+            .any(|x| x.ty == "wasm-rujira-fin/market_maker.fallback"));
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/market_maker.dropped")
+                .add_attribute("contract", mm.to_string()),
+        );
+        assert_eq!(
+            app.wrap().query_balance(&user, "btc-btc").unwrap().amount,
+            Uint128::from(100u128)
+        );
+    }
+
+    #[test]
+    fn order_flow_auction() {
+        let (mut app, contract) = setup();
+        let owner = app.api().addr_make("owner");
+        let maker = app.api().addr_make("maker");
+        let user = app.api().addr_make("user");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &maker, coins(1_000, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(1_000, "eth-usdc"))
+                .unwrap();
+        });
+
+        // Bids 10% over the book, and fills from its balance
+        let code = ContractWrapper::new(
+            |_, _, info: MessageInfo, msg: BidderMsg| -> StdResult<Response> {
+                let BidderMsg::Fill { ask_denom, bid } = msg;
+                Ok(Response::default().add_message(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: coins(bid.u128(), ask_denom),
+                }))
+            },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::default()) },
+            |_, _, msg: rujira_rs::fin::BidderQueryMsg| -> StdResult<Binary> {
+                let rujira_rs::fin::BidderQueryMsg::Bid { book, .. } = msg;
+                to_json_binary(&Some(book.multiply_ratio(11u128, 10u128)))
+            },
+        );
+        let code_id = app.store_code(Box::new(code));
+        let bidder = app
+            .instantiate_contract(code_id, owner, &Empty {}, &[], "bidder", None)
+            .unwrap();
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &bidder, coins(60, "btc-btc"))
+                .unwrap();
+        });
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetOrderFlowAuction {
+                auction: Some(rujira_rs::fin::OrderFlowAuction {
+                    min_offer: coins(100, "eth-usdc"),
+                    bidders: vec![bidder.clone()],
+                    min_improvement: Decimal::from_str("0.05").unwrap(),
+                }),
+            },
+        )
+        .unwrap();
+        app.execute_contract(
+            maker.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Base,
+                    Price::Fixed(Decimal::from_str("2").unwrap()),
+                    Some(Uint128::from(1_000u128)),
+                )],
+                None,
+            )),
+            &coins(1_000, "btc-btc"),
+        )
+        .unwrap();
+        let swap = |app: &mut RujiraApp, amount: u128| {
+            app.execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }),
+                &coins(amount, "eth-usdc"),
+            )
+            .unwrap()
+        };
+
+        // Below the minimum offer, the swap goes to the book
+        let res = swap(&mut app, 50);
+        assert!(!res
+            .events
+            .iter()
+            .any(|x| x.ty == "wasm-rujira-fin/order_flow_auction"));
+        assert_eq!(
+            app.wrap().query_balance(&user, "btc-btc").unwrap().amount,
+            Uint128::from(25u128)
+        );
+
+        // The bidder improves on the 50 the book returns
+        let res = swap(&mut app, 100);
+        res.assert_event(
+            &Event::new("wasm-rujira-fin/order_flow_auction")
+                .add_attribute("bidder", bidder.to_string())
+                .add_attribute("offer", "100eth-usdc")
+                .add_attribute("book", "50btc-btc")
+                .add_attribute("bid", "55btc-btc"),
+        );
+        assert_eq!(
+            app.wrap().query_balance(&user, "btc-btc").unwrap().amount,
+            Uint128::from(80u128)
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(&bidder, "eth-usdc")
+                .unwrap()
+                .amount,
+            Uint128::from(100u128)
+        );
+        assert!(!Lock::is_locked(app.contract_storage(&contract).as_ref()).unwrap());
+
+        // Unable to fill its bid, the swap falls through to the book
+        let res = swap(&mut app, 100);
+        assert!(res
+            .events
+            .iter()
+            .any(|x| x.ty == "wasm-rujira-fin/order_flow_auction.fallback"));
+        assert_eq!(
+            app.wrap().query_balance(&user, "btc-btc").unwrap().amount,
+            Uint128::from(130u128)
+        );
+        assert_eq!(
+            app.wrap().query_balance(&bidder, "btc-btc").unwrap().amount,
+            Uint128::from(5u128)
+        );
+        assert!(!Lock::is_locked(app.contract_storage(&contract).as_ref()).unwrap());
+
+        // Pays its bid with a deposit into the pair, which would be reclaimed once the swap settles
+        let code = ContractWrapper::new(
+            |_, _, info: MessageInfo, msg: BidderMsg| -> StdResult<Response> {
+                let BidderMsg::Fill { ask_denom, bid } = msg;
+                Ok(Response::default().add_message(WasmMsg::Execute {
+                    contract_addr: info.sender.to_string(),
+                    msg: to_json_binary(&ExecuteMsg::Twap(TwapRequest {
+                        duration: 300,
+                        interval: 100,
+                        limit: None,
+                    }))?,
+                    funds: coins(bid.u128(), ask_denom),
+                }))
+            },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::default()) },
+            |_, _, msg: rujira_rs::fin::BidderQueryMsg| -> StdResult<Binary> {
+                let rujira_rs::fin::BidderQueryMsg::Bid { book, .. } = msg;
+                to_json_binary(&Some(book.multiply_ratio(11u128, 10u128)))
+            },
+        );
+        let code_id = app.store_code(Box::new(code));
+        let depositor = app
+            .instantiate_contract(
+                code_id,
+                app.api().addr_make("owner"),
+                &Empty {},
+                &[],
+                "depositor",
+                None,
+            )
+            .unwrap();
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &depositor, coins(60, "btc-btc"))
+                .unwrap();
+        });
+        app.wasm_sudo(
+            contract.clone(),
+            &SudoMsg::SetOrderFlowAuction {
+                auction: Some(rujira_rs::fin::OrderFlowAuction {
+                    min_offer: coins(100, "eth-usdc"),
+                    bidders: vec![depositor.clone()],
+                    min_improvement: Decimal::from_str("0.05").unwrap(),
+                }),
+            },
+        )
+        .unwrap();
+        let res = swap(&mut app, 100);
+        assert!(res
+            .events
+            .iter()
+            .any(|x| x.ty == "wasm-rujira-fin/order_flow_auction.fallback"));
+        assert_eq!(
+            app.wrap()
+                .query_balance(&depositor, "btc-btc")
+                .unwrap()
+                .amount,
+            Uint128::from(60u128)
+        );
+        assert!(!Lock::is_locked(app.contract_storage(&contract).as_ref()).unwrap());
+    }
+
+    #[test]
+    fn withdraw_whitelist() {
+        let (mut app, contract) = setup();
+        let maker = app.api().addr_make("maker");
+        let user = app.api().addr_make("user");
+        let friend = app.api().addr_make("friend");
+        let stranger = app.api().addr_make("stranger");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &maker, coins(1_000, "btc-btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &user, coins(1_000, "eth-usdc"))
+                .unwrap();
+        });
+        app.execute_contract(
+            maker.clone(),
+            contract.clone(),
+            &ExecuteMsg::Order((
+                vec![(
+                    Side::Base,
+                    Price::Fixed(Decimal::from_str("2").unwrap()),
+                    Some(Uint128::from(1_000u128)),
+                )],
+                None,
+            )),
+            &coins(1_000, "btc-btc"),
+        )
+        .unwrap();
+        let swap = |app: &mut RujiraApp, to: &Addr| {
+            app.execute_contract(
+                user.clone(),
+                contract.clone(),
+                &ExecuteMsg::Swap(SwapRequest::Yolo {
+                    to: Some(to.to_string()),
+                    callback: None,
+                    max_levels: None,
+                }),
+                &coins(10, "eth-usdc"),
+            )
+        };
+        let whitelist = |app: &RujiraApp| -> Option<Vec<String>> {
+            app.wrap()
+                .query_wasm_smart(
+                    contract.clone(),
+                    &QueryMsg::WithdrawWhitelist {
+                        owner: user.to_string(),
+                    },
+                )
+                .unwrap()
+        };
+
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::SetWithdrawWhitelist {
+                addresses: Some(vec![friend.to_string()]),
+            },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(whitelist(&app), Some(vec![friend.to_string()]));
+
+        let err = swap(&mut app, &stranger).unwrap_err();
+        assert!(err.root_cause().to_string().contains("withdraw whitelist"));
+        swap(&mut app, &friend).unwrap();
+        swap(&mut app, &user).unwrap();
+        assert_eq!(
+            app.wrap().query_balance(&friend, "btc-btc").unwrap().amount,
+            Uint128::from(5u128)
+        );
+        assert_eq!(
+            app.wrap().query_balance(&user, "btc-btc").unwrap().amount,
+            Uint128::from(5u128)
+        );
+
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::SetWithdrawWhitelist {
+                addresses: Some(vec![friend.to_string(), stranger.to_string()]),
+            },
+            &[],
+        )
+        .unwrap();
+        // Loosening the whitelist is timelocked
+        assert_eq!(whitelist(&app), Some(vec![friend.to_string()]));
+        swap(&mut app, &stranger).unwrap_err();
+
+        // Tightening it applies immediately, replacing the pending change
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::SetWithdrawWhitelist {
+                addresses: Some(vec![]),
+            },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(whitelist(&app), Some(vec![]));
+        swap(&mut app, &friend).unwrap_err();
+        swap(&mut app, &user).unwrap();
+
+        app.execute_contract(
+            user.clone(),
+            contract.clone(),
+            &ExecuteMsg::SetWithdrawWhitelist { addresses: None },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(whitelist(&app), Some(vec![]));
+        swap(&mut app, &stranger).unwrap_err();
+        app.update_block(|b| {
+            b.time = b
+                .time
+                .plus_seconds(withdraw_whitelist::WITHDRAW_WHITELIST_DELAY)
+        });
+        assert_eq!(whitelist(&app), None);
+        swap(&mut app, &stranger).unwrap();
+        assert_eq!(
+            app.wrap()
+                .query_balance(&stranger, "btc-btc")
+                .unwrap()
+                .amount,
+            Uint128::from(5u128)
+        );
+    }
 }
@@ -1,5 +1,7 @@
-use cosmwasm_std::{CheckedFromRatioError, Coin, ConversionOverflowError, OverflowError, StdError};
-use cw_utils::{NativeBalance, PaymentError};
+use cosmwasm_std::{
+    Addr, CheckedFromRatioError, Coin, ConversionOverflowError, OverflowError, StdError, Timestamp,
+};
+use cw_utils::PaymentError;
 use rujira_rs::{
     bid_pool::BidPoolError, exchange::SwapError, fin::TickError, query::PoolError, OracleError,
 };
@@ -40,20 +42,44 @@ pub enum ContractError {
     #[error("Insufficient Return. expected {requested} got {returned}")]
     InsufficientReturn { requested: Coin, returned: Coin },
 
-    #[error("InsufficientFunds required {required} got {available}")]
+    #[error("InsufficientFunds at order {index}: required {required} got {available}, short {shortfall}")]
     InsufficientFunds {
-        required: NativeBalance,
-        available: NativeBalance,
+        /// The first order of the batch to spend more than was available
+        index: usize,
+        required: Coin,
+        available: Coin,
+        shortfall: Coin,
     },
 
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Locked")]
+    Locked {},
+
     #[error("NotFound")]
     NotFound {},
 
+    #[error("TwapNotDue")]
+    TwapNotDue {},
+
+    #[error("SessionActive")]
+    SessionActive {},
+
+    #[error("Fill callback rate limited until {next}")]
+    FillCallbackRateLimited { next: Timestamp },
+
+    #[error("Oracle premium {premium} outside of bounds [{min}, {max}]")]
+    PremiumOutOfBounds { premium: i16, min: i16, max: i16 },
+
+    #[error("Recipient {recipient} not in the withdraw whitelist")]
+    RecipientNotWhitelisted { recipient: Addr },
+
     #[error("Invalid: {0}")]
     Invalid(String),
+
+    #[error("Migrating: the backfill of the current version is incomplete")]
+    Migrating {},
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
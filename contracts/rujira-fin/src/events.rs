@@ -1,6 +1,10 @@
-use cosmwasm_std::{Event, Uint128};
+use cosmwasm_std::{Addr, Coin, Decimal, Event, Timestamp, Uint128};
+use rujira_rs::fin::{Price, Side};
 
-use crate::{order::Order, pool::Pool};
+use crate::{
+    grid::Grid, maker_session::MakerSession, order::Order, pool::Pool, session::Settlement,
+    twap::Twap,
+};
 
 pub fn event_create_order(pool: &Pool, order: &Order) -> Event {
     Event::new(format!("{}/order.create", env!("CARGO_PKG_NAME")))
@@ -10,6 +14,16 @@ pub fn event_create_order(pool: &Pool, order: &Order) -> Event {
         .add_attribute("offer", order.offer)
 }
 
+pub fn event_import_order(pool: &Pool, order: &Order, idx: &Uint128, filled: &Uint128) -> Event {
+    Event::new(format!("{}/order.import", env!("CARGO_PKG_NAME")))
+        .add_attribute("idx", idx.to_string())
+        .add_attribute("owner", order.owner.clone())
+        .add_attribute("side", pool.side.to_string())
+        .add_attribute("price", pool.price.to_string())
+        .add_attribute("offer", order.offer)
+        .add_attribute("filled", filled.to_string())
+}
+
 pub fn event_withdraw_order(pool: &Pool, order: &Order, amount: &Uint128) -> Event {
     Event::new(format!("{}/order.withdraw", env!("CARGO_PKG_NAME")))
         .add_attribute("owner", order.owner.clone())
@@ -33,3 +47,283 @@ pub fn event_retract_order(pool: &Pool, order: &Order, amount: &Uint128) -> Even
         .add_attribute("price", pool.price.to_string())
         .add_attribute("amount", amount.to_string())
 }
+
+pub fn event_create_grid(grid: &Grid) -> Event {
+    Event::new(format!("{}/grid.create", env!("CARGO_PKG_NAME")))
+        .add_attribute("id", grid.id.to_string())
+        .add_attribute("owner", grid.owner.clone())
+        .add_attribute("side", grid.side.to_string())
+        .add_attribute("levels", grid.levels.to_string())
+        .add_attribute("total", grid.total)
+}
+
+pub fn event_reprice_grid(grid: &Grid) -> Event {
+    Event::new(format!("{}/grid.reprice", env!("CARGO_PKG_NAME")))
+        .add_attribute("id", grid.id.to_string())
+        .add_attribute("owner", grid.owner.clone())
+        .add_attribute("from", grid.side_range.0.to_string())
+        .add_attribute("to", grid.side_range.1.to_string())
+}
+
+pub fn event_cancel_grid(grid: &Grid) -> Event {
+    Event::new(format!("{}/grid.cancel", env!("CARGO_PKG_NAME")))
+        .add_attribute("id", grid.id.to_string())
+        .add_attribute("owner", grid.owner.clone())
+}
+
+pub fn event_open_maker_session(session: &MakerSession) -> Event {
+    Event::new(format!("{}/maker_session.open", env!("CARGO_PKG_NAME")))
+        .add_attribute("id", session.id.to_string())
+        .add_attribute("owner", session.owner.clone())
+        .add_attribute("ttl", session.ttl.to_string())
+        .add_attribute("expires", session.expires.seconds().to_string())
+}
+
+pub fn event_heartbeat(session: &MakerSession) -> Event {
+    Event::new(format!(
+        "{}/maker_session.heartbeat",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("id", session.id.to_string())
+    .add_attribute("expires", session.expires.seconds().to_string())
+}
+
+pub fn event_close_maker_session(session: &MakerSession) -> Event {
+    Event::new(format!("{}/maker_session.close", env!("CARGO_PKG_NAME")))
+        .add_attribute("id", session.id.to_string())
+        .add_attribute("owner", session.owner.clone())
+}
+
+pub fn event_expire_maker_session(session: &MakerSession, caller: &Addr, orders: usize) -> Event {
+    Event::new(format!("{}/maker_session.expire", env!("CARGO_PKG_NAME")))
+        .add_attribute("id", session.id.to_string())
+        .add_attribute("owner", session.owner.clone())
+        .add_attribute("caller", caller.clone())
+        .add_attribute("orders", orders.to_string())
+}
+
+pub fn event_create_twap(twap: &Twap) -> Event {
+    Event::new(format!("{}/twap.create", env!("CARGO_PKG_NAME")))
+        .add_attribute("id", twap.id.to_string())
+        .add_attribute("owner", twap.owner.clone())
+        .add_attribute("side", twap.side.to_string())
+        .add_attribute("offer", twap.offer)
+        .add_attribute("slices", twap.slices().to_string())
+}
+
+pub fn event_crank_twap(twap: &Twap, offer: &Uint128, returned: &Uint128) -> Event {
+    Event::new(format!("{}/twap.crank", env!("CARGO_PKG_NAME")))
+        .add_attribute("id", twap.id.to_string())
+        .add_attribute("owner", twap.owner.clone())
+        .add_attribute("offer", offer.to_string())
+        .add_attribute("returned", returned.to_string())
+        .add_attribute("remaining", twap.remaining)
+}
+
+pub fn event_cancel_twap(twap: &Twap) -> Event {
+    Event::new(format!("{}/twap.cancel", env!("CARGO_PKG_NAME")))
+        .add_attribute("id", twap.id.to_string())
+        .add_attribute("owner", twap.owner.clone())
+        .add_attribute("refund", twap.remaining)
+}
+
+pub fn event_order_callback(caller: &Addr, owner: &Addr) -> Event {
+    Event::new(format!("{}/order.callback", env!("CARGO_PKG_NAME")))
+        .add_attribute("caller", caller.clone())
+        .add_attribute("owner", owner.clone())
+}
+
+pub fn event_set_fill_callback(owner: &Addr, side: &Side, price: &Price, set: bool) -> Event {
+    Event::new(format!("{}/fill_callback.set", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner.clone())
+        .add_attribute("side", side.to_string())
+        .add_attribute("price", price.to_string())
+        .add_attribute("set", set.to_string())
+}
+
+pub fn event_set_withdraw_whitelist(
+    owner: &Addr,
+    addresses: &Option<Vec<String>>,
+    effective: Timestamp,
+) -> Event {
+    let event = Event::new(format!("{}/withdraw_whitelist.set", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner.clone())
+        .add_attribute("effective", effective.seconds().to_string());
+    match addresses {
+        Some(addresses) => event.add_attribute("addresses", addresses.join(",")),
+        None => event,
+    }
+}
+
+pub fn event_fill_callback_failed(err: String) -> Event {
+    Event::new(format!("{}/fill_callback.failed", env!("CARGO_PKG_NAME")))
+        .add_attribute("error", err)
+}
+
+pub fn event_sweep(caller: &Addr, removed: usize, bounty: &[Coin]) -> Event {
+    let event = Event::new(format!("{}/sweep", env!("CARGO_PKG_NAME")))
+        .add_attribute("caller", caller.clone())
+        .add_attribute("removed", removed.to_string());
+    if bounty.is_empty() {
+        return event;
+    }
+    event.add_attribute(
+        "bounty",
+        bounty
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+pub fn event_settle(settlement: &Settlement) -> Event {
+    Event::new(format!("{}/session.settle", env!("CARGO_PKG_NAME")))
+        .add_attribute("window", settlement.window.to_string())
+        .add_attribute("rate", settlement.rate.to_string())
+        .add_attribute("base", settlement.base.to_string())
+        .add_attribute("quote", settlement.quote.to_string())
+        .add_attribute("complete", settlement.complete.to_string())
+}
+
+/// Emitted when the oracle is unavailable. `last` is the price that oracle priced orders trade
+/// at instead, and when it was observed. They're frozen without one
+pub fn event_oracle_fallback(last: Option<(Decimal, Timestamp)>) -> Event {
+    let event = Event::new(format!("{}/oracle.fallback", env!("CARGO_PKG_NAME")));
+    match last {
+        Some((price, observed_at)) => event
+            .add_attribute("mode", "last_price")
+            .add_attribute("price", price.to_string())
+            .add_attribute("observed_at", observed_at.seconds().to_string()),
+        None => event.add_attribute("mode", "freeze"),
+    }
+}
+
+/// Emitted when fees collected in the base asset are sold for the quote asset
+pub fn event_aux_quote_convert(pair: &Addr, offer: &Coin, min_return: &Coin) -> Event {
+    Event::new(format!("{}/aux_quote.convert", env!("CARGO_PKG_NAME")))
+        .add_attribute("pair", pair.clone())
+        .add_attribute("offer", offer.to_string())
+        .add_attribute("min_return", min_return.to_string())
+}
+
+pub fn event_fee_conversion(sold: &Coin, bought: &Coin) -> Event {
+    Event::new(format!("{}/fee.convert", env!("CARGO_PKG_NAME")))
+        .add_attribute("sold", sold.to_string())
+        .add_attribute("bought", bought.to_string())
+}
+
+/// Aggregates the trade events of an execution into a single bar, so that indexers needing only
+/// open/high/low/close and volume don't have to parse every fill. Rates are quoted in the quote
+/// asset per unit of the base asset, whichever side was filled
+pub fn event_trade_summary(events: &[Event]) -> Option<Event> {
+    let ty = format!("{}/trade", env!("CARGO_PKG_NAME"));
+    let mut fills = 0u32;
+    let mut bar: Option<(Decimal, Decimal, Decimal, Decimal)> = None;
+    let mut volume_base = Uint128::zero();
+    let mut volume_quote = Uint128::zero();
+    for event in events.iter().filter(|x| x.ty == ty) {
+        let attr = |key: &str| {
+            event
+                .attributes
+                .iter()
+                .find(|x| x.key == key)
+                .map(|x| x.value.as_str())
+        };
+        let (Some(rate), Some(offer), Some(bid), Some(side)) = (
+            attr("rate").and_then(|x| x.parse::<Decimal>().ok()),
+            attr("offer").and_then(|x| x.parse::<Uint128>().ok()),
+            attr("bid").and_then(|x| x.parse::<Uint128>().ok()),
+            attr("side"),
+        ) else {
+            continue;
+        };
+        // A pool on the base side is offered the quote asset, and returns the base asset
+        let (base, quote) = match side {
+            "base" => (bid, offer),
+            _ => (offer, bid),
+        };
+        fills += 1;
+        volume_base += base;
+        volume_quote += quote;
+        bar = Some(match bar {
+            None => (rate, rate, rate, rate),
+            Some((open, high, low, _)) => (open, high.max(rate), low.min(rate), rate),
+        });
+    }
+    let (open, high, low, close) = bar?;
+    Some(
+        Event::new(format!("{}/trade.summary", env!("CARGO_PKG_NAME")))
+            .add_attribute("open", open.to_string())
+            .add_attribute("high", high.to_string())
+            .add_attribute("low", low.to_string())
+            .add_attribute("close", close.to_string())
+            .add_attribute("volume_base", volume_base)
+            .add_attribute("volume_quote", volume_quote)
+            .add_attribute("fills", fills.to_string()),
+    )
+}
+
+pub fn event_rescue(to: &Addr, amount: &Coin) -> Event {
+    Event::new(format!("{}/rescue", env!("CARGO_PKG_NAME")))
+        .add_attribute("to", to.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
+pub fn event_market_maker_dropped(contract: &Addr) -> Event {
+    Event::new(format!("{}/market_maker.dropped", env!("CARGO_PKG_NAME")))
+        .add_attribute("contract", contract.to_string())
+}
+
+pub fn event_market_maker_fallback(err: String) -> Event {
+    Event::new(format!("{}/market_maker.fallback", env!("CARGO_PKG_NAME")))
+        .add_attribute("error", err)
+}
+
+/// A market swap won by `bidder` in an order flow auction, bidding `bid` against the `book` return
+pub fn event_order_flow_auction(bidder: &Addr, offer: &Coin, book: &Coin, bid: &Coin) -> Event {
+    Event::new(format!("{}/order_flow_auction", env!("CARGO_PKG_NAME")))
+        .add_attribute("bidder", bidder.clone())
+        .add_attribute("offer", offer.to_string())
+        .add_attribute("book", book.to_string())
+        .add_attribute("bid", bid.to_string())
+}
+
+pub fn event_order_flow_auction_fill(bidder: &Addr, returned: &Coin, fee: &Coin) -> Event {
+    Event::new(format!(
+        "{}/order_flow_auction.fill",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("bidder", bidder.clone())
+    .add_attribute("returned", returned.to_string())
+    .add_attribute("fee", fee.to_string())
+}
+
+pub fn event_order_flow_auction_fallback(err: String) -> Event {
+    Event::new(format!(
+        "{}/order_flow_auction.fallback",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("error", err)
+}
+
+pub fn event_arb(profit: &[Coin], usd: Option<Decimal>) -> Event {
+    let event = Event::new(format!("{}/arb", env!("CARGO_PKG_NAME"))).add_attribute(
+        "profit",
+        profit
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+    );
+    match usd {
+        Some(usd) => event.add_attribute("profit_usd", usd.to_string()),
+        None => event,
+    }
+}
+
+/// `complete` is false while the backfill of a large book needs the migration re-running
+pub fn event_migrate(complete: bool) -> Event {
+    Event::new(format!("{}/migrate", env!("CARGO_PKG_NAME")))
+        .add_attribute("complete", complete.to_string())
+}
@@ -0,0 +1,166 @@
+use cosmwasm_std::{coin, CosmosMsg, Decimal, Event, Storage, Timestamp, Uint128};
+use cw_utils::NativeBalance;
+use rujira_rs::{
+    exchange::{Swappable, Swapper},
+    fin::{Side, SwapRequest},
+    Premiumable,
+};
+
+use crate::{
+    config::Config, events::event_fee_conversion, swap_iter::SwapIter, trade::Trade, ContractError,
+};
+
+/// Sells the base asset held in `fees` into the bids of the book, when [Config::fee_conversion] is set.
+/// The sale stops at `max_slippage` below the best bid, and any base asset left over is kept in `fees`.
+/// No taker fee is charged on the sale
+pub fn convert(
+    storage: &mut dyn Storage,
+    config: &Config,
+    swap_iter: &SwapIter,
+    oracle: &impl Premiumable,
+    time: Timestamp,
+    fees: &mut NativeBalance,
+) -> Result<(Vec<CosmosMsg>, Vec<Event>), ContractError> {
+    let Some(conversion) = &config.fee_conversion else {
+        return Ok((vec![], vec![]));
+    };
+    let base = config.denoms.base();
+    let amount = fees
+        .0
+        .iter()
+        .find(|x| x.denom == base)
+        .map(|x| x.amount)
+        .unwrap_or_default();
+    if amount.is_zero() {
+        return Ok((vec![], vec![]));
+    }
+    // Bids for the base asset, best first
+    let side = Side::Quote;
+    let best = swap_iter
+        .iter(storage, &side, oracle)
+        .next()
+        .map(|x| x.rate());
+    let Some(limit) = best
+        .map(|x| x * (Decimal::one() - conversion.max_slippage))
+        .and_then(|x| x.inv())
+    else {
+        return Ok((vec![], vec![]));
+    };
+
+    let mut swapper = Swapper::new(
+        env!("CARGO_PKG_NAME"),
+        amount,
+        // Quoted in the offer per unit returned, as is a sell
+        SwapRequest::Limit {
+            price: limit,
+            min_fill: None,
+            to: None,
+            callback: None,
+            max_levels: None,
+        },
+        Decimal::zero(),
+    );
+    let mut res = {
+        let mut iter = swap_iter.iter(storage, &side, oracle);
+        swapper.swap(storage, &mut iter)?
+    };
+    if res.return_amount.is_zero() {
+        return Ok((vec![], vec![]));
+    }
+    let commit = swapper.commit(storage)?;
+    if let Some(rate) = res.last_rate {
+        Trade::new(rate, time).save(storage)?;
+    }
+
+    let sold = coin(res.consumed_offer.u128(), base);
+    let bought = coin(res.return_amount.u128(), config.denoms.quote());
+    *fees = (fees.clone() - sold.clone())?;
+    *fees += bought.clone();
+    fees.normalize();
+
+    res.events.push(event_fee_conversion(&sold, &bought));
+    Ok((commit.to_msgs(&config.denoms, &side)?, res.events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, MockApi};
+    use cosmwasm_std::QuerierWrapper;
+    use rujira_rs::fin::{Denoms, FeeConversion, InstantiateMsg, Price, Tick};
+
+    use crate::pool::Pool;
+
+    fn config(conversion: Option<FeeConversion>) -> Config {
+        let api = MockApi::default();
+        let mut config = Config::new(
+            &api,
+            InstantiateMsg {
+                denoms: Denoms::new("base", "quote"),
+                oracles: None,
+                market_makers: vec![],
+                tick: Tick::new(6),
+                fee_taker: Decimal::permille(1),
+                fee_maker: Decimal::permille(1),
+                fee_address: api.addr_make("fees").to_string(),
+            },
+        )
+        .unwrap();
+        config.fee_conversion = conversion;
+        config
+    }
+
+    #[test]
+    fn convert_within_slippage() {
+        let mut deps = mock_dependencies();
+        let owner = MockApi::default().addr_make("owner");
+        let time = Timestamp::from_seconds(0);
+        // Bids of 1000 quote at 2.0, and 1000 quote at 1.5
+        for price in ["2.0", "1.5"] {
+            let price = Price::Fixed(price.parse().unwrap());
+            let mut pool = Pool::load(&deps.storage, &price, &Side::Quote, &None::<Decimal>);
+            pool.create_order(&mut deps.storage, &time, &owner, Uint128::from(1000u128))
+                .unwrap();
+        }
+
+        let mut fees = NativeBalance(vec![coin(1000, "base"), coin(10, "quote")]);
+        let unchanged = fees.clone();
+        let querier = QuerierWrapper::new(&deps.querier);
+        let disabled = config(None);
+        let swap_iter = SwapIter::new(querier, &disabled);
+        let (msgs, events) = convert(
+            &mut deps.storage,
+            &disabled,
+            &swap_iter,
+            &None::<Decimal>,
+            time,
+            &mut fees,
+        )
+        .unwrap();
+        assert!(msgs.is_empty() && events.is_empty());
+        assert_eq!(fees, unchanged);
+
+        // Only the level at 2.0 is within 10% of the best bid
+        let enabled = config(Some(FeeConversion {
+            max_slippage: Decimal::percent(10),
+        }));
+        let swap_iter = SwapIter::new(querier, &enabled);
+        let (_, events) = convert(
+            &mut deps.storage,
+            &enabled,
+            &swap_iter,
+            &None::<Decimal>,
+            time,
+            &mut fees,
+        )
+        .unwrap();
+        assert_eq!(
+            fees,
+            NativeBalance(vec![coin(500, "base"), coin(1010, "quote")])
+        );
+        assert_eq!(
+            events.last().unwrap(),
+            &event_fee_conversion(&coin(500, "base"), &coin(1000, "quote"))
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, StdResult, Storage, SubMsg, Timestamp, Uint128};
+use cw_storage_plus::Map;
+use rujira_rs::{
+    fin::{OrderFill, Price, Side},
+    CallbackData,
+};
+
+use crate::{order::Order, pool::Pool, ContractError};
+
+// Fill callbacks, keyed by the order they are registered with
+static FILL_CALLBACKS: Map<(Addr, Side, Price), FillCallback> = Map::new("fill-callbacks");
+
+pub const FILL_CALLBACK_REPLY_ID: u64 = 1;
+/// The minimum interval between cranked callbacks of an order, in seconds
+pub const FILL_CALLBACK_INTERVAL: u64 = 60;
+pub const FILL_CALLBACK_GAS_LIMIT: u64 = 500_000;
+
+/// A callback sent to the contract owning an order when it is filled
+#[cw_serde]
+pub struct FillCallback {
+    pub callback: CallbackData,
+    /// The fill of the order already sent since its last withdrawal
+    pub notified: Uint128,
+    pub last_dispatch: Timestamp,
+}
+
+impl FillCallback {
+    pub fn set(
+        storage: &mut dyn Storage,
+        order: &Order,
+        side: &Side,
+        price: &Price,
+        callback: CallbackData,
+    ) -> StdResult<()> {
+        FILL_CALLBACKS.save(
+            storage,
+            (order.owner.clone(), side.clone(), price.clone()),
+            &Self {
+                callback,
+                notified: order.bid.filled().try_into()?,
+                last_dispatch: Timestamp::default(),
+            },
+        )
+    }
+
+    pub fn may_load(
+        storage: &dyn Storage,
+        owner: &Addr,
+        side: &Side,
+        price: &Price,
+    ) -> StdResult<Option<Self>> {
+        FILL_CALLBACKS.may_load(storage, (owner.clone(), side.clone(), price.clone()))
+    }
+
+    pub fn remove(storage: &mut dyn Storage, owner: &Addr, side: &Side, price: &Price) {
+        FILL_CALLBACKS.remove(storage, (owner.clone(), side.clone(), price.clone()))
+    }
+
+    /// Sends the callback of `order` for its fill since the last callback, rate limited by [FILL_CALLBACK_INTERVAL]
+    pub fn crank(
+        storage: &mut dyn Storage,
+        pool: &Pool,
+        order: &Order,
+        now: Timestamp,
+    ) -> Result<SubMsg, ContractError> {
+        let mut cb = Self::may_load(storage, &order.owner, &pool.side, &pool.price)?
+            .ok_or(ContractError::NotFound {})?;
+        let next = cb.last_dispatch.plus_seconds(FILL_CALLBACK_INTERVAL);
+        if now < next {
+            return Err(ContractError::FillCallbackRateLimited { next });
+        }
+        let filled: Uint128 = order.bid.filled().try_into()?;
+        let msg = cb
+            .dispatch(pool, order, filled, now)?
+            .ok_or(ContractError::Invalid("order has no new fill".to_string()))?;
+        FILL_CALLBACKS.save(
+            storage,
+            (order.owner.clone(), pool.side.clone(), pool.price.clone()),
+            &cb,
+        )?;
+        Ok(msg)
+    }
+
+    /// Sends the callback of `order` for the fill not yet sent, before `filled` is withdrawn
+    pub fn claim(
+        storage: &mut dyn Storage,
+        pool: &Pool,
+        order: &Order,
+        filled: Uint128,
+        now: Timestamp,
+    ) -> Result<Option<SubMsg>, ContractError> {
+        let Some(mut cb) = Self::may_load(storage, &order.owner, &pool.side, &pool.price)? else {
+            return Ok(None);
+        };
+        let msg = cb.dispatch(pool, order, filled, now)?;
+        cb.notified = Uint128::zero();
+        FILL_CALLBACKS.save(
+            storage,
+            (order.owner.clone(), pool.side.clone(), pool.price.clone()),
+            &cb,
+        )?;
+        Ok(msg)
+    }
+
+    /// A gas limited callback of the fill since `notified`. Failures are caught by the reply
+    /// so that they can't revert a withdrawal
+    fn dispatch(
+        &mut self,
+        pool: &Pool,
+        order: &Order,
+        filled: Uint128,
+        now: Timestamp,
+    ) -> Result<Option<SubMsg>, ContractError> {
+        let new = filled.saturating_sub(self.notified);
+        if new.is_zero() {
+            return Ok(None);
+        }
+        let msg = self.callback.to_message(
+            &order.owner,
+            OrderFill {
+                side: pool.side.clone(),
+                price: pool.price.clone(),
+                filled: new,
+            },
+            vec![],
+        )?;
+        self.notified = filled;
+        self.last_dispatch = now;
+        Ok(Some(
+            SubMsg::reply_on_error(msg, FILL_CALLBACK_REPLY_ID)
+                .with_gas_limit(FILL_CALLBACK_GAS_LIMIT),
+        ))
+    }
+}
@@ -0,0 +1,285 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, Addr, Decimal, StdResult, Storage, Uint128};
+use cw_storage_plus::{Item, Map};
+use rujira_rs::fin::{GridResponse, OrderTarget, Price, Side, Tick};
+
+use crate::ContractError;
+
+static GRID_ID: Item<u64> = Item::new("grid-id");
+static GRIDS: Map<u64, Grid> = Map::new("grids");
+
+pub const MAX_LEVELS: u8 = 50;
+
+/// A set of evenly spaced orders managed as a unit.
+/// Each level is a regular order of the owner, so it is shared with any other order
+/// the owner places at the same side and price
+#[cw_serde]
+pub struct Grid {
+    pub id: u64,
+    pub owner: Addr,
+    pub side: Side,
+    pub side_range: (Price, Price),
+    pub levels: u8,
+    pub total: Uint128,
+    /// Stored so that the grid can be retracted at the same prices after a tick change
+    pub prices: Vec<Price>,
+}
+
+impl Grid {
+    pub fn create(
+        storage: &mut dyn Storage,
+        tick: &Tick,
+        owner: Addr,
+        side: Side,
+        side_range: (Price, Price),
+        levels: u8,
+        total: Uint128,
+    ) -> Result<Self, ContractError> {
+        ensure!(
+            total >= Uint128::from(levels),
+            ContractError::Invalid("grid total must be at least 1 per level".to_string())
+        );
+        let prices = spread(tick, &side_range, levels)?;
+        let id = GRID_ID.may_load(storage)?.unwrap_or_default();
+        GRID_ID.save(storage, &(id + 1))?;
+        let grid = Self {
+            id,
+            owner,
+            side,
+            side_range,
+            levels,
+            total,
+            prices,
+        };
+        grid.save(storage)?;
+        Ok(grid)
+    }
+
+    pub fn load(storage: &dyn Storage, id: u64) -> StdResult<Self> {
+        GRIDS.load(storage, id)
+    }
+
+    /// Loads a grid, ensuring it was placed by `owner`
+    pub fn load_owned(storage: &dyn Storage, id: u64, owner: &Addr) -> Result<Self, ContractError> {
+        let grid = Self::load(storage, id)?;
+        ensure!(grid.owner == owner, ContractError::Unauthorized {});
+        Ok(grid)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        GRIDS.save(storage, self.id, self)
+    }
+
+    pub fn remove(&self, storage: &mut dyn Storage) {
+        GRIDS.remove(storage, self.id)
+    }
+
+    /// Order targets for each level, with the remainder of an uneven split added to the first levels
+    pub fn targets(&self) -> Vec<OrderTarget> {
+        let levels = u128::from(self.levels);
+        let size = self.total.u128() / levels;
+        let remainder = self.total.u128() % levels;
+        self.prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| {
+                let extra = u128::from((i as u128) < remainder);
+                (
+                    self.side.clone(),
+                    price.clone(),
+                    Some(Uint128::from(size + extra)),
+                )
+            })
+            .collect()
+    }
+
+    /// Order targets retracting every level
+    pub fn cancel_targets(&self) -> Vec<OrderTarget> {
+        self.prices
+            .iter()
+            .map(|price| (self.side.clone(), price.clone(), Some(Uint128::zero())))
+            .collect()
+    }
+
+    /// Moves the grid to `side_range`, returning order targets that retract the levels no longer
+    /// in the grid and set the new levels
+    pub fn reprice(
+        &mut self,
+        tick: &Tick,
+        side_range: (Price, Price),
+    ) -> Result<Vec<OrderTarget>, ContractError> {
+        let prices = spread(tick, &side_range, self.levels)?;
+        let mut targets: Vec<OrderTarget> = self
+            .cancel_targets()
+            .into_iter()
+            .filter(|(_, price, _)| !prices.contains(price))
+            .collect();
+        self.side_range = side_range;
+        self.prices = prices;
+        targets.append(&mut self.targets());
+        Ok(targets)
+    }
+}
+
+impl From<Grid> for GridResponse {
+    fn from(value: Grid) -> Self {
+        Self {
+            id: value.id,
+            owner: value.owner.to_string(),
+            side: value.side,
+            side_range: value.side_range,
+            levels: value.levels,
+            total: value.total,
+            prices: value.prices,
+        }
+    }
+}
+
+/// Evenly spaced prices from the first to the second price of the range, inclusive
+fn spread(tick: &Tick, range: &(Price, Price), levels: u8) -> Result<Vec<Price>, ContractError> {
+    ensure!(
+        (2..=MAX_LEVELS).contains(&levels),
+        ContractError::Invalid(format!("grid levels must be between 2 and {MAX_LEVELS}"))
+    );
+    let steps = u128::from(levels - 1);
+    let prices: Vec<Price> = match range {
+        (Price::Fixed(from), Price::Fixed(to)) => {
+            tick.validate_price(from)?;
+            tick.validate_price(to)?;
+            (0..u128::from(levels))
+                .map(|i| {
+                    let step = from.abs_diff(*to) * Decimal::from_ratio(i, steps);
+                    let price = if to > from {
+                        *from + step
+                    } else {
+                        *from - step
+                    };
+                    Price::Fixed(tick.truncate_floor(&price))
+                })
+                .collect()
+        }
+        (Price::Oracle(from), Price::Oracle(to)) => {
+            let (from, to, steps) = (i32::from(*from), i32::from(*to), steps as i32);
+            (0..i32::from(levels))
+                // Interpolated between two i16s, so always in range
+                .map(|i| Price::Oracle((from + (to - from) * i / steps) as i16))
+                .collect()
+        }
+        _ => {
+            return Err(ContractError::Invalid(
+                "grid range must be two Fixed or two Oracle prices".to_string(),
+            ))
+        }
+    };
+    ensure!(
+        prices.windows(2).all(|x| x[0] != x[1]),
+        ContractError::Invalid("grid levels must be at distinct prices".to_string())
+    );
+    Ok(prices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn spread_prices() {
+        let tick = Tick::new(4);
+        let range = (
+            Price::Fixed(Decimal::from_str("100").unwrap()),
+            Price::Fixed(Decimal::from_str("90").unwrap()),
+        );
+        assert_eq!(
+            spread(&tick, &range, 3).unwrap(),
+            vec![
+                Price::Fixed(Decimal::from_str("100").unwrap()),
+                Price::Fixed(Decimal::from_str("95").unwrap()),
+                Price::Fixed(Decimal::from_str("90").unwrap()),
+            ]
+        );
+
+        let range = (Price::Oracle(-100), Price::Oracle(50));
+        assert_eq!(
+            spread(&tick, &range, 4).unwrap(),
+            vec![
+                Price::Oracle(-100),
+                Price::Oracle(-50),
+                Price::Oracle(0),
+                Price::Oracle(50),
+            ]
+        );
+
+        // Levels collapse to the same tick
+        let range = (
+            Price::Fixed(Decimal::from_str("100").unwrap()),
+            Price::Fixed(Decimal::from_str("100.1").unwrap()),
+        );
+        spread(&tick, &range, 3).unwrap_err();
+
+        let range = (Price::Oracle(0), Price::Fixed(Decimal::one()));
+        spread(&tick, &range, 3).unwrap_err();
+        spread(&tick, &(Price::Oracle(0), Price::Oracle(10)), 1).unwrap_err();
+    }
+
+    #[test]
+    fn reprice_targets() {
+        let mut storage = cosmwasm_std::testing::MockStorage::new();
+        let tick = Tick::new(4);
+        let mut grid = Grid::create(
+            &mut storage,
+            &tick,
+            Addr::unchecked("owner"),
+            Side::Quote,
+            (Price::Oracle(-100), Price::Oracle(-300)),
+            3,
+            Uint128::from(1000u128),
+        )
+        .unwrap();
+        assert_eq!(
+            grid.targets(),
+            vec![
+                (
+                    Side::Quote,
+                    Price::Oracle(-100),
+                    Some(Uint128::from(334u128))
+                ),
+                (
+                    Side::Quote,
+                    Price::Oracle(-200),
+                    Some(Uint128::from(333u128))
+                ),
+                (
+                    Side::Quote,
+                    Price::Oracle(-300),
+                    Some(Uint128::from(333u128))
+                ),
+            ]
+        );
+
+        let targets = grid
+            .reprice(&tick, (Price::Oracle(-200), Price::Oracle(-400)))
+            .unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                (Side::Quote, Price::Oracle(-100), Some(Uint128::zero())),
+                (
+                    Side::Quote,
+                    Price::Oracle(-200),
+                    Some(Uint128::from(334u128))
+                ),
+                (
+                    Side::Quote,
+                    Price::Oracle(-300),
+                    Some(Uint128::from(333u128))
+                ),
+                (
+                    Side::Quote,
+                    Price::Oracle(-400),
+                    Some(Uint128::from(333u128))
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use cosmwasm_std::{StdResult, Storage, Uint128};
+use cw_storage_plus::Map;
+
+// Indices of the orders imported from a legacy Kujira FIN contract
+static IMPORTED: Map<u128, ()> = Map::new("legacy-imported");
+
+/// Records the import of legacy order `idx`, returning false if it has already been imported
+pub fn mark_imported(storage: &mut dyn Storage, idx: Uint128) -> StdResult<bool> {
+    if IMPORTED.has(storage, idx.u128()) {
+        return Ok(false);
+    }
+    IMPORTED.save(storage, idx.u128(), &())?;
+    Ok(true)
+}
@@ -1,14 +1,33 @@
+pub mod aux_quote;
 pub mod config;
 pub mod contract;
 mod error;
 pub mod events;
+pub mod fees;
+pub mod fill_callback;
+pub mod grid;
+pub mod legacy;
+pub mod lock;
+pub mod maker_session;
 pub mod market_maker;
+pub mod market_maker_fallback;
 pub mod market_makers;
+pub mod migration;
+pub mod oracle;
 pub mod order;
+pub mod order_flow_auction;
 pub mod order_manager;
 pub mod pool;
 pub mod pool_key;
+pub mod rounding;
+pub mod session;
+pub mod stats;
 pub mod swap_iter;
+pub mod sweep;
+pub mod trade;
+pub mod twap;
+pub mod withdraw_whitelist;
+pub mod wrap;
 
 pub use crate::error::ContractError;
 
@@ -0,0 +1,32 @@
+use cosmwasm_std::{ensure, StdResult, Storage};
+use cw_storage_plus::Item;
+
+use crate::ContractError;
+
+static LOCK: Item<bool> = Item::new("lock");
+
+/// Guards the Swap/Order -> Arb -> DoSwap/DoOrder self-call sequence.
+/// The lock is taken when a sequence starts and released once the final step has committed its state,
+/// so that contracts called in between (e.g. market makers settling an arb) can't re-enter and interleave state changes
+pub struct Lock;
+
+impl Lock {
+    pub fn acquire(storage: &mut dyn Storage) -> Result<(), ContractError> {
+        Self::ensure_released(storage)?;
+        LOCK.save(storage, &true)?;
+        Ok(())
+    }
+
+    pub fn release(storage: &mut dyn Storage) {
+        LOCK.remove(storage)
+    }
+
+    pub fn ensure_released(storage: &dyn Storage) -> Result<(), ContractError> {
+        ensure!(!Self::is_locked(storage)?, ContractError::Locked {});
+        Ok(())
+    }
+
+    pub fn is_locked(storage: &dyn Storage) -> StdResult<bool> {
+        Ok(LOCK.may_load(storage)?.unwrap_or_default())
+    }
+}
@@ -0,0 +1,181 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, Addr, Coin, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Item, Map};
+use rujira_rs::{
+    fin::{MakerSessionResponse, OrderTarget, Price, Side},
+    Premiumable,
+};
+
+use crate::{pool::Pool, stats, ContractError};
+
+static MAKER_SESSION_ID: Item<u64> = Item::new("maker-session-id");
+static MAKER_SESSIONS: Map<u64, MakerSession> = Map::new("maker-sessions");
+// The session that each order is tagged with, so that it can be untagged when the order is removed
+static TAGS: Map<(Addr, Side, Price), u64> = Map::new("maker-session-tags");
+
+/// The maximum number of orders tagged with a session, so that it can be expired in one execution
+pub const MAX_ORDERS: usize = 50;
+
+/// A set of orders retracted when their owner stops sending heartbeats, so that a maker whose
+/// infrastructure fails doesn't leave stale quotes on the book
+#[cw_serde]
+pub struct MakerSession {
+    pub id: u64,
+    pub owner: Addr,
+    pub ttl: u64,
+    pub expires: Timestamp,
+    pub bounty: Vec<Coin>,
+    pub orders: Vec<(Side, Price)>,
+}
+
+impl MakerSession {
+    pub fn open(
+        storage: &mut dyn Storage,
+        owner: Addr,
+        ttl: u64,
+        now: Timestamp,
+        bounty: Vec<Coin>,
+    ) -> Result<Self, ContractError> {
+        ensure!(
+            ttl > 0,
+            ContractError::Invalid("maker session ttl must be positive".to_string())
+        );
+        let id = MAKER_SESSION_ID.may_load(storage)?.unwrap_or_default();
+        MAKER_SESSION_ID.save(storage, &(id + 1))?;
+        let session = Self {
+            id,
+            owner,
+            ttl,
+            expires: now.plus_seconds(ttl),
+            bounty,
+            orders: vec![],
+        };
+        session.save(storage)?;
+        stats::record_bounty(storage, &session.bounty, true)?;
+        Ok(session)
+    }
+
+    pub fn load(storage: &dyn Storage, id: u64) -> StdResult<Self> {
+        MAKER_SESSIONS.load(storage, id)
+    }
+
+    /// Loads a session that hasn't expired at `now`, ensuring it was opened by `owner`
+    pub fn load_live(
+        storage: &dyn Storage,
+        id: u64,
+        owner: &Addr,
+        now: Timestamp,
+    ) -> Result<Self, ContractError> {
+        let session = Self::load(storage, id)?;
+        ensure!(session.owner == owner, ContractError::Unauthorized {});
+        ensure!(
+            !session.expired(now),
+            ContractError::Invalid("maker session expired".to_string())
+        );
+        Ok(session)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        MAKER_SESSIONS.save(storage, self.id, self)
+    }
+
+    /// Removes the session and its tags, releasing its bounty from the contract's liabilities
+    pub fn remove(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        for (side, price) in &self.orders {
+            TAGS.remove(storage, (self.owner.clone(), side.clone(), price.clone()));
+        }
+        MAKER_SESSIONS.remove(storage, self.id);
+        stats::record_bounty(storage, &self.bounty, false)
+    }
+
+    pub fn expired(&self, now: Timestamp) -> bool {
+        now >= self.expires
+    }
+
+    pub fn heartbeat(&mut self, now: Timestamp) {
+        self.expires = now.plus_seconds(self.ttl);
+    }
+
+    /// Tags the orders set by `targets`, untagging those retracted in full
+    pub fn tag(
+        &mut self,
+        storage: &mut dyn Storage,
+        targets: &[OrderTarget],
+    ) -> Result<(), ContractError> {
+        for (side, price, target) in targets {
+            let key = (side.clone(), price.clone());
+            let tag = (self.owner.clone(), side.clone(), price.clone());
+            match target {
+                Some(x) if x.is_zero() => {
+                    self.orders.retain(|x| *x != key);
+                    TAGS.remove(storage, tag);
+                }
+                _ if self.orders.contains(&key) => {}
+                _ => {
+                    ensure!(
+                        !TAGS.has(storage, tag.clone()),
+                        ContractError::Invalid(
+                            "order is tagged with another maker session".to_string()
+                        )
+                    );
+                    TAGS.save(storage, tag, &self.id)?;
+                    self.orders.push(key);
+                }
+            }
+        }
+        ensure!(
+            self.orders.len() <= MAX_ORDERS,
+            ContractError::Invalid(format!("maker session can tag at most {MAX_ORDERS} orders"))
+        );
+        Ok(())
+    }
+
+    /// Untags the order of `owner` at `side` and `price` from its session, once it has been removed
+    pub fn untag(
+        storage: &mut dyn Storage,
+        owner: &Addr,
+        side: &Side,
+        price: &Price,
+    ) -> StdResult<()> {
+        let tag = (owner.clone(), side.clone(), price.clone());
+        let Some(id) = TAGS.may_load(storage, tag.clone())? else {
+            return Ok(());
+        };
+        TAGS.remove(storage, tag);
+        if let Some(mut session) = MAKER_SESSIONS.may_load(storage, id)? {
+            session.orders.retain(|(s, p)| !(s == side && p == price));
+            session.save(storage)?;
+        }
+        Ok(())
+    }
+
+    /// Order targets retracting every tagged order that is still open
+    pub fn cancel_targets(
+        &self,
+        storage: &dyn Storage,
+        oracle: &impl Premiumable,
+    ) -> Vec<OrderTarget> {
+        self.orders
+            .iter()
+            .filter(|(side, price)| {
+                Pool::load(storage, price, side, oracle)
+                    .load_order(storage, &self.owner)
+                    .is_ok()
+            })
+            .map(|(side, price)| (side.clone(), price.clone(), Some(Uint128::zero())))
+            .collect()
+    }
+}
+
+impl From<MakerSession> for MakerSessionResponse {
+    fn from(value: MakerSession) -> Self {
+        Self {
+            id: value.id,
+            owner: value.owner.to_string(),
+            ttl: value.ttl,
+            expires: value.expires,
+            bounty: value.bounty,
+            orders: value.orders,
+        }
+    }
+}
@@ -7,11 +7,24 @@ use rujira_rs::{
     exchange::{Commitment, SwapError, Swappable},
     fin::{Denoms, Side, Tick},
 };
+
+use crate::rounding;
 use std::{
+    cell::RefCell,
     cmp::Ordering,
+    collections::BTreeSet,
     ops::{Div, Mul},
+    rc::Rc,
 };
 
+/// The most quotes taken from a single market maker in one execution
+pub const MAX_QUOTES: usize = 100;
+
+/// Market makers dropped for the rest of an execution, for returning a quote that isn't worse than
+/// the last, more than [MAX_QUOTES] quotes, or more than their limit. Shared by every iteration of
+/// the execution
+pub type Dropped = Rc<RefCell<BTreeSet<Addr>>>;
+
 pub struct MarketMaker<'a> {
     q: QuerierWrapper<'a>,
     denoms: Denoms,
@@ -22,6 +35,10 @@ pub struct MarketMaker<'a> {
     ask: Uint128,
     last_price: Option<Decimal>,
     data: Option<Binary>,
+    quotes: usize,
+    dropped: Dropped,
+    limit: Option<Uint128>,
+    capacity: Option<Uint128>,
 }
 
 impl<'a> MarketMaker<'a> {
@@ -31,6 +48,7 @@ impl<'a> MarketMaker<'a> {
         side: Side,
         contract: Addr,
         tick: Tick,
+        dropped: Dropped,
     ) -> Self {
         Self {
             q,
@@ -42,8 +60,38 @@ impl<'a> MarketMaker<'a> {
             ask: Uint128::zero(),
             last_price: None,
             data: None,
+            quotes: 0,
+            dropped,
+            limit: None,
+            capacity: None,
         }
     }
+
+    /// Bounds the total size of the quotes taken, in the asset the market maker pays. The market
+    /// maker's balance of that asset bounds it further
+    pub fn with_limit(mut self, limit: Option<Uint128>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Whether committing the market maker to a further `size` takes it past its limit. Its balance
+    /// is queried once, with the first quote
+    fn exceeds_limit(&mut self, size: Uint128) -> bool {
+        let Some(limit) = self.limit else {
+            return false;
+        };
+        let capacity = *self.capacity.get_or_insert_with(|| {
+            self.q
+                .query_balance(&self.contract, self.denoms.bid(&self.side))
+                .map(|x| x.amount.min(limit))
+                .unwrap_or_default()
+        });
+        self.ask.checked_add(size).map_or(true, |x| x > capacity)
+    }
+
+    fn drop_for_execution(&self) {
+        self.dropped.borrow_mut().insert(self.contract.clone());
+    }
 }
 
 #[cw_serde]
@@ -61,7 +109,7 @@ impl Swappable for MarketMakerOffer {
         self.rate
     }
 
-    fn attributes(&self) -> Vec<Attribute> {
+    fn attributes(&self, _storage: &dyn Storage) -> Vec<Attribute> {
         let price = match self.side {
             Side::Base => self.price.inv().unwrap().to_string(),
             Side::Quote => self.price.to_string(),
@@ -96,6 +144,7 @@ impl Swappable for MarketMakerOffer {
             }
         };
 
+        rounding::check_fill(res.0, res.1, self.price)?;
         self.commitment = res;
         Ok(res)
     }
@@ -107,6 +156,13 @@ impl Swappable for MarketMakerOffer {
 
 impl<'a> MarketMaker<'a> {
     fn query_next(&mut self, contract: Addr) -> Option<MarketMakerOffer> {
+        if self.dropped.borrow().contains(&contract) {
+            return None;
+        }
+        if self.quotes >= MAX_QUOTES {
+            self.drop_for_execution();
+            return None;
+        }
         let res: StdResult<Option<bow::QuoteResponse>> = self.q.query_wasm_smart(
             contract.to_string(),
             &bow::QueryMsg::Quote(bow::QuoteRequest {
@@ -118,7 +174,19 @@ impl<'a> MarketMaker<'a> {
         );
 
         match res {
+            // The book is walked from its best price, so each quote must be strictly worse than the
+            // last. Otherwise the market maker could trap a swap in a loop of identical levels
+            Ok(Some(res)) if self.last_price.is_some_and(|x| res.price >= x) => {
+                self.drop_for_execution();
+                None
+            }
+            // A quote the market maker can't honor would revert the settlement of the whole swap
+            Ok(Some(res)) if self.exceeds_limit(res.size) => {
+                self.drop_for_execution();
+                None
+            }
             Ok(Some(res)) => {
+                self.quotes += 1;
                 self.bid += Decimal::from_ratio(res.size, 1u128)
                     .mul(res.price)
                     .to_uint_ceil();
@@ -161,10 +229,11 @@ impl Iterator for MarketMaker<'_> {
 mod test {
     use super::*;
     use cosmwasm_std::{
-        from_json, testing::MockQuerier, to_json_binary, ContractResult, SystemError, SystemResult,
-        WasmQuery,
+        coins, from_json, testing::MockQuerier, to_json_binary, ContractResult, SystemError,
+        SystemResult, WasmQuery,
     };
-    use std::ops::Add;
+    use proptest::prelude::*;
+    use std::ops::{Add, Sub};
     use std::str::FromStr;
 
     #[test]
@@ -181,7 +250,7 @@ mod test {
                             .transpose()
                             .unwrap()
                             .unwrap_or_default();
-                        // Double the order size until we run out of funds
+                        // Double the order size until we run out of funds, at a worse price each time
                         if ask_total.gt(&Uint128::from(1000000u128)) {
                             return SystemResult::Err(SystemError::Unknown {});
                         }
@@ -198,13 +267,13 @@ mod test {
                                 data: to_json_binary(&size.add(ask_total)).ok(),
                                 price: query
                                     .min_price
-                                    .unwrap_or(Decimal::one())
-                                    .add(Decimal::from_ratio(1u128, 4u128)),
+                                    .unwrap_or(Decimal::from_ratio(5u128, 2u128))
+                                    .sub(Decimal::from_ratio(1u128, 4u128)),
                             })
                             .unwrap(),
                         ))
                     }
-                    bow::QueryMsg::Strategy {} => todo!(),
+                    bow::QueryMsg::Strategy {} | bow::QueryMsg::Ladder { .. } => unreachable!(),
                 }
             }
             _ => SystemResult::Err(SystemError::Unknown {}),
@@ -216,6 +285,7 @@ mod test {
             Side::Quote,
             contract.clone(),
             Tick::new(3),
+            Dropped::default(),
         );
 
         let items: Vec<MarketMakerOffer> = iter.by_ref().take(5).collect();
@@ -225,16 +295,16 @@ mod test {
             vec![
                 MarketMakerOffer {
                     contract: contract.clone(),
-                    price: Decimal::from_str("1.25").unwrap(),
-                    rate: Decimal::from_str("1.25").unwrap(),
+                    price: Decimal::from_str("2.25").unwrap(),
+                    rate: Decimal::from_str("2.25").unwrap(),
                     total: Uint128::from(100u128),
                     side: Side::Quote,
                     commitment: Default::default()
                 },
                 MarketMakerOffer {
                     contract: contract.clone(),
-                    price: Decimal::from_str("1.5").unwrap(),
-                    rate: Decimal::from_str("1.5").unwrap(),
+                    price: Decimal::from_str("2").unwrap(),
+                    rate: Decimal::from_str("2").unwrap(),
                     total: Uint128::from(200u128),
                     side: Side::Quote,
                     commitment: Default::default()
@@ -249,16 +319,16 @@ mod test {
                 },
                 MarketMakerOffer {
                     contract: contract.clone(),
-                    price: Decimal::from_str("2").unwrap(),
-                    rate: Decimal::from_str("2").unwrap(),
+                    price: Decimal::from_str("1.5").unwrap(),
+                    rate: Decimal::from_str("1.5").unwrap(),
                     total: Uint128::from(1800u128),
                     side: Side::Quote,
                     commitment: Default::default()
                 },
                 MarketMakerOffer {
                     contract: contract.clone(),
-                    price: Decimal::from_str("2.25").unwrap(),
-                    rate: Decimal::from_str("2.25").unwrap(),
+                    price: Decimal::from_str("1.25").unwrap(),
+                    rate: Decimal::from_str("1.25").unwrap(),
                     total: Uint128::from(5400u128),
                     side: Side::Quote,
                     commitment: Default::default()
@@ -266,6 +336,124 @@ mod test {
             ]
         );
     }
+    #[test]
+    fn test_misbehaving_quotes() {
+        // Repeats the same quote forever
+        let mut q = MockQuerier::default();
+        q.update_wasm(|x| match x {
+            WasmQuery::Smart { .. } => SystemResult::Ok(ContractResult::Ok(
+                to_json_binary(&bow::QuoteResponse {
+                    size: Uint128::from(100u128),
+                    data: None,
+                    price: Decimal::one(),
+                })
+                .unwrap(),
+            )),
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        let contract = Addr::unchecked("stuck");
+        let dropped = Dropped::default();
+        let new = |dropped: &Dropped| {
+            MarketMaker::new(
+                QuerierWrapper::new(&q),
+                Denoms::new("btc", "usdc"),
+                Side::Quote,
+                contract.clone(),
+                Tick::new(3),
+                dropped.clone(),
+            )
+        };
+        assert_eq!(new(&dropped).take(10).count(), 1);
+        assert!(dropped.borrow().contains(&contract));
+        // Dropped for the rest of the execution
+        assert_eq!(new(&dropped).count(), 0);
+
+        // Worsens every quote, but never runs out
+        let mut q = MockQuerier::default();
+        q.update_wasm(|x| match x {
+            WasmQuery::Smart { msg, .. } => {
+                let bow::QueryMsg::Quote(query) = from_json(msg).unwrap() else {
+                    return SystemResult::Err(SystemError::Unknown {});
+                };
+                SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&bow::QuoteResponse {
+                        size: Uint128::from(100u128),
+                        data: None,
+                        price: query
+                            .min_price
+                            .unwrap_or(Decimal::one())
+                            .sub(Decimal::permille(1)),
+                    })
+                    .unwrap(),
+                ))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        let dropped = Dropped::default();
+        let iter = MarketMaker::new(
+            QuerierWrapper::new(&q),
+            Denoms::new("btc", "usdc"),
+            Side::Quote,
+            contract.clone(),
+            Tick::new(3),
+            dropped.clone(),
+        );
+        assert_eq!(iter.take(MAX_QUOTES + 10).count(), MAX_QUOTES);
+        assert!(dropped.borrow().contains(&contract));
+    }
+
+    #[test]
+    fn test_limit() {
+        // Quotes 100 at a worse price each time
+        let contract = Addr::unchecked("bow");
+        let balance = coins(250, "usdc");
+        let mut q = MockQuerier::new(&[(contract.as_str(), balance.as_slice())]);
+        q.update_wasm(|x| match x {
+            WasmQuery::Smart { msg, .. } => {
+                let bow::QueryMsg::Quote(query) = from_json(msg).unwrap() else {
+                    return SystemResult::Err(SystemError::Unknown {});
+                };
+                SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&bow::QuoteResponse {
+                        size: Uint128::from(100u128),
+                        data: None,
+                        price: query
+                            .min_price
+                            .unwrap_or(Decimal::one())
+                            .sub(Decimal::permille(1)),
+                    })
+                    .unwrap(),
+                ))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        let new = |dropped: &Dropped, limit: Option<u128>| {
+            MarketMaker::new(
+                QuerierWrapper::new(&q),
+                Denoms::new("btc", "usdc"),
+                Side::Quote,
+                contract.clone(),
+                Tick::new(3),
+                dropped.clone(),
+            )
+            .with_limit(limit.map(Uint128::from))
+        };
+
+        // Unbounded without a limit
+        let dropped = Dropped::default();
+        assert_eq!(new(&dropped, None).take(5).count(), 5);
+        assert!(dropped.borrow().is_empty());
+
+        // Bound by the limit
+        assert_eq!(new(&dropped, Some(150)).take(5).count(), 1);
+        assert!(dropped.borrow().contains(&contract));
+
+        // Bound by the balance of the asset paid
+        let dropped = Dropped::default();
+        assert_eq!(new(&dropped, Some(1000)).take(5).count(), 2);
+        assert!(dropped.borrow().contains(&contract));
+    }
+
     #[test]
     fn test_market_market_item_swap() {
         let contract = Addr::unchecked("bow");
@@ -334,4 +522,31 @@ mod test {
         assert_eq!(bid_returned, Uint128::from(50000000u128));
         assert_eq!(offer_consumed, Uint128::from(499u128));
     }
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            cases: 10000,
+            ..Default::default()
+        })]
+        #[test]
+        fn test_swap_conserves_value(
+            price in 1u64..1_000_000_000,
+            total in 1u128..1_000_000_000_000,
+            offer in 1u128..1_000_000_000_000,
+        ) {
+            let price = Decimal::from_ratio(price, 1_000_000u128);
+            let mut o = MarketMakerOffer {
+                contract: Addr::unchecked("bow"),
+                price,
+                rate: price,
+                total: Uint128::new(total),
+                side: Side::Quote,
+                commitment: Default::default(),
+            };
+            let (consumed, returned) = o.swap(Uint128::new(offer)).unwrap();
+            prop_assert!(consumed <= Uint128::new(offer));
+            prop_assert!(returned <= Uint128::new(total));
+            prop_assert!(rounding::conserves(consumed, returned, price));
+        }
+    }
 }
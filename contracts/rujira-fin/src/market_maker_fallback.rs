@@ -0,0 +1,36 @@
+use cosmwasm_std::{to_json_binary, Addr, Coin, StdResult, Storage, SubMsg, WasmMsg};
+use cw_storage_plus::Item;
+use rujira_rs::fin::SwapRequest;
+
+use crate::config::Config;
+
+// Set while a swap is retried against the book alone, for the rest of its sequence
+static EXCLUDED: Item<bool> = Item::new("market-makers-excluded");
+
+pub const MARKET_MAKER_FALLBACK_REPLY_ID: u64 = 2;
+
+/// The swap retried when the sequence it started fails, resumed by the reply
+pub type FallbackPayload = (Addr, Vec<Coin>, SwapRequest);
+
+/// Sends the Arb -> DoSwap sequence `msg`. With market makers configured, a failure of the sequence
+/// (e.g. a market maker failing to settle its commitment) is caught, and the swap is retried once
+/// without market makers
+pub fn guard(config: &Config, msg: WasmMsg, payload: &FallbackPayload) -> StdResult<SubMsg> {
+    if config.market_makers.contracts.is_empty() {
+        return Ok(SubMsg::new(msg));
+    }
+    Ok(SubMsg::reply_on_error(msg, MARKET_MAKER_FALLBACK_REPLY_ID)
+        .with_payload(to_json_binary(payload)?))
+}
+
+pub fn exclude(storage: &mut dyn Storage) -> StdResult<()> {
+    EXCLUDED.save(storage, &true)
+}
+
+pub fn is_excluded(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(EXCLUDED.may_load(storage)?.unwrap_or_default())
+}
+
+pub fn clear(storage: &mut dyn Storage) {
+    EXCLUDED.remove(storage)
+}
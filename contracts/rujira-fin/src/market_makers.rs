@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Api, QuerierWrapper, StdResult};
+use cosmwasm_std::{Addr, Api, QuerierWrapper, StdResult, Uint128};
 use rujira_rs::{
     exchange::Swappable,
     fin::{Denoms, Side, Tick},
@@ -7,7 +7,7 @@ use rujira_rs::{
 };
 use schemars::Set;
 
-use crate::market_maker::{MarketMaker, MarketMakerOffer};
+use crate::market_maker::{Dropped, MarketMaker, MarketMakerOffer};
 
 #[cw_serde]
 pub struct MarketMakers {
@@ -30,16 +30,20 @@ impl MarketMakers {
         denoms: &'a Denoms,
         tick: &'a Tick,
         side: &'a Side,
+        dropped: Dropped,
+        limit: Option<Uint128>,
     ) -> impl Iterator<Item = Vec<MarketMakerOffer>> + 'a {
         MergeNByIter::new(
-            self.contracts.iter().map(|addr| {
+            self.contracts.iter().map(move |addr| {
                 MarketMaker::new(
                     querier,
                     denoms.clone(),
                     side.clone(),
                     addr.clone(),
                     tick.clone(),
+                    dropped.clone(),
                 )
+                .with_limit(limit)
             }),
             move |a, b| match side.clone() {
                 Side::Base => a.rate().cmp(&b.rate()),
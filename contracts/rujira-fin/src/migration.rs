@@ -0,0 +1,140 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, Addr, StdResult, Storage};
+use cw_storage_plus::Item;
+use rujira_rs::{
+    fin::{Price, Side},
+    Premiumable,
+};
+
+use crate::{order::Order, pool::Pool, stats, twap::Twap, ContractError};
+
+/// The number of orders, pools and TWAPs backfilled by each call of the `migrate` entry point
+pub const LIMIT: usize = 100;
+
+// The stage the backfill has reached, removed once it completes
+static BACKFILL: Item<Backfill> = Item::new("backfill");
+
+/// A stage of the backfill of the indexes and totals added in 1.2.0, with the key it resumes after
+#[cw_serde]
+enum Backfill {
+    /// Indexes each order by pool, and counts it and its unclaimed fills towards its side
+    Orders(Option<(Addr, Side, Price)>),
+    /// Totals the offer resting in each pool, and recounts the orders referencing its sum snapshots
+    Pools(Option<(Side, Price)>),
+    /// Totals the offer escrowed by each TWAP
+    Twaps(Option<u64>),
+}
+
+/// Starts the backfill when upgrading from a version before 1.2.0, clearing the totals it rebuilds.
+/// Execution is paused until [step] has completed it
+pub fn start(storage: &mut dyn Storage, previous: &str) -> StdResult<()> {
+    if !older(previous, &[1, 2]) {
+        return Ok(());
+    }
+    stats::reset(storage)?;
+    BACKFILL.save(storage, &Backfill::Orders(None))
+}
+
+/// Backfills up to `limit` items of each stage, moving on to the next stage when one is exhausted.
+/// `oracle` prices the pools whose orders are synced. Returns whether the backfill is complete
+pub fn step(
+    storage: &mut dyn Storage,
+    oracle: &impl Premiumable,
+    limit: usize,
+) -> Result<bool, ContractError> {
+    let Some(mut stage) = BACKFILL.may_load(storage)? else {
+        return Ok(true);
+    };
+    loop {
+        let next = match stage {
+            Backfill::Orders(start_after) => {
+                match Order::backfill(storage, oracle, start_after, limit)? {
+                    Some(last) => return pause(storage, Backfill::Orders(Some(last))),
+                    None => Backfill::Pools(None),
+                }
+            }
+            Backfill::Pools(start_after) => match Pool::backfill(storage, start_after, limit)? {
+                Some(last) => return pause(storage, Backfill::Pools(Some(last))),
+                None => Backfill::Twaps(None),
+            },
+            Backfill::Twaps(start_after) => match Twap::backfill(storage, start_after, limit)? {
+                Some(last) => return pause(storage, Backfill::Twaps(Some(last))),
+                None => break,
+            },
+        };
+        stage = next;
+    }
+    BACKFILL.remove(storage);
+    Ok(true)
+}
+
+pub fn ensure_complete(storage: &dyn Storage) -> Result<(), ContractError> {
+    ensure!(!BACKFILL.exists(storage), ContractError::Migrating {});
+    Ok(())
+}
+
+fn pause(storage: &mut dyn Storage, stage: Backfill) -> Result<bool, ContractError> {
+    BACKFILL.save(storage, &stage)?;
+    Ok(false)
+}
+
+/// Whether the major and minor of `version` precede `than`
+fn older(version: &str, than: &[u64]) -> bool {
+    let parsed: Vec<u64> = version
+        .split('.')
+        .take(than.len())
+        .map(|x| x.parse().unwrap_or_default())
+        .collect();
+    parsed.as_slice() < than
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{testing::MockStorage, Decimal, Timestamp, Uint128};
+
+    #[test]
+    fn versions() {
+        assert!(older("1.1.0", &[1, 2]));
+        assert!(older("0.9.3", &[1, 2]));
+        assert!(!older("1.2.0", &[1, 2]));
+        assert!(!older("1.10.0", &[1, 2]));
+    }
+
+    #[test]
+    fn backfill() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let oracle = Decimal::one();
+        let price = Price::Fixed(Decimal::one());
+        for side in [Side::Base, Side::Quote] {
+            let mut pool = Pool::load(&store, &price, &side, &oracle);
+            for i in 0..5 {
+                let owner = Addr::unchecked(format!("owner{i}"));
+                pool.create_order(&mut store, &timestamp, &owner, Uint128::new(100))
+                    .unwrap();
+            }
+            pool.commit(&mut store).unwrap();
+            pool.swap(Uint128::new(250)).unwrap();
+            pool.commit(&mut store).unwrap();
+        }
+        let expected = stats::load(&store).unwrap();
+
+        // Nothing to backfill from the current version
+        start(&mut store, "1.2.0").unwrap();
+        ensure_complete(&store).unwrap();
+
+        start(&mut store, "1.1.0").unwrap();
+        ensure_complete(&store).unwrap_err();
+        let mut steps = 0;
+        while !step(&mut store, &oracle, 3).unwrap() {
+            ensure_complete(&store).unwrap_err();
+            steps += 1;
+        }
+        ensure_complete(&store).unwrap();
+        assert_eq!(steps, 3);
+        assert_eq!(stats::load(&store).unwrap(), expected);
+        let orders = Order::by_pool(&store, &Side::Base, &price, None, 10).unwrap();
+        assert_eq!(orders.len(), 5);
+    }
+}
@@ -3,10 +3,10 @@ use cw_multi_test::{ContractWrapper, Executor};
 
 use rujira_rs::{
     fin::{
-        BookResponse, Denoms, ExecuteMsg, InstantiateMsg, OrderResponse, Price, QueryMsg, Side,
-        SwapRequest, Tick,
+        BookCursor, BookResponse, Denoms, ExecuteMsg, InstantiateMsg, OrderResponse, Price,
+        QueryMsg, Side, SwapRequest, Tick,
     },
-    Layer1Asset,
+    Layer1Asset, PageRequest,
 };
 use rujira_rs_testing::RujiraApp;
 
@@ -37,10 +37,12 @@ impl Fin {
                     to: None,
                     callback: None,
                     min_return,
+                    max_levels: None,
                 },
                 None => SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
             }),
             &coins(offer_amount, offer_denom),
@@ -85,12 +87,15 @@ impl Fin {
     pub fn query_book(
         &self,
         app: &RujiraApp,
-        limit: Option<u8>,
-        offset: Option<u8>,
+        page: PageRequest<BookCursor>,
     ) -> anyhow::Result<BookResponse> {
-        Ok(app
-            .wrap()
-            .query_wasm_smart(self.0.clone(), &QueryMsg::Book { limit, offset })?)
+        Ok(app.wrap().query_wasm_smart(
+            self.0.clone(),
+            &QueryMsg::Book {
+                page,
+                group_by: None,
+            },
+        )?)
     }
 
     /// Query a specific order
@@ -113,16 +118,14 @@ impl Fin {
         app: &RujiraApp,
         owner: &str,
         side: Option<Side>,
-        limit: Option<u8>,
-        offset: Option<u8>,
+        page: PageRequest<(Side, Price)>,
     ) -> anyhow::Result<rujira_rs::fin::OrdersResponse> {
         Ok(app.wrap().query_wasm_smart(
             self.0.clone(),
             &QueryMsg::Orders {
                 owner: owner.to_string(),
                 side,
-                offset,
-                limit,
+                page,
             },
         )?)
     }
@@ -136,11 +139,15 @@ impl Fin {
         denoms: Denoms,
         oracles: Option<[Layer1Asset; 2]>,
     ) -> Self {
-        let fin_code = Box::new(ContractWrapper::new(
-            crate::contract::execute,
-            crate::contract::instantiate,
-            crate::contract::query,
-        ));
+        let fin_code = Box::new(
+            ContractWrapper::new(
+                crate::contract::execute,
+                crate::contract::instantiate,
+                crate::contract::query,
+            )
+            .with_sudo(crate::contract::sudo)
+            .with_reply(crate::contract::reply),
+        );
         let fin_code_id = app.store_code(fin_code);
 
         let fin_addr = app
@@ -0,0 +1,173 @@
+use cosmwasm_std::{Decimal, Event, QuerierWrapper, StdResult, Storage, Timestamp};
+use cw_storage_plus::Item;
+use rujira_rs::{fin::OracleFallback, Oracle};
+
+use crate::{config::Config, events::event_oracle_fallback, ContractError};
+
+/// The last oracle price read, and when, for [OracleFallback::LastPrice]
+static LAST: Item<(Decimal, Timestamp)> = Item::new("oracle-last");
+
+/// Reads the oracle price of the pair at `time`. When the oracle is unavailable, the fallback of
+/// the pair is applied, and an event emitted
+pub fn observe(
+    storage: &mut dyn Storage,
+    querier: QuerierWrapper,
+    config: &Config,
+    time: Timestamp,
+) -> StdResult<(Option<Decimal>, Option<Event>)> {
+    let Some(oracles) = &config.oracles else {
+        return Ok((None, None));
+    };
+    match oracles.tor_price(querier) {
+        Ok(price) => {
+            if let OracleFallback::LastPrice { .. } = config.oracle_fallback {
+                LAST.save(storage, &(price, time))?;
+            }
+            Ok((Some(price), None))
+        }
+        Err(_) => {
+            let last = fallback(storage, config, time)?;
+            Ok((
+                last.map(|(price, _)| price),
+                Some(event_oracle_fallback(last)),
+            ))
+        }
+    }
+}
+
+/// The oracle price of the pair at `time` for queries, with the fallback of the pair applied.
+/// Errors when the oracle is unavailable and there's no price to fall back to
+pub fn query(
+    storage: &dyn Storage,
+    querier: QuerierWrapper,
+    config: &Config,
+    time: Timestamp,
+) -> Result<Option<Decimal>, ContractError> {
+    let Some(oracles) = &config.oracles else {
+        return Ok(None);
+    };
+    match (oracles.tor_price(querier), fallback(storage, config, time)?) {
+        (Ok(price), _) => Ok(Some(price)),
+        (Err(_), Some((price, _))) => Ok(Some(price)),
+        (Err(err), None) => Err(err.into()),
+    }
+}
+
+/// The last price read, and when, if it can still be traded at `time`
+fn fallback(
+    storage: &dyn Storage,
+    config: &Config,
+    time: Timestamp,
+) -> StdResult<Option<(Decimal, Timestamp)>> {
+    let OracleFallback::LastPrice { max_age } = config.oracle_fallback else {
+        return Ok(None);
+    };
+    Ok(LAST
+        .may_load(storage)?
+        .filter(|(_, at)| time.seconds().saturating_sub(at.seconds()) <= max_age))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{testing::mock_dependencies, Addr, Uint128};
+    use rujira_rs::{
+        fin::{DecimalDelta, Denoms, PremiumBounds, Tick},
+        Layer1Asset,
+    };
+
+    use crate::market_makers::MarketMakers;
+
+    fn config(oracle_fallback: OracleFallback) -> Config {
+        Config {
+            denoms: Denoms::new("btc-btc", "eth-usdc"),
+            oracles: Some([
+                Layer1Asset::new("BTC", "BTC"),
+                Layer1Asset::new("ETH", "USDC"),
+            ]),
+            market_makers: MarketMakers {
+                contracts: Default::default(),
+            },
+            tick: Tick::new(4),
+            fee_maker: Decimal::zero(),
+            fee_taker: Decimal::zero(),
+            fee_address: Addr::unchecked(""),
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback,
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
+        }
+    }
+
+    #[test]
+    fn fallback() {
+        // The mock querier has no oracle, so every read fails
+        let mut deps = mock_dependencies();
+        let price = Decimal::percent(200);
+
+        let frozen = config(OracleFallback::Freeze);
+        let (res, event) = observe(
+            &mut deps.storage,
+            QuerierWrapper::new(&deps.querier),
+            &frozen,
+            Timestamp::from_seconds(100),
+        )
+        .unwrap();
+        assert_eq!(res, None);
+        assert_eq!(event.unwrap().attributes[0].value, "freeze");
+
+        let config = config(OracleFallback::LastPrice { max_age: 60 });
+        LAST.save(&mut deps.storage, &(price, Timestamp::from_seconds(100)))
+            .unwrap();
+        let (res, event) = observe(
+            &mut deps.storage,
+            QuerierWrapper::new(&deps.querier),
+            &config,
+            Timestamp::from_seconds(160),
+        )
+        .unwrap();
+        assert_eq!(res, Some(price));
+        let event = event.unwrap();
+        assert_eq!(event.attributes[0].value, "last_price");
+        assert_eq!(event.attributes[1].value, "2");
+        assert_eq!(event.attributes[2].value, "100");
+        assert_eq!(
+            query(
+                &deps.storage,
+                QuerierWrapper::new(&deps.querier),
+                &config,
+                Timestamp::from_seconds(160)
+            )
+            .unwrap(),
+            Some(price)
+        );
+
+        // Beyond the max age, oracle priced orders are frozen
+        let (res, event) = observe(
+            &mut deps.storage,
+            QuerierWrapper::new(&deps.querier),
+            &config,
+            Timestamp::from_seconds(161),
+        )
+        .unwrap();
+        assert_eq!(res, None);
+        assert_eq!(event.unwrap().attributes[0].value, "freeze");
+        query(
+            &deps.storage,
+            QuerierWrapper::new(&deps.querier),
+            &config,
+            Timestamp::from_seconds(161),
+        )
+        .unwrap_err();
+    }
+}
@@ -1,18 +1,22 @@
-use std::cmp::min;
-
-use crate::{error::ContractError, pool::Pool, pool_key::PoolKey};
+use crate::{
+    error::ContractError, fill_callback::FillCallback, maker_session::MakerSession, pool::Pool,
+    pool_key::PoolKey, stats,
+};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Addr, StdResult, Storage, Timestamp, Uint128};
-use cw_storage_plus::Map;
+use cw_storage_plus::{Bound, Map};
 use rujira_rs::{
     bid_pool,
     fin::{Price, Side},
+    Premiumable,
 };
 
 pub const ORDERS: Map<(Addr, Side, Price), (Timestamp, Uint128, bid_pool::Bid)> =
     Map::new("orders");
-const MAX_LIMIT: u8 = 31;
-const DEFAULT_LIMIT: u8 = 10;
+// Index of the owners with an order resting in each pool
+const POOL_ORDERS: Map<(PoolKey, Addr), ()> = Map::new("pool-orders");
+pub const MAX_LIMIT: u8 = 31;
+pub const DEFAULT_LIMIT: u8 = 10;
 
 #[cw_serde]
 pub struct Order {
@@ -45,27 +49,35 @@ impl Order {
         storage: &dyn Storage,
         owner: &Addr,
         side: Option<Side>,
-        offset: Option<u8>,
-        limit: Option<u8>,
+        start_after: Option<(Side, Price)>,
+        limit: usize,
     ) -> StdResult<Vec<(PoolKey, Self)>> {
-        let limit = min(limit.unwrap_or(DEFAULT_LIMIT), MAX_LIMIT) as usize;
-        let offset = offset.unwrap_or(0) as usize;
         match side {
-            Some(side) => Self::by_owner_side(storage, owner, side, offset, limit),
-            None => Self::by_owner_all(storage, owner, offset, limit),
+            Some(side) => Self::by_owner_side(
+                storage,
+                owner,
+                side,
+                start_after.map(|(_, price)| price),
+                limit,
+            ),
+            None => Self::by_owner_all(storage, owner, start_after, limit),
         }
     }
 
     fn by_owner_all(
         storage: &dyn Storage,
         owner: &Addr,
-        offset: usize,
+        start_after: Option<(Side, Price)>,
         limit: usize,
     ) -> StdResult<Vec<(PoolKey, Self)>> {
         ORDERS
             .sub_prefix(owner.clone())
-            .range(storage, None, None, cosmwasm_std::Order::Ascending)
-            .skip(offset)
+            .range(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                cosmwasm_std::Order::Ascending,
+            )
             .take(limit)
             .map(|x| {
                 x.map(|(k, (updated_at, offer, bid))| {
@@ -87,18 +99,18 @@ impl Order {
         storage: &dyn Storage,
         owner: &Addr,
         side: Side,
-        offset: usize,
+        start_after: Option<Price>,
         limit: usize,
     ) -> StdResult<Vec<(PoolKey, Self)>> {
-        let order = match side {
-            Side::Base => cosmwasm_std::Order::Ascending,
-            Side::Quote => cosmwasm_std::Order::Descending,
+        let start_after = start_after.map(Bound::exclusive);
+        let (min, max, order) = match side {
+            Side::Base => (start_after, None, cosmwasm_std::Order::Ascending),
+            Side::Quote => (None, start_after, cosmwasm_std::Order::Descending),
         };
 
         ORDERS
             .prefix((owner.clone(), side.clone()))
-            .range(storage, None, None, order)
-            .skip(offset)
+            .range(storage, min, max, order)
             .take(limit)
             .map(|x| {
                 x.map(|(k, (updated_at, offer, bid))| {
@@ -116,28 +128,124 @@ impl Order {
             .collect()
     }
 
+    pub fn by_pool(
+        storage: &dyn Storage,
+        side: &Side,
+        price: &Price,
+        start_after: Option<Addr>,
+        limit: usize,
+    ) -> StdResult<Vec<Self>> {
+        POOL_ORDERS
+            .prefix(PoolKey::new(side.clone(), price.clone()))
+            .keys(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                cosmwasm_std::Order::Ascending,
+            )
+            .take(limit)
+            .map(|owner| {
+                let owner = owner?;
+                let (updated_at, offer, bid) =
+                    ORDERS.load(storage, (owner.clone(), side.clone(), price.clone()))?;
+                Ok(Self {
+                    owner,
+                    updated_at,
+                    offer,
+                    bid,
+                })
+            })
+            .collect()
+    }
+
+    /// Indexes a page of existing orders by pool, and counts them and their fills not yet withdrawn
+    /// towards the totals of their side. Returns the key of the last order when the page is full
+    pub fn backfill(
+        storage: &mut dyn Storage,
+        oracle: &impl Premiumable,
+        start_after: Option<(Addr, Side, Price)>,
+        limit: usize,
+    ) -> Result<Option<(Addr, Side, Price)>, ContractError> {
+        let orders = ORDERS
+            .range(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                cosmwasm_std::Order::Ascending,
+            )
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+        for ((owner, side, price), (updated_at, offer, bid)) in orders.iter().cloned() {
+            POOL_ORDERS.save(
+                storage,
+                (PoolKey::new(side.clone(), price.clone()), owner.clone()),
+                &(),
+            )?;
+            let pool = Pool::load(storage, &price, &side, oracle);
+            let mut order = Self {
+                owner,
+                updated_at,
+                offer,
+                bid,
+            };
+            pool.sync_order(storage, &mut order)?;
+            stats::record_order(storage, &side, true)?;
+            stats::record_fill(storage, &side, order.bid.filled().try_into()?)?;
+        }
+        Ok(orders
+            .last()
+            .filter(|_| orders.len() == limit)
+            .map(|(k, _)| k.clone()))
+    }
+
     pub fn amount(&self) -> Uint128 {
         self.bid.amount().try_into().unwrap()
     }
 
     pub fn save(&self, storage: &mut dyn Storage, pool: &Pool) -> StdResult<()> {
         if self.bid.is_empty() {
-            self.remove(storage, pool);
-            return Ok(());
+            return self.remove(storage, pool);
         }
+        let key = (self.owner.clone(), pool.side.clone(), pool.price.clone());
+        let stored = ORDERS
+            .may_load(storage, key.clone())?
+            .map(|(_, _, bid)| bid);
+        if stored.is_none() {
+            stats::record_order(storage, &pool.side, true)?;
+        }
+        pool.reference_snapshot(storage, stored.as_ref(), Some(&self.bid))?;
         ORDERS.save(
             storage,
-            (self.owner.clone(), pool.side.clone(), pool.price.clone()),
+            key,
             &(self.updated_at, self.offer, self.bid.clone()),
         )?;
+        POOL_ORDERS.save(
+            storage,
+            (
+                PoolKey::new(pool.side.clone(), pool.price.clone()),
+                self.owner.clone(),
+            ),
+            &(),
+        )?;
         Ok(())
     }
 
-    fn remove(&self, storage: &mut dyn Storage, pool: &Pool) {
-        ORDERS.remove(
+    fn remove(&self, storage: &mut dyn Storage, pool: &Pool) -> StdResult<()> {
+        let key = (self.owner.clone(), pool.side.clone(), pool.price.clone());
+        if let Some((_, _, bid)) = ORDERS.may_load(storage, key.clone())? {
+            stats::record_order(storage, &pool.side, false)?;
+            pool.reference_snapshot(storage, Some(&bid), None)?;
+        }
+        ORDERS.remove(storage, key);
+        POOL_ORDERS.remove(
             storage,
-            (self.owner.clone(), pool.side.clone(), pool.price.clone()),
-        )
+            (
+                PoolKey::new(pool.side.clone(), pool.price.clone()),
+                self.owner.clone(),
+            ),
+        );
+        FillCallback::remove(storage, &self.owner, &pool.side, &pool.price);
+        MakerSession::untag(storage, &self.owner, &pool.side, &pool.price)
     }
 }
 
@@ -236,14 +344,68 @@ mod tests {
 
         pool1.commit(&mut store).unwrap();
 
-        let orders = Order::by_owner(&store, &owner, None, None, None).unwrap();
+        let orders = Order::by_owner(&store, &owner, None, None, 10).unwrap();
         assert_eq!(orders.len(), 7);
         assert_eq!(orders[0].1.owner, owner);
         assert_eq!(orders[0].1.offer, offer);
 
-        let orders = Order::by_owner(&store, &owner, Some(Side::Quote), None, None).unwrap();
+        let page = Order::by_owner(&store, &owner, None, None, 4).unwrap();
+        let cursor = page.last().map(|(k, _)| (k.side.clone(), k.price.clone()));
+        let next = Order::by_owner(&store, &owner, None, cursor, 10).unwrap();
+        assert_eq!(next.len(), 3);
+        assert_eq!(next[0].0, orders[4].0);
+
+        let orders = Order::by_owner(&store, &owner, Some(Side::Quote), None, 10).unwrap();
         assert_eq!(orders.len(), 6);
         assert_eq!(orders[0].1.owner, owner);
         assert_eq!(orders[0].1.offer, offer);
+
+        let cursor = Some((Side::Quote, orders[1].0.price.clone()));
+        let next = Order::by_owner(&store, &owner, Some(Side::Quote), cursor, 10).unwrap();
+        assert_eq!(next.len(), 4);
+        assert_eq!(next[0].0, orders[2].0);
+    }
+
+    #[test]
+    fn query_orders_by_pool() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let owner = Addr::unchecked("owner");
+        let owner2 = Addr::unchecked("owner2");
+        let owner3 = Addr::unchecked("owner3");
+        let offer = Uint128::from(100u128);
+        let oracle = Decimal::one();
+        let price = Price::Fixed(Decimal::from_str("1.2").unwrap());
+        let mut pool = Pool::load(&store, &price, &Side::Quote, &oracle);
+        let mut other = Pool::load(&store, &price, &Side::Base, &oracle);
+
+        pool.create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+        pool.create_order(&mut store, &timestamp, &owner2, offer)
+            .unwrap();
+        let mut order = pool
+            .create_order(&mut store, &timestamp, &owner3, offer)
+            .unwrap();
+        other
+            .create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+
+        let orders = Order::by_pool(&store, &Side::Quote, &price, None, 10).unwrap();
+        assert_eq!(orders.len(), 3);
+        assert_eq!(orders[0].owner, owner);
+        assert_eq!(orders[0].offer, offer);
+
+        let orders = Order::by_pool(&store, &Side::Quote, &price, Some(owner.clone()), 1).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].owner, owner2);
+
+        // Fully retracted orders are dropped from the index
+        pool.retract_order(&mut store, &mut order, &timestamp, None)
+            .unwrap();
+        let orders = Order::by_pool(&store, &Side::Quote, &price, None, 10).unwrap();
+        assert_eq!(orders.len(), 2);
+
+        let orders = Order::by_pool(&store, &Side::Base, &price, None, 10).unwrap();
+        assert_eq!(orders.len(), 1);
     }
 }
@@ -0,0 +1,128 @@
+use cosmwasm_std::{
+    coin, to_json_binary, Addr, Coin, Decimal, Env, Event, QuerierWrapper, StdResult, Storage,
+    SubMsg, Uint128, WasmMsg,
+};
+use cw_storage_plus::Item;
+use rujira_rs::{
+    exchange::Swapper,
+    fin::{AuctionFill, BidderQueryMsg, ExecuteMsg, Scaled, SwapRequest},
+};
+
+use crate::{
+    config::Config, events::event_order_flow_auction, market_maker_fallback::FallbackPayload,
+    session, swap_iter::SwapIter, ContractError,
+};
+
+/// The most bidders an auction can query
+pub const MAX_BIDDERS: usize = 5;
+
+pub const ORDER_FLOW_AUCTION_REPLY_ID: u64 = 3;
+
+// The fill in progress, and the contract's balance of the ask denom before the offer was sent to the bidder
+static PENDING: Item<(AuctionFill, Coin)> = Item::new("order-flow-auction-pending");
+
+/// Auctions the market swap of `funds` to the bidders of the [rujira_rs::fin::OrderFlowAuction],
+/// when it offers at least the auction's `min_offer`. The highest bid wins if it beats the book return
+/// by `min_improvement` and meets the swap's `min_return`, and the swap is filled by the winner with
+/// [ExecuteMsg::DoAuctionFill]. A failure of the fill is caught, and the swap is matched against the
+/// book with the reply. Bidders that fail to answer are skipped
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    q: QuerierWrapper,
+    storage: &dyn Storage,
+    env: &Env,
+    config: &Config,
+    swap_iter: &SwapIter,
+    oracle: &Scaled,
+    sender: &Addr,
+    funds: &[Coin],
+    req: &SwapRequest,
+) -> Result<Option<(SubMsg, Event)>, ContractError> {
+    let (Some(auction), [offer]) = (&config.order_flow_auction, funds) else {
+        return Ok(None);
+    };
+    let min_return = match req {
+        SwapRequest::Yolo { .. } => Uint128::zero(),
+        SwapRequest::Min { min_return, .. } => *min_return,
+        _ => return Ok(None),
+    };
+    if !auction
+        .min_offer
+        .iter()
+        .any(|x| x.denom == offer.denom && offer.amount >= x.amount)
+        || session::is_auction(storage, config, env.block.time)?
+    {
+        return Ok(None);
+    }
+    let side = config.denoms.ask_side(offer)?;
+    let ask_denom = config.denoms.bid(&side);
+    let mut swapper = Swapper::new(
+        env!("CARGO_PKG_NAME"),
+        offer.amount,
+        SwapRequest::Yolo {
+            to: None,
+            callback: None,
+            max_levels: None,
+        },
+        config.fee_taker,
+    );
+    let res = swapper.swap(storage, &mut swap_iter.iter(storage, &side, oracle))?;
+    let book = res.return_amount + res.fee_amount;
+
+    let mut best: Option<(&Addr, Uint128)> = None;
+    for bidder in &auction.bidders {
+        let Ok(Some(bid)) = q.query_wasm_smart::<Option<Uint128>>(
+            bidder,
+            &BidderQueryMsg::Bid {
+                offer: offer.clone(),
+                ask_denom: ask_denom.to_string(),
+                book,
+            },
+        ) else {
+            continue;
+        };
+        if best.map_or(true, |(_, x)| bid > x) {
+            best = Some((bidder, bid));
+        }
+    }
+    let Some((bidder, bid)) = best.filter(|(_, bid)| {
+        *bid > book
+            && *bid >= book.mul_ceil(Decimal::one() + auction.min_improvement)
+            && *bid - bid.mul_ceil(config.fee_taker) >= min_return
+    }) else {
+        return Ok(None);
+    };
+
+    let fill = AuctionFill {
+        sender: sender.clone(),
+        req: req.clone(),
+        bidder: bidder.clone(),
+        bid,
+    };
+    let msg = WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        msg: to_json_binary(&ExecuteMsg::DoAuctionFill(fill))?,
+        funds: funds.to_vec(),
+    };
+    let payload: FallbackPayload = (sender.clone(), funds.to_vec(), req.clone());
+    Ok(Some((
+        SubMsg::reply_on_error(msg, ORDER_FLOW_AUCTION_REPLY_ID)
+            .with_payload(to_json_binary(&payload)?),
+        event_order_flow_auction(
+            bidder,
+            offer,
+            &coin(book.u128(), ask_denom),
+            &coin(bid.u128(), ask_denom),
+        ),
+    )))
+}
+
+pub fn save_pending(storage: &mut dyn Storage, fill: &AuctionFill, before: &Coin) -> StdResult<()> {
+    PENDING.save(storage, &(fill.clone(), before.clone()))
+}
+
+pub fn take_pending(storage: &mut dyn Storage) -> StdResult<(AuctionFill, Coin)> {
+    let pending = PENDING.load(storage)?;
+    PENDING.remove(storage);
+    Ok(pending)
+}
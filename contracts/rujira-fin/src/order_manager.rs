@@ -1,5 +1,7 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{coin, Addr, CosmosMsg, Decimal, Event, Fraction, Storage, Timestamp, Uint128};
+use cosmwasm_std::{
+    coin, Addr, Coin, CosmosMsg, Decimal, Event, Fraction, Storage, SubMsg, Timestamp, Uint128,
+};
 use cw_utils::NativeBalance;
 use rujira_rs::exchange::Swappable;
 use rujira_rs::exchange::Swapper;
@@ -9,12 +11,14 @@ use std::cmp::Ordering;
 use std::ops::{Mul, Sub};
 
 use crate::config::Config;
+use crate::fill_callback::FillCallback;
 use crate::swap_iter::SwapIter;
+use crate::trade::Trade;
 use crate::{
     events::{event_create_order, event_increase_order, event_retract_order, event_withdraw_order},
     order::Order,
     pool::Pool,
-    ContractError,
+    rounding, ContractError,
 };
 
 #[cw_serde]
@@ -31,6 +35,11 @@ pub struct OrderManager {
     fees: NativeBalance,
     events: Vec<Event>,
     messages: Vec<CosmosMsg>,
+    // Fill callbacks of withdrawn orders
+    sub_msgs: Vec<SubMsg>,
+    quote_usd: Option<Decimal>,
+    /// New orders rest on the book without crossing it, during a session window
+    auction: bool,
 }
 
 impl OrderManager {
@@ -44,9 +53,24 @@ impl OrderManager {
             fees: NativeBalance::default(),
             events: vec![],
             messages: vec![],
+            sub_msgs: vec![],
+            quote_usd: None,
+            auction: false,
         }
     }
 
+    /// Suspends the matching of new orders against the book, while a session window is unsettled
+    pub fn with_auction(mut self, auction: bool) -> Self {
+        self.auction = auction;
+        self
+    }
+
+    /// Sets the USD price of the quote asset, added to the events of trades made by new orders
+    pub fn with_quote_usd(mut self, quote_usd: Option<Decimal>) -> Self {
+        self.quote_usd = quote_usd;
+        self
+    }
+
     pub fn execute_orders(
         &mut self,
         storage: &mut dyn Storage,
@@ -54,34 +78,54 @@ impl OrderManager {
         o: Vec<(Side, Price, Option<Uint128>)>,
         oracle: &impl Premiumable,
     ) -> Result<ExecutionResult, ContractError> {
+        // What has been spent after each order, to find the order that caused a shortfall
+        let mut sent = vec![];
         for (side, price, target) in o {
             if let Price::Fixed(x) = price {
-                self.config.tick.validate_price(&x)?;
+                self.config
+                    .tick
+                    .validate_price_scaled(&x, &self.config.decimal_delta)?;
             }
             let mut pool = Pool::load(storage, &price, &side, oracle);
             match pool.load_order(storage, &self.owner) {
-                Ok(mut order) => {
-                    self.execute_existing_order(storage, &mut pool, &mut order, &side, target)?
-                }
+                Ok(mut order) => self.execute_existing_order(
+                    storage, &mut pool, &mut order, &side, target, oracle,
+                )?,
                 Err(ContractError::NotFound {}) => {
                     self.execute_new_order(storage, swap_iter, &mut pool, &side, target, oracle)?
                 }
                 Err(err) => return Err(err),
             }
+            sent.push(self.send.clone());
         }
         self.send.normalize();
         self.receive.normalize();
+        // Reconciled once every order has executed, as a retraction later in the batch can fund
+        // an earlier increase
         for x in self.send.clone().into_vec() {
             self.receive =
-                (self.receive.clone() - x).map_err(|_| ContractError::InsufficientFunds {
-                    required: self.send.clone(),
-                    available: self.receive.clone(),
-                })?;
+                (self.receive.clone() - x.clone()).map_err(|_| self.shortfall(&sent, x))?;
         }
 
         Ok(self.into())
     }
 
+    /// The shortfall of `required` in what has been received, attributed to the first order to
+    /// spend more of its denom than is available
+    fn shortfall(&self, sent: &[NativeBalance], required: Coin) -> ContractError {
+        let available = amount_of(&self.receive, &required.denom);
+        let index = sent
+            .iter()
+            .position(|x| amount_of(x, &required.denom) > available)
+            .unwrap_or_default();
+        ContractError::InsufficientFunds {
+            index,
+            available: coin(available.u128(), &required.denom),
+            shortfall: coin((required.amount - available).u128(), &required.denom),
+            required,
+        }
+    }
+
     fn execute_existing_order(
         &mut self,
         storage: &mut dyn Storage,
@@ -89,12 +133,19 @@ impl OrderManager {
         order: &mut Order,
         side: &Side,
         target: Option<Uint128>,
+        oracle: &impl Premiumable,
     ) -> Result<(), ContractError> {
         self.maybe_withdraw(storage, pool, order)?;
         if let Some(target) = target {
             let amount = Uint128::try_from(order.bid.amount()).unwrap();
-            match amount.cmp(&target) {
+            // Dust left by fills isn't worth a retraction or increase. Retracting in full always is
+            let ordering = match amount.abs_diff(target) <= self.config.target_epsilon {
+                true if !target.is_zero() => Ordering::Equal,
+                _ => amount.cmp(&target),
+            };
+            match ordering {
                 Ordering::Less => {
+                    self.validate_premium(&pool.price, oracle)?;
                     let diff = target - amount;
 
                     let amount = pool.increase_order(storage, order, &self.timestamp, diff)?;
@@ -116,6 +167,40 @@ impl OrderManager {
         Ok(())
     }
 
+    /// Oracle premiums are only bounded when placing or increasing an order, so that
+    /// orders placed before a bounds change, or during an oracle outage, can still be retracted
+    fn validate_premium(
+        &self,
+        price: &Price,
+        oracle: &impl Premiumable,
+    ) -> Result<(), ContractError> {
+        if !matches!(price, Price::Fixed(_)) && !oracle.available() {
+            return Err(ContractError::Invalid(
+                "oracle price unavailable".to_string(),
+            ));
+        }
+        match price {
+            Price::Oracle(bps) => self.validate_bps(bps),
+            Price::Decay(decay) => {
+                self.validate_bps(&decay.from)?;
+                self.validate_bps(&decay.to)
+            }
+            Price::Fixed(_) => Ok(()),
+        }
+    }
+
+    fn validate_bps(&self, bps: &i16) -> Result<(), ContractError> {
+        let bounds = &self.config.premium_bounds;
+        match bounds.contains(bps) {
+            true => Ok(()),
+            false => Err(ContractError::PremiumOutOfBounds {
+                premium: *bps,
+                min: bounds.min,
+                max: bounds.max,
+            }),
+        }
+    }
+
     fn execute_new_order(
         &mut self,
         storage: &mut dyn Storage,
@@ -126,6 +211,13 @@ impl OrderManager {
         oracle: &impl Premiumable,
     ) -> Result<(), ContractError> {
         if let Some(target) = target {
+            self.validate_premium(&pool.price, oracle)?;
+            if self.auction {
+                let order = pool.create_order(storage, &self.timestamp, &self.owner, target)?;
+                self.send += coin(order.amount().u128(), self.config.denoms.bid(side));
+                self.events.push(event_create_order(pool, &order));
+                return Ok(());
+            }
             let opposite = side.other();
             let mut swapper = Swapper::new(
                 env!("CARGO_PKG_NAME"),
@@ -135,19 +227,26 @@ impl OrderManager {
                         Side::Base => pool.rate(),
                         Side::Quote => pool.rate().inv().unwrap(),
                     },
+                    min_fill: None,
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
                 self.config.fee_taker,
-            );
+            )
+            .with_quote_usd(self.quote_usd);
             let mut swap = {
                 let mut iter = swap_iter.iter(storage, &opposite, oracle);
-                swapper.swap(&mut iter)?
+                swapper.swap(storage, &mut iter)?
             };
+            rounding::check_fee(&swap, self.config.fee_taker)?;
             let order =
                 pool.create_order(storage, &self.timestamp, &self.owner, swap.remaining_offer)?;
             if !swap.return_amount.is_zero() {
                 let commit = swapper.commit(storage)?;
+                if let Some(rate) = swap.last_rate {
+                    Trade::new(rate, self.timestamp).save(storage)?;
+                }
                 self.events.append(&mut swap.events);
                 self.messages
                     .append(&mut commit.to_msgs(&self.config.denoms, &opposite)?);
@@ -172,6 +271,10 @@ impl OrderManager {
         if order.bid.filled().is_zero() {
             return Ok(());
         }
+        let filled = Uint128::try_from(order.bid.filled())?;
+        if let Some(msg) = FillCallback::claim(storage, pool, order, filled, self.timestamp)? {
+            self.sub_msgs.push(msg);
+        }
         let amount = pool.claim_order(storage, order)?;
         let fees = Decimal::from_ratio(amount, 1u128)
             .mul(self.config.fee_maker)
@@ -196,6 +299,7 @@ impl From<&mut OrderManager> for ExecutionResult {
             fees: e.fees.clone(),
             events: e.events.clone(),
             messages: e.messages.clone(),
+            sub_msgs: e.sub_msgs.clone(),
         }
     }
 }
@@ -206,6 +310,16 @@ pub struct ExecutionResult {
     pub fees: NativeBalance,
     pub events: Vec<Event>,
     pub messages: Vec<CosmosMsg>,
+    pub sub_msgs: Vec<SubMsg>,
+}
+
+fn amount_of(balance: &NativeBalance, denom: &str) -> Uint128 {
+    balance
+        .0
+        .iter()
+        .find(|x| x.denom == denom)
+        .map(|x| x.amount)
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -219,7 +333,7 @@ mod tests {
         coins,
         testing::{message_info, mock_dependencies, mock_env},
     };
-    use rujira_rs::fin::{Denoms, Price, Tick};
+    use rujira_rs::fin::{DecimalDelta, Denoms, OracleFallback, PremiumBounds, Price, Tick};
     use std::str::FromStr;
 
     #[test]
@@ -239,6 +353,20 @@ mod tests {
             fee_maker: Decimal::from_str("0.001").unwrap(),
             fee_taker: Decimal::from_str("0.002").unwrap(),
             fee_address: Addr::unchecked(""),
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
         };
         let swap_iter = SwapIter::new(mut_deps.querier, &config);
         let mut e = OrderManager::new(&config, info.sender, env.block.time, funds);
@@ -288,6 +416,20 @@ mod tests {
             fee_maker: Decimal::from_str("0.001").unwrap(),
             fee_taker: Decimal::from_str("0.002").unwrap(),
             fee_address: Addr::unchecked(""),
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
         };
         let swap_iter = SwapIter::new(mut_deps.querier, &config);
 
@@ -383,6 +525,20 @@ mod tests {
             fee_maker: Decimal::from_str("0.001").unwrap(),
             fee_taker: Decimal::from_str("0.002").unwrap(),
             fee_address: Addr::unchecked(""),
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
         };
         let swap_iter = SwapIter::new(mut_deps.querier, &config);
 
@@ -401,6 +557,79 @@ mod tests {
         .unwrap_err();
     }
 
+    #[test]
+    fn test_shortfall() {
+        let mut deps = mock_dependencies();
+        let mut_deps = deps.as_mut();
+
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+
+        let oracle = Decimal::from_str("1.0").unwrap();
+        let funds = NativeBalance(coins(1500, "usdc"));
+        let config = Config {
+            denoms: Denoms::new("ruji", "usdc"),
+            oracles: None,
+            market_makers: MarketMakers::new(mut_deps.api, vec![]).unwrap(),
+            tick: Tick::new(4),
+            fee_maker: Decimal::from_str("0.001").unwrap(),
+            fee_taker: Decimal::from_str("0.002").unwrap(),
+            fee_address: Addr::unchecked(""),
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
+        };
+        let swap_iter = SwapIter::new(mut_deps.querier, &config);
+
+        let mut e = OrderManager::new(&config, info.sender, env.block.time, funds);
+
+        let err = e
+            .execute_orders(
+                mut_deps.storage,
+                &swap_iter,
+                vec![
+                    (
+                        Side::Quote,
+                        Price::Fixed(Decimal::from_str("1.0").unwrap()),
+                        Some(Uint128::from(1000u128)),
+                    ),
+                    (
+                        Side::Quote,
+                        Price::Fixed(Decimal::from_str("1.1").unwrap()),
+                        Some(Uint128::from(1000u128)),
+                    ),
+                ],
+                &oracle,
+            )
+            .unwrap_err();
+
+        match err {
+            ContractError::InsufficientFunds {
+                index,
+                required,
+                available,
+                shortfall,
+            } => {
+                assert_eq!(index, 1);
+                assert_eq!(required, coin(2000, "usdc"));
+                assert_eq!(available, coin(1500, "usdc"));
+                assert_eq!(shortfall, coin(500, "usdc"));
+            }
+            err => panic!("unexpected error {err:?}"),
+        }
+    }
+
     #[test]
     fn test_moving_orders() {
         let mut deps = mock_dependencies();
@@ -421,6 +650,20 @@ mod tests {
             fee_maker: Decimal::from_str("0.001").unwrap(),
             fee_taker: Decimal::from_str("0.002").unwrap(),
             fee_address: Addr::unchecked(""),
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
         };
         let swap_iter = SwapIter::new(mut_deps.querier, &config);
 
@@ -602,6 +845,20 @@ mod tests {
             fee_maker: Decimal::from_str("0.001").unwrap(),
             fee_taker: Decimal::from_str("0.002").unwrap(),
             fee_address: Addr::unchecked(""),
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
         };
         let swap_iter = SwapIter::new(mut_deps.querier, &config);
         let mut e = OrderManager::new(&config, info.sender, env.block.time, funds);
@@ -618,4 +875,143 @@ mod tests {
         )
         .unwrap_err();
     }
+
+    #[test]
+    fn test_premium_bounds() {
+        let mut deps = mock_dependencies();
+        let mut_deps = deps.as_mut();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let oracle = Decimal::from_str("1.0").unwrap();
+        let mut funds = NativeBalance::default();
+        funds += coin(2000, "usdc");
+        let config = Config {
+            denoms: Denoms::new("ruji", "usdc"),
+            oracles: None,
+            market_makers: MarketMakers::new(mut_deps.api, vec![]).unwrap(),
+            tick: Tick::new(4),
+            fee_maker: Decimal::from_str("0.001").unwrap(),
+            fee_taker: Decimal::from_str("0.002").unwrap(),
+            fee_address: Addr::unchecked(""),
+            premium_bounds: PremiumBounds::new(-500, 500),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::zero(),
+            order_flow_auction: None,
+        };
+        let swap_iter = SwapIter::new(mut_deps.querier, &config);
+        let mut e = OrderManager::new(&config, info.sender, env.block.time, funds);
+
+        let err = e
+            .execute_orders(
+                mut_deps.storage,
+                &swap_iter,
+                vec![(
+                    Side::Quote,
+                    Price::Oracle(-1000),
+                    Some(Uint128::from(1000u128)),
+                )],
+                &oracle,
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::PremiumOutOfBounds {
+                premium: -1000,
+                min: -500,
+                max: 500
+            }
+        ));
+
+        e.execute_orders(
+            mut_deps.storage,
+            &swap_iter,
+            vec![(
+                Side::Quote,
+                Price::Oracle(-500),
+                Some(Uint128::from(1000u128)),
+            )],
+            &oracle,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_target_epsilon() {
+        let mut deps = mock_dependencies();
+        let mut_deps = deps.as_mut();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        let oracle = Decimal::from_str("1.0").unwrap();
+        let mut funds = NativeBalance::default();
+        funds += coin(1000, "usdc");
+        let config = Config {
+            denoms: Denoms::new("ruji", "usdc"),
+            oracles: None,
+            market_makers: MarketMakers::new(mut_deps.api, vec![]).unwrap(),
+            tick: Tick::new(4),
+            fee_maker: Decimal::from_str("0.001").unwrap(),
+            fee_taker: Decimal::from_str("0.002").unwrap(),
+            fee_address: Addr::unchecked(""),
+            premium_bounds: PremiumBounds::default(),
+            trusted_callers: vec![],
+            decimal_delta: DecimalDelta::default(),
+            wrap: None,
+            sweep_bounty: None,
+            session: None,
+            oracle_fallback: OracleFallback::default(),
+            fee_conversion: None,
+            fee_address_base: None,
+            fee_address_quote: None,
+            aux_quotes: vec![],
+            market_maker_limit: None,
+            target_epsilon: Uint128::from(2u128),
+            order_flow_auction: None,
+        };
+        let swap_iter = SwapIter::new(mut_deps.querier, &config);
+        let mut execute = |price: &str, target: u128, funds: NativeBalance| {
+            OrderManager::new(&config, info.sender.clone(), env.block.time, funds)
+                .execute_orders(
+                    mut_deps.storage,
+                    &swap_iter,
+                    vec![(
+                        Side::Quote,
+                        Price::Fixed(Decimal::from_str(price).unwrap()),
+                        Some(Uint128::from(target)),
+                    )],
+                    &oracle,
+                )
+                .unwrap()
+        };
+        execute("1.0", 1000, funds);
+
+        // Within the epsilon of the target, in either direction
+        let res = execute("1.0", 998, NativeBalance::default());
+        assert!(res.events.is_empty());
+        assert_eq!(res.withdraw, NativeBalance::default());
+        let res = execute("1.0", 1002, NativeBalance::default());
+        assert!(res.events.is_empty());
+
+        // Beyond it
+        let res = execute("1.0", 997, NativeBalance::default());
+        assert_eq!(res.events[0].ty, "rujira-fin/order.retract");
+        assert_eq!(res.withdraw, NativeBalance(coins(3, "usdc")));
+        let res = execute("1.0", 1000, NativeBalance(coins(3, "usdc")));
+        assert_eq!(res.events[0].ty, "rujira-fin/order.increase");
+
+        // A target of zero retracts an order in full, even one within the epsilon
+        execute("0.9", 2, NativeBalance(coins(2, "usdc")));
+        let res = execute("0.9", 0, NativeBalance::default());
+        assert_eq!(res.events[0].ty, "rujira-fin/order.retract");
+        assert_eq!(res.withdraw, NativeBalance(coins(2, "usdc")));
+    }
 }
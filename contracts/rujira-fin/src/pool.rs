@@ -2,7 +2,7 @@ use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
     Addr, Attribute, Decimal, Decimal256, Fraction, StdResult, Storage, Timestamp, Uint128, Uint256,
 };
-use cw_storage_plus::Map;
+use cw_storage_plus::{Bound, Map};
 use itertools::{EitherOrBoth, Itertools};
 use rujira_rs::{
     bid_pool::{self, SumSnapshot},
@@ -14,13 +14,18 @@ use rujira_rs::{
 use crate::{
     order::Order,
     pool_key::{PoolKey, PoolType},
-    ContractError,
+    rounding, stats, ContractError,
 };
 const SNAPSHOTS: Map<(PoolKey, bid_pool::SumSnapshotKey), DecimalScaled> = Map::new("snapshots");
+// The number of orders whose bid references each sum snapshot. A snapshot is only read when syncing
+// an order of its epoch, so it's removed along with its count once that reaches zero
+const SNAPSHOT_ORDERS: Map<(PoolKey, bid_pool::SumSnapshotKey), u32> = Map::new("snapshot-orders");
 // The POOLS Map is used simply as an indicator that there is a non-zero BidPool at this key
 // The BID_POOLS Map is used to store the BidPool itself, and the Key is used to populate the Pool values
 const POOLS: Map<PoolKey, ()> = Map::new("pools");
 const BID_POOLS: Map<PoolKey, bid_pool::Pool> = Map::new("bid-pools");
+/// The maximum number of orders attributed in the trade event of a fully consumed pool
+pub const MAX_MAKERS: usize = 10;
 
 /// A wrapper around a BidPool to provide a side & price, used for keying orders and
 /// storing pools for iterating during execution
@@ -32,6 +37,9 @@ pub struct Pool {
     rate: Decimal,
     #[serde(skip)]
     pending_sum_snapshots: Vec<SumSnapshot>,
+    /// Offer distributed to the orders of the pool by swaps not yet committed
+    #[serde(skip)]
+    pending_filled: Uint128,
 }
 
 impl Pool {
@@ -44,38 +52,60 @@ impl Pool {
             Side::Base => cosmwasm_std::Order::Ascending,
             Side::Quote => cosmwasm_std::Order::Descending,
         };
+        // Oracle priced pools are frozen while the oracle is unavailable
+        let available = oracle.available();
 
-        let populate = |x: StdResult<(Price, ())>| -> Option<Self> {
-            match x {
-                Ok((price, _)) => Some(Self {
-                    price: price.clone(),
-                    side: side.clone(),
-                    rate: price.to_rate(oracle),
-                    // The presence of the key indicates a BidPool should be present,
-                    // so we should panic if this is incorrect
-                    pool: BID_POOLS
-                        .load(storage, PoolKey::new(side.clone(), price))
-                        .unwrap(),
-                    pending_sum_snapshots: vec![],
-                }),
-                Err(_) => None,
-            }
+        // Pools are merged by their price and rate, each rate converted once, and only loaded
+        // from storage once yielded
+        let keys = move |pool_type: PoolType| {
+            let frozen = pool_type != PoolType::Fixed && !available;
+            POOLS
+                .prefix((side.clone(), pool_type))
+                .keys(storage, None, None, order)
+                .filter(move |_| !frozen)
+                .filter_map(|x| x.ok())
+                .map(move |price| {
+                    let rate = price.to_rate(oracle);
+                    (price, rate)
+                })
         };
 
-        let fixed = POOLS
-            .prefix((side.clone(), PoolType::Fixed))
-            .range(storage, None, None, order)
-            .filter_map(populate);
-
-        let oracle = POOLS
-            .prefix((side.clone(), PoolType::Oracle))
-            .range(storage, None, None, order)
-            .filter_map(populate);
+        // Decay pool rates move with the block time rather than with their key, so they're
+        // sorted by rate, and merged with the other oracle pools
+        let mut decay: Vec<(Price, Decimal)> = keys(PoolType::Decay).collect();
+        decay.sort_by(|a, b| match side {
+            Side::Base => a.1.cmp(&b.1),
+            Side::Quote => b.1.cmp(&a.1),
+        });
+
+        let oracle = keys(PoolType::Oracle).merge_by(decay, move |o, d| match side {
+            Side::Base => o.1 <= d.1,
+            Side::Quote => o.1 >= d.1,
+        });
+
+        let load = move |(price, rate): (Price, Decimal)| Self::loaded(storage, side, price, rate);
+        keys(PoolType::Fixed)
+            .merge_join_by(oracle, move |f, o| match side {
+                Side::Base => f.1.cmp(&o.1),
+                Side::Quote => o.1.cmp(&f.1),
+            })
+            .map(move |x| x.map_any(load, load))
+    }
 
-        fixed.merge_join_by(oracle, move |f, o| match side {
-            Side::Base => f.rate().cmp(&o.rate()),
-            Side::Quote => o.rate().cmp(&f.rate()),
-        })
+    /// Loads the pool of a key yielded by [Pool::iter]
+    fn loaded(storage: &dyn Storage, side: &Side, price: Price, rate: Decimal) -> Self {
+        Self {
+            // The presence of the key indicates a BidPool should be present,
+            // so we should panic if this is incorrect
+            pool: BID_POOLS
+                .load(storage, PoolKey::new(side.clone(), price.clone()))
+                .unwrap(),
+            price,
+            side: side.clone(),
+            rate,
+            pending_sum_snapshots: vec![],
+            pending_filled: Uint128::zero(),
+        }
     }
 
     pub fn load(
@@ -85,13 +115,182 @@ impl Pool {
         oracle: &impl Premiumable,
     ) -> Self {
         let key = PoolKey::new(side.clone(), price.clone());
+        // An oracle priced pool has no rate while the oracle is unavailable. Its orders can still
+        // be retracted and withdrawn, and it's excluded from [Pool::iter]
+        let rate = match price {
+            Price::Fixed(_) => price.to_rate(oracle),
+            _ if oracle.available() => price.to_rate(oracle),
+            _ => Decimal::zero(),
+        };
         Self {
             price: price.clone(),
-            rate: price.to_rate(oracle),
+            rate,
             side: side.clone(),
             pool: BID_POOLS.load(storage, key).unwrap_or_default(),
             pending_sum_snapshots: vec![],
+            pending_filled: Uint128::zero(),
+        }
+    }
+
+    /// Swaps against the pool at `rate` rather than at the rate of its price
+    pub fn at_rate(mut self, rate: Decimal) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// The orders resting in the pool before it was swapped against, and their remaining offer. The
+    /// pool in storage is still that before the swap until it's committed
+    fn makers(&self, storage: &dyn Storage) -> Vec<(Addr, Uint128)> {
+        // Only used for trade events, so a failure to load doesn't block the swap
+        let Ok(pool) = BID_POOLS.load(storage, self.key()) else {
+            return vec![];
+        };
+        let before = Self {
+            pool,
+            ..self.clone()
+        };
+        Order::by_pool(storage, &self.side, &self.price, None, MAX_MAKERS)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|mut order| {
+                before.sync_order(storage, &mut order).ok()?;
+                Some((order.owner.clone(), order.amount()))
+            })
+            .filter(|(_, amount)| !amount.is_zero())
+            .collect()
+    }
+
+    /// Removes up to `limit` sum snapshots of the pool at `side` and `price`, and the pool itself once
+    /// none remain. Only pools that are empty and have no orders are swept, as nothing can reach
+    /// their storage. Returns the number of entries removed
+    pub fn sweep(
+        storage: &mut dyn Storage,
+        side: &Side,
+        price: &Price,
+        limit: usize,
+    ) -> StdResult<usize> {
+        let key = PoolKey::new(side.clone(), price.clone());
+        let pool = BID_POOLS.may_load(storage, key.clone())?;
+        if pool.as_ref().is_some_and(|x| !x.is_zero())
+            || POOLS.has(storage, key.clone())
+            || !Order::by_pool(storage, side, price, None, 1)?.is_empty()
+        {
+            return Ok(0);
         }
+        let snapshots = SNAPSHOTS
+            .prefix(key.clone())
+            .keys(storage, None, None, cosmwasm_std::Order::Ascending)
+            .take(limit + 1)
+            .collect::<StdResult<Vec<_>>>()?;
+        let mut removed = 0;
+        for snapshot in snapshots.iter().take(limit) {
+            SNAPSHOTS.remove(storage, (key.clone(), snapshot.clone()));
+            removed += 1;
+        }
+        if pool.is_some() && snapshots.len() <= limit && removed < limit {
+            BID_POOLS.remove(storage, key);
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// Moves the reference of an order from the sum snapshot of the bid it was stored with, `from`,
+    /// to that of the bid it's stored with now, `to`. `None` when it wasn't stored, or is removed
+    pub fn reference_snapshot(
+        &self,
+        storage: &mut dyn Storage,
+        from: Option<&bid_pool::Bid>,
+        to: Option<&bid_pool::Bid>,
+    ) -> StdResult<()> {
+        let from = from.map(|x| x.sum_snapshot_key());
+        let to = to.map(|x| x.sum_snapshot_key());
+        if from == to {
+            return Ok(());
+        }
+        if let Some(to) = to {
+            SNAPSHOT_ORDERS.update(storage, (self.key(), to), |x| -> StdResult<_> {
+                Ok(x.unwrap_or_default() + 1)
+            })?;
+        }
+        let Some(from) = from else {
+            return Ok(());
+        };
+        let key = (self.key(), from);
+        match SNAPSHOT_ORDERS.may_load(storage, key.clone())? {
+            // Any later distribution rewrites the snapshot of the pool's current epoch, so the
+            // snapshot can be removed whatever its epoch
+            Some(1) => {
+                SNAPSHOT_ORDERS.remove(storage, key.clone());
+                SNAPSHOTS.remove(storage, key);
+            }
+            Some(n) => SNAPSHOT_ORDERS.save(storage, key, &(n - 1))?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Adds the offer resting in a page of pools to the totals of their side, recounts the orders
+    /// referencing each of their sum snapshots, and removes the snapshots of past epochs that none
+    /// reference. Orders must be indexed by pool first. Returns the key of the last pool when the
+    /// page is full
+    pub fn backfill(
+        storage: &mut dyn Storage,
+        start_after: Option<(Side, Price)>,
+        limit: usize,
+    ) -> Result<Option<(Side, Price)>, ContractError> {
+        let pools = BID_POOLS
+            .range(
+                storage,
+                start_after.map(|(side, price)| Bound::exclusive(PoolKey::new(side, price))),
+                None,
+                cosmwasm_std::Order::Ascending,
+            )
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+        for (key, pool) in pools.iter() {
+            stats::record_resting(
+                storage,
+                &key.side,
+                Uint128::zero(),
+                pool.total().try_into()?,
+            )?;
+
+            let mut counts: Vec<(bid_pool::SumSnapshotKey, u32)> = vec![];
+            for order in Order::by_pool(storage, &key.side, &key.price, None, usize::MAX)? {
+                let snapshot = order.bid.sum_snapshot_key();
+                match counts.iter_mut().find(|(k, _)| *k == snapshot) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push((snapshot, 1)),
+                }
+            }
+            // The counts are overwritten rather than incremented, so a repeated backfill is harmless
+            let stale = SNAPSHOT_ORDERS
+                .prefix(key.clone())
+                .keys(storage, None, None, cosmwasm_std::Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?;
+            for snapshot in stale {
+                SNAPSHOT_ORDERS.remove(storage, (key.clone(), snapshot));
+            }
+            for (snapshot, n) in counts.iter() {
+                SNAPSHOT_ORDERS.save(storage, (key.clone(), snapshot.clone()), n)?;
+            }
+
+            let current = bid_pool::SumSnapshotKey::new(pool.epoch());
+            let snapshots = SNAPSHOTS
+                .prefix(key.clone())
+                .keys(storage, None, None, cosmwasm_std::Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?;
+            for snapshot in snapshots {
+                if snapshot == current || counts.iter().any(|(k, _)| *k == snapshot) {
+                    continue;
+                }
+                SNAPSHOTS.remove(storage, (key.clone(), snapshot));
+            }
+        }
+        Ok(pools
+            .last()
+            .filter(|_| pools.len() == limit)
+            .map(|(k, _)| (k.side.clone(), k.price.clone())))
     }
 
     fn key(&self) -> PoolKey {
@@ -158,9 +357,10 @@ impl Pool {
         storage: &mut dyn Storage,
         order: &mut Order,
     ) -> Result<Uint128, ContractError> {
-        let claimed = order.bid.claim_filled();
+        let claimed = Uint128::try_from(order.bid.claim_filled())?;
         order.save(storage, self)?;
-        Ok(Uint128::try_from(claimed)?)
+        stats::record_claim(storage, &self.side, claimed)?;
+        Ok(claimed)
     }
 
     pub fn sync_order(
@@ -191,18 +391,30 @@ impl Swappable for Pool {
             .pool
             .distribute(offer.into(), &Decimal256::from(rate))?;
         self.pending_sum_snapshots = res.snapshots;
+        let consumed = res.consumed_offer.try_into()?;
+        let returned = res.consumed_bids.try_into()?;
+        rounding::check_fill(consumed, returned, rate)?;
+        self.pending_filled += consumed;
 
-        Ok((
-            res.consumed_offer.try_into()?,
-            res.consumed_bids.try_into()?,
-        ))
+        Ok((consumed, returned))
     }
 
     fn commit(&self, storage: &mut dyn Storage) -> Result<Commitment, SwapError> {
         for s in self.pending_sum_snapshots.clone() {
             SNAPSHOTS.save(storage, (self.key(), s.key()), &s.sum)?;
         }
-
+        stats::record_fill(storage, &self.side, self.pending_filled)?;
+
+        let before = BID_POOLS
+            .may_load(storage, self.key())?
+            .map(|x| x.total())
+            .unwrap_or_default();
+        stats::record_resting(
+            storage,
+            &self.side,
+            before.try_into()?,
+            self.pool.total().try_into()?,
+        )?;
         BID_POOLS.save(storage, self.key(), &self.pool)?;
         // Clear empty pools so they're not iterated over during a swap
         if self.pool.is_zero() {
@@ -214,11 +426,21 @@ impl Swappable for Pool {
         Ok(Commitment::default())
     }
 
-    fn attributes(&self) -> Vec<Attribute> {
-        vec![
+    /// Attributes each order consumed by the trade to its owner, when it consumes the whole pool. The
+    /// makers are only loaded then
+    fn attributes(&self, storage: &dyn Storage) -> Vec<Attribute> {
+        let mut attrs = vec![
             Attribute::new("price", self.price.to_string()),
             Attribute::new("side", self.side.to_string()),
-        ]
+        ];
+        if self.pool.is_zero() {
+            attrs.extend(
+                self.makers(storage)
+                    .iter()
+                    .map(|(owner, amount)| Attribute::new("maker", format!("{owner}:{amount}"))),
+            );
+        }
+        attrs
     }
 
     fn rate(&self) -> Decimal {
@@ -238,7 +460,8 @@ mod tests {
     use cosmwasm_std::{testing::MockStorage, Decimal};
     use cw_storage_plus::Bound;
     use itertools::EitherOrBoth;
-    use rujira_rs::fin::{Price, Side};
+    use proptest::prelude::*;
+    use rujira_rs::fin::{Decay, DecimalDelta, Price, Scaled, Side};
     use std::str::FromStr;
 
     #[test]
@@ -274,6 +497,77 @@ mod tests {
         assert_eq!(pool.pool, bp);
     }
 
+    #[test]
+    fn snapshot_pruning() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let owner = Addr::unchecked("owner");
+        let owner2 = Addr::unchecked("owner2");
+        let offer = Uint128::from(100u128);
+        let price = Price::Fixed(Decimal::one());
+        let oracle = Decimal::one();
+        let k = PoolKey::new(Side::Quote, price.clone());
+        let snapshot = |epoch: u32| (k.clone(), bid_pool::SumSnapshotKey::new(epoch));
+        let mut pool = Pool::load(&store, &price, &Side::Quote, &oracle);
+        pool.create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+        pool.create_order(&mut store, &timestamp, &owner2, offer)
+            .unwrap();
+        assert_eq!(SNAPSHOT_ORDERS.load(&store, snapshot(0)).unwrap(), 2);
+
+        // Emptying the pool moves it to the next epoch
+        pool.swap(Uint128::from(200u128)).unwrap();
+        pool.commit(&mut store).unwrap();
+        SNAPSHOTS.load(&store, snapshot(0)).unwrap();
+        SNAPSHOTS.load(&store, snapshot(1)).unwrap();
+
+        // The snapshot is kept until no order references it
+        let mut order = pool.load_order(&store, &owner).unwrap();
+        assert_eq!(pool.claim_order(&mut store, &mut order).unwrap(), offer);
+        assert_eq!(SNAPSHOT_ORDERS.load(&store, snapshot(0)).unwrap(), 1);
+        SNAPSHOTS.load(&store, snapshot(0)).unwrap();
+
+        let mut order = pool.load_order(&store, &owner2).unwrap();
+        assert_eq!(pool.claim_order(&mut store, &mut order).unwrap(), offer);
+        SNAPSHOT_ORDERS.load(&store, snapshot(0)).unwrap_err();
+        SNAPSHOTS.load(&store, snapshot(0)).unwrap_err();
+        SNAPSHOTS.load(&store, snapshot(1)).unwrap();
+    }
+
+    #[test]
+    fn snapshot_backfill() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let offer = Uint128::from(100u128);
+        let price = Price::Fixed(Decimal::one());
+        let oracle = Decimal::one();
+        let k = PoolKey::new(Side::Quote, price.clone());
+        let snapshot = |epoch: u32| (k.clone(), bid_pool::SumSnapshotKey::new(epoch));
+        let mut pool = Pool::load(&store, &price, &Side::Quote, &oracle);
+        pool.create_order(&mut store, &timestamp, &Addr::unchecked("owner"), offer)
+            .unwrap();
+        pool.swap(offer).unwrap();
+        pool.commit(&mut store).unwrap();
+        pool.create_order(&mut store, &timestamp, &Addr::unchecked("owner2"), offer)
+            .unwrap();
+        pool.commit(&mut store).unwrap();
+
+        // Counts left over from a previous migration, and a snapshot no order references
+        SNAPSHOT_ORDERS.save(&mut store, snapshot(0), &3).unwrap();
+        SNAPSHOT_ORDERS.save(&mut store, snapshot(1), &5).unwrap();
+        SNAPSHOTS
+            .save(&mut store, snapshot(7), &DecimalScaled::default())
+            .unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(Pool::backfill(&mut store, None, 10).unwrap(), None);
+            assert_eq!(SNAPSHOT_ORDERS.load(&store, snapshot(0)).unwrap(), 1);
+            assert_eq!(SNAPSHOT_ORDERS.load(&store, snapshot(1)).unwrap(), 1);
+            SNAPSHOTS.load(&store, snapshot(0)).unwrap();
+            SNAPSHOTS.load(&store, snapshot(7)).unwrap_err();
+        }
+    }
+
     #[test]
     fn pools_map() {
         let mut store = MockStorage::new();
@@ -574,4 +868,91 @@ mod tests {
             Decimal::from_ratio(10002u128, 10000u128)
         );
     }
+
+    #[test]
+    fn decay_pools() {
+        let mut store = MockStorage::new();
+        let timestamp = Timestamp::default();
+        let owner = Addr::unchecked("owner");
+        let offer = Uint128::from(100u128);
+        let decay = Price::Decay(Decay {
+            from: -50,
+            to: 0,
+            start: 100,
+            duration: 100,
+        });
+        let oracle = |seconds: u64| Scaled {
+            oracle: Decimal::one(),
+            decimal_delta: DecimalDelta::default(),
+            time: Timestamp::from_seconds(seconds),
+        };
+
+        Pool::load(&store, &Price::Oracle(-10), &Side::Quote, &oracle(0))
+            .create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+        Pool::load(&store, &decay, &Side::Quote, &oracle(0))
+            .create_order(&mut store, &timestamp, &owner, offer)
+            .unwrap();
+
+        let pool = Pool::load(&store, &decay, &Side::Quote, &oracle(0));
+        assert_eq!(pool.rate(), Decimal::from_str("0.995").unwrap());
+        let pool = Pool::load(&store, &decay, &Side::Quote, &oracle(150));
+        assert_eq!(pool.rate(), Decimal::from_str("0.9975").unwrap());
+        let pool = Pool::load(&store, &decay, &Side::Quote, &oracle(1000));
+        assert_eq!(pool.rate(), Decimal::one());
+
+        // Before decaying past the oracle pool, the decay pool is the worse bid
+        let prices: Vec<Price> = Pool::iter(&store, &Side::Quote, &oracle(150))
+            .map(|x| x.right().unwrap().price)
+            .collect();
+        assert_eq!(prices, vec![Price::Oracle(-10), decay.clone()]);
+
+        let prices: Vec<Price> = Pool::iter(&store, &Side::Quote, &oracle(190))
+            .map(|x| x.right().unwrap().price)
+            .collect();
+        assert_eq!(prices, vec![decay.clone(), Price::Oracle(-10)]);
+
+        let order = Pool::load(&store, &decay, &Side::Quote, &oracle(190))
+            .load_order(&store, &owner)
+            .unwrap();
+        assert_eq!(order.offer, offer);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            cases: 1000,
+            ..Default::default()
+        })]
+        #[test]
+        fn swap_conserves_value(
+            permille in 1u64..100_000,
+            base in any::<bool>(),
+            orders in prop::collection::vec(1u128..1_000_000_000, 1..5),
+            offers in prop::collection::vec(1u128..1_000_000_000, 1..5),
+        ) {
+            let mut store = MockStorage::new();
+            let timestamp = Timestamp::default();
+            let price = Price::Fixed(Decimal::permille(permille));
+            let side = if base { Side::Base } else { Side::Quote };
+            let oracle = Decimal::one();
+            for (i, offer) in orders.iter().enumerate() {
+                let mut pool = Pool::load(&store, &price, &side, &oracle);
+                pool.create_order(&mut store, &timestamp, &Addr::unchecked(format!("owner{i}")), Uint128::new(*offer))
+                    .unwrap();
+                pool.commit(&mut store).unwrap();
+            }
+
+            let mut pool = Pool::load(&store, &price, &side, &oracle);
+            let rate = match side {
+                Side::Base => pool.rate().inv().unwrap(),
+                Side::Quote => pool.rate(),
+            };
+            for offer in offers {
+                let (consumed, returned) = pool.swap(Uint128::new(offer)).unwrap();
+                prop_assert!(consumed <= Uint128::new(offer));
+                prop_assert!(rounding::conserves(consumed, returned, rate));
+                pool.commit(&mut store).unwrap();
+            }
+        }
+    }
 }
@@ -7,6 +7,7 @@ use rujira_rs::fin::{Price, Side};
 pub enum PoolType {
     Fixed,
     Oracle,
+    Decay,
 }
 
 impl PrimaryKey<'_> for PoolType {
@@ -19,6 +20,7 @@ impl PrimaryKey<'_> for PoolType {
         match self {
             PoolType::Fixed => vec![Key::Val8([0])],
             PoolType::Oracle => vec![Key::Val8([1])],
+            PoolType::Decay => vec![Key::Val8([2])],
         }
     }
 }
@@ -37,6 +39,7 @@ impl KeyDeserialize for PoolType {
         match value.first() {
             Some(0u8) => Ok(Self::Fixed),
             Some(1u8) => Ok(Self::Oracle),
+            Some(2u8) => Ok(Self::Decay),
             _ => Err(StdError::generic_err("invalid PoolType key")),
         }
     }
@@ -70,6 +73,9 @@ impl<'a> PrimaryKey<'a> for PoolKey {
             Price::Oracle(_) => {
                 key.extend(PoolType::Oracle.key());
             }
+            Price::Decay(_) => {
+                key.extend(PoolType::Decay.key());
+            }
         };
         key.extend(self.price.key());
         key
@@ -100,7 +106,7 @@ impl KeyDeserialize for PoolKey {
 mod tests {
     use super::*;
     use cosmwasm_std::Decimal;
-    use rujira_rs::fin::{Price, Side};
+    use rujira_rs::fin::{Decay, Price, Side};
     use std::str::FromStr;
 
     #[test]
@@ -148,5 +154,17 @@ mod tests {
 
         let key = PoolKey::new(Side::Quote, Price::Fixed(Decimal::one()));
         assert_eq!(<PoolKey>::from_slice(&key.joined_key()).unwrap(), key);
+
+        let key = PoolKey::new(
+            Side::Base,
+            Price::Decay(Decay {
+                from: -50,
+                to: 10,
+                start: 1_700_000_000,
+                duration: 3600,
+            }),
+        );
+        assert_eq!(key.key()[1].as_ref(), &[2]);
+        assert_eq!(<PoolKey>::from_slice(&key.joined_key()).unwrap(), key);
     }
 }
@@ -0,0 +1,67 @@
+use cosmwasm_std::{Decimal, Uint128};
+use rujira_rs::exchange::{SwapError, SwapResult};
+
+/// Whether a fill of `consumed` offer for `returned` bids at `rate`, the bids paid per unit of
+/// offer, conserves value. Each side may round by 1 unit, so `returned` can drift from the value of
+/// `consumed` by 1 bid plus the value of 1 offer
+pub fn conserves(consumed: Uint128, returned: Uint128, rate: Decimal) -> bool {
+    let Ok(expected) = consumed.checked_mul_floor(rate) else {
+        return false;
+    };
+    expected.abs_diff(returned) <= Uint128::one() + Uint128::one().mul_ceil(rate)
+}
+
+/// Checks a fill with [conserves] when built with the `strict-rounding` feature
+pub fn check_fill(consumed: Uint128, returned: Uint128, rate: Decimal) -> Result<(), SwapError> {
+    if cfg!(feature = "strict-rounding") && !conserves(consumed, returned, rate) {
+        return Err(SwapError::RoundingDrift {
+            expected: consumed.checked_mul_floor(rate).unwrap_or(Uint128::MAX),
+            actual: returned,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that the fee of a swap at `fee` is within 1 unit of its share of the amount the swap
+/// returned, when built with the `strict-rounding` feature
+pub fn check_fee(res: &SwapResult, fee: Decimal) -> Result<(), SwapError> {
+    if !cfg!(feature = "strict-rounding") {
+        return Ok(());
+    }
+    let expected = (res.return_amount + res.fee_amount).mul_floor(fee);
+    if expected.abs_diff(res.fee_amount) > Uint128::one() {
+        return Err(SwapError::RoundingDrift {
+            expected,
+            actual: res.fee_amount,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_conserves() {
+        let rate = Decimal::from_str("1.25").unwrap();
+        assert!(conserves(Uint128::new(500), Uint128::new(625), rate));
+        // Rounding down the offer consumed by a complete fill
+        assert!(conserves(Uint128::new(8000), Uint128::new(10001), rate));
+        assert!(conserves(Uint128::new(500), Uint128::new(622), rate));
+        assert!(!conserves(Uint128::new(500), Uint128::new(621), rate));
+        assert!(!conserves(Uint128::new(500), Uint128::new(629), rate));
+
+        let rate = Decimal::from_str("0.001").unwrap();
+        assert!(conserves(Uint128::new(1999), Uint128::new(1), rate));
+        assert!(conserves(Uint128::new(1999), Uint128::new(3), rate));
+        assert!(!conserves(Uint128::new(1999), Uint128::new(4), rate));
+        assert!(!conserves(
+            Uint128::MAX,
+            Uint128::MAX,
+            Decimal::percent(200)
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+use std::cmp::min;
+
+use cosmwasm_std::{coin, Decimal, Fraction, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::Item;
+use cw_utils::NativeBalance;
+use itertools::EitherOrBoth;
+use rujira_rs::{
+    exchange::{SwapError, Swappable},
+    fin::{Session, Side},
+    Premiumable,
+};
+
+use crate::{config::Config, pool::Pool, ContractError};
+
+/// The index of the last window settled
+static SETTLED: Item<u64> = Item::new("session-settled");
+
+/// Attempts at matching a smaller amount when rounding leaves the contract short
+const MAX_ATTEMPTS: usize = 3;
+
+/// The crossing pools of each side matched by one [settle], so that a deep crossed book is settled
+/// over several executions rather than halting the market
+pub const MAX_POOLS: usize = 30;
+
+/// The result of a [settle], with the amounts exchanged between the crossing orders
+#[derive(Debug, Default)]
+pub struct Settlement {
+    pub window: u64,
+    pub rate: Decimal,
+    pub base: Uint128,
+    pub quote: Uint128,
+    /// Rounding left over by the match, collected as fees
+    pub surplus: NativeBalance,
+    /// Whether nothing is left crossing, and the window is settled. Otherwise [settle] must be
+    /// called again to match the next [MAX_POOLS] of each side
+    pub complete: bool,
+}
+
+/// Whether continuous trading is suspended at `time`: during a window, and until the last closed
+/// window is settled
+pub fn is_auction(storage: &dyn Storage, config: &Config, time: Timestamp) -> StdResult<bool> {
+    let Some(session) = &config.session else {
+        return Ok(false);
+    };
+    Ok(is_open(session, time) || pending(storage, session, time)?.is_some())
+}
+
+/// Matches the base and quote orders crossing the oracle `rate`, at that rate. Sellers are filled
+/// best price first with the quote that buyers pay for the base matched. At most [MAX_POOLS] of each
+/// side are matched at once, and the window only settles once one side has nothing left crossing
+pub fn settle(
+    storage: &mut dyn Storage,
+    config: &Config,
+    time: Timestamp,
+    oracle: &impl Premiumable,
+    rate: Decimal,
+) -> Result<Settlement, ContractError> {
+    let session = config
+        .session
+        .as_ref()
+        .ok_or(ContractError::Invalid("no session".to_string()))?;
+    let window = match pending(storage, session, time)? {
+        Some(window) if !is_open(session, time) => window,
+        _ => {
+            return Err(ContractError::Invalid(
+                "no session window to settle".to_string(),
+            ))
+        }
+    };
+    let asks = crossing(storage, &Side::Base, oracle, rate);
+    let bids = crossing(storage, &Side::Quote, oracle, rate);
+    // When either side is cut short, one of them may still cross once this batch is matched
+    let complete = asks.len() < MAX_POOLS && bids.len() < MAX_POOLS;
+    let supply: Uint128 = asks.iter().map(|x| x.total()).sum();
+    let demand = bids
+        .iter()
+        .map(|x| x.total())
+        .sum::<Uint128>()
+        .multiply_ratio(rate.denominator(), rate.numerator());
+    let mut matched = min(supply, demand);
+
+    for _ in 0..MAX_ATTEMPTS {
+        if matched.is_zero() {
+            break;
+        }
+        let mut bids = bids.clone();
+        let mut asks = asks.clone();
+        let (base_in, quote_out, filled_bids) = fill(&mut bids, matched)?;
+        let (quote_in, base_out, filled_asks) = fill(&mut asks, quote_out)?;
+        if base_out < base_in || quote_out < quote_in {
+            matched = matched.saturating_sub(base_in.saturating_sub(base_out).max(Uint128::one()));
+            continue;
+        }
+        for pool in bids[..filled_bids].iter().chain(asks[..filled_asks].iter()) {
+            pool.commit(storage)?;
+        }
+        let mut surplus = NativeBalance(vec![
+            coin((base_out - base_in).u128(), config.denoms.base()),
+            coin((quote_out - quote_in).u128(), config.denoms.quote()),
+        ]);
+        surplus.normalize();
+        if complete {
+            SETTLED.save(storage, &window)?;
+        }
+        return Ok(Settlement {
+            window,
+            rate,
+            base: base_in,
+            quote: quote_in,
+            surplus,
+            complete,
+        });
+    }
+
+    SETTLED.save(storage, &window)?;
+    Ok(Settlement {
+        window,
+        rate,
+        complete: true,
+        ..Settlement::default()
+    })
+}
+
+fn is_open(session: &Session, time: Timestamp) -> bool {
+    time.seconds()
+        .checked_sub(session.start.seconds())
+        .is_some_and(|x| x % session.interval < session.duration)
+}
+
+/// The last window closed at `time`, if it hasn't been settled
+fn pending(storage: &dyn Storage, session: &Session, time: Timestamp) -> StdResult<Option<u64>> {
+    let Some(elapsed) = time.seconds().checked_sub(session.start.seconds()) else {
+        return Ok(None);
+    };
+    let current = elapsed / session.interval;
+    let closed = match elapsed % session.interval >= session.duration {
+        true => Some(current),
+        false => current.checked_sub(1),
+    };
+    let settled = SETTLED.may_load(storage)?;
+    Ok(closed.filter(|x| !settled.is_some_and(|s| s >= *x)))
+}
+
+/// The best [MAX_POOLS] pools of `side` that cross `rate`, best price first, set to swap at `rate`
+fn crossing(
+    storage: &dyn Storage,
+    side: &Side,
+    oracle: &impl Premiumable,
+    rate: Decimal,
+) -> Vec<Pool> {
+    Pool::iter(storage, side, oracle)
+        .flat_map(|x| match x {
+            EitherOrBoth::Both(a, b) => vec![a, b],
+            EitherOrBoth::Left(x) | EitherOrBoth::Right(x) => vec![x],
+        })
+        .take_while(|x| match side {
+            Side::Base => x.rate() <= rate,
+            Side::Quote => x.rate() >= rate,
+        })
+        .take(MAX_POOLS)
+        .map(|x| x.at_rate(rate))
+        .collect()
+}
+
+/// Offers `offer` to `pools` in turn. Returns the offer consumed, the bids returned, and the number
+/// of pools swapped against
+fn fill(pools: &mut [Pool], offer: Uint128) -> Result<(Uint128, Uint128, usize), SwapError> {
+    let mut consumed = Uint128::zero();
+    let mut returned = Uint128::zero();
+    let mut filled = 0;
+    for pool in pools.iter_mut() {
+        if consumed >= offer {
+            break;
+        }
+        let (c, r) = pool.swap(offer - consumed)?;
+        consumed += c;
+        returned += r;
+        filled += 1;
+    }
+    Ok((consumed, returned, filled))
+}
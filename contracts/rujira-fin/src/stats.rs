@@ -0,0 +1,177 @@
+use cosmwasm_std::{coin, Coin, Decimal, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::{Item, Map};
+use cw_utils::NativeBalance;
+use rujira_rs::fin::{Denoms, Side, SideStatsResponse, StatsResponse};
+
+// Running totals of arb profit, in atomic units of each denom
+static ARB_PROFIT: Map<&str, Uint128> = Map::new("arb-profit");
+// Running total of arb profit, valued in USD at the time of each arb
+static ARB_PROFIT_USD: Item<Decimal> = Item::new("arb-profit-usd");
+// Total offer resting in the pools of each side
+static RESTING: Map<Side, Uint128> = Map::new("stats-resting");
+// Number of orders open on each side
+static ORDERS: Map<Side, u32> = Map::new("stats-orders");
+// Fills credited to the orders of each side and not yet withdrawn
+static UNCLAIMED: Map<Side, Uint128> = Map::new("stats-unclaimed");
+// Offer escrowed by TWAPs swapping against each side
+static ESCROWED: Map<Side, Uint128> = Map::new("stats-escrowed");
+// Bounties posted by open maker sessions, in atomic units of each denom
+static BOUNTIES: Map<&str, Uint128> = Map::new("stats-bounties");
+
+/// Adds the profit of an arb to the running totals. `usd` is omitted when the oracles are unavailable
+pub fn record_arb(
+    storage: &mut dyn Storage,
+    profit: &[(&str, Uint128)],
+    usd: Option<Decimal>,
+) -> StdResult<()> {
+    for (denom, amount) in profit.iter().filter(|(_, x)| !x.is_zero()) {
+        ARB_PROFIT.update(storage, denom, |x| -> StdResult<Uint128> {
+            Ok(x.unwrap_or_default().checked_add(*amount)?)
+        })?;
+    }
+    if let Some(usd) = usd {
+        let total = ARB_PROFIT_USD.may_load(storage)?.unwrap_or_default();
+        ARB_PROFIT_USD.save(storage, &total.checked_add(usd)?)?;
+    }
+    Ok(())
+}
+
+/// Applies the change in size of a pool to the resting total of its side
+pub fn record_resting(
+    storage: &mut dyn Storage,
+    side: &Side,
+    before: Uint128,
+    after: Uint128,
+) -> StdResult<()> {
+    if before == after {
+        return Ok(());
+    }
+    RESTING.update(storage, side.clone(), |x| -> StdResult<Uint128> {
+        Ok(x.unwrap_or_default()
+            .checked_add(after)?
+            .checked_sub(before)?)
+    })?;
+    Ok(())
+}
+
+/// Adds a fill of `amount` to the orders of `side`
+pub fn record_fill(storage: &mut dyn Storage, side: &Side, amount: Uint128) -> StdResult<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    UNCLAIMED.update(storage, side.clone(), |x| -> StdResult<Uint128> {
+        Ok(x.unwrap_or_default().checked_add(amount)?)
+    })?;
+    Ok(())
+}
+
+/// Removes a fill withdrawn from an order on `side`. Fills are distributed to orders rounding
+/// down, so the total can be left with dust that is never withdrawn
+pub fn record_claim(storage: &mut dyn Storage, side: &Side, amount: Uint128) -> StdResult<()> {
+    UNCLAIMED.update(storage, side.clone(), |x| -> StdResult<Uint128> {
+        Ok(x.unwrap_or_default().saturating_sub(amount))
+    })?;
+    Ok(())
+}
+
+/// Applies the change in the remaining offer of a TWAP to the escrowed total of its side
+pub fn record_escrow(
+    storage: &mut dyn Storage,
+    side: &Side,
+    before: Uint128,
+    after: Uint128,
+) -> StdResult<()> {
+    if before == after {
+        return Ok(());
+    }
+    ESCROWED.update(storage, side.clone(), |x| -> StdResult<Uint128> {
+        Ok(x.unwrap_or_default()
+            .checked_add(after)?
+            .checked_sub(before)?)
+    })?;
+    Ok(())
+}
+
+/// Adds the bounty of a maker session opened, or removes that of one closed or expired
+pub fn record_bounty(storage: &mut dyn Storage, bounty: &[Coin], open: bool) -> StdResult<()> {
+    for x in bounty.iter().filter(|x| !x.amount.is_zero()) {
+        BOUNTIES.update(storage, &x.denom, |total| -> StdResult<Uint128> {
+            let total = total.unwrap_or_default();
+            Ok(if open {
+                total.checked_add(x.amount)?
+            } else {
+                total.saturating_sub(x.amount)
+            })
+        })?;
+    }
+    Ok(())
+}
+
+/// The funds held by the contract on behalf of users: resting orders, fills awaiting withdrawal,
+/// offers escrowed by TWAPs and the bounties of open maker sessions
+pub fn liabilities(storage: &dyn Storage, denoms: &Denoms) -> StdResult<NativeBalance> {
+    let mut total = NativeBalance::default();
+    for x in BOUNTIES.range(storage, None, None, Order::Ascending) {
+        let (denom, amount) = x?;
+        total += coin(amount.u128(), denom);
+    }
+    for s in [Side::Base, Side::Quote] {
+        let stats = side(storage, s.clone())?;
+        total += coin(stats.resting.u128(), denoms.bid(&s));
+        total += coin(
+            stats.unclaimed.checked_add(stats.escrowed)?.u128(),
+            denoms.ask(&s),
+        );
+    }
+    total.normalize();
+    Ok(total)
+}
+
+/// Counts an order opened, or closed, on `side`
+pub fn record_order(storage: &mut dyn Storage, side: &Side, open: bool) -> StdResult<()> {
+    ORDERS.update(storage, side.clone(), |x| -> StdResult<u32> {
+        let x = x.unwrap_or_default();
+        Ok(if open { x + 1 } else { x.saturating_sub(1) })
+    })?;
+    Ok(())
+}
+
+/// The number of orders open on both sides of the book
+pub fn open_orders(storage: &dyn Storage) -> StdResult<u32> {
+    Ok(ORDERS.may_load(storage, Side::Base)?.unwrap_or_default()
+        + ORDERS.may_load(storage, Side::Quote)?.unwrap_or_default())
+}
+
+/// Clears the totals of both sides, before the backfill of pairs that predate them
+pub fn reset(storage: &mut dyn Storage) -> StdResult<()> {
+    for side in [Side::Base, Side::Quote] {
+        RESTING.remove(storage, side.clone());
+        ORDERS.remove(storage, side.clone());
+        UNCLAIMED.remove(storage, side.clone());
+        ESCROWED.remove(storage, side);
+    }
+    Ok(())
+}
+
+fn side(storage: &dyn Storage, side: Side) -> StdResult<SideStatsResponse> {
+    Ok(SideStatsResponse {
+        resting: RESTING.may_load(storage, side.clone())?.unwrap_or_default(),
+        orders: ORDERS.may_load(storage, side.clone())?.unwrap_or_default(),
+        unclaimed: UNCLAIMED
+            .may_load(storage, side.clone())?
+            .unwrap_or_default(),
+        escrowed: ESCROWED.may_load(storage, side)?.unwrap_or_default(),
+    })
+}
+
+pub fn load(storage: &dyn Storage) -> StdResult<StatsResponse> {
+    Ok(StatsResponse {
+        arb_profit: ARB_PROFIT
+            .range(storage, None, None, Order::Ascending)
+            .map(|x| x.map(|(denom, amount)| coin(amount.u128(), denom)))
+            .collect::<StdResult<Vec<_>>>()?,
+        arb_profit_usd: ARB_PROFIT_USD.may_load(storage)?.unwrap_or_default(),
+        base: side(storage, Side::Base)?,
+        quote: side(storage, Side::Quote)?,
+    })
+}
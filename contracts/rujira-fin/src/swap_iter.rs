@@ -1,18 +1,38 @@
-use cosmwasm_std::{QuerierWrapper, Storage};
+use cosmwasm_std::{Addr, QuerierWrapper, Storage};
 use itertools::{EitherOrBoth, Itertools};
 use rujira_rs::{exchange::Swappable, fin::Side, Premiumable};
 
-use crate::market_maker::MarketMakerOffer;
+use crate::market_maker::{Dropped, MarketMakerOffer};
 use crate::{config::Config, pool::Pool};
 
 pub struct SwapIter<'a> {
     querier: QuerierWrapper<'a>,
     config: &'a Config,
+    dropped: Dropped,
 }
 
 impl<'a> SwapIter<'a> {
     pub fn new(querier: QuerierWrapper<'a>, config: &'a Config) -> Self {
-        Self { querier, config }
+        Self {
+            querier,
+            config,
+            dropped: Dropped::default(),
+        }
+    }
+
+    /// Drops every market maker for the execution, so that only the book is traded against
+    pub fn without_market_makers(self, excluded: bool) -> Self {
+        if excluded {
+            self.dropped
+                .borrow_mut()
+                .extend(self.config.market_makers.contracts.iter().cloned());
+        }
+        self
+    }
+
+    /// The market makers dropped from iteration so far, for misbehaving quotes
+    pub fn dropped(&self) -> Vec<Addr> {
+        self.dropped.borrow().iter().cloned().collect()
     }
 
     pub fn iter(
@@ -27,6 +47,9 @@ impl<'a> SwapIter<'a> {
                 &self.config.denoms,
                 &self.config.tick,
                 side,
+                self.dropped.clone(),
+                self.config
+                    .market_maker_limit(self.querier, self.config.denoms.bid(side)),
             ),
             move |x, y| match side {
                 Side::Base => x.rate().cmp(&y.rate()),
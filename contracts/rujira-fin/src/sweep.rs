@@ -0,0 +1,88 @@
+use cosmwasm_std::{coin, Decimal, StdResult, Storage};
+use cw_storage_plus::Item;
+use cw_utils::NativeBalance;
+use rujira_rs::fin::{Price, Side};
+
+use crate::{config::Config, pool::Pool};
+
+/// The maximum number of entries removed by a single sweep
+pub const MAX_LIMIT: u32 = 100;
+
+/// The fees reserved to pay sweep bounties
+static RESERVE: Item<NativeBalance> = Item::new("sweep-reserve");
+
+/// Moves the share of `fees` set by the [rujira_rs::fin::SweepBounty] into the reserve
+pub fn reserve(
+    storage: &mut dyn Storage,
+    config: &Config,
+    fees: &mut NativeBalance,
+) -> StdResult<()> {
+    let Some(bounty) = &config.sweep_bounty else {
+        return Ok(());
+    };
+    let mut share = NativeBalance::default();
+    for c in fees.clone().into_vec() {
+        share += coin(c.amount.mul_floor(bounty.share).u128(), c.denom);
+    }
+    share.normalize();
+    if share.is_empty() {
+        return Ok(());
+    }
+    *fees = fees.clone().sub_saturating(share.clone())?;
+    let mut reserve = RESERVE.may_load(storage)?.unwrap_or_default();
+    for c in share.into_vec() {
+        reserve += c;
+    }
+    reserve.normalize();
+    RESERVE.save(storage, &reserve)
+}
+
+/// Sweeps the pools at `keys`, up to `limit` entries. Returns the number of entries removed and the
+/// bounty paid for them from the reserve
+pub fn sweep(
+    storage: &mut dyn Storage,
+    config: &Config,
+    keys: Vec<(Side, Price)>,
+    limit: Option<u32>,
+) -> StdResult<(usize, NativeBalance)> {
+    let mut remaining = limit.unwrap_or(MAX_LIMIT).min(MAX_LIMIT) as usize;
+    let mut removed = 0;
+    for (side, price) in keys {
+        if remaining == 0 {
+            break;
+        }
+        let n = Pool::sweep(storage, &side, &price, remaining)?;
+        removed += n;
+        remaining -= n;
+    }
+
+    let mut bounty = NativeBalance::default();
+    let Some(rate) = config.sweep_bounty.as_ref().map(|x| x.rate) else {
+        return Ok((removed, bounty));
+    };
+    let share = rate
+        .checked_mul(Decimal::from_ratio(removed as u128, 1u128))
+        .unwrap_or(Decimal::one())
+        .min(Decimal::one());
+    let reserve = RESERVE.may_load(storage)?.unwrap_or_default();
+    for c in reserve.clone().into_vec() {
+        bounty += coin(c.amount.mul_floor(share).u128(), c.denom);
+    }
+    bounty.normalize();
+    if !bounty.is_empty() {
+        RESERVE.save(storage, &reserve.sub_saturating(bounty.clone())?)?;
+    }
+    Ok((removed, bounty))
+}
+
+/// The fees held in the reserve
+pub fn reserved(storage: &dyn Storage) -> StdResult<NativeBalance> {
+    Ok(RESERVE.may_load(storage)?.unwrap_or_default())
+}
+
+/// Empties the reserve, returning the fees it held
+pub fn release(storage: &mut dyn Storage) -> StdResult<NativeBalance> {
+    let reserve = RESERVE.may_load(storage)?.unwrap_or_default();
+    RESERVE.remove(storage);
+    Ok(reserve)
+}
@@ -128,6 +128,7 @@ mod tests {
                 &ExecuteMsg::Swap(SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 }),
                 &coins(swap_amount, "btc-btc"),
             )
@@ -214,6 +215,7 @@ mod tests {
                 &ExecuteMsg::Swap(SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 }),
                 &coins(swap_amount, "btc-btc"),
             )
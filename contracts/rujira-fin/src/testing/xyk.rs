@@ -7,10 +7,10 @@ use rujira_bow::contract as bow_contract;
 use rujira_rs::{
     bow::{self, Xyk},
     fin::{
-        BookItemResponse, BookResponse, Denoms, ExecuteMsg, InstantiateMsg, OrderResponse,
-        OrdersResponse, Price, QueryMsg, Side, SwapRequest, Tick,
+        ArbOpportunityResponse, BookItemResponse, BookResponse, Denoms, ExecuteMsg, InstantiateMsg,
+        OrderResponse, OrdersResponse, Price, QueryMsg, Side, StatsResponse, SwapRequest, Tick,
     },
-    Layer1Asset, TokenMetadata,
+    Layer1Asset, PageRequest, TokenMetadata,
 };
 use rujira_rs_testing::{mock_rujira_app, RujiraApp};
 
@@ -86,8 +86,8 @@ fn mm_book() {
         .query_wasm_smart(
             fin.clone(),
             &QueryMsg::Book {
-                limit: None,
-                offset: None,
+                page: PageRequest::default(),
+                group_by: None,
             },
         )
         .unwrap();
@@ -125,8 +125,8 @@ fn mm_book() {
         .query_wasm_smart(
             fin.clone(),
             &QueryMsg::Book {
-                limit: None,
-                offset: None,
+                page: PageRequest::default(),
+                group_by: None,
             },
         )
         .unwrap();
@@ -162,6 +162,7 @@ fn mm_book() {
             &ExecuteMsg::Swap(SwapRequest::Yolo {
                 to: None,
                 callback: None,
+                max_levels: None,
             }),
             &coins(100_000_000, "usdc"),
         )
@@ -209,8 +210,8 @@ fn mm_book() {
         .query_wasm_smart(
             fin.clone(),
             &QueryMsg::Book {
-                limit: Some(10),
-                offset: None,
+                page: PageRequest::new(None, Some(10)),
+                group_by: None,
             },
         )
         .unwrap();
@@ -243,6 +244,7 @@ fn mm_book() {
             &ExecuteMsg::Swap(SwapRequest::Yolo {
                 to: None,
                 callback: None,
+                max_levels: None,
             }),
             &coins(500_000, "btc"),
         )
@@ -300,6 +302,7 @@ fn mm_book() {
         &ExecuteMsg::Swap(SwapRequest::Yolo {
             to: None,
             callback: None,
+            max_levels: None,
         }),
         &coins(600_000_000, "usdc"),
     )
@@ -341,8 +344,8 @@ fn combined_book() {
         .query_wasm_smart(
             fin.clone(),
             &QueryMsg::Book {
-                limit: Some(10),
-                offset: None,
+                page: PageRequest::new(None, Some(10)),
+                group_by: None,
             },
         )
         .unwrap();
@@ -389,8 +392,8 @@ fn combined_book() {
         .query_wasm_smart(
             fin.clone(),
             &QueryMsg::Book {
-                limit: Some(5),
-                offset: None,
+                page: PageRequest::new(None, Some(5)),
+                group_by: None,
             },
         )
         .unwrap();
@@ -414,6 +417,7 @@ fn combined_book() {
             &ExecuteMsg::Swap(SwapRequest::Yolo {
                 to: None,
                 callback: None,
+                max_levels: None,
             }),
             &coins(45_500, "btc"),
         )
@@ -482,8 +486,7 @@ fn combined_book() {
             &QueryMsg::Orders {
                 owner: owner.to_string(),
                 side: None,
-                offset: None,
-                limit: None,
+                page: PageRequest::default(),
             },
         )
         .unwrap();
@@ -563,8 +566,8 @@ fn test_arbitrage_quote_single() {
         .query_wasm_smart(
             fin.clone(),
             &QueryMsg::Book {
-                limit: Some(10),
-                offset: None,
+                page: PageRequest::new(None, Some(10)),
+                group_by: None,
             },
         )
         .unwrap();
@@ -623,6 +626,17 @@ fn test_arbitrage_quote_single() {
         ("recipient", owner.as_str()),
         ("amount", "9840158usdc"),
     ]));
+
+    res.assert_event(
+        &Event::new("wasm-rujira-fin/arb").add_attributes(vec![("profit", "10009usdc")]),
+    );
+
+    let stats: StatsResponse = app
+        .wrap()
+        .query_wasm_smart(fin.clone(), &QueryMsg::Stats {})
+        .unwrap();
+    assert_eq!(stats.arb_profit, coins(10_009, "usdc"));
+    assert!(!stats.arb_profit_usd.is_zero());
 }
 
 #[test]
@@ -664,8 +678,8 @@ fn test_arbitrage_quote_multi() {
         .query_wasm_smart(
             fin.clone(),
             &QueryMsg::Book {
-                limit: Some(10),
-                offset: None,
+                page: PageRequest::new(None, Some(10)),
+                group_by: None,
             },
         )
         .unwrap();
@@ -711,8 +725,8 @@ fn test_arbitrage_quote_multi() {
         .query_wasm_smart(
             fin.clone(),
             &QueryMsg::Book {
-                limit: Some(10),
-                offset: None,
+                page: PageRequest::new(None, Some(10)),
+                group_by: None,
             },
         )
         .unwrap();
@@ -750,6 +764,113 @@ fn test_arbitrage_quote_multi() {
     ]));
 }
 
+#[test]
+fn arb_opportunity() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let fees = app.api().addr_make("fees");
+    let (fin, bow) = setup(&mut app, &owner, &fees);
+    app.init_modules(|router, _, storage| {
+        router.bank.init_balance(
+            storage,
+            &owner,
+            vec![
+                coin(1_000_000_000_000, "btc"),
+                coin(1_000_000_000_000, "usdc"),
+            ],
+        )
+    })
+    .unwrap();
+
+    app.execute_contract(
+        owner.clone(),
+        bow.clone(),
+        &bow::ExecuteMsg::Deposit {
+            callback: None,
+            min_return: None,
+        },
+        &[coin(200_000_000, "btc"), coin(200_000_000_000, "usdc")],
+    )
+    .unwrap();
+
+    // A bid below the market maker's asks rests uncrossed
+    app.execute_contract(
+        owner.clone(),
+        fin.clone(),
+        &ExecuteMsg::Order((
+            vec![(
+                Side::Quote,
+                Price::Fixed(Decimal::from_str("1000").unwrap()),
+                Some(Uint128::from(100_000_000u128)),
+            )],
+            None,
+        )),
+        &coins(100_000_000u128, "usdc"),
+    )
+    .unwrap();
+
+    let res: ArbOpportunityResponse = app
+        .wrap()
+        .query_wasm_smart(fin.clone(), &QueryMsg::ArbOpportunity {})
+        .unwrap();
+    assert_eq!(
+        res,
+        ArbOpportunityResponse {
+            crossed: false,
+            size: vec![],
+            profit: vec![],
+            profit_usd: None,
+        }
+    );
+
+    // Selling into the pool directly moves its asks below the resting bid
+    app.execute_contract(
+        owner.clone(),
+        bow.clone(),
+        &bow::ExecuteMsg::Swap {
+            min_return: coin(1, "usdc"),
+            to: None,
+            callback: None,
+        },
+        &coins(20_000_000, "btc"),
+    )
+    .unwrap();
+
+    let res: ArbOpportunityResponse = app
+        .wrap()
+        .query_wasm_smart(fin.clone(), &QueryMsg::ArbOpportunity {})
+        .unwrap();
+    assert!(res.crossed);
+    assert!(!res.size.is_empty());
+    assert!(!res.profit.is_empty());
+    assert!(res.profit_usd.is_some());
+
+    // The arb captures what the query reported
+    let arb = app
+        .execute_contract(
+            owner.clone(),
+            fin.clone(),
+            &ExecuteMsg::Arb { then: None },
+            &[],
+        )
+        .unwrap();
+    let profit = res
+        .profit
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    arb.assert_event(
+        &Event::new("wasm-rujira-fin/arb").add_attributes(vec![("profit", profit.as_str())]),
+    );
+
+    let res: ArbOpportunityResponse = app
+        .wrap()
+        .query_wasm_smart(fin.clone(), &QueryMsg::ArbOpportunity {})
+        .unwrap();
+    assert!(!res.crossed);
+}
+
 fn assert_side(side: Vec<BookItemResponse>, items: Vec<(&str, u128)>) {
     assert!(side.len().ge(&items.len()));
     for ((price, amount), item) in items.iter().zip(side) {
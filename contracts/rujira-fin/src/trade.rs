@@ -0,0 +1,26 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, StdResult, Storage, Timestamp};
+use cw_storage_plus::Item;
+
+static LAST_TRADE: Item<Trade> = Item::new("last-trade");
+
+/// A record of a swap executed against the book, used for ticker queries
+#[cw_serde]
+pub struct Trade {
+    pub price: Decimal,
+    pub timestamp: Timestamp,
+}
+
+impl Trade {
+    pub fn new(price: Decimal, timestamp: Timestamp) -> Self {
+        Self { price, timestamp }
+    }
+
+    pub fn last(storage: &dyn Storage) -> StdResult<Option<Self>> {
+        LAST_TRADE.may_load(storage)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        LAST_TRADE.save(storage, self)
+    }
+}
@@ -0,0 +1,227 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, Addr, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use rujira_rs::fin::{Side, SwapRequest, TwapRequest, TwapResponse};
+
+use crate::{stats, ContractError};
+
+static TWAP_ID: Item<u64> = Item::new("twap-id");
+static TWAPS: Map<u64, Twap> = Map::new("twaps");
+
+pub const MAX_SLICES: u64 = 1_000;
+
+/// An escrowed offer, swapped against `side` of the book in slices until the remaining offer is exhausted
+#[cw_serde]
+pub struct Twap {
+    pub id: u64,
+    pub owner: Addr,
+    pub side: Side,
+    pub request: TwapRequest,
+    pub offer: Uint128,
+    pub remaining: Uint128,
+    pub returned: Uint128,
+    pub start: Timestamp,
+}
+
+impl Twap {
+    pub fn create(
+        storage: &mut dyn Storage,
+        owner: Addr,
+        side: Side,
+        offer: Uint128,
+        start: Timestamp,
+        request: TwapRequest,
+    ) -> Result<Self, ContractError> {
+        ensure!(
+            request.interval > 0 && request.duration >= request.interval,
+            ContractError::Invalid("twap interval must be between 1 and duration".to_string())
+        );
+        let id = TWAP_ID.may_load(storage)?.unwrap_or_default();
+        TWAP_ID.save(storage, &(id + 1))?;
+        let twap = Self {
+            id,
+            owner,
+            side,
+            request,
+            offer,
+            remaining: offer,
+            returned: Uint128::zero(),
+            start,
+        };
+        ensure!(
+            twap.slices() <= MAX_SLICES,
+            ContractError::Invalid(format!("twap must be at most {MAX_SLICES} slices"))
+        );
+        twap.save(storage)?;
+        Ok(twap)
+    }
+
+    pub fn load(storage: &dyn Storage, id: u64) -> StdResult<Self> {
+        TWAPS.load(storage, id)
+    }
+
+    /// Saves the TWAP, or removes it once the offer has been exhausted
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        if self.remaining.is_zero() {
+            return self.remove(storage);
+        }
+        let before = TWAPS
+            .may_load(storage, self.id)?
+            .map(|x| x.remaining)
+            .unwrap_or_default();
+        stats::record_escrow(storage, &self.side, before, self.remaining)?;
+        TWAPS.save(storage, self.id, self)
+    }
+
+    pub fn remove(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        if let Some(stored) = TWAPS.may_load(storage, self.id)? {
+            stats::record_escrow(storage, &self.side, stored.remaining, Uint128::zero())?;
+        }
+        TWAPS.remove(storage, self.id);
+        Ok(())
+    }
+
+    /// Total remaining offer of the TWAPs swapping against `side`
+    /// Adds the offer escrowed by a page of TWAPs to the totals of their side. Returns the id of the
+    /// last TWAP when the page is full
+    pub fn backfill(
+        storage: &mut dyn Storage,
+        start_after: Option<u64>,
+        limit: usize,
+    ) -> StdResult<Option<u64>> {
+        let twaps = TWAPS
+            .range(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                cosmwasm_std::Order::Ascending,
+            )
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+        for (_, twap) in twaps.iter() {
+            stats::record_escrow(storage, &twap.side, Uint128::zero(), twap.remaining)?;
+        }
+        Ok(twaps
+            .last()
+            .filter(|_| twaps.len() == limit)
+            .map(|(id, _)| *id))
+    }
+
+    pub fn slices(&self) -> u64 {
+        self.request.duration.div_ceil(self.request.interval)
+    }
+
+    /// The offer scheduled by `now` that has not been swapped yet. The first slice is due at `start`
+    pub fn due(&self, now: Timestamp) -> Uint128 {
+        let elapsed = now.seconds().saturating_sub(self.start.seconds());
+        let slices = self.slices();
+        let due = (elapsed / self.request.interval + 1).min(slices);
+        let scheduled = self.offer.multiply_ratio(due, slices);
+        let swapped = self.offer - self.remaining;
+        scheduled.saturating_sub(swapped)
+    }
+
+    pub fn swap_request(&self) -> SwapRequest {
+        match self.request.limit {
+            Some(price) => SwapRequest::Limit {
+                price,
+                min_fill: None,
+                to: None,
+                callback: None,
+                max_levels: None,
+            },
+            None => SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            },
+        }
+    }
+
+    /// Records a slice swapping `offer` for `returned`
+    pub fn record(&mut self, offer: Uint128, returned: Uint128) -> StdResult<()> {
+        self.remaining = self.remaining.checked_sub(offer)?;
+        self.returned += returned;
+        Ok(())
+    }
+
+    pub fn to_response(&self, now: Timestamp) -> TwapResponse {
+        TwapResponse {
+            id: self.id,
+            owner: self.owner.to_string(),
+            side: self.side.clone(),
+            request: self.request.clone(),
+            offer: self.offer,
+            remaining: self.remaining,
+            returned: self.returned,
+            start: self.start,
+            slices: self.slices(),
+            due: self.due(now),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn due() {
+        let mut storage = MockStorage::new();
+        let start = Timestamp::from_seconds(1_000);
+        let mut twap = Twap::create(
+            &mut storage,
+            Addr::unchecked("owner"),
+            Side::Base,
+            Uint128::from(1_000u128),
+            start,
+            TwapRequest {
+                duration: 250,
+                interval: 100,
+                limit: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(twap.slices(), 3);
+        assert_eq!(twap.due(start), Uint128::from(333u128));
+        assert_eq!(twap.due(start.plus_seconds(99)), Uint128::from(333u128));
+
+        // Unswapped offer is carried over
+        twap.record(Uint128::from(300u128), Uint128::from(30u128))
+            .unwrap();
+        assert_eq!(twap.due(start.plus_seconds(100)), Uint128::from(366u128));
+
+        // The final slice is due at the last interval, and takes the remainder
+        assert_eq!(twap.due(start.plus_seconds(200)), Uint128::from(700u128));
+        assert_eq!(twap.due(start.plus_seconds(10_000)), Uint128::from(700u128));
+
+        twap.record(Uint128::from(700u128), Uint128::from(70u128))
+            .unwrap();
+        twap.save(&mut storage).unwrap();
+        Twap::load(&storage, twap.id).unwrap_err();
+    }
+
+    #[test]
+    fn validation() {
+        let mut storage = MockStorage::new();
+        let create = |storage: &mut MockStorage, duration: u64, interval: u64| {
+            Twap::create(
+                storage,
+                Addr::unchecked("owner"),
+                Side::Base,
+                Uint128::from(1_000u128),
+                Timestamp::from_seconds(0),
+                TwapRequest {
+                    duration,
+                    interval,
+                    limit: None,
+                },
+            )
+        };
+        create(&mut storage, 100, 0).unwrap_err();
+        create(&mut storage, 100, 101).unwrap_err();
+        create(&mut storage, 1_001, 1).unwrap_err();
+        create(&mut storage, 1_000, 1).unwrap();
+    }
+}
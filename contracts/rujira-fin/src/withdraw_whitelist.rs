@@ -0,0 +1,113 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, Addr, Api, StdResult, Storage, Timestamp};
+use cw_storage_plus::Map;
+
+use crate::ContractError;
+
+/// The most addresses an owner can whitelist
+pub const MAX_WITHDRAW_WHITELIST: usize = 20;
+
+/// The seconds before a change that loosens a whitelist takes effect, so that a compromised key can't
+/// lift the restriction and route the owner's swap returns elsewhere in the same block
+pub const WITHDRAW_WHITELIST_DELAY: u64 = 86_400;
+
+// The addresses each owner's swaps can pay out to, in addition to the owner itself
+static WITHDRAW_WHITELISTS: Map<Addr, Vec<Addr>> = Map::new("withdraw-whitelists");
+
+// The change to each owner's whitelist that loosens it, and when it takes effect
+static PENDING: Map<Addr, Pending> = Map::new("withdraw-whitelists-pending");
+
+#[cw_serde]
+struct Pending {
+    addresses: Option<Vec<Addr>>,
+    effective: Timestamp,
+}
+
+/// Sets the whitelist of `owner`. `None` removes it, so that swaps can pay out anywhere. Removing the
+/// whitelist or adding addresses to it takes effect after [WITHDRAW_WHITELIST_DELAY], and anything
+/// else immediately, replacing a change still pending. Returns when the change takes effect
+pub fn set(
+    storage: &mut dyn Storage,
+    api: &dyn Api,
+    now: Timestamp,
+    owner: &Addr,
+    addresses: Option<Vec<String>>,
+) -> Result<Timestamp, ContractError> {
+    let addresses = addresses
+        .map(|addresses| {
+            ensure!(
+                addresses.len() <= MAX_WITHDRAW_WHITELIST,
+                ContractError::Invalid("withdraw_whitelist".into())
+            );
+            let mut addresses = addresses
+                .iter()
+                .map(|x| api.addr_validate(x))
+                .collect::<StdResult<Vec<_>>>()?;
+            addresses.sort();
+            addresses.dedup();
+            Ok(addresses)
+        })
+        .transpose()?;
+    let current = load(storage, now, owner)?;
+    PENDING.remove(storage, owner.clone());
+    save(storage, owner, &current)?;
+
+    let loosens = match (&current, &addresses) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(current), Some(addresses)) => addresses.iter().any(|x| !current.contains(x)),
+    };
+    if !loosens {
+        save(storage, owner, &addresses)?;
+        return Ok(now);
+    }
+    let effective = now.plus_seconds(WITHDRAW_WHITELIST_DELAY);
+    PENDING.save(
+        storage,
+        owner.clone(),
+        &Pending {
+            addresses,
+            effective,
+        },
+    )?;
+    Ok(effective)
+}
+
+/// The whitelist of `owner` in effect at `now`
+pub fn load(storage: &dyn Storage, now: Timestamp, owner: &Addr) -> StdResult<Option<Vec<Addr>>> {
+    match PENDING.may_load(storage, owner.clone())? {
+        Some(pending) if pending.effective <= now => Ok(pending.addresses),
+        _ => WITHDRAW_WHITELISTS.may_load(storage, owner.clone()),
+    }
+}
+
+/// Resolves the recipient of a payout of `owner` to `to`, or the owner itself. `to` must be on the
+/// owner's whitelist when it has one
+pub fn recipient(
+    storage: &dyn Storage,
+    api: &dyn Api,
+    now: Timestamp,
+    owner: &Addr,
+    to: Option<String>,
+) -> Result<Addr, ContractError> {
+    let Some(to) = to.map(|x| api.addr_validate(&x)).transpose()? else {
+        return Ok(owner.clone());
+    };
+    if let Some(whitelist) = load(storage, now, owner)? {
+        ensure!(
+            to == *owner || whitelist.contains(&to),
+            ContractError::RecipientNotWhitelisted { recipient: to }
+        );
+    }
+    Ok(to)
+}
+
+fn save(storage: &mut dyn Storage, owner: &Addr, addresses: &Option<Vec<Addr>>) -> StdResult<()> {
+    match addresses {
+        Some(addresses) => WITHDRAW_WHITELISTS.save(storage, owner.clone(), addresses),
+        None => {
+            WITHDRAW_WHITELISTS.remove(storage, owner.clone());
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Empty, StdResult, Storage, WasmMsg,
+};
+use cw_storage_plus::Item;
+use cw_utils::NativeBalance;
+use rujira_rs::{
+    fin::{OrderTarget, SwapRequest, WrapAdapter},
+    ghost, CallbackData,
+};
+
+use crate::config::Config;
+
+/// Set while a request paid in the underlying of the receipt token executes, so that the
+/// receipt tokens in its payout are unwrapped
+static UNWRAP: Item<()> = Item::new("wrap-unwrap");
+
+/// The callback of deposits into and withdrawals from the vault of the [WrapAdapter]
+#[cw_serde]
+pub enum WrapCallback {
+    /// The receipt tokens minted for a swap paid in the underlying
+    Swap((Addr, SwapRequest)),
+    /// The receipt tokens minted for orders paid in the underlying
+    Order((Addr, (Vec<OrderTarget>, Option<CallbackData>))),
+    /// The underlying withdrawn for the payout of a wrapped request, paid out with the rest of `funds`
+    Payout {
+        recipient: Addr,
+        callback: Option<CallbackData>,
+        funds: Vec<Coin>,
+    },
+}
+
+/// Deposits `funds` into the vault of the adapter when they are its underlying. The request is
+/// resumed by `then` once the vault returns the receipt tokens minted
+pub fn deposit(
+    config: &Config,
+    funds: &[Coin],
+    then: impl FnOnce() -> WrapCallback,
+) -> StdResult<Option<CosmosMsg>> {
+    let Some(adapter) = &config.wrap else {
+        return Ok(None);
+    };
+    match funds {
+        [x] if x.denom == adapter.underlying => Ok(Some(vault_msg(
+            adapter,
+            ghost::vault::ExecuteMsg::Deposit {
+                callback: Some(CallbackData::Msg(to_json_binary(&then())?)),
+            },
+            funds.to_vec(),
+        )?)),
+        _ => Ok(None),
+    }
+}
+
+/// Flags the request resumed with the receipt tokens of a [deposit], for its [payout]
+pub fn resume(storage: &mut dyn Storage) -> StdResult<()> {
+    UNWRAP.save(storage, &())
+}
+
+/// Pays `funds` to `recipient`. The receipt tokens in the payout of a wrapped request are
+/// withdrawn from the vault first, and paid out in the underlying
+pub fn payout(
+    storage: &mut dyn Storage,
+    config: &Config,
+    recipient: &Addr,
+    callback: Option<CallbackData>,
+    mut funds: NativeBalance,
+) -> StdResult<Vec<CosmosMsg>> {
+    funds.normalize();
+    let unwrap = UNWRAP.may_load(storage)?.is_some();
+    UNWRAP.remove(storage);
+    let adapter = config.wrap.as_ref().filter(|_| unwrap);
+    let receipt = adapter.and_then(|x| funds.0.iter().find(|c| c.denom == x.receipt).cloned());
+    match (adapter, receipt) {
+        (Some(adapter), Some(receipt)) => {
+            let funds = (funds - receipt.clone())?;
+            let then = WrapCallback::Payout {
+                recipient: recipient.clone(),
+                callback,
+                funds: funds.into_vec(),
+            };
+            Ok(vec![vault_msg(
+                adapter,
+                ghost::vault::ExecuteMsg::Withdraw {
+                    callback: Some(CallbackData::Msg(to_json_binary(&then)?)),
+                },
+                vec![receipt],
+            )?])
+        }
+        _ => send(recipient, callback, funds.into_vec()),
+    }
+}
+
+/// Sends `funds` to `recipient`, with its callback if it has one
+pub fn send(
+    recipient: &Addr,
+    callback: Option<CallbackData>,
+    funds: Vec<Coin>,
+) -> StdResult<Vec<CosmosMsg>> {
+    if funds.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(vec![match callback {
+        None => CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: funds,
+        }),
+        Some(cb) => cb.to_message(recipient, Empty {}, funds)?.into(),
+    }])
+}
+
+fn vault_msg(
+    adapter: &WrapAdapter,
+    msg: ghost::vault::ExecuteMsg,
+    funds: Vec<Coin>,
+) -> StdResult<CosmosMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: adapter.vault.to_string(),
+        msg: to_json_binary(&msg)?,
+        funds,
+    }
+    .into())
+}
@@ -1,22 +1,31 @@
-use crate::{config::Config, state::BORROW, ContractError};
+use crate::{
+    config::Config,
+    state::{BORROW, PREFERENCE_TEMPLATES},
+    ContractError,
+};
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{ensure, Addr, Binary, Decimal, Deps, DepsMut, Order, StdResult, WasmMsg};
+use cosmwasm_std::{
+    coin, ensure, to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut, Order,
+    StdResult, Timestamp, Uint128, WasmMsg,
+};
 use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, MultiIndex};
 use cw_utils::NativeBalance;
 use rujira_rs::{
     account::Account,
+    fin,
     ghost::credit::{
-        AccountResponse, Collateral, CollateralResponse, Debt, DebtResponse, LiquidateMsg,
-        LiquidationPreferences,
+        AccountResponse, Collateral, CollateralResponse, Correlation, Debt, DebtResponse,
+        LiquidateMsg, LiquidationPreferences, ScheduledRepay,
     },
-    NativeBalancePlus, OracleValue,
+    NativeBalancePlus, OracleValue, PageRequest,
 };
 use sha2::{Digest, Sha256};
-use std::ops::{Add, Div};
+use std::{cmp::min, collections::BTreeMap, ops::Add};
 pub static ACCOUNTS_KEY: &str = "a";
 pub static ACCOUNTS_KEY_OWNER: &str = "a__o";
 pub static ACCOUNTS_KEY_OWNER_TAG: &str = "a__ot";
 pub static ACCOUNTS_KEY_TAG: &str = "a__t";
+static FIN_ORDERS_LIMIT: u8 = 30;
 
 #[cw_serde]
 struct Stored {
@@ -25,6 +34,18 @@ struct Stored {
     #[serde(default)]
     tag: String,
     liquidation_preferences: LiquidationPreferences,
+    #[serde(default)]
+    last_liquidation: Option<LiquidationRecord>,
+    #[serde(default)]
+    preference_template: Option<String>,
+    #[serde(default)]
+    execute_unrestricted: bool,
+    #[serde(default)]
+    deleverage: bool,
+    #[serde(default)]
+    repay_schedule: Option<ScheduledRepay>,
+    #[serde(default)]
+    borrow_caps: Vec<Coin>,
 }
 
 #[cw_serde]
@@ -35,6 +56,41 @@ pub struct CreditAccount {
     pub collaterals: Vec<Valued<Collateral>>,
     pub debts: Vec<Valued<Debt>>,
     pub liquidation_preferences: LiquidationPreferences,
+    pub last_liquidation: Option<LiquidationRecord>,
+    /// The correlated denoms offset against each other by [CreditAccount::margined]
+    #[serde(default)]
+    pub correlations: Vec<Correlation>,
+    /// The owner's template followed in place of `liquidation_preferences`
+    #[serde(default)]
+    pub preference_template: Option<String>,
+    /// The preferences of `preference_template`, resolved when the account is loaded
+    #[serde(default)]
+    pub template_preferences: Option<LiquidationPreferences>,
+    /// Exempts the Account from [Config::execute_allowlist]
+    #[serde(default)]
+    pub execute_unrestricted: bool,
+    /// Opts the Account in to [rujira_rs::ghost::credit::ExecuteMsg::Deleverage]
+    #[serde(default)]
+    pub deleverage: bool,
+    /// Repays debt from the Account's balance with [rujira_rs::ghost::credit::ExecuteMsg::CrankRepay]
+    #[serde(default)]
+    pub repay_schedule: Option<ScheduledRepay>,
+    /// The owner's caps on the debt [rujira_rs::ghost::credit::AccountMsg::Borrow] can reach, by denom
+    #[serde(default)]
+    pub borrow_caps: Vec<Coin>,
+    /// Borrowed by the Account messages executed so far, not yet reflected in `debts`
+    #[serde(skip)]
+    pub borrowed: NativeBalance,
+    /// The Account's orders with fills awaiting withdrawal, by fin pair, claimed by [CreditAccount::claim_msgs]
+    #[serde(skip)]
+    pub fin_claims: Vec<(Addr, Vec<fin::OrderTarget>)>,
+}
+
+/// The time of the most recent completed liquidation, and the LTV that it was triggered at
+#[cw_serde]
+pub struct LiquidationRecord {
+    pub time: Timestamp,
+    pub ltv: Decimal,
 }
 
 #[cw_serde]
@@ -69,8 +125,34 @@ impl CreditAccount {
             collaterals: Default::default(),
             debts: Default::default(),
             liquidation_preferences: Default::default(),
+            last_liquidation: None,
+            correlations: vec![],
+            preference_template: None,
+            template_preferences: None,
+            execute_unrestricted: false,
+            deleverage: false,
+            repay_schedule: None,
+            borrow_caps: vec![],
+            borrowed: NativeBalance::default(),
+            fin_claims: vec![],
         }
     }
+
+    /// Withdraws the filled amounts of the Account's fin orders into the Account, where they
+    /// are valued at their full collateral ratio instead of through the pair's haircut
+    pub fn claim_msgs(&self) -> StdResult<Vec<CosmosMsg>> {
+        self.fin_claims
+            .iter()
+            .map(|(contract, targets)| {
+                self.account.execute(
+                    contract.to_string(),
+                    to_json_binary(&fin::ExecuteMsg::Order((targets.clone(), None)))?,
+                    vec![],
+                )
+            })
+            .collect()
+    }
+
     pub fn create(
         deps: Deps,
         code_id: u64,
@@ -124,7 +206,7 @@ impl CreditAccount {
         config: &Config,
         contract: &Addr,
         cursor: Option<Addr>,
-        limit: Option<usize>,
+        limit: usize,
     ) -> Result<Vec<Self>, ContractError> {
         Self::store()
             .range(
@@ -133,7 +215,7 @@ impl CreditAccount {
                 None,
                 Order::Ascending,
             )
-            .take(limit.unwrap_or(100))
+            .take(limit)
             .map(|res| res?.1.to_credit_account(deps, contract, config))
             .collect()
     }
@@ -149,30 +231,79 @@ impl CreditAccount {
             .to_credit_account(deps, contract, config)
     }
 
-    pub fn adjusted_ltv(&self) -> Decimal {
-        let collateral = self
-            .collaterals
+    /// The total USD value of collateral, adjusted by collateral ratios
+    pub fn collateral_adjusted(&self) -> Decimal {
+        self.collaterals
             .iter()
             .map(|x| x.value_adjusted)
             .collect::<Vec<Decimal>>()
             .into_iter()
             .reduce(|a, b| a + b)
-            .unwrap_or_default();
+            .unwrap_or_default()
+    }
 
-        let debt = self
-            .debts
+    /// The total USD value of debt
+    pub fn debt_value(&self) -> Decimal {
+        self.debts
             .iter()
             .map(|x| x.value)
             .collect::<Vec<Decimal>>()
             .into_iter()
             .reduce(|a, b| a + b)
-            .unwrap_or_default();
+            .unwrap_or_default()
+    }
+
+    /// The adjusted collateral and the debt, both net of the value offset by correlated pairs. Each
+    /// pair matches the collateral and debt left unmatched by the pairs before it, and removes
+    /// `offset` of the matched debt, and of the adjusted value of the matched collateral
+    pub fn margined(&self) -> (Decimal, Decimal) {
+        let mut collateral = self.collateral_adjusted();
+        let mut debt = self.debt_value();
+        let mut held: BTreeMap<&str, (Decimal, Decimal)> = self
+            .collaterals
+            .iter()
+            .filter_map(|x| match &x.item {
+                Collateral::Coin(coin) => Some((coin.denom.as_str(), (x.value, x.value_adjusted))),
+                Collateral::FinOrders { .. } => None,
+            })
+            .collect();
+        let mut owed: BTreeMap<&str, Decimal> = self
+            .debts
+            .iter()
+            .map(|x| (x.item.denom(), x.value))
+            .collect();
+
+        for pair in self.correlations.iter() {
+            let (Some((value, adjusted)), Some(remaining)) = (
+                held.get_mut(pair.collateral.as_str()),
+                owed.get_mut(pair.debt.as_str()),
+            ) else {
+                continue;
+            };
+            let matched = min(*value, *remaining);
+            if matched.is_zero() {
+                continue;
+            }
+            let matched_adjusted = *adjusted * (matched / *value);
+            collateral = collateral.saturating_sub(matched_adjusted * pair.offset);
+            debt = debt.saturating_sub(matched * pair.offset);
+            *value -= matched;
+            *adjusted = adjusted.saturating_sub(matched_adjusted);
+            *remaining -= matched;
+        }
+
+        (collateral, debt)
+    }
+
+    pub fn adjusted_ltv(&self) -> Decimal {
+        let (collateral, debt) = self.margined();
 
         if debt.is_zero() {
             return Decimal::zero();
         }
 
-        debt.div(collateral)
+        // Debt with no collateral left to back it is unbounded
+        debt.checked_div(collateral).unwrap_or(Decimal::MAX)
     }
 
     pub fn check_safe(&self, limit: &Decimal) -> Result<(), ContractError> {
@@ -190,6 +321,33 @@ impl CreditAccount {
         Ok(())
     }
 
+    /// An Account can hold no debt, or debt with a value of at least `min`
+    pub fn check_min_debt(&self, min: &Decimal) -> Result<(), ContractError> {
+        let value = self.debt_value();
+        ensure!(
+            value.is_zero() || value.ge(min),
+            ContractError::DebtBelowMinimum { value, min: *min }
+        );
+        Ok(())
+    }
+
+    /// Within `cooldown` seconds of the previous liquidation, the Account can only be liquidated again
+    /// if its LTV is strictly higher than the LTV that triggered the previous liquidation
+    pub fn check_cooldown(&self, now: Timestamp, cooldown: u64) -> Result<(), ContractError> {
+        if let Some(last) = &self.last_liquidation {
+            if now < last.time.plus_seconds(cooldown) {
+                ensure!(
+                    self.adjusted_ltv().gt(&last.ltv),
+                    ContractError::LiquidationCooldown {
+                        ltv: self.adjusted_ltv(),
+                        last: last.ltv
+                    }
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn store<'a>() -> IndexedMap<Addr, Stored, AccountIndexes<'a>> {
         IndexedMap::new(
             ACCOUNTS_KEY,
@@ -232,12 +390,142 @@ impl CreditAccount {
         self.liquidation_preferences.messages = msgs
     }
 
+    pub fn set_preference_template(
+        &mut self,
+        deps: Deps,
+        name: Option<String>,
+    ) -> Result<(), ContractError> {
+        self.template_preferences = match &name {
+            Some(name) => Some(
+                PREFERENCE_TEMPLATES
+                    .may_load(deps.storage, (self.owner.clone(), name.clone()))?
+                    .ok_or_else(|| ContractError::PreferenceTemplateNotFound {
+                        name: name.clone(),
+                    })?,
+            ),
+            None => None,
+        };
+        self.preference_template = name;
+        Ok(())
+    }
+
+    /// The preferences enforced on liquidation: the followed template's, if it still exists, else the Account's own
+    pub fn preferences(&self) -> &LiquidationPreferences {
+        self.template_preferences
+            .as_ref()
+            .unwrap_or(&self.liquidation_preferences)
+    }
+
     fn balance(&self) -> NativeBalance {
         self.collaterals
             .iter()
             .fold(NativeBalance::default(), |agg, v| v.item.balance().add(agg))
     }
 
+    /// The denoms of outstanding debts, largest USD value first and ties ordered by denom
+    pub fn repay_order(&self) -> Vec<String> {
+        let mut debts: Vec<(Decimal, String)> = self
+            .debts
+            .iter()
+            .flat_map(|x| {
+                NativeBalance::from(&x.item)
+                    .into_vec()
+                    .into_iter()
+                    .filter(|c| !c.amount.is_zero())
+                    .map(|c| (x.value, c.denom))
+            })
+            .collect();
+        debts.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        debts.into_iter().map(|(_, denom)| denom).collect()
+    }
+
+    /// The Account valued as holding `balance` in place of its collateral coins, once `repaid` of its
+    /// debts has been repaid. Orders on fin pairs are unchanged
+    pub fn simulated(
+        &self,
+        deps: Deps,
+        config: &Config,
+        balance: &NativeBalance,
+        repaid: &NativeBalance,
+    ) -> Result<Self, ContractError> {
+        let amount = |balance: &NativeBalance, denom: &str| {
+            balance
+                .0
+                .iter()
+                .find(|x| x.denom == denom)
+                .map(|x| x.amount)
+                .unwrap_or_default()
+        };
+        let mut account = self.clone();
+        account.collaterals = vec![];
+        account.debts = vec![];
+
+        for denom in config.collateral_ratios.keys() {
+            let item = Collateral::try_from(&coin(amount(balance, denom).u128(), denom))?;
+            let value = item.value_usd(deps.querier)?;
+            if value.is_zero() {
+                continue;
+            }
+            account.collaterals.push(Valued {
+                value,
+                value_adjusted: item.value_adjusted(deps, &config.collateral_ratios)?,
+                item,
+            });
+        }
+        account.collaterals.extend(
+            self.collaterals
+                .iter()
+                .filter(|x| matches!(x.item, Collateral::FinOrders { .. }))
+                .cloned(),
+        );
+
+        for debt in self.debts.iter() {
+            let item = debt.item.repaid(amount(repaid, debt.item.denom()));
+            let value = item.value_usd(deps.querier)?;
+            if value.is_zero() {
+                continue;
+            }
+            account.debts.push(Valued {
+                item,
+                value,
+                value_adjusted: value,
+            });
+        }
+
+        Ok(account)
+    }
+
+    /// Sets the owner's cap on the debt of `denom`, removing it with `None`
+    pub fn set_borrow_cap(&mut self, denom: &str, cap: Option<Uint128>) {
+        self.borrow_caps.retain(|x| x.denom != denom);
+        if let Some(cap) = cap {
+            self.borrow_caps.push(coin(cap.u128(), denom));
+            self.borrow_caps.sort_by(|a, b| a.denom.cmp(&b.denom));
+        }
+    }
+
+    /// Records a borrow of `amount`, checking that it keeps the debt of its denom within the owner's cap
+    pub fn borrow(&mut self, amount: &Coin) -> Result<(), ContractError> {
+        self.borrowed += amount.clone();
+        let Some(cap) = self.borrow_caps.iter().find(|x| x.denom == amount.denom) else {
+            return Ok(());
+        };
+        let debt = (self.debt() + self.borrowed.clone())
+            .into_vec()
+            .into_iter()
+            .find(|x| x.denom == amount.denom)
+            .map(|x| x.amount)
+            .unwrap_or_default();
+        ensure!(
+            debt <= cap.amount,
+            ContractError::BorrowCapExceeded {
+                cap: cap.clone(),
+                debt: coin(debt.u128(), &amount.denom),
+            }
+        );
+        Ok(())
+    }
+
     fn debt(&self) -> NativeBalance {
         self.debts.iter().fold(NativeBalance::default(), |agg, v| {
             NativeBalance::from(&v.item).add(agg)
@@ -255,9 +543,7 @@ impl CreditAccount {
         let spent = old.balance().sent(&balance);
 
         for coin in spent.clone().into_vec() {
-            self.liquidation_preferences
-                .order
-                .validate(&coin, &balance)?;
+            self.preferences().order.validate(&coin, &balance)?;
         }
 
         let spent_usd = spent.value_usd(deps.querier)?;
@@ -295,6 +581,20 @@ impl Stored {
             collaterals: vec![],
             debts: vec![],
             liquidation_preferences: self.liquidation_preferences.clone(),
+            last_liquidation: self.last_liquidation.clone(),
+            correlations: config.correlations.clone(),
+            preference_template: self.preference_template.clone(),
+            template_preferences: match &self.preference_template {
+                Some(name) => PREFERENCE_TEMPLATES
+                    .may_load(deps.storage, (self.owner.clone(), name.clone()))?,
+                None => None,
+            },
+            execute_unrestricted: self.execute_unrestricted,
+            deleverage: self.deleverage,
+            repay_schedule: self.repay_schedule.clone(),
+            borrow_caps: self.borrow_caps.clone(),
+            borrowed: NativeBalance::default(),
+            fin_claims: vec![],
         };
 
         for denom in config.collateral_ratios.keys() {
@@ -309,8 +609,25 @@ impl Stored {
             });
         }
 
+        for (contract, haircut) in config.fin_haircuts.iter() {
+            let (item, claims) = self.fin_orders(deps, config, contract, haircut)?;
+            if !claims.is_empty() {
+                ca.fin_claims.push((Addr::unchecked(contract), claims));
+            }
+            let value = item.value_usd(deps.querier)?;
+            if value.is_zero() {
+                continue;
+            }
+            ca.collaterals.push(Valued {
+                value,
+                value_adjusted: item.value_adjusted(deps, &config.collateral_ratios)?,
+                item,
+            });
+        }
+
         for vault in BORROW.range(deps.storage, None, None, Order::Ascending) {
-            let debt = Debt::from(vault?.1.delegate(deps.querier, contract, &self.account)?);
+            let (denom, vault) = vault?;
+            let debt = Debt::from(vault.delegate(deps.querier, &denom, contract, &self.account)?);
             let value = debt.value_usd(deps.querier)?;
             if value.is_zero() {
                 continue;
@@ -324,6 +641,57 @@ impl Stored {
 
         Ok(ca)
     }
+
+    /// Sums the remaining offer and the filled amount awaiting withdrawal of every order the
+    /// account holds on a fin pair. Only denoms with a collateral ratio are counted.
+    /// Also returns the targets that claim the orders with a filled amount
+    fn fin_orders(
+        &self,
+        deps: Deps,
+        config: &Config,
+        contract: &str,
+        haircut: &Decimal,
+    ) -> Result<(Collateral, Vec<fin::OrderTarget>), ContractError> {
+        let pair: fin::ConfigResponse = deps
+            .querier
+            .query_wasm_smart(contract, &fin::QueryMsg::Config {})?;
+        let mut balance = NativeBalance::default();
+        let mut claims = vec![];
+        let mut start_after = None;
+        loop {
+            let res: fin::OrdersResponse = deps.querier.query_wasm_smart(
+                contract,
+                &fin::QueryMsg::Orders {
+                    owner: self.account.to_string(),
+                    side: None,
+                    page: PageRequest::new(start_after, Some(FIN_ORDERS_LIMIT)),
+                },
+            )?;
+            for order in res.orders.iter() {
+                balance += coin(order.remaining.u128(), pair.denoms.bid(&order.side));
+                balance += coin(order.filled.u128(), pair.denoms.ask(&order.side));
+                if !order.filled.is_zero() {
+                    claims.push((order.side.clone(), order.price.clone(), None));
+                }
+            }
+            match res.page.next {
+                Some(next) => start_after = Some(next),
+                None => break,
+            }
+        }
+        balance.normalize();
+
+        let collateral = Collateral::FinOrders {
+            contract: Addr::unchecked(contract),
+            haircut: *haircut,
+            balance: balance
+                .into_vec()
+                .into_iter()
+                .filter(|x| config.collateral_ratios.contains_key(&x.denom))
+                .collect(),
+        };
+        Ok((collateral, claims))
+    }
 }
 
 impl From<&CreditAccount> for Stored {
@@ -333,6 +701,12 @@ impl From<&CreditAccount> for Stored {
             tag: value.tag.clone(),
             account: value.account.contract(),
             liquidation_preferences: value.liquidation_preferences.clone(),
+            last_liquidation: value.last_liquidation.clone(),
+            preference_template: value.preference_template.clone(),
+            execute_unrestricted: value.execute_unrestricted,
+            deleverage: value.deleverage,
+            repay_schedule: value.repay_schedule.clone(),
+            borrow_caps: value.borrow_caps.clone(),
         }
     }
 }
@@ -350,7 +724,14 @@ impl From<CreditAccount> for AccountResponse {
                 .map(CollateralResponse::from)
                 .collect(),
             debts: value.debts.iter().map(DebtResponse::from).collect(),
-            liquidation_preferences: value.liquidation_preferences,
+            liquidation_preferences: value
+                .template_preferences
+                .unwrap_or(value.liquidation_preferences),
+            preference_template: value.preference_template,
+            execute_unrestricted: value.execute_unrestricted,
+            deleverage: value.deleverage,
+            repay_schedule: value.repay_schedule,
+            borrow_caps: value.borrow_caps,
         }
     }
 }
@@ -1,9 +1,11 @@
 use std::collections::BTreeMap;
 
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Decimal, StdResult, Storage};
+use cosmwasm_std::{Addr, Decimal, StdResult, Storage, Uint128};
 use cw_storage_plus::Item;
-use rujira_rs::ghost::credit::{ConfigResponse, ConfigUpdate, InstantiateMsg};
+use rujira_rs::ghost::credit::{
+    ClaimAdapter, ConfigResponse, ConfigUpdate, Correlation, InstantiateMsg, PokeReward,
+};
 
 use crate::ContractError;
 
@@ -14,12 +16,51 @@ pub type CollateralRatios = BTreeMap<String, Decimal>;
 pub struct Config {
     pub code_id: u64,
     pub collateral_ratios: CollateralRatios,
+    /// Haircuts applied to open orders on whitelisted fin pairs, keyed by contract address
+    #[serde(default)]
+    pub fin_haircuts: BTreeMap<String, Decimal>,
     pub fee_liquidation: Decimal,
     pub fee_liquidator: Decimal,
     pub fee_address: Addr,
     pub liquidation_max_slip: Decimal,
     pub liquidation_threshold: Decimal,
     pub adjustment_threshold: Decimal,
+    /// Seconds after a liquidation during which the Account can only be liquidated again at a higher LTV
+    #[serde(default)]
+    pub liquidation_cooldown: u64,
+    /// Contract notified after every Account update and liquidation step
+    #[serde(default)]
+    pub monitor: Option<Addr>,
+    /// The minimum USD value of debt an Account can hold. Debt below it isn't worth liquidating
+    #[serde(default)]
+    pub min_debt_value: Decimal,
+    /// Margin below `adjustment_threshold` that Account updates must keep, so that accruing
+    /// interest doesn't immediately push a freshly adjusted Account over the threshold
+    #[serde(default)]
+    pub safety_buffer: Decimal,
+    /// Messages executed by an Account to claim the rewards of a collateral, keyed by denom
+    #[serde(default)]
+    pub claim_adapters: BTreeMap<String, ClaimAdapter>,
+    /// Contract receiving `fee_backstop` of the liquidation fee, as an insurance fund
+    #[serde(default)]
+    pub backstop: Option<Addr>,
+    #[serde(default)]
+    pub fee_backstop: Decimal,
+    /// Collateral and debt denoms offset against each other when computing the LTV of an Account
+    #[serde(default)]
+    pub correlations: Vec<Correlation>,
+    /// Contracts that Accounts can call with `AccountMsg::Execute`, when set
+    #[serde(default)]
+    pub execute_allowlist: Option<Vec<Addr>>,
+    /// The LTV from which opted in Accounts can be deleveraged by executing their liquidation preferences
+    #[serde(default)]
+    pub deleverage_threshold: Option<Decimal>,
+    /// Paid to callers of `ExecuteMsg::Poke` from a share of liquidation fees retained by the contract
+    #[serde(default)]
+    pub poke_reward: Option<PokeReward>,
+    /// The share of each scheduled repayment paid to the caller of `ExecuteMsg::CrankRepay`
+    #[serde(default)]
+    pub repay_crank_fee: Decimal,
 }
 
 impl From<InstantiateMsg> for Config {
@@ -27,12 +68,25 @@ impl From<InstantiateMsg> for Config {
         Self {
             code_id: value.code_id,
             collateral_ratios: BTreeMap::default(),
+            fin_haircuts: BTreeMap::default(),
             fee_liquidation: value.fee_liquidation,
             fee_liquidator: value.fee_liquidator,
             fee_address: value.fee_address,
             liquidation_max_slip: value.liquidation_max_slip,
             liquidation_threshold: value.liquidation_threshold,
             adjustment_threshold: value.adjustment_threshold,
+            liquidation_cooldown: 0,
+            monitor: None,
+            min_debt_value: Decimal::zero(),
+            safety_buffer: Decimal::zero(),
+            claim_adapters: BTreeMap::default(),
+            backstop: None,
+            fee_backstop: Decimal::zero(),
+            correlations: vec![],
+            execute_allowlist: None,
+            deleverage_threshold: None,
+            poke_reward: None,
+            repay_crank_fee: Decimal::zero(),
         }
     }
 }
@@ -42,12 +96,25 @@ impl From<Config> for ConfigResponse {
         Self {
             code_id: value.code_id,
             collateral_ratios: value.collateral_ratios,
+            fin_haircuts: value.fin_haircuts,
             fee_liquidation: value.fee_liquidation,
             fee_liquidator: value.fee_liquidator,
             fee_address: value.fee_address,
             liquidation_max_slip: value.liquidation_max_slip,
             liquidation_threshold: value.liquidation_threshold,
             adjustment_threshold: value.adjustment_threshold,
+            liquidation_cooldown: value.liquidation_cooldown,
+            monitor: value.monitor,
+            min_debt_value: value.min_debt_value,
+            safety_buffer: value.safety_buffer,
+            claim_adapters: value.claim_adapters,
+            backstop: value.backstop,
+            fee_backstop: value.fee_backstop,
+            correlations: value.correlations,
+            execute_allowlist: value.execute_allowlist,
+            deleverage_threshold: value.deleverage_threshold,
+            poke_reward: value.poke_reward,
+            repay_crank_fee: value.repay_crank_fee,
         }
     }
 }
@@ -79,6 +146,27 @@ impl Config {
         if let Some(adjustment_threshold) = update.adjustment_threshold {
             self.adjustment_threshold = adjustment_threshold;
         }
+        if let Some(liquidation_cooldown) = update.liquidation_cooldown {
+            self.liquidation_cooldown = liquidation_cooldown;
+        }
+        if let Some(min_debt_value) = update.min_debt_value {
+            self.min_debt_value = min_debt_value;
+        }
+        if let Some(safety_buffer) = update.safety_buffer {
+            self.safety_buffer = safety_buffer;
+        }
+    }
+
+    /// Whether `AccountMsg::Execute` can call `contract`, ignoring the opt-out of the Account
+    pub fn executable(&self, contract: &Addr) -> bool {
+        self.execute_allowlist
+            .as_ref()
+            .map_or(true, |x| x.contains(contract))
+    }
+
+    /// The LTV an Account must stay below after being adjusted by its owner
+    pub fn safe_threshold(&self) -> Decimal {
+        self.adjustment_threshold.saturating_sub(self.safety_buffer)
     }
 
     pub fn validate(&self) -> Result<(), ContractError> {
@@ -88,6 +176,20 @@ impl Config {
                 value: self.adjustment_threshold.to_string(),
             });
         }
+        if let Some(threshold) = self.deleverage_threshold {
+            if threshold <= self.adjustment_threshold || threshold >= self.liquidation_threshold {
+                return Err(ContractError::InvalidConfig {
+                    key: "deleverage_threshold".to_string(),
+                    value: threshold.to_string(),
+                });
+            }
+        }
+        if self.safety_buffer >= self.adjustment_threshold {
+            return Err(ContractError::InvalidConfig {
+                key: "safety_buffer".to_string(),
+                value: self.safety_buffer.to_string(),
+            });
+        }
         if self.liquidation_max_slip >= Decimal::one() {
             return Err(ContractError::InvalidConfig {
                 key: "liquidation_max_slip".to_string(),
@@ -102,6 +204,14 @@ impl Config {
                 });
             }
         }
+        for (k, v) in self.fin_haircuts.iter() {
+            if v > &Decimal::one() {
+                return Err(ContractError::InvalidConfig {
+                    key: format!("#{k} fin_haircut"),
+                    value: v.to_string(),
+                });
+            }
+        }
 
         if self.fee_liquidation >= Decimal::percent(5) {
             return Err(ContractError::InvalidConfig {
@@ -117,9 +227,58 @@ impl Config {
             });
         }
 
+        if self.fee_backstop > Decimal::one() {
+            return Err(ContractError::InvalidConfig {
+                key: "fee_backstop".to_string(),
+                value: self.fee_backstop.to_string(),
+            });
+        }
+
+        if let Some(reward) = &self.poke_reward {
+            if reward.share > Decimal::one() || reward.rate > Decimal::one() {
+                return Err(ContractError::InvalidConfig {
+                    key: "poke_reward".to_string(),
+                    value: format!("{}/{}", reward.share, reward.rate),
+                });
+            }
+        }
+
+        if self.repay_crank_fee >= Decimal::one() {
+            return Err(ContractError::InvalidConfig {
+                key: "repay_crank_fee".to_string(),
+                value: self.repay_crank_fee.to_string(),
+            });
+        }
+
+        for c in self.correlations.iter() {
+            if c.offset > Decimal::one() {
+                return Err(ContractError::InvalidConfig {
+                    key: format!("#{}/{} correlation", c.collateral, c.debt),
+                    value: c.offset.to_string(),
+                });
+            }
+        }
+
         Ok(())
     }
 
+    /// Splits a liquidation fee between `fee_address` and the backstop
+    pub fn split_liquidation_fee(&self, fee: Uint128) -> (Uint128, Uint128) {
+        let backstop = match self.backstop {
+            Some(_) => fee.mul_floor(self.fee_backstop),
+            None => Uint128::zero(),
+        };
+        (fee - backstop, backstop)
+    }
+
+    /// The share of a protocol fee retained by the contract to pay the [PokeReward]
+    pub fn poke_reserve(&self, fee: Uint128) -> Uint128 {
+        self.poke_reward
+            .as_ref()
+            .map(|x| fee.mul_floor(x.share))
+            .unwrap_or_default()
+    }
+
     pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
         CONFIG.save(storage, self)
     }
@@ -134,12 +293,28 @@ mod tests {
         Config {
             code_id: 1,
             collateral_ratios: BTreeMap::default(),
+            fin_haircuts: BTreeMap::default(),
             fee_liquidation: Decimal::percent(1),
             fee_liquidator: Decimal::percent(1),
             fee_address: Addr::unchecked(""),
             liquidation_max_slip: Decimal::percent(30),
             liquidation_threshold: Decimal::percent(100),
             adjustment_threshold: Decimal::percent(90),
+            liquidation_cooldown: 0,
+            monitor: None,
+            min_debt_value: Decimal::zero(),
+            safety_buffer: Decimal::percent(1),
+            claim_adapters: BTreeMap::default(),
+            backstop: None,
+            fee_backstop: Decimal::zero(),
+            correlations: vec![],
+            execute_allowlist: None,
+            deleverage_threshold: Some(Decimal::percent(95)),
+            poke_reward: Some(PokeReward {
+                share: Decimal::percent(10),
+                rate: Decimal::percent(1),
+            }),
+            repay_crank_fee: Decimal::permille(5),
         }
         .validate()
         .unwrap();
@@ -1,27 +1,45 @@
 use crate::config::Config;
 use crate::error::ContractError;
 use crate::events::{
-    event_create_account, event_execute_account, event_execute_account_borrow,
-    event_execute_account_execute, event_execute_account_repay, event_execute_account_send,
+    event_compound, event_crank_repay, event_create_account, event_execute_account,
+    event_execute_account_borrow, event_execute_account_execute, event_execute_account_flash_close,
+    event_execute_account_repay, event_execute_account_send, event_execute_account_set_borrow_cap,
+    event_execute_account_set_deleverage, event_execute_account_set_execute_unrestricted,
     event_execute_account_set_preference_msgs, event_execute_account_set_preference_order,
-    event_execute_account_transfer, event_execute_liquidate, event_execute_liquidate_execute,
-    event_execute_liquidate_preference_error, event_execute_liquidate_repay,
+    event_execute_account_set_preference_template, event_execute_account_set_repay_schedule,
+    event_execute_account_transfer, event_execute_deleverage, event_execute_flash_close_settle,
+    event_execute_liquidate, event_execute_liquidate_execute, event_execute_liquidate_flash_borrow,
+    event_execute_liquidate_flash_repay, event_execute_liquidate_preference_error,
+    event_execute_liquidate_queue, event_execute_liquidate_repay, event_monitor_error, event_poke,
+    event_set_preference_template,
+};
+use crate::{
+    account::{CreditAccount, LiquidationRecord},
+    liquidation,
+    risk::risk_report,
+    simulate::simulate_liquidation,
+    state::{BORROW, PREFERENCE_TEMPLATES, VALUATIONS},
 };
-use crate::{account::CreditAccount, state::BORROW};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coin, coins, ensure_eq, from_json, to_json_binary, BankMsg, Binary, CosmosMsg, Deps, DepsMut,
-    Env, Event, Fraction, MessageInfo, Reply, Response, StdError, SubMsg, SubMsgResult,
+    coin, coins, ensure, ensure_eq, from_json, to_json_binary, BankMsg, Binary, Coin, CosmosMsg,
+    Decimal, Deps, DepsMut, Env, Event, MessageInfo, Order, Reply, Response, StdError, StdResult,
+    SubMsg, SubMsgResult, WasmMsg,
 };
 use cw2::set_contract_version;
-use cw_utils::NativeBalance;
+use cw_utils::{one_coin, NativeBalance};
 use rujira_rs::ghost;
 use rujira_rs::ghost::credit::{
-    AccountMsg, AccountResponse, AccountsResponse, ConfigResponse, ExecuteMsg, InstantiateMsg,
-    LiquidateMsg, QueryMsg, SudoMsg,
+    AccountMsg, AccountResponse, AccountsResponse, ConfigResponse, Correlation, ExecuteMsg,
+    InstantiateMsg, LiquidateMsg, LiquidationPreferences, MonitorAction, MonitorMsg, PokeReward,
+    PreferenceTemplate, PreferenceTemplateResponse, PreferenceTemplatesResponse, QueryMsg,
+    ScheduledRepay, SudoMsg, Valuation,
 };
 use rujira_rs::ghost::vault::Vault;
+use rujira_rs::orca;
+use rujira_rs::{CallbackData, PageResponse};
+use std::cmp::min;
 use std::ops::Sub;
 
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
@@ -29,6 +47,9 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const REPLY_ID_PREFERENCE: u64 = 0;
 const REPLY_ID_LIQUIDATOR: u64 = 1;
+const REPLY_ID_MONITOR: u64 = 2;
+const REPLY_ID_CREATE: u64 = 3;
+const ACCOUNTS_LIMIT: u8 = 100;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -54,7 +75,12 @@ pub fn execute(
     let config = Config::load(deps.storage)?;
     let ca = env.contract.address.clone();
     match msg {
-        ExecuteMsg::Create { salt, label, tag } => {
+        ExecuteMsg::Create {
+            salt,
+            label,
+            tag,
+            init_msgs,
+        } => {
             let (account, msg) = CreditAccount::create(
                 deps.as_ref(),
                 config.code_id,
@@ -64,65 +90,145 @@ pub fn execute(
                 tag,
                 salt,
             )?;
+            let event = event_create_account(&account);
+            let addr = account.id().to_string();
             account.save(deps)?;
+            if init_msgs.is_empty() && info.funds.is_empty() {
+                return Ok(Response::default().add_message(msg).add_event(event));
+            }
 
+            // The Account must exist before it can be funded and its msgs executed
             Ok(Response::default()
-                .add_message(msg)
-                .add_event(event_create_account(&account)))
+                .add_submessage(
+                    SubMsg::reply_on_success(msg, REPLY_ID_CREATE)
+                        .with_payload(to_json_binary(&(addr, info.funds, init_msgs))?),
+                )
+                .add_event(event))
         }
         ExecuteMsg::Liquidate { addr, msgs } => {
             let account =
                 CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
-            account.check_unsafe(&config.liquidation_threshold)?;
-            let mut queue: Vec<(LiquidateMsg, bool)> =
-                msgs.iter().map(|x| (x.clone(), false)).collect();
-            queue.reverse();
-            let mut prefs: Vec<(LiquidateMsg, bool)> = account
-                .liquidation_preferences
-                .messages
-                .iter()
-                .map(|x| (x.clone(), true))
-                .collect();
-            prefs.reverse();
-            queue.append(&mut prefs);
+            let (flash, queue) = liquidation::start(&env, &config, &account, msgs)?;
+
+            let msg = ExecuteMsg::DoLiquidate {
+                addr: account.id().to_string(),
+                queue,
+                payload: to_json_binary(&account)?,
+                flash: flash.clone(),
+            };
+            // A flash borrow wraps the liquidation in a callback from the vault,
+            // which checks that the loan has been repaid once it completes
+            let msg = match flash {
+                None => msg.call(&ca)?,
+                Some(flash) => BORROW
+                    .load(deps.storage, flash.denom.clone())?
+                    .market_msg_flash_loan(&flash, CallbackData::Msg(to_json_binary(&msg)?))?,
+            };
 
             Ok(Response::default()
-                .add_message(
-                    ExecuteMsg::DoLiquidate {
-                        addr: account.id().to_string(),
-                        queue,
-                        payload: to_json_binary(&account)?,
-                    }
-                    .call(&ca)?,
-                )
+                .add_messages(account.claim_msgs()?)
+                .add_message(msg)
                 .add_event(event_execute_liquidate(&account, &info.sender)))
         }
+        ExecuteMsg::Callback(cb) => {
+            let funds = one_coin(&info)?;
+            let vault = BORROW.load(deps.storage, funds.denom.clone())?;
+            ensure_eq!(&info.sender, vault.addr(), ContractError::Unauthorized {});
+            let msg: ExecuteMsg = cb.deserialize_callback()?;
+            match &msg {
+                ExecuteMsg::DoLiquidate {
+                    addr,
+                    flash: Some(flash),
+                    ..
+                } if *flash == funds => Ok(Response::default()
+                    .add_message(BankMsg::Send {
+                        to_address: addr.clone(),
+                        amount: vec![funds.clone()],
+                    })
+                    .add_message(msg.call(&ca)?)
+                    .add_event(event_execute_liquidate_flash_borrow(&funds))),
+                ExecuteMsg::DoRepay { addr } => {
+                    let account = CreditAccount::load(
+                        deps.as_ref(),
+                        &config,
+                        &ca,
+                        deps.api.addr_validate(addr)?,
+                    )?;
+                    let debt = account
+                        .debts
+                        .iter()
+                        .flat_map(|x| NativeBalance::from(&x.item).into_vec())
+                        .find(|x| x.denom == funds.denom)
+                        .map(|x| x.amount)
+                        .unwrap_or_default();
+                    let repay = min(debt, funds.amount);
+                    let mut response = Response::default();
+                    if !repay.is_zero() {
+                        response = response.add_message(vault.market_msg_repay(
+                            Some(addr.clone()),
+                            &coin(repay.u128(), &funds.denom),
+                        )?);
+                    }
+                    if repay < funds.amount {
+                        response = response.add_message(BankMsg::Send {
+                            to_address: addr.clone(),
+                            amount: coins((funds.amount - repay).u128(), &funds.denom),
+                        });
+                    }
+                    Ok(response)
+                }
+                _ => Err(ContractError::Unauthorized {}),
+            }
+        }
         ExecuteMsg::DoLiquidate {
             addr,
             mut queue,
             payload,
+            flash,
         } => {
             ensure_eq!(info.sender, ca, ContractError::Unauthorized {});
-            let account =
+            let mut account =
                 CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
             let original_account: CreditAccount = from_json(&payload)?;
 
-            let check = account
-                // Check safe against the liquidation threshold
-                .check_safe(&config.liquidation_threshold)
-                // Check we've not gone below the adjustment threshold
-                .and_then(|_| account.check_unsafe(&config.adjustment_threshold))
-                .and_then(|_| {
-                    account.validate_liquidation(deps.as_ref(), &config, &original_account)
-                });
-            match (queue.pop(), check) {
-                (_, Ok(())) => Ok(Response::default()),
-                (None, Err(err)) => {
-                    // We're done and the Account hasn't passed checks. Fail
-                    Err(err)
+            // Repay the flash loan from the Account before it is checked
+            if let Some(flash) = flash
+                .as_ref()
+                .filter(|_| liquidation::flash_repay_due(&queue))
+            {
+                let vault = BORROW.load(deps.storage, flash.denom.clone())?;
+                return Ok(Response::default()
+                    .add_message(account.account.send(ca.to_string(), vec![flash.clone()])?)
+                    .add_message(vault.market_msg_flash_repay(flash)?)
+                    .add_message(
+                        ExecuteMsg::DoLiquidate {
+                            addr,
+                            queue,
+                            payload,
+                            flash: None,
+                        }
+                        .call(&ca)?,
+                    )
+                    .add_event(event_execute_liquidate_flash_repay(flash)));
+            }
+
+            let check =
+                liquidation::check(deps.as_ref(), &config, &account, &original_account, &flash);
+            match liquidation::next(&mut queue, check) {
+                Ok(None) => {
+                    account.last_liquidation = Some(LiquidationRecord {
+                        time: env.block.time,
+                        ltv: original_account.adjusted_ltv(),
+                    });
+                    let monitor = monitor_msg(&config, &account, MonitorAction::Liquidated)?;
+                    account.save(deps)?;
+                    Ok(Response::default().add_submessages(monitor))
                 }
-                (Some((msg, is_preference)), Err(_)) => {
+                // We're done and the Account hasn't passed checks. Fail
+                Err(err) => Err(err),
+                Ok(Some((msg, is_preference))) => {
                     // Not safe, more messages to go. Continue
+                    let monitor = monitor_msg(&config, &account, MonitorAction::LiquidateStep)?;
                     Ok(execute_liquidate(
                         deps.as_ref(),
                         env.clone(),
@@ -141,18 +247,94 @@ pub fn execute(
                             addr: account.id().to_string(),
                             queue,
                             payload,
+                            flash,
                         }
                         .call(&ca)?,
-                    ))
+                    )
+                    .add_submessages(monitor))
                 }
             }
         }
+        ExecuteMsg::Deleverage { addr } => {
+            let threshold = config
+                .deleverage_threshold
+                .ok_or(ContractError::DeleverageDisabled {})?;
+            let account =
+                CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
+            ensure!(account.deleverage, ContractError::DeleverageDisabled {});
+            account.check_unsafe(&threshold)?;
+            // Past the liquidation threshold the Account is liquidated instead
+            account.check_safe(&config.liquidation_threshold)?;
+            let mut queue = account.preferences().messages.clone();
+            queue.reverse();
+
+            Ok(Response::default()
+                .add_messages(account.claim_msgs()?)
+                .add_message(
+                    ExecuteMsg::DoDeleverage {
+                        addr: account.id().to_string(),
+                        queue,
+                        payload: to_json_binary(&account)?,
+                    }
+                    .call(&ca)?,
+                )
+                .add_event(event_execute_deleverage(&account, &info.sender)))
+        }
+        ExecuteMsg::DoDeleverage {
+            addr,
+            mut queue,
+            payload,
+        } => {
+            ensure_eq!(info.sender, ca, ContractError::Unauthorized {});
+            let threshold = config
+                .deleverage_threshold
+                .ok_or(ContractError::DeleverageDisabled {})?;
+            let account =
+                CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
+            let original_account: CreditAccount = from_json(&payload)?;
+            let check = account.check_safe(&threshold).and_then(|_| {
+                account.validate_liquidation(deps.as_ref(), &config, &original_account)
+            });
+            match (queue.pop(), check) {
+                (_, Ok(())) => Ok(Response::default()),
+                (None, Err(err)) => Err(err),
+                (Some(msg), Err(_)) => {
+                    Ok(
+                        execute_deleverage(deps.as_ref(), env, info, &config, msg, &account)?
+                            .add_message(
+                                ExecuteMsg::DoDeleverage {
+                                    addr,
+                                    queue,
+                                    payload,
+                                }
+                                .call(&ca)?,
+                            ),
+                    )
+                }
+            }
+        }
+        ExecuteMsg::DoRepay { .. } => Err(ContractError::Unauthorized {}),
+        ExecuteMsg::DoFlashClose { addr, mut queue } => {
+            ensure_eq!(info.sender, ca, ContractError::Unauthorized {});
+            let account =
+                CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
+            match queue.pop() {
+                Some(msg) => Ok(
+                    execute_flash_close(deps.as_ref(), env, &config, msg, &account)?
+                        .add_message(ExecuteMsg::DoFlashClose { addr, queue }.call(&ca)?),
+                ),
+                None => settle_flash_close(deps.as_ref(), env, &config, &account),
+            }
+        }
 
         ExecuteMsg::Account { addr, msgs } => {
             let mut account =
                 CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
             ensure_eq!(account.owner, info.sender, ContractError::Unauthorized {});
-            let mut response = Response::default().add_event(event_execute_account(&account));
+            let min_debt = msgs.iter().any(AccountMsg::changes_debt);
+            let mut response = Response::default()
+                .add_messages(account.claim_msgs()?)
+                .add_event(event_execute_account(&account));
             for msg in msgs {
                 let (messages, events) =
                     execute_account(deps.as_ref(), env.clone(), &config, msg, &mut account)?;
@@ -160,13 +342,239 @@ pub fn execute(
             }
             account.save(deps)?;
 
-            Ok(response.add_message(ExecuteMsg::CheckAccount { addr }.call(&ca)?))
+            Ok(response.add_message(ExecuteMsg::CheckAccount { addr, min_debt }.call(&ca)?))
         }
-        ExecuteMsg::CheckAccount { addr } => {
+        ExecuteMsg::Compound { addr, denoms } => {
             let account =
                 CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
-            account.check_safe(&config.adjustment_threshold)?;
-            Ok(Response::default())
+            let mut response = Response::default();
+            let mut claimed = vec![];
+            for denom in denoms {
+                let adapter = config.claim_adapters.get(&denom).ok_or_else(|| {
+                    ContractError::NoClaimAdapter {
+                        denom: denom.clone(),
+                    }
+                })?;
+                // Collaterals can share a claim adapter, which only needs executing once
+                if claimed.contains(&adapter) {
+                    continue;
+                }
+                claimed.push(adapter);
+                response = response
+                    .add_message(account.account.execute(
+                        adapter.contract_addr.clone(),
+                        adapter.msg.clone(),
+                        vec![],
+                    )?)
+                    .add_event(event_compound(&account, &denom, &adapter.contract_addr));
+            }
+            Ok(response)
+        }
+        ExecuteMsg::CheckAccount { addr, min_debt } => {
+            let account =
+                CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
+            account.check_safe(&config.safe_threshold())?;
+            // Debt that falls below the minimum in value doesn't block unrelated changes
+            if min_debt {
+                account.check_min_debt(&config.min_debt_value)?;
+            }
+            VALUATIONS.save(
+                deps.storage,
+                account.id(),
+                &Valuation {
+                    ltv: account.adjusted_ltv(),
+                    liquidatable: false,
+                    timestamp: env.block.time,
+                },
+            )?;
+            Ok(Response::default().add_submessages(monitor_msg(
+                &config,
+                &account,
+                MonitorAction::Account,
+            )?))
+        }
+        ExecuteMsg::Poke { addr } => {
+            let account =
+                CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
+            let liquidatable = account.check_unsafe(&config.liquidation_threshold).is_ok();
+            // Accounts without a valuation were last seen safe by their owner's updates
+            let crossed = liquidatable
+                && !VALUATIONS
+                    .may_load(deps.storage, account.id())?
+                    .is_some_and(|x| x.liquidatable);
+            VALUATIONS.save(
+                deps.storage,
+                account.id(),
+                &Valuation {
+                    ltv: account.adjusted_ltv(),
+                    liquidatable,
+                    timestamp: env.block.time,
+                },
+            )?;
+            let reward = match (&config.poke_reward, crossed) {
+                (Some(reward), true) => poke_reward(deps.as_ref(), &env, reward)?,
+                _ => NativeBalance::default(),
+            };
+            let mut response = Response::default().add_event(event_poke(
+                &account,
+                &info.sender,
+                liquidatable,
+                &reward,
+            ));
+            if !reward.is_empty() {
+                response = response.add_message(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: reward.into_vec(),
+                });
+            }
+            Ok(response)
+        }
+        ExecuteMsg::CrankRepay { addr } => {
+            let mut account =
+                CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
+            let ScheduledRepay { schedule, due } = account
+                .repay_schedule
+                .clone()
+                .ok_or(ContractError::NoRepaySchedule {})?;
+            ensure!(env.block.time >= due, ContractError::RepayNotDue { due });
+            let denom = schedule.amount.denom.clone();
+            let (debt, value) = account
+                .debts
+                .iter()
+                .find_map(|x| {
+                    NativeBalance::from(&x.item)
+                        .into_vec()
+                        .into_iter()
+                        .find(|c| c.denom == denom)
+                        .map(|c| (c.amount, x.value))
+                })
+                .ok_or(ContractError::NoRepayableDebt {})?;
+            let balance = deps.querier.query_balance(account.id(), &denom)?.amount;
+            let repaid = min(schedule.amount.amount, debt).min(balance);
+            let fee = repaid.mul_floor(config.repay_crank_fee);
+            // The fee is drawn on top of the repayment, which is reduced when the balance is short
+            let repaid = min(repaid, balance - fee);
+            ensure!(!repaid.is_zero(), ContractError::NoRepayableDebt {});
+            // As with an owner's partial repay, it can't leave debt too small to be worth liquidating
+            let remaining = account
+                .debt_value()
+                .saturating_sub(value * Decimal::from_ratio(repaid, debt));
+            ensure!(
+                repaid == debt || remaining >= config.min_debt_value,
+                ContractError::DebtBelowMinimum {
+                    value: remaining,
+                    min: config.min_debt_value
+                }
+            );
+
+            let vault = BORROW.load(deps.storage, denom.clone())?;
+            let repaid = coin(repaid.u128(), &denom);
+            let fee = coin(fee.u128(), &denom);
+            let mut msgs = vec![
+                account
+                    .account
+                    .send(env.contract.address.clone(), vec![repaid.clone()])?,
+                vault.market_msg_repay(Some(account.id().to_string()), &repaid)?,
+            ];
+            if !fee.amount.is_zero() {
+                msgs.push(
+                    account
+                        .account
+                        .send(info.sender.clone(), vec![fee.clone()])?,
+                );
+            }
+            let event = event_crank_repay(&account, &info.sender, &repaid, &fee);
+            account.repay_schedule = Some(ScheduledRepay {
+                due: env.block.time.plus_seconds(schedule.interval),
+                schedule,
+            });
+            account.save(deps)?;
+            // The fee can be drawn from collateral, so the Account is checked as after an owner's change
+            Ok(Response::default()
+                .add_messages(msgs)
+                .add_message(
+                    ExecuteMsg::CheckAccount {
+                        addr,
+                        min_debt: false,
+                    }
+                    .call(&ca)?,
+                )
+                .add_event(event))
+        }
+        ExecuteMsg::SetPreferenceTemplate { name, template } => {
+            let key = (info.sender.clone(), name.clone());
+            match template.clone() {
+                Some(template) => {
+                    let preferences = preference_template(&config, template)?;
+                    PREFERENCE_TEMPLATES.save(deps.storage, key, &preferences)?
+                }
+                None => PREFERENCE_TEMPLATES.remove(deps.storage, key),
+            }
+            Ok(Response::default().add_event(event_set_preference_template(
+                &info.sender,
+                &name,
+                template.is_some(),
+            )))
+        }
+    }
+}
+
+/// The `rate` of the liquidation fees retained by the contract, in each borrowable denom
+fn poke_reward(deps: Deps, env: &Env, reward: &PokeReward) -> StdResult<NativeBalance> {
+    let mut amount = NativeBalance::default();
+    for denom in BORROW.keys(deps.storage, None, None, Order::Ascending) {
+        let balance = deps.querier.query_balance(&env.contract.address, denom?)?;
+        amount += coin(balance.amount.mul_floor(reward.rate).u128(), balance.denom);
+    }
+    amount.normalize();
+    Ok(amount)
+}
+
+/// Validates a template as the equivalent [AccountMsg::SetPreferenceMsgs] and [AccountMsg::SetPreferenceOrder]
+fn preference_template(
+    config: &Config,
+    template: PreferenceTemplate,
+) -> Result<LiquidationPreferences, ContractError> {
+    ensure!(
+        !template.messages.iter().any(LiquidateMsg::is_flash_borrow),
+        ContractError::InvalidFlashBorrow {}
+    );
+    let mut preferences = LiquidationPreferences {
+        messages: template.messages,
+        ..Default::default()
+    };
+    for (denom, after) in template.order {
+        for denom in [&denom, &after] {
+            if !config.collateral_ratios.contains_key(denom) {
+                return Err(ContractError::InvalidCollateral {
+                    denom: denom.clone(),
+                });
+            }
+        }
+        preferences.order.insert(denom, after)?;
+    }
+    Ok(preferences)
+}
+
+/// Notifies the monitor, if registered, of the adjusted LTV of `account`.
+/// The message is dispatched as a SubMsg so that a failing monitor can't block the Account
+fn monitor_msg(
+    config: &Config,
+    account: &CreditAccount,
+    action: MonitorAction,
+) -> Result<Vec<SubMsg>, ContractError> {
+    match &config.monitor {
+        None => Ok(vec![]),
+        Some(monitor) => {
+            let msg = MonitorMsg::CreditAccount {
+                account: account.id().to_string(),
+                ltv: account.adjusted_ltv(),
+                action,
+            };
+            Ok(vec![SubMsg::reply_on_error(
+                msg.call(monitor)?,
+                REPLY_ID_MONITOR,
+            )])
         }
     }
 }
@@ -182,6 +590,7 @@ pub fn execute_account(
 
     match msg {
         AccountMsg::Borrow(coin) => {
+            account.borrow(&coin)?;
             let vault = BORROW.load(deps.storage, coin.denom.clone())?;
             let msgs = vec![
                 vault.market_msg_borrow(Some(delegate.clone()), None, &coin)?,
@@ -194,6 +603,27 @@ pub fn execute_account(
             Ok((msgs, vec![event_execute_account_borrow(&coin)]))
         }
         AccountMsg::Repay(coin) => {
+            if let Some(vault) = Vault::underlying(&coin.denom)
+                .and_then(|x| BORROW.may_load(deps.storage, x.to_string()).transpose())
+            {
+                // Receipt tokens are withdrawn from the vault, which calls back with the underlying
+                let callback =
+                    CallbackData::Msg(to_json_binary(&ExecuteMsg::DoRepay { addr: delegate })?);
+                let msgs = vec![
+                    account
+                        .account
+                        .send(env.contract.address, vec![coin.clone()])?,
+                    WasmMsg::Execute {
+                        contract_addr: vault?.addr().to_string(),
+                        msg: to_json_binary(&ghost::vault::ExecuteMsg::Withdraw {
+                            callback: Some(callback),
+                        })?,
+                        funds: vec![coin.clone()],
+                    }
+                    .into(),
+                ];
+                return Ok((msgs, vec![event_execute_account_repay(&coin)]));
+            }
             let vault = BORROW.load(deps.storage, coin.denom.clone())?;
             let msgs = vec![
                 account
@@ -208,6 +638,13 @@ pub fn execute_account(
             msg,
             funds,
         } => {
+            ensure!(
+                account.execute_unrestricted
+                    || config.executable(&deps.api.addr_validate(&contract_addr)?),
+                ContractError::ExecuteNotAllowed {
+                    contract: contract_addr
+                }
+            );
             let event =
                 event_execute_account_execute(&contract_addr, &msg, &NativeBalance(funds.clone()));
             Ok((
@@ -226,6 +663,8 @@ pub fn execute_account(
         AccountMsg::Transfer(recipient) => {
             let recipient = deps.api.addr_validate(&recipient)?;
             account.owner = recipient.clone();
+            // Templates are kept by owner, and don't carry over to the recipient
+            account.set_preference_template(deps, None)?;
             Ok((vec![], vec![event_execute_account_transfer(&recipient)]))
         }
         AccountMsg::SetPreferenceOrder { denom, after } => {
@@ -244,12 +683,235 @@ pub fn execute_account(
             ))
         }
         AccountMsg::SetPreferenceMsgs(msgs) => {
+            ensure!(
+                !msgs.iter().any(LiquidateMsg::is_flash_borrow),
+                ContractError::InvalidFlashBorrow {}
+            );
             account.set_preference_msgs(msgs);
             Ok((vec![], vec![event_execute_account_set_preference_msgs()]))
         }
+        AccountMsg::SetPreferenceTemplate(name) => {
+            account.set_preference_template(deps, name.clone())?;
+            Ok((
+                vec![],
+                vec![event_execute_account_set_preference_template(&name)],
+            ))
+        }
+        AccountMsg::SetExecuteUnrestricted(unrestricted) => {
+            account.execute_unrestricted = unrestricted;
+            Ok((
+                vec![],
+                vec![event_execute_account_set_execute_unrestricted(unrestricted)],
+            ))
+        }
+        AccountMsg::SetRepaySchedule(schedule) => {
+            if let Some(schedule) = &schedule {
+                ensure!(
+                    !schedule.amount.amount.is_zero()
+                        && schedule.interval > 0
+                        && BORROW.has(deps.storage, schedule.amount.denom.clone()),
+                    ContractError::InvalidRepaySchedule {}
+                );
+            }
+            let event = event_execute_account_set_repay_schedule(&schedule);
+            account.repay_schedule = schedule.map(|schedule| ScheduledRepay {
+                due: env.block.time.plus_seconds(schedule.interval),
+                schedule,
+            });
+            Ok((vec![], vec![event]))
+        }
+        AccountMsg::SetBorrowCap { denom, cap } => {
+            ensure!(
+                BORROW.has(deps.storage, denom.clone()),
+                ContractError::NotBorrowable { denom }
+            );
+            account.set_borrow_cap(&denom, cap);
+            Ok((
+                vec![],
+                vec![event_execute_account_set_borrow_cap(&denom, &cap)],
+            ))
+        }
+        AccountMsg::SetDeleverage(enabled) => {
+            account.deleverage = enabled;
+            Ok((vec![], vec![event_execute_account_set_deleverage(enabled)]))
+        }
+        AccountMsg::FlashClose { mut routes } => {
+            let event = event_execute_account_flash_close(routes.len());
+            routes.reverse();
+            Ok((
+                vec![ExecuteMsg::DoFlashClose {
+                    addr: delegate,
+                    queue: routes,
+                }
+                .call(&env.contract.address)?],
+                vec![event],
+            ))
+        }
     }
 }
 
+pub fn execute_flash_close(
+    deps: Deps,
+    env: Env,
+    config: &Config,
+    msg: LiquidateMsg,
+    account: &CreditAccount,
+) -> Result<Response, ContractError> {
+    let delegate = account.id().to_string();
+
+    match best_repay(deps, msg, account)? {
+        LiquidateMsg::Repay(denom) => {
+            let vault = BORROW.load(deps.storage, denom.clone())?;
+            let balance = deps.querier.query_balance(account.id(), &denom)?;
+            // Unlike a liquidation we can't over-repay here, as the remainder is
+            // returned to the owner once all debts are cleared
+            let debt = account
+                .debts
+                .iter()
+                .flat_map(|x| NativeBalance::from(&x.item).into_vec())
+                .find(|x| x.denom == denom)
+                .map(|x| x.amount)
+                .unwrap_or_default();
+            let amount = coin(min(balance.amount, debt).u128(), denom.clone());
+
+            if amount.amount.is_zero() {
+                return Err(ContractError::ZeroDebtTokens { denom });
+            }
+
+            Ok(Response::default()
+                .add_message(
+                    account
+                        .account
+                        .send(env.contract.address, vec![amount.clone()])?,
+                )
+                .add_message(vault.market_msg_repay(Some(delegate), &amount)?)
+                .add_event(event_execute_account_repay(&amount)))
+        }
+        LiquidateMsg::Execute {
+            contract_addr,
+            msg,
+            funds,
+        } => {
+            // Routes are chosen by the owner, and are restricted as an AccountMsg::Execute is
+            ensure!(
+                account.execute_unrestricted
+                    || config.executable(&deps.api.addr_validate(&contract_addr)?),
+                ContractError::ExecuteNotAllowed {
+                    contract: contract_addr
+                }
+            );
+            let event =
+                event_execute_account_execute(&contract_addr, &msg, &NativeBalance(funds.clone()));
+            Ok(Response::default()
+                .add_message(account.account.execute(contract_addr, msg, funds)?)
+                .add_event(event))
+        }
+        LiquidateMsg::Queue {
+            contract_addr,
+            amount,
+        } => {
+            let event = event_execute_liquidate_queue(&contract_addr, &amount);
+            Ok(Response::default()
+                .add_messages(queue_msgs(&env, account, contract_addr, amount)?)
+                .add_event(event))
+        }
+        LiquidateMsg::FlashBorrow { .. } => Err(ContractError::InvalidFlashBorrow {}),
+        // Resolved by best_repay
+        LiquidateMsg::RepayBest {} => Err(ContractError::NoRepayableDebt {}),
+    }
+}
+
+/// Executes a preference message of an [ExecuteMsg::Deleverage]. Repays are made as in a
+/// flash close, without liquidation fees, and other failing messages are skipped
+fn execute_deleverage(
+    deps: Deps,
+    env: Env,
+    info: MessageInfo,
+    config: &Config,
+    msg: LiquidateMsg,
+    account: &CreditAccount,
+) -> Result<Response, ContractError> {
+    match msg {
+        LiquidateMsg::Repay(_) | LiquidateMsg::RepayBest {} => {
+            execute_flash_close(deps, env, config, msg, account)
+        }
+        msg => execute_liquidate(deps, env, info, config, msg, account, REPLY_ID_PREFERENCE),
+    }
+}
+
+/// Resolves [LiquidateMsg::RepayBest] against the balances of the Account
+fn best_repay(
+    deps: Deps,
+    msg: LiquidateMsg,
+    account: &CreditAccount,
+) -> Result<LiquidateMsg, ContractError> {
+    liquidation::resolve(account, msg, |denom| {
+        Ok(deps.querier.query_balance(account.id(), denom)?.amount)
+    })
+}
+
+/// Repays every outstanding debt in full from the Account balance,
+/// and returns all remaining collateral to the owner
+pub fn settle_flash_close(
+    deps: Deps,
+    env: Env,
+    config: &Config,
+    account: &CreditAccount,
+) -> Result<Response, ContractError> {
+    let delegate = account.id().to_string();
+    let mut response = Response::default();
+    let mut repaid = NativeBalance::default();
+
+    for debt in account
+        .debts
+        .iter()
+        .flat_map(|x| NativeBalance::from(&x.item).into_vec())
+    {
+        let balance = deps.querier.query_balance(account.id(), &debt.denom)?;
+        ensure!(
+            balance.amount.ge(&debt.amount),
+            ContractError::Insolvent {
+                debt,
+                available: balance
+            }
+        );
+        let vault = BORROW.load(deps.storage, debt.denom.clone())?;
+        response = response
+            .add_message(
+                account
+                    .account
+                    .send(env.contract.address.clone(), vec![debt.clone()])?,
+            )
+            .add_message(vault.market_msg_repay(Some(delegate.clone()), &debt)?);
+        repaid += debt;
+    }
+
+    let mut remainder = vec![];
+    for denom in config.collateral_ratios.keys() {
+        let balance = deps.querier.query_balance(account.id(), denom)?;
+        let owed = repaid
+            .clone()
+            .into_vec()
+            .iter()
+            .find(|x| x.denom == *denom)
+            .map(|x| x.amount)
+            .unwrap_or_default();
+        let amount = balance.amount.sub(owed);
+        if !amount.is_zero() {
+            remainder.push(coin(amount.u128(), denom));
+        }
+    }
+
+    if !remainder.is_empty() {
+        response = response.add_message(account.account.send(&account.owner, remainder.clone())?);
+    }
+
+    Ok(response.add_event(event_execute_flash_close_settle(
+        &repaid,
+        &NativeBalance(remainder),
+    )))
+}
+
 pub fn execute_liquidate(
     deps: Deps,
     env: Env,
@@ -261,7 +923,7 @@ pub fn execute_liquidate(
 ) -> Result<Response, ContractError> {
     let delegate = account.id().to_string();
 
-    match msg {
+    match best_repay(deps, msg, account)? {
         LiquidateMsg::Repay(denom) => {
             let vault = BORROW.load(deps.storage, denom.clone())?;
             // We repay the full balance so that Repay can be chained in liquidation preferences messages
@@ -278,33 +940,36 @@ pub fn execute_liquidate(
             // Collect fees from the amount retrieved from the rujira-account.
             // A liquidation solver must ensure that the repayment is sufficient
             // after these fees are deducted
-            let liquidation_fee = balance.amount.multiply_ratio(
-                config.fee_liquidation.numerator(),
-                config.fee_liquidation.denominator(),
-            );
-            let liquidator_fee = balance.amount.multiply_ratio(
-                config.fee_liquidator.numerator(),
-                config.fee_liquidator.denominator(),
-            );
-
-            let repay_amount = balance.amount.sub(liquidation_fee).sub(liquidator_fee);
+            let (repay_amount, liquidation_fee, liquidator_fee) =
+                liquidation::repay_split(config, balance.amount);
+            let (protocol_fee, backstop_fee) = config.split_liquidation_fee(liquidation_fee);
+            // Retained by the contract to pay the poke reward
+            let protocol_fee = protocol_fee - config.poke_reserve(protocol_fee);
 
-            Ok(Response::default()
-                .add_message(
-                    account
-                        .account
-                        .send(env.contract.address.to_string(), vec![balance.clone()])?,
-                )
-                .add_message(
-                    vault.market_msg_repay(
+            let mut response =
+                Response::default()
+                    .add_message(
+                        account
+                            .account
+                            .send(env.contract.address.to_string(), vec![balance.clone()])?,
+                    )
+                    .add_message(vault.market_msg_repay(
                         Some(delegate),
                         &coin(repay_amount.u128(), denom.clone()),
-                    )?,
-                )
-                .add_message(BankMsg::Send {
+                    )?);
+            if !protocol_fee.is_zero() {
+                response = response.add_message(BankMsg::Send {
                     to_address: config.fee_address.to_string(),
-                    amount: coins(liquidation_fee.u128(), denom.clone()),
-                })
+                    amount: coins(protocol_fee.u128(), denom.clone()),
+                });
+            }
+            if let Some(backstop) = config.backstop.as_ref().filter(|_| !backstop_fee.is_zero()) {
+                response = response.add_message(BankMsg::Send {
+                    to_address: backstop.to_string(),
+                    amount: coins(backstop_fee.u128(), denom.clone()),
+                });
+            }
+            Ok(response
                 .add_message(BankMsg::Send {
                     to_address: info.sender.to_string(),
                     amount: coins(liquidator_fee.u128(), denom.clone()),
@@ -313,6 +978,7 @@ pub fn execute_liquidate(
                     &balance,
                     repay_amount,
                     liquidation_fee,
+                    backstop_fee,
                     liquidator_fee,
                 )))
         }
@@ -335,17 +1001,88 @@ pub fn execute_liquidate(
                 &msg,
                 &NativeBalance(funds),
             ))),
+        LiquidateMsg::Queue {
+            contract_addr,
+            amount,
+        } => {
+            let event = event_execute_liquidate_queue(&contract_addr, &amount);
+            let [send, liquidate] = queue_msgs(&env, account, contract_addr, amount)?;
+            // The collateral has left the Account once sent, so a failing queue must fail the
+            // liquidation rather than be skipped, even as a preference message
+            Ok(Response::default()
+                .add_message(send)
+                .add_submessage(
+                    SubMsg::reply_on_success(liquidate, reply_id)
+                        .with_payload(to_json_binary(&account)?),
+                )
+                .add_event(event))
+        }
+        LiquidateMsg::FlashBorrow { .. } => Err(ContractError::InvalidFlashBorrow {}),
+        // Resolved by best_repay
+        LiquidateMsg::RepayBest {} => Err(ContractError::NoRepayableDebt {}),
     }
 }
 
+/// Moves `amount` of collateral from the Account to this contract, and sells it into the
+/// liquidation queue at `contract_addr`, returning the bid denom to the Account
+fn queue_msgs(
+    env: &Env,
+    account: &CreditAccount,
+    contract_addr: String,
+    amount: Coin,
+) -> Result<[CosmosMsg; 2], ContractError> {
+    Ok([
+        account
+            .account
+            .send(env.contract.address.to_string(), vec![amount.clone()])?,
+        WasmMsg::Execute {
+            contract_addr,
+            msg: to_json_binary(&orca::ExecuteMsg::Liquidate {
+                min_return: None,
+                to: Some(account.id().to_string()),
+                callback: None,
+            })?,
+            funds: vec![amount],
+        }
+        .into(),
+    ])
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
     match (msg.result, msg.id) {
+        (SubMsgResult::Ok(_), REPLY_ID_CREATE) => {
+            let config = Config::load(deps.storage)?;
+            let ca = env.contract.address.clone();
+            let (addr, funds, msgs): (String, Vec<Coin>, Vec<AccountMsg>) =
+                from_json(&msg.payload)?;
+            let mut account =
+                CreditAccount::load(deps.as_ref(), &config, &ca, deps.api.addr_validate(&addr)?)?;
+            let min_debt = msgs.iter().any(AccountMsg::changes_debt);
+            let mut response = Response::default().add_event(event_execute_account(&account));
+            if !funds.is_empty() {
+                response = response.add_message(BankMsg::Send {
+                    to_address: addr.clone(),
+                    amount: funds,
+                });
+            }
+            for msg in msgs {
+                let (messages, events) =
+                    execute_account(deps.as_ref(), env.clone(), &config, msg, &mut account)?;
+                response = response.add_messages(messages).add_events(events);
+            }
+            account.save(deps)?;
+
+            Ok(response.add_message(ExecuteMsg::CheckAccount { addr, min_debt }.call(&ca)?))
+        }
         (SubMsgResult::Err(err), REPLY_ID_PREFERENCE) => {
             // Don't block execution if this is a preferential step
             Ok(Response::default().add_event(event_execute_liquidate_preference_error(err)))
         }
         (SubMsgResult::Err(err), REPLY_ID_LIQUIDATOR) => Err(StdError::generic_err(err).into()),
+        (SubMsgResult::Err(err), REPLY_ID_MONITOR) => {
+            Ok(Response::default().add_event(event_monitor_error(err)))
+        }
         (SubMsgResult::Ok(_), _) => Ok(Response::default()),
         _ => Err(ContractError::Unauthorized {}),
     }
@@ -358,12 +1095,12 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
     match msg {
         SudoMsg::SetVault { address } => {
             let vault: Vault = (&deps.api.addr_validate(&address)?).into();
-            let denom = vault.config(deps.querier)?.denom;
-
-            if !config.collateral_ratios.contains_key(&denom) {
-                return Err(ContractError::InvalidCollateral { denom });
+            for denom in vault.pools(deps.querier)?.denoms {
+                if !config.collateral_ratios.contains_key(&denom) {
+                    return Err(ContractError::InvalidCollateral { denom });
+                }
+                BORROW.save(deps.storage, denom, &vault)?;
             }
-            BORROW.save(deps.storage, denom, &vault)?;
             Ok(Response::default())
         }
         SudoMsg::SetCollateral {
@@ -377,12 +1114,101 @@ pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, Contract
             config.save(deps.storage)?;
             Ok(Response::default())
         }
+        SudoMsg::SetFinCollateral { contract, haircut } => {
+            let contract = deps.api.addr_validate(&contract)?;
+            match haircut {
+                Some(haircut) => config.fin_haircuts.insert(contract.to_string(), haircut),
+                None => config.fin_haircuts.remove(contract.as_str()),
+            };
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
         SudoMsg::UpdateConfig(update) => {
             config.update(&update);
             config.validate()?;
             config.save(deps.storage)?;
             Ok(Response::default())
         }
+        SudoMsg::SetMonitor { contract } => {
+            config.monitor = contract.map(|x| deps.api.addr_validate(&x)).transpose()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetClaimAdapter { denom, adapter } => {
+            ensure!(
+                config.collateral_ratios.contains_key(&denom),
+                ContractError::InvalidCollateral { denom }
+            );
+            match adapter {
+                Some(adapter) => {
+                    deps.api.addr_validate(&adapter.contract_addr)?;
+                    config.claim_adapters.insert(denom, adapter)
+                }
+                None => config.claim_adapters.remove(&denom),
+            };
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetBackstop { contract, share } => {
+            config.backstop = contract.map(|x| deps.api.addr_validate(&x)).transpose()?;
+            config.fee_backstop = share;
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetCorrelation {
+            collateral,
+            debt,
+            offset,
+        } => {
+            ensure!(
+                config.collateral_ratios.contains_key(&collateral),
+                ContractError::InvalidCollateral { denom: collateral }
+            );
+            config
+                .correlations
+                .retain(|x| x.collateral != collateral || x.debt != debt);
+            if let Some(offset) = offset {
+                config.correlations.push(Correlation {
+                    collateral,
+                    debt,
+                    offset,
+                });
+            }
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetRepayCrankFee { fee } => {
+            config.repay_crank_fee = fee;
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetPokeReward { reward } => {
+            config.poke_reward = reward;
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetDeleverageThreshold { threshold } => {
+            config.deleverage_threshold = threshold;
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetExecuteAllowlist { contracts } => {
+            config.execute_allowlist = contracts
+                .map(|x| {
+                    x.iter()
+                        .map(|x| deps.api.addr_validate(x))
+                        .collect::<StdResult<Vec<_>>>()
+                })
+                .transpose()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
     }
 }
 
@@ -397,11 +1223,14 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractErro
                 .collateral_ratios
                 .keys()
                 .map(|x| {
-                    Ok(BORROW
-                        .load(deps.storage, x.clone())?
-                        .borrower(deps.querier, &env.contract.address)?)
+                    Ok(BORROW.load(deps.storage, x.clone())?.borrower(
+                        deps.querier,
+                        x,
+                        &env.contract.address,
+                    )?)
                 })
                 .collect::<Result<Vec<ghost::vault::BorrowerResponse>, ContractError>>()?,
+            page: PageResponse::default(),
         })?),
         QueryMsg::Account(addr) => Ok(to_json_binary(&AccountResponse::from(
             CreditAccount::load(
@@ -423,20 +1252,69 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractErro
             .iter()
             .map(|x| AccountResponse::from(x.clone()))
             .collect(),
+            page: PageResponse::default(),
         })?),
 
-        QueryMsg::AllAccounts { cursor, limit } => Ok(to_json_binary(&AccountsResponse {
-            accounts: CreditAccount::list(
+        QueryMsg::Valuation { addr } => Ok(to_json_binary(
+            &VALUATIONS.may_load(deps.storage, deps.api.addr_validate(&addr)?)?,
+        )?),
+        QueryMsg::PreferenceTemplates { owner } => {
+            Ok(to_json_binary(&PreferenceTemplatesResponse {
+                templates: PREFERENCE_TEMPLATES
+                    .prefix(deps.api.addr_validate(&owner)?)
+                    .range(deps.storage, None, None, Order::Ascending)
+                    .map(|x| {
+                        x.map(|(name, preferences)| PreferenceTemplateResponse {
+                            name,
+                            preferences,
+                        })
+                    })
+                    .collect::<StdResult<Vec<_>>>()?,
+            })?)
+        }
+
+        QueryMsg::AllAccounts { page } => {
+            let limit = page.limit(ACCOUNTS_LIMIT, ACCOUNTS_LIMIT);
+            let page = page.try_map(|x| deps.api.addr_validate(&x))?;
+            let accounts = CreditAccount::list(
                 deps,
                 &config,
                 &env.contract.address,
-                cursor.map(|x| deps.api.addr_validate(&x)).transpose()?,
+                page.start_after,
                 limit,
-            )?
-            .iter()
-            .map(|x| AccountResponse::from(x.clone()))
-            .collect(),
-        })?),
+            )?;
+            Ok(to_json_binary(&AccountsResponse {
+                page: PageResponse::new(&accounts, limit, |x| x.id().to_string()),
+                accounts: accounts
+                    .iter()
+                    .map(|x| AccountResponse::from(x.clone()))
+                    .collect(),
+            })?)
+        }
+
+        QueryMsg::RiskReport { addr } => Ok(to_json_binary(&risk_report(
+            deps,
+            &config,
+            &CreditAccount::load(
+                deps,
+                &config,
+                &env.contract.address,
+                deps.api.addr_validate(&addr)?,
+            )?,
+        )?)?),
+
+        QueryMsg::SimulateLiquidation { addr, msgs } => Ok(to_json_binary(&simulate_liquidation(
+            deps,
+            &env,
+            &config,
+            &CreditAccount::load(
+                deps,
+                &config,
+                &env.contract.address,
+                deps.api.addr_validate(&addr)?,
+            )?,
+            msgs,
+        )?)?),
 
         QueryMsg::Predict { owner, salt } => {
             let a = &CreditAccount::create(
@@ -1,4 +1,6 @@
-use cosmwasm_std::{Addr, CheckedFromRatioError, Decimal, Instantiate2AddressError, StdError};
+use cosmwasm_std::{
+    Addr, CheckedFromRatioError, Coin, Decimal, Instantiate2AddressError, StdError, Timestamp,
+};
 use cw_utils::{ParseReplyError, PaymentError};
 use rujira_rs::{
     account::AccountError,
@@ -48,12 +50,18 @@ pub enum ContractError {
     #[error("ZeroDebtTokens {denom}")]
     ZeroDebtTokens { denom: String },
 
+    #[error("NoRepayableDebt")]
+    NoRepayableDebt {},
+
     #[error("Invalid Config: {key}:{value}")]
     InvalidConfig { key: String, value: String },
 
     #[error("{denom} not registered as valid collateral")]
     InvalidCollateral { denom: String },
 
+    #[error("No claim adapter registered for {denom}")]
+    NoClaimAdapter { denom: String },
+
     #[error("Reply Error: {0}")]
     ReplyError(String),
 
@@ -72,8 +80,46 @@ pub enum ContractError {
     #[error("Over liquidation max {max} actual {actual}")]
     OverLiquidation { max: Decimal, actual: Decimal },
 
+    #[error("Insolvent: debt {debt} available {available}")]
+    Insolvent { debt: Coin, available: Coin },
+
     #[error("Max Slip exceeded during liquidation: #{slip}")]
     LiquidationMaxSlipExceeded { slip: Decimal },
+
+    #[error("Liquidation cooldown: ltv {ltv} not above previous liquidation ltv {last}")]
+    LiquidationCooldown { ltv: Decimal, last: Decimal },
+
+    #[error("FlashBorrow is only valid as the first liquidation message")]
+    InvalidFlashBorrow {},
+
+    #[error("Flash borrow outstanding")]
+    FlashBorrowOutstanding {},
+
+    #[error("Debt value {value} below minimum {min}")]
+    DebtBelowMinimum { value: Decimal, min: Decimal },
+
+    #[error("Preference template {name} not found")]
+    PreferenceTemplateNotFound { name: String },
+
+    #[error("Account can't execute {contract}, it isn't in the allowlist")]
+    ExecuteNotAllowed { contract: String },
+
+    #[error("Deleverage not enabled")]
+    DeleverageDisabled {},
+
+    #[error("Invalid repay schedule")]
+    InvalidRepaySchedule {},
+
+    #[error("No repay schedule")]
+    NoRepaySchedule {},
+
+    #[error("Repayment not due until {due}")]
+    RepayNotDue { due: Timestamp },
+    #[error("{denom} can't be borrowed")]
+    NotBorrowable { denom: String },
+
+    #[error("Borrow cap {cap} exceeded: debt {debt}")]
+    BorrowCapExceeded { cap: Coin, debt: Coin },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
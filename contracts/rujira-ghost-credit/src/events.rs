@@ -1,5 +1,6 @@
 use cosmwasm_std::{Addr, Binary, Coin, Event, Uint128};
 use cw_utils::NativeBalance;
+use rujira_rs::ghost::credit::RepaySchedule;
 
 use crate::account::CreditAccount;
 
@@ -63,6 +64,79 @@ pub fn event_execute_account_set_preference_msgs() -> Event {
     ))
 }
 
+pub fn event_execute_account_set_preference_template(name: &Option<String>) -> Event {
+    Event::new(format!(
+        "{}/account.msg/set_preference_template",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("name", name.clone().unwrap_or_default())
+}
+
+pub fn event_execute_account_set_execute_unrestricted(unrestricted: bool) -> Event {
+    Event::new(format!(
+        "{}/account.msg/set_execute_unrestricted",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("unrestricted", unrestricted.to_string())
+}
+
+pub fn event_execute_account_set_deleverage(enabled: bool) -> Event {
+    Event::new(format!(
+        "{}/account.msg/set_deleverage",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("enabled", enabled.to_string())
+}
+
+pub fn event_execute_account_set_repay_schedule(schedule: &Option<RepaySchedule>) -> Event {
+    let event = Event::new(format!(
+        "{}/account.msg/set_repay_schedule",
+        env!("CARGO_PKG_NAME")
+    ));
+    match schedule {
+        Some(schedule) => event
+            .add_attribute("amount", schedule.amount.to_string())
+            .add_attribute("interval", schedule.interval.to_string()),
+        None => event,
+    }
+}
+
+pub fn event_execute_account_set_borrow_cap(denom: &str, cap: &Option<Uint128>) -> Event {
+    let event = Event::new(format!(
+        "{}/account.msg/set_borrow_cap",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("denom", denom);
+    match cap {
+        Some(cap) => event.add_attribute("cap", cap.to_string()),
+        None => event,
+    }
+}
+
+pub fn event_set_preference_template(owner: &Addr, name: &String, saved: bool) -> Event {
+    Event::new(format!("{}/preference_template", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner.to_string())
+        .add_attribute("name", name.to_string())
+        .add_attribute("action", if saved { "save" } else { "remove" })
+}
+
+pub fn event_execute_account_flash_close(routes: usize) -> Event {
+    Event::new(format!(
+        "{}/account.msg/flash_close",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("routes", routes.to_string())
+}
+
+pub fn event_execute_flash_close_settle(
+    repaid: &NativeBalance,
+    remainder: &NativeBalance,
+) -> Event {
+    Event::new(format!("{}/flash_close.settle", env!("CARGO_PKG_NAME")))
+        .add_attribute("repaid", repaid.to_string())
+        .add_attribute("remainder", remainder.to_string())
+}
+
 pub fn event_execute_liquidate(account: &CreditAccount, caller: &Addr) -> Event {
     Event::new(format!("{}/account.liquidate", env!("CARGO_PKG_NAME")))
         .add_attribute("owner", account.owner.clone())
@@ -70,6 +144,43 @@ pub fn event_execute_liquidate(account: &CreditAccount, caller: &Addr) -> Event
         .add_attribute("caller", caller.to_string())
 }
 
+pub fn event_execute_deleverage(account: &CreditAccount, caller: &Addr) -> Event {
+    Event::new(format!("{}/account.deleverage", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", account.owner.clone())
+        .add_attribute("address", account.id().to_string())
+        .add_attribute("caller", caller.to_string())
+        .add_attribute("ltv", account.adjusted_ltv().to_string())
+}
+
+pub fn event_poke(
+    account: &CreditAccount,
+    caller: &Addr,
+    liquidatable: bool,
+    reward: &NativeBalance,
+) -> Event {
+    Event::new(format!("{}/account.poke", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", account.owner.clone())
+        .add_attribute("address", account.id().to_string())
+        .add_attribute("caller", caller.to_string())
+        .add_attribute("ltv", account.adjusted_ltv().to_string())
+        .add_attribute("liquidatable", liquidatable.to_string())
+        .add_attribute("reward", reward.to_string())
+}
+
+pub fn event_crank_repay(
+    account: &CreditAccount,
+    caller: &Addr,
+    repaid: &Coin,
+    fee: &Coin,
+) -> Event {
+    Event::new(format!("{}/account.crank_repay", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", account.owner.clone())
+        .add_attribute("address", account.id().to_string())
+        .add_attribute("caller", caller.to_string())
+        .add_attribute("repaid", repaid.to_string())
+        .add_attribute("fee", fee.to_string())
+}
+
 pub fn event_execute_liquidate_preference_error(msg: String) -> Event {
     Event::new(format!(
         "{}/liquidate.msg/preference.error",
@@ -82,12 +193,14 @@ pub fn event_execute_liquidate_repay(
     amount: &Coin,
     repay_amount: Uint128,
     fee_liquidation: Uint128,
+    fee_backstop: Uint128,
     fee_liquidator: Uint128,
 ) -> Event {
     Event::new(format!("{}/liquidate.msg/repay", env!("CARGO_PKG_NAME")))
         .add_attribute("amount", amount.to_string())
         .add_attribute("repay_amount", repay_amount.to_string())
         .add_attribute("fee_liquidation", fee_liquidation.to_string())
+        .add_attribute("fee_backstop", fee_backstop.to_string())
         .add_attribute("fee_liquidator", fee_liquidator.to_string())
 }
 
@@ -101,3 +214,36 @@ pub fn event_execute_liquidate_execute(
         .add_attribute("msg", msg.to_string())
         .add_attribute("funds", funds.to_string())
 }
+
+pub fn event_execute_liquidate_queue(contract_addr: &String, amount: &Coin) -> Event {
+    Event::new(format!("{}/liquidate.msg/queue", env!("CARGO_PKG_NAME")))
+        .add_attribute("contract_addr", contract_addr.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
+pub fn event_execute_liquidate_flash_borrow(amount: &Coin) -> Event {
+    Event::new(format!(
+        "{}/liquidate.msg/flash_borrow",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("amount", amount.to_string())
+}
+
+pub fn event_execute_liquidate_flash_repay(amount: &Coin) -> Event {
+    Event::new(format!(
+        "{}/liquidate.msg/flash_repay",
+        env!("CARGO_PKG_NAME")
+    ))
+    .add_attribute("amount", amount.to_string())
+}
+
+pub fn event_compound(account: &CreditAccount, denom: &str, contract_addr: &str) -> Event {
+    Event::new(format!("{}/compound", env!("CARGO_PKG_NAME")))
+        .add_attribute("address", account.id().to_string())
+        .add_attribute("denom", denom)
+        .add_attribute("contract_addr", contract_addr)
+}
+
+pub fn event_monitor_error(msg: String) -> Event {
+    Event::new(format!("{}/monitor.error", env!("CARGO_PKG_NAME"))).add_attribute("error", msg)
+}
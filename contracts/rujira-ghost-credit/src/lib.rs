@@ -3,6 +3,9 @@ pub mod config;
 pub mod contract;
 mod error;
 mod events;
+mod liquidation;
+mod risk;
+mod simulate;
 mod state;
 
 pub use crate::error::ContractError;
@@ -0,0 +1,117 @@
+use cosmwasm_std::{coin, ensure, Coin, Deps, Env, Fraction, StdResult, Uint128};
+use rujira_rs::ghost::credit::LiquidateMsg;
+
+use crate::{account::CreditAccount, config::Config, ContractError};
+
+/// The messages of a liquidation still to execute, the next one last. Each is flagged when it is one
+/// of the Account's preference messages
+pub type Queue = Vec<(LiquidateMsg, bool)>;
+
+/// Validates the liquidation of `account` with the liquidator's `msgs`, returning the flash loan
+/// that funds it and the queue of messages, the Account's preferences ahead of the liquidator's
+pub fn start(
+    env: &Env,
+    config: &Config,
+    account: &CreditAccount,
+    mut msgs: Vec<LiquidateMsg>,
+) -> Result<(Option<Coin>, Queue), ContractError> {
+    account.check_unsafe(&config.liquidation_threshold)?;
+    account.check_cooldown(env.block.time, config.liquidation_cooldown)?;
+    let flash = match msgs.first() {
+        Some(LiquidateMsg::FlashBorrow { denom, amount }) => Some(coin(amount.u128(), denom)),
+        _ => None,
+    };
+    if flash.is_some() {
+        msgs.remove(0);
+    }
+    ensure!(
+        !msgs.iter().any(LiquidateMsg::is_flash_borrow),
+        ContractError::InvalidFlashBorrow {}
+    );
+    let mut queue: Queue = account
+        .preferences()
+        .messages
+        .iter()
+        .map(|x| (x.clone(), true))
+        .chain(msgs.into_iter().map(|x| (x, false)))
+        .collect();
+    queue.reverse();
+    Ok((flash, queue))
+}
+
+/// The liquidator's messages have executed once the next message is a preference, and a flash
+/// loan is then repaid from the Account
+pub fn flash_repay_due(queue: &Queue) -> bool {
+    !matches!(queue.last(), Some((_, false)))
+}
+
+/// Checks the liquidation of `original` is complete at `account`
+pub fn check(
+    deps: Deps,
+    config: &Config,
+    account: &CreditAccount,
+    original: &CreditAccount,
+    flash: &Option<Coin>,
+) -> Result<(), ContractError> {
+    match flash {
+        // The Account holds the flash loan, so can't be checked until it is repaid
+        Some(_) => Err(ContractError::FlashBorrowOutstanding {}),
+        None => account
+            // Check safe against the liquidation threshold
+            .check_safe(&config.liquidation_threshold)
+            // Check we've not gone below the adjustment threshold
+            .and_then(|_| account.check_unsafe(&config.adjustment_threshold))
+            .and_then(|_| account.validate_liquidation(deps, config, original)),
+    }
+}
+
+/// The step following `check`: `None` once the liquidation is complete, otherwise the next
+/// message to execute. Fails with the check once the queue is exhausted
+pub fn next(
+    queue: &mut Queue,
+    check: Result<(), ContractError>,
+) -> Result<Option<(LiquidateMsg, bool)>, ContractError> {
+    match (queue.pop(), check) {
+        (_, Ok(())) => Ok(None),
+        (None, Err(err)) => Err(err),
+        (Some(next), Err(_)) => Ok(Some(next)),
+    }
+}
+
+/// Resolves [LiquidateMsg::RepayBest] to a [LiquidateMsg::Repay] of the first debt in the Account's
+/// repay order that it `holds` a balance of
+pub fn resolve(
+    account: &CreditAccount,
+    msg: LiquidateMsg,
+    holds: impl Fn(&str) -> StdResult<Uint128>,
+) -> Result<LiquidateMsg, ContractError> {
+    match msg {
+        LiquidateMsg::RepayBest {} => {
+            for denom in account.repay_order() {
+                if !holds(&denom)?.is_zero() {
+                    return Ok(LiquidateMsg::Repay(denom));
+                }
+            }
+            Err(ContractError::NoRepayableDebt {})
+        }
+        msg => Ok(msg),
+    }
+}
+
+/// Splits the balance of a debt token retrieved from the Account into the repayment, the liquidation
+/// fee and the liquidator's fee
+pub fn repay_split(config: &Config, balance: Uint128) -> (Uint128, Uint128, Uint128) {
+    let liquidation_fee = balance.multiply_ratio(
+        config.fee_liquidation.numerator(),
+        config.fee_liquidation.denominator(),
+    );
+    let liquidator_fee = balance.multiply_ratio(
+        config.fee_liquidator.numerator(),
+        config.fee_liquidator.denominator(),
+    );
+    (
+        balance - liquidation_fee - liquidator_fee,
+        liquidation_fee,
+        liquidator_fee,
+    )
+}
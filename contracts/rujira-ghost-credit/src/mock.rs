@@ -4,9 +4,12 @@ use cosmwasm_std::{coin, coins, to_json_binary, Addr, Binary, Coin, Decimal};
 use cw_multi_test::{AppResponse, ContractWrapper, Executor};
 use rujira_ghost_vault::mock::GhostVault;
 use rujira_rs::ghost::credit::{
-    AccountMsg, AccountResponse, AccountsResponse, ConfigResponse, ExecuteMsg, InstantiateMsg,
-    LiquidateMsg, QueryMsg, SudoMsg,
+    AccountMsg, AccountResponse, AccountsResponse, ClaimAdapter, ConfigResponse, ConfigUpdate,
+    ExecuteMsg, InstantiateMsg, LiquidateMsg, PokeReward, PreferenceTemplate,
+    PreferenceTemplatesResponse, QueryMsg, RiskReportResponse, SimulateLiquidationResponse,
+    SudoMsg, Valuation,
 };
+use rujira_rs::PageRequest;
 use rujira_rs_testing::RujiraApp;
 use serde::Serialize;
 
@@ -37,12 +40,37 @@ impl GhostCredit {
                 salt,
                 label: label.to_string(),
                 tag: tag.to_string(),
+                init_msgs: vec![],
             },
             &[],
         )
         .unwrap()
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_account_with(
+        &self,
+        app: &mut RujiraApp,
+        owner: &Addr,
+        label: &str,
+        tag: &str,
+        salt: Binary,
+        init_msgs: Vec<AccountMsg>,
+        funds: &[Coin],
+    ) -> anyhow::Result<AppResponse> {
+        app.execute_contract(
+            owner.clone(),
+            self.0.clone(),
+            &ExecuteMsg::Create {
+                salt,
+                label: label.to_string(),
+                tag: tag.to_string(),
+                init_msgs,
+            },
+            funds,
+        )
+    }
+
     pub fn account(
         &self,
         app: &mut RujiraApp,
@@ -127,6 +155,15 @@ impl GhostCredit {
         )
     }
 
+    pub fn account_flash_close(
+        &self,
+        app: &mut RujiraApp,
+        account: &AccountResponse,
+        routes: Vec<LiquidateMsg>,
+    ) -> anyhow::Result<AppResponse> {
+        self.account(app, account, vec![AccountMsg::FlashClose { routes }])
+    }
+
     pub fn liquidate(
         &self,
         app: &mut RujiraApp,
@@ -144,6 +181,54 @@ impl GhostCredit {
         )
     }
 
+    pub fn deleverage(
+        &self,
+        app: &mut RujiraApp,
+        sender: &Addr,
+        account: &AccountResponse,
+    ) -> anyhow::Result<AppResponse> {
+        app.execute_contract(
+            sender.clone(),
+            self.0.clone(),
+            &ExecuteMsg::Deleverage {
+                addr: account.account.to_string(),
+            },
+            &[],
+        )
+    }
+
+    pub fn poke(
+        &self,
+        app: &mut RujiraApp,
+        sender: &Addr,
+        account: &AccountResponse,
+    ) -> anyhow::Result<AppResponse> {
+        app.execute_contract(
+            sender.clone(),
+            self.0.clone(),
+            &ExecuteMsg::Poke {
+                addr: account.account.to_string(),
+            },
+            &[],
+        )
+    }
+
+    pub fn crank_repay(
+        &self,
+        app: &mut RujiraApp,
+        sender: &Addr,
+        account: &AccountResponse,
+    ) -> anyhow::Result<AppResponse> {
+        app.execute_contract(
+            sender.clone(),
+            self.0.clone(),
+            &ExecuteMsg::CrankRepay {
+                addr: account.account.to_string(),
+            },
+            &[],
+        )
+    }
+
     pub fn liquidate_execute<T>(
         &self,
         app: &mut RujiraApp,
@@ -203,6 +288,27 @@ impl GhostCredit {
         .unwrap()
     }
 
+    pub fn set_fin_collateral(
+        &self,
+        app: &mut RujiraApp,
+        contract: &Addr,
+        haircut: Option<&str>,
+    ) -> AppResponse {
+        app.wasm_sudo(
+            self.0.clone(),
+            &SudoMsg::SetFinCollateral {
+                contract: contract.to_string(),
+                haircut: haircut.map(|x| Decimal::from_str(x).unwrap()),
+            },
+        )
+        .unwrap()
+    }
+
+    pub fn update_config(&self, app: &mut RujiraApp, update: ConfigUpdate) -> AppResponse {
+        app.wasm_sudo(self.0.clone(), &SudoMsg::UpdateConfig(update))
+            .unwrap()
+    }
+
     pub fn set_vault(&self, app: &mut RujiraApp, vault: &GhostVault) -> AppResponse {
         app.wasm_sudo(
             self.0.clone(),
@@ -213,6 +319,171 @@ impl GhostCredit {
         .unwrap()
     }
 
+    pub fn set_monitor(&self, app: &mut RujiraApp, contract: Option<&Addr>) -> AppResponse {
+        app.wasm_sudo(
+            self.0.clone(),
+            &SudoMsg::SetMonitor {
+                contract: contract.map(|x| x.to_string()),
+            },
+        )
+        .unwrap()
+    }
+
+    pub fn set_backstop(
+        &self,
+        app: &mut RujiraApp,
+        contract: Option<&Addr>,
+        share: Decimal,
+    ) -> AppResponse {
+        app.wasm_sudo(
+            self.0.clone(),
+            &SudoMsg::SetBackstop {
+                contract: contract.map(|x| x.to_string()),
+                share,
+            },
+        )
+        .unwrap()
+    }
+
+    pub fn set_correlation(
+        &self,
+        app: &mut RujiraApp,
+        collateral: &str,
+        debt: &str,
+        offset: Option<&str>,
+    ) -> AppResponse {
+        app.wasm_sudo(
+            self.0.clone(),
+            &SudoMsg::SetCorrelation {
+                collateral: collateral.to_string(),
+                debt: debt.to_string(),
+                offset: offset.map(|x| Decimal::from_str(x).unwrap()),
+            },
+        )
+        .unwrap()
+    }
+
+    pub fn set_claim_adapter(
+        &self,
+        app: &mut RujiraApp,
+        denom: &str,
+        adapter: Option<ClaimAdapter>,
+    ) -> AppResponse {
+        app.wasm_sudo(
+            self.0.clone(),
+            &SudoMsg::SetClaimAdapter {
+                denom: denom.to_string(),
+                adapter,
+            },
+        )
+        .unwrap()
+    }
+
+    pub fn set_execute_allowlist(
+        &self,
+        app: &mut RujiraApp,
+        contracts: Option<Vec<&Addr>>,
+    ) -> AppResponse {
+        app.wasm_sudo(
+            self.0.clone(),
+            &SudoMsg::SetExecuteAllowlist {
+                contracts: contracts.map(|x| x.iter().map(|x| x.to_string()).collect()),
+            },
+        )
+        .unwrap()
+    }
+
+    pub fn set_deleverage_threshold(
+        &self,
+        app: &mut RujiraApp,
+        threshold: Option<&str>,
+    ) -> anyhow::Result<AppResponse> {
+        app.wasm_sudo(
+            self.0.clone(),
+            &SudoMsg::SetDeleverageThreshold {
+                threshold: threshold.map(|x| Decimal::from_str(x).unwrap()),
+            },
+        )
+    }
+
+    pub fn set_poke_reward(
+        &self,
+        app: &mut RujiraApp,
+        reward: Option<PokeReward>,
+    ) -> anyhow::Result<AppResponse> {
+        app.wasm_sudo(self.0.clone(), &SudoMsg::SetPokeReward { reward })
+    }
+
+    pub fn set_repay_crank_fee(
+        &self,
+        app: &mut RujiraApp,
+        fee: Decimal,
+    ) -> anyhow::Result<AppResponse> {
+        app.wasm_sudo(self.0.clone(), &SudoMsg::SetRepayCrankFee { fee })
+    }
+
+    pub fn set_preference_template(
+        &self,
+        app: &mut RujiraApp,
+        owner: &Addr,
+        name: &str,
+        template: Option<PreferenceTemplate>,
+    ) -> anyhow::Result<AppResponse> {
+        app.execute_contract(
+            owner.clone(),
+            self.0.clone(),
+            &ExecuteMsg::SetPreferenceTemplate {
+                name: name.to_string(),
+                template,
+            },
+            &[],
+        )
+    }
+
+    pub fn query_preference_templates(
+        &self,
+        app: &RujiraApp,
+        owner: &Addr,
+    ) -> PreferenceTemplatesResponse {
+        app.wrap()
+            .query_wasm_smart(
+                self.0.clone(),
+                &QueryMsg::PreferenceTemplates {
+                    owner: owner.to_string(),
+                },
+            )
+            .unwrap()
+    }
+
+    pub fn compound(
+        &self,
+        app: &mut RujiraApp,
+        sender: &Addr,
+        account: &AccountResponse,
+        denoms: Vec<&str>,
+    ) -> anyhow::Result<AppResponse> {
+        app.execute_contract(
+            sender.clone(),
+            self.0.clone(),
+            &ExecuteMsg::Compound {
+                addr: account.account.to_string(),
+                denoms: denoms.into_iter().map(String::from).collect(),
+            },
+            &[],
+        )
+    }
+
+    pub fn query_valuation(&self, app: &RujiraApp, addr: &Addr) -> Option<Valuation> {
+        app.wrap()
+            .query_wasm_smart(
+                self.0.clone(),
+                &QueryMsg::Valuation {
+                    addr: addr.to_string(),
+                },
+            )
+            .unwrap()
+    }
+
     pub fn query_account(&self, app: &RujiraApp, addr: &Addr) -> AccountResponse {
         app.wrap()
             .query_wasm_smart(self.0.clone(), &QueryMsg::Account(addr.to_string()))
@@ -241,13 +512,38 @@ impl GhostCredit {
             .query_wasm_smart(
                 self.0.clone(),
                 &QueryMsg::AllAccounts {
-                    cursor: cursor.map(|x| x.to_string()),
-                    limit: None,
+                    page: PageRequest::new(cursor.map(|x| x.to_string()), None),
                 },
             )
             .unwrap()
     }
 
+    pub fn query_risk_report(&self, app: &RujiraApp, addr: &Addr) -> RiskReportResponse {
+        app.wrap()
+            .query_wasm_smart(
+                self.0.clone(),
+                &QueryMsg::RiskReport {
+                    addr: addr.to_string(),
+                },
+            )
+            .unwrap()
+    }
+
+    pub fn query_simulate_liquidation(
+        &self,
+        app: &RujiraApp,
+        addr: &Addr,
+        msgs: Vec<LiquidateMsg>,
+    ) -> anyhow::Result<SimulateLiquidationResponse> {
+        Ok(app.wrap().query_wasm_smart(
+            self.0.clone(),
+            &QueryMsg::SimulateLiquidation {
+                addr: addr.to_string(),
+                msgs,
+            },
+        )?)
+    }
+
     pub fn query_config(&self, app: &RujiraApp) -> ConfigResponse {
         app.wrap()
             .query_wasm_smart(self.0.clone(), &QueryMsg::Config {})
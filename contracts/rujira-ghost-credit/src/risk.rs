@@ -0,0 +1,89 @@
+use cosmwasm_std::{coin, Coin, Decimal, Deps, Order};
+use rujira_rs::{
+    ghost::credit::{Collateral, RiskReportResponse, ThresholdDistance},
+    OracleValue,
+};
+
+use crate::{account::CreditAccount, config::Config, state::BORROW, ContractError};
+
+pub fn risk_report(
+    deps: Deps,
+    config: &Config,
+    account: &CreditAccount,
+) -> Result<RiskReportResponse, ContractError> {
+    let (collateral, debt) = account.margined();
+    let ltv = account.adjusted_ltv();
+    let target = config.safe_threshold();
+
+    // Borrowed funds are sent to the account, so each unit borrowed also adds its
+    // adjusted value to the collateral
+    let headroom = (target * collateral).saturating_sub(debt);
+    let mut max_borrow = vec![];
+    for denom in BORROW.keys(deps.storage, None, None, Order::Ascending) {
+        let denom = denom?;
+        let factor = Decimal::one().saturating_sub(target * ratio(config, &denom));
+        if let Ok(usd) = headroom.checked_div(factor) {
+            let amount = usd.checked_div(unit_price(deps, &denom)?)?.to_uint_floor();
+            max_borrow.push(coin(amount.u128(), denom));
+        }
+    }
+
+    // Selling collateral to repay debt reduces the debt by the full value sold, and the
+    // collateral by its adjusted value
+    let excess = debt.saturating_sub(target * collateral);
+    let mut shortfall = vec![];
+    if !excess.is_zero() {
+        for valued in account.collaterals.iter() {
+            if let Collateral::Coin(held) = &valued.item {
+                let factor = Decimal::one().saturating_sub(target * ratio(config, &held.denom));
+                if let Ok(usd) = excess.checked_div(factor) {
+                    let amount = usd
+                        .checked_div(unit_price(deps, &held.denom)?)?
+                        .to_uint_ceil()
+                        .min(held.amount);
+                    shortfall.push(coin(amount.u128(), &held.denom));
+                }
+            }
+        }
+    }
+
+    Ok(RiskReportResponse {
+        account: account.id(),
+        collateral,
+        debt,
+        ltv,
+        adjustment: distance(config.adjustment_threshold, ltv, collateral, debt),
+        liquidation: distance(config.liquidation_threshold, ltv, collateral, debt),
+        max_borrow,
+        shortfall,
+    })
+}
+
+fn ratio(config: &Config, denom: &str) -> Decimal {
+    config
+        .collateral_ratios
+        .get(denom)
+        .copied()
+        .unwrap_or_default()
+}
+
+fn unit_price(deps: Deps, denom: &str) -> Result<Decimal, ContractError> {
+    Ok(Coin::new(1u128, denom).value_usd(deps.querier)?)
+}
+
+fn distance(
+    threshold: Decimal,
+    ltv: Decimal,
+    collateral: Decimal,
+    debt: Decimal,
+) -> ThresholdDistance {
+    ThresholdDistance {
+        threshold,
+        ltv: threshold.saturating_sub(ltv),
+        debt_usd: (threshold * collateral).saturating_sub(debt),
+        collateral_usd: debt
+            .checked_div(threshold)
+            .map(|x| collateral.saturating_sub(x))
+            .unwrap_or_default(),
+    }
+}
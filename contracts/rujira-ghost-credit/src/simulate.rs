@@ -0,0 +1,204 @@
+use cosmwasm_std::{coin, from_json, Coin, Deps, Env, QuerierWrapper, StdResult, Uint128};
+use cw_utils::NativeBalance;
+use rujira_rs::{
+    fin,
+    ghost::credit::{
+        LiquidateMsg, LiquidationRepayResponse, LiquidationStepResponse,
+        SimulateLiquidationResponse,
+    },
+    orca,
+};
+use std::cmp::min;
+
+use crate::{account::CreditAccount, config::Config, liquidation, state::BORROW, ContractError};
+
+/// Replays [rujira_rs::ghost::credit::ExecuteMsg::Liquidate] against the balances and debts of
+/// `account`, taking the steps of DoLiquidate
+pub fn simulate_liquidation(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    account: &CreditAccount,
+    msgs: Vec<LiquidateMsg>,
+) -> Result<SimulateLiquidationResponse, ContractError> {
+    let (mut flash, mut queue) = liquidation::start(env, config, account, msgs)?;
+
+    let mut balance = NativeBalance::default();
+    for denom in config.collateral_ratios.keys() {
+        balance += deps.querier.query_balance(account.id(), denom)?;
+    }
+    balance.normalize();
+    if let Some(flash) = &flash {
+        balance += flash.clone();
+    }
+    let mut repaid = NativeBalance::default();
+    let mut steps = vec![];
+
+    let (simulated, error) = loop {
+        if let Some(loan) = flash.take_if(|_| liquidation::flash_repay_due(&queue)) {
+            match balance.clone() - loan {
+                Ok(x) => balance = x,
+                Err(_) => {
+                    let simulated = account.simulated(deps, config, &balance, &repaid)?;
+                    break (simulated, Some(ContractError::InsufficientFunds {}));
+                }
+            }
+        }
+
+        let simulated = account.simulated(deps, config, &balance, &repaid)?;
+        let check = liquidation::check(deps, config, &simulated, account, &flash);
+        match liquidation::next(&mut queue, check) {
+            Ok(None) => break (simulated, None),
+            Err(err) => break (simulated, Some(err)),
+            Ok(Some((msg, preference))) => {
+                let mut next = balance.clone();
+                match step(deps, config, account, &mut next, &mut repaid, msg.clone()) {
+                    Ok((msg, repay, is_simulated)) => {
+                        balance = next;
+                        steps.push(LiquidationStepResponse {
+                            msg,
+                            preference,
+                            repay,
+                            simulated: is_simulated,
+                            error: None,
+                        });
+                    }
+                    // Preference messages on other contracts are executed as SubMsgs, and their errors ignored
+                    Err(err) if preference && matches!(msg, LiquidateMsg::Execute { .. }) => {
+                        steps.push(LiquidationStepResponse {
+                            msg,
+                            preference,
+                            repay: None,
+                            simulated: true,
+                            error: Some(err.to_string()),
+                        });
+                    }
+                    Err(err) => {
+                        steps.push(LiquidationStepResponse {
+                            msg,
+                            preference,
+                            repay: None,
+                            simulated: true,
+                            error: Some(err.to_string()),
+                        });
+                        break (simulated, Some(err));
+                    }
+                }
+            }
+        }
+    };
+
+    Ok(SimulateLiquidationResponse {
+        steps,
+        ltv: simulated.adjusted_ltv(),
+        error: error.map(|x| x.to_string()),
+    })
+}
+
+/// Applies `msg` to the Account `balance` and the debt `repaid`, returning the message executed,
+/// the repayment made, and whether its outcome could be simulated
+fn step(
+    deps: Deps,
+    config: &Config,
+    account: &CreditAccount,
+    balance: &mut NativeBalance,
+    repaid: &mut NativeBalance,
+    msg: LiquidateMsg,
+) -> Result<(LiquidateMsg, Option<LiquidationRepayResponse>, bool), ContractError> {
+    let msg = liquidation::resolve(account, msg, |denom| Ok(amount(balance, denom)))?;
+    match &msg {
+        LiquidateMsg::Repay(denom) => {
+            BORROW.load(deps.storage, denom.clone())?;
+            let held = amount(balance, denom);
+            if held.is_zero() {
+                return Err(ContractError::ZeroDebtTokens {
+                    denom: denom.clone(),
+                });
+            }
+            let (repay, liquidation_fee, liquidator_fee) = liquidation::repay_split(config, held);
+            let outstanding = account
+                .debts
+                .iter()
+                .filter(|x| x.item.denom() == denom)
+                .fold(Uint128::zero(), |agg, x| {
+                    agg + amount(&NativeBalance::from(&x.item), denom)
+                })
+                .saturating_sub(amount(repaid, denom));
+            // The vault refunds any excess to this contract
+            let repay = min(repay, outstanding);
+            *balance = (balance.clone() - coin(held.u128(), denom))?;
+            *repaid += coin(repay.u128(), denom);
+            Ok((
+                msg.clone(),
+                Some(LiquidationRepayResponse {
+                    repaid: coin(repay.u128(), denom),
+                    fee_liquidation: coin(liquidation_fee.u128(), denom),
+                    fee_liquidator: coin(liquidator_fee.u128(), denom),
+                }),
+                true,
+            ))
+        }
+        LiquidateMsg::Execute {
+            contract_addr,
+            msg: inner,
+            funds,
+        } => {
+            *balance = spend(balance, funds)?;
+            match (from_json(inner), funds.as_slice()) {
+                (Ok(fin::ExecuteMsg::Swap(req)), [offer]) if req.to().is_none() => {
+                    *balance += fin_swap(deps.querier, contract_addr, offer)?;
+                    Ok((msg.clone(), None, true))
+                }
+                _ => Ok((msg.clone(), None, false)),
+            }
+        }
+        LiquidateMsg::Queue {
+            contract_addr,
+            amount,
+        } => {
+            *balance = spend(balance, &[amount.clone()])?;
+            for x in queue_sale(deps.querier, contract_addr, amount)? {
+                *balance += x;
+            }
+            Ok((msg.clone(), None, true))
+        }
+        LiquidateMsg::FlashBorrow { .. } => Err(ContractError::InvalidFlashBorrow {}),
+        LiquidateMsg::RepayBest {} => Err(ContractError::NoRepayableDebt {}),
+    }
+}
+
+fn amount(balance: &NativeBalance, denom: &str) -> Uint128 {
+    balance
+        .0
+        .iter()
+        .find(|x| x.denom == denom)
+        .map(|x| x.amount)
+        .unwrap_or_default()
+}
+
+fn spend(balance: &NativeBalance, funds: &[Coin]) -> Result<NativeBalance, ContractError> {
+    funds.iter().try_fold(balance.clone(), |agg, x| {
+        (agg - x.clone()).map_err(|_| ContractError::InsufficientFunds {})
+    })
+}
+
+/// The return of a market swap of `offer` on the fin pair at `contract`
+fn fin_swap(q: QuerierWrapper, contract: &str, offer: &Coin) -> StdResult<Coin> {
+    let config: fin::ConfigResponse = q.query_wasm_smart(contract, &fin::QueryMsg::Config {})?;
+    let res: fin::SimulationResponse =
+        q.query_wasm_smart(contract, &fin::QueryMsg::Simulate(offer.clone()))?;
+    let side = config.denoms.ask_side(offer)?;
+    Ok(coin(res.returned.u128(), config.denoms.bid(&side)))
+}
+
+/// The bid denom returned for, and the remainder of, `amount` of collateral sold into the
+/// liquidation queue at `contract`
+fn queue_sale(q: QuerierWrapper, contract: &str, amount: &Coin) -> StdResult<Vec<Coin>> {
+    let config: orca::ConfigResponse = q.query_wasm_smart(contract, &orca::QueryMsg::Config {})?;
+    let res: orca::SimulationResponse =
+        q.query_wasm_smart(contract, &orca::QueryMsg::Simulate(amount.clone()))?;
+    Ok(vec![
+        coin(res.returned.u128(), config.denoms.bid()),
+        coin(res.remaining.u128(), &amount.denom),
+    ])
+}
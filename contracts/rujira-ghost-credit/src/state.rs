@@ -1,5 +1,16 @@
+use cosmwasm_std::Addr;
 use cw_storage_plus::Map;
-use rujira_rs::ghost::vault::Vault;
+use rujira_rs::ghost::{
+    credit::{LiquidationPreferences, Valuation},
+    vault::Vault,
+};
 
 /// Contracts and messages to borrow String denom
 pub static BORROW: Map<String, Vault> = Map::new("borrow");
+
+/// Liquidation preference templates by (owner, name), followed by the owner's Accounts
+pub static PREFERENCE_TEMPLATES: Map<(Addr, String), LiquidationPreferences> =
+    Map::new("preference-templates");
+
+/// The last valuation recorded for each Account, by Account address
+pub static VALUATIONS: Map<Addr, Valuation> = Map::new("valuations");
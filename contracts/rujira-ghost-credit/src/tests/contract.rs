@@ -1,20 +1,27 @@
 use std::str::FromStr;
 
 use cosmwasm_std::{
-    coin, coins, to_json_binary, Addr, BankMsg, Binary, CosmosMsg, Decimal, Event, Uint128, WasmMsg,
+    coin, coins, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Empty, Event,
+    StdError, StdResult, Uint128, WasmMsg,
 };
-use cw_multi_test::Executor;
+use cw_multi_test::{ContractWrapper, Executor};
 use rujira_bow::mock::Bow;
+use rujira_fin::mock::Fin;
 use rujira_ghost_vault::mock::GhostVault;
 use rujira_rs::{
     bow,
-    ghost::credit::{AccountMsg, AccountResponse, AccountsResponse, Collateral, ExecuteMsg},
+    fin::{self, Denoms, Price, Side},
+    ghost::credit::{
+        AccountMsg, AccountResponse, AccountsResponse, ClaimAdapter, Collateral, ConfigUpdate,
+        ExecuteMsg, LiquidateMsg, MonitorMsg, PreferenceTemplate, RepaySchedule,
+    },
+    PageRequest,
 };
 use rujira_rs_testing::{mock_rujira_app, RujiraApp};
 
 use crate::{
     mock::GhostCredit,
-    tests::support::{BTC, ETH, USDC},
+    tests::support::{fixture, funded_account, lending_vault, Fixture, BTC, ETH, USDC},
 };
 
 #[test]
@@ -83,11 +90,984 @@ fn account_lifecycle() {
 
     let all = credit.query_all_accounts(&app, None);
     assert_eq!(all.accounts.len(), 2);
+    assert_eq!(all.page.next, None);
     let page = credit.query_all_accounts(&app, Some(all.accounts[0].clone().account));
     assert_eq!(page.accounts.len(), 1);
     close(&mut app, &credit, &account);
 }
 
+#[test]
+fn risk_report() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    credit.set_collateral(&mut app, USDC, "0.5");
+    lending_vault(&mut app, &owner, &credit, USDC);
+    let account = funded_account(&mut app, &owner, &credit, &coins(2000, BTC));
+
+    // Borrow 500 USDC against 1000 of adjusted BTC collateral, and send it out of the account
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![
+                AccountMsg::Borrow(coin(500, USDC)),
+                AccountMsg::Send {
+                    to_address: owner.to_string(),
+                    funds: coins(500, USDC),
+                },
+            ],
+        )
+        .unwrap();
+
+    let report = credit.query_risk_report(&app, &account.account);
+    assert_eq!(report.collateral, Decimal::from_str("1000").unwrap());
+    assert_eq!(report.debt, Decimal::from_str("500").unwrap());
+    assert_eq!(report.ltv, Decimal::from_str("0.5").unwrap());
+    assert_eq!(report.adjustment.ltv, Decimal::from_str("0.45").unwrap());
+    assert_eq!(
+        report.adjustment.debt_usd,
+        Decimal::from_str("450").unwrap()
+    );
+    assert_eq!(
+        report.adjustment.collateral_usd,
+        Decimal::from_str("473.684210526315789474").unwrap()
+    );
+    assert_eq!(report.liquidation.ltv, Decimal::from_str("0.5").unwrap());
+    assert_eq!(
+        report.liquidation.debt_usd,
+        Decimal::from_str("500").unwrap()
+    );
+    assert_eq!(
+        report.liquidation.collateral_usd,
+        Decimal::from_str("500").unwrap()
+    );
+    // 450 of headroom, less the 0.475 of each borrowed USDC counted as collateral
+    assert_eq!(report.max_borrow, vec![coin(857, USDC)]);
+    assert_eq!(report.shortfall, vec![]);
+
+    // Halving the BTC price takes the account to the liquidation threshold
+    app.init_modules(|router, _, _| {
+        router
+            .stargate
+            .with_price("BTC", Decimal::from_str("0.5").unwrap());
+    });
+    let report = credit.query_risk_report(&app, &account.account);
+    assert_eq!(report.ltv, Decimal::one());
+    assert_eq!(report.adjustment.ltv, Decimal::zero());
+    assert_eq!(report.adjustment.collateral_usd, Decimal::zero());
+    assert_eq!(report.liquidation.debt_usd, Decimal::zero());
+    assert_eq!(report.max_borrow, vec![coin(0, USDC)]);
+    // 25 USD of excess debt, less the 0.475 of each BTC sold that leaves the collateral
+    assert_eq!(report.shortfall, vec![coin(96, BTC)]);
+}
+
+#[test]
+fn monitor() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    lending_vault(&mut app, &owner, &credit, USDC);
+    let account = funded_account(&mut app, &owner, &credit, &coins(2000, BTC));
+
+    let code =
+        ContractWrapper::new(
+            |_, _, _, msg: MonitorMsg| -> StdResult<cosmwasm_std::Response> {
+                let MonitorMsg::CreditAccount {
+                    account,
+                    ltv,
+                    action,
+                } = msg;
+                Ok(cosmwasm_std::Response::default().add_event(
+                    Event::new("credit").add_attributes(vec![
+                        ("account", account),
+                        ("ltv", ltv.to_string()),
+                        ("action", format!("{action:?}")),
+                    ]),
+                ))
+            },
+            |_, _, _, _: Empty| -> StdResult<cosmwasm_std::Response> { Ok(Default::default()) },
+            |_, _, _: Empty| -> StdResult<Binary> { Ok(Binary::default()) },
+        );
+    let code_id = app.store_code(Box::new(code));
+    let monitor = app
+        .instantiate_contract(code_id, owner.clone(), &Empty {}, &[], "monitor", None)
+        .unwrap();
+    credit.set_monitor(&mut app, Some(&monitor));
+    assert_eq!(credit.query_config(&app).monitor, Some(monitor));
+
+    let res = credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Borrow(coin(500, USDC))],
+        )
+        .unwrap();
+    res.assert_event(&Event::new("wasm-credit").add_attributes(vec![
+        ("account", account.account.as_str()),
+        ("action", "Account"),
+    ]));
+
+    // A failing monitor doesn't block the Account
+    let code = ContractWrapper::new(
+        |_, _, _, _: MonitorMsg| -> StdResult<cosmwasm_std::Response> {
+            Err(StdError::generic_err("monitor down"))
+        },
+        |_, _, _, _: Empty| -> StdResult<cosmwasm_std::Response> { Ok(Default::default()) },
+        |_, _, _: Empty| -> StdResult<Binary> { Ok(Binary::default()) },
+    );
+    let code_id = app.store_code(Box::new(code));
+    let monitor = app
+        .instantiate_contract(code_id, owner.clone(), &Empty {}, &[], "monitor", None)
+        .unwrap();
+    credit.set_monitor(&mut app, Some(&monitor));
+    let res = credit
+        .account(&mut app, &account, vec![AccountMsg::Repay(coin(100, USDC))])
+        .unwrap();
+    res.assert_event(&Event::new("wasm-rujira-ghost-credit/monitor.error"));
+
+    credit.set_monitor(&mut app, None);
+    assert_eq!(credit.query_config(&app).monitor, None);
+}
+
+#[test]
+fn min_debt() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    lending_vault(&mut app, &owner, &credit, USDC);
+    let account = funded_account(&mut app, &owner, &credit, &coins(2000, BTC));
+
+    credit.update_config(
+        &mut app,
+        ConfigUpdate {
+            code_id: None,
+            fee_liquidation: None,
+            fee_liquidator: None,
+            fee_address: None,
+            liquidation_max_slip: None,
+            liquidation_threshold: None,
+            adjustment_threshold: None,
+            liquidation_cooldown: None,
+            min_debt_value: Some(Decimal::from_str("100").unwrap()),
+            safety_buffer: None,
+        },
+    );
+    assert_eq!(
+        credit.query_config(&app).min_debt_value,
+        Decimal::from_str("100").unwrap()
+    );
+
+    let err = credit
+        .account(&mut app, &account, vec![AccountMsg::Borrow(coin(50, USDC))])
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("below minimum"));
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Borrow(coin(500, USDC))],
+        )
+        .unwrap();
+
+    // A partial repay can't leave dust debt, a full repay can
+    let err = credit
+        .account(&mut app, &account, vec![AccountMsg::Repay(coin(450, USDC))])
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("below minimum"));
+    credit
+        .account(&mut app, &account, vec![AccountMsg::Repay(coin(400, USDC))])
+        .unwrap();
+
+    // Debt that falls below the minimum in value only holds back borrows and partial repays
+    app.init_modules(|router, _, _| {
+        router.stargate.with_prices(vec![
+            ("USDC", Decimal::percent(50)),
+            ("BTC", Decimal::one()),
+        ]);
+    });
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Send {
+                to_address: owner.to_string(),
+                funds: coins(100, BTC),
+            }],
+        )
+        .unwrap();
+    let err = credit
+        .account(&mut app, &account, vec![AccountMsg::Repay(coin(10, USDC))])
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("below minimum"));
+    credit
+        .account(&mut app, &account, vec![AccountMsg::Repay(coin(100, USDC))])
+        .unwrap();
+    let account = credit.query_account(&app, &account.account);
+    assert_eq!(account.debts, vec![]);
+}
+
+#[test]
+fn repay_schedule() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    let keeper = app.api().addr_make("keeper");
+    lending_vault(&mut app, &owner, &credit, USDC);
+    let account = funded_account(&mut app, &owner, &credit, &coins(2000, BTC));
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Borrow(coin(500, USDC))],
+        )
+        .unwrap();
+    credit
+        .set_repay_crank_fee(&mut app, Decimal::one())
+        .unwrap_err();
+    credit
+        .set_repay_crank_fee(&mut app, Decimal::percent(1))
+        .unwrap();
+
+    let schedule = |amount: Coin, interval: u64| {
+        AccountMsg::SetRepaySchedule(Some(RepaySchedule { amount, interval }))
+    };
+    credit
+        .account(&mut app, &account, vec![schedule(coin(100, USDC), 0)])
+        .unwrap_err();
+    credit
+        .account(&mut app, &account, vec![schedule(coin(100, BTC), 86400)])
+        .unwrap_err();
+    credit
+        .account(&mut app, &account, vec![schedule(coin(100, USDC), 86400)])
+        .unwrap();
+    let due = credit
+        .query_account(&app, &account.account)
+        .repay_schedule
+        .unwrap()
+        .due;
+    assert_eq!(due, app.block_info().time.plus_seconds(86400));
+
+    let err = credit.crank_repay(&mut app, &keeper, &account).unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("not due"));
+
+    app.update_block(|b| b.time = b.time.plus_seconds(86400));
+    let debt = credit.query_account(&app, &account.account).debts[0].value;
+    let res = credit.crank_repay(&mut app, &keeper, &account).unwrap();
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-credit/account.crank_repay").add_attributes(vec![
+            ("caller", keeper.as_str()),
+            ("repaid", &coin(100, USDC).to_string()),
+            ("fee", &coin(1, USDC).to_string()),
+        ]),
+    );
+    assert_eq!(
+        app.wrap().query_balance(&keeper, USDC).unwrap().amount,
+        Uint128::one()
+    );
+    assert_eq!(
+        app.wrap()
+            .query_balance(&account.account, USDC)
+            .unwrap()
+            .amount,
+        Uint128::from(399u128)
+    );
+    let updated = credit.query_account(&app, &account.account);
+    assert_eq!(
+        updated.debts[0].value,
+        debt - Decimal::from_str("100").unwrap()
+    );
+    assert_eq!(
+        updated.repay_schedule.unwrap().due,
+        app.block_info().time.plus_seconds(86400)
+    );
+
+    // One repayment per period
+    credit.crank_repay(&mut app, &keeper, &account).unwrap_err();
+
+    credit
+        .account(&mut app, &account, vec![AccountMsg::SetRepaySchedule(None)])
+        .unwrap();
+    app.update_block(|b| b.time = b.time.plus_seconds(86400));
+    let err = credit.crank_repay(&mut app, &keeper, &account).unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("No repay schedule"));
+}
+
+#[test]
+fn repay_schedule_safety() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    let keeper = app.api().addr_make("keeper");
+    credit.set_collateral(&mut app, USDC, "0.9");
+    lending_vault(&mut app, &owner, &credit, USDC);
+    let account = funded_account(&mut app, &owner, &credit, &coins(2000, BTC));
+
+    // 1900 of debt against 1000 of adjusted BTC and 1035 of adjusted USDC, an LTV of 0.934
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![
+                AccountMsg::Borrow(coin(1900, USDC)),
+                AccountMsg::Send {
+                    to_address: owner.to_string(),
+                    funds: coins(750, USDC),
+                },
+                AccountMsg::SetRepaySchedule(Some(RepaySchedule {
+                    amount: coin(100, USDC),
+                    interval: 86400,
+                })),
+            ],
+        )
+        .unwrap();
+    app.update_block(|b| b.time = b.time.plus_seconds(86400));
+
+    // Repaying 100 with a fee of 90 drawn from the USDC collateral would leave an LTV of 0.966
+    credit
+        .set_repay_crank_fee(&mut app, Decimal::percent(90))
+        .unwrap();
+    let err = credit.crank_repay(&mut app, &keeper, &account).unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("Account Unsafe"));
+    assert!(app
+        .wrap()
+        .query_balance(&keeper, USDC)
+        .unwrap()
+        .amount
+        .is_zero());
+
+    credit
+        .set_repay_crank_fee(&mut app, Decimal::percent(1))
+        .unwrap();
+    credit.crank_repay(&mut app, &keeper, &account).unwrap();
+    let account = credit.query_account(&app, &account.account);
+    assert!(account.ltv < Decimal::from_str("0.95").unwrap());
+}
+
+#[test]
+fn borrow_cap() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    lending_vault(&mut app, &owner, &credit, USDC);
+    let account = funded_account(&mut app, &owner, &credit, &coins(2000, BTC));
+
+    let cap = |denom: &str, cap: Option<u128>| AccountMsg::SetBorrowCap {
+        denom: denom.to_string(),
+        cap: cap.map(Uint128::from),
+    };
+    credit
+        .account(&mut app, &account, vec![cap(BTC, Some(100))])
+        .unwrap_err();
+    credit
+        .account(&mut app, &account, vec![cap(USDC, Some(300))])
+        .unwrap();
+    assert_eq!(
+        credit.query_account(&app, &account.account).borrow_caps,
+        coins(300, USDC)
+    );
+
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Borrow(coin(200, USDC))],
+        )
+        .unwrap();
+    let err = credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Borrow(coin(101, USDC))],
+        )
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("Borrow cap"));
+
+    // Borrows earlier in the same batch count towards the cap
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![
+                AccountMsg::Borrow(coin(60, USDC)),
+                AccountMsg::Borrow(coin(60, USDC)),
+            ],
+        )
+        .unwrap_err();
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Borrow(coin(100, USDC))],
+        )
+        .unwrap();
+
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![cap(USDC, None), AccountMsg::Borrow(coin(100, USDC))],
+        )
+        .unwrap();
+    let account = credit.query_account(&app, &account.account);
+    assert_eq!(account.borrow_caps, vec![]);
+    assert_eq!(account.debts[0].value, Decimal::from_str("400").unwrap());
+}
+
+#[test]
+fn repay_receipt() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    let vault = lending_vault(&mut app, &owner, &credit, USDC);
+    let account = funded_account(
+        &mut app,
+        &owner,
+        &credit,
+        &[coin(2000, BTC), coin(200, USDC)],
+    );
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![
+                AccountMsg::Borrow(coin(500, USDC)),
+                AccountMsg::Execute {
+                    contract_addr: vault.addr().to_string(),
+                    msg: to_json_binary(&rujira_rs::ghost::vault::ExecuteMsg::Deposit {
+                        callback: None,
+                    })
+                    .unwrap(),
+                    funds: coins(700, USDC),
+                },
+            ],
+        )
+        .unwrap();
+    let receipt = format!("x/ghost-vault/{USDC}");
+    assert_eq!(
+        app.wrap()
+            .query_balance(&account.account, &receipt)
+            .unwrap()
+            .amount,
+        Uint128::new(700)
+    );
+
+    // A partial repay in receipt tokens
+    let res = credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Repay(coin(300, &receipt))],
+        )
+        .unwrap();
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-vault/repay").add_attributes(vec![
+            ("borrower", credit.addr().as_str()),
+            ("delegate", account.account.as_str()),
+            ("amount", "300"),
+        ]),
+    );
+    let res = credit.query_account(&app, &account.account);
+    assert_eq!(res.debts[0].value, Decimal::from_str("200").unwrap());
+
+    // The excess over the debt is returned to the Account
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Repay(coin(400, &receipt))],
+        )
+        .unwrap();
+    let res = credit.query_account(&app, &account.account);
+    assert_eq!(res.debts, vec![]);
+    assert_eq!(
+        app.wrap()
+            .query_balance(&account.account, USDC)
+            .unwrap()
+            .amount,
+        Uint128::new(200)
+    );
+    assert_eq!(
+        app.wrap()
+            .query_balance(&account.account, &receipt)
+            .unwrap()
+            .amount,
+        Uint128::zero()
+    );
+
+    // Only the vault can deliver the underlying
+    let err = app
+        .execute_contract(
+            owner.clone(),
+            credit.addr().clone(),
+            &ExecuteMsg::DoRepay {
+                addr: account.account.to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("Unauthorized"));
+}
+
+#[test]
+fn create_funded() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    lending_vault(&mut app, &owner, &credit, USDC);
+
+    // The Account is still checked once its msgs have executed
+    let err = credit
+        .create_account_with(
+            &mut app,
+            &owner,
+            "",
+            "",
+            Binary::new(vec![0]),
+            vec![AccountMsg::Borrow(coin(1500, USDC))],
+            &coins(2000, BTC),
+        )
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("Unsafe"));
+
+    let res = credit
+        .create_account_with(
+            &mut app,
+            &owner,
+            "",
+            "",
+            Binary::new(vec![0]),
+            vec![AccountMsg::Borrow(coin(500, USDC))],
+            &coins(2000, BTC),
+        )
+        .unwrap();
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-credit/account.msg/borrow")
+            .add_attributes(vec![("amount", format!("500{USDC}"))]),
+    );
+    let account = credit.query_accounts(&app, &owner, None).accounts[0].clone();
+    assert_eq!(
+        account.collaterals[0].collateral,
+        Collateral::Coin(coin(2000, BTC))
+    );
+    assert_eq!(account.debts[0].value, Decimal::from_str("500").unwrap());
+    assert_eq!(
+        app.wrap()
+            .query_balance(&account.account, USDC)
+            .unwrap()
+            .amount,
+        Uint128::from(500u128)
+    );
+}
+
+#[test]
+fn safety_buffer() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    lending_vault(&mut app, &owner, &credit, USDC);
+    let account = funded_account(&mut app, &owner, &credit, &coins(2000, BTC));
+
+    credit.update_config(
+        &mut app,
+        ConfigUpdate {
+            code_id: None,
+            fee_liquidation: None,
+            fee_liquidator: None,
+            fee_address: None,
+            liquidation_max_slip: None,
+            liquidation_threshold: None,
+            adjustment_threshold: None,
+            liquidation_cooldown: None,
+            min_debt_value: None,
+            safety_buffer: Some(Decimal::percent(5)),
+        },
+    );
+    assert_eq!(credit.query_config(&app).safety_buffer, Decimal::percent(5));
+
+    // 1000 adjusted collateral. 940 debt is under the 0.95 adjustment threshold, but not the buffer
+    let err = credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Borrow(coin(940, USDC))],
+        )
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("Unsafe"));
+    let report = credit.query_risk_report(&app, &account.account);
+    assert_eq!(report.max_borrow, vec![coin(900, USDC)]);
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::Borrow(coin(890, USDC))],
+        )
+        .unwrap();
+}
+
+#[test]
+fn preference_template() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let other = app.api().addr_make("other");
+    let fees = app.api().addr_make("fee");
+    let credit = GhostCredit::create(&mut app, &owner, &fees);
+    credit.set_collateral(&mut app, BTC, "0.9");
+    credit.set_collateral(&mut app, ETH, "0.9");
+
+    credit.create_account(&mut app, &owner, "", "", Binary::new(vec![0]));
+    credit.create_account(&mut app, &owner, "", "", Binary::new(vec![1]));
+    let accounts = credit.query_accounts(&app, &owner, None).accounts;
+
+    let msg = LiquidateMsg::Repay(USDC.to_string());
+    let template = |order: Vec<(&str, &str)>| PreferenceTemplate {
+        messages: vec![msg.clone()],
+        order: order
+            .into_iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect(),
+    };
+
+    // Only registered collaterals, without cycles
+    credit
+        .set_preference_template(&mut app, &owner, "fund", Some(template(vec![(BTC, USDC)])))
+        .unwrap_err();
+    credit
+        .set_preference_template(
+            &mut app,
+            &owner,
+            "fund",
+            Some(template(vec![(BTC, ETH), (ETH, BTC)])),
+        )
+        .unwrap_err();
+    // Unknown templates can't be followed
+    credit
+        .account(
+            &mut app,
+            &accounts[0],
+            vec![AccountMsg::SetPreferenceTemplate(Some("fund".to_string()))],
+        )
+        .unwrap_err();
+
+    let res = credit
+        .set_preference_template(&mut app, &owner, "fund", Some(template(vec![])))
+        .unwrap();
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-credit/preference_template").add_attributes(vec![
+            ("owner", owner.as_str()),
+            ("name", "fund"),
+            ("action", "save"),
+        ]),
+    );
+    let templates = credit.query_preference_templates(&app, &owner).templates;
+    assert_eq!(templates.len(), 1);
+    assert_eq!(templates[0].name, "fund");
+    assert_eq!(templates[0].preferences.messages, vec![msg.clone()]);
+    assert!(credit
+        .query_preference_templates(&app, &other)
+        .templates
+        .is_empty());
+
+    for account in accounts.iter() {
+        credit
+            .account(
+                &mut app,
+                account,
+                vec![AccountMsg::SetPreferenceTemplate(Some("fund".to_string()))],
+            )
+            .unwrap();
+    }
+    for account in credit.query_accounts(&app, &owner, None).accounts {
+        assert_eq!(account.preference_template, Some("fund".to_string()));
+        assert_eq!(account.liquidation_preferences, templates[0].preferences);
+    }
+
+    // Updates propagate to every account following the template
+    credit
+        .set_preference_template(&mut app, &owner, "fund", Some(template(vec![(BTC, ETH)])))
+        .unwrap();
+    let updated = credit.query_preference_templates(&app, &owner).templates[0]
+        .preferences
+        .clone();
+    assert_ne!(updated, templates[0].preferences);
+    for account in credit.query_accounts(&app, &owner, None).accounts {
+        assert_eq!(account.liquidation_preferences, updated);
+    }
+
+    // Own preferences are kept, and enforced once the template is removed
+    credit
+        .account_preference_order(&mut app, &accounts[0], ETH, Some(BTC))
+        .unwrap();
+    let account = credit.query_account(&app, &accounts[0].account);
+    assert_eq!(account.liquidation_preferences, updated);
+    credit
+        .set_preference_template(&mut app, &owner, "fund", None)
+        .unwrap();
+    let account = credit.query_account(&app, &accounts[0].account);
+    assert_eq!(account.preference_template, Some("fund".to_string()));
+    assert!(account.liquidation_preferences.messages.is_empty());
+    assert_ne!(account.liquidation_preferences, Default::default());
+    let account = credit.query_account(&app, &accounts[1].account);
+    assert_eq!(account.liquidation_preferences, Default::default());
+
+    // Templates stay with the owner on transfer
+    credit
+        .set_preference_template(&mut app, &owner, "fund", Some(template(vec![])))
+        .unwrap();
+    credit
+        .account(
+            &mut app,
+            &accounts[1],
+            vec![AccountMsg::Transfer(other.to_string())],
+        )
+        .unwrap();
+    let account = credit.query_account(&app, &accounts[1].account);
+    assert_eq!(account.preference_template, None);
+    assert_eq!(account.liquidation_preferences, Default::default());
+}
+
+#[test]
+fn correlation() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    credit.set_collateral(&mut app, USDC, "0.5");
+    lending_vault(&mut app, &owner, &credit, BTC);
+    let account = funded_account(&mut app, &owner, &credit, &coins(2000, BTC));
+    credit
+        .account(&mut app, &account, vec![AccountMsg::Borrow(coin(900, BTC))])
+        .unwrap();
+
+    // 2900 BTC held, 1450 adjusted, against 900 BTC of debt
+    let res = credit.query_account(&app, &account.account);
+    assert_eq!(
+        res.collaterals[0].value_adjusted,
+        Decimal::from_str("1450").unwrap()
+    );
+    let ltv = res.debts[0].value / res.collaterals[0].value_adjusted;
+    assert_eq!(res.ltv, ltv);
+
+    // Collateral held in another denom doesn't offset the debt
+    credit.set_correlation(&mut app, USDC, BTC, Some("1"));
+    assert_eq!(credit.query_account(&app, &account.account).ltv, ltv);
+
+    // The 900 BTC of debt is fully hedged by the BTC held
+    credit.set_correlation(&mut app, BTC, BTC, Some("1"));
+    assert_eq!(credit.query_config(&app).correlations.len(), 2);
+    let res = credit.query_account(&app, &account.account);
+    assert_eq!(res.ltv, Decimal::zero());
+    let report = credit.query_risk_report(&app, &account.account);
+    assert_eq!(report.debt, Decimal::zero());
+    assert!(report.collateral >= Decimal::from_str("999").unwrap());
+
+    credit.set_correlation(&mut app, BTC, BTC, None);
+    credit.set_correlation(&mut app, USDC, BTC, None);
+    assert_eq!(credit.query_config(&app).correlations, vec![]);
+    assert_eq!(credit.query_account(&app, &account.account).ltv, ltv);
+}
+
+#[test]
+fn fin_order_collateral() {
+    let mut app = mock_rujira_app();
+    app.init_modules(|router, _, _| {
+        router.stargate.with_prices(vec![
+            ("USDC", Decimal::one()),
+            ("BTC", Decimal::from_str("2").unwrap()),
+        ]);
+    });
+
+    let owner = app.api().addr_make("owner");
+    let fees = app.api().addr_make("fee");
+    let taker = app.api().addr_make("taker");
+    let credit = GhostCredit::create(&mut app, &owner, &fees);
+    let account = create(&mut app, &credit, owner.clone());
+    fund(&mut app, &credit, &account);
+    configure(&mut app, &credit);
+    app.init_modules(|router, _api, storage| {
+        router.bank.init_balance(storage, &taker, coins(1000, BTC))
+    })
+    .unwrap();
+
+    let fin = Fin::create(&mut app, &owner, &fees, &[], Denoms::new(BTC, USDC), None);
+    credit
+        .account_execute(
+            &mut app,
+            &account,
+            fin.addr(),
+            &fin::ExecuteMsg::Order((
+                vec![(
+                    Side::Quote,
+                    Price::Fixed(Decimal::from_str("2").unwrap()),
+                    None,
+                )],
+                None,
+            )),
+            coins(1000, USDC),
+        )
+        .unwrap();
+
+    // Pair not yet whitelisted, the order is invisible
+    let res = credit.query_account(&app, &account.account);
+    assert_eq!(res.collaterals, vec![]);
+
+    credit.set_fin_collateral(&mut app, fin.addr(), Some("0.1"));
+    let res = credit.query_account(&app, &account.account);
+    assert_eq!(res.collaterals.len(), 1);
+    assert_eq!(
+        res.collaterals[0].collateral,
+        Collateral::FinOrders {
+            contract: fin.addr().clone(),
+            haircut: Decimal::percent(10),
+            balance: coins(1000, USDC),
+        }
+    );
+    assert_eq!(
+        res.collaterals[0].value_full,
+        Decimal::from_str("1000").unwrap()
+    );
+    // 1000 * 0.9 collateral ratio * 0.9 haircut
+    assert_eq!(
+        res.collaterals[0].value_adjusted,
+        Decimal::from_str("810").unwrap()
+    );
+
+    // Partial fill, filled BTC is counted alongside the remaining USDC
+    fin.swap(&mut app, &taker, 100, BTC, None).unwrap();
+    let order = fin
+        .query_orders(&app, account.account.as_str(), None, PageRequest::default())
+        .unwrap()
+        .orders[0]
+        .clone();
+    assert!(!order.filled.is_zero());
+    let res = credit.query_account(&app, &account.account);
+    assert_eq!(
+        res.collaterals[0].collateral,
+        Collateral::FinOrders {
+            contract: fin.addr().clone(),
+            haircut: Decimal::percent(10),
+            balance: vec![
+                coin(order.filled.u128(), BTC),
+                coin(order.remaining.u128(), USDC)
+            ],
+        }
+    );
+
+    // Loading the Account for execution claims the filled BTC into the Account
+    let before = app.wrap().query_balance(&account.account, BTC).unwrap();
+    credit.account(&mut app, &account, vec![]).unwrap();
+    let after = app.wrap().query_balance(&account.account, BTC).unwrap();
+    assert_eq!(after.amount - before.amount, order.filled);
+    let claimed = fin
+        .query_orders(&app, account.account.as_str(), None, None, None)
+        .unwrap()
+        .orders[0]
+        .clone();
+    assert!(claimed.filled.is_zero());
+    assert_eq!(claimed.remaining, order.remaining);
+    let res = credit.query_account(&app, &account.account);
+    assert!(res.collaterals.iter().any(|x| x.collateral
+        == Collateral::FinOrders {
+            contract: fin.addr().clone(),
+            haircut: Decimal::percent(10),
+            balance: coins(order.remaining.u128(), USDC),
+        }));
+
+    credit.set_fin_collateral(&mut app, fin.addr(), None);
+    let res = credit.query_account(&app, &account.account);
+    assert!(!res
+        .collaterals
+        .iter()
+        .any(|x| matches!(x.collateral, Collateral::FinOrders { .. })));
+}
+
+#[test]
+fn execute_allowlist() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let fees = app.api().addr_make("fee");
+    let credit = GhostCredit::create(&mut app, &owner, &fees);
+    let account = create(&mut app, &credit, owner.clone());
+    fund(&mut app, &credit, &account);
+    configure(&mut app, &credit);
+    let fin = Fin::create(&mut app, &owner, &fees, &[], Denoms::new(BTC, USDC), None);
+    let other = Fin::create(&mut app, &owner, &fees, &[], Denoms::new(ETH, USDC), None);
+    let order = |app: &mut RujiraApp, fin: &Fin, account: &AccountResponse| {
+        credit.account_execute(
+            app,
+            account,
+            fin.addr(),
+            &fin::ExecuteMsg::Order((
+                vec![(
+                    Side::Quote,
+                    Price::Fixed(Decimal::from_str("2").unwrap()),
+                    None,
+                )],
+                None,
+            )),
+            coins(10, USDC),
+        )
+    };
+
+    // Unrestricted by default
+    order(&mut app, &other, &account).unwrap();
+
+    credit.set_execute_allowlist(&mut app, Some(vec![fin.addr()]));
+    assert_eq!(
+        credit.query_config(&app).execute_allowlist,
+        Some(vec![fin.addr().clone()])
+    );
+    order(&mut app, &fin, &account).unwrap();
+    let err = order(&mut app, &other, &account).unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("isn't in the allowlist"));
+
+    // The owner opts the Account out
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::SetExecuteUnrestricted(true)],
+        )
+        .unwrap();
+    let account = credit.query_account(&app, &account.account);
+    assert!(account.execute_unrestricted);
+    order(&mut app, &other, &account).unwrap();
+
+    credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::SetExecuteUnrestricted(false)],
+        )
+        .unwrap();
+    order(&mut app, &other, &account).unwrap_err();
+
+    credit.set_execute_allowlist(&mut app, None);
+    order(&mut app, &other, &account).unwrap();
+}
+
 fn create(app: &mut RujiraApp, credit: &GhostCredit, owner: Addr) -> AccountResponse {
     let res = credit.create_account(app, &owner, "", "", Binary::new(vec![0]));
     res.assert_event(
@@ -390,3 +1370,82 @@ fn close(app: &mut RujiraApp, credit: &GhostCredit, account: &AccountResponse) {
     assert_eq!(res.debts.len(), 0);
     assert_eq!(res.ltv, Decimal::zero());
 }
+
+#[test]
+fn compound() {
+    let Fixture {
+        mut app,
+        owner,
+        credit,
+    } = fixture();
+    let keeper = app.api().addr_make("keeper");
+    let account = funded_account(&mut app, &owner, &credit, &coins(2000, BTC));
+
+    // Pays out 10 BTC of rewards to the caller on every claim
+    let code = ContractWrapper::new(
+        |_, _, info, _: Empty| -> StdResult<cosmwasm_std::Response> {
+            Ok(
+                cosmwasm_std::Response::default().add_message(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: coins(10, BTC),
+                }),
+            )
+        },
+        |_, _, _, _: Empty| -> StdResult<cosmwasm_std::Response> { Ok(Default::default()) },
+        |_, _, _: Empty| -> StdResult<Binary> { Ok(Binary::default()) },
+    );
+    let code_id = app.store_code(Box::new(code));
+    let claim = app
+        .instantiate_contract(code_id, owner.clone(), &Empty {}, &[], "claim", None)
+        .unwrap();
+    app.send_tokens(owner.clone(), claim.clone(), &coins(100, BTC))
+        .unwrap();
+
+    let err = credit
+        .compound(&mut app, &keeper, &account, vec![BTC])
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("No claim adapter"));
+
+    let adapter = ClaimAdapter {
+        contract_addr: claim.to_string(),
+        msg: to_json_binary(&Empty {}).unwrap(),
+    };
+    let err = app
+        .wasm_sudo(
+            credit.addr().clone(),
+            &rujira_rs::ghost::credit::SudoMsg::SetClaimAdapter {
+                denom: USDC.to_string(),
+                adapter: Some(adapter.clone()),
+            },
+        )
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("not registered as valid collateral"));
+
+    credit.set_claim_adapter(&mut app, BTC, Some(adapter.clone()));
+    assert_eq!(
+        credit.query_config(&app).claim_adapters.get(BTC),
+        Some(&adapter)
+    );
+
+    // Anyone can compound, and the rewards land in the Account. Repeated denoms claim once
+    let res = credit
+        .compound(&mut app, &keeper, &account, vec![BTC, BTC])
+        .unwrap();
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-credit/compound").add_attributes(vec![
+            ("address", account.account.as_str()),
+            ("denom", BTC),
+            ("contract_addr", claim.as_str()),
+        ]),
+    );
+    assert_eq!(
+        app.wrap()
+            .query_balance(&account.account, BTC)
+            .unwrap()
+            .amount,
+        Uint128::new(2010)
+    );
+
+    credit.set_claim_adapter(&mut app, BTC, None);
+    assert!(credit.query_config(&app).claim_adapters.is_empty());
+}
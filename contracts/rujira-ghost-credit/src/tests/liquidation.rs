@@ -1,16 +1,21 @@
 use std::str::FromStr;
 
-use cosmwasm_std::{coin, coins, Addr, Binary, Decimal, Uint128};
+use cosmwasm_std::{coin, coins, to_json_binary, Addr, Binary, Coin, Decimal, Event, Uint128};
 use cw_multi_test::Executor;
 use rujira_bow::mock::Bow;
 use rujira_fin::mock::Fin;
 use rujira_ghost_vault::mock::GhostVault;
+use rujira_orca::mock::Orca;
 use rujira_rs::{
     fin::{self, Denoms},
     ghost::{
-        credit::{AccountResponse, Collateral, CollateralResponse, Debt},
+        credit::{
+            AccountMsg, AccountResponse, Collateral, CollateralResponse, ConfigUpdate, Debt,
+            LiquidateMsg, PokeReward,
+        },
         vault::{BorrowerResponse, DelegateResponse},
     },
+    orca,
 };
 use rujira_rs_testing::{mock_rujira_app, RujiraApp};
 
@@ -234,6 +239,7 @@ fn liquidation_preference_order() {
             fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
                 to: None,
                 callback: None,
+                max_levels: None,
             }),
             coins(1000000, BTC),
             USDC,
@@ -254,6 +260,7 @@ fn liquidation_preference_order() {
             fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
                 to: None,
                 callback: None,
+                max_levels: None,
             }),
             coins(30000000, ETH),
             USDC,
@@ -283,6 +290,8 @@ fn liquidation_preference_order() {
                 current: Uint128::from(1207480372940u128),
                 shares: Uint128::from(1207480372940u128),
                 available: Uint128::from(98792519627060u128),
+                health_check: None,
+                concentration: Decimal::from_ratio(1207480372940u128, 100000000000000u128),
             },
             addr: ctx.account.account.to_string(),
             current: Uint128::from(1207480372940u128),
@@ -309,6 +318,7 @@ fn liquidation_preference_order() {
             fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
                 to: None,
                 callback: None,
+                max_levels: None,
             }),
             coins(170000000, ETH),
             USDC,
@@ -340,6 +350,7 @@ fn liquidation_preference_order() {
             fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
                 to: None,
                 callback: None,
+                max_levels: None,
             }),
             coins(500000, BTC),
             USDC,
@@ -402,6 +413,7 @@ fn incorrect_return_asset() {
             fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
                 to: None,
                 callback: None,
+                max_levels: None,
             }),
             coins(1000000, BTC),
             USDT,
@@ -413,3 +425,956 @@ fn incorrect_return_asset() {
     // Zero Debt Pool in USDT
     assert_eq!(msg, "ZeroDebt");
 }
+
+#[test]
+fn flash_close() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let ctx = setup(&mut app, &owner);
+
+    // 0.1BTC
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC)],
+    )
+    .unwrap();
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+
+    // $5,000 against $8,880 adjusted collateral
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 500000000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 500000000000, USDC, &owner)
+        .unwrap();
+
+    let swap = |amount: u128| LiquidateMsg::Execute {
+        contract_addr: ctx.fin_btc_usdc.addr().to_string(),
+        msg: to_json_binary(&fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+            to: None,
+            callback: None,
+            max_levels: None,
+        }))
+        .unwrap(),
+        funds: coins(amount, BTC),
+    };
+
+    // 0.01 BTC is insufficient to cover the debt
+    let err = ctx
+        .ghost_credit
+        .account_flash_close(&mut app, &account, vec![swap(1000000)])
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("Insolvent"));
+
+    let btc_before = app.wrap().query_balance(&owner, BTC).unwrap().amount;
+    let usdc_before = app.wrap().query_balance(&owner, USDC).unwrap().amount;
+
+    ctx.ghost_credit
+        .account_flash_close(&mut app, &account, vec![swap(5000000)])
+        .unwrap();
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.debts.is_empty());
+    assert!(account.collaterals.is_empty());
+    assert_eq!(account.ltv, Decimal::zero());
+
+    // Remaining BTC and surplus USDC returned to the owner, no fees taken
+    let btc_after = app.wrap().query_balance(&owner, BTC).unwrap().amount;
+    let usdc_after = app.wrap().query_balance(&owner, USDC).unwrap().amount;
+    assert_eq!(btc_after - btc_before, Uint128::from(5000000u128));
+    assert!(usdc_after > usdc_before);
+    assert!(app
+        .wrap()
+        .query_balance(ctx.ghost_credit.addr(), USDC)
+        .unwrap()
+        .amount
+        .is_zero());
+}
+
+#[test]
+fn flash_close_allowlist() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let ctx = setup(&mut app, &owner);
+
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC)],
+    )
+    .unwrap();
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 500000000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 500000000000, USDC, &owner)
+        .unwrap();
+
+    let swap = LiquidateMsg::Execute {
+        contract_addr: ctx.fin_btc_usdc.addr().to_string(),
+        msg: to_json_binary(&fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+            to: None,
+            callback: None,
+            max_levels: None,
+        }))
+        .unwrap(),
+        funds: coins(5000000, BTC),
+    };
+
+    // Routes can't call a contract that the Account can't execute
+    let other = app.api().addr_make("other");
+    ctx.ghost_credit
+        .set_execute_allowlist(&mut app, Some(vec![&other]));
+    let err = ctx
+        .ghost_credit
+        .account_flash_close(&mut app, &account, vec![swap.clone()])
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("isn't in the allowlist"));
+
+    ctx.ghost_credit
+        .set_execute_allowlist(&mut app, Some(vec![ctx.fin_btc_usdc.addr()]));
+    ctx.ghost_credit
+        .account_flash_close(&mut app, &account, vec![swap])
+        .unwrap();
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.debts.is_empty());
+}
+
+#[test]
+fn flash_liquidation() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let ctx = setup(&mut app, &owner);
+
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC), coin(200000000, ETH)],
+    )
+    .unwrap();
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 1309100000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 1309100000000, USDC, &owner)
+        .unwrap();
+
+    app.init_modules(|router, _api, _storage| {
+        router.stargate.with_prices(vec![
+            ("BTC", Decimal::from_str("104450").unwrap()),
+            ("ETH", Decimal::from_str("3225").unwrap()),
+        ]);
+    });
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv > Decimal::one());
+
+    let flash = |amount: u128| LiquidateMsg::FlashBorrow {
+        denom: USDC.to_string(),
+        amount: Uint128::from(amount),
+    };
+    let swap = LiquidateMsg::Execute {
+        contract_addr: ctx.fin_eth_usdc.addr().to_string(),
+        msg: to_json_binary(&fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+            to: None,
+            callback: None,
+            max_levels: None,
+        }))
+        .unwrap(),
+        funds: coins(50000000, ETH),
+    };
+
+    // Only valid as the first message
+    let err = ctx
+        .ghost_credit
+        .liquidate(&mut app, &account, vec![swap.clone(), flash(150000000000)])
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("InvalidFlashBorrow"));
+
+    // 0.5 ETH can't cover a $3,000 loan
+    ctx.ghost_credit
+        .liquidate(
+            &mut app,
+            &account,
+            vec![
+                flash(300000000000),
+                LiquidateMsg::Repay(USDC.to_string()),
+                swap.clone(),
+            ],
+        )
+        .unwrap_err();
+
+    // Debt is repaid with the loan before the collateral is sold, and the loan
+    // is repaid from the swap return
+    let res = ctx
+        .ghost_credit
+        .liquidate(
+            &mut app,
+            &account,
+            vec![
+                flash(150000000000),
+                LiquidateMsg::Repay(USDC.to_string()),
+                swap,
+            ],
+        )
+        .unwrap();
+    res.assert_event(
+        &cosmwasm_std::Event::new("wasm-rujira-ghost-credit/liquidate.msg/flash_repay")
+            .add_attribute("amount", format!("150000000000{USDC}")),
+    );
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv < Decimal::one());
+    assert_eq!(
+        account.collaterals[1].collateral,
+        Collateral::Coin(coin(150000000, ETH))
+    );
+    assert!(app
+        .wrap()
+        .query_balance(ctx.ghost_credit.addr(), USDC)
+        .unwrap()
+        .amount
+        .is_zero());
+}
+
+#[test]
+fn repay_best() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let ctx = setup(&mut app, &owner);
+
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC), coin(200000000, ETH)],
+    )
+    .unwrap();
+
+    // The USDT debt is the largest, but the liquidator only returns USDC to the Account
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 309100000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 1000000000000, USDT)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 309100000000, USDC, &owner)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 1000000000000, USDT, &owner)
+        .unwrap();
+
+    app.init_modules(|router, _api, _storage| {
+        router.stargate.with_prices(vec![
+            ("BTC", Decimal::from_str("104450").unwrap()),
+            ("ETH", Decimal::from_str("3225").unwrap()),
+        ]);
+    });
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv > Decimal::one());
+    let usdt_debt = |account: &AccountResponse| {
+        account
+            .debts
+            .iter()
+            .find(|x| x.debt.can_accept(&coin(1, USDT)))
+            .cloned()
+            .unwrap()
+    };
+    let before = usdt_debt(&account);
+
+    // Nothing to repay with
+    let err = ctx
+        .ghost_credit
+        .liquidate(&mut app, &account, vec![LiquidateMsg::RepayBest {}])
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("NoRepayableDebt"));
+
+    let swap = LiquidateMsg::Execute {
+        contract_addr: ctx.fin_eth_usdc.addr().to_string(),
+        msg: to_json_binary(&fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+            to: None,
+            callback: None,
+            max_levels: None,
+        }))
+        .unwrap(),
+        funds: coins(50000000, ETH),
+    };
+    let res = ctx
+        .ghost_credit
+        .liquidate(&mut app, &account, vec![swap, LiquidateMsg::RepayBest {}])
+        .unwrap();
+    let repay = res
+        .events
+        .iter()
+        .find(|e| e.ty == "wasm-rujira-ghost-credit/liquidate.msg/repay")
+        .unwrap();
+    assert!(repay
+        .attributes
+        .iter()
+        .any(|a| a.key == "amount" && a.value.ends_with(USDC)));
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv < Decimal::one());
+    assert_eq!(usdt_debt(&account), before);
+}
+
+#[test]
+fn deleverage() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let keeper = app.api().addr_make("keeper");
+    let ctx = setup(&mut app, &owner);
+
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC), coin(200000000, ETH)],
+    )
+    .unwrap();
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 1309100000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 1309100000000, USDC, &owner)
+        .unwrap();
+
+    // Must sit between the adjustment and liquidation thresholds
+    ctx.ghost_credit
+        .set_deleverage_threshold(&mut app, Some("1"))
+        .unwrap_err();
+    let err = ctx
+        .ghost_credit
+        .deleverage(&mut app, &keeper, &account)
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("DeleverageDisabled"));
+    ctx.ghost_credit
+        .set_deleverage_threshold(&mut app, Some("0.97"))
+        .unwrap();
+    assert_eq!(
+        ctx.ghost_credit.query_config(&app).deleverage_threshold,
+        Some(Decimal::from_str("0.97").unwrap())
+    );
+    let err = ctx
+        .ghost_credit
+        .deleverage(&mut app, &keeper, &account)
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("DeleverageDisabled"));
+
+    let swap = LiquidateMsg::Execute {
+        contract_addr: ctx.fin_eth_usdc.addr().to_string(),
+        msg: to_json_binary(&fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+            to: None,
+            callback: None,
+            max_levels: None,
+        }))
+        .unwrap(),
+        funds: coins(30000000, ETH),
+    };
+    ctx.ghost_credit
+        .account(
+            &mut app,
+            &account,
+            vec![
+                AccountMsg::SetPreferenceMsgs(vec![swap, LiquidateMsg::RepayBest {}]),
+                AccountMsg::SetDeleverage(true),
+            ],
+        )
+        .unwrap();
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.deleverage);
+    let err = ctx
+        .ghost_credit
+        .deleverage(&mut app, &keeper, &account)
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("Safe"));
+
+    // Past the deleverage threshold, short of liquidation
+    app.init_modules(|router, _api, _storage| {
+        router.stargate.with_prices(vec![
+            ("BTC", Decimal::from_str("108000").unwrap()),
+            ("ETH", Decimal::from_str("3400").unwrap()),
+        ]);
+    });
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv > Decimal::from_str("0.97").unwrap());
+    assert!(account.ltv < Decimal::one());
+    ctx.ghost_credit
+        .liquidate(&mut app, &account, vec![])
+        .unwrap_err();
+
+    let fees = app.wrap().query_balance(&owner, USDC).unwrap();
+    let res = ctx
+        .ghost_credit
+        .deleverage(&mut app, &keeper, &account)
+        .unwrap();
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-credit/account.deleverage")
+            .add_attribute("caller", keeper.to_string()),
+    );
+    // Repaid without liquidation fees
+    assert!(!res
+        .events
+        .iter()
+        .any(|e| e.ty == "wasm-rujira-ghost-credit/liquidate.msg/repay"));
+    assert_eq!(app.wrap().query_balance(&owner, USDC).unwrap(), fees);
+    assert_eq!(
+        app.wrap().query_balance(&keeper, USDC).unwrap().amount,
+        Uint128::zero()
+    );
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv < Decimal::from_str("0.97").unwrap());
+    assert_eq!(
+        account.collaterals[1].collateral,
+        Collateral::Coin(coin(170000000, ETH))
+    );
+}
+
+#[test]
+fn poke() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let keeper = app.api().addr_make("keeper");
+    let ctx = setup(&mut app, &owner);
+
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC), coin(200000000, ETH)],
+    )
+    .unwrap();
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 1309100000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 1309100000000, USDC, &owner)
+        .unwrap();
+    // Recorded safe by the owner's update
+    let valuation = ctx
+        .ghost_credit
+        .query_valuation(&app, &ctx.account.account)
+        .unwrap();
+    assert!(!valuation.liquidatable);
+
+    ctx.ghost_credit
+        .set_poke_reward(
+            &mut app,
+            Some(PokeReward {
+                share: Decimal::percent(50),
+                rate: Decimal::percent(101),
+            }),
+        )
+        .unwrap_err();
+    ctx.ghost_credit
+        .set_poke_reward(
+            &mut app,
+            Some(PokeReward {
+                share: Decimal::percent(50),
+                rate: Decimal::percent(10),
+            }),
+        )
+        .unwrap();
+    // Fees retained from earlier liquidations
+    app.send_tokens(
+        owner.clone(),
+        ctx.ghost_credit.addr().clone(),
+        &coins(1000000, USDC),
+    )
+    .unwrap();
+
+    let res = ctx.ghost_credit.poke(&mut app, &keeper, &account).unwrap();
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-credit/account.poke").add_attribute("liquidatable", "false"),
+    );
+    assert_eq!(
+        app.wrap().query_balance(&keeper, USDC).unwrap().amount,
+        Uint128::zero()
+    );
+
+    app.init_modules(|router, _api, _storage| {
+        router.stargate.with_prices(vec![
+            ("BTC", Decimal::from_str("104450").unwrap()),
+            ("ETH", Decimal::from_str("3225").unwrap()),
+        ]);
+    });
+    app.update_block(|b| b.time = b.time.plus_seconds(60));
+    let res = ctx.ghost_credit.poke(&mut app, &keeper, &account).unwrap();
+    res.assert_event(
+        &Event::new("wasm-rujira-ghost-credit/account.poke")
+            .add_attributes(vec![("caller", keeper.as_str()), ("liquidatable", "true")]),
+    );
+    assert_eq!(
+        app.wrap().query_balance(&keeper, USDC).unwrap().amount,
+        Uint128::from(100000u128)
+    );
+    let valuation = ctx
+        .ghost_credit
+        .query_valuation(&app, &ctx.account.account)
+        .unwrap();
+    assert!(valuation.liquidatable);
+    assert!(valuation.ltv > Decimal::one());
+    assert_eq!(valuation.timestamp, app.block_info().time);
+
+    // Only the crossing is rewarded
+    ctx.ghost_credit.poke(&mut app, &keeper, &account).unwrap();
+    assert_eq!(
+        app.wrap().query_balance(&keeper, USDC).unwrap().amount,
+        Uint128::from(100000u128)
+    );
+}
+
+#[test]
+fn backstop_fee() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let backstop = app.api().addr_make("backstop");
+    let ctx = setup(&mut app, &owner);
+    ctx.ghost_credit
+        .set_backstop(&mut app, Some(&backstop), Decimal::percent(40));
+    let config = ctx.ghost_credit.query_config(&app);
+    assert_eq!(config.backstop, Some(backstop.clone()));
+    assert_eq!(config.fee_backstop, Decimal::percent(40));
+
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC), coin(200000000, ETH)],
+    )
+    .unwrap();
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 309100000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 1000000000000, USDT)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 309100000000, USDC, &owner)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 1000000000000, USDT, &owner)
+        .unwrap();
+    app.init_modules(|router, _api, _storage| {
+        router.stargate.with_prices(vec![
+            ("BTC", Decimal::from_str("104450").unwrap()),
+            ("ETH", Decimal::from_str("3225").unwrap()),
+        ]);
+    });
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv > Decimal::one());
+    let swap = LiquidateMsg::Execute {
+        contract_addr: ctx.fin_eth_usdc.addr().to_string(),
+        msg: to_json_binary(&fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+            to: None,
+            callback: None,
+            max_levels: None,
+        }))
+        .unwrap(),
+        funds: coins(50000000, ETH),
+    };
+    let res = ctx
+        .ghost_credit
+        .liquidate(&mut app, &account, vec![swap, LiquidateMsg::RepayBest {}])
+        .unwrap();
+    let repay = res
+        .events
+        .iter()
+        .find(|e| e.ty == "wasm-rujira-ghost-credit/liquidate.msg/repay")
+        .unwrap();
+    let attr = |key: &str| {
+        Uint128::from_str(
+            &repay
+                .attributes
+                .iter()
+                .find(|a| a.key == key)
+                .unwrap()
+                .value,
+        )
+        .unwrap()
+    };
+
+    // The backstop receives its share of the liquidation fee, the rest goes to the fee address
+    let fee_backstop = attr("fee_backstop");
+    assert!(!fee_backstop.is_zero());
+    assert_eq!(
+        fee_backstop,
+        attr("fee_liquidation").mul_floor(Decimal::percent(40))
+    );
+    assert_eq!(
+        app.wrap().query_balance(&backstop, USDC).unwrap().amount,
+        fee_backstop
+    );
+
+    // Above 100% is rejected
+    app.wasm_sudo(
+        ctx.ghost_credit.addr().clone(),
+        &rujira_rs::ghost::credit::SudoMsg::SetBackstop {
+            contract: Some(backstop.to_string()),
+            share: Decimal::percent(101),
+        },
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn liquidation_cooldown() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let ctx = setup(&mut app, &owner);
+
+    ctx.ghost_credit.update_config(
+        &mut app,
+        ConfigUpdate {
+            code_id: None,
+            fee_liquidation: None,
+            fee_liquidator: None,
+            fee_address: None,
+            liquidation_max_slip: None,
+            liquidation_threshold: None,
+            adjustment_threshold: None,
+            liquidation_cooldown: Some(3600),
+            min_debt_value: None,
+            safety_buffer: None,
+        },
+    );
+
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC), coin(200000000, ETH)],
+    )
+    .unwrap();
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 1309100000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 1309100000000, USDC, &owner)
+        .unwrap();
+
+    app.init_modules(|router, _api, _storage| {
+        router.stargate.with_prices(vec![
+            ("BTC", Decimal::from_str("104450").unwrap()),
+            ("ETH", Decimal::from_str("3225").unwrap()),
+        ]);
+    });
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv > Decimal::one());
+
+    ctx.ghost_credit
+        .liquidate_execute_repay(
+            &mut app,
+            &account,
+            ctx.fin_eth_usdc.addr(),
+            fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            coins(30000000, ETH),
+            USDC,
+        )
+        .unwrap();
+
+    // Price moves back against the account, but not beyond the previous liquidation LTV
+    app.init_modules(|router, _api, _storage| {
+        router.stargate.with_prices(vec![
+            ("BTC", Decimal::from_str("104450").unwrap()),
+            ("ETH", Decimal::from_str("3000").unwrap()),
+        ]);
+    });
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv > Decimal::one());
+
+    let err = ctx
+        .ghost_credit
+        .liquidate_execute_repay(
+            &mut app,
+            &account,
+            ctx.fin_eth_usdc.addr(),
+            fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            coins(30000000, ETH),
+            USDC,
+        )
+        .unwrap_err();
+    assert!(format!("{:?}", err.root_cause()).contains("LiquidationCooldown"));
+
+    // Once the cooldown has elapsed the account can be liquidated as normal
+    app.update_block(|b| b.time = b.time.plus_seconds(3601));
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .liquidate_execute_repay(
+            &mut app,
+            &account,
+            ctx.fin_eth_usdc.addr(),
+            fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+                to: None,
+                callback: None,
+                max_levels: None,
+            }),
+            coins(30000000, ETH),
+            USDC,
+        )
+        .unwrap();
+}
+
+#[test]
+fn liquidation_queue() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let ctx = setup(&mut app, &owner);
+    let orca = Orca::create(
+        &mut app,
+        &owner,
+        &owner,
+        &[ctx.ghost_credit.addr().clone()],
+        orca::Denoms::new(ETH, USDC),
+    );
+    orca.bid(
+        &mut app,
+        &owner,
+        vec![(1, Uint128::from(1000000000000u128))],
+        &coins(1000000000000, USDC),
+    )
+    .unwrap();
+
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC), coin(200000000, ETH)],
+    )
+    .unwrap();
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 1309100000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 1309100000000, USDC, &owner)
+        .unwrap();
+
+    app.init_modules(|router, _api, _storage| {
+        router.stargate.with_prices(vec![
+            ("BTC", Decimal::from_str("104450").unwrap()),
+            ("ETH", Decimal::from_str("3225").unwrap()),
+        ]);
+    });
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv > Decimal::one());
+
+    // A preference selling into a queue that this contract isn't a market of fails the liquidation,
+    // rather than leaving the collateral with this contract
+    let closed = Orca::create(
+        &mut app,
+        &owner,
+        &owner,
+        &[owner.clone()],
+        orca::Denoms::new(ETH, USDC),
+    );
+    ctx.ghost_credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::SetPreferenceMsgs(vec![LiquidateMsg::Queue {
+                contract_addr: closed.addr().to_string(),
+                amount: coin(50000000, ETH),
+            }])],
+        )
+        .unwrap();
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .liquidate(
+            &mut app,
+            &account,
+            vec![
+                LiquidateMsg::Queue {
+                    contract_addr: orca.addr().to_string(),
+                    amount: coin(50000000, ETH),
+                },
+                LiquidateMsg::Repay(USDC.to_string()),
+            ],
+        )
+        .unwrap_err();
+    ctx.ghost_credit
+        .account(
+            &mut app,
+            &account,
+            vec![AccountMsg::SetPreferenceMsgs(vec![])],
+        )
+        .unwrap();
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+
+    // 0.5 ETH is sold into the queue at a 1% discount, and the USDC returned is repaid
+    let res = ctx
+        .ghost_credit
+        .liquidate(
+            &mut app,
+            &account,
+            vec![
+                LiquidateMsg::Queue {
+                    contract_addr: orca.addr().to_string(),
+                    amount: coin(50000000, ETH),
+                },
+                LiquidateMsg::Repay(USDC.to_string()),
+            ],
+        )
+        .unwrap();
+    res.assert_event(
+        &cosmwasm_std::Event::new("wasm-rujira-ghost-credit/liquidate.msg/queue")
+            .add_attribute("amount", format!("50000000{ETH}")),
+    );
+    res.assert_event(
+        &cosmwasm_std::Event::new("wasm-rujira-orca/liquidate")
+            .add_attribute("market", ctx.ghost_credit.addr().as_str()),
+    );
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert!(account.ltv < Decimal::one());
+    assert_eq!(
+        account.collaterals[1].collateral,
+        Collateral::Coin(coin(150000000, ETH))
+    );
+    assert_eq!(
+        orca.query_bid(&app, &owner, 1).unwrap().filled,
+        Uint128::from(50000000u128)
+    );
+}
+
+#[test]
+fn simulate_liquidation() {
+    let mut app = mock_rujira_app();
+    let owner = app.api().addr_make("owner");
+    let ctx = setup(&mut app, &owner);
+
+    app.send_tokens(
+        owner.clone(),
+        ctx.account.account.clone(),
+        &[coin(10000000, BTC), coin(200000000, ETH)],
+    )
+    .unwrap();
+
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .account_borrow(&mut app, &account, 1309100000000, USDC)
+        .unwrap();
+    ctx.ghost_credit
+        .account_send(&mut app, &account, 1309100000000, USDC, &owner)
+        .unwrap();
+    ctx.ghost_credit
+        .account_preference_order(&mut app, &account, BTC, Some(ETH))
+        .unwrap();
+
+    let swap_repay = |contract: &Addr, offer: Coin| {
+        vec![
+            LiquidateMsg::Execute {
+                contract_addr: contract.to_string(),
+                msg: to_json_binary(&fin::ExecuteMsg::Swap(fin::SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                }))
+                .unwrap(),
+                funds: vec![offer],
+            },
+            LiquidateMsg::Repay(USDC.to_string()),
+        ]
+    };
+
+    let err = ctx
+        .ghost_credit
+        .query_simulate_liquidation(
+            &app,
+            &ctx.account.account,
+            swap_repay(ctx.fin_eth_usdc.addr(), coin(30000000, ETH)),
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("Account Safe"));
+
+    app.init_modules(|router, _api, _storage| {
+        router.stargate.with_prices(vec![
+            ("BTC", Decimal::from_str("104450").unwrap()),
+            ("ETH", Decimal::from_str("3225").unwrap()),
+        ]);
+    });
+
+    // Selling BTC breaks the preference order
+    let res = ctx
+        .ghost_credit
+        .query_simulate_liquidation(
+            &app,
+            &ctx.account.account,
+            swap_repay(ctx.fin_btc_usdc.addr(), coin(1000000, BTC)),
+        )
+        .unwrap();
+    assert_eq!(res.steps.len(), 2);
+    assert!(res.steps.iter().all(|x| x.simulated && x.error.is_none()));
+    assert!(res
+        .error
+        .unwrap()
+        .contains("Invalid liquidation attempted 1000000btc-btc"));
+
+    // Nothing to repay without the swap
+    let res = ctx
+        .ghost_credit
+        .query_simulate_liquidation(&app, &ctx.account.account, vec![LiquidateMsg::RepayBest {}])
+        .unwrap();
+    assert_eq!(res.steps.len(), 1);
+    assert_eq!(res.steps[0].error, Some("NoRepayableDebt".to_string()));
+    assert_eq!(res.error, Some("NoRepayableDebt".to_string()));
+
+    let res = ctx
+        .ghost_credit
+        .query_simulate_liquidation(
+            &app,
+            &ctx.account.account,
+            swap_repay(ctx.fin_eth_usdc.addr(), coin(30000000, ETH)),
+        )
+        .unwrap();
+    assert_eq!(res.error, None);
+    let repay = res.steps[1].repay.clone().unwrap();
+    assert_eq!(repay.repaid, coin(101619627060, USDC));
+    let held = repay.repaid.amount + repay.fee_liquidation.amount + repay.fee_liquidator.amount;
+    assert_eq!(
+        repay.fee_liquidation.amount,
+        held.multiply_ratio(1u128, 100u128)
+    );
+    assert_eq!(
+        repay.fee_liquidator.amount,
+        held.multiply_ratio(5u128, 1000u128)
+    );
+
+    // The simulation matches the liquidation once executed
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    ctx.ghost_credit
+        .liquidate(
+            &mut app,
+            &account,
+            swap_repay(ctx.fin_eth_usdc.addr(), coin(30000000, ETH)),
+        )
+        .unwrap();
+    let account = ctx.ghost_credit.query_account(&app, &ctx.account.account);
+    assert_eq!(account.ltv, res.ltv);
+}
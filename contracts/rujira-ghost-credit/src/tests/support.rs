@@ -1,4 +1,71 @@
+use cosmwasm_std::{coin, Addr, Binary, Coin, Decimal, Uint128};
+use cw_multi_test::Executor;
+use rujira_ghost_vault::mock::GhostVault;
+use rujira_rs::ghost::credit::AccountResponse;
+use rujira_rs_testing::{mock_rujira_app, RujiraApp};
+
+use crate::mock::GhostCredit;
+
 pub static BTC: &str = "btc-btc";
 pub static ETH: &str = "eth-eth";
 pub static USDC: &str = "eth-usdc-0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
 pub static USDT: &str = "eth-usdt-0xdac17f958d2ee523a2206206994597c13d831ec7";
+
+pub struct Fixture {
+    pub app: RujiraApp,
+    pub owner: Addr,
+    pub credit: GhostCredit,
+}
+
+/// USDC and BTC priced at 1, an owner holding 10000 of each, and a Credit contract that accepts BTC
+/// as collateral at 0.5
+pub fn fixture() -> Fixture {
+    let mut app = mock_rujira_app();
+    app.init_modules(|router, _, _| {
+        router
+            .stargate
+            .with_prices(vec![("USDC", Decimal::one()), ("BTC", Decimal::one())]);
+    });
+
+    let owner = app.api().addr_make("owner");
+    let fees = app.api().addr_make("fee");
+    app.init_modules(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &owner, vec![coin(10000, BTC), coin(10000, USDC)])
+    })
+    .unwrap();
+    let credit = GhostCredit::create(&mut app, &owner, &fees);
+    credit.set_collateral(&mut app, BTC, "0.5");
+    Fixture { app, owner, credit }
+}
+
+/// Creates a vault of `denom` that `credit` borrows from, with 2000 deposited by `owner`
+pub fn lending_vault(
+    app: &mut RujiraApp,
+    owner: &Addr,
+    credit: &GhostCredit,
+    denom: &str,
+) -> GhostVault {
+    let vault = GhostVault::create(app, owner, denom);
+    vault
+        .set_borrower(app, credit.addr().as_str(), Uint128::MAX)
+        .unwrap();
+    credit.set_vault(app, &vault);
+    vault.deposit(app, owner, 2000, denom).unwrap();
+    vault
+}
+
+/// Creates an Account of `owner`, and sends it `funds`
+pub fn funded_account(
+    app: &mut RujiraApp,
+    owner: &Addr,
+    credit: &GhostCredit,
+    funds: &[Coin],
+) -> AccountResponse {
+    credit.create_account(app, owner, "", "", Binary::new(vec![0]));
+    let account = credit.query_accounts(app, owner, None).accounts[0].clone();
+    app.send_tokens(owner.clone(), account.account.clone(), funds)
+        .unwrap();
+    account
+}
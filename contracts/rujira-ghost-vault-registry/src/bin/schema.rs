@@ -0,0 +1,12 @@
+use cosmwasm_schema::write_api;
+
+use rujira_rs::ghost::vault_registry;
+
+fn main() {
+    write_api! {
+        instantiate: vault_registry::InstantiateMsg,
+        execute: vault_registry::ExecuteMsg,
+        query: vault_registry::QueryMsg,
+        sudo: vault_registry::SudoMsg,
+    }
+}
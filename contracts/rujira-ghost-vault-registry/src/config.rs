@@ -0,0 +1,38 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{StdResult, Storage};
+use cw_storage_plus::Item;
+use rujira_rs::ghost::vault_registry::{ConfigResponse, InstantiateMsg};
+
+static CONFIG: Item<Config> = Item::new("config");
+
+#[cw_serde]
+pub struct Config {
+    /// Code ids of the ghost-vault contracts permitted to register their pools
+    pub code_ids: Vec<u64>,
+}
+
+impl From<InstantiateMsg> for Config {
+    fn from(value: InstantiateMsg) -> Self {
+        Self {
+            code_ids: value.code_ids,
+        }
+    }
+}
+
+impl From<Config> for ConfigResponse {
+    fn from(value: Config) -> Self {
+        Self {
+            code_ids: value.code_ids,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(storage: &dyn Storage) -> StdResult<Self> {
+        CONFIG.load(storage)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        CONFIG.save(storage, self)
+    }
+}
@@ -0,0 +1,351 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    ensure, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+use cw2::set_contract_version;
+use rujira_rs::ghost::vault_registry::{
+    ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, SudoMsg, VaultResponse, VaultsResponse,
+};
+use rujira_rs::PageResponse;
+
+use crate::config::Config;
+use crate::error::ContractError;
+use crate::events::{event_approve, event_deregister, event_register};
+use crate::vault::Vault;
+
+const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const VAULTS_LIMIT: u8 = 100;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Config::from(msg).save(deps.storage)?;
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let config = Config::load(deps.storage)?;
+    match msg {
+        ExecuteMsg::Register { denom, receipt } => {
+            // Only ghost-vault contracts can register, so that a denom can't be claimed by another contract
+            let code_id = deps
+                .querier
+                .query_wasm_contract_info(&info.sender)
+                .ok()
+                .map(|x| x.code_id);
+            ensure!(
+                code_id.is_some_and(|x| config.code_ids.contains(&x)),
+                ContractError::Unauthorized {}
+            );
+            let vault = Vault {
+                denom,
+                address: info.sender,
+                receipt,
+            };
+            // Re-registering the approved vault is a no-op, so that the vault's own admin messages
+            // don't fail once its denom is registered
+            if !vault.register(deps.storage)? {
+                return Ok(Response::default());
+            }
+            Ok(Response::default().add_event(event_register(&vault)))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    let mut config = Config::load(deps.storage)?;
+    match msg {
+        SudoMsg::SetCodeIds(code_ids) => {
+            config.code_ids = code_ids;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::Approve { denom, address } => {
+            let address = deps.api.addr_validate(&address)?;
+            let vault = Vault::approve(deps.storage, &denom, &address)?;
+            Ok(Response::default().add_event(event_approve(&vault)))
+        }
+        SudoMsg::Deregister { denom } => {
+            let vault = Vault::remove(deps.storage, &denom)?;
+            Ok(Response::default().add_event(event_deregister(&vault)))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    match msg {
+        QueryMsg::Config {} => Ok(to_json_binary(&ConfigResponse::from(Config::load(
+            deps.storage,
+        )?))?),
+        QueryMsg::Vault { denom } => Ok(to_json_binary(&VaultResponse::from(Vault::load(
+            deps.storage,
+            &denom,
+        )?))?),
+        QueryMsg::Vaults { page } => {
+            let limit = page.limit(VAULTS_LIMIT, VAULTS_LIMIT);
+            let vaults = Vault::list(deps.storage, limit, page.start_after.as_deref())
+                .map(|x| x.map(VaultResponse::from))
+                .collect::<StdResult<Vec<_>>>()?;
+            Ok(to_json_binary(&VaultsResponse {
+                page: PageResponse::new(&vaults, limit, |x| x.denom.clone()),
+                vaults,
+            })?)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{Addr, Decimal, Event};
+    use cw_multi_test::{ContractWrapper, Executor};
+    use rujira_ghost_vault::contract as vault;
+    use rujira_rs::{
+        ghost::vault::{self as ghost_vault, Interest},
+        PageRequest, TokenMetadata,
+    };
+    use rujira_rs_testing::{mock_rujira_app, RujiraApp};
+
+    fn vault_msg(owner: &Addr, denom: &str, registry: &Addr) -> ghost_vault::InstantiateMsg {
+        ghost_vault::InstantiateMsg {
+            denom: denom.to_string(),
+            interest: Interest::default(),
+            receipt: TokenMetadata {
+                description: denom.to_string(),
+                display: denom.to_string(),
+                name: denom.to_string(),
+                symbol: denom.to_string(),
+                uri: None,
+                uri_hash: None,
+            },
+            fee: Decimal::zero(),
+            fee_address: owner.to_string(),
+            registry: Some(registry.to_string()),
+        }
+    }
+
+    fn approve(app: &mut RujiraApp, registry: &Addr, denom: &str, address: &Addr) {
+        app.wasm_sudo(
+            registry.clone(),
+            &SudoMsg::Approve {
+                denom: denom.to_string(),
+                address: address.to_string(),
+            },
+        )
+        .unwrap();
+    }
+
+    fn query_vault(app: &RujiraApp, registry: &Addr, denom: &str) -> StdResult<VaultResponse> {
+        app.wrap().query_wasm_smart(
+            registry,
+            &QueryMsg::Vault {
+                denom: denom.to_string(),
+            },
+        )
+    }
+
+    #[test]
+    fn registration() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+
+        let vault_code_id = app.store_code(Box::new(
+            ContractWrapper::new(vault::execute, vault::instantiate, vault::query)
+                .with_sudo(vault::sudo),
+        ));
+        let code_id = app.store_code(Box::new(
+            ContractWrapper::new(execute, instantiate, query).with_sudo(sudo),
+        ));
+        let registry = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    code_ids: vec![vault_code_id],
+                },
+                &[],
+                "registry",
+                None,
+            )
+            .unwrap();
+
+        // Only ghost-vaults can register
+        app.execute_contract(
+            owner.clone(),
+            registry.clone(),
+            &ExecuteMsg::Register {
+                denom: "btc".to_string(),
+                receipt: "x/ghost-vault/btc".to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+
+        let btc = app
+            .instantiate_contract(
+                vault_code_id,
+                owner.clone(),
+                &vault_msg(&owner, "btc", &registry),
+                &[],
+                "ghost-vault-btc",
+                None,
+            )
+            .unwrap();
+
+        // Registrations only take effect once approved
+        query_vault(&app, &registry, "btc").unwrap_err();
+        approve(&mut app, &registry, "btc", &btc);
+        assert_eq!(
+            query_vault(&app, &registry, "btc").unwrap(),
+            VaultResponse {
+                denom: "btc".to_string(),
+                address: btc.clone(),
+                receipt: "x/ghost-vault/btc".to_string(),
+            }
+        );
+        query_vault(&app, &registry, "eth").unwrap_err();
+
+        // Pools added to the vault are registered as they're created
+        let res = app
+            .wasm_sudo(
+                btc.clone(),
+                &ghost_vault::SudoMsg::AddPool(vault_msg(&owner, "eth", &registry)),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault-registry/register").add_attributes(vec![
+                ("denom", "eth"),
+                ("address", btc.as_str()),
+                ("receipt", "x/ghost-vault/eth"),
+            ]),
+        );
+        approve(&mut app, &registry, "eth", &btc);
+
+        let page: VaultsResponse = app
+            .wrap()
+            .query_wasm_smart(
+                registry.clone(),
+                &QueryMsg::Vaults {
+                    page: PageRequest::new(None, Some(1)),
+                },
+            )
+            .unwrap();
+        assert_eq!(page.vaults.len(), 1);
+        assert_eq!(page.vaults[0].denom, "btc");
+        let page: VaultsResponse = app
+            .wrap()
+            .query_wasm_smart(
+                registry.clone(),
+                &QueryMsg::Vaults {
+                    page: PageRequest::new(page.page.next, Some(1)),
+                },
+            )
+            .unwrap();
+        assert_eq!(page.vaults[0].denom, "eth");
+
+        // Another vault registering a denom doesn't replace its vault until approved
+        let replacement = app
+            .instantiate_contract(
+                vault_code_id,
+                owner.clone(),
+                &vault_msg(&owner, "btc", &registry),
+                &[],
+                "ghost-vault-btc",
+                None,
+            )
+            .unwrap();
+        assert_eq!(query_vault(&app, &registry, "btc").unwrap().address, btc);
+        approve(&mut app, &registry, "btc", &replacement);
+        assert_eq!(
+            query_vault(&app, &registry, "btc").unwrap().address,
+            replacement
+        );
+        app.wasm_sudo(
+            registry.clone(),
+            &SudoMsg::Approve {
+                denom: "btc".to_string(),
+                address: btc.to_string(),
+            },
+        )
+        .unwrap_err();
+
+        app.wasm_sudo(
+            registry.clone(),
+            &SudoMsg::Deregister {
+                denom: "btc".to_string(),
+            },
+        )
+        .unwrap();
+        query_vault(&app, &registry, "btc").unwrap_err();
+    }
+
+    #[test]
+    fn set_registry() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+
+        let vault = rujira_ghost_vault::mock::GhostVault::create(&mut app, &owner, "btc");
+        vault.add_pool(&mut app, &owner, "eth").unwrap();
+
+        let code_id = app.store_code(Box::new(
+            ContractWrapper::new(execute, instantiate, query).with_sudo(sudo),
+        ));
+        let registry = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg { code_ids: vec![] },
+                &[],
+                "registry",
+                None,
+            )
+            .unwrap();
+
+        // The vault's code id must be permitted
+        let msg = ghost_vault::SudoMsg::SetRegistry(registry.to_string());
+        app.wasm_sudo(vault.addr().clone(), &msg).unwrap_err();
+
+        let vault_code_id = app
+            .wrap()
+            .query_wasm_contract_info(vault.addr())
+            .unwrap()
+            .code_id;
+        app.wasm_sudo(registry.clone(), &SudoMsg::SetCodeIds(vec![vault_code_id]))
+            .unwrap();
+
+        // Existing pools are registered, and can be registered again once approved
+        app.wasm_sudo(vault.addr().clone(), &msg).unwrap();
+        approve(&mut app, &registry, "btc", vault.addr());
+        approve(&mut app, &registry, "eth", vault.addr());
+        app.wasm_sudo(vault.addr().clone(), &msg).unwrap();
+        assert_eq!(
+            query_vault(&app, &registry, "btc").unwrap().address,
+            *vault.addr()
+        );
+        assert_eq!(
+            query_vault(&app, &registry, "eth").unwrap().receipt,
+            "x/ghost-vault/eth"
+        );
+    }
+}
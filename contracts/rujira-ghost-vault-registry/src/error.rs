@@ -0,0 +1,17 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("No registration of {address} pending for {denom}")]
+    NotPending { denom: String, address: String },
+
+    #[error("No vault registered for {denom}")]
+    NotFound { denom: String },
+}
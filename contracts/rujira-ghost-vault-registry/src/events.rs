@@ -0,0 +1,22 @@
+use cosmwasm_std::Event;
+
+use crate::vault::Vault;
+
+pub fn event_register(vault: &Vault) -> Event {
+    Event::new(format!("{}/register", env!("CARGO_PKG_NAME")))
+        .add_attribute("denom", vault.denom.clone())
+        .add_attribute("address", vault.address.to_string())
+        .add_attribute("receipt", vault.receipt.clone())
+}
+
+pub fn event_approve(vault: &Vault) -> Event {
+    Event::new(format!("{}/approve", env!("CARGO_PKG_NAME")))
+        .add_attribute("denom", vault.denom.clone())
+        .add_attribute("address", vault.address.to_string())
+}
+
+pub fn event_deregister(vault: &Vault) -> Event {
+    Event::new(format!("{}/deregister", env!("CARGO_PKG_NAME")))
+        .add_attribute("denom", vault.denom.clone())
+        .add_attribute("address", vault.address.to_string())
+}
@@ -0,0 +1,7 @@
+pub mod config;
+pub mod contract;
+mod error;
+mod events;
+pub mod vault;
+
+pub use crate::error::ContractError;
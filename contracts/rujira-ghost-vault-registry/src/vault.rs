@@ -0,0 +1,89 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Order, StdResult, Storage};
+use cw_storage_plus::{Bound, Map};
+use rujira_rs::ghost::vault_registry::VaultResponse;
+
+use crate::ContractError;
+
+static VAULTS: Map<&str, Vault> = Map::new("vaults");
+// Registrations awaiting approval, keyed by denom and vault address
+static CANDIDATES: Map<(&str, &Addr), Vault> = Map::new("candidates");
+
+/// A ghost-vault pool, keyed by the denom it lends
+#[cw_serde]
+pub struct Vault {
+    pub denom: String,
+    pub address: Addr,
+    pub receipt: String,
+}
+
+impl Vault {
+    pub fn load(storage: &dyn Storage, denom: &str) -> Result<Self, ContractError> {
+        VAULTS
+            .may_load(storage, denom)?
+            .ok_or_else(|| ContractError::NotFound {
+                denom: denom.to_string(),
+            })
+    }
+
+    /// Submits the vault for approval, returning false if it's already the vault of its denom
+    pub fn register(&self, storage: &mut dyn Storage) -> Result<bool, ContractError> {
+        if VAULTS
+            .may_load(storage, &self.denom)?
+            .is_some_and(|x| x.address == self.address)
+        {
+            return Ok(false);
+        }
+        CANDIDATES.save(storage, (&self.denom, &self.address), self)?;
+        Ok(true)
+    }
+
+    /// Approves the registration of `address` as the vault of `denom`, replacing any existing vault
+    pub fn approve(
+        storage: &mut dyn Storage,
+        denom: &str,
+        address: &Addr,
+    ) -> Result<Self, ContractError> {
+        let vault = CANDIDATES
+            .may_load(storage, (denom, address))?
+            .ok_or_else(|| ContractError::NotPending {
+                denom: denom.to_string(),
+                address: address.to_string(),
+            })?;
+        CANDIDATES.remove(storage, (denom, address));
+        VAULTS.save(storage, denom, &vault)?;
+        Ok(vault)
+    }
+
+    pub fn remove(storage: &mut dyn Storage, denom: &str) -> Result<Self, ContractError> {
+        let vault = Self::load(storage, denom)?;
+        VAULTS.remove(storage, denom);
+        Ok(vault)
+    }
+
+    pub fn list<'a>(
+        storage: &'a dyn Storage,
+        limit: usize,
+        start_after: Option<&'a str>,
+    ) -> impl Iterator<Item = StdResult<Self>> + 'a {
+        VAULTS
+            .range(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .map(|x| x.map(|(_, v)| v))
+    }
+}
+
+impl From<Vault> for VaultResponse {
+    fn from(value: Vault) -> Self {
+        Self {
+            denom: value.denom,
+            address: value.address,
+            receipt: value.receipt,
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::Map;
+use rujira_rs::{ghost::vault::BackstopResponse, SharePool};
+
+use crate::ContractError;
+
+// Backstop positions, keyed by (denom, owner)
+static POSITIONS: Map<(&str, Addr), Backstop> = Map::new("backstop");
+
+/// A provider's shares of the backstop pool of a denom
+#[cw_serde]
+pub struct Backstop {
+    pub denom: String,
+    pub owner: Addr,
+    pub shares: Uint128,
+    pub unbonding: Uint128,
+    pub release: Timestamp,
+    /// The backstop epoch the shares were issued in. Shares of a previous epoch were exhausted by a write-off
+    pub epoch: u64,
+}
+
+impl Backstop {
+    /// Loads the position of `owner`, empty if it has none in the current `epoch`
+    pub fn load(storage: &dyn Storage, denom: &str, owner: Addr, epoch: u64) -> StdResult<Self> {
+        Ok(POSITIONS
+            .may_load(storage, (denom, owner.clone()))?
+            .filter(|x| x.epoch == epoch)
+            .unwrap_or(Self {
+                denom: denom.to_string(),
+                owner,
+                shares: Uint128::zero(),
+                unbonding: Uint128::zero(),
+                release: Timestamp::default(),
+                epoch,
+            }))
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        let key = (self.denom.as_str(), self.owner.clone());
+        if self.shares.is_zero() && self.unbonding.is_zero() {
+            POSITIONS.remove(storage, key);
+            return Ok(());
+        }
+        POSITIONS.save(storage, key, self)
+    }
+
+    /// Moves `shares` to unbonding. Shares already unbonding are added to, and released at the new `release`
+    pub fn unbond(&mut self, shares: Uint128, release: Timestamp) -> Result<(), ContractError> {
+        self.shares = self.shares.checked_sub(shares)?;
+        self.unbonding += shares;
+        self.release = release;
+        Ok(())
+    }
+
+    /// Takes the unbonding shares released by `now`
+    pub fn take_released(&mut self, now: Timestamp) -> Result<Uint128, ContractError> {
+        if self.unbonding.is_zero() || now < self.release {
+            return Err(ContractError::BackstopUnbonding {
+                release: self.release,
+            });
+        }
+        Ok(std::mem::take(&mut self.unbonding))
+    }
+
+    pub fn to_response(&self, pool: &SharePool) -> BackstopResponse {
+        BackstopResponse {
+            addr: self.owner.to_string(),
+            denom: self.denom.clone(),
+            shares: self.shares,
+            unbonding: self.unbonding,
+            release: self.release,
+            value: pool.ownership(self.shares + self.unbonding),
+        }
+    }
+}
@@ -10,20 +10,27 @@ use std::{
     ops::{Add, Sub},
 };
 
-static BORROWERS: Map<Addr, Borrower> = Map::new("borrowers");
-// Delegated shares for a borrower
-static DELEGATE_SHARES: Map<(Addr, Addr), Uint128> = Map::new("delegates");
+// Borrowers are whitelisted per pool, keyed by (denom, borrower)
+static BORROWERS: Map<(&str, Addr), Borrower> = Map::new("pool-borrowers");
+// Delegated shares for a borrower, keyed by (denom, borrower, delegate)
+static DELEGATE_SHARES: Map<(&str, Addr, Addr), Uint128> = Map::new("pool-delegates");
+// Amounts a borrower allows a spender to draw, keyed by (denom, borrower, spender)
+static ALLOWANCES: Map<(&str, Addr, Addr), Uint128> = Map::new("pool-allowances");
 
 #[cw_serde]
 pub struct Borrower {
+    pub denom: String,
     pub addr: Addr,
     pub limit: Uint128,
     pub shares: Uint128,
+    /// Called after each Borrow, reverting the Borrow if it fails
+    #[serde(default)]
+    pub health_check: Option<Addr>,
 }
 
 impl Borrower {
-    pub fn load(storage: &dyn Storage, addr: Addr) -> Result<Self, ContractError> {
-        match BORROWERS.load(storage, addr.clone()) {
+    pub fn load(storage: &dyn Storage, denom: &str, addr: Addr) -> Result<Self, ContractError> {
+        match BORROWERS.load(storage, (denom, addr)) {
             Ok(x) => Ok(x),
             Err(StdError::NotFound { .. }) => Err(ContractError::UnauthorizedBorrower {}),
             Err(err) => Err(ContractError::Std(err)),
@@ -31,12 +38,12 @@ impl Borrower {
     }
 
     pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
-        BORROWERS.save(storage, self.addr.clone(), self)
+        BORROWERS.save(storage, (self.denom.as_str(), self.addr.clone()), self)
     }
 
     pub fn delegate_shares(&self, storage: &dyn Storage, delegate: Addr) -> Uint128 {
         DELEGATE_SHARES
-            .load(storage, (self.addr.clone(), delegate))
+            .load(storage, (self.denom.as_str(), self.addr.clone(), delegate))
             .unwrap_or_default()
     }
 
@@ -49,7 +56,7 @@ impl Borrower {
     ) -> Result<(), ContractError> {
         DELEGATE_SHARES.update(
             storage,
-            (self.addr.clone(), delegate),
+            (self.denom.as_str(), self.addr.clone(), delegate),
             |v| -> Result<Uint128, ContractError> { Ok(v.unwrap_or_default().add(shares)) },
         )?;
         self.borrow(storage, pool, shares)
@@ -85,7 +92,7 @@ impl Borrower {
         delegate: Addr,
         shares: Uint128,
     ) -> Result<Uint128, ContractError> {
-        let k = (self.addr.clone(), delegate);
+        let k = (self.denom.as_str(), self.addr.clone(), delegate);
         let delegate = DELEGATE_SHARES.load(storage, k.clone())?;
         let repaid = min(shares, delegate);
         DELEGATE_SHARES.save(storage, k, &delegate.checked_sub(repaid)?)?;
@@ -93,24 +100,87 @@ impl Borrower {
         Ok(shares.sub(repaid))
     }
 
-    pub fn set(storage: &mut dyn Storage, addr: Addr, limit: Uint128) -> StdResult<()> {
-        let mut borrower = BORROWERS.load(storage, addr.clone()).unwrap_or(Borrower {
-            addr: addr.clone(),
-            limit: Default::default(),
-            shares: Default::default(),
-        });
+    pub fn allowance(&self, storage: &dyn Storage, spender: Addr) -> Uint128 {
+        ALLOWANCES
+            .load(storage, (self.denom.as_str(), self.addr.clone(), spender))
+            .unwrap_or_default()
+    }
+
+    pub fn set_allowance(
+        &self,
+        storage: &mut dyn Storage,
+        spender: Addr,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        let k = (self.denom.as_str(), self.addr.clone(), spender);
+        if amount.is_zero() {
+            ALLOWANCES.remove(storage, k);
+            return Ok(());
+        }
+        ALLOWANCES.save(storage, k, &amount)
+    }
+
+    /// Moves delegated shares between two delegates. The borrower's total shares are unchanged
+    pub fn delegate_transfer(
+        &self,
+        storage: &mut dyn Storage,
+        from: Addr,
+        to: Addr,
+        shares: Uint128,
+    ) -> Result<(), ContractError> {
+        let from_shares = self.delegate_shares(storage, from.clone());
+        DELEGATE_SHARES.save(
+            storage,
+            (self.denom.as_str(), self.addr.clone(), from),
+            &from_shares.checked_sub(shares)?,
+        )?;
+        DELEGATE_SHARES.update(
+            storage,
+            (self.denom.as_str(), self.addr.clone(), to),
+            |v| -> Result<Uint128, ContractError> {
+                Ok(v.unwrap_or_default().checked_add(shares)?)
+            },
+        )?;
+        Ok(())
+    }
+
+    pub fn set(
+        storage: &mut dyn Storage,
+        denom: &str,
+        addr: Addr,
+        limit: Uint128,
+    ) -> StdResult<()> {
+        let mut borrower = BORROWERS
+            .load(storage, (denom, addr.clone()))
+            .unwrap_or(Borrower {
+                denom: denom.to_string(),
+                addr,
+                limit: Default::default(),
+                shares: Default::default(),
+                health_check: None,
+            });
         borrower.limit = limit;
-        BORROWERS.save(storage, addr, &borrower)
+        borrower.save(storage)
+    }
+
+    /// The proportion of the borrow limit currently drawn, including accrued interest
+    pub fn utilization(&self, pool: &SharePool) -> Decimal {
+        let current = pool.ownership(self.shares);
+        if current.is_zero() {
+            return Decimal::zero();
+        }
+        Decimal::checked_from_ratio(current, self.limit).unwrap_or(Decimal::MAX)
     }
 
-    pub fn list(
-        storage: &dyn Storage,
-        limit: Option<u8>,
+    pub fn list<'a>(
+        storage: &'a dyn Storage,
+        denom: &'a str,
+        limit: usize,
         start_after: Option<Addr>,
-    ) -> impl Iterator<Item = StdResult<Self>> + '_ {
-        let limit = limit.unwrap_or(100) as usize;
+    ) -> impl Iterator<Item = StdResult<Self>> + 'a {
         let min = start_after.map(Bound::exclusive);
         BORROWERS
+            .prefix(denom)
             .range(storage, min, None, Order::Ascending)
             .take(limit)
             .map(|x| x.map(|(_, v)| v))
@@ -118,23 +188,58 @@ impl Borrower {
 }
 
 // ------------ Migration ------------
+#[cw_serde]
+struct LegacyBorrower {
+    addr: Addr,
+    limit: Uint128,
+    shares: Uint128,
+}
+
 #[cw_serde]
 struct TempSharePool {
     size: Uint128,
     shares: Decimal,
 }
 
-pub fn migrate(storage: &mut dyn Storage) -> StdResult<()> {
-    let total_borrower_shares: Uint128 = BORROWERS
+/// Moves the borrowers and delegates of a single-denom vault to the pool keyed by `denom`, and
+/// reconciles the shares of its debt_pool with the total shares of its borrowers
+pub fn migrate(storage: &mut dyn Storage, denom: &str) -> StdResult<()> {
+    let legacy_borrowers: Map<Addr, LegacyBorrower> = Map::new("borrowers");
+    let legacy_delegates: Map<(Addr, Addr), Uint128> = Map::new("delegates");
+
+    let borrowers = legacy_borrowers
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let total_borrower_shares: Uint128 = borrowers.iter().map(|(_, b)| b.shares).sum();
+    for (addr, legacy) in borrowers {
+        Borrower {
+            denom: denom.to_string(),
+            addr: legacy.addr,
+            limit: legacy.limit,
+            shares: legacy.shares,
+            health_check: None,
+        }
+        .save(storage)?;
+        legacy_borrowers.remove(storage, addr);
+    }
+
+    let delegates = legacy_delegates
         .range(storage, None, None, Order::Ascending)
-        .map(|r| r.map(|(_, b)| b.shares))
-        .sum::<StdResult<Uint128>>()?;
+        .collect::<StdResult<Vec<_>>>()?;
+    for ((borrower, delegate), shares) in delegates {
+        DELEGATE_SHARES.save(
+            storage,
+            (denom, borrower.clone(), delegate.clone()),
+            &shares,
+        )?;
+        legacy_delegates.remove(storage, (borrower, delegate));
+    }
 
-    let mut state = State::load(storage)?;
+    let mut state = State::load(storage, denom)?;
     let serialized = to_json_vec(&TempSharePool {
         size: state.debt_pool.size(),
         shares: Decimal::from_ratio(total_borrower_shares, 1u128),
     })?;
     state.debt_pool = from_json(&serialized)?;
-    state.save(storage)
+    state.save(storage, denom)
 }
@@ -1,11 +1,18 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Api, Decimal, StdResult, Storage};
-use cw_storage_plus::Item;
-use rujira_rs::ghost::vault::{InstantiateMsg, Interest};
+use cosmwasm_std::{Addr, Api, Decimal, Env, Order, StdError, StdResult, Storage, Timestamp};
+use cw_storage_plus::{Item, Map};
+use rujira_rs::{
+    ghost::vault::{
+        ConfigChange, IdleStrategy, InstantiateMsg, Interest, PendingConfigResponse, TermRate,
+    },
+    TokenFactory,
+};
 
 use crate::ContractError;
 
-static CONFIG: Item<Config> = Item::new("config");
+// Each denom is an isolated pool with its own config
+static CONFIG: Map<&str, Config> = Map::new("configs");
+static PENDING: Map<&str, PendingConfigResponse> = Map::new("config-pending");
 
 #[cw_serde]
 pub struct Config {
@@ -13,6 +20,32 @@ pub struct Config {
     pub interest: Interest,
     pub fee: Decimal,
     pub fee_address: Addr,
+    /// Fixed rates available for term deposits
+    #[serde(default)]
+    pub terms: Vec<TermRate>,
+    #[serde(default)]
+    pub early_exit_fee: Decimal,
+    /// Borrower limit utilization levels that emit an alert event when crossed by a borrow
+    #[serde(default)]
+    pub utilization_alerts: Vec<Decimal>,
+    /// The share of net interest paid to the backstop pool
+    #[serde(default)]
+    pub backstop_share: Decimal,
+    /// Seconds backstop shares must unbond before withdrawal
+    #[serde(default)]
+    pub backstop_unbonding: u64,
+    /// Where a share of unborrowed deposits is parked to earn yield
+    #[serde(default)]
+    pub idle_strategy: Option<IdleStrategy>,
+    /// Seconds that a proposed change to the fee and interest parameters waits before it can be applied
+    #[serde(default)]
+    pub timelock: u64,
+    /// Rejects withdrawals by an address in the same block as its last deposit
+    #[serde(default)]
+    pub same_block_guard: bool,
+    /// The maximum share of deposits that a single borrower's debt can reach with a borrow
+    #[serde(default)]
+    pub max_concentration: Option<Decimal>,
 }
 
 impl Config {
@@ -22,13 +55,59 @@ impl Config {
             interest: value.interest,
             fee: value.fee,
             fee_address: api.addr_validate(value.fee_address.as_str())?,
+            terms: vec![],
+            early_exit_fee: Decimal::zero(),
+            utilization_alerts: vec![],
+            backstop_share: Decimal::zero(),
+            backstop_unbonding: 0,
+            idle_strategy: None,
+            timelock: 0,
+            same_block_guard: false,
+            max_concentration: None,
         })
     }
 }
 
 impl Config {
-    pub fn load(storage: &dyn Storage) -> StdResult<Self> {
-        CONFIG.load(storage)
+    pub fn load(storage: &dyn Storage, denom: &str) -> Result<Self, ContractError> {
+        match CONFIG.load(storage, denom) {
+            Ok(x) => Ok(x),
+            Err(StdError::NotFound { .. }) => Err(ContractError::PoolNotFound {
+                denom: denom.to_string(),
+            }),
+            Err(err) => Err(ContractError::Std(err)),
+        }
+    }
+
+    /// Finds the pool that issues the `receipt` denom
+    pub fn load_by_receipt(
+        storage: &dyn Storage,
+        env: &Env,
+        receipt: &str,
+    ) -> Result<Self, ContractError> {
+        for config in Self::all(storage) {
+            let config = config?;
+            if config.receipt(env).denom() == receipt {
+                return Ok(config);
+            }
+        }
+        Err(ContractError::PoolNotFound {
+            denom: receipt.to_string(),
+        })
+    }
+
+    pub fn exists(storage: &dyn Storage, denom: &str) -> bool {
+        CONFIG.has(storage, denom)
+    }
+
+    pub fn all(storage: &dyn Storage) -> impl Iterator<Item = StdResult<Self>> + '_ {
+        CONFIG
+            .range(storage, None, None, Order::Ascending)
+            .map(|x| x.map(|(_, v)| v))
+    }
+
+    pub fn receipt(&self, env: &Env) -> TokenFactory {
+        TokenFactory::new(env, format!("ghost-vault/{}", self.denom).as_str())
     }
 
     pub fn validate(&self) -> Result<(), ContractError> {
@@ -36,11 +115,171 @@ impl Config {
             return Err(ContractError::Invalid("config.fee".to_string()));
         }
 
+        if self.early_exit_fee >= Decimal::one() {
+            return Err(ContractError::Invalid("config.early_exit_fee".to_string()));
+        }
+
+        for (i, term) in self.terms.iter().enumerate() {
+            if term.duration == 0 || term.rate >= Decimal::one() {
+                return Err(ContractError::Invalid("config.terms".to_string()));
+            }
+            if self.terms[..i].iter().any(|x| x.duration == term.duration) {
+                return Err(ContractError::Invalid("config.terms".to_string()));
+            }
+        }
+
+        if self.backstop_share >= Decimal::one() {
+            return Err(ContractError::Invalid("config.backstop_share".to_string()));
+        }
+
+        if let Some(strategy) = &self.idle_strategy {
+            if strategy.max_share > Decimal::one() {
+                return Err(ContractError::Invalid(
+                    "config.idle_strategy.max_share".to_string(),
+                ));
+            }
+        }
+
+        for threshold in self.utilization_alerts.iter() {
+            if threshold.is_zero() || threshold > &Decimal::one() {
+                return Err(ContractError::Invalid(
+                    "config.utilization_alerts".to_string(),
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_concentration {
+            if max.is_zero() || max > Decimal::one() {
+                return Err(ContractError::Invalid(
+                    "config.max_concentration".to_string(),
+                ));
+            }
+        }
+
         Ok(self.interest.validate()?)
     }
 
     pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
-        CONFIG.save(storage, self)
+        CONFIG.save(storage, self.denom.as_str(), self)
+    }
+
+    /// Queues `change`, replacing any pending change. It is validated now, so that a change
+    /// that could never be applied is rejected before the timelock starts
+    pub fn propose(
+        &self,
+        storage: &mut dyn Storage,
+        api: &dyn Api,
+        now: Timestamp,
+        change: ConfigChange,
+    ) -> Result<PendingConfigResponse, ContractError> {
+        let mut proposed = self.clone();
+        proposed.apply(api, &change)?;
+        proposed.validate()?;
+        let pending = PendingConfigResponse {
+            change,
+            executable_at: now.plus_seconds(self.timelock),
+        };
+        PENDING.save(storage, self.denom.as_str(), &pending)?;
+        Ok(pending)
+    }
+
+    /// A timelocked pool only changes the parameters of a [ConfigChange] through a proposal
+    pub fn ensure_untimelocked(&self, now: Timestamp) -> Result<(), ContractError> {
+        if self.timelock > 0 {
+            return Err(ContractError::Timelocked {
+                until: now.plus_seconds(self.timelock),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn pending(&self, storage: &dyn Storage) -> StdResult<Option<PendingConfigResponse>> {
+        PENDING.may_load(storage, self.denom.as_str())
+    }
+
+    pub fn cancel_pending(
+        &self,
+        storage: &mut dyn Storage,
+    ) -> Result<PendingConfigResponse, ContractError> {
+        let pending = self
+            .pending(storage)?
+            .ok_or(ContractError::NoPendingConfig {})?;
+        PENDING.remove(storage, self.denom.as_str());
+        Ok(pending)
+    }
+
+    /// Applies the pending change once its timelock has elapsed
+    pub fn apply_pending(
+        &mut self,
+        storage: &mut dyn Storage,
+        api: &dyn Api,
+        now: Timestamp,
+    ) -> Result<PendingConfigResponse, ContractError> {
+        let pending = self
+            .pending(storage)?
+            .ok_or(ContractError::NoPendingConfig {})?;
+        if now < pending.executable_at {
+            return Err(ContractError::Timelocked {
+                until: pending.executable_at,
+            });
+        }
+        self.apply(api, &pending.change)?;
+        self.validate()?;
+        PENDING.remove(storage, self.denom.as_str());
+        Ok(pending)
+    }
+
+    fn apply(&mut self, api: &dyn Api, change: &ConfigChange) -> StdResult<()> {
+        if let Some(fee) = change.fee {
+            self.fee = fee;
+        }
+        if let Some(fee_address) = &change.fee_address {
+            self.fee_address = api.addr_validate(fee_address)?;
+        }
+        if let Some(interest) = &change.interest {
+            self.interest = interest.clone();
+        }
+        if let Some(terms) = &change.terms {
+            self.terms = terms.clone();
+        }
+        if let Some(early_exit_fee) = change.early_exit_fee {
+            self.early_exit_fee = early_exit_fee;
+        }
+        if let Some(timelock) = change.timelock {
+            self.timelock = timelock;
+        }
+        Ok(())
+    }
+
+    pub fn term_rate(&self, duration: u64) -> Result<Decimal, ContractError> {
+        self.terms
+            .iter()
+            .find(|x| x.duration == duration)
+            .map(|x| x.rate)
+            .ok_or(ContractError::InvalidTerm { duration })
+    }
+
+    /// The alert thresholds crossed when utilization moves from `from` to `to`
+    pub fn utilization_alerts(&self, from: Decimal, to: Decimal) -> Vec<Decimal> {
+        self.utilization_alerts
+            .iter()
+            .filter(|x| from < **x && to >= **x)
+            .copied()
+            .collect()
+    }
+
+    /// Moves the config of a single-denom vault to the pool keyed by its denom,
+    /// returning that denom for the rest of the migration
+    pub fn migrate(storage: &mut dyn Storage) -> StdResult<Option<String>> {
+        let legacy: Item<Config> = Item::new("config");
+        match legacy.may_load(storage)? {
+            Some(config) => {
+                config.save(storage)?;
+                legacy.remove(storage);
+                Ok(Some(config.denom))
+            }
+            None => Ok(None),
+        }
     }
 }
 
@@ -59,11 +298,70 @@ mod tests {
                 base_rate: Decimal::from_ratio(3u128, 10000u128),
                 step1: Decimal::from_ratio(8u128, 10u128),
                 step2: Decimal::from_ratio(3u128, 1u128),
+                min_lend_rate: None,
+                max_debt_rate: None,
             },
             fee: Decimal::zero(),
             fee_address: Addr::unchecked("addr0000000000000000000000000000000000000000"),
+            terms: vec![],
+            early_exit_fee: Decimal::zero(),
+            utilization_alerts: vec![],
+            backstop_share: Decimal::zero(),
+            backstop_unbonding: 0,
+            idle_strategy: None,
+            timelock: 0,
+            same_block_guard: false,
+            max_concentration: None,
         }
         .validate()
         .unwrap();
     }
+
+    #[test]
+    fn terms_validation() {
+        let mut config = Config {
+            denom: "btc".to_string(),
+            interest: Interest {
+                target_utilization: Decimal::from_ratio(8u128, 10u128),
+                base_rate: Decimal::from_ratio(3u128, 10000u128),
+                step1: Decimal::from_ratio(8u128, 10u128),
+                step2: Decimal::from_ratio(3u128, 1u128),
+                min_lend_rate: None,
+                max_debt_rate: None,
+            },
+            fee: Decimal::zero(),
+            fee_address: Addr::unchecked("addr0000000000000000000000000000000000000000"),
+            terms: vec![
+                TermRate {
+                    duration: 2_592_000,
+                    rate: Decimal::percent(4),
+                },
+                TermRate {
+                    duration: 31_536_000,
+                    rate: Decimal::percent(6),
+                },
+            ],
+            early_exit_fee: Decimal::percent(1),
+            utilization_alerts: vec![],
+            backstop_share: Decimal::zero(),
+            backstop_unbonding: 0,
+            idle_strategy: None,
+            timelock: 0,
+            same_block_guard: false,
+            max_concentration: None,
+        };
+        config.validate().unwrap();
+        assert_eq!(config.term_rate(2_592_000).unwrap(), Decimal::percent(4));
+        config.term_rate(1).unwrap_err();
+
+        config.terms.push(TermRate {
+            duration: 2_592_000,
+            rate: Decimal::percent(5),
+        });
+        config.validate().unwrap_err();
+
+        config.terms.pop();
+        config.early_exit_fee = Decimal::one();
+        config.validate().unwrap_err();
+    }
 }
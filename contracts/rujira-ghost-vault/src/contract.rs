@@ -1,25 +1,42 @@
+use crate::backstop::Backstop;
 use crate::borrowers::Borrower;
 use crate::config::Config;
 use crate::error::ContractError;
-use crate::events::{event_borrow, event_deposit, event_repay, event_withdraw};
+use crate::events::{
+    event_accrue, event_backstop_deposit, event_backstop_unbond, event_backstop_withdraw,
+    event_borrow, event_deposit, event_deposit_term, event_flash_loan, event_flash_repay,
+    event_pending_config, event_repay, event_set_allowance, event_transfer_debt,
+    event_utilization_alert, event_withdraw, event_withdraw_term, event_write_off,
+};
+use crate::flash::FlashLoan;
+use crate::guard;
+use crate::history;
+use crate::idle;
+use crate::registry;
 use crate::state::State;
+use crate::terms::TermDeposit;
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    coins, to_json_binary, BankMsg, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response,
-    StdResult, Uint128,
+    coin, coins, to_json_binary, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env,
+    MessageInfo, Response, StdResult, Storage, SubMsg, Uint128, WasmMsg,
 };
 use cw2::set_contract_version;
-use cw_utils::must_pay;
+use cw_utils::{must_pay, nonpayable, one_coin};
 use rujira_rs::ghost::vault::{
-    BorrowerResponse, BorrowersResponse, ConfigResponse, DelegateResponse, ExecuteMsg,
-    InstantiateMsg, MarketMsg, PoolResponse, QueryMsg, StatusResponse, SudoMsg,
+    AllowanceResponse, BorrowerResponse, BorrowersResponse, ConfigResponse, DelegateResponse,
+    DepositResponse, DepositorResponse, ExecuteMsg, HealthCheckMsg, IdleStrategy, InstantiateMsg,
+    Interest, MarketMsg, PoolResponse, PoolsResponse, ProjectRatesResponse, QueryMsg,
+    RateCurveResponse, RateHistoryResponse, RatePoint, StatusResponse, SudoMsg,
+    TermDepositResponse, TermDepositsResponse, TermsResponse,
 };
-use rujira_rs::TokenFactory;
+use rujira_rs::PageResponse;
 use std::cmp::min;
 
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const BORROWERS_LIMIT: u8 = 100;
+const RATE_CURVE_POINTS: u16 = 101;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -29,13 +46,29 @@ pub fn instantiate(
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    if let Some(registry) = &msg.registry {
+        registry::set(deps.storage, &deps.api.addr_validate(registry)?)?;
+    }
+    Ok(Response::default().add_messages(add_pool(deps, &env, msg)?))
+}
+
+/// Creates an isolated pool for `msg.denom`, returning the messages to create its receipt token
+/// and register it with the vault registry
+fn add_pool(
+    deps: DepsMut,
+    env: &Env,
+    msg: InstantiateMsg,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    if Config::exists(deps.storage, &msg.denom) {
+        return Err(ContractError::PoolExists { denom: msg.denom });
+    }
     let config = Config::new(deps.api, msg.clone())?;
     config.validate()?;
     config.save(deps.storage)?;
-    State::init(deps.storage, &env)?;
-    let rcpt = TokenFactory::new(&env, format!("ghost-vault/{}", config.denom).as_str());
-
-    Ok(Response::default().add_message(rcpt.create_msg(msg.receipt)))
+    State::init(deps.storage, env, &config.denom)?;
+    let mut msgs = vec![config.receipt(env).create_msg(msg.receipt)];
+    msgs.extend(registry::register_msg(deps.storage, env, &config)?);
+    Ok(msgs)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -45,20 +78,45 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    let config = Config::load(deps.storage)?;
-    let mut state = State::load(deps.storage)?;
-    let rcpt = TokenFactory::new(&env, format!("ghost-vault/{}", config.denom).as_str());
+    let config = match &msg {
+        ExecuteMsg::Accrue {} => return accrue(deps, &env, &info),
+        ExecuteMsg::Withdraw { .. } => {
+            Config::load_by_receipt(deps.storage, &env, &one_coin(&info)?.denom)?
+        }
+        ExecuteMsg::WithdrawTerm { id, .. } => {
+            Config::load(deps.storage, &TermDeposit::load(deps.storage, *id)?.denom)?
+        }
+        ExecuteMsg::Market(MarketMsg::Borrow { denom, .. })
+        | ExecuteMsg::Market(MarketMsg::FlashLoan { denom, .. })
+        | ExecuteMsg::Market(MarketMsg::TransferDebt { denom, .. })
+        | ExecuteMsg::Market(MarketMsg::SetAllowance { denom, .. })
+        | ExecuteMsg::BorrowAllowance { denom, .. }
+        | ExecuteMsg::CheckFlashLoan { denom }
+        | ExecuteMsg::BackstopUnbond { denom, .. }
+        | ExecuteMsg::BackstopWithdraw { denom, .. }
+        | ExecuteMsg::Rebalance { denom } => Config::load(deps.storage, denom)?,
+        ExecuteMsg::Deposit { .. }
+        | ExecuteMsg::DepositTerm { .. }
+        | ExecuteMsg::BackstopDeposit {}
+        | ExecuteMsg::Market(MarketMsg::Repay { .. })
+        | ExecuteMsg::Market(MarketMsg::FlashRepay {})
+        | ExecuteMsg::RepayAllowance { .. } => Config::load(deps.storage, &one_coin(&info)?.denom)?,
+    };
+    let mut state = State::load(deps.storage, &config.denom)?;
+    let rcpt = config.receipt(&env);
     let fees = state.distribute_interest(&env, &config)?;
+    history::record(deps.storage, &config, &state)?;
     let mut response = match msg {
         ExecuteMsg::Deposit { callback } => {
             let amount = must_pay(&info, config.denom.as_str())?;
             let mint = state.deposit(amount)?;
-            state.save(deps.storage)?;
+            state.save(deps.storage, &config.denom)?;
+            guard::record(deps.storage, &env, &config)?;
 
             match callback {
                 None => Response::default()
                     .add_message(rcpt.mint_msg(mint, info.sender.clone()))
-                    .add_event(event_deposit(info.sender, amount, mint)),
+                    .add_event(event_deposit(info.sender, &config.denom, amount, mint)),
                 Some(cb) => Response::default()
                     .add_message(rcpt.mint_msg(mint, env.contract.address))
                     .add_message(cb.to_message(
@@ -66,35 +124,252 @@ pub fn execute(
                         Empty {},
                         coins(mint.u128(), rcpt.denom()),
                     )?)
-                    .add_event(event_deposit(info.sender, amount, mint)),
+                    .add_event(event_deposit(info.sender, &config.denom, amount, mint)),
             }
         }
         ExecuteMsg::Withdraw { callback } => {
+            guard::check(deps.storage, &env, &config)?;
             let amount = must_pay(&info, rcpt.denom().as_str())?;
             let withdrawn = state.withdraw(amount)?;
-            state.save(deps.storage)?;
+            let recall = idle::recall(deps.querier, &env, &config, &mut state, withdrawn)?;
+            state.save(deps.storage, &config.denom)?;
 
             match callback {
-                None => Response::default()
+                None => recall
                     .add_message(rcpt.burn_msg(amount))
                     .add_message(BankMsg::Send {
                         to_address: info.sender.to_string(),
-                        amount: coins(withdrawn.u128(), config.denom),
+                        amount: coins(withdrawn.u128(), &config.denom),
                     })
-                    .add_event(event_withdraw(info.sender, withdrawn, amount)),
-                Some(cb) => Response::default()
+                    .add_event(event_withdraw(
+                        info.sender,
+                        &config.denom,
+                        withdrawn,
+                        amount,
+                    )),
+                Some(cb) => recall
                     .add_message(rcpt.burn_msg(amount))
                     .add_message(cb.to_message(
                         &info.sender,
                         Empty {},
                         coins(withdrawn.u128(), &config.denom),
                     )?)
-                    .add_event(event_withdraw(info.sender, withdrawn, amount)),
+                    .add_event(event_withdraw(
+                        info.sender,
+                        &config.denom,
+                        withdrawn,
+                        amount,
+                    )),
+            }
+        }
+        ExecuteMsg::DepositTerm { duration } => {
+            let amount = must_pay(&info, config.denom.as_str())?;
+            let shares = state.deposit_term(amount)?;
+            let deposit = TermDeposit::create(
+                deps.storage,
+                &config,
+                info.sender.clone(),
+                amount,
+                shares,
+                env.block.time,
+                duration,
+            )?;
+            state.save(deps.storage, &config.denom)?;
+
+            Response::default().add_event(event_deposit_term(
+                info.sender,
+                &config.denom,
+                deposit.id,
+                amount,
+                deposit.rate,
+                deposit.maturity,
+            ))
+        }
+        ExecuteMsg::WithdrawTerm { id, callback } => {
+            let deposit = TermDeposit::load(deps.storage, id)?;
+            if deposit.owner != info.sender {
+                return Err(ContractError::Unauthorized {});
+            }
+            let penalty = deposit.penalty(env.block.time, config.early_exit_fee);
+            let value = deposit.value(env.block.time, config.early_exit_fee);
+            let withdrawn = state.withdraw_term(deposit.shares, value)?;
+            let recall = idle::recall(deps.querier, &env, &config, &mut state, withdrawn)?;
+            deposit.remove(deps.storage)?;
+            state.save(deps.storage, &config.denom)?;
+
+            let event =
+                event_withdraw_term(info.sender.clone(), &config.denom, id, withdrawn, penalty);
+            match callback {
+                None => recall
+                    .add_message(BankMsg::Send {
+                        to_address: info.sender.to_string(),
+                        amount: coins(withdrawn.u128(), &config.denom),
+                    })
+                    .add_event(event),
+                Some(cb) => recall
+                    .add_message(cb.to_message(
+                        &info.sender,
+                        Empty {},
+                        coins(withdrawn.u128(), &config.denom),
+                    )?)
+                    .add_event(event),
+            }
+        }
+        ExecuteMsg::BackstopDeposit {} => {
+            let amount = must_pay(&info, config.denom.as_str())?;
+            let shares = state.deposit_backstop(amount)?;
+            let mut backstop = Backstop::load(
+                deps.storage,
+                &config.denom,
+                info.sender.clone(),
+                state.backstop_epoch,
+            )?;
+            backstop.shares += shares;
+            backstop.save(deps.storage)?;
+            state.save(deps.storage, &config.denom)?;
+
+            Response::default().add_event(event_backstop_deposit(
+                info.sender,
+                &config.denom,
+                amount,
+                shares,
+            ))
+        }
+        ExecuteMsg::BackstopUnbond { denom: _, shares } => {
+            let mut backstop = Backstop::load(
+                deps.storage,
+                &config.denom,
+                info.sender.clone(),
+                state.backstop_epoch,
+            )?;
+            let release = env.block.time.plus_seconds(config.backstop_unbonding);
+            backstop.unbond(shares, release)?;
+            backstop.save(deps.storage)?;
+            state.save(deps.storage, &config.denom)?;
+
+            Response::default().add_event(event_backstop_unbond(
+                info.sender,
+                &config.denom,
+                shares,
+                release,
+            ))
+        }
+        ExecuteMsg::BackstopWithdraw { denom: _, callback } => {
+            let mut backstop = Backstop::load(
+                deps.storage,
+                &config.denom,
+                info.sender.clone(),
+                state.backstop_epoch,
+            )?;
+            let shares = backstop.take_released(env.block.time)?;
+            let withdrawn = state.withdraw_backstop(shares)?;
+            let recall = idle::recall(deps.querier, &env, &config, &mut state, withdrawn)?;
+            backstop.save(deps.storage)?;
+            state.save(deps.storage, &config.denom)?;
+
+            let event =
+                event_backstop_withdraw(info.sender.clone(), &config.denom, withdrawn, shares);
+            match callback {
+                None => recall
+                    .add_message(BankMsg::Send {
+                        to_address: info.sender.to_string(),
+                        amount: coins(withdrawn.u128(), &config.denom),
+                    })
+                    .add_event(event),
+                Some(cb) => recall
+                    .add_message(cb.to_message(
+                        &info.sender,
+                        Empty {},
+                        coins(withdrawn.u128(), &config.denom),
+                    )?)
+                    .add_event(event),
             }
         }
         ExecuteMsg::Market(market_msg) => {
-            let mut borrower = Borrower::load(deps.storage, info.sender.clone())?;
-            execute_market(deps, info, &mut state, market_msg, &mut borrower)?
+            let mut borrower = Borrower::load(deps.storage, &config.denom, info.sender.clone())?;
+            execute_market(
+                deps,
+                &env,
+                info,
+                &config,
+                &mut state,
+                market_msg,
+                &mut borrower,
+            )?
+        }
+        ExecuteMsg::BorrowAllowance {
+            denom,
+            borrower,
+            amount,
+            callback,
+        } => {
+            let mut borrower = Borrower::load(
+                deps.storage,
+                &config.denom,
+                deps.api.addr_validate(&borrower)?,
+            )?;
+            let allowance = borrower.allowance(deps.storage, info.sender.clone());
+            let drawn = state
+                .debt_pool
+                .ownership(borrower.delegate_shares(deps.storage, info.sender.clone()));
+            if drawn.checked_add(amount)? > allowance {
+                return Err(ContractError::AllowanceExceeded { allowance });
+            }
+            let delegate = Some(info.sender.to_string());
+            execute_market(
+                deps,
+                &env,
+                info,
+                &config,
+                &mut state,
+                MarketMsg::Borrow {
+                    denom,
+                    amount,
+                    callback,
+                    delegate,
+                },
+                &mut borrower,
+            )?
+        }
+        ExecuteMsg::RepayAllowance { borrower } => {
+            let mut borrower = Borrower::load(
+                deps.storage,
+                &config.denom,
+                deps.api.addr_validate(&borrower)?,
+            )?;
+            let delegate = Some(info.sender.to_string());
+            execute_market(
+                deps,
+                &env,
+                info,
+                &config,
+                &mut state,
+                MarketMsg::Repay { delegate },
+                &mut borrower,
+            )?
+        }
+        ExecuteMsg::CheckFlashLoan { denom: _ } => {
+            if info.sender != env.contract.address {
+                return Err(ContractError::Unauthorized {});
+            }
+            if FlashLoan::may_load(deps.storage, &config.denom)?.is_some() {
+                return Err(ContractError::FlashLoanOutstanding {
+                    denom: config.denom.clone(),
+                });
+            }
+            state.save(deps.storage, &config.denom)?;
+            Response::default()
+        }
+        ExecuteMsg::Rebalance { denom: _ } => {
+            nonpayable(&info)?;
+            let response = idle::rebalance(deps.querier, &env, &config, &mut state)?;
+            state.save(deps.storage, &config.denom)?;
+            response
+        }
+        ExecuteMsg::Accrue {} => {
+            return Err(ContractError::Invalid(
+                "Accrue is not scoped to a pool".to_string(),
+            ))
         }
     };
     if fees.gt(&Uint128::zero()) {
@@ -106,46 +381,66 @@ pub fn execute(
 
 pub fn execute_market(
     deps: DepsMut,
+    env: &Env,
     info: MessageInfo,
+    config: &Config,
     state: &mut State,
     msg: MarketMsg,
     borrower: &mut Borrower,
 ) -> Result<Response, ContractError> {
-    let config = Config::load(deps.storage)?;
     let response = match msg {
         MarketMsg::Borrow {
+            denom: _,
             amount,
             callback,
             delegate,
         } => {
-            let shares = state.borrow(amount)?;
-            match delegate.clone() {
+            let delegate_address = delegate
+                .clone()
+                .map(|d| deps.api.addr_validate(&d))
+                .transpose()?;
+            let utilization = borrower.utilization(&state.debt_pool);
+            let debt = state.debt_pool.ownership(borrower.shares);
+            let shares = state.borrow(amount, debt, config.max_concentration)?;
+            let recall = idle::recall(deps.querier, env, config, state, amount)?;
+            match delegate_address.clone() {
                 Some(d) => {
-                    borrower.delegate_borrow(
-                        deps.storage,
-                        deps.api.addr_validate(&d)?,
-                        &state.debt_pool,
-                        shares,
-                    )?;
+                    borrower.delegate_borrow(deps.storage, d, &state.debt_pool, shares)?;
                 }
                 None => {
                     borrower.borrow(deps.storage, &state.debt_pool, shares)?;
                 }
             };
+            let alerts =
+                config.utilization_alerts(utilization, borrower.utilization(&state.debt_pool));
+            // Dispatched after the borrowed funds are sent, and reverts the Borrow on error
+            let health_check = borrower
+                .health_check
+                .as_ref()
+                .map(|x| {
+                    HealthCheckMsg::Borrow {
+                        borrower: borrower.addr.to_string(),
+                        delegate: delegate.clone(),
+                        amount: coin(amount.u128(), &config.denom),
+                    }
+                    .call(x)
+                })
+                .transpose()?;
 
-            match callback {
-                None => Response::default()
+            let response = match callback {
+                None => recall
                     .add_message(BankMsg::Send {
                         to_address: info.sender.to_string(),
-                        amount: coins(amount.u128(), config.denom),
+                        amount: coins(amount.u128(), &config.denom),
                     })
                     .add_event(event_borrow(
                         borrower.addr.clone(),
+                        &config.denom,
                         delegate,
                         amount,
                         shares,
                     )),
-                Some(cb) => Response::default()
+                Some(cb) => recall
                     .add_message(cb.to_message(
                         &info.sender,
                         Empty {},
@@ -153,11 +448,22 @@ pub fn execute_market(
                     )?)
                     .add_event(event_borrow(
                         borrower.addr.clone(),
+                        &config.denom,
                         delegate,
                         amount,
                         shares,
                     )),
-            }
+            };
+            response
+                .add_submessages(health_check.map(SubMsg::new))
+                .add_events(alerts.iter().map(|threshold| {
+                    event_utilization_alert(
+                        borrower.addr.clone(),
+                        &config.denom,
+                        *threshold,
+                        borrower.utilization(&state.debt_pool),
+                    )
+                }))
         }
         MarketMsg::Repay { delegate } => {
             let amount = must_pay(&info, config.denom.as_str())?;
@@ -165,7 +471,6 @@ pub fn execute_market(
                 .clone()
                 .map(|d| deps.api.addr_validate(&d))
                 .transpose()?;
-
             let borrower_shares = match delegate_address.as_ref() {
                 Some(d) => borrower.delegate_shares(deps.storage, d.clone()),
                 None => borrower.shares,
@@ -182,6 +487,7 @@ pub fn execute_market(
 
             let mut response = Response::default().add_event(event_repay(
                 borrower.addr.clone(),
+                &config.denom,
                 delegate,
                 repay_amount,
                 shares,
@@ -196,131 +502,613 @@ pub fn execute_market(
             }
             response
         }
+        MarketMsg::FlashLoan {
+            denom: _,
+            amount,
+            callback,
+        } => {
+            let available = state
+                .deposit_pool
+                .size()
+                .saturating_sub(state.debt_pool.size());
+            if amount > available {
+                return Err(ContractError::InsufficientLiquidity { available });
+            }
+            FlashLoan::open(deps.storage, &config.denom, borrower.addr.clone(), amount)?;
+            let recall = idle::recall(deps.querier, env, config, state, amount)?;
+            // Checked once the callback, and every message it dispatches, has executed
+            let check = WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_json_binary(&ExecuteMsg::CheckFlashLoan {
+                    denom: config.denom.clone(),
+                })?,
+                funds: vec![],
+            };
+            recall
+                .add_message(callback.to_message(
+                    &info.sender,
+                    Empty {},
+                    coins(amount.u128(), &config.denom),
+                )?)
+                .add_message(check)
+                .add_event(event_flash_loan(
+                    borrower.addr.clone(),
+                    &config.denom,
+                    amount,
+                ))
+        }
+        MarketMsg::FlashRepay {} => {
+            let amount = must_pay(&info, config.denom.as_str())?;
+            let refund = FlashLoan::repay(deps.storage, &config.denom, &borrower.addr, amount)?;
+            let mut response = Response::default().add_event(event_flash_repay(
+                borrower.addr.clone(),
+                &config.denom,
+                amount.checked_sub(refund)?,
+            ));
+            if !refund.is_zero() {
+                response = response.add_message(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: coins(refund.u128(), &config.denom),
+                });
+            }
+            response
+        }
+        MarketMsg::TransferDebt {
+            denom: _,
+            from_delegate,
+            to_delegate,
+            shares,
+        } => {
+            nonpayable(&info)?;
+            borrower.delegate_transfer(
+                deps.storage,
+                deps.api.addr_validate(&from_delegate)?,
+                deps.api.addr_validate(&to_delegate)?,
+                shares,
+            )?;
+            Response::default().add_event(event_transfer_debt(
+                borrower.addr.clone(),
+                &config.denom,
+                from_delegate,
+                to_delegate,
+                shares,
+            ))
+        }
+        MarketMsg::SetAllowance {
+            denom: _,
+            spender,
+            amount,
+        } => {
+            nonpayable(&info)?;
+            borrower.set_allowance(deps.storage, deps.api.addr_validate(&spender)?, amount)?;
+            Response::default().add_event(event_set_allowance(
+                borrower.addr.clone(),
+                &config.denom,
+                spender,
+                amount,
+            ))
+        }
     };
-    state.save(deps.storage)?;
+    state.save(deps.storage, &config.denom)?;
     Ok(response)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
-    let mut config = Config::load(deps.storage)?;
-
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
     match msg {
-        SudoMsg::SetBorrower { contract, limit } => {
-            Borrower::set(deps.storage, deps.api.addr_validate(&contract)?, limit)?;
+        SudoMsg::AddPool(msg) => Ok(Response::default().add_messages(add_pool(deps, &env, msg)?)),
+        SudoMsg::SetRegistry(registry) => {
+            registry::set(deps.storage, &deps.api.addr_validate(&registry)?)?;
+            let mut msgs = vec![];
+            for config in Config::all(deps.storage).collect::<StdResult<Vec<_>>>()? {
+                msgs.extend(registry::register_msg(deps.storage, &env, &config)?);
+            }
+            Ok(Response::default().add_messages(msgs))
+        }
+        SudoMsg::SetBorrower {
+            denom,
+            contract,
+            limit,
+        } => {
+            let config = Config::load(deps.storage, &denom)?;
+            Borrower::set(
+                deps.storage,
+                &config.denom,
+                deps.api.addr_validate(&contract)?,
+                limit,
+            )?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetHealthCheck {
+            denom,
+            borrower,
+            health_check,
+        } => {
+            let mut borrower =
+                Borrower::load(deps.storage, &denom, deps.api.addr_validate(&borrower)?)?;
+            borrower.health_check = health_check
+                .map(|x| deps.api.addr_validate(&x))
+                .transpose()?;
+            borrower.save(deps.storage)?;
             Ok(Response::default())
         }
-        SudoMsg::SetInterest(interest) => {
+        SudoMsg::SetInterest { denom, interest } => {
+            let mut config = Config::load(deps.storage, &denom)?;
+            config.ensure_untimelocked(env.block.time)?;
             interest.validate()?;
             config.interest = interest;
             config.save(deps.storage)?;
             Ok(Response::default())
         }
+        SudoMsg::SetTerms {
+            denom,
+            terms,
+            early_exit_fee,
+        } => {
+            let mut config = Config::load(deps.storage, &denom)?;
+            config.ensure_untimelocked(env.block.time)?;
+            config.terms = terms;
+            config.early_exit_fee = early_exit_fee;
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetUtilizationAlerts { denom, thresholds } => {
+            let mut config = Config::load(deps.storage, &denom)?;
+            config.utilization_alerts = thresholds;
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetBackstop {
+            denom,
+            interest_share,
+            unbonding,
+        } => {
+            let mut config = Config::load(deps.storage, &denom)?;
+            // Settle interest at the previous share before it changes
+            let mut state = State::load(deps.storage, &denom)?;
+            let fees = state.distribute_interest(&env, &config)?;
+            state.save(deps.storage, &denom)?;
+            config.backstop_share = interest_share;
+            config.backstop_unbonding = unbonding;
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(fee_response(&env, &config, fees))
+        }
+        SudoMsg::WriteOff {
+            denom,
+            borrower,
+            delegate,
+        } => {
+            let config = Config::load(deps.storage, &denom)?;
+            let mut state = State::load(deps.storage, &denom)?;
+            let fees = state.distribute_interest(&env, &config)?;
+            let mut borrower =
+                Borrower::load(deps.storage, &denom, deps.api.addr_validate(&borrower)?)?;
+            let delegate_address = delegate
+                .clone()
+                .map(|d| deps.api.addr_validate(&d))
+                .transpose()?;
+            let shares = match delegate_address.as_ref() {
+                Some(d) => borrower.delegate_shares(deps.storage, d.clone()),
+                None => borrower.shares,
+            };
+            let (backstop, deposits) = state.write_off(shares)?;
+            match delegate_address {
+                Some(d) => borrower.delegate_repay(deps.storage, d, shares),
+                None => borrower.repay(deps.storage, shares),
+            }?;
+            state.save(deps.storage, &denom)?;
+
+            Ok(fee_response(&env, &config, fees).add_event(event_write_off(
+                borrower.addr,
+                &denom,
+                delegate,
+                backstop,
+                deposits,
+            )))
+        }
+        SudoMsg::SetIdleStrategy {
+            denom,
+            contract,
+            max_share,
+        } => {
+            let mut config = Config::load(deps.storage, &denom)?;
+            let mut state = State::load(deps.storage, &denom)?;
+            let fees = state.distribute_interest(&env, &config)?;
+            // Recall everything from the previous strategy, settling its yield to depositors
+            let closed = idle::close(deps.querier, &env, &config, &mut state)?;
+            config.idle_strategy = contract
+                .map(|x| -> StdResult<IdleStrategy> {
+                    Ok(IdleStrategy {
+                        contract: deps.api.addr_validate(&x)?,
+                        max_share,
+                    })
+                })
+                .transpose()?;
+            config.validate()?;
+            config.save(deps.storage)?;
+            state.save(deps.storage, &denom)?;
+            Ok(fee_response(&env, &config, fees)
+                .add_submessages(closed.messages)
+                .add_events(closed.events))
+        }
+        SudoMsg::ProposeConfig { denom, change } => {
+            let config = Config::load(deps.storage, &denom)?;
+            let pending = config.propose(deps.storage, deps.api, env.block.time, change)?;
+            Ok(Response::default().add_event(event_pending_config(
+                "propose",
+                &denom,
+                pending.executable_at,
+            )))
+        }
+        SudoMsg::ApplyConfig { denom } => {
+            let mut config = Config::load(deps.storage, &denom)?;
+            // Settle interest at the previous fee and rates before they change
+            let mut state = State::load(deps.storage, &denom)?;
+            let fees = state.distribute_interest(&env, &config)?;
+            state.save(deps.storage, &denom)?;
+            let response = fee_response(&env, &config, fees);
+            let pending = config.apply_pending(deps.storage, deps.api, env.block.time)?;
+            config.save(deps.storage)?;
+            Ok(response.add_event(event_pending_config("apply", &denom, pending.executable_at)))
+        }
+        SudoMsg::CancelConfig { denom } => {
+            let config = Config::load(deps.storage, &denom)?;
+            let pending = config.cancel_pending(deps.storage)?;
+            Ok(Response::default().add_event(event_pending_config(
+                "cancel",
+                &denom,
+                pending.executable_at,
+            )))
+        }
+        SudoMsg::SetSameBlockGuard { denom, enabled } => {
+            let mut config = Config::load(deps.storage, &denom)?;
+            config.same_block_guard = enabled;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::SetMaxConcentration { denom, max } => {
+            let mut config = Config::load(deps.storage, &denom)?;
+            config.max_concentration = max;
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
     }
 }
 
+/// Distributes the interest of every pool, minting the protocol fee shares of each
+fn accrue(deps: DepsMut, env: &Env, info: &MessageInfo) -> Result<Response, ContractError> {
+    nonpayable(info)?;
+    let configs = Config::all(deps.storage).collect::<StdResult<Vec<Config>>>()?;
+    let mut response = Response::default();
+    for config in configs {
+        let mut state = State::load(deps.storage, &config.denom)?;
+        let fees = state.distribute_interest(env, &config)?;
+        state.save(deps.storage, &config.denom)?;
+        history::record(deps.storage, &config, &state)?;
+        response = response
+            .add_submessages(fee_response(env, &config, fees).messages)
+            .add_event(event_accrue(&config.denom, state.last_updated, fees));
+    }
+    Ok(response)
+}
+
+/// Mints the protocol fee shares accrued outside of [execute]
+fn fee_response(env: &Env, config: &Config, fees: Uint128) -> Response {
+    if fees.is_zero() {
+        return Response::default();
+    }
+    Response::default().add_message(
+        config
+            .receipt(env)
+            .mint_msg(fees, config.fee_address.clone()),
+    )
+}
+
+/// Loads the config and state of the pool for `denom`, with interest accrued to now
+fn load_pool(
+    storage: &dyn Storage,
+    env: &Env,
+    denom: &str,
+) -> Result<(Config, State), ContractError> {
+    let config = Config::load(storage, denom)?;
+    let mut state = State::load(storage, denom)?;
+    state.distribute_interest(env, &config)?;
+    Ok((config, state))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
-    let mut state = State::load(deps.storage)?;
-    let config = Config::load(deps.storage)?;
-    state.distribute_interest(&env, &config)?;
-
     match msg {
-        QueryMsg::Config {} => Ok(to_json_binary(&ConfigResponse {
-            denom: config.denom,
-            interest: config.interest,
+        QueryMsg::Pools {} => Ok(to_json_binary(&PoolsResponse {
+            denoms: Config::all(deps.storage)
+                .map(|x| x.map(|config| config.denom))
+                .collect::<StdResult<Vec<String>>>()?,
         })?),
 
-        QueryMsg::Status {} => Ok(to_json_binary(&StatusResponse {
-            debt_rate: state.debt_rate(&config.interest)?,
-            lend_rate: state.lend_rate(&config.interest)?,
-            utilization_ratio: state.utilization(),
-            last_updated: state.last_updated,
-            debt_pool: PoolResponse {
-                size: state.debt_pool.size(),
-                shares: state.debt_pool.shares(),
-                ratio: state.debt_pool.ratio(),
-            },
-            deposit_pool: PoolResponse {
-                size: state.deposit_pool.size(),
-                shares: state.deposit_pool.shares(),
-                ratio: state.deposit_pool.ratio(),
-            },
-        })?),
-        QueryMsg::Borrower { addr } => {
-            let borrower = Borrower::load(deps.storage, deps.api.addr_validate(&addr)?)?;
-            let current = state.debt_pool.ownership(borrower.shares);
-            Ok(to_json_binary(&BorrowerResponse {
-                addr: borrower.addr.to_string(),
+        QueryMsg::Config { denom } => {
+            let config = Config::load(deps.storage, &denom)?;
+            Ok(to_json_binary(&ConfigResponse {
                 denom: config.denom,
-                limit: borrower.limit,
-                current,
-                shares: borrower.shares,
-                available: min(
-                    // Current borrows can exceed limit due to interest
-                    borrower.limit.checked_sub(current).unwrap_or_default(),
-                    state.deposit_pool.size() - state.debt_pool.size(),
-                ),
+                interest: config.interest,
+                backstop_share: config.backstop_share,
+                backstop_unbonding: config.backstop_unbonding,
+                idle_strategy: config.idle_strategy,
+                fee: config.fee,
+                timelock: config.timelock,
+                same_block_guard: config.same_block_guard,
+                max_concentration: config.max_concentration,
+            })?)
+        }
+
+        QueryMsg::Status { denom } => {
+            let (config, state) = load_pool(deps.storage, &env, &denom)?;
+            let held = deps
+                .querier
+                .query_balance(&env.contract.address, &denom)?
+                .amount;
+            let solvency_ratio = state.solvency(held);
+            Ok(to_json_binary(&StatusResponse {
+                debt_rate: state.debt_rate(&config.interest)?,
+                lend_rate: state.lend_rate(&config.interest)?,
+                utilization_ratio: state.utilization(),
+                last_updated: state.last_updated,
+                debt_pool: PoolResponse {
+                    size: state.debt_pool.size(),
+                    shares: state.debt_pool.shares(),
+                    ratio: state.debt_pool.ratio(),
+                },
+                deposit_pool: PoolResponse {
+                    size: state.deposit_pool.size(),
+                    shares: state.deposit_pool.shares(),
+                    ratio: state.deposit_pool.ratio(),
+                },
+                backstop_pool: PoolResponse {
+                    size: state.backstop_pool.size(),
+                    shares: state.backstop_pool.shares(),
+                    ratio: state.backstop_pool.ratio(),
+                },
+                idle_deployed: state.idle_deployed,
+                written_off_backstop: state.written_off_backstop,
+                written_off_deposits: state.written_off_deposits,
+                term_shortfall: state.term_shortfall,
+                solvency_ratio,
+                insolvent: solvency_ratio < Decimal::one(),
             })?)
         }
-        QueryMsg::Delegate { borrower, addr } => {
-            let borrower = Borrower::load(deps.storage, deps.api.addr_validate(&borrower)?)?;
+        QueryMsg::Borrower { denom, addr } => {
+            let (config, state) = load_pool(deps.storage, &env, &denom)?;
+            let borrower = Borrower::load(deps.storage, &denom, deps.api.addr_validate(&addr)?)?;
+            Ok(to_json_binary(&borrower_response(
+                &borrower, &state, &config,
+            ))?)
+        }
+        QueryMsg::Delegate {
+            denom,
+            borrower,
+            addr,
+        } => {
+            let (config, state) = load_pool(deps.storage, &env, &denom)?;
+            let borrower =
+                Borrower::load(deps.storage, &denom, deps.api.addr_validate(&borrower)?)?;
             let delegate = borrower.delegate_shares(deps.storage, deps.api.addr_validate(&addr)?);
-            let current = state.debt_pool.ownership(borrower.shares);
 
             Ok(to_json_binary(&DelegateResponse {
-                borrower: BorrowerResponse {
-                    addr: borrower.addr.to_string(),
-                    denom: config.denom,
-                    limit: borrower.limit,
-                    current,
-                    shares: borrower.shares,
-                    available: min(
-                        borrower.limit.checked_sub(current).unwrap_or_default(),
-                        state.deposit_pool.size() - state.debt_pool.size(),
-                    ),
-                },
+                borrower: borrower_response(&borrower, &state, &config),
                 addr,
                 current: state.debt_pool.ownership(delegate),
                 shares: delegate,
             })?)
         }
-        QueryMsg::Borrowers { limit, start_after } => {
-            let borrowers = Borrower::list(
+        QueryMsg::Allowance {
+            denom,
+            borrower,
+            spender,
+        } => {
+            let (_, state) = load_pool(deps.storage, &env, &denom)?;
+            let borrower =
+                Borrower::load(deps.storage, &denom, deps.api.addr_validate(&borrower)?)?;
+            let addr = deps.api.addr_validate(&spender)?;
+
+            Ok(to_json_binary(&AllowanceResponse {
+                borrower: borrower.addr.to_string(),
+                allowance: borrower.allowance(deps.storage, addr.clone()),
+                drawn: state
+                    .debt_pool
+                    .ownership(borrower.delegate_shares(deps.storage, addr)),
+                spender,
+            })?)
+        }
+        QueryMsg::Borrowers { denom, page } => {
+            let (config, state) = load_pool(deps.storage, &env, &denom)?;
+            let limit = page.limit(BORROWERS_LIMIT, BORROWERS_LIMIT);
+            let page = page.try_map(|x| deps.api.addr_validate(x.as_str()))?;
+            let borrowers = Borrower::list(deps.storage, &denom, limit, page.start_after)
+                .map(|x| x.map(|borrower| borrower_response(&borrower, &state, &config)))
+                .collect::<StdResult<Vec<BorrowerResponse>>>()?;
+            Ok(to_json_binary(&BorrowersResponse {
+                page: PageResponse::new(&borrowers, limit, |x| x.addr.clone()),
+                borrowers,
+            })?)
+        }
+        QueryMsg::BorrowersAtRisk {
+            denom,
+            threshold,
+            page,
+        } => {
+            let (config, state) = load_pool(deps.storage, &env, &denom)?;
+            let limit = page.limit(BORROWERS_LIMIT, BORROWERS_LIMIT);
+            let page = page.try_map(|x| deps.api.addr_validate(x.as_str()))?;
+            // The page bounds the borrowers scanned, so the cursor is the last scanned
+            let scanned = Borrower::list(deps.storage, &denom, limit, page.start_after)
+                .collect::<StdResult<Vec<Borrower>>>()?;
+            let borrowers = scanned
+                .iter()
+                .filter(|x| x.utilization(&state.debt_pool) >= threshold)
+                .map(|x| borrower_response(x, &state, &config))
+                .collect();
+            Ok(to_json_binary(&BorrowersResponse {
+                page: PageResponse::new(&scanned, limit, |x| x.addr.to_string()),
+                borrowers,
+            })?)
+        }
+        QueryMsg::Terms { denom } => {
+            let config = Config::load(deps.storage, &denom)?;
+            Ok(to_json_binary(&TermsResponse {
+                terms: config.terms,
+                early_exit_fee: config.early_exit_fee,
+            })?)
+        }
+        QueryMsg::TermDeposit { id } => {
+            let deposit = TermDeposit::load(deps.storage, id)?;
+            let config = Config::load(deps.storage, &deposit.denom)?;
+            Ok(to_json_binary(
+                &deposit.to_response(env.block.time, config.early_exit_fee),
+            )?)
+        }
+        QueryMsg::TermDeposits {
+            owner,
+            limit,
+            start_after,
+        } => {
+            let deposits = TermDeposit::by_owner(
                 deps.storage,
+                &deps.api.addr_validate(&owner)?,
                 limit,
-                start_after
-                    .map(|x| deps.api.addr_validate(x.as_str()))
-                    .transpose()?,
-            )
-            .map(|x| {
-                x.map(|borrower| {
-                    let current = state.debt_pool.ownership(borrower.shares);
-
-                    BorrowerResponse {
-                        addr: borrower.addr.to_string(),
-                        denom: config.denom.clone(),
-                        limit: borrower.limit,
-                        current,
-                        shares: borrower.shares,
-                        available: min(
-                            // Current borrows can exceed limit due to interest
-                            borrower.limit.checked_sub(current).unwrap_or_default(),
-                            state.deposit_pool.size() - state.debt_pool.size(),
-                        ),
-                    }
-                })
+                start_after,
+            )?
+            .iter()
+            .map(|x| -> Result<TermDepositResponse, ContractError> {
+                let config = Config::load(deps.storage, &x.denom)?;
+                Ok(x.to_response(env.block.time, config.early_exit_fee))
             })
-            .collect::<StdResult<Vec<BorrowerResponse>>>()?;
-            Ok(to_json_binary(&BorrowersResponse { borrowers })?)
+            .collect::<Result<Vec<TermDepositResponse>, ContractError>>()?;
+            Ok(to_json_binary(&TermDepositsResponse { deposits })?)
+        }
+        QueryMsg::Backstop { denom, addr } => {
+            let (_, state) = load_pool(deps.storage, &env, &denom)?;
+            let backstop = Backstop::load(
+                deps.storage,
+                &denom,
+                deps.api.addr_validate(&addr)?,
+                state.backstop_epoch,
+            )?;
+            Ok(to_json_binary(&backstop.to_response(&state.backstop_pool))?)
+        }
+
+        QueryMsg::Depositor { addr } => {
+            let addr = deps.api.addr_validate(&addr)?;
+            let mut deposits = vec![];
+            for config in Config::all(deps.storage) {
+                let config = config?;
+                let receipt = config.receipt(&env).denom();
+                let shares = deps.querier.query_balance(&addr, &receipt)?.amount;
+                if shares.is_zero() {
+                    continue;
+                }
+                let (_, state) = load_pool(deps.storage, &env, &config.denom)?;
+                deposits.push(DepositResponse {
+                    denom: config.denom,
+                    receipt,
+                    shares,
+                    value: state.deposit_value(shares),
+                });
+            }
+            Ok(to_json_binary(&DepositorResponse {
+                addr: addr.to_string(),
+                deposits,
+            })?)
+        }
+
+        QueryMsg::PendingConfig { denom } => {
+            let config = Config::load(deps.storage, &denom)?;
+            Ok(to_json_binary(&config.pending(deps.storage)?)?)
+        }
+        QueryMsg::RateHistory { denom, limit } => Ok(to_json_binary(&RateHistoryResponse {
+            observations: history::list(deps.storage, &denom, limit)?,
+        })?),
+        QueryMsg::RateCurve {
+            denom,
+            points,
+            interest,
+        } => {
+            let interest = match interest {
+                Some(interest) => {
+                    interest.validate()?;
+                    interest
+                }
+                None => Config::load(deps.storage, &denom)?.interest,
+            };
+            let steps = points.clamp(2, RATE_CURVE_POINTS) - 1;
+            Ok(to_json_binary(&RateCurveResponse {
+                points: (0..=steps)
+                    .map(|x| rate_point(&interest, Decimal::from_ratio(x, steps)))
+                    .collect::<StdResult<Vec<_>>>()?,
+            })?)
+        }
+        QueryMsg::ProjectRates {
+            denom,
+            borrow_delta,
+            deposit_delta,
+        } => {
+            let (config, state) = load_pool(deps.storage, &env, &denom)?;
+            let projected = state.projected_utilization(borrow_delta, deposit_delta)?;
+            Ok(to_json_binary(&ProjectRatesResponse {
+                current: rate_point(&config.interest, state.utilization())?,
+                projected: rate_point(&config.interest, projected)?,
+            })?)
         }
     }
 }
 
+fn rate_point(interest: &Interest, utilization: Decimal) -> StdResult<RatePoint> {
+    let debt_rate = interest.rate(utilization)?;
+    Ok(RatePoint {
+        utilization,
+        debt_rate,
+        lend_rate: debt_rate * utilization,
+    })
+}
+
+fn borrower_response(borrower: &Borrower, state: &State, config: &Config) -> BorrowerResponse {
+    let current = state.debt_pool.ownership(borrower.shares);
+    BorrowerResponse {
+        addr: borrower.addr.to_string(),
+        denom: config.denom.clone(),
+        limit: borrower.limit,
+        current,
+        shares: borrower.shares,
+        available: min(
+            // Current borrows can exceed limit due to interest
+            borrower.limit.checked_sub(current).unwrap_or_default(),
+            state.deposit_pool.size() - state.debt_pool.size(),
+        )
+        .min(match config.max_concentration {
+            Some(max) => state
+                .deposit_pool
+                .size()
+                .mul_floor(max)
+                .checked_sub(current)
+                .unwrap_or_default(),
+            None => Uint128::MAX,
+        }),
+        health_check: borrower.health_check.as_ref().map(|x| x.to_string()),
+        concentration: state.concentration(current),
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, _env: Env, _msg: ()) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    crate::borrowers::migrate(deps.storage)?;
+    // Move a single-denom vault into the pool for its denom
+    if let Some(denom) = Config::migrate(deps.storage)? {
+        State::migrate(deps.storage, &denom)?;
+        crate::borrowers::migrate(deps.storage, &denom)?;
+        crate::terms::migrate(deps.storage, &denom)?;
+    }
     Ok(Response::default())
 }
 
@@ -330,10 +1118,18 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
-    use cosmwasm_std::{coin, Decimal, Event, Uint128};
+    use cosmwasm_std::{coin, Addr, Decimal, Event, Int128, Uint128};
     use cw_multi_test::{ContractWrapper, Executor};
-    use rujira_rs::{ghost::vault::Interest, TokenMetadata};
-    use rujira_rs_testing::mock_rujira_app;
+    use rujira_rs::{
+        ghost::vault::{
+            BackstopResponse, ConfigChange, IdleBalanceResponse, IdleStrategyMsg,
+            IdleStrategyQueryMsg, Interest, PendingConfigResponse, RateObservation, TermRate,
+        },
+        PageRequest, TokenMetadata,
+    };
+    use rujira_rs_testing::{mock_rujira_app, RujiraApp};
+
+    use crate::mock::GhostVault;
 
     #[test]
     fn lifecycle() {
@@ -373,9 +1169,12 @@ mod tests {
                         base_rate: Decimal::from_ratio(1u128, 10u128),
                         step1: Decimal::from_ratio(1u128, 10u128),
                         step2: Decimal::from_ratio(3u128, 1u128),
+                        min_lend_rate: None,
+                        max_debt_rate: None,
                     },
                     fee: Decimal::zero(),
                     fee_address: owner.to_string(),
+                    registry: None,
                 },
                 &[],
                 "template",
@@ -435,6 +1234,7 @@ mod tests {
         app.wasm_sudo(
             contract.clone(),
             &SudoMsg::SetBorrower {
+                denom: "btc".to_string(),
                 contract: borrower.to_string(),
                 limit: Uint128::from(500u128),
             },
@@ -446,6 +1246,7 @@ mod tests {
             .query_wasm_smart(
                 contract.clone(),
                 &QueryMsg::Borrower {
+                    denom: "btc".to_string(),
                     addr: borrower.to_string(),
                 },
             )
@@ -459,6 +1260,7 @@ mod tests {
             borrower.clone(),
             contract.clone(),
             &ExecuteMsg::Market(MarketMsg::Borrow {
+                denom: "btc".to_string(),
                 callback: None,
                 amount: Uint128::from(501u128),
                 delegate: None,
@@ -473,6 +1275,7 @@ mod tests {
                 borrower.clone(),
                 contract.clone(),
                 &ExecuteMsg::Market(MarketMsg::Borrow {
+                    denom: "btc".to_string(),
                     callback: None,
                     amount: Uint128::from(500u128),
                     delegate: None,
@@ -517,7 +1320,12 @@ mod tests {
         // Check the rate has increased
         let status: StatusResponse = app
             .wrap()
-            .query_wasm_smart(contract.clone(), &QueryMsg::Status {})
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Status {
+                    denom: "btc".to_string(),
+                },
+            )
             .unwrap();
         assert_eq!(
             status.utilization_ratio,
@@ -533,6 +1341,26 @@ mod tests {
             Decimal::from_str("1.02").unwrap()
         );
 
+        // The owner's receipt tokens are valued with the interest accrued
+        let depositor: DepositorResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Depositor {
+                    addr: owner.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            depositor.deposits,
+            vec![DepositResponse {
+                denom: "btc".to_string(),
+                receipt: "x/ghost-vault/btc".to_string(),
+                shares: Uint128::from(800u128),
+                value: Uint128::from(807u128),
+            }]
+        );
+
         // Make another deposit
         let res = app
             .execute_contract(
@@ -548,12 +1376,12 @@ mod tests {
             &Event::new("wasm-rujira-ghost-vault/deposit").add_attributes(vec![
                 ("amount", "1000"),
                 ("owner", owner.as_str()),
-                ("shares", "980"),
+                ("shares", "991"),
             ]),
         );
 
         res.assert_event(&Event::new("mint").add_attributes(vec![
-            ("amount", "980"),
+            ("amount", "991"),
             ("denom", "x/ghost-vault/btc"),
             ("recipient", owner.as_str()),
         ]));
@@ -590,7 +1418,7 @@ mod tests {
 
         res.assert_event(
             &Event::new("wasm-rujira-ghost-vault/withdraw").add_attributes(vec![
-                ("amount", "204"),
+                ("amount", "201"),
                 ("owner", owner.as_str()),
                 ("shares", "200"),
             ]),
@@ -613,18 +1441,1688 @@ mod tests {
         // Check the rate has increased
         let status: StatusResponse = app
             .wrap()
-            .query_wasm_smart(contract.clone(), &QueryMsg::Status {})
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Status {
+                    denom: "btc".to_string(),
+                },
+            )
             .unwrap();
 
         assert_eq!(status.utilization_ratio, Decimal::zero());
         assert_eq!(status.debt_pool.size, Uint128::zero());
         assert_eq!(status.debt_pool.shares, Uint128::zero());
         assert_eq!(status.debt_pool.ratio, Decimal::zero());
-        assert_eq!(status.deposit_pool.size, Uint128::from(1612u128));
-        assert_eq!(status.deposit_pool.shares, Uint128::from(1580u128));
+        assert_eq!(status.deposit_pool.size, Uint128::from(1615u128));
+        assert_eq!(status.deposit_pool.shares, Uint128::from(1591u128));
         assert_eq!(
             status.deposit_pool.ratio,
-            Decimal::from_str("1.020253164556962025").unwrap()
+            Decimal::from_str("1.015084852294154619").unwrap()
         );
     }
+
+    #[test]
+    fn utilization_alerts() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let borrower = app.api().addr_make("borrower");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        vault
+            .set_borrower(&mut app, borrower.as_str(), Uint128::from(100u128))
+            .unwrap();
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::SetUtilizationAlerts {
+                denom: "btc".to_string(),
+                thresholds: vec![Decimal::percent(80), Decimal::percent(95)],
+            },
+        )
+        .unwrap();
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::SetUtilizationAlerts {
+                denom: "btc".to_string(),
+                thresholds: vec![Decimal::percent(101)],
+            },
+        )
+        .unwrap_err();
+
+        let borrow = |amount: u128| MarketMsg::Borrow {
+            denom: "btc".to_string(),
+            amount: Uint128::from(amount),
+            callback: None,
+            delegate: None,
+        };
+        let res = app
+            .execute_contract(
+                borrower.clone(),
+                vault.addr().clone(),
+                &ExecuteMsg::Market(borrow(50)),
+                &[],
+            )
+            .unwrap();
+        assert!(!res
+            .events
+            .iter()
+            .any(|e| e.ty == "wasm-rujira-ghost-vault/utilization_alert"));
+
+        // Crosses both thresholds in one borrow
+        let res = app
+            .execute_contract(
+                borrower.clone(),
+                vault.addr().clone(),
+                &ExecuteMsg::Market(borrow(46)),
+                &[],
+            )
+            .unwrap();
+        for threshold in ["0.8", "0.95"] {
+            res.assert_event(
+                &Event::new("wasm-rujira-ghost-vault/utilization_alert").add_attributes(vec![
+                    ("borrower", borrower.as_str()),
+                    ("threshold", threshold),
+                    ("utilization", "0.96"),
+                ]),
+            );
+        }
+
+        let at_risk =
+            |app: &RujiraApp, threshold: Decimal, page: PageRequest<String>| -> BorrowersResponse {
+                app.wrap()
+                    .query_wasm_smart(
+                        vault.addr(),
+                        &QueryMsg::BorrowersAtRisk {
+                            denom: "btc".to_string(),
+                            threshold,
+                            page,
+                        },
+                    )
+                    .unwrap()
+            };
+        let res = at_risk(&app, Decimal::percent(95), PageRequest::default());
+        assert_eq!(res.borrowers.len(), 1);
+        assert_eq!(res.borrowers[0].addr, borrower.to_string());
+        assert_eq!(res.borrowers[0].current, Uint128::from(96u128));
+        assert_eq!(
+            at_risk(&app, Decimal::percent(97), PageRequest::default()).borrowers,
+            vec![]
+        );
+
+        // Each page scans up to its limit of borrowers, at risk or not
+        for name in ["safe1", "safe2"] {
+            vault
+                .set_borrower(
+                    &mut app,
+                    app.api().addr_make(name).as_str(),
+                    Uint128::from(100u128),
+                )
+                .unwrap();
+        }
+        let mut found = vec![];
+        let mut start_after = None;
+        for _ in 0..3 {
+            let res = at_risk(
+                &app,
+                Decimal::percent(95),
+                PageRequest::new(start_after, Some(1)),
+            );
+            assert!(res.borrowers.len() <= 1);
+            found.extend(res.borrowers.into_iter().map(|x| x.addr));
+            start_after = res.page.next;
+        }
+        let res = at_risk(
+            &app,
+            Decimal::percent(95),
+            PageRequest::new(start_after, Some(1)),
+        );
+        assert_eq!(res.borrowers, vec![]);
+        assert_eq!(res.page.next, None);
+        assert_eq!(found, vec![borrower.to_string()]);
+    }
+
+    #[test]
+    fn health_check() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let borrower = app.api().addr_make("borrower");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        vault
+            .set_borrower(&mut app, borrower.as_str(), Uint128::from(500u128))
+            .unwrap();
+
+        // Fails any single borrow above 100
+        let code = ContractWrapper::new(
+            |_, _, _, msg: HealthCheckMsg| -> StdResult<Response> {
+                let HealthCheckMsg::Borrow { amount, .. } = msg;
+                if amount.amount > Uint128::from(100u128) {
+                    return Err(cosmwasm_std::StdError::generic_err("unhealthy"));
+                }
+                Ok(Response::default())
+            },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::default()) },
+            |_, _, _: Empty| -> StdResult<Binary> { Ok(Binary::default()) },
+        );
+        let code_id = app.store_code(Box::new(code));
+        let hook = app
+            .instantiate_contract(code_id, owner.clone(), &Empty {}, &[], "hook", None)
+            .unwrap();
+
+        // Only set for an existing borrower
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::SetHealthCheck {
+                denom: "btc".to_string(),
+                borrower: owner.to_string(),
+                health_check: Some(hook.to_string()),
+            },
+        )
+        .unwrap_err();
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::SetHealthCheck {
+                denom: "btc".to_string(),
+                borrower: borrower.to_string(),
+                health_check: Some(hook.to_string()),
+            },
+        )
+        .unwrap();
+
+        let borrow = |amount: u128| {
+            ExecuteMsg::Market(MarketMsg::Borrow {
+                denom: "btc".to_string(),
+                amount: Uint128::from(amount),
+                callback: None,
+                delegate: None,
+            })
+        };
+        app.execute_contract(borrower.clone(), vault.addr().clone(), &borrow(100), &[])
+            .unwrap();
+        let err = app
+            .execute_contract(borrower.clone(), vault.addr().clone(), &borrow(101), &[])
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("unhealthy"));
+
+        // The failed borrow is reverted
+        let res: BorrowerResponse = app
+            .wrap()
+            .query_wasm_smart(
+                vault.addr(),
+                &QueryMsg::Borrower {
+                    denom: "btc".to_string(),
+                    addr: borrower.to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.current, Uint128::from(100u128));
+        assert_eq!(res.health_check, Some(hook.to_string()));
+        assert_eq!(
+            app.wrap().query_balance(&borrower, "btc").unwrap().amount,
+            Uint128::from(100u128)
+        );
+
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::SetHealthCheck {
+                denom: "btc".to_string(),
+                borrower: borrower.to_string(),
+                health_check: None,
+            },
+        )
+        .unwrap();
+        app.execute_contract(borrower.clone(), vault.addr().clone(), &borrow(101), &[])
+            .unwrap();
+    }
+
+    #[test]
+    fn flash_loan() {
+        #[cosmwasm_schema::cw_serde]
+        enum ReceiverMsg {
+            Flash {
+                vault: String,
+                amount: Uint128,
+                repay: Uint128,
+            },
+            Callback(rujira_rs::CallbackMsg),
+        }
+
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+
+        // Takes a flash loan of `amount`, and repays `repay` of it in the callback
+        let code = ContractWrapper::new(
+            |_, _, info, msg: ReceiverMsg| -> StdResult<Response> {
+                match msg {
+                    ReceiverMsg::Flash {
+                        vault,
+                        amount,
+                        repay,
+                    } => Ok(Response::default().add_message(WasmMsg::Execute {
+                        contract_addr: vault,
+                        msg: to_json_binary(&ExecuteMsg::Market(MarketMsg::FlashLoan {
+                            denom: "btc".to_string(),
+                            amount,
+                            callback: rujira_rs::CallbackData::Msg(to_json_binary(&repay)?),
+                        }))?,
+                        funds: vec![],
+                    })),
+                    ReceiverMsg::Callback(cb) => {
+                        let repay: Uint128 = cb.deserialize_callback()?;
+                        if repay.is_zero() {
+                            return Ok(Response::default());
+                        }
+                        Ok(Response::default().add_message(WasmMsg::Execute {
+                            contract_addr: info.sender.to_string(),
+                            msg: to_json_binary(&ExecuteMsg::Market(MarketMsg::FlashRepay {}))?,
+                            funds: coins(repay.u128(), "btc"),
+                        }))
+                    }
+                }
+            },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::default()) },
+            |_, _, _: Empty| -> StdResult<Binary> { Ok(Binary::default()) },
+        );
+        let code_id = app.store_code(Box::new(code));
+        let receiver = app
+            .instantiate_contract(code_id, owner.clone(), &Empty {}, &[], "receiver", None)
+            .unwrap();
+        let flash = |amount: u128, repay: u128| ReceiverMsg::Flash {
+            vault: vault.addr().to_string(),
+            amount: Uint128::from(amount),
+            repay: Uint128::from(repay),
+        };
+
+        // Only whitelisted borrowers can take a flash loan
+        let err = app
+            .execute_contract(owner.clone(), receiver.clone(), &flash(100, 100), &[])
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("UnauthorizedBorrower"));
+
+        // The flash loan doesn't count towards the borrower limit
+        vault
+            .set_borrower(&mut app, receiver.as_str(), Uint128::from(1u128))
+            .unwrap();
+
+        let err = app
+            .execute_contract(owner.clone(), receiver.clone(), &flash(1_001, 1_001), &[])
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("InsufficientLiquidity"));
+
+        let err = app
+            .execute_contract(owner.clone(), receiver.clone(), &flash(1_000, 0), &[])
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("FlashLoanOutstanding"));
+
+        let err = app
+            .execute_contract(owner.clone(), receiver.clone(), &flash(1_000, 999), &[])
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("InsufficientFlashRepay"));
+
+        let res = app
+            .execute_contract(owner.clone(), receiver.clone(), &flash(1_000, 1_000), &[])
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/flash_repay").add_attributes(vec![
+                ("borrower", receiver.as_str()),
+                ("denom", "btc"),
+                ("amount", "1000"),
+            ]),
+        );
+        assert_eq!(
+            app.wrap()
+                .query_balance(vault.addr(), "btc")
+                .unwrap()
+                .amount,
+            Uint128::from(1_000u128)
+        );
+
+        // The check can only be dispatched by the vault
+        app.execute_contract(
+            owner.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::CheckFlashLoan {
+                denom: "btc".to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn borrowers_page() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        for name in ["borrower1", "borrower2", "borrower3"] {
+            let addr = app.api().addr_make(name);
+            vault
+                .set_borrower(&mut app, addr.as_str(), Uint128::from(100u128))
+                .unwrap();
+        }
+
+        let query = |app: &RujiraApp, page: PageRequest<String>| -> BorrowersResponse {
+            app.wrap()
+                .query_wasm_smart(
+                    vault.addr(),
+                    &QueryMsg::Borrowers {
+                        denom: "btc".to_string(),
+                        page,
+                    },
+                )
+                .unwrap()
+        };
+
+        let all = query(&app, PageRequest::default());
+        assert_eq!(all.borrowers.len(), 3);
+        assert_eq!(all.page.next, None);
+
+        let first = query(&app, PageRequest::new(None, Some(2)));
+        assert_eq!(first.borrowers, all.borrowers[..2].to_vec());
+        assert_eq!(first.page.next, Some(all.borrowers[1].addr.clone()));
+
+        let second = query(&app, PageRequest::new(first.page.next, Some(2)));
+        assert_eq!(second.borrowers, all.borrowers[2..].to_vec());
+        assert_eq!(second.page.next, None);
+    }
+
+    #[test]
+    fn accrue() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let borrower = app.api().addr_make("borrower");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        vault
+            .set_borrower(&mut app, borrower.as_str(), Uint128::from(800u128))
+            .unwrap();
+        app.execute_contract(
+            borrower.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::Market(MarketMsg::Borrow {
+                denom: "btc".to_string(),
+                amount: Uint128::from(800u128),
+                callback: None,
+                delegate: None,
+            }),
+            &[],
+        )
+        .unwrap();
+        let debt = vault.query_status(&app).unwrap().debt_pool.size;
+
+        // The crank distributes the interest accrued up to the current block
+        app.update_block(|b| b.time = b.time.plus_seconds(31_536_000));
+        app.execute_contract(
+            owner.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::Accrue {},
+            &coins(1, "btc"),
+        )
+        .unwrap_err();
+        let res = app
+            .execute_contract(
+                borrower.clone(),
+                vault.addr().clone(),
+                &ExecuteMsg::Accrue {},
+                &[],
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/accrue").add_attributes(vec![
+                ("denom", "btc"),
+                ("last_updated", app.block_info().time.to_string().as_str()),
+            ]),
+        );
+        let status = vault.query_status(&app).unwrap();
+        assert_eq!(status.last_updated, app.block_info().time);
+        assert!(status.debt_pool.size > debt);
+    }
+
+    #[test]
+    fn rate_history() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let borrower = app.api().addr_make("borrower");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        let start = app.block_info().time;
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        vault
+            .set_borrower(&mut app, borrower.as_str(), Uint128::from(800u128))
+            .unwrap();
+        app.execute_contract(
+            borrower.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::Market(MarketMsg::Borrow {
+                denom: "btc".to_string(),
+                amount: Uint128::from(800u128),
+                callback: None,
+                delegate: None,
+            }),
+            &[],
+        )
+        .unwrap();
+
+        app.update_block(|b| b.time = b.time.plus_days(1));
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        app.update_block(|b| b.time = b.time.plus_days(1));
+        app.execute_contract(
+            borrower.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::Accrue {},
+            &[],
+        )
+        .unwrap();
+
+        let history = |app: &RujiraApp, limit: Option<u16>| -> Vec<RateObservation> {
+            let res: RateHistoryResponse = app
+                .wrap()
+                .query_wasm_smart(
+                    vault.addr(),
+                    &QueryMsg::RateHistory {
+                        denom: "btc".to_string(),
+                        limit,
+                    },
+                )
+                .unwrap();
+            res.observations
+        };
+
+        // One observation per accrual, newest first
+        let observations = history(&app, None);
+        assert_eq!(
+            observations.iter().map(|x| x.timestamp).collect::<Vec<_>>(),
+            vec![start.plus_days(2), start.plus_days(1), start]
+        );
+        assert_eq!(observations[2].utilization, Decimal::zero());
+        assert!(observations[1].utilization > observations[0].utilization);
+        assert!(observations[1].debt_rate > observations[0].debt_rate);
+        assert_eq!(
+            observations[0].lend_rate,
+            observations[0].debt_rate * observations[0].utilization
+        );
+        assert_eq!(history(&app, Some(1)), observations[..1].to_vec());
+
+        // Only the most recent observations are retained
+        for _ in 0..history::MAX_OBSERVATIONS {
+            app.update_block(|b| b.time = b.time.plus_seconds(60));
+            app.execute_contract(
+                borrower.clone(),
+                vault.addr().clone(),
+                &ExecuteMsg::Accrue {},
+                &[],
+            )
+            .unwrap();
+        }
+        let observations = history(&app, None);
+        assert_eq!(observations.len() as u64, history::MAX_OBSERVATIONS);
+        assert_eq!(observations[0].timestamp, app.block_info().time);
+    }
+
+    #[test]
+    fn rate_curve() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        let curve = |app: &RujiraApp, points: u16, interest: Option<Interest>| {
+            app.wrap()
+                .query_wasm_smart::<RateCurveResponse>(
+                    vault.addr(),
+                    &QueryMsg::RateCurve {
+                        denom: "btc".to_string(),
+                        points,
+                        interest,
+                    },
+                )
+                .map(|x| x.points)
+        };
+        let rates = |points: Vec<RatePoint>| {
+            points
+                .iter()
+                .map(|x| {
+                    (
+                        x.utilization.to_string(),
+                        x.debt_rate.to_string(),
+                        x.lend_rate.to_string(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let strings = |x: &[(&str, &str, &str)]| {
+            x.iter()
+                .map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        // The default model kinks at 80% utilization
+        assert_eq!(
+            rates(curve(&app, 5, None).unwrap()),
+            strings(&[
+                ("0", "0", "0"),
+                ("0.25", "0.3125", "0.078125"),
+                ("0.5", "0.625", "0.3125"),
+                ("0.75", "0.9375", "0.703125"),
+                ("1", "3", "3"),
+            ])
+        );
+        assert_eq!(curve(&app, 0, None).unwrap().len(), 2);
+        assert_eq!(curve(&app, 1_000, None).unwrap().len(), 101);
+
+        // A proposed model is previewed without being applied
+        let proposed = Interest {
+            step2: Decimal::from_ratio(3u128, 1u128),
+            ..Interest::default()
+        };
+        let points = curve(&app, 2, Some(proposed.clone())).unwrap();
+        assert_eq!(points[1].debt_rate, Decimal::from_ratio(4u128, 1u128));
+        curve(
+            &app,
+            2,
+            Some(Interest {
+                step2: Decimal::zero(),
+                ..proposed
+            }),
+        )
+        .unwrap_err();
+        let points = curve(&app, 2, None).unwrap();
+        assert_eq!(points[1].debt_rate, Decimal::from_ratio(3u128, 1u128));
+    }
+
+    #[test]
+    fn project_rates() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, vec![coin(1_000, "btc")])
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        let project = |app: &RujiraApp, borrow_delta: i128, deposit_delta: i128| {
+            app.wrap().query_wasm_smart::<ProjectRatesResponse>(
+                vault.addr(),
+                &QueryMsg::ProjectRates {
+                    denom: "btc".to_string(),
+                    borrow_delta: Int128::new(borrow_delta),
+                    deposit_delta: Int128::new(deposit_delta),
+                },
+            )
+        };
+
+        let res = project(&app, 500, 0).unwrap();
+        assert_eq!(res.current.utilization, Decimal::zero());
+        assert_eq!(res.current.debt_rate, Decimal::zero());
+        assert_eq!(res.projected.utilization, Decimal::percent(50));
+        assert_eq!(res.projected.debt_rate, Decimal::from_str("0.625").unwrap());
+        assert_eq!(
+            res.projected.lend_rate,
+            Decimal::from_str("0.3125").unwrap()
+        );
+
+        // A withdrawal alongside the borrow
+        let res = project(&app, 500, -500).unwrap();
+        assert_eq!(res.projected.utilization, Decimal::one());
+        assert_eq!(res.projected.debt_rate, Decimal::from_ratio(3u128, 1u128));
+
+        // Debt can't exceed deposits, or either go below zero
+        project(&app, 1_001, 0).unwrap_err();
+        project(&app, 500, -501).unwrap_err();
+        project(&app, -1, 0).unwrap_err();
+        project(&app, 0, -1_001).unwrap_err();
+    }
+
+    #[test]
+    fn multi_asset() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let borrower = app.api().addr_make("borrower");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &owner,
+                    vec![coin(1_000_000, "btc"), coin(1_000_000, "eth")],
+                )
+                .unwrap();
+        });
+        let btc = GhostVault::create(&mut app, &owner, "btc");
+        btc.add_pool(&mut app, &owner, "btc").unwrap_err();
+        btc.add_pool(&mut app, &owner, "eth").unwrap();
+        let eth = btc.pool("eth");
+
+        let pools: PoolsResponse = app
+            .wrap()
+            .query_wasm_smart(btc.addr(), &QueryMsg::Pools {})
+            .unwrap();
+        assert_eq!(pools.denoms, vec!["btc".to_string(), "eth".to_string()]);
+
+        btc.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        let res = eth.deposit(&mut app, &owner, 2_000, "eth").unwrap();
+        res.assert_event(&Event::new("mint").add_attributes(vec![
+            ("amount", "2000"),
+            ("denom", "x/ghost-vault/eth"),
+            ("recipient", owner.as_str()),
+        ]));
+
+        // Borrowers and debt are isolated to each pool
+        eth.set_borrower(&mut app, borrower.as_str(), Uint128::from(500u128))
+            .unwrap();
+        btc.query_borrower(&app, borrower.as_str()).unwrap_err();
+        let borrow = |denom: &str| {
+            ExecuteMsg::Market(MarketMsg::Borrow {
+                denom: denom.to_string(),
+                amount: Uint128::from(500u128),
+                callback: None,
+                delegate: None,
+            })
+        };
+        app.execute_contract(borrower.clone(), btc.addr().clone(), &borrow("btc"), &[])
+            .unwrap_err();
+        app.execute_contract(borrower.clone(), eth.addr().clone(), &borrow("eth"), &[])
+            .unwrap();
+
+        let status = btc.query_status(&app).unwrap();
+        assert_eq!(status.deposit_pool.size, Uint128::from(1_000u128));
+        assert_eq!(status.debt_pool.size, Uint128::zero());
+        let status = eth.query_status(&app).unwrap();
+        assert_eq!(status.deposit_pool.size, Uint128::from(2_000u128));
+        assert_eq!(status.debt_pool.size, Uint128::from(500u128));
+
+        // Receipts are redeemed from the pool that issued them
+        let res = btc
+            .withdraw(&mut app, &owner, Uint128::from(1_000u128))
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/withdraw").add_attributes(vec![
+                ("amount", "1000"),
+                ("denom", "btc"),
+                ("owner", owner.as_str()),
+            ]),
+        );
+        assert_eq!(
+            eth.query_status(&app).unwrap().deposit_pool.size,
+            Uint128::from(2_000u128)
+        );
+    }
+
+    #[test]
+    fn term_shortfall() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let term = app.api().addr_make("term");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &term, coins(1_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::SetTerms {
+                denom: "btc".to_string(),
+                terms: vec![TermRate {
+                    duration: 31_536_000,
+                    rate: Decimal::percent(10),
+                }],
+                early_exit_fee: Decimal::zero(),
+            },
+        )
+        .unwrap();
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        app.execute_contract(
+            term.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::DepositTerm {
+                duration: 31_536_000,
+            },
+            &coins(1_000, "btc"),
+        )
+        .unwrap();
+
+        // Nothing is borrowed, so the floating rate is below the fixed rate for the whole term
+        app.update_block(|b| b.time = b.time.plus_seconds(31_536_000));
+        app.execute_contract(
+            term.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::WithdrawTerm {
+                id: 0,
+                callback: None,
+            },
+            &[],
+        )
+        .unwrap()
+        .assert_event(
+            &Event::new("wasm-rujira-ghost-vault/withdraw_term")
+                .add_attributes(vec![("amount", "1100"), ("penalty", "0")]),
+        );
+
+        // The fixed interest is funded by floating depositors, and reported
+        let status = vault.query_status(&app).unwrap();
+        assert_eq!(status.term_shortfall, Uint128::from(100u128));
+        assert_eq!(status.deposit_pool.size, Uint128::from(900u128));
+        assert_eq!(status.deposit_pool.shares, Uint128::from(1_000u128));
+        assert_eq!(
+            app.wrap().query_balance(&term, "btc").unwrap().amount,
+            Uint128::from(1_100u128)
+        );
+    }
+
+    #[test]
+    fn backstop() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let provider = app.api().addr_make("provider");
+        let borrower = app.api().addr_make("borrower");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &provider, coins(1_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        vault
+            .set_borrower(&mut app, borrower.as_str(), Uint128::from(500u128))
+            .unwrap();
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::SetBackstop {
+                denom: "btc".to_string(),
+                interest_share: Decimal::percent(20),
+                unbonding: 86_400,
+            },
+        )
+        .unwrap();
+
+        let backstop_deposit = |app: &mut RujiraApp, amount: u128| {
+            app.execute_contract(
+                provider.clone(),
+                vault.addr().clone(),
+                &ExecuteMsg::BackstopDeposit {},
+                &coins(amount, "btc"),
+            )
+            .unwrap();
+        };
+        let borrow = |app: &mut RujiraApp, amount: u128| {
+            app.execute_contract(
+                borrower.clone(),
+                vault.addr().clone(),
+                &ExecuteMsg::Market(MarketMsg::Borrow {
+                    denom: "btc".to_string(),
+                    amount: Uint128::from(amount),
+                    callback: None,
+                    delegate: None,
+                }),
+                &[],
+            )
+            .unwrap();
+        };
+        let write_off = SudoMsg::WriteOff {
+            denom: "btc".to_string(),
+            borrower: borrower.to_string(),
+            delegate: None,
+        };
+        let position = |app: &RujiraApp| -> BackstopResponse {
+            app.wrap()
+                .query_wasm_smart(
+                    vault.addr(),
+                    &QueryMsg::Backstop {
+                        denom: "btc".to_string(),
+                        addr: provider.to_string(),
+                    },
+                )
+                .unwrap()
+        };
+
+        backstop_deposit(&mut app, 100);
+        borrow(&mut app, 300);
+
+        // The backstop is exhausted first, and depositors take the remainder
+        let res = app.wasm_sudo(vault.addr().clone(), &write_off).unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/write_off").add_attributes(vec![
+                ("borrower", borrower.as_str()),
+                ("backstop", "100"),
+                ("deposits", "200"),
+            ]),
+        );
+        let status = vault.query_status(&app).unwrap();
+        assert_eq!(status.debt_pool.size, Uint128::zero());
+        assert_eq!(status.deposit_pool.size, Uint128::from(800u128));
+        assert_eq!(status.backstop_pool.size, Uint128::zero());
+        assert_eq!(status.written_off_backstop, Uint128::from(100u128));
+        assert_eq!(status.written_off_deposits, Uint128::from(200u128));
+        // The loss is realised, so the remaining deposits are fully backed
+        assert_eq!(status.solvency_ratio, Decimal::one());
+        assert!(!status.insolvent);
+        assert_eq!(
+            vault
+                .query_borrower(&app, borrower.as_str())
+                .unwrap()
+                .shares,
+            Uint128::zero()
+        );
+        assert_eq!(position(&app).shares, Uint128::zero());
+
+        // A partial loss is taken from the backstop alone
+        backstop_deposit(&mut app, 100);
+        borrow(&mut app, 40);
+        let res = app.wasm_sudo(vault.addr().clone(), &write_off).unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/write_off")
+                .add_attributes(vec![("backstop", "40"), ("deposits", "0")]),
+        );
+        let res = position(&app);
+        assert_eq!(res.shares, Uint128::from(100u128));
+        assert_eq!(res.value, Uint128::from(60u128));
+        let status = vault.query_status(&app).unwrap();
+        assert_eq!(status.written_off_backstop, Uint128::from(140u128));
+        assert_eq!(status.written_off_deposits, Uint128::from(200u128));
+
+        let unbond = |app: &mut RujiraApp, shares: u128| {
+            app.execute_contract(
+                provider.clone(),
+                vault.addr().clone(),
+                &ExecuteMsg::BackstopUnbond {
+                    denom: "btc".to_string(),
+                    shares: Uint128::from(shares),
+                },
+                &[],
+            )
+        };
+        let withdraw = |app: &mut RujiraApp| {
+            app.execute_contract(
+                provider.clone(),
+                vault.addr().clone(),
+                &ExecuteMsg::BackstopWithdraw {
+                    denom: "btc".to_string(),
+                    callback: None,
+                },
+                &[],
+            )
+        };
+        unbond(&mut app, 101).unwrap_err();
+        unbond(&mut app, 100).unwrap();
+        let err = withdraw(&mut app).unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("BackstopUnbonding"));
+
+        app.update_block(|b| b.time = b.time.plus_seconds(86_400));
+        withdraw(&mut app).unwrap();
+        assert_eq!(
+            app.wrap().query_balance(&provider, "btc").unwrap().amount,
+            Uint128::from(860u128)
+        );
+        assert_eq!(position(&app).unbonding, Uint128::zero());
+        withdraw(&mut app).unwrap_err();
+    }
+
+    #[test]
+    fn transfer_debt() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let borrower = app.api().addr_make("borrower");
+        let account1 = app.api().addr_make("account1");
+        let account2 = app.api().addr_make("account2");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        vault
+            .set_borrower(&mut app, borrower.as_str(), Uint128::from(500u128))
+            .unwrap();
+        app.execute_contract(
+            borrower.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::Market(MarketMsg::Borrow {
+                denom: "btc".to_string(),
+                amount: Uint128::from(300u128),
+                callback: None,
+                delegate: Some(account1.to_string()),
+            }),
+            &[],
+        )
+        .unwrap();
+
+        let transfer = |shares: u128| {
+            ExecuteMsg::Market(MarketMsg::TransferDebt {
+                denom: "btc".to_string(),
+                from_delegate: account1.to_string(),
+                to_delegate: account2.to_string(),
+                shares: Uint128::from(shares),
+            })
+        };
+        let delegate = |app: &RujiraApp, addr: &Addr| -> DelegateResponse {
+            app.wrap()
+                .query_wasm_smart(
+                    vault.addr(),
+                    &QueryMsg::Delegate {
+                        denom: "btc".to_string(),
+                        borrower: borrower.to_string(),
+                        addr: addr.to_string(),
+                    },
+                )
+                .unwrap()
+        };
+
+        // Only whitelisted borrowers can move their delegates' debt
+        app.execute_contract(owner.clone(), vault.addr().clone(), &transfer(100), &[])
+            .unwrap_err();
+        app.execute_contract(borrower.clone(), vault.addr().clone(), &transfer(301), &[])
+            .unwrap_err();
+
+        let status = vault.query_status(&app).unwrap();
+        let res = app
+            .execute_contract(borrower.clone(), vault.addr().clone(), &transfer(100), &[])
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/transfer_debt").add_attributes(vec![
+                ("borrower", borrower.as_str()),
+                ("denom", "btc"),
+                ("from_delegate", account1.as_str()),
+                ("to_delegate", account2.as_str()),
+                ("shares", "100"),
+            ]),
+        );
+
+        assert_eq!(delegate(&app, &account1).shares, Uint128::from(200u128));
+        assert_eq!(delegate(&app, &account2).shares, Uint128::from(100u128));
+        // Vault side accounting is unchanged
+        assert_eq!(vault.query_status(&app).unwrap(), status);
+        assert_eq!(
+            vault
+                .query_borrower(&app, borrower.as_str())
+                .unwrap()
+                .shares,
+            Uint128::from(300u128)
+        );
+    }
+
+    #[test]
+    fn allowance() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let borrower = app.api().addr_make("borrower");
+        let spender = app.api().addr_make("spender");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        vault
+            .set_borrower(&mut app, borrower.as_str(), Uint128::from(500u128))
+            .unwrap();
+
+        let set_allowance = |amount: u128| {
+            ExecuteMsg::Market(MarketMsg::SetAllowance {
+                denom: "btc".to_string(),
+                spender: spender.to_string(),
+                amount: Uint128::from(amount),
+            })
+        };
+        let borrow = |amount: u128| ExecuteMsg::BorrowAllowance {
+            denom: "btc".to_string(),
+            borrower: borrower.to_string(),
+            amount: Uint128::from(amount),
+            callback: None,
+        };
+        let allowance = |app: &RujiraApp| -> AllowanceResponse {
+            app.wrap()
+                .query_wasm_smart(
+                    vault.addr(),
+                    &QueryMsg::Allowance {
+                        denom: "btc".to_string(),
+                        borrower: borrower.to_string(),
+                        spender: spender.to_string(),
+                    },
+                )
+                .unwrap()
+        };
+
+        let err = app
+            .execute_contract(spender.clone(), vault.addr().clone(), &borrow(100), &[])
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("AllowanceExceeded"));
+
+        // Only whitelisted borrowers can grant an allowance
+        let err = app
+            .execute_contract(
+                owner.clone(),
+                vault.addr().clone(),
+                &set_allowance(200),
+                &[],
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("UnauthorizedBorrower"));
+
+        let res = app
+            .execute_contract(
+                borrower.clone(),
+                vault.addr().clone(),
+                &set_allowance(200),
+                &[],
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/set_allowance").add_attributes(vec![
+                ("borrower", borrower.as_str()),
+                ("denom", "btc"),
+                ("spender", spender.as_str()),
+                ("amount", "200"),
+            ]),
+        );
+
+        let res = app
+            .execute_contract(spender.clone(), vault.addr().clone(), &borrow(150), &[])
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/borrow").add_attributes(vec![
+                ("borrower", borrower.as_str()),
+                ("denom", "btc"),
+                ("delegate", spender.as_str()),
+                ("amount", "150"),
+            ]),
+        );
+        assert_eq!(
+            app.wrap().query_balance(&spender, "btc").unwrap().amount,
+            Uint128::from(150u128)
+        );
+        // The debt is owed by the borrower
+        assert_eq!(
+            vault
+                .query_borrower(&app, borrower.as_str())
+                .unwrap()
+                .current,
+            Uint128::from(150u128)
+        );
+        let res = allowance(&app);
+        assert_eq!(res.allowance, Uint128::from(200u128));
+        assert_eq!(res.drawn, Uint128::from(150u128));
+
+        let err = app
+            .execute_contract(spender.clone(), vault.addr().clone(), &borrow(51), &[])
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("AllowanceExceeded"));
+
+        // Repaying frees the allowance to be drawn again
+        app.execute_contract(
+            spender.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::RepayAllowance {
+                borrower: borrower.to_string(),
+            },
+            &coins(100, "btc"),
+        )
+        .unwrap();
+        assert_eq!(allowance(&app).drawn, Uint128::from(50u128));
+        app.execute_contract(spender.clone(), vault.addr().clone(), &borrow(150), &[])
+            .unwrap();
+
+        // Revoking leaves the outstanding debt in place
+        app.execute_contract(
+            borrower.clone(),
+            vault.addr().clone(),
+            &set_allowance(0),
+            &[],
+        )
+        .unwrap();
+        let res = allowance(&app);
+        assert_eq!(res.allowance, Uint128::zero());
+        assert_eq!(res.drawn, Uint128::from(200u128));
+        app.execute_contract(spender.clone(), vault.addr().clone(), &borrow(1), &[])
+            .unwrap_err();
+    }
+
+    #[test]
+    fn max_concentration() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let borrower = app.api().addr_make("borrower");
+        let other = app.api().addr_make("other");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        vault
+            .set_borrower(&mut app, borrower.as_str(), Uint128::MAX)
+            .unwrap();
+        vault
+            .set_borrower(&mut app, other.as_str(), Uint128::MAX)
+            .unwrap();
+
+        let set = |app: &mut RujiraApp, max: Option<Decimal>| {
+            app.wasm_sudo(
+                vault.addr().clone(),
+                &SudoMsg::SetMaxConcentration {
+                    denom: "btc".to_string(),
+                    max,
+                },
+            )
+        };
+        set(&mut app, Some(Decimal::percent(101))).unwrap_err();
+        set(&mut app, Some(Decimal::zero())).unwrap_err();
+        set(&mut app, Some(Decimal::percent(40))).unwrap();
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(
+                vault.addr(),
+                &QueryMsg::Config {
+                    denom: "btc".to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(config.max_concentration, Some(Decimal::percent(40)));
+
+        let borrow = |app: &mut RujiraApp, sender: &Addr, amount: u128| {
+            app.execute_contract(
+                sender.clone(),
+                vault.addr().clone(),
+                &ExecuteMsg::Market(MarketMsg::Borrow {
+                    denom: "btc".to_string(),
+                    amount: Uint128::from(amount),
+                    callback: None,
+                    delegate: None,
+                }),
+                &[],
+            )
+        };
+        borrow(&mut app, &borrower, 300).unwrap();
+        let res = vault.query_borrower(&app, borrower.as_str()).unwrap();
+        assert_eq!(res.concentration, Decimal::percent(30));
+        assert_eq!(res.available, Uint128::from(100u128));
+
+        // The cap applies to the borrower's resulting debt
+        let err = borrow(&mut app, &borrower, 101).unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            "ConcentrationLimitReached 0.4"
+        );
+        borrow(&mut app, &borrower, 100).unwrap();
+        // Each borrower is capped separately
+        borrow(&mut app, &other, 400).unwrap();
+
+        set(&mut app, None).unwrap();
+        let res = vault.query_borrower(&app, borrower.as_str()).unwrap();
+        assert_eq!(res.concentration, Decimal::percent(40));
+        assert_eq!(res.available, Uint128::from(200u128));
+        borrow(&mut app, &borrower, 200).unwrap();
+    }
+
+    #[test]
+    fn same_block_guard() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let other = app.api().addr_make("other");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+
+        // Disabled by default
+        vault.deposit(&mut app, &owner, 10_000, "btc").unwrap();
+        vault
+            .withdraw(&mut app, &owner, Uint128::from(1_000u128))
+            .unwrap();
+
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::SetSameBlockGuard {
+                denom: "btc".to_string(),
+                enabled: true,
+            },
+        )
+        .unwrap();
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(
+                vault.addr(),
+                &QueryMsg::Config {
+                    denom: "btc".to_string(),
+                },
+            )
+            .unwrap();
+        assert!(config.same_block_guard);
+
+        let height = app.block_info().height;
+        vault.deposit(&mut app, &owner, 10_000, "btc").unwrap();
+        let err = vault
+            .withdraw(&mut app, &owner, Uint128::from(1_000u128))
+            .unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            format!("SameBlockWithdrawal deposited at height {height}")
+        );
+
+        // Receipt tokens transferred to another address can't be withdrawn in the block either
+        app.send_tokens(
+            owner.clone(),
+            other.clone(),
+            &coins(1_000, "x/ghost-vault/btc"),
+        )
+        .unwrap();
+        let err = vault
+            .withdraw(&mut app, &other, Uint128::from(1_000u128))
+            .unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            format!("SameBlockWithdrawal deposited at height {height}")
+        );
+
+        app.update_block(|b| b.height += 1);
+        vault
+            .withdraw(&mut app, &owner, Uint128::from(1_000u128))
+            .unwrap();
+        vault
+            .withdraw(&mut app, &other, Uint128::from(1_000u128))
+            .unwrap();
+    }
+
+    #[test]
+    fn config_timelock() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let fees = app.api().addr_make("fees");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        let denom = "btc".to_string();
+        let query_pending = |app: &RujiraApp| -> Option<PendingConfigResponse> {
+            app.wrap()
+                .query_wasm_smart(
+                    vault.addr(),
+                    &QueryMsg::PendingConfig {
+                        denom: "btc".to_string(),
+                    },
+                )
+                .unwrap()
+        };
+        let query_config = |app: &RujiraApp| -> ConfigResponse {
+            app.wrap()
+                .query_wasm_smart(
+                    vault.addr(),
+                    &QueryMsg::Config {
+                        denom: "btc".to_string(),
+                    },
+                )
+                .unwrap()
+        };
+
+        // Without a timelock, a proposal can be applied immediately
+        let change = ConfigChange {
+            timelock: Some(86_400),
+            ..ConfigChange::default()
+        };
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::ProposeConfig {
+                denom: denom.clone(),
+                change,
+            },
+        )
+        .unwrap();
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::ApplyConfig {
+                denom: denom.clone(),
+            },
+        )
+        .unwrap();
+        assert_eq!(query_config(&app).timelock, 86_400);
+        assert_eq!(query_pending(&app), None);
+
+        let interest = Interest {
+            target_utilization: Decimal::percent(90),
+            base_rate: Decimal::percent(1),
+            step1: Decimal::percent(10),
+            step2: Decimal::percent(200),
+            min_lend_rate: None,
+            max_debt_rate: None,
+        };
+        let err = app
+            .wasm_sudo(
+                vault.addr().clone(),
+                &SudoMsg::SetInterest {
+                    denom: denom.clone(),
+                    interest: interest.clone(),
+                },
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Timelocked"));
+        let terms = vec![TermRate {
+            duration: 86_400,
+            rate: Decimal::percent(5),
+        }];
+        let err = app
+            .wasm_sudo(
+                vault.addr().clone(),
+                &SudoMsg::SetTerms {
+                    denom: denom.clone(),
+                    terms: terms.clone(),
+                    early_exit_fee: Decimal::percent(1),
+                },
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Timelocked"));
+
+        // Invalid changes are rejected at proposal
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::ProposeConfig {
+                denom: denom.clone(),
+                change: ConfigChange {
+                    fee: Some(Decimal::one()),
+                    ..ConfigChange::default()
+                },
+            },
+        )
+        .unwrap_err();
+
+        let change = ConfigChange {
+            fee: Some(Decimal::percent(20)),
+            fee_address: Some(fees.to_string()),
+            interest: Some(interest.clone()),
+            terms: Some(terms.clone()),
+            early_exit_fee: Some(Decimal::percent(1)),
+            timelock: None,
+        };
+        let res = app
+            .wasm_sudo(
+                vault.addr().clone(),
+                &SudoMsg::ProposeConfig {
+                    denom: denom.clone(),
+                    change: change.clone(),
+                },
+            )
+            .unwrap();
+        let executable_at = app.block_info().time.plus_seconds(86_400);
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/config.propose").add_attributes(vec![
+                ("denom", "btc"),
+                ("executable_at", executable_at.to_string().as_str()),
+            ]),
+        );
+        assert_eq!(
+            query_pending(&app),
+            Some(PendingConfigResponse {
+                change: change.clone(),
+                executable_at,
+            })
+        );
+
+        app.update_block(|b| b.time = b.time.plus_seconds(86_399));
+        let err = app
+            .wasm_sudo(
+                vault.addr().clone(),
+                &SudoMsg::ApplyConfig {
+                    denom: denom.clone(),
+                },
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Timelocked"));
+
+        // A cancelled change can't be applied
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::CancelConfig {
+                denom: denom.clone(),
+            },
+        )
+        .unwrap();
+        assert_eq!(query_pending(&app), None);
+        app.update_block(|b| b.time = b.time.plus_seconds(1));
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::ApplyConfig {
+                denom: denom.clone(),
+            },
+        )
+        .unwrap_err();
+
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::ProposeConfig {
+                denom: denom.clone(),
+                change,
+            },
+        )
+        .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(86_400));
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &SudoMsg::ApplyConfig {
+                denom: denom.clone(),
+            },
+        )
+        .unwrap();
+        let config = query_config(&app);
+        assert_eq!(config.fee, Decimal::percent(20));
+        assert_eq!(config.interest, interest);
+        assert_eq!(query_pending(&app), None);
+        let res: TermsResponse = app
+            .wrap()
+            .query_wasm_smart(
+                vault.addr(),
+                &QueryMsg::Terms {
+                    denom: denom.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(res.terms, terms);
+        assert_eq!(res.early_exit_fee, Decimal::percent(1));
+    }
+
+    #[test]
+    fn idle_strategy() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let borrower = app.api().addr_make("borrower");
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &owner, coins(1_000_000, "btc"))
+                .unwrap();
+        });
+        let vault = GhostVault::create(&mut app, &owner, "btc");
+        vault.deposit(&mut app, &owner, 1_000, "btc").unwrap();
+        vault
+            .set_borrower(&mut app, borrower.as_str(), Uint128::from(1_000u128))
+            .unwrap();
+
+        // Holds deposits, and reports its balance as the value of the vault's deposits
+        let code = ContractWrapper::new(
+            |_, _, info, msg: IdleStrategyMsg| -> StdResult<Response> {
+                match msg {
+                    IdleStrategyMsg::Deposit {} => Ok(Response::default()),
+                    IdleStrategyMsg::Withdraw { amount } => {
+                        Ok(Response::default().add_message(BankMsg::Send {
+                            to_address: info.sender.to_string(),
+                            amount: coins(amount.u128(), "btc"),
+                        }))
+                    }
+                }
+            },
+            |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::default()) },
+            |deps, env, _: IdleStrategyQueryMsg| -> StdResult<Binary> {
+                to_json_binary(&IdleBalanceResponse {
+                    amount: deps
+                        .querier
+                        .query_balance(env.contract.address, "btc")?
+                        .amount,
+                })
+            },
+        );
+        let code_id = app.store_code(Box::new(code));
+        let strategy = app
+            .instantiate_contract(code_id, owner.clone(), &Empty {}, &[], "strategy", None)
+            .unwrap();
+
+        let set = |contract: Option<&Addr>, max_share: Decimal| SudoMsg::SetIdleStrategy {
+            denom: "btc".to_string(),
+            contract: contract.map(|x| x.to_string()),
+            max_share,
+        };
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &set(Some(&strategy), Decimal::percent(101)),
+        )
+        .unwrap_err();
+        app.wasm_sudo(
+            vault.addr().clone(),
+            &set(Some(&strategy), Decimal::percent(50)),
+        )
+        .unwrap();
+
+        let rebalance = ExecuteMsg::Rebalance {
+            denom: "btc".to_string(),
+        };
+        let res = app
+            .execute_contract(owner.clone(), vault.addr().clone(), &rebalance, &[])
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/rebalance").add_attributes(vec![
+                ("denom", "btc"),
+                ("yield", "0"),
+                ("deployed", "500"),
+            ]),
+        );
+        assert_eq!(
+            app.wrap().query_balance(&strategy, "btc").unwrap().amount,
+            Uint128::from(500u128)
+        );
+
+        // Yield is allocated to depositors, and the strategy trimmed back to its share
+        app.send_tokens(owner.clone(), strategy.clone(), &coins(50, "btc"))
+            .unwrap();
+        let res = app
+            .execute_contract(owner.clone(), vault.addr().clone(), &rebalance, &[])
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-ghost-vault/rebalance").add_attributes(vec![
+                ("yield", "50"),
+                ("loss", "0"),
+                ("deployed", "525"),
+            ]),
+        );
+        let status: StatusResponse = app
+            .wrap()
+            .query_wasm_smart(
+                vault.addr(),
+                &QueryMsg::Status {
+                    denom: "btc".to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(status.deposit_pool.size, Uint128::from(1_050u128));
+        assert_eq!(status.idle_deployed, Uint128::from(525u128));
+
+        // The shortfall of a borrow is recalled in the same transaction
+        app.execute_contract(
+            borrower.clone(),
+            vault.addr().clone(),
+            &ExecuteMsg::Market(MarketMsg::Borrow {
+                denom: "btc".to_string(),
+                amount: Uint128::from(800u128),
+                callback: None,
+                delegate: None,
+            }),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            app.wrap().query_balance(&borrower, "btc").unwrap().amount,
+            Uint128::from(800u128)
+        );
+        assert_eq!(
+            app.wrap().query_balance(&strategy, "btc").unwrap().amount,
+            Uint128::from(250u128)
+        );
+
+        // And of a withdrawal
+        vault
+            .withdraw(&mut app, &owner, Uint128::from(200u128))
+            .unwrap();
+        assert_eq!(
+            app.wrap().query_balance(&strategy, "btc").unwrap().amount,
+            Uint128::from(45u128)
+        );
+
+        // Removing the strategy recalls everything deployed
+        app.wasm_sudo(vault.addr().clone(), &set(None, Decimal::zero()))
+            .unwrap();
+        assert_eq!(
+            app.wrap().query_balance(&strategy, "btc").unwrap().amount,
+            Uint128::zero()
+        );
+        let status: StatusResponse = app
+            .wrap()
+            .query_wasm_smart(
+                vault.addr(),
+                &QueryMsg::Status {
+                    denom: "btc".to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(status.idle_deployed, Uint128::zero());
+        app.execute_contract(owner.clone(), vault.addr().clone(), &rebalance, &[])
+            .unwrap_err();
+    }
 }
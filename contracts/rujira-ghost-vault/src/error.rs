@@ -1,5 +1,6 @@
 use cosmwasm_std::{
-    CheckedFromRatioError, ConversionOverflowError, OverflowError, StdError, Uint128,
+    CheckedFromRatioError, ConversionOverflowError, Decimal, OverflowError, StdError, Timestamp,
+    Uint128,
 };
 use cw_utils::PaymentError;
 use rujira_rs::SharePoolError;
@@ -34,6 +35,12 @@ pub enum ContractError {
     #[error("BorrowLimitReached {limit}")]
     BorrowLimitReached { limit: Uint128 },
 
+    #[error("ConcentrationLimitReached {max}")]
+    ConcentrationLimitReached { max: Decimal },
+
+    #[error("AllowanceExceeded {allowance}")]
+    AllowanceExceeded { allowance: Uint128 },
+
     #[error("InsufficientRepay debt {debt} value {value} repaid {repaid}")]
     InsufficientRepay {
         debt: Uint128,
@@ -47,8 +54,44 @@ pub enum ContractError {
     #[error("ZeroDebt")]
     ZeroDebt {},
 
+    #[error("InvalidTerm {duration}")]
+    InvalidTerm { duration: u64 },
+
+    #[error("TermDepositNotFound {id}")]
+    TermDepositNotFound { id: u64 },
+
+    #[error("PoolNotFound {denom}")]
+    PoolNotFound { denom: String },
+
+    #[error("PoolExists {denom}")]
+    PoolExists { denom: String },
+
+    #[error("BackstopUnbonding until {release}")]
+    BackstopUnbonding { release: Timestamp },
+
+    #[error("InsufficientLiquidity available {available}")]
+    InsufficientLiquidity { available: Uint128 },
+
+    #[error("FlashLoanOutstanding {denom}")]
+    FlashLoanOutstanding { denom: String },
+
+    #[error("FlashLoanNotFound")]
+    FlashLoanNotFound {},
+
+    #[error("InsufficientFlashRepay amount {amount} repaid {repaid}")]
+    InsufficientFlashRepay { amount: Uint128, repaid: Uint128 },
+
     #[error("Invalid: {0}")]
     Invalid(String),
+
+    #[error("Timelocked until {until}")]
+    Timelocked { until: Timestamp },
+
+    #[error("NoPendingConfig")]
+    NoPendingConfig {},
+
+    #[error("SameBlockWithdrawal deposited at height {height}")]
+    SameBlockWithdrawal { height: u64 },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
@@ -1,41 +1,199 @@
-use cosmwasm_std::{Addr, Event, Uint128};
+use cosmwasm_std::{Addr, Decimal, Event, Timestamp, Uint128};
 
-pub fn event_deposit(owner: Addr, amount: Uint128, shares: Uint128) -> Event {
+pub fn event_deposit(owner: Addr, denom: &str, amount: Uint128, shares: Uint128) -> Event {
     Event::new(format!("{}/deposit", env!("CARGO_PKG_NAME")))
         .add_attribute("owner", owner)
+        .add_attribute("denom", denom)
         .add_attribute("amount", amount)
         .add_attribute("shares", shares)
 }
 
-pub fn event_withdraw(owner: Addr, amount: Uint128, shares: Uint128) -> Event {
+pub fn event_withdraw(owner: Addr, denom: &str, amount: Uint128, shares: Uint128) -> Event {
     Event::new(format!("{}/withdraw", env!("CARGO_PKG_NAME")))
         .add_attribute("owner", owner)
+        .add_attribute("denom", denom)
         .add_attribute("amount", amount)
         .add_attribute("shares", shares)
 }
 
 pub fn event_borrow(
     borrower: Addr,
+    denom: &str,
     delegate: Option<String>,
     amount: Uint128,
     shares: Uint128,
 ) -> Event {
     Event::new(format!("{}/borrow", env!("CARGO_PKG_NAME")))
         .add_attribute("borrower", borrower)
+        .add_attribute("denom", denom)
         .add_attribute("delegate", delegate.unwrap_or_default())
         .add_attribute("amount", amount)
         .add_attribute("shares", shares)
 }
 
+pub fn event_utilization_alert(
+    borrower: Addr,
+    denom: &str,
+    threshold: Decimal,
+    utilization: Decimal,
+) -> Event {
+    Event::new(format!("{}/utilization_alert", env!("CARGO_PKG_NAME")))
+        .add_attribute("borrower", borrower)
+        .add_attribute("denom", denom)
+        .add_attribute("threshold", threshold.to_string())
+        .add_attribute("utilization", utilization.to_string())
+}
+
 pub fn event_repay(
     borrower: Addr,
+    denom: &str,
     delegate: Option<String>,
     amount: Uint128,
     shares: Uint128,
 ) -> Event {
     Event::new(format!("{}/repay", env!("CARGO_PKG_NAME")))
         .add_attribute("borrower", borrower)
+        .add_attribute("denom", denom)
         .add_attribute("delegate", delegate.unwrap_or_default())
         .add_attribute("amount", amount)
         .add_attribute("shares", shares)
 }
+
+pub fn event_transfer_debt(
+    borrower: Addr,
+    denom: &str,
+    from_delegate: String,
+    to_delegate: String,
+    shares: Uint128,
+) -> Event {
+    Event::new(format!("{}/transfer_debt", env!("CARGO_PKG_NAME")))
+        .add_attribute("borrower", borrower)
+        .add_attribute("denom", denom)
+        .add_attribute("from_delegate", from_delegate)
+        .add_attribute("to_delegate", to_delegate)
+        .add_attribute("shares", shares)
+}
+
+pub fn event_set_allowance(borrower: Addr, denom: &str, spender: String, amount: Uint128) -> Event {
+    Event::new(format!("{}/set_allowance", env!("CARGO_PKG_NAME")))
+        .add_attribute("borrower", borrower)
+        .add_attribute("denom", denom)
+        .add_attribute("spender", spender)
+        .add_attribute("amount", amount)
+}
+
+pub fn event_deposit_term(
+    owner: Addr,
+    denom: &str,
+    id: u64,
+    amount: Uint128,
+    rate: Decimal,
+    maturity: Timestamp,
+) -> Event {
+    Event::new(format!("{}/deposit_term", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner)
+        .add_attribute("denom", denom)
+        .add_attribute("id", id.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("rate", rate.to_string())
+        .add_attribute("maturity", maturity.to_string())
+}
+
+pub fn event_withdraw_term(
+    owner: Addr,
+    denom: &str,
+    id: u64,
+    amount: Uint128,
+    penalty: Uint128,
+) -> Event {
+    Event::new(format!("{}/withdraw_term", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner)
+        .add_attribute("denom", denom)
+        .add_attribute("id", id.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("penalty", penalty)
+}
+
+pub fn event_backstop_deposit(owner: Addr, denom: &str, amount: Uint128, shares: Uint128) -> Event {
+    Event::new(format!("{}/backstop_deposit", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner)
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+        .add_attribute("shares", shares)
+}
+
+pub fn event_backstop_unbond(
+    owner: Addr,
+    denom: &str,
+    shares: Uint128,
+    release: Timestamp,
+) -> Event {
+    Event::new(format!("{}/backstop_unbond", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner)
+        .add_attribute("denom", denom)
+        .add_attribute("shares", shares)
+        .add_attribute("release", release.to_string())
+}
+
+pub fn event_backstop_withdraw(
+    owner: Addr,
+    denom: &str,
+    amount: Uint128,
+    shares: Uint128,
+) -> Event {
+    Event::new(format!("{}/backstop_withdraw", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", owner)
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+        .add_attribute("shares", shares)
+}
+
+pub fn event_write_off(
+    borrower: Addr,
+    denom: &str,
+    delegate: Option<String>,
+    backstop: Uint128,
+    deposits: Uint128,
+) -> Event {
+    Event::new(format!("{}/write_off", env!("CARGO_PKG_NAME")))
+        .add_attribute("borrower", borrower)
+        .add_attribute("denom", denom)
+        .add_attribute("delegate", delegate.unwrap_or_default())
+        .add_attribute("backstop", backstop)
+        .add_attribute("deposits", deposits)
+}
+
+pub fn event_flash_loan(borrower: Addr, denom: &str, amount: Uint128) -> Event {
+    Event::new(format!("{}/flash_loan", env!("CARGO_PKG_NAME")))
+        .add_attribute("borrower", borrower)
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+}
+
+pub fn event_flash_repay(borrower: Addr, denom: &str, amount: Uint128) -> Event {
+    Event::new(format!("{}/flash_repay", env!("CARGO_PKG_NAME")))
+        .add_attribute("borrower", borrower)
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+}
+
+pub fn event_accrue(denom: &str, last_updated: Timestamp, fees: Uint128) -> Event {
+    Event::new(format!("{}/accrue", env!("CARGO_PKG_NAME")))
+        .add_attribute("denom", denom)
+        .add_attribute("last_updated", last_updated.to_string())
+        .add_attribute("fees", fees)
+}
+
+pub fn event_pending_config(action: &str, denom: &str, executable_at: Timestamp) -> Event {
+    Event::new(format!("{}/config.{action}", env!("CARGO_PKG_NAME")))
+        .add_attribute("denom", denom)
+        .add_attribute("executable_at", executable_at.to_string())
+}
+
+pub fn event_rebalance(denom: &str, earned: Uint128, lost: Uint128, deployed: Uint128) -> Event {
+    Event::new(format!("{}/rebalance", env!("CARGO_PKG_NAME")))
+        .add_attribute("denom", denom)
+        .add_attribute("yield", earned)
+        .add_attribute("loss", lost)
+        .add_attribute("deployed", deployed)
+}
@@ -0,0 +1,60 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, StdResult, Storage, Uint128};
+use cw_storage_plus::Map;
+
+use crate::ContractError;
+
+// The outstanding flash loan of each pool, keyed by denom. Only one can be open at a time
+static FLASH_LOANS: Map<&str, FlashLoan> = Map::new("flash-loans");
+
+/// Funds lent to a borrower for the duration of a single callback.
+/// Flash loans are not issued from the debt pool, and accrue no interest
+#[cw_serde]
+pub struct FlashLoan {
+    pub borrower: Addr,
+    pub amount: Uint128,
+}
+
+impl FlashLoan {
+    /// Opens a flash loan on the pool of `denom`, failing if one is already outstanding
+    pub fn open(
+        storage: &mut dyn Storage,
+        denom: &str,
+        borrower: Addr,
+        amount: Uint128,
+    ) -> Result<Self, ContractError> {
+        if FLASH_LOANS.has(storage, denom) {
+            return Err(ContractError::FlashLoanOutstanding {
+                denom: denom.to_string(),
+            });
+        }
+        let loan = Self { borrower, amount };
+        FLASH_LOANS.save(storage, denom, &loan)?;
+        Ok(loan)
+    }
+
+    pub fn may_load(storage: &dyn Storage, denom: &str) -> StdResult<Option<Self>> {
+        FLASH_LOANS.may_load(storage, denom)
+    }
+
+    /// Closes the flash loan of `borrower` with `repaid`, returning the excess to be refunded
+    pub fn repay(
+        storage: &mut dyn Storage,
+        denom: &str,
+        borrower: &Addr,
+        repaid: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        let loan = Self::may_load(storage, denom)?
+            .filter(|x| x.borrower == borrower)
+            .ok_or(ContractError::FlashLoanNotFound {})?;
+        let refund =
+            repaid
+                .checked_sub(loan.amount)
+                .map_err(|_| ContractError::InsufficientFlashRepay {
+                    amount: loan.amount,
+                    repaid,
+                })?;
+        FLASH_LOANS.remove(storage, denom);
+        Ok(refund)
+    }
+}
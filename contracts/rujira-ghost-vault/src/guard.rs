@@ -0,0 +1,30 @@
+use cosmwasm_std::{Env, StdResult, Storage};
+use cw_storage_plus::Map;
+
+use crate::{config::Config, ContractError};
+
+/// The block height of the last deposit into each pool with the same-block guard enabled
+static LAST_DEPOSIT: Map<&str, u64> = Map::new("last-deposit-height");
+
+/// Records a deposit into the pool, if it guards against same-block withdrawals
+pub fn record(storage: &mut dyn Storage, env: &Env, config: &Config) -> StdResult<()> {
+    if config.same_block_guard {
+        LAST_DEPOSIT.save(storage, config.denom.as_str(), &env.block.height)?;
+    }
+    Ok(())
+}
+
+/// Rejects any withdrawal from the pool in a block that it has had a deposit in, so that deposits
+/// can't be used to move the rates around a borrow and exit straight after. The guard is kept per pool
+/// rather than per depositor, as receipt tokens can be transferred to another address to withdraw
+pub fn check(storage: &dyn Storage, env: &Env, config: &Config) -> Result<(), ContractError> {
+    if !config.same_block_guard {
+        return Ok(());
+    }
+    match LAST_DEPOSIT.may_load(storage, config.denom.as_str())? {
+        Some(height) if height == env.block.height => {
+            Err(ContractError::SameBlockWithdrawal { height })
+        }
+        _ => Ok(()),
+    }
+}
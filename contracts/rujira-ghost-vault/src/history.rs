@@ -0,0 +1,54 @@
+use cosmwasm_std::{StdResult, Storage};
+use cw_storage_plus::Map;
+use rujira_rs::ghost::vault::RateObservation;
+
+use crate::{config::Config, state::State};
+
+/// The number of observations retained for each pool, oldest overwritten first
+pub const MAX_OBSERVATIONS: u64 = 256;
+
+/// Ring buffer of rate observations, keyed by pool and slot
+static OBSERVATIONS: Map<(&str, u64), RateObservation> = Map::new("rate-observations");
+
+/// The number of observations ever recorded for each pool
+static RECORDED: Map<&str, u64> = Map::new("rate-recorded");
+
+/// Records the rates charged over the period to which `state` has been accrued, once per accrual
+pub fn record(storage: &mut dyn Storage, config: &Config, state: &State) -> StdResult<()> {
+    let denom = config.denom.as_str();
+    let recorded = RECORDED.may_load(storage, denom)?.unwrap_or_default();
+    if let Some(last) = recorded.checked_sub(1) {
+        let last = OBSERVATIONS.load(storage, (denom, last % MAX_OBSERVATIONS))?;
+        if last.timestamp >= state.last_updated {
+            return Ok(());
+        }
+    }
+    OBSERVATIONS.save(
+        storage,
+        (denom, recorded % MAX_OBSERVATIONS),
+        &RateObservation {
+            timestamp: state.last_updated,
+            utilization: state.utilization(),
+            debt_rate: state.debt_rate(&config.interest)?,
+            lend_rate: state.lend_rate(&config.interest)?,
+        },
+    )?;
+    RECORDED.save(storage, denom, &(recorded + 1))
+}
+
+/// The most recent `limit` observations of the pool for `denom`, newest first
+pub fn list(
+    storage: &dyn Storage,
+    denom: &str,
+    limit: Option<u16>,
+) -> StdResult<Vec<RateObservation>> {
+    let recorded = RECORDED.may_load(storage, denom)?.unwrap_or_default();
+    let limit = limit
+        .map(u64::from)
+        .unwrap_or(MAX_OBSERVATIONS)
+        .min(MAX_OBSERVATIONS)
+        .min(recorded);
+    (1..=limit)
+        .map(|x| OBSERVATIONS.load(storage, (denom, (recorded - x) % MAX_OBSERVATIONS)))
+        .collect()
+}
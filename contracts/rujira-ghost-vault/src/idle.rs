@@ -0,0 +1,125 @@
+use cosmwasm_std::{coin, Env, QuerierWrapper, Response, Uint128};
+use rujira_rs::ghost::vault::IdleStrategy;
+use std::cmp::{min, Ordering};
+
+use crate::{config::Config, events::event_rebalance, state::State, ContractError};
+
+/// Recalls from the pool's idle strategy any part of `amount` that the contract doesn't hold, ahead
+/// of it being paid out in the same transaction
+pub fn recall(
+    querier: QuerierWrapper,
+    env: &Env,
+    config: &Config,
+    state: &mut State,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let Some(strategy) = &config.idle_strategy else {
+        return Ok(Response::default());
+    };
+    let held = querier
+        .query_balance(env.contract.address.as_str(), &config.denom)?
+        .amount;
+    let recalled = min(amount.saturating_sub(held), state.idle_deployed);
+    if recalled.is_zero() {
+        return Ok(Response::default());
+    }
+    state.idle_deployed -= recalled;
+    Ok(Response::default().add_message(strategy.withdraw_msg(recalled)?))
+}
+
+/// Settles the strategy's yield or loss since the last rebalance into the deposit pool.
+/// Returns (yield, loss)
+fn harvest(
+    querier: QuerierWrapper,
+    env: &Env,
+    strategy: &IdleStrategy,
+    state: &mut State,
+) -> Result<(Uint128, Uint128), ContractError> {
+    let balance = strategy.balance(querier, &env.contract.address)?;
+    let settled = match balance.cmp(&state.idle_deployed) {
+        Ordering::Greater => {
+            let earned = balance - state.idle_deployed;
+            state.deposit_pool.deposit(earned)?;
+            (earned, Uint128::zero())
+        }
+        Ordering::Less => {
+            let lost = state.idle_deployed - balance;
+            state.deposit_pool.withdraw(lost)?;
+            (Uint128::zero(), lost)
+        }
+        Ordering::Equal => (Uint128::zero(), Uint128::zero()),
+    };
+    state.idle_deployed = balance;
+    Ok(settled)
+}
+
+/// Harvests the strategy and moves idle deposits to or from it, so that it holds `max_share`
+/// of the pool's unborrowed deposits
+pub fn rebalance(
+    querier: QuerierWrapper,
+    env: &Env,
+    config: &Config,
+    state: &mut State,
+) -> Result<Response, ContractError> {
+    let strategy = config
+        .idle_strategy
+        .as_ref()
+        .ok_or(ContractError::Invalid("idle_strategy".to_string()))?;
+    let (earned, lost) = harvest(querier, env, strategy, state)?;
+    let target = state
+        .deposit_pool
+        .size()
+        .saturating_sub(state.debt_pool.size())
+        .mul_floor(strategy.max_share);
+
+    let mut response = Response::default();
+    match target.cmp(&state.idle_deployed) {
+        Ordering::Greater => {
+            let held = querier
+                .query_balance(env.contract.address.as_str(), &config.denom)?
+                .amount;
+            let amount = min(target - state.idle_deployed, held);
+            if !amount.is_zero() {
+                state.idle_deployed += amount;
+                response =
+                    response.add_message(strategy.deposit_msg(coin(amount.u128(), &config.denom))?);
+            }
+        }
+        Ordering::Less => {
+            let amount = state.idle_deployed - target;
+            state.idle_deployed = target;
+            response = response.add_message(strategy.withdraw_msg(amount)?);
+        }
+        Ordering::Equal => {}
+    }
+    Ok(response.add_event(event_rebalance(
+        &config.denom,
+        earned,
+        lost,
+        state.idle_deployed,
+    )))
+}
+
+/// Harvests the strategy and recalls everything deployed to it
+pub fn close(
+    querier: QuerierWrapper,
+    env: &Env,
+    config: &Config,
+    state: &mut State,
+) -> Result<Response, ContractError> {
+    let Some(strategy) = &config.idle_strategy else {
+        return Ok(Response::default());
+    };
+    let (earned, lost) = harvest(querier, env, strategy, state)?;
+    let mut response = Response::default();
+    if !state.idle_deployed.is_zero() {
+        response = response.add_message(strategy.withdraw_msg(state.idle_deployed)?);
+        state.idle_deployed = Uint128::zero();
+    }
+    Ok(response.add_event(event_rebalance(
+        &config.denom,
+        earned,
+        lost,
+        Uint128::zero(),
+    )))
+}
@@ -1,9 +1,16 @@
+pub mod backstop;
 pub mod borrowers;
 pub mod config;
 pub mod contract;
 mod error;
 mod events;
+mod flash;
+mod guard;
+mod history;
+mod idle;
+mod registry;
 mod state;
+mod terms;
 
 pub use crate::error::ContractError;
 
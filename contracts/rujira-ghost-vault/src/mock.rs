@@ -7,9 +7,10 @@ use rujira_rs::{
 };
 use rujira_rs_testing::RujiraApp;
 
-/// Wrapper struct for Ghost Vault contract with convenience methods
+/// Wrapper struct for Ghost Vault contract with convenience methods.
+/// Holds the denom of the pool that the helpers act on
 #[derive(Debug, Clone)]
-pub struct GhostVault(pub Addr);
+pub struct GhostVault(pub Addr, pub String);
 
 impl GhostVault {
     /// Get the contract address
@@ -17,6 +18,29 @@ impl GhostVault {
         &self.0
     }
 
+    /// Get the denom of the pool
+    pub fn denom(&self) -> &str {
+        &self.1
+    }
+
+    /// The same vault, acting on the pool for `denom`
+    pub fn pool(&self, denom: &str) -> Self {
+        Self(self.0.clone(), denom.to_string())
+    }
+
+    /// Add a pool for `denom` via sudo
+    pub fn add_pool(
+        &self,
+        app: &mut RujiraApp,
+        owner: &Addr,
+        denom: &str,
+    ) -> anyhow::Result<cw_multi_test::AppResponse> {
+        app.wasm_sudo(
+            self.0.clone(),
+            &ghost::vault::SudoMsg::AddPool(instantiate_msg(owner, denom)),
+        )
+    }
+
     /// Execute a deposit into the vault
     pub fn deposit(
         &self,
@@ -44,7 +68,8 @@ impl GhostVault {
             sender.clone(),
             self.0.clone(),
             &ghost::vault::ExecuteMsg::Withdraw { callback: None },
-            &coins(amount.u128(), "receipt-token"), // Withdraw by burning receipt tokens
+            // Withdraw by burning receipt tokens
+            &coins(amount.u128(), format!("x/ghost-vault/{}", self.1)),
         )
     }
 
@@ -58,6 +83,7 @@ impl GhostVault {
         app.wasm_sudo(
             self.0.clone(),
             &ghost::vault::SudoMsg::SetBorrower {
+                denom: self.1.clone(),
                 contract: contract.to_string(),
                 limit,
             },
@@ -66,9 +92,12 @@ impl GhostVault {
 
     /// Query vault status
     pub fn query_status(&self, app: &RujiraApp) -> anyhow::Result<ghost::vault::StatusResponse> {
-        Ok(app
-            .wrap()
-            .query_wasm_smart(self.0.clone(), &ghost::vault::QueryMsg::Status {})?)
+        Ok(app.wrap().query_wasm_smart(
+            self.0.clone(),
+            &ghost::vault::QueryMsg::Status {
+                denom: self.1.clone(),
+            },
+        )?)
     }
 
     /// Query borrower info
@@ -80,6 +109,7 @@ impl GhostVault {
         Ok(app.wrap().query_wasm_smart(
             self.0.clone(),
             &ghost::vault::QueryMsg::Borrower {
+                denom: self.1.clone(),
                 addr: addr.to_string(),
             },
         )?)
@@ -101,27 +131,32 @@ impl GhostVault {
             .instantiate_contract(
                 vault_code_id,
                 owner.clone(),
-                &ghost::vault::InstantiateMsg {
-                    denom: denom.to_string(),
-                    interest: Interest::default(),
-                    receipt: TokenMetadata {
-                        description: denom.to_string(),
-                        display: denom.to_string(),
-                        name: denom.to_string(),
-                        symbol: denom.to_string(),
-                        uri: None,
-                        uri_hash: None,
-                    },
-                    fee: Decimal::zero(),
-                    fee_address: owner.to_string(),
-                },
+                &instantiate_msg(owner, denom),
                 &[],
                 format!("ghost-vault-{}", denom),
                 Some(owner.to_string()),
             )
             .unwrap();
 
-        Self(vault_addr)
+        Self(vault_addr, denom.to_string())
+    }
+}
+
+fn instantiate_msg(owner: &Addr, denom: &str) -> ghost::vault::InstantiateMsg {
+    ghost::vault::InstantiateMsg {
+        denom: denom.to_string(),
+        interest: Interest::default(),
+        receipt: TokenMetadata {
+            description: denom.to_string(),
+            display: denom.to_string(),
+            name: denom.to_string(),
+            symbol: denom.to_string(),
+            uri: None,
+            uri_hash: None,
+        },
+        fee: Decimal::zero(),
+        fee_address: owner.to_string(),
+        registry: None,
     }
 }
 #[cfg(test)]
@@ -0,0 +1,29 @@
+use cosmwasm_std::{Addr, CosmosMsg, Env, StdResult, Storage};
+use cw_storage_plus::Item;
+use rujira_rs::ghost::vault_registry::ExecuteMsg;
+
+use crate::config::Config;
+
+// The vault registry that pools are registered with as they are created
+static REGISTRY: Item<Addr> = Item::new("registry");
+
+pub fn set(storage: &mut dyn Storage, registry: &Addr) -> StdResult<()> {
+    REGISTRY.save(storage, registry)
+}
+
+/// The message registering the pool of `config` with the registry, when one is set
+pub fn register_msg(
+    storage: &dyn Storage,
+    env: &Env,
+    config: &Config,
+) -> StdResult<Option<CosmosMsg>> {
+    let Some(registry) = REGISTRY.may_load(storage)? else {
+        return Ok(None);
+    };
+    ExecuteMsg::Register {
+        denom: config.denom.clone(),
+        receipt: config.receipt(env).denom(),
+    }
+    .call(&registry)
+    .map(Some)
+}
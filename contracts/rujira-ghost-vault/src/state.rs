@@ -1,12 +1,19 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Decimal, Decimal256, Env, StdResult, Storage, Timestamp, Uint128};
-use cw_storage_plus::Item;
+use cosmwasm_std::{Decimal, Decimal256, Env, Int128, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Item, Map};
 use rujira_rs::{ghost::vault::Interest, DecimalScaled, SharePool, SharePoolError};
-use std::ops::{Add, Mul, Sub};
+use std::{
+    cmp::{min, Ordering},
+    ops::{Add, Mul, Sub},
+};
 
 use crate::{config::Config, ContractError};
 
-static STATE: Item<State> = Item::new("state");
+static STATE: Map<&str, State> = Map::new("states");
+
+// Virtual shares and assets priced into deposit_pool issuance and redemption, so that a first
+// depositor can't inflate the share ratio to steal the rounding of subsequent deposits
+const VIRTUAL_OFFSET: Uint128 = Uint128::new(1_000);
 
 #[cw_serde]
 pub struct State {
@@ -23,45 +30,177 @@ pub struct State {
     pub pending_interest: DecimalScaled,
     #[serde(default)]
     pub pending_fees: DecimalScaled,
+    // Shares of the deposit_pool held by the contract on behalf of term deposits.
+    // These earn the floating rate until withdrawal, when they are settled at the fixed rate
+    // and the difference is absorbed by floating depositors
+    #[serde(default)]
+    pub term_shares: Uint128,
+    // Cumulative fixed interest paid to term deposits in excess of the floating return of their
+    // shares, ie the amount funded by floating depositors
+    #[serde(default)]
+    pub term_shortfall: Uint128,
+    // First-loss capital. Not lent out, it absorbs written off debt before the deposit_pool,
+    // and is allocated `Config::backstop_share` of the interest otherwise paid to the deposit_pool
+    #[serde(default)]
+    pub backstop_pool: SharePool,
+    // Incremented when a write-off exhausts the backstop_pool, invalidating all existing backstop shares
+    #[serde(default)]
+    pub backstop_epoch: u64,
+    // Deposits parked in the idle strategy, as of the last rebalance or recall.
+    // Counted in the deposit_pool, but not held by the contract
+    #[serde(default)]
+    pub idle_deployed: Uint128,
+    // Cumulative debt written off against the backstop_pool and the deposit_pool
+    #[serde(default)]
+    pub written_off_backstop: Uint128,
+    #[serde(default)]
+    pub written_off_deposits: Uint128,
 }
 
 impl State {
-    pub fn init(storage: &mut dyn Storage, env: &Env) -> StdResult<()> {
+    pub fn init(storage: &mut dyn Storage, env: &Env, denom: &str) -> StdResult<()> {
         STATE.save(
             storage,
+            denom,
             &Self {
                 last_updated: env.block.time,
                 debt_pool: SharePool::default(),
                 deposit_pool: SharePool::default(),
                 pending_interest: DecimalScaled::zero(),
                 pending_fees: DecimalScaled::zero(),
+                term_shares: Uint128::zero(),
+                term_shortfall: Uint128::zero(),
+                backstop_pool: SharePool::default(),
+                backstop_epoch: 0,
+                idle_deployed: Uint128::zero(),
+                written_off_backstop: Uint128::zero(),
+                written_off_deposits: Uint128::zero(),
             },
         )?;
 
         Ok(())
     }
 
-    pub fn load(storage: &dyn Storage) -> StdResult<Self> {
-        STATE.load(storage)
+    pub fn load(storage: &dyn Storage, denom: &str) -> StdResult<Self> {
+        STATE.load(storage, denom)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage, denom: &str) -> StdResult<()> {
+        STATE.save(storage, denom, self)
     }
 
-    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
-        STATE.save(storage, self)
+    /// Moves the state of a single-denom vault to the pool keyed by `denom`
+    pub fn migrate(storage: &mut dyn Storage, denom: &str) -> StdResult<()> {
+        let legacy: Item<State> = Item::new("state");
+        if let Some(state) = legacy.may_load(storage)? {
+            state.save(storage, denom)?;
+            legacy.remove(storage);
+        }
+        Ok(())
     }
 
     pub fn deposit(&mut self, amount: Uint128) -> Result<Uint128, ContractError> {
-        Ok(self.deposit_pool.join(amount)?)
+        Ok(self.deposit_pool.join_virtual(amount, VIRTUAL_OFFSET)?)
     }
 
     pub fn withdraw(&mut self, amount: Uint128) -> Result<Uint128, ContractError> {
-        let withdrawn = self.deposit_pool.leave(amount)?;
+        let withdrawn = self.deposit_pool.leave_virtual(amount, VIRTUAL_OFFSET)?;
         Ok(withdrawn)
     }
 
-    pub fn borrow(&mut self, amount: Uint128) -> Result<Uint128, ContractError> {
+    /// The value of `shares` of the deposit pool
+    pub fn deposit_value(&self, shares: Uint128) -> Uint128 {
+        self.deposit_pool.ownership_virtual(shares, VIRTUAL_OFFSET)
+    }
+
+    pub fn deposit_term(&mut self, amount: Uint128) -> Result<Uint128, ContractError> {
+        let shares = self.deposit_pool.join_virtual(amount, VIRTUAL_OFFSET)?;
+        self.term_shares += shares;
+        Ok(shares)
+    }
+
+    /// Redeems the shares of a term deposit, paying out the fixed `value`.
+    /// Any surplus value of the shares is retained by the deposit pool, and any shortfall
+    /// is taken from it and recorded in `term_shortfall`
+    pub fn withdraw_term(
+        &mut self,
+        shares: Uint128,
+        value: Uint128,
+    ) -> Result<Uint128, ContractError> {
+        self.term_shares = self.term_shares.checked_sub(shares)?;
+        let redeemed = self.deposit_pool.leave_virtual(shares, VIRTUAL_OFFSET)?;
+        match redeemed.cmp(&value) {
+            Ordering::Greater => self.deposit_pool.deposit(redeemed.sub(value))?,
+            Ordering::Less => {
+                let shortfall = value.sub(redeemed);
+                self.deposit_pool.withdraw(shortfall)?;
+                self.term_shortfall = self.term_shortfall.checked_add(shortfall)?;
+            }
+            Ordering::Equal => {}
+        }
+        Ok(value)
+    }
+
+    pub fn deposit_backstop(&mut self, amount: Uint128) -> Result<Uint128, ContractError> {
+        Ok(self.backstop_pool.join(amount)?)
+    }
+
+    pub fn withdraw_backstop(&mut self, shares: Uint128) -> Result<Uint128, ContractError> {
+        Ok(self.backstop_pool.leave(shares)?)
+    }
+
+    /// Removes `shares` of debt that will not be repaid. The loss is absorbed by the backstop_pool
+    /// first, and any remainder by the deposit_pool.
+    /// Returns the amounts absorbed by each
+    pub fn write_off(&mut self, shares: Uint128) -> Result<(Uint128, Uint128), ContractError> {
+        let loss = self.debt_pool.leave(shares)?;
+        let backstop = min(loss, self.backstop_pool.size());
+        if !self.backstop_pool.shares().is_zero() && backstop == self.backstop_pool.size() {
+            self.backstop_pool = SharePool::default();
+            self.backstop_epoch += 1;
+        } else {
+            self.backstop_pool.withdraw(backstop)?;
+        }
+        let deposits = loss.sub(backstop);
+        self.deposit_pool.withdraw(deposits)?;
+        self.written_off_backstop = self.written_off_backstop.checked_add(backstop)?;
+        self.written_off_deposits = self.written_off_deposits.checked_add(deposits)?;
+        Ok((backstop, deposits))
+    }
+
+    /// Borrows `amount` for a borrower with `debt` outstanding, which can't take the borrower's debt
+    /// above `max_concentration` of the deposits
+    pub fn borrow(
+        &mut self,
+        amount: Uint128,
+        debt: Uint128,
+        max_concentration: Option<Decimal>,
+    ) -> Result<Uint128, ContractError> {
+        if let Some(max) = max_concentration {
+            if debt.checked_add(amount)? > self.deposit_pool.size().mul_floor(max) {
+                return Err(ContractError::ConcentrationLimitReached { max });
+            }
+        }
         Ok(self.debt_pool.join(amount)?)
     }
 
+    /// The share of deposits owed by a borrower with `debt` outstanding
+    pub fn concentration(&self, debt: Uint128) -> Decimal {
+        Decimal::checked_from_ratio(debt, self.deposit_pool.size()).unwrap_or_default()
+    }
+
+    /// The value recoverable by the pool per unit owed to depositors and the backstop, with `held`
+    /// the balance of the contract: the held and idle deposits plus the outstanding debt.
+    /// One when nothing is owed
+    pub fn solvency(&self, held: Uint128) -> Decimal {
+        let owed = self.deposit_pool.size() + self.backstop_pool.size();
+        let value = held + self.idle_deployed + self.debt_pool.size();
+        if owed.is_zero() {
+            return Decimal::one();
+        }
+        Decimal::checked_from_ratio(value, owed).unwrap_or(Decimal::MAX)
+    }
+
     pub fn repay(&mut self, amount: Uint128) -> Result<Uint128, ContractError> {
         if self.debt_pool.size().is_zero() {
             return Err(ContractError::ZeroDebt {});
@@ -74,16 +213,23 @@ impl State {
 
     pub fn utilization(&self) -> Decimal {
         // We consider accrued interest and debt in the utilization rate
-        if self.deposit_pool.size().is_zero() {
-            Decimal::zero()
-        } else {
-            Decimal::one()
-                - Decimal::from_ratio(
-                    // We use the debt pool size to determine utilization
-                    self.deposit_pool.size().sub(self.debt_pool.size()),
-                    self.deposit_pool.size(),
-                )
+        utilization(self.debt_pool.size(), self.deposit_pool.size())
+    }
+
+    /// The utilization once `borrow_delta` is added to the debt and `deposit_delta` to the deposits
+    pub fn projected_utilization(
+        &self,
+        borrow_delta: Int128,
+        deposit_delta: Int128,
+    ) -> Result<Decimal, ContractError> {
+        let debt = apply_delta(self.debt_pool.size(), borrow_delta)?;
+        let deposits = apply_delta(self.deposit_pool.size(), deposit_delta)?;
+        if debt > deposits {
+            return Err(ContractError::InsufficientLiquidity {
+                available: self.deposit_pool.size() - self.debt_pool.size(),
+            });
         }
+        Ok(utilization(debt, deposits))
     }
 
     pub fn debt_rate(&self, interest: &Interest) -> StdResult<Decimal> {
@@ -144,7 +290,7 @@ impl State {
         let mut shares = Uint128::zero();
 
         // deposit the protocol fee to the deposit pool to issue shares
-        match self.deposit_pool.join(fee) {
+        match self.deposit_pool.join_virtual(fee, VIRTUAL_OFFSET) {
             Ok(amount) => {
                 shares = amount;
             }
@@ -157,8 +303,16 @@ impl State {
             Err(err) => return Err(err.into()),
         }
 
-        // Allocate the interest to the deposit pool
-        self.deposit_pool.deposit(interest)?;
+        // Allocate the backstop its share of the interest, and the remainder to the deposit pool
+        let backstop = if self.backstop_pool.size().is_zero() {
+            Uint128::zero()
+        } else {
+            interest.mul_floor(config.backstop_share)
+        };
+        if !backstop.is_zero() {
+            self.backstop_pool.deposit(backstop)?;
+        }
+        self.deposit_pool.deposit(interest.sub(backstop))?;
         // Charge the interest to the debt pool, so that outstanding debt tokens are required to
         // pay this interest on return
         self.debt_pool.deposit(interest.add(fee))?;
@@ -168,6 +322,22 @@ impl State {
     }
 }
 
+fn utilization(debt: Uint128, deposits: Uint128) -> Decimal {
+    if deposits.is_zero() {
+        Decimal::zero()
+    } else {
+        Decimal::one() - Decimal::from_ratio(deposits.sub(debt), deposits)
+    }
+}
+
+fn apply_delta(size: Uint128, delta: Int128) -> Result<Uint128, ContractError> {
+    Ok(if delta.is_negative() {
+        size.checked_sub(delta.unsigned_abs())?
+    } else {
+        size.checked_add(delta.unsigned_abs())?
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,8 +348,8 @@ mod tests {
     fn test_distribute_interest_no_mint_path() {
         let env = mock_env();
         let mut storage = cosmwasm_std::testing::MockStorage::new();
-        State::init(&mut storage, &env).unwrap();
-        let mut state = State::load(&storage).unwrap();
+        State::init(&mut storage, &env, "test").unwrap();
+        let mut state = State::load(&storage, "test").unwrap();
 
         let config = Config {
             denom: "test".to_string(),
@@ -188,14 +358,27 @@ mod tests {
                 base_rate: Decimal::from_ratio(1u128, 1000000u128), // 0.0001% per year
                 step1: Decimal::from_ratio(20u128, 100u128),
                 step2: Decimal::from_ratio(100u128, 100u128),
+                min_lend_rate: None,
+                max_debt_rate: None,
             },
             fee: Decimal::from_ratio(1u128, 10u128), // 10% fee
             fee_address: cosmwasm_std::Addr::unchecked("fee_addr"),
+            terms: vec![],
+            early_exit_fee: Decimal::zero(),
+            utilization_alerts: vec![],
+            backstop_share: Decimal::zero(),
+            backstop_unbonding: 0,
+            idle_strategy: None,
+            timelock: 0,
+            same_block_guard: false,
+            max_concentration: None,
         };
 
         // Deposit 1000, borrow 800
         state.deposit(Uint128::new(1000)).unwrap();
-        state.borrow(Uint128::new(800)).unwrap();
+        state
+            .borrow(Uint128::new(800), Uint128::zero(), None)
+            .unwrap();
 
         // Wait 1 second
         let mut env = mock_env();
@@ -226,8 +409,8 @@ mod tests {
     fn test_distribute_interest_mint_path() {
         let env = mock_env();
         let mut storage = cosmwasm_std::testing::MockStorage::new();
-        State::init(&mut storage, &env).unwrap();
-        let mut state = State::load(&storage).unwrap();
+        State::init(&mut storage, &env, "test").unwrap();
+        let mut state = State::load(&storage, "test").unwrap();
 
         let config = Config {
             denom: "test".to_string(),
@@ -236,14 +419,27 @@ mod tests {
                 base_rate: Decimal::from_ratio(10u128, 100u128), // 10% base rate
                 step1: Decimal::from_ratio(20u128, 100u128),
                 step2: Decimal::from_ratio(100u128, 100u128),
+                min_lend_rate: None,
+                max_debt_rate: None,
             },
             fee: Decimal::from_ratio(1u128, 10u128), // 10% fee
             fee_address: cosmwasm_std::Addr::unchecked("fee_addr"),
+            terms: vec![],
+            early_exit_fee: Decimal::zero(),
+            utilization_alerts: vec![],
+            backstop_share: Decimal::zero(),
+            backstop_unbonding: 0,
+            idle_strategy: None,
+            timelock: 0,
+            same_block_guard: false,
+            max_concentration: None,
         };
 
         // Deposit 1000, borrow 800
         state.deposit(Uint128::new(1000)).unwrap();
-        state.borrow(Uint128::new(800)).unwrap();
+        state
+            .borrow(Uint128::new(800), Uint128::zero(), None)
+            .unwrap();
 
         // Wait 1 year
         let mut env = mock_env();
@@ -267,4 +463,129 @@ mod tests {
         assert_eq!(state.deposit_pool.size().u128() - 1000, 240);
         assert_eq!(state.debt_pool.size().u128() - 800, 240);
     }
+
+    #[test]
+    fn test_share_inflation() {
+        let env = mock_env();
+        let mut storage = cosmwasm_std::testing::MockStorage::new();
+        State::init(&mut storage, &env, "test").unwrap();
+        let mut state = State::load(&storage, "test").unwrap();
+
+        // Attacker takes the first share, and donates to the pool to inflate its price
+        let attacker = state.deposit(Uint128::new(1)).unwrap();
+        assert_eq!(attacker, Uint128::one());
+        state.deposit_pool.deposit(Uint128::new(5)).unwrap();
+
+        // Without the offset the victim would be issued 1 share, worth 8
+        let victim = state.deposit(Uint128::new(11)).unwrap();
+        assert_eq!(victim, Uint128::new(10));
+        assert_eq!(state.withdraw(victim).unwrap(), Uint128::new(10));
+        // And the attacker loses most of what they put in to the virtual shares
+        assert_eq!(state.withdraw(attacker).unwrap(), Uint128::new(1));
+
+        // What is left behind is priced into later deposits
+        assert_eq!(state.deposit_pool.size(), Uint128::new(6));
+        let shares = state.deposit(Uint128::new(1000)).unwrap();
+        assert_eq!(shares, Uint128::new(994));
+        assert_eq!(state.deposit_value(shares), Uint128::new(999));
+    }
+
+    #[test]
+    fn test_withdraw_term() {
+        let env = mock_env();
+        let mut storage = cosmwasm_std::testing::MockStorage::new();
+        State::init(&mut storage, &env, "test").unwrap();
+        let mut state = State::load(&storage, "test").unwrap();
+
+        // Floating 1000, term 1000
+        state.deposit(Uint128::new(1000)).unwrap();
+        let shares = state.deposit_term(Uint128::new(1000)).unwrap();
+        assert_eq!(shares, Uint128::new(1000));
+        assert_eq!(state.term_shares, Uint128::new(1000));
+
+        // Floating rate earns 200 across the pool
+        state.deposit_pool.deposit(Uint128::new(200)).unwrap();
+
+        // Fixed payout of 1050, the remaining 50 stays with floating depositors
+        state.withdraw_term(shares, Uint128::new(1050)).unwrap();
+        assert_eq!(state.term_shares, Uint128::zero());
+        assert_eq!(state.deposit_pool.size(), Uint128::new(1150));
+        assert_eq!(state.deposit_pool.shares(), Uint128::new(1000));
+        assert_eq!(state.term_shortfall, Uint128::zero());
+
+        // Fixed payout above the floating return is funded by floating depositors, and recorded
+        let shares = state.deposit_term(Uint128::new(1150)).unwrap();
+        state.withdraw_term(shares, Uint128::new(1250)).unwrap();
+        assert_eq!(state.deposit_pool.size(), Uint128::new(1050));
+        assert_eq!(state.deposit_pool.shares(), Uint128::new(1000));
+        assert_eq!(state.term_shortfall, Uint128::new(100));
+
+        state
+            .withdraw_term(Uint128::one(), Uint128::one())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_backstop() {
+        let env = mock_env();
+        let mut storage = cosmwasm_std::testing::MockStorage::new();
+        State::init(&mut storage, &env, "test").unwrap();
+        let mut state = State::load(&storage, "test").unwrap();
+
+        let config = Config {
+            denom: "test".to_string(),
+            interest: Interest {
+                target_utilization: Decimal::from_ratio(8u128, 10u128),
+                base_rate: Decimal::from_ratio(10u128, 100u128), // 10% base rate
+                step1: Decimal::from_ratio(20u128, 100u128),
+                step2: Decimal::from_ratio(100u128, 100u128),
+                min_lend_rate: None,
+                max_debt_rate: None,
+            },
+            fee: Decimal::from_ratio(1u128, 10u128), // 10% fee
+            fee_address: cosmwasm_std::Addr::unchecked("fee_addr"),
+            terms: vec![],
+            early_exit_fee: Decimal::zero(),
+            utilization_alerts: vec![],
+            backstop_share: Decimal::percent(50),
+            backstop_unbonding: 0,
+            idle_strategy: None,
+            timelock: 0,
+            same_block_guard: false,
+            max_concentration: None,
+        };
+
+        // Deposit 1000, backstop 100, borrow 800
+        state.deposit(Uint128::new(1000)).unwrap();
+        state.deposit_backstop(Uint128::new(100)).unwrap();
+        state
+            .borrow(Uint128::new(800), Uint128::zero(), None)
+            .unwrap();
+
+        // Wait 1 year
+        let mut env = mock_env();
+        env.block.time = state.last_updated.plus_seconds(31_536_000);
+        state.distribute_interest(&env, &config).unwrap();
+
+        // Net interest of 216 is split with the backstop, the fee of 24 joins the deposit pool
+        assert_eq!(state.backstop_pool.size(), Uint128::new(208));
+        assert_eq!(state.deposit_pool.size(), Uint128::new(1132));
+        assert_eq!(state.debt_pool.size(), Uint128::new(1040));
+
+        // A partial write-off is absorbed by the backstop alone
+        let (backstop, deposits) = state.write_off(Uint128::new(100)).unwrap();
+        assert_eq!(backstop, Uint128::new(130));
+        assert_eq!(deposits, Uint128::zero());
+        assert_eq!(state.backstop_pool.size(), Uint128::new(78));
+        assert_eq!(state.backstop_epoch, 0);
+
+        // The remainder exhausts the backstop
+        let (backstop, deposits) = state.write_off(Uint128::new(700)).unwrap();
+        assert_eq!(backstop, Uint128::new(78));
+        assert_eq!(deposits, Uint128::new(832));
+        assert_eq!(state.backstop_pool.shares(), Uint128::zero());
+        assert_eq!(state.backstop_epoch, 1);
+        assert_eq!(state.deposit_pool.size(), Uint128::new(300));
+        assert_eq!(state.debt_pool.size(), Uint128::zero());
+    }
 }
@@ -0,0 +1,232 @@
+use crate::{config::Config, ContractError};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Order, StdError, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use rujira_rs::ghost::vault::TermDepositResponse;
+
+static TERM_ID: Item<u64> = Item::new("term-id");
+
+const SECONDS_PER_YEAR: u128 = 31_536_000;
+const DEFAULT_LIMIT: u8 = 10;
+const MAX_LIMIT: u8 = 100;
+
+/// A deposit locked for a fixed term at a fixed rate.
+/// The principal is added to the deposit pool, and the shares issued are held by the
+/// contract on behalf of the owner until withdrawal
+#[cw_serde]
+pub struct TermDeposit {
+    pub id: u64,
+    pub owner: Addr,
+    /// The denom of the pool the deposit was made to
+    pub denom: String,
+    pub amount: Uint128,
+    pub shares: Uint128,
+    pub rate: Decimal,
+    pub start: Timestamp,
+    pub maturity: Timestamp,
+}
+
+pub struct TermDepositIndexes<'a> {
+    owner: MultiIndex<'a, Addr, TermDeposit, u64>,
+}
+
+impl<'a> IndexList<TermDeposit> for TermDepositIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<TermDeposit>> + '_> {
+        let v: Vec<&dyn Index<TermDeposit>> = vec![&self.owner];
+        Box::new(v.into_iter())
+    }
+}
+
+impl TermDeposit {
+    /// Creates a deposit in the pool of `config`, at its fixed rate for `duration`
+    pub fn create(
+        storage: &mut dyn Storage,
+        config: &Config,
+        owner: Addr,
+        amount: Uint128,
+        shares: Uint128,
+        start: Timestamp,
+        duration: u64,
+    ) -> Result<Self, ContractError> {
+        let rate = config.term_rate(duration)?;
+        let id = TERM_ID.may_load(storage)?.unwrap_or_default();
+        TERM_ID.save(storage, &(id + 1))?;
+        let deposit = Self {
+            id,
+            owner,
+            denom: config.denom.clone(),
+            amount,
+            shares,
+            rate,
+            start,
+            maturity: start.plus_seconds(duration),
+        };
+        Self::store().save(storage, id, &deposit)?;
+        Ok(deposit)
+    }
+
+    pub fn load(storage: &dyn Storage, id: u64) -> Result<Self, ContractError> {
+        match Self::store().load(storage, id) {
+            Ok(x) => Ok(x),
+            Err(StdError::NotFound { .. }) => Err(ContractError::TermDepositNotFound { id }),
+            Err(err) => Err(ContractError::Std(err)),
+        }
+    }
+
+    pub fn remove(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        Self::store().remove(storage, self.id)
+    }
+
+    pub fn by_owner(
+        storage: &dyn Storage,
+        owner: &Addr,
+        limit: Option<u8>,
+        start_after: Option<u64>,
+    ) -> StdResult<Vec<Self>> {
+        Self::store()
+            .idx
+            .owner
+            .prefix(owner.clone())
+            .range(
+                storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize)
+            .map(|x| x.map(|(_, v)| v))
+            .collect()
+    }
+
+    pub fn is_mature(&self, now: Timestamp) -> bool {
+        now >= self.maturity
+    }
+
+    /// The fixed interest earned up to `now`, capped at maturity
+    pub fn interest(&self, now: Timestamp) -> Uint128 {
+        let end = if self.is_mature(now) {
+            self.maturity
+        } else {
+            now
+        };
+        let seconds = end.seconds().saturating_sub(self.start.seconds());
+        self.amount
+            .mul_floor(self.rate)
+            .multiply_ratio(seconds, SECONDS_PER_YEAR)
+    }
+
+    /// The early exit fee charged if withdrawn at `now`
+    pub fn penalty(&self, now: Timestamp, early_exit_fee: Decimal) -> Uint128 {
+        if self.is_mature(now) {
+            return Uint128::zero();
+        }
+        self.amount.mul_ceil(early_exit_fee)
+    }
+
+    /// The amount returned to the owner if withdrawn at `now`
+    pub fn value(&self, now: Timestamp, early_exit_fee: Decimal) -> Uint128 {
+        self.amount
+            .checked_add(self.interest(now))
+            .unwrap_or(Uint128::MAX)
+            .saturating_sub(self.penalty(now, early_exit_fee))
+    }
+
+    pub fn to_response(&self, now: Timestamp, early_exit_fee: Decimal) -> TermDepositResponse {
+        TermDepositResponse {
+            id: self.id,
+            owner: self.owner.to_string(),
+            denom: self.denom.clone(),
+            amount: self.amount,
+            rate: self.rate,
+            start: self.start,
+            maturity: self.maturity,
+            value: self.value(now, early_exit_fee),
+        }
+    }
+
+    fn store<'a>() -> IndexedMap<u64, TermDeposit, TermDepositIndexes<'a>> {
+        IndexedMap::new(
+            "terms",
+            TermDepositIndexes {
+                owner: MultiIndex::new(|_k, d: &TermDeposit| d.owner.clone(), "terms", "terms__o"),
+            },
+        )
+    }
+}
+
+// ------------ Migration ------------
+#[cw_serde]
+struct LegacyTermDeposit {
+    id: u64,
+    owner: Addr,
+    amount: Uint128,
+    shares: Uint128,
+    rate: Decimal,
+    start: Timestamp,
+    maturity: Timestamp,
+}
+
+/// Assigns the term deposits of a single-denom vault to the pool keyed by `denom`.
+/// Deposits are re-written in place, the owner index is unchanged
+pub fn migrate(storage: &mut dyn Storage, denom: &str) -> StdResult<()> {
+    let legacy: Map<u64, LegacyTermDeposit> = Map::new("terms");
+    let deposits: Map<u64, TermDeposit> = Map::new("terms");
+    let all = legacy
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (id, x) in all {
+        deposits.save(
+            storage,
+            id,
+            &TermDeposit {
+                id: x.id,
+                owner: x.owner,
+                denom: denom.to_string(),
+                amount: x.amount,
+                shares: x.shares,
+                rate: x.rate,
+                start: x.start,
+                maturity: x.maturity,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Sub;
+
+    #[test]
+    fn value() {
+        let start = Timestamp::from_seconds(1_000);
+        let deposit = TermDeposit {
+            id: 0,
+            owner: Addr::unchecked("owner"),
+            denom: "btc".to_string(),
+            amount: Uint128::from(1_000_000u128),
+            shares: Uint128::from(1_000_000u128),
+            rate: Decimal::percent(10),
+            start,
+            maturity: start.plus_seconds(SECONDS_PER_YEAR as u64),
+        };
+        let fee = Decimal::percent(1);
+
+        // Half way through the term, half the interest less the exit fee
+        let now = start.plus_seconds(SECONDS_PER_YEAR as u64 / 2);
+        assert_eq!(deposit.interest(now), Uint128::from(50_000u128));
+        assert_eq!(deposit.penalty(now, fee), Uint128::from(10_000u128));
+        assert_eq!(deposit.value(now, fee), Uint128::from(1_040_000u128));
+
+        // Interest stops accruing at maturity
+        let now = start.plus_seconds(SECONDS_PER_YEAR as u64 * 2);
+        assert_eq!(deposit.interest(now), Uint128::from(100_000u128));
+        assert_eq!(deposit.penalty(now, fee), Uint128::zero());
+        assert_eq!(deposit.value(now, fee), Uint128::from(1_100_000u128));
+        assert_eq!(
+            deposit.value(now, fee).sub(deposit.amount),
+            Uint128::from(100_000u128)
+        );
+    }
+}
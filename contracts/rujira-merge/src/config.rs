@@ -1,7 +1,7 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{StdResult, Storage, Timestamp, Uint128};
 use cw_storage_plus::Item;
-use rujira_rs::merge::{ConfigResponse, InstantiateMsg};
+use rujira_rs::merge::{ConfigResponse, DepositCaps, InstantiateMsg};
 
 use crate::ContractError;
 
@@ -15,6 +15,14 @@ pub struct Config {
     pub ruji_allocation: Uint128,
     pub decay_starts_at: Timestamp,
     pub decay_ends_at: Timestamp,
+    #[serde(default)]
+    pub deposit_caps: Option<DepositCaps>,
+    /// Set while the decay schedule is paused
+    #[serde(default)]
+    pub paused_at: Option<Timestamp>,
+    /// The seconds the decay schedule has spent paused, not including a current pause
+    #[serde(default)]
+    pub paused_for: u64,
 }
 
 impl From<InstantiateMsg> for Config {
@@ -26,6 +34,9 @@ impl From<InstantiateMsg> for Config {
             ruji_allocation: value.ruji_allocation,
             decay_starts_at: value.decay_starts_at,
             decay_ends_at: value.decay_ends_at,
+            deposit_caps: value.deposit_caps,
+            paused_at: None,
+            paused_for: 0,
         }
     }
 }
@@ -39,6 +50,7 @@ impl From<Config> for ConfigResponse {
             ruji_allocation: value.ruji_allocation,
             decay_starts_at: value.decay_starts_at,
             decay_ends_at: value.decay_ends_at,
+            deposit_caps: value.deposit_caps,
         }
     }
 }
@@ -67,7 +79,71 @@ impl Config {
         if self.ruji_allocation.eq(&Uint128::zero()) {
             return Err(ContractError::Invalid("ruji_allocation".to_string()));
         }
+        self.validate_deposit_caps()
+    }
+
+    /// Validated separately as the caps can be changed after the merge has started
+    pub fn validate_deposit_caps(&self) -> Result<(), ContractError> {
+        if let Some(caps) = &self.deposit_caps {
+            if caps.window == 0 {
+                return Err(ContractError::Invalid("deposit_caps window".to_string()));
+            }
+            if caps.account.is_some_and(|x| x.is_zero()) || caps.global.is_some_and(|x| x.is_zero())
+            {
+                return Err(ContractError::Invalid("deposit_caps amount".to_string()));
+            }
+        }
+        Ok(())
+    }
 
+    /// The time that the decay schedule has reached at `now`, which stops while paused
+    pub fn clock(&self, now: &Timestamp) -> Timestamp {
+        self.paused_at
+            .unwrap_or(*now)
+            .minus_seconds(self.paused_for)
+    }
+
+    /// The seconds the decay schedule has spent paused at `now`
+    pub fn paused_for(&self, now: &Timestamp) -> u64 {
+        self.paused_for
+            + self
+                .paused_at
+                .map(|x| now.seconds().saturating_sub(x.seconds()))
+                .unwrap_or_default()
+    }
+
+    /// Moves the decay schedule, and pauses or resumes it. A schedule can only be moved before its
+    /// decay starts, as the $RUJI released by the decay so far has already been allocated.
+    /// A paused schedule resumes from where it was paused, delayed by the time spent paused
+    pub fn update_schedule(
+        &mut self,
+        now: &Timestamp,
+        decay_starts_at: Timestamp,
+        decay_ends_at: Timestamp,
+        paused: bool,
+    ) -> Result<(), ContractError> {
+        let clock = self.clock(now);
+        if decay_starts_at != self.decay_starts_at || decay_ends_at != self.decay_ends_at {
+            if self.decay_starts_at.lt(&clock) {
+                return Err(ContractError::Invalid("decay has started".to_string()));
+            }
+            if decay_starts_at.lt(&clock) {
+                return Err(ContractError::Invalid("decay_starts_at".to_string()));
+            }
+            if decay_ends_at.lt(&decay_starts_at) {
+                return Err(ContractError::Invalid("decay_ends_at".to_string()));
+            }
+            self.decay_starts_at = decay_starts_at;
+            self.decay_ends_at = decay_ends_at;
+        }
+        match (self.paused_at, paused) {
+            (None, true) => self.paused_at = Some(*now),
+            (Some(_), false) => {
+                self.paused_for = self.paused_for(now);
+                self.paused_at = None;
+            }
+            _ => {}
+        }
         Ok(())
     }
 
@@ -89,6 +165,9 @@ mod tests {
             ruji_allocation: Uint128::from(100u128),
             decay_starts_at: Timestamp::from_seconds(0),
             decay_ends_at: Timestamp::from_seconds(100),
+            deposit_caps: None,
+            paused_at: None,
+            paused_for: 0,
         }
         .validate(&Timestamp::default())
         .unwrap_err();
@@ -100,6 +179,9 @@ mod tests {
             ruji_allocation: Uint128::from(100u128),
             decay_starts_at: Timestamp::from_seconds(0),
             decay_ends_at: Timestamp::from_seconds(100),
+            deposit_caps: None,
+            paused_at: None,
+            paused_for: 0,
         }
         .validate(&Timestamp::default())
         .unwrap_err();
@@ -111,6 +193,9 @@ mod tests {
             ruji_allocation: Uint128::from(100u128),
             decay_starts_at: Timestamp::from_seconds(0),
             decay_ends_at: Timestamp::from_seconds(100),
+            deposit_caps: None,
+            paused_at: None,
+            paused_for: 0,
         }
         .validate(&Timestamp::default())
         .unwrap_err();
@@ -122,6 +207,9 @@ mod tests {
             ruji_allocation: Uint128::zero(),
             decay_starts_at: Timestamp::from_seconds(0),
             decay_ends_at: Timestamp::from_seconds(100),
+            deposit_caps: None,
+            paused_at: None,
+            paused_for: 0,
         }
         .validate(&Timestamp::default())
         .unwrap_err();
@@ -133,6 +221,9 @@ mod tests {
             ruji_allocation: Uint128::from(100u128),
             decay_starts_at: Timestamp::default(),
             decay_ends_at: Timestamp::from_seconds(100),
+            deposit_caps: None,
+            paused_at: None,
+            paused_for: 0,
         }
         .validate(&Timestamp::from_seconds(100))
         .unwrap_err();
@@ -144,6 +235,9 @@ mod tests {
             ruji_allocation: Uint128::from(100u128),
             decay_starts_at: Timestamp::from_seconds(200),
             decay_ends_at: Timestamp::from_seconds(150),
+            deposit_caps: None,
+            paused_at: None,
+            paused_for: 0,
         }
         .validate(&Timestamp::from_seconds(100))
         .unwrap_err();
@@ -1,21 +1,28 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
+use std::str::FromStr;
+
 use cosmwasm_std::{
-    ensure_eq, to_json_binary, BankMsg, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Response,
+    ensure, ensure_eq, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, Response, Uint256,
 };
 use cw2::set_contract_version;
 use cw_utils::{must_pay, nonpayable};
-use rujira_rs::merge::{ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+use rujira_rs::merge::{
+    AccountsResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, Payout, QueryMsg, SudoMsg,
+};
+use rujira_rs::{Asset, Destination, MsgSwap, PageResponse, Slip};
 
 // use cw2::set_contract_version;
 
 use crate::config::Config;
 use crate::error::ContractError;
 use crate::events::{event_deposit, event_withdraw};
-use crate::state::{account, execute_deposit, execute_withdraw, init, status};
+use crate::state::{account, accounts, capacity, execute_deposit, execute_withdraw, init, status};
 
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const ACCOUNTS_LIMIT: u8 = 100;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -48,7 +55,7 @@ pub fn execute(
     let time = env.block.time;
     let balance = deps
         .querier
-        .query_balance(env.contract.address, config.ruji_denom.clone())?
+        .query_balance(&env.contract.address, config.ruji_denom.clone())?
         .amount;
     match msg {
         ExecuteMsg::Deposit {} => {
@@ -57,7 +64,10 @@ pub fn execute(
                 execute_deposit(deps.storage, &config, time, &balance, &info.sender, amount)?;
             Ok(Response::default().add_event(event_deposit(info.sender, amount, shares)))
         }
-        ExecuteMsg::Withdraw { share_amount } => {
+        ExecuteMsg::Withdraw {
+            share_amount,
+            payout,
+        } => {
             nonpayable(&info)?;
             let amount = execute_withdraw(
                 deps.storage,
@@ -67,27 +77,99 @@ pub fn execute(
                 &info.sender,
                 share_amount,
             )?;
-            Ok(Response::default()
-                .add_message(BankMsg::Send {
+            let coin = Coin::new(amount, config.ruji_denom);
+            let msg: CosmosMsg = match payout {
+                None => BankMsg::Send {
                     to_address: info.sender.to_string(),
-                    amount: vec![Coin::new(amount, config.ruji_denom)],
-                })
+                    amount: vec![coin],
+                }
+                .into(),
+                Some(Payout::Swap {
+                    to,
+                    destination,
+                    min_return,
+                }) => {
+                    // The memo fields are delimited by `:` and `/`, neither can be injected
+                    for field in [&to, &destination] {
+                        ensure!(
+                            !field.is_empty() && !field.contains([':', '/']),
+                            ContractError::Invalid(format!("payout {field}"))
+                        );
+                    }
+                    MsgSwap::new(
+                        (&coin).try_into()?,
+                        Asset::from_str(&to)?,
+                        Destination::Refundable {
+                            destination_addr: Addr::unchecked(destination),
+                            refund_addr: info.sender.clone(),
+                        },
+                        min_return.map(|x| Slip::Limit(Uint256::from(x))),
+                        None,
+                        None,
+                        deps.api.addr_canonicalize(env.contract.address.as_str())?,
+                    )
+                    .into()
+                }
+            };
+            Ok(Response::default()
+                .add_message(msg)
                 .add_event(event_withdraw(info.sender, share_amount, amount)))
         }
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    let mut config = Config::load(deps.storage)?;
+    match msg {
+        SudoMsg::SetDepositCaps(caps) => {
+            config.deposit_caps = caps;
+            config.validate_deposit_caps()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+        SudoMsg::UpdateSchedule {
+            decay_starts_at,
+            decay_ends_at,
+            paused,
+        } => {
+            config.update_schedule(&env.block.time, decay_starts_at, decay_ends_at, paused)?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
         QueryMsg::Config {} => Ok(to_json_binary(&ConfigResponse::from(Config::load(
             deps.storage,
         )?))?),
-        QueryMsg::Status {} => Ok(to_json_binary(&status(deps.storage)?)?),
+        QueryMsg::Status {} => Ok(to_json_binary(&status(
+            deps.storage,
+            &Config::load(deps.storage)?,
+            env.block.time,
+        )?)?),
         QueryMsg::Account { addr } => Ok(to_json_binary(&account(
             deps.storage,
             &deps.api.addr_validate(&addr)?,
         )?)?),
+        QueryMsg::Accounts { page } => {
+            let limit = page.limit(ACCOUNTS_LIMIT, ACCOUNTS_LIMIT);
+            let page = page.try_map(|x| deps.api.addr_validate(&x))?;
+            let accounts = accounts(deps.storage, page.start_after, limit)?;
+            Ok(to_json_binary(&AccountsResponse {
+                page: PageResponse::new(&accounts, limit, |x| x.addr.clone()),
+                accounts,
+            })?)
+        }
+        QueryMsg::Capacity { addr } => Ok(to_json_binary(&capacity(
+            deps.storage,
+            &Config::load(deps.storage)?,
+            env.block.time,
+            &deps.api.addr_validate(&addr)?,
+        )?)?),
     }
 }
 
@@ -100,9 +182,16 @@ pub fn migrate(deps: DepsMut, _env: Env, msg: BankMsg) -> Result<Response, Contr
 #[cfg(test)]
 mod tests {
 
-    use cosmwasm_std::{coin, coins, BlockInfo, Event, Timestamp, Uint128};
+    use cosmwasm_std::{
+        coin, coins, from_json,
+        testing::{message_info, mock_dependencies_with_balance, mock_env},
+        Addr, BlockInfo, Decimal, Event, Timestamp, Uint128,
+    };
     use cw_multi_test::{BasicApp, ContractWrapper, Executor};
-    use rujira_rs::merge::{AccountResponse, StatusResponse};
+    use rujira_rs::merge::{
+        AccountResponse, CapacityResponse, DepositCaps, ScheduleResponse, StatusResponse,
+    };
+    use rujira_rs::PageRequest;
 
     use super::*;
 
@@ -135,6 +224,7 @@ mod tests {
                 ruji_allocation: Uint128::from(100_000_000u128),
                 decay_starts_at: Timestamp::from_seconds(1_000),
                 decay_ends_at: Timestamp::from_seconds(1_001_000),
+                deposit_caps: None,
             },
             &coins(100_000_000, "uruji"),
             "merge",
@@ -153,6 +243,7 @@ mod tests {
                 ruji_allocation: Uint128::from(10_000_000u128),
                 decay_starts_at: Timestamp::from_seconds(1_000),
                 decay_ends_at: Timestamp::from_seconds(1_001_000),
+                deposit_caps: None,
             },
             &coins(100_000_000, "uruji"),
             "merge",
@@ -171,6 +262,7 @@ mod tests {
                 ruji_allocation: Uint128::from(100_000_000u128),
                 decay_starts_at: Timestamp::from_seconds(1_000),
                 decay_ends_at: Timestamp::from_seconds(1_001_000),
+                deposit_caps: None,
             },
             &coins(100_000_000, "uruji"),
             "merge",
@@ -215,6 +307,7 @@ mod tests {
                     ruji_allocation: Uint128::from(100_000_000u128),
                     decay_starts_at: Timestamp::from_seconds(1_000),
                     decay_ends_at: Timestamp::from_seconds(1_001_000),
+                    deposit_caps: None,
                 },
                 &coins(100_000_000, "uruji"),
                 "merge",
@@ -248,7 +341,14 @@ mod tests {
             StatusResponse {
                 merged: Uint128::from(1_000_000u128),
                 shares: Uint128::from(400_000u128),
-                size: Uint128::from(400_000u128)
+                size: Uint128::from(400_000u128),
+                schedule: ScheduleResponse {
+                    decay_starts_at: Timestamp::from_seconds(1_000),
+                    decay_ends_at: Timestamp::from_seconds(1_001_000),
+                    paused: false,
+                    paused_for: 0,
+                    decay_factor: Decimal::one(),
+                },
             }
         );
 
@@ -272,11 +372,24 @@ mod tests {
             }
         );
 
+        let accounts: AccountsResponse = app
+            .wrap()
+            .query_wasm_smart(
+                contract.clone(),
+                &QueryMsg::Accounts {
+                    page: PageRequest::default(),
+                },
+            )
+            .unwrap();
+        assert_eq!(accounts.accounts, vec![account]);
+        assert_eq!(accounts.page.next, None);
+
         app.execute_contract(
             owner.clone(),
             contract.clone(),
             &ExecuteMsg::Withdraw {
                 share_amount: Uint128::from(200_000u128),
+                payout: None,
             },
             &coins(1_000_000, "ukuji"),
         )
@@ -288,6 +401,7 @@ mod tests {
                 contract.clone(),
                 &ExecuteMsg::Withdraw {
                     share_amount: Uint128::from(200_000u128),
+                    payout: None,
                 },
                 &[],
             )
@@ -309,7 +423,14 @@ mod tests {
             StatusResponse {
                 merged: Uint128::from(1_000_000u128),
                 shares: Uint128::from(200_000u128),
-                size: Uint128::from(200_000u128)
+                size: Uint128::from(200_000u128),
+                schedule: ScheduleResponse {
+                    decay_starts_at: Timestamp::from_seconds(1_000),
+                    decay_ends_at: Timestamp::from_seconds(1_001_000),
+                    paused: false,
+                    paused_for: 0,
+                    decay_factor: Decimal::one(),
+                },
             }
         );
 
@@ -343,6 +464,7 @@ mod tests {
                 contract.clone(),
                 &ExecuteMsg::Withdraw {
                     share_amount: Uint128::from(50_000u128),
+                    payout: None,
                 },
                 &[],
             )
@@ -360,6 +482,7 @@ mod tests {
                 contract.clone(),
                 &ExecuteMsg::Withdraw {
                     share_amount: Uint128::from(50_000u128),
+                    payout: None,
                 },
                 &[],
             )
@@ -381,6 +504,7 @@ mod tests {
                 contract.clone(),
                 &ExecuteMsg::Withdraw {
                     share_amount: Uint128::from(50_000u128),
+                    payout: None,
                 },
                 &[],
             )
@@ -398,6 +522,7 @@ mod tests {
                 contract.clone(),
                 &ExecuteMsg::Withdraw {
                     share_amount: Uint128::from(50_000u128),
+                    payout: None,
                 },
                 &[],
             )
@@ -409,4 +534,281 @@ mod tests {
             ("amount", "174500uruji"),
         ]));
     }
+
+    #[test]
+    fn deposit_caps() {
+        let mut deps = mock_dependencies_with_balance(&coins(100_000_000, "x/ruji"));
+        let mut env = mock_env();
+        let owner = deps.api.addr_make("owner");
+        let other = deps.api.addr_make("other");
+        let week = 604_800;
+        let caps = DepositCaps {
+            start: env.block.time,
+            window: week,
+            account: Some(Uint128::from(1_000_000u128)),
+            global: Some(Uint128::from(1_500_000u128)),
+        };
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&owner, &coins(100_000_000, "x/ruji")),
+            InstantiateMsg {
+                merge_denom: "ukuji".to_string(),
+                merge_supply: Uint128::from(250_000_000u128),
+                ruji_denom: "x/ruji".to_string(),
+                ruji_allocation: Uint128::from(100_000_000u128),
+                decay_starts_at: env.block.time.plus_seconds(1_000),
+                decay_ends_at: env.block.time.plus_seconds(1_001_000),
+                deposit_caps: Some(caps.clone()),
+            },
+        )
+        .unwrap();
+        fn deposit(
+            deps: DepsMut,
+            env: &Env,
+            sender: &Addr,
+            amount: u128,
+        ) -> Result<Response, ContractError> {
+            execute(
+                deps,
+                env.clone(),
+                message_info(sender, &coins(amount, "ukuji")),
+                ExecuteMsg::Deposit {},
+            )
+        }
+        fn capacity(deps: Deps, env: &Env, addr: &Addr) -> CapacityResponse {
+            from_json(
+                query(
+                    deps,
+                    env.clone(),
+                    QueryMsg::Capacity {
+                        addr: addr.to_string(),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        }
+
+        deposit(deps.as_mut(), &env, &owner, 600_000).unwrap();
+        let err = deposit(deps.as_mut(), &env, &owner, 400_001).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::AccountCapExceeded { remaining, .. } if remaining == Uint128::from(400_000u128)
+        ));
+        deposit(deps.as_mut(), &env, &owner, 400_000).unwrap();
+
+        assert_eq!(
+            capacity(deps.as_ref(), &env, &other),
+            CapacityResponse {
+                account: Some(Uint128::from(1_000_000u128)),
+                global: Some(Uint128::from(500_000u128)),
+                resets_at: Some(env.block.time.plus_seconds(week)),
+            }
+        );
+        let err = deposit(deps.as_mut(), &env, &other, 500_001).unwrap_err();
+        assert!(matches!(err, ContractError::GlobalCapExceeded { .. }));
+
+        // Caps reset in the next window
+        env.block.time = env.block.time.plus_seconds(week);
+        assert_eq!(
+            capacity(deps.as_ref(), &env, &owner),
+            CapacityResponse {
+                account: Some(Uint128::from(1_000_000u128)),
+                global: Some(Uint128::from(1_500_000u128)),
+                resets_at: Some(caps.start.plus_seconds(2 * week)),
+            }
+        );
+        deposit(deps.as_mut(), &env, &other, 1_000_000).unwrap();
+
+        sudo(deps.as_mut(), env.clone(), SudoMsg::SetDepositCaps(None)).unwrap();
+        assert_eq!(
+            capacity(deps.as_ref(), &env, &other),
+            CapacityResponse {
+                account: None,
+                global: None,
+                resets_at: None,
+            }
+        );
+        deposit(deps.as_mut(), &env, &other, 10_000_000).unwrap();
+
+        sudo(
+            deps.as_mut(),
+            env.clone(),
+            SudoMsg::SetDepositCaps(Some(DepositCaps { window: 0, ..caps })),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn update_schedule() {
+        let mut deps = mock_dependencies_with_balance(&coins(100_000_000, "x/ruji"));
+        let mut env = mock_env();
+        let owner = deps.api.addr_make("owner");
+        let start = env.block.time;
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&owner, &coins(100_000_000, "x/ruji")),
+            InstantiateMsg {
+                merge_denom: "ukuji".to_string(),
+                merge_supply: Uint128::from(250_000_000u128),
+                ruji_denom: "x/ruji".to_string(),
+                ruji_allocation: Uint128::from(100_000_000u128),
+                decay_starts_at: start.plus_seconds(1_000),
+                decay_ends_at: start.plus_seconds(1_001_000),
+                deposit_caps: None,
+            },
+        )
+        .unwrap();
+        fn update(
+            deps: DepsMut,
+            env: &Env,
+            starts: Timestamp,
+            ends: Timestamp,
+            paused: bool,
+        ) -> Result<Response, ContractError> {
+            sudo(
+                deps,
+                env.clone(),
+                SudoMsg::UpdateSchedule {
+                    decay_starts_at: starts,
+                    decay_ends_at: ends,
+                    paused,
+                },
+            )
+        }
+        fn schedule(deps: Deps, env: &Env) -> ScheduleResponse {
+            let status: StatusResponse =
+                from_json(query(deps, env.clone(), QueryMsg::Status {}).unwrap()).unwrap();
+            status.schedule
+        }
+
+        let starts = start.plus_seconds(2_000);
+        let ends = start.plus_seconds(2_002_000);
+        update(deps.as_mut(), &env, starts, starts.minus_seconds(1), false).unwrap_err();
+        update(deps.as_mut(), &env, start.minus_seconds(1), ends, false).unwrap_err();
+        update(deps.as_mut(), &env, starts, ends, false).unwrap();
+        assert_eq!(
+            schedule(deps.as_ref(), &env),
+            ScheduleResponse {
+                decay_starts_at: starts,
+                decay_ends_at: ends,
+                paused: false,
+                paused_for: 0,
+                decay_factor: Decimal::one(),
+            }
+        );
+
+        // Once the decay has started the schedule can't be moved
+        env.block.time = start.plus_seconds(502_000);
+        assert_eq!(
+            schedule(deps.as_ref(), &env).decay_factor,
+            Decimal::percent(75)
+        );
+        let err = update(deps.as_mut(), &env, starts, ends.plus_seconds(1), false).unwrap_err();
+        assert!(matches!(err, ContractError::Invalid(x) if x == "decay has started"));
+
+        // The decay factor is held while paused
+        update(deps.as_mut(), &env, starts, ends, true).unwrap();
+        env.block.time = env.block.time.plus_seconds(100_000);
+        assert_eq!(
+            schedule(deps.as_ref(), &env),
+            ScheduleResponse {
+                decay_starts_at: starts,
+                decay_ends_at: ends,
+                paused: true,
+                paused_for: 100_000,
+                decay_factor: Decimal::percent(75),
+            }
+        );
+
+        // And resumes from where it was paused
+        update(deps.as_mut(), &env, starts, ends, false).unwrap();
+        assert_eq!(
+            schedule(deps.as_ref(), &env).decay_factor,
+            Decimal::percent(75)
+        );
+        env.block.time = env.block.time.plus_seconds(500_000);
+        assert_eq!(
+            schedule(deps.as_ref(), &env),
+            ScheduleResponse {
+                decay_starts_at: starts,
+                decay_ends_at: ends,
+                paused: false,
+                paused_for: 100_000,
+                decay_factor: Decimal::percent(50),
+            }
+        );
+    }
+
+    #[test]
+    fn withdraw_payout() {
+        let mut deps = mock_dependencies_with_balance(&coins(100_000_000, "x/ruji"));
+        let env = mock_env();
+        let owner = deps.api.addr_make("owner");
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&owner, &coins(100_000_000, "x/ruji")),
+            InstantiateMsg {
+                merge_denom: "ukuji".to_string(),
+                merge_supply: Uint128::from(250_000_000u128),
+                ruji_denom: "x/ruji".to_string(),
+                ruji_allocation: Uint128::from(100_000_000u128),
+                decay_starts_at: env.block.time.plus_seconds(1_000),
+                decay_ends_at: env.block.time.plus_seconds(1_001_000),
+                deposit_caps: None,
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&owner, &coins(1_000_000, "ukuji")),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+
+        let withdraw = |deps: DepsMut, to: &str, destination: &str| {
+            execute(
+                deps,
+                env.clone(),
+                message_info(&owner, &[]),
+                ExecuteMsg::Withdraw {
+                    share_amount: Uint128::from(200_000u128),
+                    payout: Some(Payout::Swap {
+                        to: to.to_string(),
+                        destination: destination.to_string(),
+                        min_return: Some(Uint128::from(5_000u128)),
+                    }),
+                },
+            )
+        };
+
+        // Fields that would add to or reroute the memo, eg the refund address, are rejected
+        withdraw(deps.as_mut(), "BTC.BTC", "").unwrap_err();
+        withdraw(deps.as_mut(), "BTC.BTC", "bc1qxy2kg/attacker").unwrap_err();
+        withdraw(deps.as_mut(), "BTC.BTC", "bc1qxy2kg:0:attacker:100").unwrap_err();
+        withdraw(deps.as_mut(), "BTC.BTC:bc1qxy2kg", "bc1qxy2kg").unwrap_err();
+
+        let res = withdraw(deps.as_mut(), "btc.btc", "bc1qxy2kg").unwrap();
+        let expected: CosmosMsg = MsgSwap::new(
+            (&coin(200_000, "x/ruji")).try_into().unwrap(),
+            Asset::from_str("BTC.BTC").unwrap(),
+            Destination::Refundable {
+                destination_addr: Addr::unchecked("bc1qxy2kg"),
+                refund_addr: owner.clone(),
+            },
+            Some(Slip::Limit(Uint256::from(5_000u128))),
+            None,
+            None,
+            deps.api
+                .addr_canonicalize(env.contract.address.as_str())
+                .unwrap(),
+        )
+        .into();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].msg, expected);
+    }
 }
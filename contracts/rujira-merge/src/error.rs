@@ -1,6 +1,6 @@
-use cosmwasm_std::{CheckedFromRatioError, OverflowError, StdError};
+use cosmwasm_std::{CheckedFromRatioError, OverflowError, StdError, Timestamp, Uint128};
 use cw_utils::PaymentError;
-use rujira_rs::SharePoolError;
+use rujira_rs::{AssetError, SharePoolError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -20,6 +20,9 @@ pub enum ContractError {
     #[error("{0}")]
     SharePool(#[from] SharePoolError),
 
+    #[error("{0}")]
+    Asset(#[from] AssetError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -28,6 +31,18 @@ pub enum ContractError {
 
     #[error("Invalid: {0}")]
     Invalid(String),
+
+    #[error("AccountCapExceeded: {remaining} remaining until {resets_at}")]
+    AccountCapExceeded {
+        remaining: Uint128,
+        resets_at: Timestamp,
+    },
+
+    #[error("GlobalCapExceeded: {remaining} remaining until {resets_at}")]
+    GlobalCapExceeded {
+        remaining: Uint128,
+        resets_at: Timestamp,
+    },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
@@ -3,10 +3,10 @@ use std::{
     ops::{Add, Mul, Sub},
 };
 
-use cosmwasm_std::{Addr, Decimal, StdResult, Storage, Timestamp, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Decimal, Order, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
 use rujira_rs::{
-    merge::{AccountResponse, StatusResponse},
+    merge::{AccountResponse, CapacityResponse, DepositCaps, ScheduleResponse, StatusResponse},
     SharePool,
 };
 
@@ -15,6 +15,9 @@ use crate::{config::Config, ContractError};
 pub static TOTAL_MERGED: Item<Uint128> = Item::new("merged");
 pub static POOL: Item<SharePool> = Item::new("pool");
 pub static ACCOUNTS: Map<Addr, (Uint128, Uint128)> = Map::new("accounts");
+// (window, amount) deposited in the latest deposit cap window, in total and by account
+pub static WINDOW_MERGED: Item<(u64, Uint128)> = Item::new("window-merged");
+pub static WINDOW_ACCOUNTS: Map<Addr, (u64, Uint128)> = Map::new("window-accounts");
 
 pub fn init(storage: &mut dyn Storage) -> StdResult<()> {
     TOTAL_MERGED.save(storage, &Uint128::zero())?;
@@ -22,12 +25,19 @@ pub fn init(storage: &mut dyn Storage) -> StdResult<()> {
     Ok(())
 }
 
-pub fn status(storage: &dyn Storage) -> StdResult<StatusResponse> {
+pub fn status(storage: &dyn Storage, config: &Config, now: Timestamp) -> StdResult<StatusResponse> {
     let pool = POOL.load(storage)?;
     Ok(StatusResponse {
         merged: TOTAL_MERGED.load(storage)?,
         shares: pool.shares(),
         size: pool.size(),
+        schedule: ScheduleResponse {
+            decay_starts_at: config.decay_starts_at,
+            decay_ends_at: config.decay_ends_at,
+            paused: config.paused_at.is_some(),
+            paused_for: config.paused_for(&now),
+            decay_factor: decay_factor(config, &now),
+        },
     })
 }
 
@@ -43,6 +53,53 @@ pub fn account(storage: &dyn Storage, addr: &Addr) -> Result<AccountResponse, Co
     })
 }
 
+pub fn accounts(
+    storage: &dyn Storage,
+    start_after: Option<Addr>,
+    limit: usize,
+) -> Result<Vec<AccountResponse>, ContractError> {
+    let pool = POOL.load(storage)?;
+    ACCOUNTS
+        .range(
+            storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|x| {
+            let (addr, (shares, merged)) = x?;
+            Ok(AccountResponse {
+                addr: addr.to_string(),
+                shares,
+                merged,
+                size: pool.ownership(shares),
+            })
+        })
+        .collect()
+}
+
+pub fn capacity(
+    storage: &dyn Storage,
+    config: &Config,
+    now: Timestamp,
+    addr: &Addr,
+) -> StdResult<CapacityResponse> {
+    let Some(caps) = &config.deposit_caps else {
+        return Ok(CapacityResponse {
+            account: None,
+            global: None,
+            resets_at: None,
+        });
+    };
+    let (window, global, account) = window_merged(storage, caps, &now, addr)?;
+    Ok(CapacityResponse {
+        account: caps.account.map(|x| x.saturating_sub(account)),
+        global: caps.global.map(|x| x.saturating_sub(global)),
+        resets_at: Some(caps.window_end(window)),
+    })
+}
+
 /// Deposit an `amount` of merge tokens to an account.
 /// Returns the amount of share tokens issued
 pub fn execute_deposit(
@@ -53,6 +110,9 @@ pub fn execute_deposit(
     account: &Addr,
     amount: Uint128,
 ) -> Result<Uint128, ContractError> {
+    if let Some(caps) = &config.deposit_caps {
+        apply_deposit_caps(storage, caps, &now, account, amount)?;
+    }
     let factor = decay_factor(config, &now);
     // 1. We allocate before we process the deposit in order to increase the share ratio _after_ the
     // suplus has been applied to it. Otherwise a new depositor would instantly earn a share of the
@@ -104,6 +164,57 @@ pub fn execute_withdraw(
     Ok(allocation)
 }
 
+/// The (window, global, account) amounts deposited in the current window of `caps`
+fn window_merged(
+    storage: &dyn Storage,
+    caps: &DepositCaps,
+    now: &Timestamp,
+    account: &Addr,
+) -> StdResult<(u64, Uint128, Uint128)> {
+    let window = caps.window_at(now);
+    let current = |(w, amount): (u64, Uint128)| if w == window { amount } else { Uint128::zero() };
+    let global = WINDOW_MERGED.may_load(storage)?.map(current);
+    let account = WINDOW_ACCOUNTS
+        .may_load(storage, account.clone())?
+        .map(current);
+    Ok((
+        window,
+        global.unwrap_or_default(),
+        account.unwrap_or_default(),
+    ))
+}
+
+/// Ensures that depositing `amount` stays within the caps of the current window, and records it
+fn apply_deposit_caps(
+    storage: &mut dyn Storage,
+    caps: &DepositCaps,
+    now: &Timestamp,
+    account: &Addr,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let (window, global, merged) = window_merged(storage, caps, now, account)?;
+    let resets_at = caps.window_end(window);
+    if let Some(cap) = caps.account {
+        if merged.add(amount).gt(&cap) {
+            return Err(ContractError::AccountCapExceeded {
+                remaining: cap.saturating_sub(merged),
+                resets_at,
+            });
+        }
+    }
+    if let Some(cap) = caps.global {
+        if global.add(amount).gt(&cap) {
+            return Err(ContractError::GlobalCapExceeded {
+                remaining: cap.saturating_sub(global),
+                resets_at,
+            });
+        }
+    }
+    WINDOW_MERGED.save(storage, &(window, global.add(amount)))?;
+    WINDOW_ACCOUNTS.save(storage, account.clone(), &(window, merged.add(amount)))?;
+    Ok(())
+}
+
 /// Calculates the maximum liability of the contract based on the remaining merge tokens,
 /// and therefore the surplus supply of allocation, which can be dsitributed to current
 /// Share holders (mergers), and increases TOTAL_ALLOCATED accordingly
@@ -125,8 +236,9 @@ fn allocate(
     Ok(pool)
 }
 
-/// The amount of decay remaining in a linear model
+/// The amount of decay remaining in a linear model, at the time the schedule has reached
 fn decay_factor(config: &Config, now: &Timestamp) -> Decimal {
+    let now = &config.clock(now);
     if now.le(&config.decay_starts_at) {
         return Decimal::one();
     }
@@ -161,6 +273,9 @@ mod tests {
             ruji_allocation: Uint128::from(100_000_000u128),
             decay_starts_at: Timestamp::from_seconds(1_000),
             decay_ends_at: Timestamp::from_seconds(1_000_000),
+            deposit_caps: None,
+            paused_at: None,
+            paused_for: 0,
         };
         let now = Timestamp::from_seconds(0);
         let mut deps = mock_dependencies();
@@ -265,6 +380,9 @@ mod tests {
             ruji_allocation: Uint128::from(100_000_000u128),
             decay_starts_at: Timestamp::from_seconds(1_000),
             decay_ends_at: Timestamp::from_seconds(1_001_000),
+            deposit_caps: None,
+            paused_at: None,
+            paused_for: 0,
         };
         let mut deps = mock_dependencies();
         let storage = deps.as_mut().storage;
@@ -400,6 +518,9 @@ mod tests {
             ruji_allocation: Uint128::from(100_000_000u128),
             decay_starts_at: Timestamp::from_seconds(0),
             decay_ends_at: Timestamp::from_seconds(1_000_000),
+            deposit_caps: None,
+            paused_at: None,
+            paused_for: 0,
         };
         let mut deps = mock_dependencies();
         let storage = deps.as_mut().storage;
@@ -0,0 +1,269 @@
+use cosmwasm_std::{coin, Addr, Decimal, Event, Storage, Timestamp, Uint128};
+use cw_utils::NativeBalance;
+use rujira_rs::orca::Denoms;
+use std::cmp::Ordering;
+use std::ops::{Mul, Sub};
+
+use rujira_pilot::{
+    order::{Order, MAX_LIMIT},
+    pool::Pool,
+};
+
+use crate::{
+    events::{event_claim_bid, event_create_bid, event_increase_bid, event_retract_bid},
+    ContractError,
+};
+
+pub struct BidManager {
+    denoms: Denoms,
+    fee: Decimal,
+    owner: Addr,
+    timestamp: Timestamp,
+    oracle: Decimal,
+    max_premium: u8,
+    // NativeBalance can't be negative. Store in and out separately and we'll validate
+    // no negative balances at the end
+    // What we receive from the user and claimed and retracted bids
+    receive: NativeBalance,
+    // What we spend creating and increasing bids
+    send: NativeBalance,
+    fees: NativeBalance,
+    events: Vec<Event>,
+}
+
+impl BidManager {
+    pub fn new(
+        denoms: Denoms,
+        fee: Decimal,
+        max_premium: u8,
+        owner: Addr,
+        timestamp: Timestamp,
+        oracle: Decimal,
+        funds: NativeBalance,
+    ) -> Self {
+        Self {
+            denoms,
+            fee,
+            max_premium,
+            owner,
+            timestamp,
+            oracle,
+            receive: funds,
+            send: NativeBalance::default(),
+            fees: NativeBalance::default(),
+            events: vec![],
+        }
+    }
+
+    pub fn execute_bids(
+        &mut self,
+        storage: &mut dyn Storage,
+        b: Vec<(u8, Uint128)>,
+    ) -> Result<ExecutionResult, ContractError> {
+        for (premium, target) in b {
+            if premium > self.max_premium {
+                return Err(ContractError::InvalidPremium { premium });
+            }
+            let mut pool = Pool::load(storage, &premium, &self.oracle);
+            match pool.load_order(storage, &self.owner) {
+                Ok(mut bid) => self.execute_existing_bid(storage, &mut pool, &mut bid, target)?,
+                Err(rujira_pilot::ContractError::NotFound {}) => {
+                    self.execute_new_bid(storage, &mut pool, target)?
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        for x in self.send.clone().into_vec() {
+            self.receive = (self.receive.clone() - x)?;
+        }
+
+        Ok(self.into())
+    }
+
+    /// Claims the filled collateral of every bid of the owner
+    pub fn claim_all(
+        &mut self,
+        storage: &mut dyn Storage,
+    ) -> Result<ExecutionResult, ContractError> {
+        // Premiums are capped below MAX_LIMIT, so a single page holds every bid
+        for (premium, mut bid) in Order::by_owner(storage, &self.owner, None, Some(MAX_LIMIT))? {
+            let mut pool = Pool::load(storage, &premium, &self.oracle);
+            pool.sync_order(storage, &mut bid)?;
+            self.maybe_claim(storage, &mut pool, &mut bid)?;
+        }
+        Ok(self.into())
+    }
+
+    fn execute_existing_bid(
+        &mut self,
+        storage: &mut dyn Storage,
+        pool: &mut Pool,
+        bid: &mut Order,
+        target: Uint128,
+    ) -> Result<(), ContractError> {
+        self.maybe_claim(storage, pool, bid)?;
+        let amount = bid.amount();
+        match amount.cmp(&target) {
+            Ordering::Less => {
+                let diff = target - amount;
+                let amount = pool.increase_order(storage, bid, &self.timestamp, diff)?;
+                self.send += coin(amount.u128(), self.denoms.bid());
+                self.events.push(event_increase_bid(pool, bid, &diff));
+            }
+            Ordering::Greater => {
+                let diff = amount - target;
+                let amount = pool.retract_order(storage, bid, &self.timestamp, Some(diff))?;
+                self.receive += coin(amount.u128(), self.denoms.bid());
+                self.events.push(event_retract_bid(pool, bid, &diff));
+            }
+            Ordering::Equal => {}
+        }
+        Ok(())
+    }
+
+    fn execute_new_bid(
+        &mut self,
+        storage: &mut dyn Storage,
+        pool: &mut Pool,
+        target: Uint128,
+    ) -> Result<(), ContractError> {
+        let bid = pool.create_order(storage, &self.timestamp, &self.owner, target)?;
+        self.send += coin(bid.amount().u128(), self.denoms.bid());
+        self.events.push(event_create_bid(pool, &bid));
+        Ok(())
+    }
+
+    fn maybe_claim(
+        &mut self,
+        storage: &mut dyn Storage,
+        pool: &mut Pool,
+        bid: &mut Order,
+    ) -> Result<(), ContractError> {
+        if bid.bid.filled().is_zero() {
+            return Ok(());
+        }
+        let amount = pool.claim_order(storage, bid)?;
+        let fees = Decimal::from_ratio(amount, 1u128)
+            .mul(self.fee)
+            .to_uint_ceil();
+
+        self.receive += coin(amount.sub(fees).u128(), self.denoms.collateral());
+        self.fees += coin(fees.u128(), self.denoms.collateral());
+        self.events.push(event_claim_bid(pool, bid, &amount));
+        Ok(())
+    }
+}
+
+impl From<&mut BidManager> for ExecutionResult {
+    fn from(e: &mut BidManager) -> Self {
+        e.fees.normalize();
+        e.receive.normalize();
+        Self {
+            withdraw: e.receive.clone(),
+            fees: e.fees.clone(),
+            events: e.events.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ExecutionResult {
+    pub withdraw: NativeBalance,
+    pub fees: NativeBalance,
+    pub events: Vec<Event>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{
+        coins,
+        testing::{message_info, mock_dependencies, mock_env},
+    };
+    use rujira_rs::exchange::Swappable;
+    use std::str::FromStr;
+
+    fn manager(funds: NativeBalance) -> BidManager {
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked("addr0000"), &[]);
+        BidManager::new(
+            Denoms::new("btc-btc", "eth-usdc"),
+            Decimal::from_str("0.001").unwrap(),
+            30,
+            info.sender,
+            env.block.time,
+            Decimal::one(),
+            funds,
+        )
+    }
+
+    #[test]
+    fn test_moving_bids() {
+        let mut deps = mock_dependencies();
+
+        manager(NativeBalance(coins(4500, "eth-usdc")))
+            .execute_bids(
+                &mut deps.storage,
+                vec![
+                    (0, Uint128::from(1000u128)),
+                    (1, Uint128::from(2000u128)),
+                    (10, Uint128::from(1500u128)),
+                ],
+            )
+            .unwrap();
+
+        let res = manager(NativeBalance::default())
+            .execute_bids(
+                &mut deps.storage,
+                vec![
+                    // Split 2000 into 2 x 1000
+                    (1, Uint128::from(1000u128)),
+                    (2, Uint128::from(1000u128)),
+                    (10, Uint128::zero()),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(res.withdraw, NativeBalance(coins(1500, "eth-usdc")));
+        assert_eq!(res.events.len(), 3);
+        assert_eq!(res.events[0].ty, "rujira-orca/bid.retract");
+        assert_eq!(res.events[1].ty, "rujira-orca/bid.create");
+        assert_eq!(res.events[2].ty, "rujira-orca/bid.retract");
+
+        let err = manager(NativeBalance::default())
+            .execute_bids(&mut deps.storage, vec![(31, Uint128::from(1000u128))])
+            .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPremium { premium: 31 }));
+    }
+
+    #[test]
+    fn test_claim_all() {
+        let mut deps = mock_dependencies();
+
+        manager(NativeBalance(coins(2000, "eth-usdc")))
+            .execute_bids(
+                &mut deps.storage,
+                vec![(0, Uint128::from(1000u128)), (5, Uint128::from(1000u128))],
+            )
+            .unwrap();
+
+        // Fill the premium 0 bid entirely at a rate of 1
+        let mut pool = Pool::load(&deps.storage, &0, &Decimal::one());
+        pool.swap(Uint128::from(1000u128)).unwrap();
+        pool.commit(&mut deps.storage).unwrap();
+
+        let res = manager(NativeBalance::default())
+            .claim_all(&mut deps.storage)
+            .unwrap();
+        assert_eq!(res.withdraw, NativeBalance(coins(999, "btc-btc")));
+        assert_eq!(res.fees, NativeBalance(coins(1, "btc-btc")));
+        assert_eq!(res.events.len(), 1);
+
+        // Nothing left to claim
+        let res = manager(NativeBalance::default())
+            .claim_all(&mut deps.storage)
+            .unwrap();
+        assert_eq!(res.withdraw, NativeBalance::default());
+    }
+}
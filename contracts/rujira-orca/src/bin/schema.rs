@@ -0,0 +1,12 @@
+use cosmwasm_schema::write_api;
+
+use rujira_rs::orca;
+
+fn main() {
+    write_api! {
+        instantiate: orca::InstantiateMsg,
+        execute: orca::ExecuteMsg,
+        query: orca::QueryMsg,
+        sudo: orca::SudoMsg,
+    }
+}
@@ -0,0 +1,99 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Api, Decimal, QuerierWrapper, StdResult, Storage};
+use cw_storage_plus::Item;
+use rujira_rs::{
+    orca::{ConfigResponse, Denoms, InstantiateMsg},
+    Oracle, SecuredAsset,
+};
+
+use rujira_pilot::order::MAX_LIMIT;
+
+use crate::ContractError;
+
+pub static CONFIG: Item<Config> = Item::new("config");
+
+#[cw_serde]
+pub struct Config {
+    pub denoms: Denoms,
+    pub markets: Vec<Addr>,
+    pub max_premium: u8,
+    pub fee: Decimal,
+    pub fee_address: Addr,
+}
+
+impl Config {
+    pub fn new(api: &dyn Api, value: InstantiateMsg) -> StdResult<Self> {
+        Ok(Self {
+            denoms: value.denoms.clone(),
+            markets: value
+                .markets
+                .iter()
+                .map(|x| api.addr_validate(x))
+                .collect::<StdResult<Vec<Addr>>>()?,
+            max_premium: value.max_premium,
+            fee: value.fee,
+            fee_address: api.addr_validate(value.fee_address.as_str())?,
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), ContractError> {
+        SecuredAsset::from_denom(&self.denoms.collateral().to_string())?;
+        SecuredAsset::from_denom(&self.denoms.bid().to_string())?;
+        // Every bid of an owner must fit in a single page to be claimed at once
+        if self.max_premium >= MAX_LIMIT {
+            return Err(ContractError::InvalidConfig {
+                key: "max_premium".to_string(),
+                value: self.max_premium.to_string(),
+            });
+        }
+        if self.fee >= Decimal::one() {
+            return Err(ContractError::InvalidConfig {
+                key: "fee".to_string(),
+                value: self.fee.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The oracle price of the collateral denom, in units of the bid denom
+    pub fn oracle_price(&self, q: QuerierWrapper) -> Result<Decimal, ContractError> {
+        Ok([
+            SecuredAsset::from_denom(&self.denoms.collateral().to_string())?.to_layer_1(),
+            SecuredAsset::from_denom(&self.denoms.bid().to_string())?.to_layer_1(),
+        ]
+        .oracle_price(q)?)
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) -> StdResult<()> {
+        CONFIG.save(storage, self)
+    }
+
+    pub fn update(
+        &mut self,
+        markets: Option<Vec<Addr>>,
+        fee: Option<Decimal>,
+        fee_address: Option<Addr>,
+    ) {
+        if let Some(markets) = markets {
+            self.markets = markets;
+        }
+        if let Some(fee) = fee {
+            self.fee = fee;
+        }
+        if let Some(fee_address) = fee_address {
+            self.fee_address = fee_address;
+        }
+    }
+}
+
+impl From<Config> for ConfigResponse {
+    fn from(value: Config) -> Self {
+        Self {
+            denoms: value.denoms,
+            markets: value.markets.iter().map(|x| x.to_string()).collect(),
+            max_premium: value.max_premium,
+            fee: value.fee,
+            fee_address: value.fee_address.to_string(),
+        }
+    }
+}
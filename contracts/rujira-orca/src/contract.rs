@@ -0,0 +1,441 @@
+use crate::bid_manager::{BidManager, ExecutionResult};
+use crate::config::{Config, CONFIG};
+use crate::error::ContractError;
+use crate::events::event_liquidate;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    coin, ensure, to_json_binary, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env,
+    MessageInfo, Response,
+};
+use cw2::set_contract_version;
+use cw_utils::{must_pay, nonpayable, NativeBalance};
+use rujira_pilot::{
+    order::{Order, MAX_LIMIT},
+    pool::Pool,
+    premium::Premium,
+};
+use rujira_rs::exchange::{SwapError, SwapResult, Swapper};
+use rujira_rs::fin::SwapRequest;
+use rujira_rs::orca::{
+    BidResponse, BidsResponse, ConfigResponse, ExecuteMsg, InstantiateMsg, PoolResponse,
+    PoolsResponse, QueryMsg, SimulationResponse, SudoMsg,
+};
+use rujira_rs::PageResponse;
+
+// version info for migration info
+const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let config = Config::new(deps.api, msg)?;
+    config.validate()?;
+    config.save(deps.storage)?;
+    Ok(Response::default())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let oracle = config.oracle_price(deps.querier)?;
+    let mut manager = BidManager::new(
+        config.denoms.clone(),
+        config.fee,
+        config.max_premium,
+        info.sender.clone(),
+        env.block.time,
+        oracle,
+        NativeBalance(info.funds.clone()),
+    );
+
+    match msg {
+        ExecuteMsg::Bid(bids) => {
+            let res = manager.execute_bids(deps.storage, bids)?;
+            Ok(withdraw_response(&config, &info, res))
+        }
+        ExecuteMsg::Claim {} => {
+            nonpayable(&info)?;
+            let res = manager.claim_all(deps.storage)?;
+            Ok(withdraw_response(&config, &info, res))
+        }
+        ExecuteMsg::Liquidate {
+            min_return,
+            to,
+            callback,
+        } => {
+            ensure!(
+                config.markets.contains(&info.sender),
+                ContractError::Unauthorized {}
+            );
+            let req = match min_return {
+                Some(min_return) => SwapRequest::Min {
+                    min_return,
+                    to: to.clone(),
+                    callback: callback.clone(),
+                    max_levels: None,
+                },
+                None => SwapRequest::Yolo {
+                    to: to.clone(),
+                    callback: callback.clone(),
+                    max_levels: None,
+                },
+            };
+            let to = to.map(|x| deps.api.addr_validate(&x)).transpose()?;
+            let funds = must_pay(&info, config.denoms.collateral())?;
+            let mut swapper = Swapper::new(env!("CARGO_PKG_NAME"), funds, req, Decimal::zero());
+            let res = simulate_liquidation(&mut swapper, deps.as_ref(), &oracle)?;
+            swapper.commit(deps.storage)?;
+
+            let mut returned = NativeBalance(vec![
+                coin(res.return_amount.u128(), config.denoms.bid()),
+                coin(res.remaining_offer.u128(), config.denoms.collateral()),
+            ]);
+            returned.normalize();
+
+            let recipient = to.unwrap_or(info.sender.clone());
+            let mut messages: Vec<CosmosMsg> = vec![];
+            if !returned.is_empty() {
+                match callback {
+                    None => messages.push(CosmosMsg::Bank(BankMsg::Send {
+                        to_address: recipient.to_string(),
+                        amount: returned.into_vec(),
+                    })),
+                    Some(cb) => messages.push(
+                        cb.to_message(&recipient, Empty {}, returned.into_vec())?
+                            .into(),
+                    ),
+                }
+            }
+
+            Ok(Response::default()
+                .add_messages(messages)
+                .add_events(res.events)
+                .add_event(event_liquidate(
+                    &info.sender,
+                    &coin(res.consumed_offer.u128(), config.denoms.collateral()),
+                    &res.return_amount,
+                )))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::UpdateConfig {
+            markets,
+            fee,
+            fee_address,
+        } => {
+            let markets = markets
+                .map(|x| {
+                    x.iter()
+                        .map(|x| deps.api.addr_validate(x))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .transpose()?;
+            let fee_address = fee_address
+                .map(|x| deps.api.addr_validate(&x))
+                .transpose()?;
+
+            let mut config = CONFIG.load(deps.storage)?;
+            config.update(markets, fee, fee_address);
+            config.validate()?;
+            config.save(deps.storage)?;
+            Ok(Response::default())
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let oracle = config.oracle_price(deps.querier)?;
+    Ok(match msg {
+        QueryMsg::Config {} => to_json_binary(&ConfigResponse::from(config)),
+        QueryMsg::Bid((owner, premium)) => {
+            let addr = deps.api.addr_validate(&owner)?;
+            let pool = Pool::load(deps.storage, &premium, &oracle);
+            let bid = pool.load_order(deps.storage, &addr)?;
+            to_json_binary(&bid_response(&bid, &pool.premium, &oracle))
+        }
+        QueryMsg::Bids {
+            owner,
+            offset,
+            limit,
+        } => {
+            let addr = deps.api.addr_validate(owner.as_str())?;
+            let bids: Result<Vec<BidResponse>, ContractError> =
+                Order::by_owner(deps.storage, &addr, offset, limit)?
+                    .iter_mut()
+                    .map(|(k, bid)| {
+                        let pool = Pool::load(deps.storage, k, &oracle);
+                        pool.sync_order(deps.storage, bid)?;
+                        Ok(bid_response(bid, k, &oracle))
+                    })
+                    .collect();
+
+            to_json_binary(&BidsResponse { bids: bids? })
+        }
+        QueryMsg::Pools { page } => {
+            let limit = page.limit(MAX_LIMIT, MAX_LIMIT);
+            let pools: Vec<PoolResponse> = Pool::iter(deps.storage, &oracle)
+                .skip_while(|v| page.start_after.is_some_and(|s| v.premium <= s))
+                .take(limit)
+                .map(|v| PoolResponse {
+                    premium: v.premium,
+                    epoch: v.pool.epoch(),
+                    price: v.rate,
+                    total: v.pool.total().try_into().unwrap(),
+                })
+                .collect();
+            let page = PageResponse::new(&pools, limit, |x| x.premium);
+
+            to_json_binary(&PoolsResponse { pools, page })
+        }
+        QueryMsg::Simulate(offer) => {
+            let mut swapper = Swapper::new(
+                env!("CARGO_PKG_NAME"),
+                offer.amount,
+                SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                },
+                Decimal::zero(),
+            );
+            let res = simulate_liquidation(&mut swapper, deps, &oracle)?;
+            to_json_binary(&SimulationResponse {
+                returned: res.return_amount,
+                remaining: res.remaining_offer,
+            })
+        }
+    }?)
+}
+
+fn simulate_liquidation(
+    swapper: &mut Swapper<Pool>,
+    deps: Deps,
+    oracle: &Decimal,
+) -> Result<SwapResult, SwapError> {
+    let mut iter = Pool::iter(deps.storage, oracle);
+    swapper.swap(deps.storage, &mut iter)
+}
+
+/// Returns claimed collateral and retracted bids to the owner, and claim fees to the fee address
+fn withdraw_response(config: &Config, info: &MessageInfo, res: ExecutionResult) -> Response {
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !res.withdraw.is_empty() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: res.withdraw.into_vec(),
+        }))
+    }
+    if !res.fees.is_empty() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: config.fee_address.to_string(),
+            amount: res.fees.into_vec(),
+        }))
+    }
+    Response::default()
+        .add_messages(messages)
+        .add_events(res.events)
+}
+
+fn bid_response(bid: &Order, premium: &u8, oracle: &Decimal) -> BidResponse {
+    BidResponse {
+        owner: bid.owner.to_string(),
+        premium: *premium,
+        rate: premium.to_rate(oracle),
+        updated_at: bid.updated_at,
+        offer: bid.offer,
+        remaining: bid.bid.amount().try_into().unwrap(),
+        filled: bid.bid.filled().try_into().unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::Orca;
+    use cosmwasm_std::{coins, Addr, Event, Uint128};
+    use cw_multi_test::Executor;
+    use rujira_rs::orca::Denoms;
+    use rujira_rs_testing::{mock_rujira_app, RujiraApp};
+
+    const BTC: &str = "btc-btc";
+    const USDC: &str = "eth-usdc";
+
+    fn setup() -> (RujiraApp, Orca, Addr) {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let market = app.api().addr_make("market");
+        let fees = app.api().addr_make("fees");
+        app.init_modules(|router, _, storage| {
+            router.stargate.with_prices(vec![
+                ("BTC", Decimal::from_ratio(100u128, 1u128)),
+                ("USDC", Decimal::one()),
+            ]);
+            router
+                .bank
+                .init_balance(storage, &owner, coins(10_000_000, USDC))
+                .unwrap();
+            router
+                .bank
+                .init_balance(storage, &market, coins(100_000, BTC))
+                .unwrap();
+        });
+        let orca = Orca::create(
+            &mut app,
+            &owner,
+            &fees,
+            &[market.clone()],
+            Denoms::new(BTC, USDC),
+        );
+        (app, orca, market)
+    }
+
+    #[test]
+    fn instantiation() {
+        let mut app = mock_rujira_app();
+        let owner = app.api().addr_make("owner");
+        let code = Box::new(cw_multi_test::ContractWrapper::new(
+            execute,
+            instantiate,
+            query,
+        ));
+        let code_id = app.store_code(code);
+        let msg = |denoms: Denoms, max_premium: u8| InstantiateMsg {
+            denoms,
+            markets: vec![],
+            max_premium,
+            fee: Decimal::zero(),
+            fee_address: owner.to_string(),
+        };
+
+        app.instantiate_contract(
+            code_id,
+            owner.clone(),
+            &msg(Denoms::new(BTC, "x/ruji"), 30),
+            &[],
+            "orca",
+            None,
+        )
+        .unwrap_err();
+        app.instantiate_contract(
+            code_id,
+            owner.clone(),
+            &msg(Denoms::new(BTC, USDC), 31),
+            &[],
+            "orca",
+            None,
+        )
+        .unwrap_err();
+        app.instantiate_contract(
+            code_id,
+            owner.clone(),
+            &msg(Denoms::new(BTC, USDC), 30),
+            &[],
+            "orca",
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn liquidate() {
+        let (mut app, orca, market) = setup();
+        let owner = app.api().addr_make("owner");
+
+        orca.bid(
+            &mut app,
+            &owner,
+            vec![
+                (0, Uint128::from(1_000_000u128)),
+                (5, Uint128::from(1_900_000u128)),
+            ],
+            &coins(2_900_000, USDC),
+        )
+        .unwrap();
+
+        // 10_000 BTC fills the 0% tier at 100, and the rest sells into the 5% tier at 95
+        let sim: SimulationResponse = app
+            .wrap()
+            .query_wasm_smart(orca.addr(), &QueryMsg::Simulate(coin(20_000, BTC)))
+            .unwrap();
+        assert_eq!(sim.returned, Uint128::from(1_950_000u128));
+        assert_eq!(sim.remaining, Uint128::zero());
+
+        let liquidate = |min_return: Option<u128>| ExecuteMsg::Liquidate {
+            min_return: min_return.map(Uint128::from),
+            to: None,
+            callback: None,
+        };
+
+        let err = app
+            .execute_contract(
+                owner.clone(),
+                orca.addr().clone(),
+                &liquidate(None),
+                &coins(20_000, BTC),
+            )
+            .unwrap_err();
+        assert!(format!("{:?}", err.root_cause()).contains("Unauthorized"));
+        app.execute_contract(
+            market.clone(),
+            orca.addr().clone(),
+            &liquidate(Some(2_000_000)),
+            &coins(20_000, BTC),
+        )
+        .unwrap_err();
+
+        let res = app
+            .execute_contract(
+                market.clone(),
+                orca.addr().clone(),
+                &liquidate(Some(1_950_000)),
+                &coins(20_000, BTC),
+            )
+            .unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-orca/liquidate").add_attributes(vec![
+                ("market", market.as_str()),
+                ("collateral", "20000btc-btc"),
+                ("returned", "1950000"),
+            ]),
+        );
+        res.assert_event(&Event::new("transfer").add_attributes(vec![
+            ("recipient", market.as_str()),
+            ("sender", orca.addr().as_str()),
+            ("amount", "1950000eth-usdc"),
+        ]));
+
+        let bid = orca.query_bid(&app, &owner, 0).unwrap();
+        assert_eq!(bid.remaining, Uint128::zero());
+        assert_eq!(bid.filled, Uint128::from(10_000u128));
+        let bid = orca.query_bid(&app, &owner, 5).unwrap();
+        assert_eq!(bid.remaining, Uint128::from(950_000u128));
+
+        // Collateral is claimed less the 0.5% fee
+        let res = orca.claim(&mut app, &owner).unwrap();
+        res.assert_event(
+            &Event::new("wasm-rujira-orca/bid.claim")
+                .add_attributes(vec![("premium", "0"), ("amount", "10000")]),
+        );
+        let balance = app.wrap().query_balance(&owner, BTC).unwrap();
+        assert!(balance.amount > Uint128::from(19_800u128));
+        orca.query_bid(&app, &owner, 0).unwrap_err();
+    }
+}
@@ -0,0 +1,60 @@
+use cosmwasm_std::{
+    CheckedFromRatioError, Coin, ConversionOverflowError, OverflowError, StdError, Uint128,
+};
+use cw_utils::PaymentError;
+use rujira_rs::{bid_pool::BidPoolError, exchange::SwapError, OracleError, SecuredAssetError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("{0}")]
+    CheckedFromRatio(#[from] CheckedFromRatioError),
+
+    #[error("{0}")]
+    ConversionOverflow(#[from] ConversionOverflowError),
+
+    #[error("{0}")]
+    BidPool(#[from] BidPoolError),
+
+    #[error("{0}")]
+    Oracle(#[from] OracleError),
+
+    #[error("{0}")]
+    SecuredAsset(#[from] SecuredAssetError),
+
+    #[error("{0}")]
+    Pilot(#[from] rujira_pilot::ContractError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    Swap(#[from] SwapError),
+
+    #[error("InsufficientReturn expected {expected} got {returned}")]
+    InsufficientReturn {
+        expected: Uint128,
+        returned: Uint128,
+    },
+
+    #[error("InsufficientFunds expected {expected} got {returned}")]
+    InsufficientFunds { expected: Coin, returned: Coin },
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("NotFound")]
+    NotFound {},
+
+    #[error("Invalid Premium: {premium}")]
+    InvalidPremium { premium: u8 },
+
+    #[error("Invalid config {key}: {value}")]
+    InvalidConfig { key: String, value: String },
+}
@@ -0,0 +1,38 @@
+use cosmwasm_std::{Addr, Coin, Event, Uint128};
+
+use rujira_pilot::{order::Order, pool::Pool};
+
+pub fn event_create_bid(pool: &Pool, bid: &Order) -> Event {
+    Event::new(format!("{}/bid.create", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", bid.owner.clone())
+        .add_attribute("premium", pool.premium.to_string())
+        .add_attribute("offer", bid.offer)
+}
+
+pub fn event_claim_bid(pool: &Pool, bid: &Order, amount: &Uint128) -> Event {
+    Event::new(format!("{}/bid.claim", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", bid.owner.clone())
+        .add_attribute("premium", pool.premium.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
+pub fn event_increase_bid(pool: &Pool, bid: &Order, amount: &Uint128) -> Event {
+    Event::new(format!("{}/bid.increase", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", bid.owner.clone())
+        .add_attribute("premium", pool.premium.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
+pub fn event_retract_bid(pool: &Pool, bid: &Order, amount: &Uint128) -> Event {
+    Event::new(format!("{}/bid.retract", env!("CARGO_PKG_NAME")))
+        .add_attribute("owner", bid.owner.clone())
+        .add_attribute("premium", pool.premium.to_string())
+        .add_attribute("amount", amount.to_string())
+}
+
+pub fn event_liquidate(market: &Addr, collateral: &Coin, returned: &Uint128) -> Event {
+    Event::new(format!("{}/liquidate", env!("CARGO_PKG_NAME")))
+        .add_attribute("market", market.clone())
+        .add_attribute("collateral", collateral.to_string())
+        .add_attribute("returned", returned.to_string())
+}
@@ -0,0 +1,10 @@
+pub mod bid_manager;
+pub mod config;
+pub mod contract;
+mod error;
+pub mod events;
+
+pub use crate::error::ContractError;
+
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
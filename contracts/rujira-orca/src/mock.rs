@@ -0,0 +1,87 @@
+use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
+use cw_multi_test::{AppResponse, ContractWrapper, Executor};
+
+use rujira_rs::orca::{BidResponse, Denoms, ExecuteMsg, InstantiateMsg, QueryMsg};
+use rujira_rs_testing::RujiraApp;
+
+/// Wrapper struct for ORCA contract with convenience methods
+#[derive(Debug, Clone)]
+pub struct Orca(pub Addr);
+
+impl Orca {
+    /// Get the contract address
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    /// Set the bids of `sender`
+    pub fn bid(
+        &self,
+        app: &mut RujiraApp,
+        sender: &Addr,
+        bids: Vec<(u8, Uint128)>,
+        funds: &[Coin],
+    ) -> anyhow::Result<AppResponse> {
+        app.execute_contract(
+            sender.clone(),
+            self.0.clone(),
+            &ExecuteMsg::Bid(bids),
+            funds,
+        )
+    }
+
+    /// Claim the filled collateral of all bids of `sender`
+    pub fn claim(&self, app: &mut RujiraApp, sender: &Addr) -> anyhow::Result<AppResponse> {
+        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::Claim {}, &[])
+    }
+
+    /// Query a specific bid
+    pub fn query_bid(
+        &self,
+        app: &RujiraApp,
+        owner: &Addr,
+        premium: u8,
+    ) -> anyhow::Result<BidResponse> {
+        Ok(app
+            .wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Bid((owner.to_string(), premium)))?)
+    }
+
+    /// Setup ORCA contract
+    pub fn create(
+        app: &mut RujiraApp,
+        owner: &Addr,
+        fees: &Addr,
+        markets: &[Addr],
+        denoms: Denoms,
+    ) -> Self {
+        let code = Box::new(
+            ContractWrapper::new(
+                crate::contract::execute,
+                crate::contract::instantiate,
+                crate::contract::query,
+            )
+            .with_sudo(crate::contract::sudo),
+        );
+        let code_id = app.store_code(code);
+
+        let addr = app
+            .instantiate_contract(
+                code_id,
+                owner.clone(),
+                &InstantiateMsg {
+                    denoms,
+                    markets: markets.iter().map(|x| x.to_string()).collect(),
+                    max_premium: 30,
+                    fee: Decimal::from_ratio(5u128, 1000u128), // 0.5%
+                    fee_address: fees.to_string(),
+                },
+                &[],
+                "orca",
+                None,
+            )
+            .unwrap();
+
+        Orca(addr)
+    }
+}
@@ -60,10 +60,12 @@ pub fn execute(
                     min_return,
                     to: to.clone(),
                     callback,
+                    max_levels: None,
                 },
                 None => SwapRequest::Yolo {
                     to: to.clone(),
                     callback,
+                    max_levels: None,
                 },
             };
             let to = to.map(|x| deps.api.addr_validate(&x)).transpose()?;
@@ -213,6 +215,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
                 SwapRequest::Yolo {
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
                 config.fee_taker,
             );
@@ -232,7 +235,7 @@ fn simulate_swap(
     oracle: &Decimal,
 ) -> Result<SwapResult, SwapError> {
     let mut iter = Pool::iter(deps.storage, oracle);
-    swapper.swap(&mut iter)
+    swapper.swap(deps.storage, &mut iter)
 }
 
 fn load_oracle_price(_q: QuerierWrapper, _config: &Config) -> Result<Decimal, ContractError> {
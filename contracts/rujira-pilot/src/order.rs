@@ -7,7 +7,7 @@ use cw_storage_plus::Map;
 use rujira_rs::bid_pool;
 
 pub const ORDERS: Map<(Addr, u8), (Timestamp, Uint128, bid_pool::Bid)> = Map::new("orders");
-const MAX_LIMIT: u8 = 31;
+pub const MAX_LIMIT: u8 = 31;
 const DEFAULT_LIMIT: u8 = 10;
 
 #[cw_serde]
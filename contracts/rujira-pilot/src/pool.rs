@@ -170,7 +170,7 @@ impl Swappable for Pool {
         Ok(Commitment::default())
     }
 
-    fn attributes(&self) -> Vec<Attribute> {
+    fn attributes(&self, _storage: &dyn Storage) -> Vec<Attribute> {
         vec![Attribute::new("premium", self.premium.to_string())]
     }
 
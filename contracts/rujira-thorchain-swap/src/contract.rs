@@ -112,7 +112,11 @@ pub fn execute(
                     &quote.memo,
                 ))
                 .add_messages(execute_repayments(deps.as_ref(), env, Some(funds))?)
-                .add_message(vault.borrow_msg(net_return, to.clone(), callback)?)
+                .add_message(vault.borrow_msg(
+                    coin(net_return.u128(), min_return.denom.clone()),
+                    to.clone(),
+                    callback,
+                )?)
                 .add_message(swap_msg))
         }
         ExecuteMsg::Callback(msg) => {
@@ -157,7 +161,7 @@ fn execute_repayments(deps: Deps, env: Env, funds: Option<Coin>) -> StdResult<Ve
 
         match VAULTS.load(deps.storage, balance.denom.clone()) {
             Ok(vault) => {
-                let debt = vault.debt(deps.querier, &env.contract.address)?;
+                let debt = vault.debt(deps.querier, &balance.denom, &env.contract.address)?;
                 let repay = min(debt, amount);
                 // Ghost Vault has truncation issues when calculating repaid shares
                 // TODO: Add a min_repay to ghost vault to protect against small values
@@ -207,7 +211,8 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractErro
             let mut state = match req.data {
                 Some(data) => QuoteState::decode(&data)?,
                 None => {
-                    let borrow_limit = vault.available(deps.querier, &env.contract.address)?;
+                    let borrow_limit =
+                        vault.available(deps.querier, &req.ask_denom, &env.contract.address)?;
 
                     QuoteState::load(
                         deps.querier,
@@ -133,6 +133,7 @@ pub fn setup(app: &mut RujiraApp, owner: &Addr) -> Addr {
                 },
                 fee: Decimal::zero(),
                 fee_address: owner.to_string(),
+                registry: None,
             },
             &[],
             "ghost btc",
@@ -164,6 +165,7 @@ pub fn setup(app: &mut RujiraApp, owner: &Addr) -> Addr {
                 },
                 fee: Decimal::zero(),
                 fee_address: owner.to_string(),
+                registry: None,
             },
             &[],
             "ghost rune",
@@ -200,6 +202,7 @@ pub fn setup(app: &mut RujiraApp, owner: &Addr) -> Addr {
     app.wasm_sudo(
         vault_btc.clone(),
         &ghost::vault::SudoMsg::SetBorrower {
+            denom: "btc-btc".to_string(),
             contract: contract.to_string(),
             limit: Uint128::MAX,
         },
@@ -209,6 +212,7 @@ pub fn setup(app: &mut RujiraApp, owner: &Addr) -> Addr {
     app.wasm_sudo(
         vault_rune.clone(),
         &ghost::vault::SudoMsg::SetBorrower {
+            denom: "rune".to_string(),
             contract: contract.to_string(),
             limit: Uint128::MAX,
         },
@@ -1,5 +1,7 @@
 pub(crate) mod fixtures;
 mod mock;
+mod price_path;
 mod swap;
 pub use mock::*;
+pub use price_path::PricePath;
 pub use swap::{mock_swap_contract, MsgSwap};
@@ -14,7 +14,10 @@ use cw_storage_plus::Map;
 use serde::de::DeserializeOwned;
 use std::{collections::BTreeMap, str::FromStr};
 
-use crate::fixtures::{mock_mimir, mock_network, mock_oracle_price, mock_pool, mock_quote};
+use crate::{
+    fixtures::{mock_mimir, mock_network, mock_oracle_price, mock_pool, mock_quote},
+    PricePath,
+};
 
 pub type RujiraApp = App<
     BankKeeper,
@@ -44,13 +47,22 @@ pub fn mock_rujira_app() -> RujiraApp {
 #[derive(Default)]
 pub struct RujiraStargate {
     prices: BTreeMap<String, Decimal>,
+    paths: BTreeMap<String, PricePath>,
 }
 
 impl RujiraStargate {
+    /// Sets a fixed price, replacing any path set with [RujiraStargate::with_price_path]
     pub fn with_price(&mut self, symbol: &str, price: Decimal) {
+        self.paths.remove(symbol);
         self.prices.insert(symbol.to_string(), price);
     }
 
+    /// Prices `symbol` along `path` at the block time of each query
+    pub fn with_price_path(&mut self, symbol: &str, path: PricePath) {
+        self.prices.remove(symbol);
+        self.paths.insert(symbol.to_string(), path);
+    }
+
     pub fn with_prices(&mut self, prices: Vec<(&str, Decimal)>) {
         for (symbol, price) in prices {
             self.with_price(symbol, price);
@@ -239,7 +251,7 @@ impl Stargate for RujiraStargate {
         _api: &dyn Api,
         _storage: &dyn Storage,
         _querier: &dyn Querier,
-        _block: &BlockInfo,
+        block: &BlockInfo,
         request: GrpcQuery,
     ) -> AnyResult<Binary> {
         match request.path.as_str() {
@@ -247,7 +259,13 @@ impl Stargate for RujiraStargate {
             "/types.Query/Pool" => mock_pool(request.data),
             "/types.Query/Network" => mock_network(),
             "/types.Query/QuoteSwap" => mock_quote(request.data),
-            "/types.Query/OraclePrice" => mock_oracle_price(request.data, &self.prices),
+            "/types.Query/OraclePrice" => {
+                let mut prices = self.prices.clone();
+                for (symbol, path) in &self.paths {
+                    prices.insert(symbol.clone(), path.price(block.time));
+                }
+                mock_oracle_price(request.data, &prices)
+            }
             _ => {
                 anyhow::bail!("Unexpected grpc query: request={:?}", request)
             }
@@ -287,7 +305,7 @@ mod tests {
     use cosmwasm_std::Decimal;
     use rujira_rs::{
         query::{Pool, PoolStatus},
-        Asset, Layer1Asset,
+        Asset, Layer1Asset, Oracle,
     };
 
     use super::*;
@@ -318,4 +336,31 @@ mod tests {
         assert_eq!(res.synth_supply_remaining, Uint128::from(22913550433u128));
         assert_eq!(res.derived_depth_bps, 9639);
     }
+
+    #[test]
+    fn price_path() {
+        let mut app = mock_rujira_app();
+        let start = app.block_info().time;
+        app.init_modules(|router, _, _| {
+            router.stargate.with_price_path(
+                "BTC",
+                PricePath::new(start, Decimal::from_str("100000").unwrap())
+                    .ramp(100, Decimal::from_str("90000").unwrap()),
+            );
+        });
+        let price = |app: &RujiraApp| "BTC".to_string().oracle_price(app.wrap()).unwrap();
+        assert_eq!(price(&app), Decimal::from_str("100000").unwrap());
+        app.update_block(|b| b.time = b.time.plus_seconds(50));
+        assert_eq!(price(&app), Decimal::from_str("95000").unwrap());
+        app.update_block(|b| b.time = b.time.plus_seconds(100));
+        assert_eq!(price(&app), Decimal::from_str("90000").unwrap());
+
+        // A fixed price replaces the path
+        app.init_modules(|router, _, _| {
+            router
+                .stargate
+                .with_price("BTC", Decimal::from_str("1").unwrap());
+        });
+        assert_eq!(price(&app), Decimal::one());
+    }
 }
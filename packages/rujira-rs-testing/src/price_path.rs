@@ -0,0 +1,214 @@
+use cosmwasm_std::{Decimal, Timestamp};
+use std::str::FromStr;
+
+/// A scripted oracle price over block time. Registered on [crate::RujiraStargate] with
+/// `with_price_path`, the price returned by the oracle query is evaluated at the block time
+/// of each query, so tests only need to advance the block.
+///
+/// Segments run back to back from `start`. The price before `start` is the initial price, and
+/// the price after the last segment is the price it ended on
+#[derive(Clone, Debug)]
+pub struct PricePath {
+    start: Timestamp,
+    price: Decimal,
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    /// Linear move to `to` over `duration` seconds
+    Ramp { duration: u64, to: Decimal },
+    /// Unchanged for `duration` seconds
+    Hold { duration: u64 },
+    /// Instant move to `to`
+    Jump { to: Decimal },
+    /// Geometric brownian motion over `duration` seconds, stepping every `interval` seconds.
+    /// `drift` and `volatility` are per step
+    Gbm {
+        duration: u64,
+        interval: u64,
+        drift: f64,
+        volatility: f64,
+        seed: u64,
+    },
+}
+
+impl PricePath {
+    pub fn new(start: Timestamp, price: Decimal) -> Self {
+        Self {
+            start,
+            price,
+            segments: vec![],
+        }
+    }
+
+    pub fn ramp(mut self, duration: u64, to: Decimal) -> Self {
+        self.segments.push(Segment::Ramp { duration, to });
+        self
+    }
+
+    pub fn hold(mut self, duration: u64) -> Self {
+        self.segments.push(Segment::Hold { duration });
+        self
+    }
+
+    pub fn jump(mut self, to: Decimal) -> Self {
+        self.segments.push(Segment::Jump { to });
+        self
+    }
+
+    /// A random walk that is identical for the same `seed`
+    pub fn gbm(
+        mut self,
+        duration: u64,
+        interval: u64,
+        drift: f64,
+        volatility: f64,
+        seed: u64,
+    ) -> Self {
+        assert!(interval > 0, "gbm interval must be positive");
+        self.segments.push(Segment::Gbm {
+            duration,
+            interval,
+            drift,
+            volatility,
+            seed,
+        });
+        self
+    }
+
+    /// The price at block time `time`
+    pub fn price(&self, time: Timestamp) -> Decimal {
+        let mut price = self.price;
+        let Some(mut elapsed) = time.seconds().checked_sub(self.start.seconds()) else {
+            return price;
+        };
+        for segment in &self.segments {
+            match segment {
+                Segment::Ramp { duration, to } => {
+                    if elapsed < *duration {
+                        let progress = Decimal::from_ratio(elapsed, *duration);
+                        return if *to > price {
+                            price + (*to - price) * progress
+                        } else {
+                            price - (price - *to) * progress
+                        };
+                    }
+                    elapsed -= duration;
+                    price = *to;
+                }
+                Segment::Hold { duration } => {
+                    if elapsed < *duration {
+                        return price;
+                    }
+                    elapsed -= duration;
+                }
+                Segment::Jump { to } => price = *to,
+                Segment::Gbm {
+                    duration,
+                    interval,
+                    drift,
+                    volatility,
+                    seed,
+                } => {
+                    let steps = elapsed.min(*duration) / interval;
+                    let mut rng = SplitMix64(*seed);
+                    let mut value = to_f64(price);
+                    for _ in 0..steps {
+                        let z = rng.normal();
+                        value *= ((drift - volatility * volatility / 2.0) + volatility * z).exp();
+                    }
+                    price = from_f64(value);
+                    if elapsed < *duration {
+                        return price;
+                    }
+                    elapsed -= duration;
+                }
+            }
+        }
+        price
+    }
+}
+
+/// Minimal deterministic generator, so that paths don't depend on an external rng
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in (0, 1]
+    fn uniform(&mut self) -> f64 {
+        ((self.next() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal, by Box-Muller
+    fn normal(&mut self) -> f64 {
+        let (u1, u2) = (self.uniform(), self.uniform());
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+fn to_f64(value: Decimal) -> f64 {
+    value.to_string().parse().unwrap()
+}
+
+fn from_f64(value: f64) -> Decimal {
+    Decimal::from_str(&format!("{value:.18}")).unwrap_or(Decimal::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments() {
+        let start = Timestamp::from_seconds(1000);
+        let path = PricePath::new(start, Decimal::from_str("100").unwrap())
+            .ramp(100, Decimal::from_str("50").unwrap())
+            .hold(50)
+            .jump(Decimal::from_str("80").unwrap())
+            .ramp(10, Decimal::from_str("90").unwrap());
+
+        let at = |seconds: u64| path.price(start.plus_seconds(seconds)).to_string();
+        assert_eq!(path.price(Timestamp::from_seconds(0)).to_string(), "100");
+        assert_eq!(at(0), "100");
+        assert_eq!(at(25), "87.5");
+        assert_eq!(at(100), "50");
+        assert_eq!(at(149), "50");
+        assert_eq!(at(150), "80");
+        assert_eq!(at(155), "85");
+        assert_eq!(at(1000), "90");
+    }
+
+    #[test]
+    fn gbm() {
+        let start = Timestamp::from_seconds(0);
+        let path = PricePath::new(start, Decimal::from_str("100").unwrap())
+            .gbm(600, 6, 0.0, 0.01, 42)
+            .hold(60);
+        let other = PricePath::new(start, Decimal::from_str("100").unwrap())
+            .gbm(600, 6, 0.0, 0.01, 7)
+            .hold(60);
+
+        // Deterministic for a seed, and constant between steps
+        let first = path.price(start.plus_seconds(300));
+        assert_eq!(first, path.price(start.plus_seconds(300)));
+        assert_eq!(first, path.price(start.plus_seconds(305)));
+        assert_ne!(first, other.price(start.plus_seconds(300)));
+        assert_eq!(
+            path.price(start.plus_seconds(600)),
+            path.price(start.plus_seconds(660))
+        );
+
+        // 100 steps of 1% volatility stay well within a factor of 2
+        let end = path.price(start.plus_seconds(600));
+        assert!(end > Decimal::from_str("50").unwrap());
+        assert!(end < Decimal::from_str("200").unwrap());
+    }
+}
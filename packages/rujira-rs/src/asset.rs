@@ -43,6 +43,10 @@ pub enum AssetError {
     Layer1(#[from] Layer1AssetError),
     #[error("Secured {0}")]
     Secured(#[from] SecuredAssetError),
+    #[error("Invalid asset string {0}")]
+    Invalid(String),
+    #[error("Unsupported asset {0}")]
+    Unsupported(String),
 }
 
 impl Asset {
@@ -133,6 +137,27 @@ impl SecuredAsset {
     pub fn to_layer_1(&self) -> Layer1Asset {
         Layer1Asset::new(&self.chain, &self.symbol)
     }
+
+    /// Parses strict THORChain secured notation, eg `ETH-USDC-0XA0B8...`
+    pub fn parse(value: &str) -> result::Result<Self, SecuredAssetError> {
+        match AssetNotation::from_str(value) {
+            Ok(AssetNotation {
+                kind: AssetKind::Secured,
+                chain,
+                symbol,
+            }) => Ok(Self { chain, symbol }),
+            _ => Err(SecuredAssetError::Invalid(value.to_owned())),
+        }
+    }
+}
+
+impl From<Layer1Asset> for SecuredAsset {
+    fn from(value: Layer1Asset) -> Self {
+        Self {
+            chain: value.chain,
+            symbol: value.symbol,
+        }
+    }
 }
 
 impl From<SecuredAsset> for Anybuf {
@@ -203,6 +228,32 @@ impl Layer1Asset {
         self.chain == "THOR" && self.symbol == "RUNE"
     }
 
+    /// Parses strict THORChain layer 1 notation, eg `ETH.USDC-0XA0B8...`
+    pub fn parse(value: &str) -> result::Result<Self, Layer1AssetError> {
+        match AssetNotation::from_str(value) {
+            Ok(AssetNotation {
+                kind: AssetKind::Layer1,
+                chain,
+                symbol,
+            }) => Ok(Self { chain, symbol }),
+            _ => Err(Layer1AssetError::Invalid(value.to_owned())),
+        }
+    }
+
+    /// The denom of the secured form of this asset, eg `eth-usdc-0xa0b8...`
+    pub fn secured_denom(&self) -> String {
+        self.to_secured().denom()
+    }
+
+    pub fn to_secured(&self) -> SecuredAsset {
+        self.clone().into()
+    }
+
+    /// The layer 1 asset of a secured asset denom
+    pub fn from_secured_denom(denom: &String) -> result::Result<Self, SecuredAssetError> {
+        Ok(SecuredAsset::from_denom(denom)?.to_layer_1())
+    }
+
     pub fn migrate(&self) -> Self {
         Self {
             chain: self.chain.to_uppercase(),
@@ -293,6 +344,132 @@ impl TryFrom<&str> for SecuredAsset {
     }
 }
 
+/// The forms an asset takes on THORChain, distinguished by the separator between chain and symbol
+#[cw_serde]
+pub enum AssetKind {
+    /// `BTC.BTC`
+    Layer1,
+    /// `BTC/BTC`
+    Synth,
+    /// `BTC~BTC`
+    Trade,
+    /// `BTC-BTC`
+    Secured,
+}
+
+impl AssetKind {
+    pub fn separator(&self) -> char {
+        match self {
+            AssetKind::Layer1 => '.',
+            AssetKind::Synth => '/',
+            AssetKind::Trade => '~',
+            AssetKind::Secured => '-',
+        }
+    }
+
+    fn from_separator(c: char) -> Option<Self> {
+        match c {
+            '.' => Some(AssetKind::Layer1),
+            '/' => Some(AssetKind::Synth),
+            '~' => Some(AssetKind::Trade),
+            '-' => Some(AssetKind::Secured),
+            _ => None,
+        }
+    }
+}
+
+/// A validated asset in full THORChain notation `CHAIN<sep>TICKER[-ADDRESS]`.
+/// Chain and symbol are normalized to upper case
+#[cw_serde]
+pub struct AssetNotation {
+    pub kind: AssetKind,
+    pub chain: String,
+    pub symbol: String,
+}
+
+impl AssetNotation {
+    pub fn ticker(&self) -> String {
+        self.symbol
+            .split('-')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// The same asset in another form, eg the secured form of a layer 1 asset
+    pub fn to_kind(&self, kind: AssetKind) -> Self {
+        Self {
+            kind,
+            chain: self.chain.clone(),
+            symbol: self.symbol.clone(),
+        }
+    }
+}
+
+impl FromStr for AssetNotation {
+    type Err = AssetError;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let invalid = || AssetError::Invalid(s.to_string());
+        // The chain never contains a separator, so the first one found splits the asset
+        let (idx, kind) = s
+            .char_indices()
+            .find_map(|(i, c)| AssetKind::from_separator(c).map(|k| (i, k)))
+            .ok_or_else(invalid)?;
+        let (chain, symbol) = (&s[..idx], &s[idx + 1..]);
+        ensure!(is_alpha(chain), invalid());
+        let (ticker, address) = match symbol.split_once('-') {
+            Some((ticker, address)) => (ticker, Some(address)),
+            None => (symbol, None),
+        };
+        ensure!(is_alphanumeric(ticker), invalid());
+        ensure!(address.map(is_alphanumeric).unwrap_or(true), invalid());
+        Ok(Self {
+            kind,
+            chain: chain.to_uppercase(),
+            symbol: symbol.to_uppercase(),
+        })
+    }
+}
+
+impl Display for AssetNotation {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        write!(f, "{}{}{}", self.chain, self.kind.separator(), self.symbol)
+    }
+}
+
+impl From<Layer1Asset> for AssetNotation {
+    fn from(value: Layer1Asset) -> Self {
+        Self {
+            kind: AssetKind::Layer1,
+            chain: value.chain,
+            symbol: value.symbol,
+        }
+    }
+}
+
+impl From<SecuredAsset> for AssetNotation {
+    fn from(value: SecuredAsset) -> Self {
+        Self {
+            kind: AssetKind::Secured,
+            chain: value.chain,
+            symbol: value.symbol,
+        }
+    }
+}
+
+impl TryFrom<AssetNotation> for Asset {
+    type Error = AssetError;
+
+    fn try_from(value: AssetNotation) -> result::Result<Self, Self::Error> {
+        match value.kind {
+            AssetKind::Layer1 => Ok(Layer1Asset::new(&value.chain, &value.symbol).into()),
+            AssetKind::Secured => Ok(SecuredAsset::new(&value.chain, &value.symbol).into()),
+            _ => Err(AssetError::Unsupported(value.to_string())),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum SecuredAssetError {
     #[error("Invalid secured asset string {0}")]
@@ -302,6 +479,14 @@ pub enum SecuredAssetError {
     InvalidDenom(String),
 }
 
+fn is_alpha(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b: u8| b.is_ascii_alphabetic())
+}
+
+fn is_alphanumeric(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b: u8| b.is_ascii_alphanumeric())
+}
+
 fn is_lower_alpha(s: &str) -> bool {
     !s.is_empty() && s.bytes().all(|b: u8| b.is_ascii_lowercase())
 }
@@ -387,4 +572,64 @@ mod tests {
         let asset: Asset = SecuredAsset::new("BTC", "BTC").into();
         assert_eq!(asset.to_memo(), "BTC-BTC");
     }
+
+    #[test]
+    fn notation() {
+        let usdc = "ETH.USDC-0XA0B86991C6218B36C1D19D4A2E9EB0CE3606EB48";
+        for (s, kind) in [
+            ("BTC.BTC", AssetKind::Layer1),
+            ("btc/btc", AssetKind::Synth),
+            ("BTC~BTC", AssetKind::Trade),
+            ("BTC-BTC", AssetKind::Secured),
+            (usdc, AssetKind::Layer1),
+        ] {
+            let parsed = AssetNotation::from_str(s).unwrap();
+            assert_eq!(parsed.kind, kind);
+            assert_eq!(parsed.to_string(), s.to_uppercase());
+        }
+
+        let parsed = AssetNotation::from_str(usdc).unwrap();
+        assert_eq!(parsed.ticker(), "USDC");
+        assert_eq!(
+            parsed.to_kind(AssetKind::Trade).to_string(),
+            "ETH~USDC-0XA0B86991C6218B36C1D19D4A2E9EB0CE3606EB48"
+        );
+        assert_eq!(
+            Asset::try_from(parsed.to_kind(AssetKind::Secured))
+                .unwrap()
+                .denom()
+                .unwrap(),
+            "eth-usdc-0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"
+        );
+        Asset::try_from(parsed.to_kind(AssetKind::Synth)).unwrap_err();
+
+        for s in [
+            "",
+            "BTC",
+            ".BTC",
+            "BTC.",
+            "B1C.BTC",
+            "BTC.B$C",
+            "BTC.BTC-",
+            "ETH.USDC-0X-1",
+        ] {
+            AssetNotation::from_str(s).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn secured_conversion() {
+        let l1 = Layer1Asset::parse("ETH.USDC-0XA0B86991C6218B36C1D19D4A2E9EB0CE3606EB48").unwrap();
+        let denom = l1.secured_denom();
+        assert_eq!(denom, "eth-usdc-0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        assert_eq!(Layer1Asset::from_secured_denom(&denom).unwrap(), l1);
+        assert_eq!(
+            l1.to_secured(),
+            SecuredAsset::parse("ETH-USDC-0XA0B86991C6218B36C1D19D4A2E9EB0CE3606EB48").unwrap()
+        );
+
+        Layer1Asset::parse("BTC-BTC").unwrap_err();
+        SecuredAsset::parse("BTC.BTC").unwrap_err();
+        Layer1Asset::from_secured_denom(&"x/btc-btc".to_string()).unwrap_err();
+    }
 }
@@ -25,7 +25,7 @@ impl From<Pool> for SumSnapshot {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct SumSnapshotKey(u32);
 
 impl SumSnapshotKey {
@@ -10,9 +10,22 @@ use cosmwasm_std::{
 pub struct CallbackMsg {
     pub data: Binary,
     pub callback: CallbackData,
+    /// Opaque context supplied with [CallbackData::WithPayload], echoed back unchanged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<Binary>,
 }
+
 #[cw_serde]
-pub struct CallbackData(pub Binary);
+#[serde(untagged)]
+pub enum CallbackData {
+    Msg(Binary),
+    /// Echoes `payload` back in [CallbackMsg::payload], so that a caller can correlate the
+    /// callback with its own context without storing it
+    WithPayload {
+        msg: Binary,
+        payload: Binary,
+    },
+}
 
 #[cw_serde]
 /// Serialization Helper for Callbacks
@@ -29,7 +42,8 @@ impl CallbackData {
     ) -> StdResult<impl Into<CosmosMsg<T>>> {
         let msg = CallbackMsg {
             data: to_json_binary(&data)?,
-            callback: self.clone(),
+            callback: Self::Msg(self.msg().clone()),
+            payload: self.payload().cloned(),
         };
         Ok(WasmMsg::Execute {
             contract_addr: cb_addr.to_string(),
@@ -38,21 +52,48 @@ impl CallbackData {
         })
     }
 
+    pub fn with_payload(self, payload: Binary) -> Self {
+        Self::WithPayload {
+            msg: self.into_json_binary(),
+            payload,
+        }
+    }
+
+    pub fn msg(&self) -> &Binary {
+        match self {
+            Self::Msg(msg) | Self::WithPayload { msg, .. } => msg,
+        }
+    }
+
+    pub fn payload(&self) -> Option<&Binary> {
+        match self {
+            Self::Msg(_) => None,
+            Self::WithPayload { payload, .. } => Some(payload),
+        }
+    }
+
     pub fn into_json_binary(self) -> Binary {
-        self.0
+        match self {
+            Self::Msg(msg) | Self::WithPayload { msg, .. } => msg,
+        }
     }
 }
 
 impl CallbackMsg {
     pub fn new<D: Serialize>(data: D, callback: CallbackData) -> StdResult<Self> {
         let data = to_json_binary(&data)?;
-        Ok(Self { data, callback })
+        Ok(Self {
+            data,
+            callback,
+            payload: None,
+        })
     }
 
     pub fn new_without_data(callback: CallbackData) -> Self {
         Self {
             data: to_json_binary(&Empty {}).unwrap(),
             callback,
+            payload: None,
         }
     }
 
@@ -75,6 +116,43 @@ impl CallbackMsg {
 
 impl From<Binary> for CallbackData {
     fn from(b: Binary) -> Self {
-        Self(b)
+        Self::Msg(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_passthrough() {
+        let cb: CallbackData = from_json(r#""e30=""#).unwrap();
+        assert_eq!(cb, CallbackData::Msg(Binary::from(b"{}")));
+        assert_eq!(to_json_binary(&cb).unwrap(), Binary::from(br#""e30=""#));
+
+        let cb: CallbackData = from_json(r#"{"msg":"e30=","payload":"AQ=="}"#).unwrap();
+        assert_eq!(
+            cb,
+            CallbackData::from(Binary::from(b"{}")).with_payload(Binary::from(vec![1]))
+        );
+
+        let msg: CosmosMsg = cb
+            .to_message(&Addr::unchecked("cb"), Empty {}, vec![])
+            .unwrap()
+            .into();
+        let CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) = msg else {
+            panic!("unexpected msg")
+        };
+        let ReceiverExecuteMsg::Callback(msg) = from_json(msg).unwrap();
+        assert_eq!(msg.callback, CallbackData::Msg(Binary::from(b"{}")));
+        assert_eq!(msg.payload, Some(Binary::from(vec![1])));
+        assert_eq!(msg.deserialize_callback::<Empty>().unwrap(), Empty {});
+
+        // Callbacks without a payload are unchanged on the wire
+        let msg = CallbackMsg::new_without_data(CallbackData::Msg(Binary::from(b"{}")));
+        assert_eq!(
+            String::from_utf8(to_json_binary(&msg).unwrap().to_vec()).unwrap(),
+            r#"{"data":"e30=","callback":"e30="}"#
+        );
     }
 }
@@ -1,4 +1,4 @@
-use crate::asset::Asset;
+use crate::asset::{Asset, AssetError};
 use anybuf::Anybuf;
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::Uint256;
@@ -18,6 +18,17 @@ impl Coin {
     }
 }
 
+impl TryFrom<&cosmwasm_std::Coin> for Coin {
+    type Error = AssetError;
+
+    fn try_from(value: &cosmwasm_std::Coin) -> Result<Self, Self::Error> {
+        Ok(Self::new(
+            Asset::from_denom(&value.denom)?,
+            Uint256::from(value.amount),
+        ))
+    }
+}
+
 impl From<Coin> for Anybuf {
     fn from(value: Coin) -> Self {
         Anybuf::new()
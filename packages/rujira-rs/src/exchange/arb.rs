@@ -30,11 +30,16 @@ impl<T: Arbitrage + Clone> Arber<T> {
         let mut quote = quote_iter.next();
         let mut profit_base = Uint128::zero();
         let mut profit_quote = Uint128::zero();
+        let mut size_base = Uint128::zero();
+        let mut size_quote = Uint128::zero();
         while let (Some(b), Some(q)) = (&mut base, &mut quote) {
+            let (total_base, total_quote) = (b.total(), q.total());
             match b.arbitrage(q)? {
                 Some((base_profit, quote_profit)) => {
                     profit_base += base_profit;
                     profit_quote += quote_profit;
+                    size_base += total_base - b.total();
+                    size_quote += total_quote - q.total();
                     self.pending_quote.push(q.clone());
                     self.pending_base.push(b.clone());
 
@@ -52,6 +57,8 @@ impl<T: Arbitrage + Clone> Arber<T> {
         Ok(ArbitrageResult {
             profit_base,
             profit_quote,
+            size_base,
+            size_quote,
         })
     }
 
@@ -77,6 +84,9 @@ impl<T: Arbitrage + Clone> Arber<T> {
 pub struct ArbitrageResult {
     pub profit_base: Uint128,
     pub profit_quote: Uint128,
+    /// The bids filled from each side of the crossed book
+    pub size_base: Uint128,
+    pub size_quote: Uint128,
 }
 
 pub trait Arbitrage: Swappable + Sized {
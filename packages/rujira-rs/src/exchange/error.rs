@@ -22,4 +22,10 @@ pub enum SwapError {
         expected: Uint128,
         returned: Uint128,
     },
+
+    #[error("InsufficientFill expected {expected} got {filled}")]
+    InsufficientFill { expected: Uint128, filled: Uint128 },
+
+    #[error("RoundingDrift expected {expected} got {actual}")]
+    RoundingDrift { expected: Uint128, actual: Uint128 },
 }
@@ -11,8 +11,9 @@ pub trait Swappable {
     /// The rate that the swap should be executed at
     fn rate(&self) -> Decimal;
 
-    /// Extra attributes to append to trade events
-    fn attributes(&self) -> Vec<Attribute>;
+    /// Extra attributes to append to the trade event, once swapped against.
+    /// Storage is provided to load what only the event needs, e.g. for an item the swap consumed
+    fn attributes(&self, storage: &dyn Storage) -> Vec<Attribute>;
 
     /// Total amount of bids available for swapping
     fn total(&self) -> Uint128;
@@ -39,15 +40,15 @@ where
         }
     }
 
-    fn attributes(&self) -> Vec<Attribute> {
+    fn attributes(&self, storage: &dyn Storage) -> Vec<Attribute> {
         match self {
             EitherOrBoth::Both(a, b) => {
-                let mut res = a.attributes();
-                res.append(&mut b.attributes());
+                let mut res = a.attributes(storage);
+                res.append(&mut b.attributes(storage));
                 res
             }
-            EitherOrBoth::Left(x) => x.attributes(),
-            EitherOrBoth::Right(x) => x.attributes(),
+            EitherOrBoth::Left(x) => x.attributes(storage),
+            EitherOrBoth::Right(x) => x.attributes(storage),
         }
     }
 
@@ -94,8 +95,8 @@ where
         self.first().map(|x| x.rate()).unwrap_or(Decimal::zero())
     }
 
-    fn attributes(&self) -> Vec<Attribute> {
-        self.iter().flat_map(|x| x.attributes()).collect()
+    fn attributes(&self, storage: &dyn Storage) -> Vec<Attribute> {
+        self.iter().flat_map(|x| x.attributes(storage)).collect()
     }
 
     fn total(&self) -> Uint128 {
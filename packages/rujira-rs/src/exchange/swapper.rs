@@ -17,6 +17,7 @@ pub struct Swapper<T> {
     remaining_offer: Uint128,
     returned: Uint128,
     pending: Vec<T>,
+    quote_usd: Option<Decimal>,
 }
 
 impl<T: Swappable> Swapper<T> {
@@ -30,14 +31,33 @@ impl<T: Swappable> Swapper<T> {
             remaining_offer: offer,
             returned: Uint128::zero(),
             pending: vec![],
+            quote_usd: None,
         }
     }
 
-    pub fn swap(&mut self, iter: &mut dyn Iterator<Item = T>) -> Result<SwapResult, SwapError>
+    /// Adds a `rate_usd` attribute to trade events, converting each rate with the USD price of the quote asset
+    pub fn with_quote_usd(mut self, quote_usd: Option<Decimal>) -> Self {
+        self.quote_usd = quote_usd;
+        self
+    }
+
+    /// Swaps the offer against `iter`. `storage` is read for the attributes of each trade event
+    pub fn swap(
+        &mut self,
+        storage: &dyn Storage,
+        iter: &mut dyn Iterator<Item = T>,
+    ) -> Result<SwapResult, SwapError>
     where
         T: std::fmt::Debug,
     {
+        let mut last_rate = None;
+        let mut levels = 0u32;
         for mut v in iter {
+            // Stop short once the level budget is spent, leaving the rest of the offer unswapped
+            if self.req.max_levels().is_some_and(|max| levels >= max) {
+                break;
+            }
+            levels += 1;
             let (offer, bids) = v.swap(self.remaining_offer)?;
 
             // If we've breached reached a SwapRequest::Limit, don't commit this step and break
@@ -50,9 +70,13 @@ impl<T: Swappable> Swapper<T> {
                 }
             }
 
-            let attrs = v.attributes();
-            self.events
-                .push(event(&v, &self.event_prefix, offer, bids, &attrs));
+            let attrs = v.attributes(storage);
+            let mut e = event(&v, &self.event_prefix, offer, bids, &attrs);
+            if let Some(quote_usd) = self.quote_usd {
+                e = e.add_attribute("rate_usd", (v.rate() * quote_usd).to_string());
+            }
+            self.events.push(e);
+            last_rate = Some(v.rate());
             self.pending.push(v);
             self.consumed_offer += offer;
             self.remaining_offer -= offer;
@@ -85,6 +109,17 @@ impl<T: Swappable> Swapper<T> {
                     });
                 }
             }
+            SwapRequest::Limit {
+                min_fill: Some(min_fill),
+                ..
+            } => {
+                if self.consumed_offer < min_fill {
+                    return Err(SwapError::InsufficientFill {
+                        expected: min_fill,
+                        filled: self.consumed_offer,
+                    });
+                }
+            }
             _ => {}
         }
 
@@ -94,6 +129,7 @@ impl<T: Swappable> Swapper<T> {
             return_amount: self.returned,
             consumed_offer: self.consumed_offer,
             remaining_offer: self.remaining_offer,
+            last_rate,
         })
     }
 
@@ -128,13 +164,15 @@ pub struct SwapResult {
     pub return_amount: Uint128,
     pub consumed_offer: Uint128,
     pub remaining_offer: Uint128,
+    /// The rate of the last Swappable that was traded against, if any
+    pub last_rate: Option<Decimal>,
 }
 
 #[cfg(test)]
 
 mod tests {
 
-    use cosmwasm_std::Fraction;
+    use cosmwasm_std::{testing::MockStorage, Fraction};
 
     use crate::exchange::testing::TestIter;
 
@@ -160,10 +198,11 @@ mod tests {
             SwapRequest::Yolo {
                 to: None,
                 callback: None,
+                max_levels: None,
             },
             fee,
         );
-        let res = s.swap(&mut iter).unwrap();
+        let res = s.swap(&MockStorage::new(), &mut iter).unwrap();
         assert_eq!(res.return_amount, Uint128::from(6283u128));
         assert_eq!(res.fee_amount, Uint128::from(7u128));
         assert_eq!(res.remaining_offer, Uint128::zero());
@@ -256,6 +295,7 @@ mod tests {
                     min_return: Uint128::from(1000u128),
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
                 None,
             ),
@@ -265,6 +305,7 @@ mod tests {
                     min_return: Uint128::from(1000u128),
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
                 Some((
                     Uint128::from(1093u128),
@@ -278,6 +319,7 @@ mod tests {
                     exact_return: Uint128::from(1000u128),
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
                 None,
             ),
@@ -288,6 +330,7 @@ mod tests {
                     exact_return: Uint128::from(899u128),
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
                 Some((
                     Uint128::from(899u128),
@@ -299,8 +342,10 @@ mod tests {
                 Uint128::from(1100u128),
                 SwapRequest::Limit {
                     price: Decimal::one(),
+                    min_fill: None,
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
                 Some((
                     Uint128::from(999u128),
@@ -313,8 +358,10 @@ mod tests {
                 SwapRequest::Limit {
                     // Price is inverted from the bid price in the iterator
                     price: Decimal::from_str("0.85").unwrap().inv().unwrap(),
+                    min_fill: None,
                     to: None,
                     callback: None,
+                    max_levels: None,
                 },
                 Some((
                     // Should get up to 0.85 and halt
@@ -324,6 +371,57 @@ mod tests {
                     Uint128::from(5661u128),
                 )),
             ),
+            (
+                Uint128::from(10000u128),
+                SwapRequest::Limit {
+                    price: Decimal::from_str("0.85").unwrap().inv().unwrap(),
+                    // 4339 is swapped within the limit
+                    min_fill: Some(Uint128::from(4339u128)),
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                },
+                Some((
+                    Uint128::from(3996u128),
+                    Uint128::from(4u128),
+                    Uint128::from(5661u128),
+                )),
+            ),
+            (
+                Uint128::from(10000u128),
+                SwapRequest::Limit {
+                    price: Decimal::from_str("0.85").unwrap().inv().unwrap(),
+                    min_fill: Some(Uint128::from(4340u128)),
+                    to: None,
+                    callback: None,
+                    max_levels: None,
+                },
+                None,
+            ),
+            (
+                Uint128::from(7500u128),
+                SwapRequest::Yolo {
+                    to: None,
+                    callback: None,
+                    // The first two levels only
+                    max_levels: Some(2),
+                },
+                Some((
+                    Uint128::from(1998u128),
+                    Uint128::from(2u128),
+                    Uint128::from(5448u128),
+                )),
+            ),
+            (
+                Uint128::from(7500u128),
+                SwapRequest::Min {
+                    min_return: Uint128::from(2000u128),
+                    to: None,
+                    callback: None,
+                    max_levels: Some(1),
+                },
+                None,
+            ),
         ] {
             let mut iter = TestIter::new(vec![
                 (Decimal::from_str("1.0").unwrap(), Uint128::from(1000u128)),
@@ -336,7 +434,7 @@ mod tests {
             ]);
 
             let mut s = Swapper::new("some-prefix", offer, req, fee);
-            let res = s.swap(&mut iter);
+            let res = s.swap(&MockStorage::new(), &mut iter);
             match result {
                 Some((returned, fee, remaining)) => {
                     let res = res.unwrap();
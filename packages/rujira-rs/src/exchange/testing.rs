@@ -30,7 +30,7 @@ impl Swappable for TestItem {
         self.price
     }
 
-    fn attributes(&self) -> Vec<Attribute> {
+    fn attributes(&self, _storage: &dyn Storage) -> Vec<Attribute> {
         vec![Attribute::new("test", "attr")]
     }
 
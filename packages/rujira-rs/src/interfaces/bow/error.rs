@@ -39,6 +39,12 @@ pub enum StrategyError {
     #[error("InsufficientReturn expected {expected} got {returned}")]
     InsufficientReturn { expected: Coin, returned: Coin },
 
+    #[error("InsufficientLiquidity")]
+    InsufficientLiquidity {},
+
     #[error("Invalid Config {0}")]
     InvalidConfig(String),
+
+    #[error("InvariantViolated {0}")]
+    InvariantViolated(String),
 }
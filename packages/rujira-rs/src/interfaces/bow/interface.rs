@@ -1,7 +1,9 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Binary, Coin, Decimal, Uint128};
+use cosmwasm_std::{
+    to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, StdResult, Uint128, WasmMsg,
+};
 
-use crate::{CallbackData, TokenMetadata};
+use crate::{CallbackData, Layer1Asset, TokenMetadata};
 
 use super::{strategy::Strategies, xyk::XykState, Xyk};
 
@@ -27,11 +29,78 @@ pub enum ExecuteMsg {
     Withdraw {
         callback: Option<CallbackData>,
     },
+    /// Deposits a single asset of the pool, swapping the portion of it along the curve that
+    /// balances the deposit, and minting the LP shares to the sender
+    Zap {
+        /// The minimum amount of LP shares to be returned
+        min_shares: Option<Uint128>,
+    },
+    /// Re-quotes the strategy against current oracle prices, using accrued pool fees to move
+    /// the pool price towards the oracle price. The caller is paid a bounty from the fees when
+    /// the pool price has deviated from the oracle by more than the configured threshold
+    Poke {},
+    /// Claims the rewards accrued to the sender's LP shares from the gauge
+    ClaimRewards {},
 }
 
 #[cw_serde]
 pub enum SudoMsg {
+    /// Replaces the strategy, migrating the current underlying assets and shares to it.
+    /// Fails if the share redemption value changes by more than the configured epsilon
     SetStrategy(Strategies),
+    /// Sets the maximum change in share redemption value permitted on a SetStrategy
+    SetMigrationEpsilon(Decimal),
+    /// Enables or disables Poke
+    SetPoke(Option<PokeConfig>),
+    /// Pauses or resumes swaps, deposits and pokes. Withdrawals are always permitted
+    SetPaused(bool),
+    /// Sets the fee charged on each swap against the strategy
+    SetFee(Decimal),
+    /// Sets or removes an override of the fee for swaps offering the quote denom of the strategy
+    SetFeeBid(Option<Decimal>),
+    /// Sets or removes an override of the fee for swaps offering the base denom of the strategy
+    SetFeeAsk(Option<Decimal>),
+    /// Sets the share of the reserves offered at each quoted price, which sets how far the
+    /// price skews with each quote of the strategy
+    SetSkew(Decimal),
+    /// Sets the minimum reserves that the strategy must hold to quote
+    SetMinQuote(Uint128),
+    /// Sets or removes the gauge that LP share changes are reported to
+    SetGauge(Option<String>),
+}
+
+/// Sent to the gauge of the pool, which distributes LP incentives
+#[cw_serde]
+pub enum GaugeMsg {
+    /// Reports shares minted to or burned by `owner` on a Deposit or Withdraw
+    Checkpoint {
+        owner: String,
+        minted: Uint128,
+        burned: Uint128,
+    },
+    /// Claims the rewards accrued to `owner`, paid out to them by the gauge
+    Claim { owner: String },
+}
+
+impl GaugeMsg {
+    pub fn call(&self, address: &Addr) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: address.to_string(),
+            msg: to_json_binary(self)?,
+            funds: vec![],
+        }
+        .into())
+    }
+}
+
+#[cw_serde]
+pub struct PokeConfig {
+    /// The oracle assets for the base and quote denoms of the strategy
+    pub oracles: [Layer1Asset; 2],
+    /// The minimum deviation of the pool price from the oracle price for a Poke to earn a bounty
+    pub threshold: Decimal,
+    /// The share of accrued pool fees paid to the caller of a Poke that earns a bounty
+    pub bounty: Decimal,
 }
 
 #[cw_serde]
@@ -41,8 +110,21 @@ pub enum QueryMsg {
     Strategy {},
     #[returns(QuoteResponse)]
     Quote(QuoteRequest),
+    /// The first `levels` quotes, up to [LADDER_LEVELS], that a FIN market maker would consume
+    /// for `offer_denom`, each request carrying the price and data of the previous quote.
+    /// Pass [LadderResponse::next] as `after` to continue from the last level
+    #[returns(LadderResponse)]
+    Ladder {
+        offer_denom: String,
+        ask_denom: String,
+        levels: u16,
+        after: Option<LadderCursor>,
+    },
 }
 
+/// The maximum number of levels returned by [QueryMsg::Ladder]
+pub const LADDER_LEVELS: u16 = 100;
+
 #[cw_serde]
 pub struct QuoteRequest {
     /// The minimum price that BOW must respond with
@@ -83,6 +165,30 @@ pub struct QuoteResponse {
     pub data: Option<Binary>,
 }
 
+#[cw_serde]
+pub struct LadderCursor {
+    /// The price of the last level returned
+    pub min_price: Decimal,
+    /// The data of the last level returned
+    pub data: Option<Binary>,
+}
+
+#[cw_serde]
+pub struct LadderLevel {
+    /// See [QuoteResponse::price]
+    pub price: Decimal,
+    /// See [QuoteResponse::size]
+    pub size: Uint128,
+}
+
+#[cw_serde]
+pub struct LadderResponse {
+    pub levels: Vec<LadderLevel>,
+    /// Set when `levels` were returned in full, and the strategy may quote further
+    pub next: Option<LadderCursor>,
+}
+
+/// The strategy with all of its parameters, and its current state
 #[cw_serde]
 pub enum StrategyResponse {
     Xyk((Xyk, XykState)),
@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Coin, Deps, DepsMut, Env, StdResult, Uint128};
+use cosmwasm_std::{Coin, Decimal, Deps, DepsMut, Env, StdResult, Uint128};
 use cw_utils::NativeBalance;
 
 use super::{
@@ -15,6 +15,10 @@ pub trait Strategy<T> {
     fn load_state(&self, deps: Deps, env: Env) -> StdResult<T>;
     fn commit_state(&self, deps: DepsMut, state: &T) -> StdResult<()>;
 
+    /// Checks the transition from `before` to `after` against the strategy's invariants,
+    /// ahead of `commit_state`. Withdrawals are exempt
+    fn check_invariants(&self, before: &T, after: &T) -> Result<(), StrategyError>;
+
     /// The receipt token denom string for the strategy
     fn denom(&self) -> String;
     /// Validates a swap size against the strategy
@@ -35,9 +39,36 @@ pub trait Strategy<T> {
     /// the amount of shares that it has earned
     fn deposit(&self, state: &mut T, funds: NativeBalance) -> Result<Uint128, StrategyError>;
 
+    /// Deposits a single `offer` coin, swapping the portion of it along the curve that balances
+    /// the deposit of the remainder with the swap return. Returns the swap fee retained, and the
+    /// amount of shares earned
+    fn zap(&self, state: &mut T, offer: Coin) -> Result<(Coin, Uint128), StrategyError>;
+
     /// Withdraws the `amount` of shares from the strategy, returning
     /// the amount of underlying assets to be repaid
     fn withdraw(&self, state: &mut T, amount: Uint128) -> Result<NativeBalance, StrategyError>;
+
+    /// The underlying assets allocated to the strategy
+    fn balances(&self, state: &T) -> NativeBalance;
+
+    /// The total number of shares issued by the strategy
+    fn shares(&self, state: &T) -> Uint128;
+
+    /// Builds a fresh state from the underlying assets and shares of a previous strategy,
+    /// used when the strategy of a pool is changed
+    fn migrate_state(&self, balances: &NativeBalance, shares: Uint128) -> Result<T, StrategyError>;
+
+    /// The current marginal price of the base denom, quoted in the quote denom
+    fn price(&self, state: &T) -> Decimal;
+
+    /// Moves the strategy towards an external `price`, adding up to `available` funds to the
+    /// strategy's assets. Returns the funds that were added
+    fn poke(
+        &self,
+        state: &mut T,
+        price: Decimal,
+        available: &NativeBalance,
+    ) -> Result<NativeBalance, StrategyError>;
 }
 
 #[cw_serde]
@@ -75,6 +106,18 @@ impl Strategy<StrategyState> for Strategies {
         }
     }
 
+    fn check_invariants(
+        &self,
+        before: &StrategyState,
+        after: &StrategyState,
+    ) -> Result<(), StrategyError> {
+        match (self, before, after) {
+            (Strategies::Xyk(x), StrategyState::Xyk(b), StrategyState::Xyk(a)) => {
+                x.check_invariants(b, a)
+            }
+        }
+    }
+
     fn validate_swap(
         &self,
         state: &mut StrategyState,
@@ -109,6 +152,16 @@ impl Strategy<StrategyState> for Strategies {
         }
     }
 
+    fn zap(
+        &self,
+        state: &mut StrategyState,
+        offer: Coin,
+    ) -> Result<(Coin, Uint128), StrategyError> {
+        match (self, state) {
+            (Strategies::Xyk(x), StrategyState::Xyk(ref mut s)) => x.zap(s, offer),
+        }
+    }
+
     fn withdraw(
         &self,
         state: &mut StrategyState,
@@ -119,4 +172,43 @@ impl Strategy<StrategyState> for Strategies {
             // _ => Err(StrategyError::InvalidStrategyState {}),
         }
     }
+
+    fn balances(&self, state: &StrategyState) -> NativeBalance {
+        match (self, state) {
+            (Strategies::Xyk(x), StrategyState::Xyk(s)) => x.balances(s),
+        }
+    }
+
+    fn shares(&self, state: &StrategyState) -> Uint128 {
+        match (self, state) {
+            (Strategies::Xyk(x), StrategyState::Xyk(s)) => x.shares(s),
+        }
+    }
+
+    fn migrate_state(
+        &self,
+        balances: &NativeBalance,
+        shares: Uint128,
+    ) -> Result<StrategyState, StrategyError> {
+        match self {
+            Strategies::Xyk(x) => x.migrate_state(balances, shares).map(StrategyState::Xyk),
+        }
+    }
+
+    fn price(&self, state: &StrategyState) -> Decimal {
+        match (self, state) {
+            (Strategies::Xyk(x), StrategyState::Xyk(s)) => x.price(s),
+        }
+    }
+
+    fn poke(
+        &self,
+        state: &mut StrategyState,
+        price: Decimal,
+        available: &NativeBalance,
+    ) -> Result<NativeBalance, StrategyError> {
+        match (self, state) {
+            (Strategies::Xyk(x), StrategyState::Xyk(ref mut s)) => x.poke(s, price, available),
+        }
+    }
 }
@@ -2,12 +2,12 @@ use crate::bow::error::StrategyError;
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
     coin, ensure, from_json, to_json_binary, Coin, Decimal, Decimal256, Deps, DepsMut, Env,
-    Fraction, Isqrt, StdResult, Uint128, Uint256,
+    Fraction, Isqrt, StdResult, Timestamp, Uint128, Uint256, Uint512,
 };
 use cw_storage_plus::Item;
 use cw_utils::NativeBalance;
 use std::{
-    cmp::min,
+    cmp::{min, Ordering},
     ops::{Add, Div, Mul, Sub},
 };
 
@@ -26,6 +26,39 @@ pub struct Xyk {
     // The fee that's charged on each quote and required to be paid
     // in `validate` function
     fee: Decimal,
+    // Optional offsets added to the reserves when pricing, used to bootstrap a new pool
+    #[serde(default)]
+    virtual_reserves: Option<VirtualReserves>,
+    // Optional overrides of `fee` for swaps that buy X with Y (bids), and sell X for Y (asks)
+    #[serde(default)]
+    fee_bid: Option<Decimal>,
+    #[serde(default)]
+    fee_ask: Option<Decimal>,
+}
+
+/// Virtual liquidity added to the X and Y reserves of a pool when pricing swaps and quotes,
+/// so that a new pool can quote around a target price before it has real depth.
+/// Virtual liquidity is never paid out: swaps are limited to the real reserves, and
+/// withdrawals are made from the real reserves only
+#[cw_serde]
+pub struct VirtualReserves {
+    pub x: Uint128,
+    pub y: Uint128,
+    /// The offsets decay linearly from `start` to zero at `start + duration` seconds
+    pub start: Timestamp,
+    pub duration: u64,
+}
+
+impl VirtualReserves {
+    /// The decayed (X, Y) offsets at `now`
+    pub fn at(&self, now: Timestamp) -> (Uint128, Uint128) {
+        let elapsed = now.seconds().saturating_sub(self.start.seconds());
+        let remaining = self.duration.saturating_sub(elapsed).min(self.duration);
+        (
+            self.x.multiply_ratio(remaining, self.duration),
+            self.y.multiply_ratio(remaining, self.duration),
+        )
+    }
 }
 
 impl Xyk {
@@ -42,8 +75,76 @@ impl Xyk {
             step,
             min_quote,
             fee,
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
         }
     }
+
+    pub fn with_virtual_reserves(mut self, virtual_reserves: VirtualReserves) -> Self {
+        self.virtual_reserves = Some(virtual_reserves);
+        self
+    }
+
+    pub fn with_fee(mut self, fee: Decimal) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    pub fn with_fee_bid(mut self, fee: Option<Decimal>) -> Self {
+        self.fee_bid = fee;
+        self
+    }
+
+    pub fn with_fee_ask(mut self, fee: Option<Decimal>) -> Self {
+        self.fee_ask = fee;
+        self
+    }
+
+    pub fn with_step(mut self, step: Decimal) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn with_min_quote(mut self, min_quote: Uint128) -> Self {
+        self.min_quote = min_quote;
+        self
+    }
+
+    pub fn fee(&self) -> Decimal {
+        self.fee
+    }
+
+    /// The fee charged on swaps offering Y for X
+    pub fn fee_bid(&self) -> Decimal {
+        self.fee_bid.unwrap_or(self.fee)
+    }
+
+    /// The fee charged on swaps offering X for Y
+    pub fn fee_ask(&self) -> Decimal {
+        self.fee_ask.unwrap_or(self.fee)
+    }
+
+    /// The fee charged on a swap of `offer_denom`. Deposits are charged `fee`
+    fn swap_fee(&self, offer_denom: &str) -> Decimal {
+        if offer_denom == self.y {
+            self.fee_bid()
+        } else {
+            self.fee_ask()
+        }
+    }
+
+    pub fn step(&self) -> Decimal {
+        self.step
+    }
+
+    pub fn min_quote(&self) -> Uint128 {
+        self.min_quote
+    }
+
+    pub fn virtual_reserves(&self) -> Option<&VirtualReserves> {
+        self.virtual_reserves.as_ref()
+    }
 }
 
 #[cw_serde]
@@ -52,6 +153,11 @@ pub struct XykState {
     y: Uint128,
     k: Uint256,
     pub(crate) shares: Uint128,
+    /// Decayed virtual reserves at the time the state was loaded. Not stored
+    #[serde(default)]
+    virtual_x: Uint128,
+    #[serde(default)]
+    virtual_y: Uint128,
 }
 
 impl Default for XykState {
@@ -67,6 +173,8 @@ impl XykState {
             y: Uint128::zero(),
             k: Uint256::zero(),
             shares: Uint128::zero(),
+            virtual_x: Uint128::zero(),
+            virtual_y: Uint128::zero(),
         }
     }
 
@@ -74,39 +182,52 @@ impl XykState {
         self.k
     }
 
+    /// The (X, Y) reserves used for pricing, including any virtual reserves
+    pub fn reserves(&self) -> (Uint128, Uint128) {
+        (self.x.add(self.virtual_x), self.y.add(self.virtual_y))
+    }
+
     /// Swaps an offer_size of X and returns (Amount, Price) of Y
     pub fn swap(&mut self, amount: &Uint128) -> Result<Uint128, StrategyError> {
-        if self.x.is_zero() {
+        let (x_cur, y_cur) = self.reserves();
+        if x_cur.is_zero() {
             return Ok(Uint128::zero());
         }
-        let k = self.k;
-        self.x = self.x.add(amount);
-        let y_cur = self.y;
-        let y_new: Uint128 = Decimal256::from_ratio(self.k, self.x)
-            .to_uint_ceil()
-            .try_into()?;
+        let k = Uint256::from(x_cur) * Uint256::from(y_cur);
+        let x_new = x_cur.add(amount);
+        let y_new: Uint128 = Decimal256::from_ratio(k, x_new).to_uint_ceil().try_into()?;
         // We used to deduct fees here, however:
         // The way the fees compund makes the pool smaller in each iteration,
         // reducing the fees collected in subseuqent actions
         // We should swap at vanilla XYK, and take fees at the edges
         let return_amount = y_cur.sub(y_new);
-        self.y = y_cur.sub(return_amount);
-        self.k = Uint256::from(self.x) * Uint256::from(self.y);
-        ensure!(self.k >= k, StrategyError::Underflow {});
+        // Virtual reserves price the swap, but can't be paid out
+        ensure!(
+            return_amount <= self.y,
+            StrategyError::InsufficientLiquidity {}
+        );
+        self.set(self.x.add(amount), self.y.sub(return_amount));
+        ensure!(
+            Uint256::from(x_new) * Uint256::from(y_new) >= k,
+            StrategyError::Underflow {}
+        );
         Ok(return_amount)
     }
 
     pub fn price(&self) -> Decimal {
-        if self.x.is_zero() {
+        let (x, y) = self.reserves();
+        if x.is_zero() {
             return Decimal::zero();
         }
-        Decimal::from_ratio(self.y, self.x)
+        Decimal::from_ratio(y, x)
     }
 
     pub fn invert(&mut self) {
         let cloned = self.clone();
         self.x = cloned.y;
         self.y = cloned.x;
+        self.virtual_x = cloned.virtual_y;
+        self.virtual_y = cloned.virtual_x;
     }
 
     pub fn set(&mut self, x: Uint128, y: Uint128) {
@@ -123,6 +244,8 @@ impl From<(Uint128, Uint128, Uint128)> for XykState {
             y,
             k: Uint256::from(x) * Uint256::from(y),
             shares,
+            virtual_x: Uint128::zero(),
+            virtual_y: Uint128::zero(),
         }
     }
 }
@@ -132,24 +255,71 @@ impl Strategy<XykState> for Xyk {
         if self.step > Decimal::bps(Self::MAX_STEP) || self.step < Decimal::bps(Self::MIN_STEP) {
             return Err(StrategyError::InvalidConfig("step".into()));
         }
-        if self.fee > Decimal::bps(Self::MAX_FEE) || self.fee < Decimal::bps(Self::MIN_FEE) {
-            return Err(StrategyError::InvalidConfig("fee".into()));
+        for (key, fee) in [
+            ("fee", self.fee),
+            ("fee_bid", self.fee_bid()),
+            ("fee_ask", self.fee_ask()),
+        ] {
+            if fee > Decimal::bps(Self::MAX_FEE) || fee < Decimal::bps(Self::MIN_FEE) {
+                return Err(StrategyError::InvalidConfig(key.into()));
+            }
         }
         if self.min_quote < Uint128::from(Self::MIN_MIN_QUOTE) {
             return Err(StrategyError::InvalidConfig("min_quote".into()));
         }
+        if let Some(v) = &self.virtual_reserves {
+            if v.x.is_zero() || v.y.is_zero() || v.duration == 0 {
+                return Err(StrategyError::InvalidConfig("virtual_reserves".into()));
+            }
+        }
         Ok(())
     }
 
-    fn load_state(&self, deps: Deps, _env: Env) -> StdResult<XykState> {
+    fn load_state(&self, deps: Deps, env: Env) -> StdResult<XykState> {
         let stored = STORE.load(deps.storage).unwrap_or_default();
-        Ok(XykState::from(stored))
+        let mut state = XykState::from(stored);
+        if let Some(v) = &self.virtual_reserves {
+            (state.virtual_x, state.virtual_y) = v.at(env.block.time);
+        }
+        Ok(state)
     }
 
     fn commit_state(&self, deps: DepsMut, state: &XykState) -> StdResult<()> {
         STORE.save(deps.storage, &(state.x, state.y, state.shares))
     }
 
+    fn check_invariants(&self, before: &XykState, after: &XykState) -> Result<(), StrategyError> {
+        match after.shares.cmp(&before.shares) {
+            // Withdrawals reduce the reserves along with the shares
+            Ordering::Less => Ok(()),
+            // Swaps and pokes can only grow the product of the reserves that swaps are priced with
+            Ordering::Equal => {
+                let product = |s: &XykState| {
+                    let (x, y) = s.reserves();
+                    Uint256::from(x).full_mul(y)
+                };
+                ensure!(
+                    product(after) >= product(before),
+                    StrategyError::InvariantViolated("reserve product decreased".to_string())
+                );
+                Ok(())
+            }
+            // Deposits can't dilute existing shares. Share value is measured as isqrt(k) / shares,
+            // as shares are issued
+            Ordering::Greater => {
+                if before.shares.is_zero() {
+                    return Ok(());
+                }
+                ensure!(
+                    after.k.isqrt().full_mul(before.shares)
+                        >= before.k.isqrt().full_mul(after.shares),
+                    StrategyError::InvariantViolated("share value decreased".to_string())
+                );
+                Ok(())
+            }
+        }
+    }
+
     fn denom(&self) -> String {
         format!("bow-xyk-{}-{}", self.x, self.y)
     }
@@ -167,8 +337,8 @@ impl Strategy<XykState> for Xyk {
         }
 
         let return_amount_total = state.swap(&offer.amount)?;
-        let fee_amount =
-            return_amount_total.multiply_ratio(self.fee.numerator(), self.fee.denominator());
+        let fee = self.swap_fee(&offer.denom);
+        let fee_amount = return_amount_total.multiply_ratio(fee.numerator(), fee.denominator());
         let return_amount = return_amount_total.sub(fee_amount);
         ensure!(
             return_amount >= ask.amount,
@@ -213,18 +383,25 @@ impl Strategy<XykState> for Xyk {
         };
 
         let amount = working_state
-            .x
+            .reserves()
+            .0
             .multiply_ratio(self.step.numerator(), self.step.denominator());
 
         let current_price = working_state.price();
-        let ask_size_total = working_state.swap(&amount)?;
+        let ask_size_total = match working_state.swap(&amount) {
+            Ok(x) => x,
+            // The real reserves are exhausted
+            Err(StrategyError::InsufficientLiquidity {}) => return Ok(None),
+            Err(err) => return Err(err),
+        };
 
         if amount.lt(&self.min_quote) || ask_size_total.lt(&self.min_quote) {
             return Ok(None);
         }
 
+        let fee = self.swap_fee(&req.offer_denom);
         let fee_amount = ask_size_total
-            .multiply_ratio(self.fee.numerator(), self.fee.denominator())
+            .multiply_ratio(fee.numerator(), fee.denominator())
             // See note in `XykState::swap` regards fees
             // Truncation here can cause insufficient return expectations from multiple quotes
             // Eg ask_size_total of 1046 and 1021 with fee amount of 102 bps = 10 + 10
@@ -293,6 +470,36 @@ impl Strategy<XykState> for Xyk {
         Ok(minted)
     }
 
+    fn zap(&self, state: &mut XykState, offer: Coin) -> Result<(Coin, Uint128), StrategyError> {
+        let ask = if offer.denom == self.x {
+            self.y.clone()
+        } else if offer.denom == self.y {
+            state.invert();
+            self.x.clone()
+        } else {
+            return Err(StrategyError::InvalidDeposit {});
+        };
+        // A zap can't set the initial price of the pool
+        ensure!(!state.shares.is_zero(), StrategyError::InvalidDeposit {});
+
+        let fee = self.swap_fee(&offer.denom);
+        let amount = zap_swap_amount(state.reserves().0, offer.amount, fee)?;
+        let return_amount_total = state.swap(&amount)?;
+        let fee_amount = return_amount_total.multiply_ratio(fee.numerator(), fee.denominator());
+
+        if offer.denom == self.y {
+            state.invert()
+        }
+
+        let mut funds = NativeBalance(vec![
+            coin(offer.amount.sub(amount).u128(), offer.denom),
+            coin(return_amount_total.sub(fee_amount).u128(), ask.as_str()),
+        ]);
+        funds.normalize();
+        let minted = self.deposit(state, funds)?;
+        Ok((coin(fee_amount.u128(), ask), minted))
+    }
+
     fn withdraw(
         &self,
         state: &mut XykState,
@@ -322,6 +529,79 @@ impl Strategy<XykState> for Xyk {
 
         Ok(balances)
     }
+
+    fn balances(&self, state: &XykState) -> NativeBalance {
+        let mut balances = NativeBalance(vec![
+            coin(state.x.u128(), self.x.as_str()),
+            coin(state.y.u128(), self.y.as_str()),
+        ]);
+        balances.normalize();
+        balances
+    }
+
+    fn shares(&self, state: &XykState) -> Uint128 {
+        state.shares
+    }
+
+    fn migrate_state(
+        &self,
+        balances: &NativeBalance,
+        shares: Uint128,
+    ) -> Result<XykState, StrategyError> {
+        let x = balance_of(balances, &self.x);
+        let y = balance_of(balances, &self.y);
+        if !shares.is_zero() && (x.is_zero() || y.is_zero()) {
+            return Err(StrategyError::InvalidStrategyState {});
+        }
+        Ok(XykState::from((x, y, shares)))
+    }
+
+    fn price(&self, state: &XykState) -> Decimal {
+        state.price()
+    }
+
+    fn poke(
+        &self,
+        state: &mut XykState,
+        price: Decimal,
+        available: &NativeBalance,
+    ) -> Result<NativeBalance, StrategyError> {
+        let current = state.price();
+        if current.is_zero() || price.is_zero() || current == price {
+            return Ok(NativeBalance::default());
+        }
+        let (x, y) = state.reserves();
+        let mut added = if current < price {
+            // X is under-priced, add Y up to the amount that brings the price to the oracle
+            let needed = x.mul_floor(price).saturating_sub(y);
+            let amount = min(needed, balance_of(available, &self.y));
+            state.set(state.x, state.y.add(amount));
+            NativeBalance(vec![coin(amount.u128(), self.y.as_str())])
+        } else {
+            // X is over-priced, add X
+            let needed = y
+                .multiply_ratio(price.denominator(), price.numerator())
+                .saturating_sub(x);
+            let amount = min(needed, balance_of(available, &self.x));
+            state.set(state.x.add(amount), state.y);
+            NativeBalance(vec![coin(amount.u128(), self.x.as_str())])
+        };
+        added.normalize();
+        Ok(added)
+    }
+}
+
+/// The amount of an `offer` of X to swap against reserves of `x` so that the remaining X and the
+/// Y returned, less the `fee`, are deposited at the reserve ratio after the swap. Solves
+/// (1 - fee)s^2 + (2 - fee)xs - offer * x = 0 for s
+fn zap_swap_amount(x: Uint128, offer: Uint128, fee: Decimal) -> Result<Uint128, StrategyError> {
+    let one = Uint512::from(Decimal::one().atomics());
+    let fee = Uint512::from(fee.atomics());
+    let x = Uint512::from(x);
+    let b = (one + one - fee) * x;
+    let disc = b * b + Uint512::from(4u128) * (one - fee) * one * Uint512::from(offer) * x;
+    let amount = (disc.isqrt() - b) / ((one - fee) * Uint512::from(2u128));
+    Ok(min(Uint128::try_from(amount)?, offer))
 }
 
 fn balance_of(balance: &NativeBalance, denom: &String) -> Uint128 {
@@ -353,6 +633,9 @@ mod test {
             min_quote: Uint128::zero(),
             step: Decimal::zero(),
             fee: Decimal::from_str("0.2").unwrap(),
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
         };
         let mut state = XykState::new();
         xyk.deposit(
@@ -395,6 +678,80 @@ mod test {
             .unwrap_err();
     }
 
+    #[test]
+    fn test_asymmetric_fee() {
+        let xyk = Xyk::new(
+            "x".to_string(),
+            "y".to_string(),
+            Decimal::permille(1),
+            Uint128::from(1000u128),
+            Decimal::percent(2),
+        )
+        .with_fee_ask(Some(Decimal::percent(1)))
+        .with_fee_bid(Some(Decimal::percent(5)));
+        xyk.validate().unwrap();
+        assert_eq!(xyk.fee_ask(), Decimal::percent(1));
+        assert_eq!(xyk.fee_bid(), Decimal::percent(5));
+        let err = xyk
+            .clone()
+            .with_fee_bid(Some(Decimal::percent(20)))
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("fee_bid"));
+
+        let deposit = || {
+            let mut state = XykState::new();
+            xyk.deposit(
+                &mut state,
+                NativeBalance(vec![coin(1000, "x"), coin(2000, "y")]),
+            )
+            .unwrap();
+            state
+        };
+
+        // 50 x returns 95 y, charged the 1% ask fee
+        let fee = xyk
+            .validate_swap(&mut deposit(), coin(50, "x"), coin(90, "y"))
+            .unwrap();
+        assert_eq!(fee, (coin(0, "y"), coin(5, "y")));
+        // 100 y returns 47 x, charged the 5% bid fee
+        let fee = xyk
+            .validate_swap(&mut deposit(), coin(100, "y"), coin(45, "x"))
+            .unwrap();
+        assert_eq!(fee, (coin(2, "x"), coin(0, "x")));
+        xyk.validate_swap(&mut deposit(), coin(100, "y"), coin(46, "x"))
+            .unwrap_err();
+
+        // Quotes net of each side's fee
+        let mut state = deposit();
+        xyk.deposit(
+            &mut state,
+            NativeBalance(vec![coin(1_000_000_000, "x"), coin(2_000_000_000, "y")]),
+        )
+        .unwrap();
+        let symmetric = xyk
+            .clone()
+            .with_fee(Decimal::percent(1))
+            .with_fee_ask(None)
+            .with_fee_bid(None);
+        let quote = |xyk: &Xyk, offer: &str, ask: &str| {
+            xyk.quote(
+                &state,
+                QuoteRequest {
+                    min_price: None,
+                    offer_denom: offer.to_string(),
+                    ask_denom: ask.to_string(),
+                    data: None,
+                },
+            )
+            .unwrap()
+            .unwrap()
+        };
+        assert_eq!(quote(&xyk, "x", "y"), quote(&symmetric, "x", "y"));
+        assert!(quote(&xyk, "y", "x").size < quote(&symmetric, "y", "x").size);
+        assert!(quote(&xyk, "y", "x").price < quote(&symmetric, "y", "x").price);
+    }
+
     #[test]
     fn test_deposit() {
         let xyk = Xyk {
@@ -403,6 +760,9 @@ mod test {
             min_quote: Uint128::zero(),
             step: Decimal::zero(),
             fee: Decimal::from_ratio(1u128, 10u128),
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
         };
 
         // Initial deposit. Share = sqrt(k)
@@ -475,6 +835,66 @@ mod test {
         assert_eq!(state.y, Uint128::from(3000u128));
     }
 
+    #[test]
+    fn test_zap() {
+        let xyk = |fee: Decimal| Xyk {
+            x: "x".to_string(),
+            y: "y".to_string(),
+            min_quote: Uint128::zero(),
+            step: Decimal::zero(),
+            fee,
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
+        };
+
+        // A zap can't open the pool
+        let mut state = XykState::new();
+        xyk(Decimal::zero())
+            .zap(&mut state, coin(1000, "x"))
+            .unwrap_err();
+
+        let mut state = XykState::from((
+            Uint128::from(1000u128),
+            Uint128::from(1000u128),
+            Uint128::from(1000u128),
+        ));
+        xyk(Decimal::zero())
+            .zap(&mut state.clone(), coin(1000, "z"))
+            .unwrap_err();
+
+        // 414x is swapped for 292y, and 586x deposited with it
+        let (fee, minted) = xyk(Decimal::zero())
+            .zap(&mut state.clone(), coin(1000, "x"))
+            .unwrap();
+        assert_eq!(fee, coin(0, "y"));
+        assert_eq!(minted, Uint128::from(414u128));
+
+        // With a fee, more is swapped, and the fee is retained outside of the reserves
+        let (fee, minted) = xyk(Decimal::percent(10))
+            .zap(&mut state, coin(1000, "x"))
+            .unwrap();
+        assert_eq!(fee, coin(30, "y"));
+        assert_eq!(minted, Uint128::from(392u128));
+        assert_eq!(state.x, Uint128::from(2000u128));
+        assert_eq!(state.y, Uint128::from(970u128));
+        assert_eq!(state.shares, Uint128::from(1392u128));
+
+        // And from the other side
+        let mut state = XykState::from((
+            Uint128::from(1000u128),
+            Uint128::from(1000u128),
+            Uint128::from(1000u128),
+        ));
+        let (fee, minted) = xyk(Decimal::zero())
+            .zap(&mut state, coin(1000, "y"))
+            .unwrap();
+        assert_eq!(fee, coin(0, "x"));
+        assert_eq!(minted, Uint128::from(414u128));
+        assert_eq!(state.x, Uint128::from(1000u128));
+        assert_eq!(state.y, Uint128::from(2000u128));
+    }
+
     #[test]
     fn test_withdraw() {
         let xyk = Xyk {
@@ -483,6 +903,9 @@ mod test {
             min_quote: Uint128::zero(),
             step: Decimal::zero(),
             fee: Decimal::zero(),
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
         };
 
         // Initial deposit. Share = sqrt(k)
@@ -507,6 +930,227 @@ mod test {
         assert_eq!(amount, NativeBalance(vec![coin(126, "x"), coin(251, "y")]));
     }
 
+    #[test]
+    fn test_invariants() {
+        let xyk = Xyk {
+            x: "x".to_string(),
+            y: "y".to_string(),
+            min_quote: Uint128::zero(),
+            step: Decimal::zero(),
+            fee: Decimal::percent(1),
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
+        };
+        let mut state = XykState::new();
+        let before = state.clone();
+        xyk.deposit(
+            &mut state,
+            NativeBalance(vec![coin(1000, "x"), coin(2000, "y")]),
+        )
+        .unwrap();
+        xyk.check_invariants(&before, &state).unwrap();
+
+        let before = state.clone();
+        xyk.deposit(&mut state, NativeBalance(vec![coin(333, "x")]))
+            .unwrap();
+        xyk.check_invariants(&before, &state).unwrap();
+
+        let before = state.clone();
+        xyk.validate_swap(&mut state, coin(100, "y"), coin(10, "x"))
+            .unwrap();
+        xyk.check_invariants(&before, &state).unwrap();
+
+        let before = state.clone();
+        xyk.withdraw(&mut state, Uint128::from(500u128)).unwrap();
+        xyk.check_invariants(&before, &state).unwrap();
+
+        // Reserves drained without burning shares
+        let mut broken = state.clone();
+        broken.set(state.x, state.y - Uint128::one());
+        let err = xyk.check_invariants(&state, &broken).unwrap_err();
+        assert!(err.to_string().contains("reserve product decreased"));
+
+        // Shares minted without a matching deposit
+        let mut broken = state.clone();
+        broken.set(state.x + Uint128::one(), state.y);
+        broken.shares += Uint128::from(10u128);
+        let err = xyk.check_invariants(&state, &broken).unwrap_err();
+        assert!(err.to_string().contains("share value decreased"));
+    }
+
+    #[test]
+    fn test_migrate_state() {
+        let xyk = Xyk {
+            x: "x".to_string(),
+            y: "y".to_string(),
+            min_quote: Uint128::zero(),
+            step: Decimal::zero(),
+            fee: Decimal::zero(),
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
+        };
+
+        let mut state = XykState::new();
+        xyk.deposit(
+            &mut state,
+            NativeBalance(vec![coin(250, "x"), coin(500, "y")]),
+        )
+        .unwrap();
+
+        let migrated = xyk
+            .migrate_state(&xyk.balances(&state), xyk.shares(&state))
+            .unwrap();
+        assert_eq!(migrated, state);
+
+        // Assets of a different pair can't back the existing shares
+        let other = Xyk {
+            x: "z".to_string(),
+            ..xyk.clone()
+        };
+        other
+            .migrate_state(&xyk.balances(&state), xyk.shares(&state))
+            .unwrap_err();
+
+        // An empty strategy can be migrated to any pair
+        let migrated = other
+            .migrate_state(&NativeBalance::default(), Uint128::zero())
+            .unwrap();
+        assert_eq!(migrated, XykState::new());
+    }
+
+    #[test]
+    fn test_poke() {
+        let xyk = Xyk {
+            x: "x".to_string(),
+            y: "y".to_string(),
+            min_quote: Uint128::zero(),
+            step: Decimal::zero(),
+            fee: Decimal::zero(),
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
+        };
+
+        let mut state = XykState::new();
+        xyk.deposit(
+            &mut state,
+            NativeBalance(vec![coin(1000, "x"), coin(2000, "y")]),
+        )
+        .unwrap();
+
+        // Only as much as is available is added
+        let added = xyk
+            .poke(
+                &mut state,
+                Decimal::from_ratio(3u128, 1u128),
+                &NativeBalance(vec![coin(100, "x"), coin(100, "y")]),
+            )
+            .unwrap();
+        assert_eq!(added, NativeBalance(vec![coin(100, "y")]));
+        assert_eq!(state.y, Uint128::from(2100u128));
+
+        // Never beyond the oracle price
+        let added = xyk
+            .poke(
+                &mut state,
+                Decimal::from_ratio(2u128, 1u128),
+                &NativeBalance(vec![coin(1000, "x"), coin(1000, "y")]),
+            )
+            .unwrap();
+        assert_eq!(added, NativeBalance(vec![coin(50, "x")]));
+        assert_eq!(xyk.price(&state), Decimal::from_ratio(2u128, 1u128));
+
+        // Nothing to do at the oracle price
+        let added = xyk
+            .poke(
+                &mut state,
+                Decimal::from_ratio(2u128, 1u128),
+                &NativeBalance(vec![coin(1000, "x"), coin(1000, "y")]),
+            )
+            .unwrap();
+        assert_eq!(added, NativeBalance::default());
+    }
+
+    #[test]
+    fn test_virtual_reserves() {
+        let v = VirtualReserves {
+            x: Uint128::from(1000u128),
+            y: Uint128::from(2000u128),
+            start: Timestamp::from_seconds(100),
+            duration: 100,
+        };
+        let full = (Uint128::from(1000u128), Uint128::from(2000u128));
+        assert_eq!(v.at(Timestamp::from_seconds(0)), full);
+        assert_eq!(v.at(Timestamp::from_seconds(100)), full);
+        assert_eq!(
+            v.at(Timestamp::from_seconds(150)),
+            (Uint128::from(500u128), Uint128::from(1000u128))
+        );
+        assert_eq!(
+            v.at(Timestamp::from_seconds(1000)),
+            (Uint128::zero(), Uint128::zero())
+        );
+
+        Xyk::new(
+            "x".into(),
+            "y".into(),
+            Decimal::percent(10),
+            Uint128::from(Xyk::MIN_MIN_QUOTE),
+            Decimal::zero(),
+        )
+        .with_virtual_reserves(VirtualReserves {
+            duration: 0,
+            ..v.clone()
+        })
+        .validate()
+        .unwrap_err();
+
+        let xyk = Xyk::new(
+            "x".into(),
+            "y".into(),
+            Decimal::percent(10),
+            Uint128::zero(),
+            Decimal::zero(),
+        )
+        .with_virtual_reserves(v.clone());
+        let mut state = XykState::new();
+        xyk.deposit(
+            &mut state,
+            NativeBalance(vec![coin(100, "x"), coin(200, "y")]),
+        )
+        .unwrap();
+        (state.virtual_x, state.virtual_y) = v.at(Timestamp::from_seconds(100));
+        assert_eq!(state.price(), Decimal::from_ratio(2u128, 1u128));
+
+        // Priced against the virtual reserves, paid from the real reserves
+        let returned = state.swap(&Uint128::from(110u128)).unwrap();
+        assert_eq!(returned, Uint128::from(200u128));
+        assert_eq!(state.x, Uint128::from(210u128));
+        assert_eq!(state.y, Uint128::zero());
+        state.swap(&Uint128::one()).unwrap_err();
+
+        // No quotes once the real reserves are exhausted
+        let quote = xyk
+            .quote(
+                &state,
+                QuoteRequest {
+                    min_price: None,
+                    offer_denom: "x".to_string(),
+                    ask_denom: "y".to_string(),
+                    data: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(quote, None);
+
+        // Virtual reserves can't be withdrawn
+        let shares = xyk.shares(&state);
+        let withdrawn = xyk.withdraw(&mut state, shares).unwrap();
+        assert_eq!(withdrawn, NativeBalance(vec![coin(210, "x")]));
+    }
+
     #[test]
     fn pool_state_swaps() {
         let xyk = Xyk {
@@ -515,6 +1159,9 @@ mod test {
             min_quote: Uint128::zero(),
             step: Decimal::zero(),
             fee: Decimal::zero(),
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
         };
 
         let mut state = XykState::new();
@@ -552,6 +1199,9 @@ mod test {
             min_quote: Uint128::from(10_000u128),
             step: Decimal::from_ratio(1u128, 1000u128),
             fee: Decimal::zero(),
+            virtual_reserves: None,
+            fee_bid: None,
+            fee_ask: None,
         };
 
         let mut state = XykState::new();
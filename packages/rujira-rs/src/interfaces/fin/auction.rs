@@ -0,0 +1,59 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{to_json_binary, Addr, Coin, CosmosMsg, Decimal, StdResult, Uint128, WasmMsg};
+
+use super::SwapRequest;
+
+/// Market swaps offering at least `min_offer` are first auctioned to `bidders`, who may return more
+/// than the book for the offer. See [super::SudoMsg::SetOrderFlowAuction]
+#[cw_serde]
+pub struct OrderFlowAuction {
+    /// The smallest offer auctioned, per denom. Swaps of denoms not listed go to the book
+    pub min_offer: Vec<Coin>,
+    /// The contracts queried for bids. Must implement [BidderQueryMsg] and [BidderMsg]
+    pub bidders: Vec<Addr>,
+    /// How much more than the book return, before fees, a bid must be to win
+    pub min_improvement: Decimal,
+}
+
+/// Queried by the pair for each auctioned swap
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum BidderQueryMsg {
+    /// The amount of `ask_denom` the bidder commits to return for `offer`, or `None` to pass.
+    /// `book` is the amount the book returns for `offer`, before fees
+    #[returns(Option<Uint128>)]
+    Bid {
+        offer: Coin,
+        ask_denom: String,
+        book: Uint128,
+    },
+}
+
+/// Executed by the pair on the winning bidder
+#[cw_serde]
+pub enum BidderMsg {
+    /// Sent with the offer. The bidder must send at least `bid` of `ask_denom` back to the pair
+    /// with a bank send within the execution, or the swap falls through to the book. The pair
+    /// rejects funds sent with any other message while the swap is in progress
+    Fill { ask_denom: String, bid: Uint128 },
+}
+
+impl BidderMsg {
+    pub fn call(&self, bidder: &Addr, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: bidder.to_string(),
+            msg: to_json_binary(self)?,
+            funds,
+        }
+        .into())
+    }
+}
+
+/// A swap won by `bidder` at `bid`, filled with [super::ExecuteMsg::DoAuctionFill]
+#[cw_serde]
+pub struct AuctionFill {
+    pub sender: Addr,
+    pub req: SwapRequest,
+    pub bidder: Addr,
+    pub bid: Uint128,
+}
@@ -1,4 +1,4 @@
-use crate::{CallbackData, Layer1Asset};
+use crate::{CallbackData, CallbackMsg, Layer1Asset};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Addr, Binary, Decimal, Uint128};
 
@@ -19,6 +19,7 @@ pub struct InstantiateMsg {
     /// This accommodates prices decreasing and adding zeroes,
     /// but may need adjusting for better UX as prices increase
     /// (e.g. 4sf is plenty for most tokens < $10 - 1 cent, but above $1000 it creates a $1 tick)  
+    /// [Tick::free] disables truncation, for long-tail pairs that need prices at the full Decimal precision
     pub tick: Tick,
 
     /// The fee charged on swaps, and instantly filled limit orders
@@ -37,6 +38,8 @@ pub type OrderTarget = (Side, Price, Option<Uint128>);
 #[cw_serde]
 pub enum ExecuteMsg {
     /// Executes a market trade based on current order book.
+    /// Funds in the underlying of a [super::WrapAdapter] are wrapped into its receipt token first,
+    /// and any of the receipt token returned is unwrapped
     Swap(SwapRequest),
 
     /// Manage all orders
@@ -49,8 +52,130 @@ pub enum ExecuteMsg {
     ///
     /// Funds sent must be equal to the net change of balances. Funds withdrawn in step 0 and retracted in 1's,
     /// can be reused to fund orders in 1 and 3  
+    ///
+    /// Funds in the underlying of a [super::WrapAdapter] are wrapped as with [ExecuteMsg::Swap], and
+    /// funds in an [super::AuxQuote] are swapped into the quote asset first
     Order((Vec<OrderTarget>, Option<CallbackData>)),
 
+    /// Place `levels` orders on `side`, evenly spaced from the first to the second price of `side_range`,
+    /// splitting `total` equally between them.
+    /// Both prices must be the same [Price] variant. Fixed prices are truncated to the tick.
+    /// The grid is stored under an id, emitted on the `grid.create` event, so that it can be repriced
+    /// or cancelled as a unit. Funds sent must cover `total`
+    Grid {
+        side_range: (Price, Price),
+        levels: u8,
+        total: Uint128,
+        side: Side,
+    },
+
+    /// Move a grid to a new price range, keeping its levels and total.
+    /// Filled amounts are withdrawn, and retracted orders are reused to fund the new levels
+    RepriceGrid {
+        id: u64,
+        side_range: (Price, Price),
+    },
+
+    /// Retract all orders of a grid, withdrawing any filled amounts, and remove the grid
+    CancelGrid {
+        id: u64,
+    },
+
+    /// Escrow the funds sent, to be swapped in equal slices over time. See [TwapRequest].
+    /// The id is emitted on the `twap.create` event
+    Twap(TwapRequest),
+
+    /// Places orders with funds sent by a trusted caller, e.g. a GHOST or BOW withdrawal with a callback.
+    /// [CallbackMsg::callback] must be an [OrderCallback], and the orders are owned by
+    /// [OrderCallback::for_account]. The sender must be set with [super::SudoMsg::SetTrustedCaller],
+    /// and can only place or increase orders: each target must be at least the order's remaining offer
+    ///
+    /// Also receives the deposits and withdrawals made with the vault of a [super::WrapAdapter],
+    /// and the swaps of an [super::AuxQuote]
+    Callback(CallbackMsg),
+
+    /// Registers a callback with the order of the sender at `side` and `price`, sent an [OrderFill]
+    /// when the order is filled. Only contracts can register a callback, and `None` removes it.
+    /// Callbacks are sent when the fill is withdrawn, or with [ExecuteMsg::CrankFill]. They are gas limited,
+    /// and a failing callback doesn't revert the withdrawal or crank
+    SetFillCallback {
+        side: Side,
+        price: Price,
+        callback: Option<CallbackData>,
+    },
+
+    /// Restricts the `to` of the sender's swaps to `addresses`, in addition to the sender itself, so that a
+    /// compromised key can't route the sender's swap returns elsewhere. It's enforced when the swap is
+    /// requested and again at its payout. Orders, grids and TWAPs always pay their owner. `None` removes
+    /// the restriction. Removing it or adding addresses takes effect after a 24 hour delay, and
+    /// tightening it immediately
+    SetWithdrawWhitelist {
+        addresses: Option<Vec<String>>,
+    },
+
+    /// Permissionless. Sends the fill callback of an order that has been filled since its last callback.
+    /// Rate limited per order
+    CrankFill {
+        owner: String,
+        side: Side,
+        price: Price,
+    },
+
+    /// Permissionless. Swaps the slices of a TWAP that are due, sending the return to its owner
+    CrankTwap {
+        id: u64,
+    },
+
+    /// Cancel a TWAP, refunding the remaining offer
+    CancelTwap {
+        id: u64,
+    },
+
+    /// Opens a maker session that expires `ttl` seconds from now, unless kept alive with [ExecuteMsg::Heartbeat].
+    /// Funds sent are held as the bounty for retracting its orders with [ExecuteMsg::ExpireMakerSession].
+    /// The id is emitted on the `maker_session.open` event
+    OpenMakerSession {
+        ttl: u64,
+    },
+
+    /// Extends a maker session to `ttl` seconds from now. Only its owner can heartbeat, and only
+    /// before it has expired
+    Heartbeat {
+        id: u64,
+    },
+
+    /// Executes `orders` as [ExecuteMsg::Order], tagging the orders with the maker session `id` so that
+    /// they are retracted when it expires. Orders retracted in full are untagged. Tags apply to the order
+    /// of the owner at a side and price, which is shared with any other order placed there
+    MakerSessionOrder {
+        id: u64,
+        orders: (Vec<OrderTarget>, Option<CallbackData>),
+    },
+
+    /// Closes a maker session before it expires, returning its bounty. Its orders are left in place
+    CloseMakerSession {
+        id: u64,
+    },
+
+    /// Permissionless. Retracts the orders of an expired maker session, withdrawing any filled amounts
+    /// to its owner, and pays its bounty to the caller
+    ExpireMakerSession {
+        id: u64,
+    },
+
+    /// Permissionless. Removes the stored sum snapshots and bid pools at each of `keys` that are empty
+    /// and have no orders, up to `limit` entries in total. When a [super::SweepBounty] is set, the
+    /// caller is paid a share of the fees reserved for it, per entry removed
+    Sweep {
+        keys: Vec<(Side, Price)>,
+        limit: Option<u32>,
+    },
+
+    /// Permissionless. Matches the crossing orders of the last closed window of a [super::Session] at the
+    /// oracle price, resuming continuous trading. A deep crossed book is matched over several calls,
+    /// a bounded number of pools at a time, until nothing is left crossing
+    Settle {},
+
     Arb {
         then: Option<Binary>,
     },
@@ -59,6 +184,38 @@ pub enum ExecuteMsg {
     DoSwap((Addr, SwapRequest)),
     /// Callback action to support an arb prior to a order execution
     DoOrder((Addr, (Vec<OrderTarget>, Option<CallbackData>))),
+    /// Callback action to support an arb prior to a TWAP slice execution
+    DoTwap(u64),
+    /// Callback action sending the offer of a swap won in a [super::OrderFlowAuction] to its bidder
+    DoAuctionFill(super::AuctionFill),
+    /// Callback action paying out the return of a swap won in a [super::OrderFlowAuction]
+    SettleAuction {},
+}
+
+/// The callback data of an [ExecuteMsg::Callback]. `orders` are executed as [ExecuteMsg::Order]
+#[cw_serde]
+pub struct OrderCallback {
+    pub for_account: String,
+    pub orders: (Vec<OrderTarget>, Option<CallbackData>),
+}
+
+/// The data of a fill callback registered with [ExecuteMsg::SetFillCallback]
+#[cw_serde]
+pub struct OrderFill {
+    pub side: Side,
+    pub price: Price,
+    /// The amount filled since the previous callback
+    pub filled: Uint128,
+}
+
+/// A parent order swapped in slices, one every `interval` seconds over `duration` seconds.
+/// Any part of a slice not swapped within the `limit` is carried over to the next slice
+#[cw_serde]
+pub struct TwapRequest {
+    pub duration: u64,
+    pub interval: u64,
+    /// The limit price of each slice. See [SwapRequest::Limit]
+    pub limit: Option<Decimal>,
 }
 
 #[cw_serde]
@@ -68,26 +225,41 @@ pub enum SwapRequest {
     Yolo {
         to: Option<String>,
         callback: Option<CallbackData>,
+        /// Stop after trading against this many price levels, returning the remaining offer
+        #[serde(default)]
+        max_levels: Option<u32>,
     },
     /// Return at least `min_return` or fail
     Min {
         min_return: Uint128,
         to: Option<String>,
         callback: Option<CallbackData>,
+        /// See [SwapRequest::Yolo]
+        #[serde(default)]
+        max_levels: Option<u32>,
     },
     /// Return exactly `exact_return` or fail
     Exact {
         exact_return: Uint128,
         to: Option<String>,
         callback: Option<CallbackData>,
+        /// See [SwapRequest::Yolo]
+        #[serde(default)]
+        max_levels: Option<u32>,
     },
     /// Swap as much of the input as possible at or better than `price`, returning any unused offer
     Limit {
         /// The Limit price.
         /// N.B. this is quoted in the `ask` token. I.e a buy will be a "normal" price, a sell will be 1/"normal" price;
         price: Decimal,
+        /// Fail unless at least this much of the offer is swapped within the limit
+        #[serde(default)]
+        min_fill: Option<Uint128>,
         to: Option<String>,
         callback: Option<CallbackData>,
+        /// See [SwapRequest::Yolo]
+        #[serde(default)]
+        max_levels: Option<u32>,
     },
 }
 
@@ -108,4 +280,13 @@ impl SwapRequest {
             SwapRequest::Limit { to, .. } => to.clone(),
         }
     }
+    /// The maximum number of price levels traded against. Bounds the gas of a deep swap
+    pub fn max_levels(&self) -> Option<u32> {
+        match self {
+            SwapRequest::Min { max_levels, .. } => *max_levels,
+            SwapRequest::Exact { max_levels, .. } => *max_levels,
+            SwapRequest::Yolo { max_levels, .. } => *max_levels,
+            SwapRequest::Limit { max_levels, .. } => *max_levels,
+        }
+    }
 }
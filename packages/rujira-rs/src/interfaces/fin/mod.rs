@@ -1,3 +1,4 @@
+mod auction;
 mod denoms;
 mod execute;
 mod price;
@@ -6,10 +7,16 @@ mod side;
 mod sudo;
 mod tick;
 
+pub use auction::{AuctionFill, BidderMsg, BidderQueryMsg, OrderFlowAuction};
 pub use denoms::Denoms;
-pub use execute::{ExecuteMsg, InstantiateMsg, OrderTarget, SwapRequest};
-pub use price::Price;
+pub use execute::{
+    ExecuteMsg, InstantiateMsg, OrderCallback, OrderFill, OrderTarget, SwapRequest, TwapRequest,
+};
+pub use price::{Decay, DecimalDelta, PremiumBounds, Price, Scaled};
 pub use query::*;
 pub use side::Side;
-pub use sudo::SudoMsg;
+pub use sudo::{
+    AuxQuote, FeeConversion, LegacyOrder, MarketMakerLimit, OracleFallback, Session, SudoMsg,
+    SweepBounty, WrapAdapter,
+};
 pub use tick::{Tick, TickError};
@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Decimal, Fraction, StdError, Uint128};
+use cosmwasm_std::{Decimal, Fraction, StdError, Timestamp, Uint128};
 use cw_storage_plus::{IntKey, Key, KeyDeserialize, PrimaryKey};
 
 use crate::Premiumable;
@@ -10,6 +10,52 @@ use crate::Premiumable;
 pub enum Price {
     Fixed(Decimal),
     Oracle(i16),
+    /// An oracle premium that moves linearly from `from` to `to` over `duration` seconds,
+    /// starting at `start`
+    Decay(Decay),
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub struct Decay {
+    pub from: i16,
+    pub to: i16,
+    /// Unix time in seconds
+    pub start: u64,
+    /// Seconds
+    pub duration: u64,
+}
+
+impl Decay {
+    /// The premium in effect at `time`. Fixed at `from` before `start`, and at `to` once `duration` has elapsed
+    pub fn premium(&self, time: &Timestamp) -> i16 {
+        if self.duration == 0 {
+            return self.to;
+        }
+        let elapsed = time.seconds().saturating_sub(self.start).min(self.duration);
+        let delta = (i128::from(self.to) - i128::from(self.from)) * i128::from(elapsed)
+            / i128::from(self.duration);
+        self.from + delta as i16
+    }
+
+    fn to_bytes(self) -> Vec<u8> {
+        [
+            self.from.to_cw_bytes().as_slice(),
+            self.to.to_cw_bytes().as_slice(),
+            self.start.to_cw_bytes().as_slice(),
+            self.duration.to_cw_bytes().as_slice(),
+        ]
+        .concat()
+    }
+
+    fn from_bytes(value: [u8; 20]) -> Self {
+        Self {
+            from: i16::from_cw_bytes(value[0..2].try_into().unwrap()),
+            to: i16::from_cw_bytes(value[2..4].try_into().unwrap()),
+            start: u64::from_cw_bytes(value[4..12].try_into().unwrap()),
+            duration: u64::from_cw_bytes(value[12..20].try_into().unwrap()),
+        }
+    }
 }
 
 impl Display for Price {
@@ -17,16 +63,106 @@ impl Display for Price {
         match self {
             Price::Fixed(fixed) => write!(f, "fixed:{}", fixed),
             Price::Oracle(deviation) => write!(f, "oracle:{}", deviation),
+            Price::Decay(decay) => write!(
+                f,
+                "decay:{}:{}:{}:{}",
+                decay.from, decay.to, decay.start, decay.duration
+            ),
+        }
+    }
+}
+
+/// The range of premiums, in basis points, accepted on new [Price::Oracle] orders
+#[cw_serde]
+pub struct PremiumBounds {
+    pub min: i16,
+    pub max: i16,
+}
+
+impl Default for PremiumBounds {
+    fn default() -> Self {
+        Self {
+            min: -5000,
+            max: 5000,
+        }
+    }
+}
+
+impl PremiumBounds {
+    pub fn new(min: i16, max: i16) -> Self {
+        Self { min, max }
+    }
+
+    /// A premium of -10000 or below would price the order at zero
+    pub fn is_valid(&self) -> bool {
+        self.min > -10000 && self.min <= self.max
+    }
+
+    pub fn contains(&self, bps: &i16) -> bool {
+        (self.min..=self.max).contains(bps)
+    }
+}
+
+/// The difference between the decimals of the quote and the base asset.
+/// Prices are expressed in human units, and scaled by `10^delta` to the atomic rate used to settle trades
+#[cw_serde]
+#[derive(Copy, Default)]
+pub struct DecimalDelta(pub i8);
+
+impl DecimalDelta {
+    pub const MAX: i8 = 18;
+
+    pub fn is_valid(&self) -> bool {
+        self.0.unsigned_abs() <= Self::MAX.unsigned_abs()
+    }
+
+    pub fn to_atomic(&self, rate: Decimal) -> Decimal {
+        let pow = Decimal::from_ratio(10u128.pow(u32::from(self.0.unsigned_abs())), 1u128);
+        if self.0 < 0 {
+            rate / pow
+        } else {
+            rate.saturating_mul(pow)
         }
     }
+
+    pub fn to_human(&self, rate: Decimal) -> Decimal {
+        Self(-self.0).to_atomic(rate)
+    }
+}
+
+/// An oracle scaled by the [DecimalDelta] of the pair, so that [Price::to_rate] returns atomic rates
+pub struct Scaled<T> {
+    pub oracle: T,
+    pub decimal_delta: DecimalDelta,
+    /// The block time that [Price::Decay] premiums are evaluated at
+    pub time: Timestamp,
+}
+
+impl<T: Premiumable> Premiumable for Scaled<T> {
+    fn adjust(&self, bps: &i16) -> Decimal {
+        self.oracle.adjust(bps)
+    }
+
+    fn time(&self) -> Timestamp {
+        self.time
+    }
+
+    fn available(&self) -> bool {
+        self.oracle.available()
+    }
+
+    fn scale(&self, rate: Decimal) -> Decimal {
+        self.decimal_delta.to_atomic(rate)
+    }
 }
 
 impl Price {
     pub fn to_rate(&self, oracle: &impl Premiumable) -> Decimal {
-        match self {
+        oracle.scale(match self {
             Price::Fixed(fixed) => *fixed,
             Price::Oracle(bps) => oracle.adjust(bps),
-        }
+            Price::Decay(decay) => oracle.adjust(&decay.premium(&oracle.time())),
+        })
     }
 }
 
@@ -40,6 +176,7 @@ impl PrimaryKey<'_> for Price {
         match self {
             Price::Fixed(fixed) => vec![Key::Val128(fixed.numerator().to_be_bytes())],
             Price::Oracle(deviation) => vec![Key::Val16(deviation.to_cw_bytes())],
+            Price::Decay(decay) => vec![Key::Owned(decay.to_bytes())],
         }
     }
 }
@@ -54,6 +191,7 @@ impl KeyDeserialize for Price {
                 u128::from_be_bytes(value.try_into().unwrap()),
             )))),
             2 => Ok(Self::Oracle(i16::from_cw_bytes(value.try_into().unwrap()))),
+            20 => Ok(Self::Decay(Decay::from_bytes(value.try_into().unwrap()))),
             _ => Err(StdError::generic_err("invalid Price key")),
         }
     }
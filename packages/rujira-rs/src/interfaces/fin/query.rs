@@ -1,9 +1,9 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{Coin, Decimal, Timestamp, Uint128};
 
-use crate::Layer1Asset;
+use crate::{Layer1Asset, PageRequest, PageResponse};
 
-use super::{side::Side, Denoms, Price, Tick};
+use super::{side::Side, DecimalDelta, Denoms, PremiumBounds, Price, Tick, TwapRequest};
 
 /// Standard interface to query contract state
 #[cw_serde]
@@ -27,15 +27,55 @@ pub enum QueryMsg {
         /// N.B: This sorts on the underlying Price key, not the effective execution price,
         /// so Oracle and Fixed prices will be grouped
         side: Option<Side>,
-        offset: Option<u8>,
-        limit: Option<u8>,
+        page: PageRequest<(Side, Price)>,
     },
 
+    /// Paginate all orders resting at a price level, ordered by owner. Upper limit of 30 per page
+    #[returns(PoolOrdersResponse)]
+    PoolOrders {
+        side: Side,
+        price: Price,
+        page: PageRequest<String>,
+    },
+
+    /// Paginate the aggregated price levels of each side of the book. Upper limit of 100 per page
     #[returns(BookResponse)]
     Book {
-        limit: Option<u8>,
-        offset: Option<u8>,
+        page: PageRequest<BookCursor>,
+        /// Merges levels into buckets of this price multiple. Asks are rounded up and bids down
+        /// to the bucket bound, and each bucket reports its total and best price
+        #[serde(default)]
+        group_by: Option<Decimal>,
     },
+
+    /// Top of the book and most recent trade, without loading the full book
+    #[returns(TickerResponse)]
+    Ticker {},
+
+    /// Find a grid placed with [super::ExecuteMsg::Grid]
+    #[returns(GridResponse)]
+    Grid { id: u64 },
+
+    /// Progress of a TWAP placed with [super::ExecuteMsg::Twap]
+    #[returns(TwapResponse)]
+    Twap { id: u64 },
+
+    /// A maker session opened with [super::ExecuteMsg::OpenMakerSession]
+    #[returns(MakerSessionResponse)]
+    MakerSession { id: u64 },
+
+    /// Running totals of revenue earned by the contract
+    #[returns(StatsResponse)]
+    Stats {},
+
+    /// The arbitrage of the crossed book that [super::ExecuteMsg::Arb] would capture if cranked now
+    #[returns(ArbOpportunityResponse)]
+    ArbOpportunity {},
+
+    /// The addresses set with [super::ExecuteMsg::SetWithdrawWhitelist] by `owner` in effect now, excluding a
+    /// change still timelocked. `None` when it has no whitelist
+    #[returns(Option<Vec<String>>)]
+    WithdrawWhitelist { owner: String },
 }
 
 #[cw_serde]
@@ -60,6 +100,62 @@ pub struct ConfigResponse {
 
     /// See [InstantiateMsg::fee_address]
     pub fee_address: String,
+
+    /// The range of premiums accepted on new [Price::Oracle] orders
+    #[serde(default)]
+    pub premium_bounds: PremiumBounds,
+
+    /// The decimals of the quote asset less those of the base asset. Prices are expressed in human units
+    #[serde(default)]
+    pub decimal_delta: DecimalDelta,
+
+    /// Contracts permitted to place orders on behalf of other accounts
+    #[serde(default)]
+    pub trusted_callers: Vec<String>,
+
+    /// See [super::SudoMsg::SetWrapAdapter]
+    #[serde(default)]
+    pub wrap: Option<super::WrapAdapter>,
+
+    /// See [super::SudoMsg::SetSweepBounty]
+    #[serde(default)]
+    pub sweep_bounty: Option<super::SweepBounty>,
+
+    /// See [super::SudoMsg::SetSession]
+    #[serde(default)]
+    pub session: Option<super::Session>,
+
+    /// See [super::SudoMsg::SetOracleFallback]
+    #[serde(default)]
+    pub oracle_fallback: super::OracleFallback,
+
+    /// See [super::SudoMsg::SetFeeConversion]
+    #[serde(default)]
+    pub fee_conversion: Option<super::FeeConversion>,
+
+    /// See [super::SudoMsg::SetFeeAddresses]
+    #[serde(default)]
+    pub fee_address_base: Option<String>,
+
+    /// See [super::SudoMsg::SetFeeAddresses]
+    #[serde(default)]
+    pub fee_address_quote: Option<String>,
+
+    /// See [super::SudoMsg::SetAuxQuote]
+    #[serde(default)]
+    pub aux_quotes: Vec<super::AuxQuote>,
+
+    /// See [super::SudoMsg::SetMarketMakerLimit]
+    #[serde(default)]
+    pub market_maker_limit: Option<super::MarketMakerLimit>,
+
+    /// See [super::SudoMsg::SetTargetEpsilon]
+    #[serde(default)]
+    pub target_epsilon: Uint128,
+
+    /// See [super::SudoMsg::SetOrderFlowAuction]
+    #[serde(default)]
+    pub order_flow_auction: Option<super::OrderFlowAuction>,
 }
 
 #[cw_serde]
@@ -76,6 +172,10 @@ pub struct OrderResponse {
     /// The rate at which this order would execute at the current moment in time
     pub rate: Decimal,
 
+    /// The rate in USD, converted with the oracle price of the quote asset. None if the pair has no oracles
+    #[serde(default)]
+    pub rate_usd: Option<Decimal>,
+
     /// The last time this order was touched (created, incremented or reduced) in an Order execution
     pub updated_at: Timestamp,
 
@@ -92,18 +192,164 @@ pub struct OrderResponse {
 #[cw_serde]
 pub struct OrdersResponse {
     pub orders: Vec<OrderResponse>,
+    pub page: PageResponse<(Side, Price)>,
+}
+
+#[cw_serde]
+pub struct PoolOrdersResponse {
+    pub orders: Vec<OrderResponse>,
+    pub page: PageResponse<String>,
 }
 
 #[cw_serde]
 pub struct BookResponse {
     pub base: Vec<BookItemResponse>,
     pub quote: Vec<BookItemResponse>,
+    pub page: PageResponse<BookCursor>,
+}
+
+/// The last rate returned on each side of the book
+#[cw_serde]
+#[derive(Default)]
+pub struct BookCursor {
+    pub base: Option<Decimal>,
+    pub quote: Option<Decimal>,
 }
 
 #[cw_serde]
 pub struct BookItemResponse {
     pub price: Decimal,
     pub total: Uint128,
+    /// The price in USD, converted with the oracle price of the quote asset. None if the pair has no oracles
+    #[serde(default)]
+    pub rate_usd: Option<Decimal>,
+    /// The bound of the bucket when queried with `group_by`. `price` is the best price within it
+    #[serde(default)]
+    pub bucket: Option<Decimal>,
+}
+
+#[cw_serde]
+pub struct StatsResponse {
+    /// Total profit from arbitrage between the book and market makers, per denom
+    pub arb_profit: Vec<Coin>,
+
+    /// Total arbitrage profit, valued in USD by the oracles at the time of each arb
+    pub arb_profit_usd: Decimal,
+
+    #[serde(default)]
+    pub base: SideStatsResponse,
+
+    #[serde(default)]
+    pub quote: SideStatsResponse,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct SideStatsResponse {
+    /// Total offer resting in the pools of the side, in its bid denom
+    pub resting: Uint128,
+
+    /// Number of open orders, including filled orders awaiting withdrawal
+    pub orders: u32,
+
+    /// Fills credited to the orders of the side and not yet withdrawn, in its ask denom
+    #[serde(default)]
+    pub unclaimed: Uint128,
+
+    /// Offer escrowed by TWAPs swapping against the side, in its ask denom
+    #[serde(default)]
+    pub escrowed: Uint128,
+}
+
+#[cw_serde]
+pub struct TickerResponse {
+    /// Highest rate on the Quote side of the book
+    pub best_bid: Option<Decimal>,
+
+    /// Lowest rate on the Base side of the book
+    pub best_ask: Option<Decimal>,
+
+    /// Midpoint of best_bid and best_ask, when both are present
+    pub mid: Option<Decimal>,
+
+    /// Absolute difference between best_bid and best_ask, when both are present
+    pub spread: Option<Decimal>,
+
+    /// Rate of the last swap executed against the book
+    pub last_price: Option<Decimal>,
+
+    /// Block time of the last swap executed against the book
+    pub last_time: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct MakerSessionResponse {
+    pub id: u64,
+
+    /// The account which opened the session
+    pub owner: String,
+
+    pub ttl: u64,
+
+    /// The session can be expired from this time, unless its owner sends a heartbeat before it
+    pub expires: Timestamp,
+
+    /// Paid to the caller of [super::ExecuteMsg::ExpireMakerSession]
+    pub bounty: Vec<Coin>,
+
+    /// The orders of the owner tagged with the session
+    pub orders: Vec<(Side, Price)>,
+}
+
+#[cw_serde]
+pub struct GridResponse {
+    pub id: u64,
+
+    /// The account which placed the grid
+    pub owner: String,
+
+    pub side: Side,
+
+    /// The price range the grid was last placed over
+    pub side_range: (Price, Price),
+
+    pub levels: u8,
+
+    /// The total offer amount split across the levels
+    pub total: Uint128,
+
+    /// The price of each level. See [QueryMsg::Order] for the current state of each order
+    pub prices: Vec<Price>,
+}
+
+#[cw_serde]
+pub struct TwapResponse {
+    pub id: u64,
+
+    /// The account which placed the TWAP, and receives the return of each slice
+    pub owner: String,
+
+    /// The side of the book the offer is swapped against
+    pub side: Side,
+
+    pub request: TwapRequest,
+
+    /// The total offer amount escrowed
+    pub offer: Uint128,
+
+    /// The offer amount not yet swapped
+    pub remaining: Uint128,
+
+    /// The total amount returned to the owner so far
+    pub returned: Uint128,
+
+    pub start: Timestamp,
+
+    /// The number of slices the offer is split into
+    pub slices: u64,
+
+    /// The offer amount that would be swapped if cranked now
+    pub due: Uint128,
 }
 
 #[cw_serde]
@@ -111,3 +357,18 @@ pub struct SimulationResponse {
     pub returned: Uint128,
     pub fee: Uint128,
 }
+
+#[cw_serde]
+pub struct ArbOpportunityResponse {
+    /// Whether orders on each side of the book cross
+    pub crossed: bool,
+
+    /// The amount of each denom filled from the crossing orders
+    pub size: Vec<Coin>,
+
+    /// The profit captured, per denom
+    pub profit: Vec<Coin>,
+
+    /// The USD value of `profit`, when the pair has oracles
+    pub profit_usd: Option<Decimal>,
+}
@@ -1,9 +1,9 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Decimal;
+use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128};
 
 use crate::Layer1Asset;
 
-use super::Tick;
+use super::{DecimalDelta, PremiumBounds, Tick};
 
 #[cw_serde]
 pub enum SudoMsg {
@@ -14,5 +14,153 @@ pub enum SudoMsg {
         fee_address: Option<String>,
         market_makers: Option<Vec<String>>,
         oracles: Option<[Layer1Asset; 2]>,
+        premium_bounds: Option<PremiumBounds>,
+        /// Can only be changed while no orders are open
+        decimal_delta: Option<DecimalDelta>,
     },
+    /// Allows or disallows `address` to place orders on behalf of other accounts with [super::ExecuteMsg::Callback]
+    SetTrustedCaller { address: String, trusted: bool },
+    /// Imports resting orders from a legacy Kujira FIN market. Orders already imported are skipped.
+    /// The contract must already hold the remaining offer and unclaimed filled amounts of `orders`
+    ImportLegacyOrders { orders: Vec<LegacyOrder> },
+    /// Sets the ghost-vault whose receipt token `receipt` is one of the pair's denoms, so that
+    /// swaps and orders can be paid in its `underlying`. `None` removes the adapter
+    SetWrapAdapter {
+        vault: Option<String>,
+        underlying: String,
+        receipt: String,
+    },
+    /// Sets the bounty paid to callers of [super::ExecuteMsg::Sweep]. `None` removes it,
+    /// and sends any fees already reserved to the fee address
+    SetSweepBounty { bounty: Option<SweepBounty> },
+    /// Sets the recurring windows of a trade-at-settlement session. `None` restores continuous trading
+    SetSession { session: Option<Session> },
+    /// Sets how oracle priced orders are treated while the oracle price is unavailable
+    SetOracleFallback { fallback: OracleFallback },
+    /// Converts fees collected in the base asset into the quote asset before they're sent to the
+    /// fee address. `None` sends fees in the asset they were collected in
+    SetFeeConversion { conversion: Option<FeeConversion> },
+    /// Sends fees in the base and quote assets to their own addresses in place of the fee address,
+    /// e.g. base fees to a buyback contract and quote fees to a treasury. `None` uses the fee address
+    SetFeeAddresses {
+        base: Option<String>,
+        quote: Option<String>,
+    },
+    /// Accepts `denom` in place of the quote asset to fund orders. It is swapped into the quote asset on
+    /// the fin `pair` of the two, for no less than its `oracle` value less `max_slippage`, and the orders
+    /// are placed with the quote asset returned. `denom` must have the decimals of the quote asset, and
+    /// the pair must have oracles. `None` removes it
+    SetAuxQuote {
+        denom: String,
+        pair: Option<String>,
+        oracle: Layer1Asset,
+        max_slippage: Decimal,
+    },
+    /// Bounds what each market maker can be committed to pay in one pass over the book. A market maker
+    /// whose quotes exceed it is dropped for the rest of the execution. `None` removes the bound
+    SetMarketMakerLimit { limit: Option<MarketMakerLimit> },
+    /// Treats an existing order within `epsilon` of the target amount of an order request as at its
+    /// target, so that dust left by fills doesn't churn retractions and increases of a unit. A target
+    /// of zero always retracts the order
+    SetTargetEpsilon { epsilon: Uint128 },
+    /// Auctions market swaps ([super::SwapRequest::Yolo] and [super::SwapRequest::Min]) offering at
+    /// least the auction's `min_offer` to its bidders, before they reach the book. The best bid wins if
+    /// it improves on the book return by `min_improvement`, and the swap is filled by the bidder within
+    /// the same execution. A swap without a winning bid, or whose bidder fails to fill it, is matched
+    /// against the book as usual. `None` sends all swaps to the book
+    SetOrderFlowAuction {
+        auction: Option<super::OrderFlowAuction>,
+    },
+    /// Sends the balance of `denom` held above the contract's liabilities to `to`, recovering funds
+    /// sent to the contract by mistake. Liabilities are the offers of resting orders, fills not yet
+    /// withdrawn, offers escrowed by TWAPs and the sweep bounty reserve
+    Rescue { denom: String, to: String },
+}
+
+/// Fees collected in the base asset are sold into the book at the end of each execution, so that
+/// revenue is denominated in the quote asset
+#[cw_serde]
+pub struct FeeConversion {
+    /// How far below the best bid the sale can go. Fees that can't be sold within it are sent
+    /// in the base asset
+    pub max_slippage: Decimal,
+}
+
+/// The treatment of [super::Price::Oracle] and [super::Price::Decay] orders while the oracle price
+/// can't be read
+#[cw_serde]
+#[derive(Default)]
+pub enum OracleFallback {
+    /// Oracle priced orders are excluded from swaps, and can't be placed or increased, until the
+    /// oracle recovers. They can still be retracted and withdrawn
+    #[default]
+    Freeze,
+    /// Oracle priced orders keep trading at the last price observed, for up to `max_age` seconds
+    /// after it was observed. They're frozen beyond that
+    LastPrice { max_age: u64 },
+}
+
+/// Recurring windows during which swaps are suspended and orders only rest on the book. Once a window
+/// closes, crossing orders are matched at the oracle price with [super::ExecuteMsg::Settle], and
+/// continuous trading resumes
+#[cw_serde]
+pub struct Session {
+    /// The start of the first window
+    pub start: Timestamp,
+    /// Seconds between the starts of consecutive windows
+    pub interval: u64,
+    /// Seconds that each window lasts. Must be less than `interval`
+    pub duration: u64,
+}
+
+/// The bounty paid for sweeping orphaned pool storage
+#[cw_serde]
+pub struct SweepBounty {
+    /// The share of swap fees reserved to pay bounties, instead of being sent to the fee address
+    pub share: Decimal,
+    /// The share of the reserve paid per entry removed by a sweep
+    pub rate: Decimal,
+}
+
+/// A ghost-vault that wraps `underlying` into `receipt`, one of the pair's denoms
+#[cw_serde]
+pub struct WrapAdapter {
+    pub vault: Addr,
+    pub underlying: String,
+    pub receipt: String,
+}
+
+/// A denom accepted in place of the quote asset to fund orders. See [SudoMsg::SetAuxQuote]
+#[cw_serde]
+pub struct AuxQuote {
+    pub denom: String,
+    /// The fin pair of `denom` and the quote asset
+    pub pair: Addr,
+    pub oracle: Layer1Asset,
+    /// How far below the oracle value of `denom` the swap into the quote asset can return
+    pub max_slippage: Decimal,
+}
+
+/// The commitment bound of each market maker. See [SudoMsg::SetMarketMakerLimit]
+#[cw_serde]
+pub struct MarketMakerLimit {
+    /// The most USD value, at the oracle price, that a market maker can be committed to pay. It is
+    /// further bound by the market maker's balance of the asset it pays. Requires oracles
+    pub max_value: Decimal,
+}
+
+/// An order in the legacy Kujira FIN `OrderResponse` format
+#[cw_serde]
+pub struct LegacyOrder {
+    /// The index of the order in the legacy contract
+    pub idx: Uint128,
+    pub owner: String,
+    pub quote_price: Decimal,
+    pub offer_denom: String,
+    pub original_offer_amount: Uint128,
+    /// The remaining offer amount, resting on the book
+    pub offer_amount: Uint128,
+    /// The amount of the ask denom filled and not yet withdrawn
+    pub filled_amount: Uint128,
+    pub created_at: Timestamp,
 }
@@ -2,6 +2,9 @@ use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Decimal, Fraction, Uint128};
 use thiserror::Error;
 
+use super::DecimalDelta;
+
+/// The number of significant figures of a price. A zero tick is free, and any Decimal price is valid
 #[cw_serde]
 pub struct Tick(u8);
 
@@ -10,8 +13,18 @@ impl Tick {
         Self(size)
     }
 
+    /// A tick that doesn't truncate prices, grouping the book by exact Decimal price
+    pub fn free() -> Self {
+        Self(0)
+    }
+
+    pub fn is_free(&self) -> bool {
+        self.0 == 0
+    }
+
     pub fn validate(&self) -> Result<(), TickError> {
-        if self.0 == 0 {
+        // A u128 numerator has at most 39 digits
+        if self.0 > 39 {
             return Err(TickError::Invalid(self.0));
         }
         Ok(())
@@ -41,6 +54,23 @@ impl Tick {
         })
     }
 
+    /// Validates a price in human units, ensuring it also converts to an atomic rate without loss of precision
+    pub fn validate_price_scaled(
+        &self,
+        v: &Decimal,
+        delta: &DecimalDelta,
+    ) -> Result<(), TickError> {
+        self.validate_price(v)?;
+        let atomic = delta.to_atomic(*v);
+        if atomic.is_zero() || delta.to_human(atomic) != *v {
+            return Err(TickError::InvalidPrice {
+                price: *v,
+                tick: self.0,
+            });
+        }
+        Ok(())
+    }
+
     pub fn truncate_floor(&self, v: &Decimal) -> Decimal {
         self.do_truncate(v, |x, y| x.mul_floor(y))
     }
@@ -55,6 +85,9 @@ impl Tick {
     {
         let int = v.numerator();
         let len = int.to_string().as_str().bytes().len() as u32;
+        if self.is_free() || len <= self.0 as u32 {
+            return *v;
+        }
         let decimals: u32 = len - self.0 as u32;
         let pow = Uint128::from(10u128).pow(decimals);
         let truncated = fn_trunc(Uint128::one(), Decimal::from_ratio(int, pow));
@@ -108,4 +141,39 @@ mod tests {
         tick.validate_price(&Decimal::from_str("0.00012").unwrap())
             .unwrap();
     }
+
+    #[test]
+    fn free() {
+        let tick = Tick::free();
+        tick.validate().unwrap();
+        let price = Decimal::from_str("0.000000000123456789").unwrap();
+        tick.validate_price(&price).unwrap();
+        assert_eq!(tick.truncate_floor(&price), price);
+        assert_eq!(tick.truncate_ceil(&price), price);
+        tick.validate_price(&Decimal::from_str("123456.123456789").unwrap())
+            .unwrap();
+        tick.validate_price(&Decimal::zero()).unwrap_err();
+
+        Tick::new(40).validate().unwrap_err();
+    }
+
+    #[test]
+    fn scaled() {
+        let tick = Tick::new(2u8);
+        // 8 decimal base, 6 decimal quote
+        let delta = DecimalDelta(-2);
+        let price = Decimal::from_str("91000").unwrap();
+        assert_eq!(delta.to_atomic(price), Decimal::from_str("910").unwrap());
+        assert_eq!(delta.to_human(Decimal::from_str("910").unwrap()), price);
+        tick.validate_price_scaled(&price, &delta).unwrap();
+
+        // Lost beyond the 18 decimals of the atomic rate
+        tick.validate_price_scaled(&Decimal::from_str("0.000000000000000012").unwrap(), &delta)
+            .unwrap_err();
+        tick.validate_price_scaled(
+            &Decimal::from_str("0.000000000000000012").unwrap(),
+            &DecimalDelta(2),
+        )
+        .unwrap();
+    }
 }
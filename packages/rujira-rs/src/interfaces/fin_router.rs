@@ -0,0 +1,57 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::Coin;
+
+use crate::{fin::OrderTarget, CallbackData};
+
+#[cw_serde]
+pub struct InstantiateMsg {}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Places the orders of each pair atomically, each with its share of the funds sent. The orders
+    /// are owned by the sender, and each pair pays its withdrawals to the sender. Once the last pair
+    /// has placed its orders, `callback` is sent to the sender once, with the [OrderResponse] of the
+    /// batch and the funds not allocated to a pair. Without a callback, those funds are refunded.
+    ///
+    /// The router must be a trusted caller of each pair, set with [crate::fin::SudoMsg::SetTrustedCaller]
+    Order {
+        pairs: Vec<PairOrders>,
+        callback: Option<CallbackData>,
+    },
+}
+
+/// The orders placed on a single FIN pair
+#[cw_serde]
+pub struct PairOrders {
+    /// The address of the FIN pair
+    pub fin: String,
+    pub orders: Vec<OrderTarget>,
+    /// The share of the funds sent allocated to the orders of this pair
+    pub funds: Vec<Coin>,
+}
+
+/// The orders placed on each pair by [ExecuteMsg::Order], and the funds refunded
+#[cw_serde]
+pub struct OrderResponse {
+    pub pairs: Vec<PairResponse>,
+    pub refund: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct PairResponse {
+    pub fin: String,
+    /// The number of orders placed
+    pub orders: usize,
+    pub funds: Vec<Coin>,
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    /// Simulates [ExecuteMsg::Order] sent with `funds`, validating the pairs and their allocation
+    #[returns(OrderResponse)]
+    Simulate {
+        pairs: Vec<PairOrders>,
+        funds: Vec<Coin>,
+    },
+}
@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{coin, Coin, Decimal, Deps};
+use cosmwasm_std::{coin, Addr, Coin, Decimal, Deps};
 use cw_utils::NativeBalance;
 use std::ops::{Add, Mul};
 use std::{collections::BTreeMap, fmt::Display};
@@ -10,6 +10,13 @@ use crate::{OracleError, OracleValue, SecuredAsset, SecuredAssetError};
 #[cw_serde]
 pub enum Collateral {
     Coin(Coin),
+    /// Remaining offer and unclaimed proceeds of open orders on a fin pair,
+    /// discounted by `haircut` on top of the collateral ratio of each denom
+    FinOrders {
+        contract: Addr,
+        haircut: Decimal,
+        balance: Vec<Coin>,
+    },
 }
 
 impl Collateral {
@@ -18,19 +25,29 @@ impl Collateral {
         deps: Deps,
         ratios: &BTreeMap<String, Decimal>,
     ) -> Result<Decimal, CollateralError> {
-        self.balance()
+        let value = self
+            .balance()
             .into_vec()
             .iter()
             .try_fold(Decimal::zero(), |agg, v| {
-                Ok(v.value_usd(deps.querier)?
-                    .mul(ratios.get(&v.denom).copied().unwrap_or_default())
-                    .add(agg))
-            })
+                Ok::<Decimal, CollateralError>(
+                    v.value_usd(deps.querier)?
+                        .mul(ratios.get(&v.denom).copied().unwrap_or_default())
+                        .add(agg),
+                )
+            })?;
+        match self {
+            Collateral::Coin(_) => Ok(value),
+            Collateral::FinOrders { haircut, .. } => {
+                Ok(value.mul(Decimal::one().checked_sub(*haircut).unwrap_or_default()))
+            }
+        }
     }
 
     pub fn balance(&self) -> NativeBalance {
         match self {
             Collateral::Coin(coin) => NativeBalance(vec![coin.clone()]),
+            Collateral::FinOrders { balance, .. } => NativeBalance(balance.clone()),
         }
     }
 }
@@ -39,6 +56,9 @@ impl OracleValue for Collateral {
     fn value_usd(&self, q: cosmwasm_std::QuerierWrapper) -> Result<Decimal, OracleError> {
         match self {
             Collateral::Coin(coin) => Ok(coin.value_usd(q)?),
+            Collateral::FinOrders { balance, .. } => {
+                Ok(NativeBalance(balance.clone()).value_usd(q)?)
+            }
         }
     }
 }
@@ -47,6 +67,9 @@ impl Display for Collateral {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Collateral::Coin(coin) => write!(f, "coin:{}", coin),
+            Collateral::FinOrders {
+                contract, balance, ..
+            } => write!(f, "fin:{}:{}", contract, NativeBalance(balance.clone())),
         }
     }
 }
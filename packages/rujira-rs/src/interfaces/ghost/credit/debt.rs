@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{coins, Coin, Decimal};
+use cosmwasm_std::{coins, Coin, Decimal, Uint128};
 use cw_utils::NativeBalance;
 use thiserror::Error;
 
@@ -19,6 +19,17 @@ impl Debt {
     pub fn can_accept(&self, coin: &Coin) -> bool {
         coin.denom == self.0.borrower.denom && coin.amount.le(&self.0.current)
     }
+
+    pub fn denom(&self) -> &str {
+        &self.0.borrower.denom
+    }
+
+    /// The debt that remains once `amount` of it has been repaid
+    pub fn repaid(&self, amount: Uint128) -> Self {
+        let mut debt = self.0.clone();
+        debt.current = debt.current.saturating_sub(amount);
+        Self(debt)
+    }
 }
 
 impl OracleValue for Debt {
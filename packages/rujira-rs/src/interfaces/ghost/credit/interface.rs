@@ -2,10 +2,13 @@ use std::collections::BTreeMap;
 
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{
-    coin, to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, StdResult, Uint128, WasmMsg,
+    coin, to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, StdResult, Timestamp, Uint128,
+    WasmMsg,
 };
 use cw_utils::NativeBalance;
 
+use crate::{CallbackMsg, PageRequest, PageResponse};
+
 #[cw_serde]
 pub struct InstantiateMsg {
     /// The Code ID for rujira-account
@@ -33,14 +36,40 @@ pub enum ExecuteMsg {
         label: String,
         /// Tag to allow filtering of accounts when queried
         tag: String,
+        /// Msgs executed on the Account once it is instantiated, after any funds attached
+        /// have been sent to it. The Account is checked as with [ExecuteMsg::Account]
+        #[serde(default)]
+        init_msgs: Vec<AccountMsg>,
     },
 
     /// Executes msgs on the Account on behalf of the owner
     /// The account must have collateralizaion ratio < 1 after the message has been executed, to succeed
     Account { addr: String, msgs: Vec<AccountMsg> },
 
-    /// NOOP function that checks position health against adjustment_threshold
-    CheckAccount { addr: String },
+    /// NOOP function that checks position health against adjustment_threshold.
+    /// Records the Account's valuation as below the liquidation threshold.
+    /// `min_debt` also checks the debt against [ConfigResponse::min_debt_value], after a borrow or repay
+    CheckAccount {
+        addr: String,
+        #[serde(default)]
+        min_debt: bool,
+    },
+
+    /// Permissionless. Records the current valuation of the Account, queryable with [QueryMsg::Valuation].
+    /// When the Account has reached the liquidation threshold since its last valuation, the caller is paid
+    /// the [PokeReward], so that Accounts drifting towards liquidation without being touched are surfaced
+    Poke { addr: String },
+
+    /// Permissionless. Has the Account execute the claim adapter registered with
+    /// [SudoMsg::SetClaimAdapter] for each of `denoms`, so that rewards accruing to its
+    /// collateral are claimed back into the Account
+    Compound { addr: String, denoms: Vec<String> },
+
+    /// Permissionless. Makes the repayment of the Account's [RepaySchedule] once it is due, from the
+    /// Account's balance of its denom. The caller is paid [SudoMsg::SetRepayCrankFee] of the repayment,
+    /// drawn from the Account on top of it, and the Account must be left safe as after an owner's change.
+    /// The next repayment is due one interval later
+    CrankRepay { addr: String },
 
     /// Liquidate the credit account
     /// Can only be called if the account is above a LTV of 1
@@ -61,10 +90,54 @@ pub enum ExecuteMsg {
     DoLiquidate {
         addr: String,
         /// Vec of (msg, is_preference)
-        /// When is_preference is set, errors will be ignored, logged and the next message in the queue will be processed
+        /// When is_preference is set, errors will be ignored, logged and the next message in the queue will be processed.
+        /// Errors of [LiquidateMsg::Queue] always fail the liquidation
         queue: Vec<(LiquidateMsg, bool)>,
         /// Arbitrary payload to pass through from initial account load to be delivered to CheckLiquidate
         payload: Binary,
+        /// A flash loan taken for the liquidation, held by the Account until it is repaid.
+        /// It is repaid once the liquidator's messages have executed, and the Account isn't checked until then
+        #[serde(default)]
+        flash: Option<Coin>,
+    },
+
+    /// Internal entrypoint used to process an AccountMsg::FlashClose route in sequence.
+    /// Once the queue is empty, all outstanding debts are repaid from the Account balance
+    /// and the remainder is returned to the owner. Fails if any debt can't be repaid in full
+    DoFlashClose {
+        addr: String,
+        queue: Vec<LiquidateMsg>,
+    },
+
+    /// Internal entrypoint used to complete an AccountMsg::Repay made with vault receipt tokens.
+    /// Delivered by the vault with the underlying withdrawn, which repays the Account's debt.
+    /// Any excess is returned to the Account
+    DoRepay { addr: String },
+
+    /// Receives a flash loan from a ghost-vault, and starts the liquidation it was taken for
+    Callback(CallbackMsg),
+
+    /// Permissionless. Executes the Account's own liquidation preference messages, without liquidation
+    /// fees, once its LTV has reached [SudoMsg::SetDeleverageThreshold] but not the liquidation threshold.
+    /// The Account must have opted in with [AccountMsg::SetDeleverage]. Execution halts once the LTV
+    /// is back below the deleverage threshold, and fails if the preferences don't get it there
+    Deleverage { addr: String },
+
+    /// Internal entrypoint used to process the preference messages of an [ExecuteMsg::Deleverage] in sequence.
+    /// Errors in [LiquidateMsg::Execute] are ignored, as in a liquidation
+    DoDeleverage {
+        addr: String,
+        queue: Vec<LiquidateMsg>,
+        /// The Account at the start of the deleverage, checked against the liquidation max slip
+        payload: Binary,
+    },
+
+    /// Saves a named liquidation preference template under the sender. Accounts owned by the sender
+    /// follow it with [AccountMsg::SetPreferenceTemplate], and updates apply to all of them at once.
+    /// `None` removes the template, returning its Accounts to their own preferences
+    SetPreferenceTemplate {
+        name: String,
+        template: Option<PreferenceTemplate>,
     },
 }
 
@@ -98,6 +171,33 @@ pub enum AccountMsg {
         denom: String,
         after: Option<String>,
     },
+    /// Follows the owner's named template from [ExecuteMsg::SetPreferenceTemplate] in place of the
+    /// Account's own preferences, which are kept for when it is unset with `None`
+    SetPreferenceTemplate(Option<String>),
+    /// Exempts the Account from the allowlist of [SudoMsg::SetExecuteAllowlist], letting
+    /// [AccountMsg::Execute] call any contract. Collateral moved into contracts that aren't
+    /// valued by the credit contract no longer counts towards the LTV
+    SetExecuteUnrestricted(bool),
+    /// Allows anyone to execute the Account's liquidation preferences with [ExecuteMsg::Deleverage]
+    /// once it reaches the deleverage threshold, as a stop-loss ahead of liquidation
+    SetDeleverage(bool),
+    /// Opts the Account in to a recurring repayment of its debt with [ExecuteMsg::CrankRepay], so
+    /// that it amortizes without the owner running a bot. `None` removes the schedule
+    SetRepaySchedule(Option<RepaySchedule>),
+    /// Caps the outstanding debt of `denom` that [AccountMsg::Borrow] can take the Account to, as a
+    /// hard stop below the protocol limits, e.g. for an automated strategy. Interest can accrue above
+    /// the cap. `None` removes it
+    SetBorrowCap {
+        denom: String,
+        cap: Option<Uint128>,
+    },
+    /// Closes the Account position in full: executes the `routes` to exchange collateral
+    /// for debt tokens, repays all debts and sends the remaining balance to the owner.
+    /// Intermediate steps are not checked for safety, the Account must be fully solvent at the end.
+    /// No liquidation fees are charged, and `LiquidateMsg::Repay` repays up to the outstanding debt
+    FlashClose {
+        routes: Vec<LiquidateMsg>,
+    },
 }
 
 #[cw_serde]
@@ -109,10 +209,36 @@ pub enum LiquidateMsg {
         msg: Binary,
         funds: Vec<Coin>,
     },
+    /// Flash borrow `amount` of `denom` from its ghost-vault into the Account, so that debt can be
+    /// repaid before collateral is exchanged. Only valid as the first message of [ExecuteMsg::Liquidate].
+    /// The loan is taken from the Account balance once the remaining liquidator messages have executed
+    FlashBorrow { denom: String, amount: Uint128 },
+    /// Sells `amount` of collateral into a rujira-orca liquidation queue. The collateral is moved from the
+    /// Account to this contract, which must be a market of the queue, and the bid denom is returned to the Account.
+    /// A failing sale fails the liquidation, including as a preference message, so no collateral is stranded
+    Queue { contract_addr: String, amount: Coin },
+    /// Repays the debt with the largest USD value that the Account holds a balance of, as
+    /// [LiquidateMsg::Repay]. Debts of equal value are ordered by denom
+    RepayBest {},
+}
+
+impl LiquidateMsg {
+    pub fn is_flash_borrow(&self) -> bool {
+        matches!(self, Self::FlashBorrow { .. })
+    }
+}
+
+impl AccountMsg {
+    /// Whether the msg changes the Account's debt, which is then held to [ConfigResponse::min_debt_value]
+    pub fn changes_debt(&self) -> bool {
+        matches!(self, Self::Borrow(_) | Self::Repay(_))
+    }
 }
 
 #[cw_serde]
 pub enum SudoMsg {
+    /// Registers every pool of a ghost vault as the source of borrows for its denom.
+    /// Each pool denom must be a configured collateral
     SetVault {
         address: String,
     },
@@ -122,7 +248,148 @@ pub enum SudoMsg {
         collateralization_ratio: Decimal,
     },
 
+    /// Counts open orders on a fin pair towards account collateral, valued at the
+    /// collateral ratio of each denom less `haircut`. `None` removes the pair
+    SetFinCollateral {
+        contract: String,
+        haircut: Option<Decimal>,
+    },
+
     UpdateConfig(ConfigUpdate),
+
+    /// Registers a contract to receive a [MonitorMsg] after every Account update and liquidation step.
+    /// Errors in the monitor are ignored. `None` removes the monitor
+    SetMonitor {
+        contract: Option<String>,
+    },
+
+    /// Whitelists the message an Account executes to claim the rewards of a collateral `denom`
+    /// on [ExecuteMsg::Compound]. `None` removes the adapter
+    SetClaimAdapter {
+        denom: String,
+        adapter: Option<ClaimAdapter>,
+    },
+
+    /// Sends `share` of the liquidation fee of every liquidation repay to a backstop contract,
+    /// accumulating an insurance fund. The rest is sent to `fee_address`. `None` removes the backstop
+    SetBackstop {
+        contract: Option<String>,
+        share: Decimal,
+    },
+
+    /// Offsets debt in `debt` against collateral in `collateral` held by the same Account, as their
+    /// prices move together. `offset` of the matched value is removed from both the debt and the
+    /// adjusted collateral when computing the LTV. `None` removes the pair
+    SetCorrelation {
+        collateral: String,
+        debt: String,
+        offset: Option<Decimal>,
+    },
+
+    /// Restricts the contracts that [AccountMsg::Execute] can call to `contracts`, unless the Account
+    /// has opted out with [AccountMsg::SetExecuteUnrestricted]. `None` lifts the restriction
+    SetExecuteAllowlist {
+        contracts: Option<Vec<String>>,
+    },
+
+    /// The LTV from which Accounts opted in with [AccountMsg::SetDeleverage] can be deleveraged with
+    /// [ExecuteMsg::Deleverage]. Must be between the adjustment and liquidation thresholds.
+    /// `None` disables deleveraging
+    SetDeleverageThreshold {
+        threshold: Option<Decimal>,
+    },
+
+    /// Funds a reward for callers of [ExecuteMsg::Poke] from liquidation fees. `None` stops funding and
+    /// paying it, leaving any fees already retained in the contract
+    SetPokeReward {
+        reward: Option<PokeReward>,
+    },
+
+    /// Sets the share of each scheduled repayment paid to the caller of [ExecuteMsg::CrankRepay]
+    SetRepayCrankFee {
+        fee: Decimal,
+    },
+}
+
+/// The reward paid for an [ExecuteMsg::Poke] that finds an Account newly at the liquidation threshold
+#[cw_serde]
+pub struct PokeReward {
+    /// The share of the liquidation fee otherwise sent to `fee_address`, retained by the contract to pay rewards
+    pub share: Decimal,
+    /// The share of the retained fees of each denom paid per reward
+    pub rate: Decimal,
+}
+
+/// The valuation of an Account, as of its last [ExecuteMsg::Poke] or [ExecuteMsg::CheckAccount]
+#[cw_serde]
+pub struct Valuation {
+    pub ltv: Decimal,
+    /// Set when `ltv` was at or above the liquidation threshold
+    pub liquidatable: bool,
+    pub timestamp: Timestamp,
+}
+
+/// A recurring repayment of an Account's debt from its own balance. See [AccountMsg::SetRepaySchedule]
+#[cw_serde]
+pub struct RepaySchedule {
+    /// Repaid each period, capped at the Account's debt of the denom and its balance
+    pub amount: Coin,
+    /// The period between repayments, in seconds
+    pub interval: u64,
+}
+
+/// A [RepaySchedule] and the time its next repayment is due
+#[cw_serde]
+pub struct ScheduledRepay {
+    pub schedule: RepaySchedule,
+    pub due: Timestamp,
+}
+
+/// A collateral and a debt denom whose prices move together. See [SudoMsg::SetCorrelation]
+#[cw_serde]
+pub struct Correlation {
+    pub collateral: String,
+    pub debt: String,
+    pub offset: Decimal,
+}
+
+/// A fixed message, executed by an Account without funds, that claims rewards to the Account
+#[cw_serde]
+pub struct ClaimAdapter {
+    pub contract_addr: String,
+    pub msg: Binary,
+}
+
+/// Sent to the monitor contract registered with [SudoMsg::SetMonitor]
+#[cw_serde]
+pub enum MonitorMsg {
+    /// `ltv` is the adjusted LTV of the Account after `action`
+    CreditAccount {
+        account: String,
+        ltv: Decimal,
+        action: MonitorAction,
+    },
+}
+
+#[cw_serde]
+pub enum MonitorAction {
+    /// The owner has executed [ExecuteMsg::Account]
+    Account,
+    /// The Account is being liquidated, and the next step is about to execute
+    LiquidateStep,
+    /// The liquidation has completed
+    Liquidated,
+}
+
+impl MonitorMsg {
+    pub fn call(&self, address: &Addr) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: address.to_string(),
+            msg: to_json_binary(self)?,
+            funds: vec![],
+        }
+        .into())
+    }
 }
 
 #[cw_serde]
@@ -134,6 +401,9 @@ pub struct ConfigUpdate {
     pub liquidation_max_slip: Option<Decimal>,
     pub liquidation_threshold: Option<Decimal>,
     pub adjustment_threshold: Option<Decimal>,
+    pub liquidation_cooldown: Option<u64>,
+    pub min_debt_value: Option<Decimal>,
+    pub safety_buffer: Option<Decimal>,
 }
 
 #[cw_serde]
@@ -158,35 +428,89 @@ pub enum QueryMsg {
         tag: Option<String>,
     },
 
-    /// Pages through all accounts, 100 at a time
+    /// Pages through all accounts by Credit Account address. Upper limit of 100 per page
     #[returns(AccountsResponse)]
-    AllAccounts {
-        /// Address of the Credit Account
-        cursor: Option<String>,
-        /// Number of accounts to return
-        limit: Option<usize>,
-    },
+    AllAccounts { page: PageRequest<String> },
 
     /// Returns the predicted next account address for the given owner
     #[returns(Addr)]
     Predict { owner: String, salt: Binary },
+
+    /// Distance of an account from the adjustment and liquidation thresholds, and the
+    /// borrows and collateral sales that would move it to the adjustment threshold
+    #[returns(RiskReportResponse)]
+    RiskReport { addr: String },
+
+    /// Runs the checks of [ExecuteMsg::Liquidate] against the current state of an account without
+    /// executing it. Repays are simulated in full, as are swaps on fin pairs and sales into liquidation
+    /// queues. Any other [LiquidateMsg::Execute] is assumed to spend its funds and return nothing
+    #[returns(SimulateLiquidationResponse)]
+    SimulateLiquidation {
+        addr: String,
+        msgs: Vec<LiquidateMsg>,
+    },
+
+    /// Liquidation preference templates saved by the owner
+    #[returns(PreferenceTemplatesResponse)]
+    PreferenceTemplates { owner: String },
+
+    /// The last valuation recorded for an account, if any
+    #[returns(Option<Valuation>)]
+    Valuation { addr: String },
 }
 
 #[cw_serde]
 pub struct ConfigResponse {
     pub code_id: u64,
     pub collateral_ratios: BTreeMap<String, Decimal>,
+    pub fin_haircuts: BTreeMap<String, Decimal>,
     pub fee_liquidation: Decimal,
     pub fee_liquidator: Decimal,
     pub fee_address: Addr,
     pub liquidation_max_slip: Decimal,
     pub liquidation_threshold: Decimal,
     pub adjustment_threshold: Decimal,
+    pub liquidation_cooldown: u64,
+    /// See [SudoMsg::SetMonitor]
+    #[serde(default)]
+    pub monitor: Option<Addr>,
+    /// The minimum USD value of the debt of an Account that has any
+    #[serde(default)]
+    pub min_debt_value: Decimal,
+    /// The margin below `adjustment_threshold` that Account updates must keep
+    #[serde(default)]
+    pub safety_buffer: Decimal,
+    /// See [SudoMsg::SetClaimAdapter]
+    #[serde(default)]
+    pub claim_adapters: BTreeMap<String, ClaimAdapter>,
+    /// See [SudoMsg::SetBackstop]
+    #[serde(default)]
+    pub backstop: Option<Addr>,
+    /// The share of the liquidation fee sent to `backstop`
+    #[serde(default)]
+    pub fee_backstop: Decimal,
+    /// See [SudoMsg::SetCorrelation]
+    #[serde(default)]
+    pub correlations: Vec<Correlation>,
+    /// See [SudoMsg::SetExecuteAllowlist]
+    #[serde(default)]
+    pub execute_allowlist: Option<Vec<Addr>>,
+    /// See [SudoMsg::SetDeleverageThreshold]
+    #[serde(default)]
+    pub deleverage_threshold: Option<Decimal>,
+    /// See [SudoMsg::SetPokeReward]
+    #[serde(default)]
+    pub poke_reward: Option<PokeReward>,
+    /// See [SudoMsg::SetRepayCrankFee]
+    #[serde(default)]
+    pub repay_crank_fee: Decimal,
 }
 
 #[cw_serde]
 pub struct AccountsResponse {
     pub accounts: Vec<AccountResponse>,
+    /// Cursor for AllAccounts. Accounts by owner are not paginated
+    pub page: PageResponse<String>,
 }
 
 #[cw_serde]
@@ -197,7 +521,94 @@ pub struct AccountResponse {
     pub collaterals: Vec<CollateralResponse>,
     pub debts: Vec<DebtResponse>,
     pub ltv: Decimal,
+    /// The preferences enforced on the Account, taken from `preference_template` when it follows one
     pub liquidation_preferences: LiquidationPreferences,
+    #[serde(default)]
+    pub preference_template: Option<String>,
+    /// See [AccountMsg::SetExecuteUnrestricted]
+    #[serde(default)]
+    pub execute_unrestricted: bool,
+    /// See [AccountMsg::SetDeleverage]
+    #[serde(default)]
+    pub deleverage: bool,
+    /// See [AccountMsg::SetRepaySchedule]
+    #[serde(default)]
+    pub repay_schedule: Option<ScheduledRepay>,
+    /// See [AccountMsg::SetBorrowCap]
+    #[serde(default)]
+    pub borrow_caps: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct PreferenceTemplatesResponse {
+    pub templates: Vec<PreferenceTemplateResponse>,
+}
+
+#[cw_serde]
+pub struct PreferenceTemplateResponse {
+    pub name: String,
+    pub preferences: LiquidationPreferences,
+}
+
+#[cw_serde]
+pub struct RiskReportResponse {
+    pub account: Addr,
+    /// USD value of the account's collateral, adjusted by collateral ratios, net of the value
+    /// offset by correlated debt
+    pub collateral: Decimal,
+    /// USD value of the account's debt, net of the value offset by correlated collateral
+    pub debt: Decimal,
+    pub ltv: Decimal,
+    pub adjustment: ThresholdDistance,
+    pub liquidation: ThresholdDistance,
+    /// The maximum amount of each borrowable denom that keeps the account below the
+    /// adjustment threshold, accounting for the borrowed funds held as collateral
+    pub max_borrow: Vec<Coin>,
+    /// For each collateral held as a coin, the amount that would need to be sold alone
+    /// and repaid as debt to return to the adjustment threshold, capped at the balance
+    /// held. Ignores slippage and fees. Empty when below the threshold
+    pub shortfall: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct SimulateLiquidationResponse {
+    /// The messages executed, the account's liquidation preferences first, in order
+    pub steps: Vec<LiquidationStepResponse>,
+    /// The adjusted LTV of the account once the steps have executed
+    pub ltv: Decimal,
+    /// The error that the liquidation would fail with. `None` if it would succeed
+    pub error: Option<String>,
+}
+
+#[cw_serde]
+pub struct LiquidationStepResponse {
+    /// The message executed, with [LiquidateMsg::RepayBest] resolved to the debt repaid
+    pub msg: LiquidateMsg,
+    pub preference: bool,
+    /// The debt repaid and fees collected by a repay
+    pub repay: Option<LiquidationRepayResponse>,
+    /// False for an [LiquidateMsg::Execute] that can't be simulated
+    pub simulated: bool,
+    /// The error of the step. Errors of preference messages executed on other contracts are ignored
+    pub error: Option<String>,
+}
+
+#[cw_serde]
+pub struct LiquidationRepayResponse {
+    pub repaid: Coin,
+    pub fee_liquidation: Coin,
+    pub fee_liquidator: Coin,
+}
+
+#[cw_serde]
+pub struct ThresholdDistance {
+    pub threshold: Decimal,
+    /// The LTV increase before the threshold is reached. Zero once reached
+    pub ltv: Decimal,
+    /// The additional debt, in USD, before the threshold is reached
+    pub debt_usd: Decimal,
+    /// The fall in adjusted collateral value, in USD, before the threshold is reached
+    pub collateral_usd: Decimal,
 }
 
 #[cw_serde]
@@ -264,6 +675,15 @@ pub struct LiquidationPreferences {
     pub order: LiquidationPreferenceOrder,
 }
 
+/// Liquidation preferences shared by the Accounts of an owner. See [ExecuteMsg::SetPreferenceTemplate]
+#[cw_serde]
+pub struct PreferenceTemplate {
+    /// As [LiquidationPreferences::messages]
+    pub messages: Vec<LiquidateMsg>,
+    /// (denom, after) constraints, as [AccountMsg::SetPreferenceOrder]
+    pub order: Vec<(String, String)>,
+}
+
 #[cw_serde]
 pub struct LiquidationPreferenceOrder {
     map: BTreeMap<String, String>,
@@ -1,2 +1,3 @@
 pub mod credit;
 pub mod vault;
+pub mod vault_registry;
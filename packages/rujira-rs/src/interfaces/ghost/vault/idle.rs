@@ -0,0 +1,66 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{
+    to_json_binary, Addr, Coin, CosmosMsg, Decimal, QuerierWrapper, StdResult, Uint128, WasmMsg,
+};
+
+/// A whitelisted contract that a share of a pool's unborrowed deposits is parked in to earn yield.
+/// See [super::SudoMsg::SetIdleStrategy]
+#[cw_serde]
+pub struct IdleStrategy {
+    pub contract: Addr,
+    /// The maximum share of unborrowed deposits deployed to the strategy
+    pub max_share: Decimal,
+}
+
+/// Execute interface that an idle strategy contract must implement
+#[cw_serde]
+pub enum IdleStrategyMsg {
+    /// Deposits the funds sent
+    Deposit {},
+    /// Returns `amount` to the caller. Must be paid within the same message
+    Withdraw { amount: Uint128 },
+}
+
+/// Query interface that an idle strategy contract must implement
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum IdleStrategyQueryMsg {
+    /// The current value of the deposits of `addr`, including yield
+    #[returns(IdleBalanceResponse)]
+    Balance { addr: String },
+}
+
+#[cw_serde]
+pub struct IdleBalanceResponse {
+    pub amount: Uint128,
+}
+
+impl IdleStrategy {
+    pub fn deposit_msg(&self, amount: Coin) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.contract.to_string(),
+            msg: to_json_binary(&IdleStrategyMsg::Deposit {})?,
+            funds: vec![amount],
+        }
+        .into())
+    }
+
+    pub fn withdraw_msg(&self, amount: Uint128) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.contract.to_string(),
+            msg: to_json_binary(&IdleStrategyMsg::Withdraw { amount })?,
+            funds: vec![],
+        }
+        .into())
+    }
+
+    pub fn balance(&self, q: QuerierWrapper, addr: &Addr) -> StdResult<Uint128> {
+        let res: IdleBalanceResponse = q.query_wasm_smart(
+            self.contract.to_string(),
+            &IdleStrategyQueryMsg::Balance {
+                addr: addr.to_string(),
+            },
+        )?;
+        Ok(res.amount)
+    }
+}
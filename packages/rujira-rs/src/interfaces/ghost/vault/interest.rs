@@ -1,7 +1,7 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{ensure, Decimal, StdError, StdResult};
 use std::{
-    cmp::min,
+    cmp::{max, min},
     ops::{Add, Div, Mul, Sub},
 };
 
@@ -14,6 +14,13 @@ pub struct Interest {
     pub step1: Decimal,
     // The additional rate at the full utilization, added on top of base rate and step1
     pub step2: Decimal,
+    // The minimum lend rate guaranteed to depositors. The debt rate is raised so that the lend rate
+    // meets it whenever anything is borrowed, up to `max_debt_rate`
+    #[serde(default)]
+    pub min_lend_rate: Option<Decimal>,
+    // The maximum rate charged to borrowers, applied after `min_lend_rate`
+    #[serde(default)]
+    pub max_debt_rate: Option<Decimal>,
 }
 
 impl Default for Interest {
@@ -23,6 +30,8 @@ impl Default for Interest {
             base_rate: Decimal::zero(),
             step1: Decimal::one(),
             step2: Decimal::from_ratio(2u128, 1u128),
+            min_lend_rate: None,
+            max_debt_rate: None,
         }
     }
 }
@@ -43,6 +52,14 @@ impl Interest {
             self.target_utilization.lt(&Decimal::one()),
             StdError::generic_err("target_utilization must be < 1".to_string())
         );
+
+        if let (Some(min_lend_rate), Some(max_debt_rate)) = (self.min_lend_rate, self.max_debt_rate)
+        {
+            ensure!(
+                min_lend_rate.le(&max_debt_rate),
+                StdError::generic_err("min_lend_rate must be <= max_debt_rate".to_string())
+            );
+        }
         Ok(())
     }
 
@@ -63,7 +80,19 @@ impl Interest {
             .unwrap_or_default()
             .mul(self.step2);
 
-        Ok(self.base_rate.add(part1).add(part2))
+        let mut rate = self.base_rate.add(part1).add(part2);
+
+        // The lend rate is the debt rate scaled by utilization
+        if let Some(floor) = self
+            .min_lend_rate
+            .and_then(|x| x.checked_div(utilization).ok())
+        {
+            rate = max(rate, floor);
+        }
+        if let Some(cap) = self.max_debt_rate {
+            rate = min(rate, cap);
+        }
+        Ok(rate)
     }
 }
 
@@ -71,6 +100,45 @@ impl Interest {
 mod test {
     use super::*;
 
+    #[test]
+    fn clamps() {
+        let i = Interest {
+            target_utilization: Decimal::from_ratio(8u128, 10u128),
+            base_rate: Decimal::zero(),
+            step1: Decimal::from_ratio(1u128, 10u128),
+            step2: Decimal::from_ratio(3u128, 1u128),
+            min_lend_rate: Some(Decimal::from_ratio(5u128, 100u128)),
+            max_debt_rate: Some(Decimal::one()),
+        };
+        i.validate().unwrap();
+
+        // Nothing borrowed, nothing to lend
+        assert_eq!(i.rate(Decimal::zero()).unwrap(), Decimal::zero());
+        // 10% utilization is raised to a 50% debt rate, for a 5% lend rate
+        assert_eq!(
+            i.rate(Decimal::from_ratio(1u128, 10u128)).unwrap(),
+            Decimal::from_ratio(50u128, 100u128)
+        );
+        // Above the floor, the curve applies
+        assert_eq!(
+            i.rate(Decimal::from_ratio(8u128, 10u128)).unwrap(),
+            Decimal::from_ratio(10u128, 100u128)
+        );
+        // The cap wins over the floor
+        assert_eq!(
+            i.rate(Decimal::from_ratio(1u128, 100u128)).unwrap(),
+            Decimal::one()
+        );
+        assert_eq!(i.rate(Decimal::one()).unwrap(), Decimal::one());
+
+        Interest {
+            min_lend_rate: Some(Decimal::from_ratio(2u128, 1u128)),
+            ..i
+        }
+        .validate()
+        .unwrap_err();
+    }
+
     #[test]
     fn interest_curves() {
         let i = Interest {
@@ -78,6 +146,8 @@ mod test {
             base_rate: Decimal::zero(),
             step1: Decimal::zero(),
             step2: Decimal::zero(),
+            min_lend_rate: None,
+            max_debt_rate: None,
         };
 
         assert_eq!(i.rate(Decimal::zero()).unwrap(), Decimal::zero());
@@ -88,6 +158,8 @@ mod test {
             base_rate: Decimal::from_ratio(1u128, 10u128),
             step1: Decimal::zero(),
             step2: Decimal::zero(),
+            min_lend_rate: None,
+            max_debt_rate: None,
         };
 
         assert_eq!(
@@ -104,6 +176,8 @@ mod test {
             base_rate: Decimal::from_ratio(1u128, 10u128),
             step1: Decimal::zero(),
             step2: Decimal::zero(),
+            min_lend_rate: None,
+            max_debt_rate: None,
         };
 
         assert_eq!(
@@ -120,6 +194,8 @@ mod test {
             base_rate: Decimal::from_ratio(1u128, 10u128),
             step1: Decimal::from_ratio(1u128, 10u128),
             step2: Decimal::from_ratio(3u128, 1u128),
+            min_lend_rate: None,
+            max_debt_rate: None,
         };
 
         assert_eq!(
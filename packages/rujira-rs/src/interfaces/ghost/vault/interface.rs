@@ -1,13 +1,14 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{
-    coin, to_json_binary, Addr, Coin, CosmosMsg, Decimal, QuerierWrapper, StdResult, Timestamp,
-    Uint128, WasmMsg,
+    coin, to_json_binary, Addr, Coin, CosmosMsg, Decimal, Int128, QuerierWrapper, StdResult,
+    Timestamp, Uint128, WasmMsg,
 };
 
-use crate::{CallbackData, OracleError, OracleValue, TokenMetadata};
+use crate::{CallbackData, OracleError, OracleValue, PageRequest, PageResponse, TokenMetadata};
 
-use super::interest::Interest;
+use super::{idle::IdleStrategy, interest::Interest};
 
+/// Creates the vault's first pool. Further pools are added with [SudoMsg::AddPool]
 #[cw_serde]
 pub struct InstantiateMsg {
     /// The denom string that can be deposited and lent
@@ -16,22 +17,65 @@ pub struct InstantiateMsg {
     pub receipt: TokenMetadata,
     pub fee: Decimal,
     pub fee_address: String,
+    /// The vault registry that each pool is registered with as it's created. Only read on
+    /// instantiate, see [SudoMsg::SetRegistry]
+    #[serde(default)]
+    pub registry: Option<String>,
 }
 
+/// Each denom is held in an isolated pool. Where a message carries funds, the pool is
+/// selected by the denom of the funds sent
 #[cw_serde]
 pub enum ExecuteMsg {
     /// Deposit the borrowable asset into the money market.
     Deposit { callback: Option<CallbackData> },
-    /// Withdraw the borrowable asset from the money market.
+    /// Withdraw the borrowable asset from the money market, by sending the pool's receipt token
     Withdraw { callback: Option<CallbackData> },
+    /// Lock a deposit for `duration` seconds at the fixed rate configured for that term.
+    /// No receipt token is issued, the deposit is tracked by its id
+    DepositTerm { duration: u64 },
+    /// Withdraw a term deposit. Before maturity the early exit fee is deducted from the principal
+    WithdrawTerm {
+        id: u64,
+        callback: Option<CallbackData>,
+    },
+    /// Deposit into the backstop of the pool. Backstop capital is not lent out, and absorbs
+    /// bad debt written off with [SudoMsg::WriteOff] before deposits, in exchange for a share of interest
+    BackstopDeposit {},
+    /// Start unbonding backstop shares. Unbonding shares continue to absorb losses and earn
+    /// interest until withdrawn
+    BackstopUnbond { denom: String, shares: Uint128 },
+    /// Withdraw backstop shares that have completed unbonding
+    BackstopWithdraw {
+        denom: String,
+        callback: Option<CallbackData>,
+    },
     /// Privileged Msgs for whitelisted contracts
     Market(MarketMsg),
+    /// Borrow against the limit of `borrower`, up to the allowance it has granted the caller with
+    /// [MarketMsg::SetAllowance]. The debt is owed by `borrower`, delegated to the caller
+    BorrowAllowance {
+        denom: String,
+        borrower: String,
+        amount: Uint128,
+        callback: Option<CallbackData>,
+    },
+    /// Repay debt drawn by the caller with [ExecuteMsg::BorrowAllowance]. Any excess is refunded
+    RepayAllowance { borrower: String },
+    /// Internal entrypoint dispatched after a flash loan callback. Fails if the loan is still outstanding
+    CheckFlashLoan { denom: String },
+    /// Permissionless. Distributes the interest accrued by every pool
+    Accrue {},
+    /// Permissionless. Settles the yield of the pool's idle strategy into the deposit pool, and moves
+    /// idle deposits to or from the strategy, see [SudoMsg::SetIdleStrategy]
+    Rebalance { denom: String },
 }
 
 #[cw_serde]
 pub enum MarketMsg {
     /// Borrow the borrowable asset from the money market. Only callable by whitelisted market contracts.
     Borrow {
+        denom: String,
         amount: Uint128,
         callback: Option<CallbackData>,
         /// optional delegate address for the debt obligation to be allocated to
@@ -42,40 +86,278 @@ pub enum MarketMsg {
         /// Optionally repay a delegate's debt obligation instead of the caller's
         delegate: Option<String>,
     },
+    /// Borrow `amount` for the duration of `callback`, which is executed on the caller with the funds.
+    /// The loan must be repaid in full with [MarketMsg::FlashRepay] before the callback completes.
+    /// No interest is charged, and the loan doesn't count towards the borrower limit.
+    /// Only callable by whitelisted market contracts.
+    FlashLoan {
+        denom: String,
+        amount: Uint128,
+        callback: CallbackData,
+    },
+    /// Repay the caller's outstanding flash loan. Any excess is refunded
+    FlashRepay {},
+    /// Moves `shares` of debt from one of the caller's delegates to another, without
+    /// changing the caller's total debt. Only callable by whitelisted market contracts.
+    TransferDebt {
+        denom: String,
+        from_delegate: String,
+        to_delegate: String,
+        shares: Uint128,
+    },
+    /// Allows `spender` to draw up to `amount` of the caller's limit with [ExecuteMsg::BorrowAllowance].
+    /// The allowance caps the spender's outstanding debt, including interest. `0` revokes it.
+    /// Only callable by whitelisted market contracts.
+    SetAllowance {
+        denom: String,
+        spender: String,
+        amount: Uint128,
+    },
 }
 
 #[cw_serde]
 pub enum SudoMsg {
-    SetBorrower { contract: String, limit: Uint128 },
-    SetInterest(Interest),
+    /// Adds an isolated pool for a new denom, with its own receipt token
+    AddPool(InstantiateMsg),
+    SetBorrower {
+        denom: String,
+        contract: String,
+        limit: Uint128,
+    },
+    /// Only available while the pool has no timelock. See [SudoMsg::ProposeConfig]
+    SetInterest { denom: String, interest: Interest },
+    /// Only available while the pool has no timelock. See [SudoMsg::ProposeConfig]
+    SetTerms {
+        denom: String,
+        terms: Vec<TermRate>,
+        early_exit_fee: Decimal,
+    },
+    /// Sets the borrower limit utilization levels at which an alert event is emitted on Borrow
+    SetUtilizationAlerts {
+        denom: String,
+        thresholds: Vec<Decimal>,
+    },
+    /// Sets the share of interest paid to the backstop, and the unbonding period in seconds
+    SetBackstop {
+        denom: String,
+        interest_share: Decimal,
+        unbonding: u64,
+    },
+    /// Sets a contract that is sent a [HealthCheckMsg] after each Borrow by `borrower`.
+    /// If the health check fails, the Borrow reverts. `None` removes the health check
+    SetHealthCheck {
+        denom: String,
+        borrower: String,
+        health_check: Option<String>,
+    },
+    /// Writes off the outstanding debt of a borrower, or of one of its delegates, that will not be repaid.
+    /// The loss is absorbed by the backstop first, and any remainder by depositors
+    WriteOff {
+        denom: String,
+        borrower: String,
+        delegate: Option<String>,
+    },
+    /// Sets the contract that up to `max_share` of the pool's unborrowed deposits are parked in.
+    /// Any funds in the previous strategy are recalled. `None` removes the strategy
+    SetIdleStrategy {
+        denom: String,
+        contract: Option<String>,
+        max_share: Decimal,
+    },
+    /// Queues a change to the fee and interest parameters of a pool, replacing any pending change.
+    /// It can be applied with [SudoMsg::ApplyConfig] once the pool's current timelock has elapsed
+    ProposeConfig { denom: String, change: ConfigChange },
+    /// Applies the pending change of a pool, settling interest at the previous parameters first
+    ApplyConfig { denom: String },
+    /// Discards the pending change of a pool
+    CancelConfig { denom: String },
+    /// Enables or disables the rejection of withdrawals from a pool in any block that it has had a
+    /// deposit in, deterring deposits that move the rates around a large borrow. Integrations that
+    /// deposit and withdraw in a single transaction, such as a fin wrap adapter, can't use a guarded pool
+    SetSameBlockGuard { denom: String, enabled: bool },
+    /// Sets the maximum share of a pool's deposits that any single borrower's debt can reach
+    /// with a Borrow. `None` removes the cap
+    SetMaxConcentration { denom: String, max: Option<Decimal> },
+    /// Sets the vault registry that new pools are registered with, and registers the existing pools
+    SetRegistry(String),
+}
+
+/// Fee, interest and term parameters changed with [SudoMsg::ProposeConfig]. `None` fields are unchanged
+#[cw_serde]
+#[derive(Default)]
+pub struct ConfigChange {
+    pub fee: Option<Decimal>,
+    pub fee_address: Option<String>,
+    pub interest: Option<Interest>,
+    /// See [SudoMsg::SetTerms]
+    pub terms: Option<Vec<TermRate>>,
+    pub early_exit_fee: Option<Decimal>,
+    /// Seconds between a proposal and when it can be applied
+    pub timelock: Option<u64>,
+}
+
+#[cw_serde]
+pub struct PendingConfigResponse {
+    pub change: ConfigChange,
+    pub executable_at: Timestamp,
+}
+
+/// Sent to a borrower's health check contract, after the borrowed funds have been sent
+#[cw_serde]
+pub enum HealthCheckMsg {
+    Borrow {
+        borrower: String,
+        delegate: Option<String>,
+        amount: Coin,
+    },
+}
+
+impl HealthCheckMsg {
+    pub fn call(&self, address: &Addr) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: address.to_string(),
+            msg: to_json_binary(self)?,
+            funds: vec![],
+        }
+        .into())
+    }
 }
 
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
+    /// The denoms of all pools in the vault
+    #[returns(PoolsResponse)]
+    Pools {},
+
     #[returns(ConfigResponse)]
-    Config {},
+    Config { denom: String },
 
     #[returns(StatusResponse)]
-    Status {},
+    Status { denom: String },
 
     #[returns(BorrowerResponse)]
-    Borrower { addr: String },
+    Borrower { denom: String, addr: String },
 
     #[returns(DelegateResponse)]
-    Delegate { borrower: String, addr: String },
+    Delegate {
+        denom: String,
+        borrower: String,
+        addr: String,
+    },
+
+    /// The allowance `borrower` has granted `spender`, see [MarketMsg::SetAllowance]
+    #[returns(AllowanceResponse)]
+    Allowance {
+        denom: String,
+        borrower: String,
+        spender: String,
+    },
 
+    /// Paginate borrowers of a pool by address. Upper limit of 100 per page
     #[returns(BorrowersResponse)]
     Borrowers {
+        denom: String,
+        page: PageRequest<String>,
+    },
+
+    /// Borrowers whose utilization of their limit is at or above `threshold`. Each page scans up to
+    /// 100 borrowers by address, so a page can hold fewer borrowers than its limit before the last
+    #[returns(BorrowersResponse)]
+    BorrowersAtRisk {
+        denom: String,
+        threshold: Decimal,
+        page: PageRequest<String>,
+    },
+
+    #[returns(TermsResponse)]
+    Terms { denom: String },
+
+    #[returns(TermDepositResponse)]
+    TermDeposit { id: u64 },
+
+    #[returns(TermDepositsResponse)]
+    TermDeposits {
+        owner: String,
         limit: Option<u8>,
-        start_after: Option<String>,
+        start_after: Option<u64>,
+    },
+
+    #[returns(BackstopResponse)]
+    Backstop { denom: String, addr: String },
+
+    /// The receipt tokens `addr` holds of every pool, valued in the underlying including pending
+    /// interest. Term deposits aren't included
+    #[returns(DepositorResponse)]
+    Depositor { addr: String },
+
+    /// The change queued with [SudoMsg::ProposeConfig], if any
+    #[returns(Option<PendingConfigResponse>)]
+    PendingConfig { denom: String },
+
+    /// The rates observed at the most recent accruals of the pool, newest first. A bounded
+    /// history is retained, so older observations are dropped
+    #[returns(RateHistoryResponse)]
+    RateHistory { denom: String, limit: Option<u16> },
+
+    /// Samples the rates at `points` utilizations spaced evenly from 0 to 1 inclusive, under the
+    /// pool's interest model, or under `interest` to preview a proposed model. `points` is clamped
+    /// to between 2 and 101
+    #[returns(RateCurveResponse)]
+    RateCurve {
+        denom: String,
+        points: u16,
+        interest: Option<Interest>,
+    },
+
+    /// The rates once `borrow_delta` is borrowed, or repaid when negative, and `deposit_delta` is
+    /// deposited, or withdrawn when negative, from the pool's current state with interest accrued.
+    /// Fails when the deltas take debt or deposits below zero, or debt above deposits
+    #[returns(ProjectRatesResponse)]
+    ProjectRates {
+        denom: String,
+        borrow_delta: Int128,
+        deposit_delta: Int128,
     },
 }
 
+/// The fixed annual rate paid on a deposit locked for `duration` seconds
+#[cw_serde]
+pub struct TermRate {
+    pub duration: u64,
+    pub rate: Decimal,
+}
+
+#[cw_serde]
+pub struct PoolsResponse {
+    pub denoms: Vec<String>,
+}
+
 #[cw_serde]
 pub struct ConfigResponse {
     pub denom: String,
     pub interest: Interest,
+    /// The share of interest paid to the backstop
+    #[serde(default)]
+    pub backstop_share: Decimal,
+    /// Seconds between unbonding and withdrawing backstop shares
+    #[serde(default)]
+    pub backstop_unbonding: u64,
+    /// See [SudoMsg::SetIdleStrategy]
+    #[serde(default)]
+    pub idle_strategy: Option<IdleStrategy>,
+    /// The share of interest collected as a fee
+    #[serde(default)]
+    pub fee: Decimal,
+    /// See [ConfigChange::timelock]
+    #[serde(default)]
+    pub timelock: u64,
+    /// See [SudoMsg::SetSameBlockGuard]
+    #[serde(default)]
+    pub same_block_guard: bool,
+    /// See [SudoMsg::SetMaxConcentration]
+    #[serde(default)]
+    pub max_concentration: Option<Decimal>,
 }
 
 #[cw_serde]
@@ -83,14 +365,68 @@ pub struct StatusResponse {
     pub last_updated: Timestamp,
 
     pub utilization_ratio: Decimal,
-
+    // The effective debt rate, within the `min_lend_rate` and `max_debt_rate` of the Interest
     pub debt_rate: Decimal,
-
+    // The effective debt rate scaled by utilization
     pub lend_rate: Decimal,
     // Share pool that accounts for accrued debt interest
     pub debt_pool: PoolResponse,
     // Share pool that allocated collected debt interest to lenders
     pub deposit_pool: PoolResponse,
+    // Share pool of first-loss capital, allocated its share of debt interest
+    pub backstop_pool: PoolResponse,
+    // Deposits parked in the idle strategy, as of the last rebalance
+    #[serde(default)]
+    pub idle_deployed: Uint128,
+    // Cumulative debt written off against the backstop_pool and the deposit_pool
+    #[serde(default)]
+    pub written_off_backstop: Uint128,
+    #[serde(default)]
+    pub written_off_deposits: Uint128,
+    // Cumulative fixed interest paid to term deposits in excess of the floating rate earned by
+    // their principal, funded by the deposit_pool
+    #[serde(default)]
+    pub term_shortfall: Uint128,
+    // The held and idle deposits plus the outstanding debt, per unit of the deposit_pool and backstop_pool
+    #[serde(default)]
+    pub solvency_ratio: Decimal,
+    // Set when the solvency_ratio is below one, and depositors can't all be repaid in full
+    #[serde(default)]
+    pub insolvent: bool,
+}
+
+/// The rates charged over the period up to `timestamp`, sampled when interest was accrued to it
+#[cw_serde]
+pub struct RateObservation {
+    pub timestamp: Timestamp,
+    pub utilization: Decimal,
+    pub debt_rate: Decimal,
+    pub lend_rate: Decimal,
+}
+
+#[cw_serde]
+pub struct RateHistoryResponse {
+    pub observations: Vec<RateObservation>,
+}
+
+/// The rates charged at `utilization`. See [QueryMsg::RateCurve]
+#[cw_serde]
+pub struct RatePoint {
+    pub utilization: Decimal,
+    pub debt_rate: Decimal,
+    pub lend_rate: Decimal,
+}
+
+#[cw_serde]
+pub struct RateCurveResponse {
+    pub points: Vec<RatePoint>,
+}
+
+/// See [QueryMsg::ProjectRates]
+#[cw_serde]
+pub struct ProjectRatesResponse {
+    pub current: RatePoint,
+    pub projected: RatePoint,
 }
 
 #[cw_serde]
@@ -116,11 +452,18 @@ pub struct BorrowerResponse {
     pub shares: Uint128,
     /// The remaining amount of borrowable funds for this borrower
     pub available: Uint128,
+    /// See [SudoMsg::SetHealthCheck]
+    #[serde(default)]
+    pub health_check: Option<String>,
+    /// The borrower's current debt as a share of the pool's deposits
+    #[serde(default)]
+    pub concentration: Decimal,
 }
 
 #[cw_serde]
 pub struct BorrowersResponse {
     pub borrowers: Vec<BorrowerResponse>,
+    pub page: PageResponse<String>,
 }
 
 #[cw_serde]
@@ -133,6 +476,72 @@ pub struct DelegateResponse {
     pub shares: Uint128,
 }
 
+#[cw_serde]
+pub struct AllowanceResponse {
+    pub borrower: String,
+    pub spender: String,
+    pub allowance: Uint128,
+    /// The spender's outstanding debt, drawn against the allowance
+    pub drawn: Uint128,
+}
+
+#[cw_serde]
+pub struct TermsResponse {
+    pub terms: Vec<TermRate>,
+    /// The fee charged on the principal of a term deposit withdrawn before maturity
+    pub early_exit_fee: Decimal,
+}
+
+#[cw_serde]
+pub struct TermDepositResponse {
+    pub id: u64,
+    pub owner: String,
+    pub denom: String,
+    /// The principal deposited
+    pub amount: Uint128,
+    /// The fixed annual rate
+    pub rate: Decimal,
+    pub start: Timestamp,
+    pub maturity: Timestamp,
+    /// The amount that would be returned if withdrawn now
+    pub value: Uint128,
+}
+
+#[cw_serde]
+pub struct TermDepositsResponse {
+    pub deposits: Vec<TermDepositResponse>,
+}
+
+#[cw_serde]
+pub struct BackstopResponse {
+    pub addr: String,
+    pub denom: String,
+    /// Bonded shares of the backstop pool
+    pub shares: Uint128,
+    /// Shares unbonding until `release`
+    pub unbonding: Uint128,
+    pub release: Timestamp,
+    /// The current value of bonded and unbonding shares
+    pub value: Uint128,
+}
+
+#[cw_serde]
+pub struct DepositorResponse {
+    pub addr: String,
+    /// The pools that `addr` holds receipt tokens of
+    pub deposits: Vec<DepositResponse>,
+}
+
+#[cw_serde]
+pub struct DepositResponse {
+    pub denom: String,
+    pub receipt: String,
+    /// The receipt tokens held, ie shares of the deposit pool
+    pub shares: Uint128,
+    /// The amount of `denom` the shares can currently be withdrawn for
+    pub value: Uint128,
+}
+
 impl OracleValue for DelegateResponse {
     fn value_usd(&self, q: QuerierWrapper) -> Result<Decimal, OracleError> {
         coin(self.current.u128(), &self.borrower.denom).value_usd(q)
@@ -149,14 +558,38 @@ impl From<&Addr> for Vault {
 }
 
 impl Vault {
-    pub fn config(&self, q: QuerierWrapper) -> StdResult<ConfigResponse> {
-        q.query_wasm_smart(self.0.to_string(), &QueryMsg::Config {})
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    /// The denom of the pool that issues the receipt token `denom`, if it is one
+    pub fn underlying(denom: &str) -> Option<&str> {
+        denom.strip_prefix("x/ghost-vault/")
+    }
+
+    pub fn pools(&self, q: QuerierWrapper) -> StdResult<PoolsResponse> {
+        q.query_wasm_smart(self.0.to_string(), &QueryMsg::Pools {})
+    }
+
+    pub fn config(&self, q: QuerierWrapper, denom: &str) -> StdResult<ConfigResponse> {
+        q.query_wasm_smart(
+            self.0.to_string(),
+            &QueryMsg::Config {
+                denom: denom.to_string(),
+            },
+        )
     }
 
-    pub fn borrower(&self, q: QuerierWrapper, addr: &Addr) -> StdResult<BorrowerResponse> {
+    pub fn borrower(
+        &self,
+        q: QuerierWrapper,
+        denom: &str,
+        addr: &Addr,
+    ) -> StdResult<BorrowerResponse> {
         q.query_wasm_smart(
             self.0.to_string(),
             &QueryMsg::Borrower {
+                denom: denom.to_string(),
                 addr: addr.to_string(),
             },
         )
@@ -164,12 +597,14 @@ impl Vault {
     pub fn delegate(
         &self,
         q: QuerierWrapper,
+        denom: &str,
         borrower: &Addr,
         addr: &Addr,
     ) -> StdResult<DelegateResponse> {
         q.query_wasm_smart(
             self.0.to_string(),
             &QueryMsg::Delegate {
+                denom: denom.to_string(),
                 addr: addr.to_string(),
                 borrower: borrower.to_string(),
             },
@@ -201,6 +636,7 @@ impl Vault {
     ) -> StdResult<CosmosMsg> {
         self.market_msg(
             MarketMsg::Borrow {
+                denom: amount.denom.clone(),
                 amount: amount.amount,
                 callback,
                 delegate,
@@ -208,4 +644,41 @@ impl Vault {
             vec![],
         )
     }
+
+    pub fn market_msg_transfer_debt(
+        &self,
+        denom: &str,
+        from_delegate: String,
+        to_delegate: String,
+        shares: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        self.market_msg(
+            MarketMsg::TransferDebt {
+                denom: denom.to_string(),
+                from_delegate,
+                to_delegate,
+                shares,
+            },
+            vec![],
+        )
+    }
+
+    pub fn market_msg_flash_loan(
+        &self,
+        amount: &Coin,
+        callback: CallbackData,
+    ) -> StdResult<CosmosMsg> {
+        self.market_msg(
+            MarketMsg::FlashLoan {
+                denom: amount.denom.clone(),
+                amount: amount.amount,
+                callback,
+            },
+            vec![],
+        )
+    }
+
+    pub fn market_msg_flash_repay(&self, amount: &Coin) -> StdResult<CosmosMsg> {
+        self.market_msg(MarketMsg::FlashRepay {}, vec![amount.clone()])
+    }
 }
@@ -1,4 +1,6 @@
+pub mod idle;
 pub mod interest;
 pub mod interface;
+pub use idle::*;
 pub use interest::*;
 pub use interface::*;
@@ -0,0 +1,74 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{to_json_binary, Addr, CosmosMsg, StdResult, WasmMsg};
+
+use crate::{PageRequest, PageResponse};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Code ids of the ghost-vault contracts permitted to register their pools
+    pub code_ids: Vec<u64>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Submits a pool of the sending ghost-vault for approval with [SudoMsg::Approve]. Sent by the
+    /// vault as each pool is created. Re-registering the approved vault of a denom is a no-op
+    Register { denom: String, receipt: String },
+}
+
+impl ExecuteMsg {
+    pub fn call(&self, address: &Addr) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: address.to_string(),
+            msg: to_json_binary(self)?,
+            funds: vec![],
+        }
+        .into())
+    }
+}
+
+#[cw_serde]
+pub enum SudoMsg {
+    /// Sets the code ids of the ghost-vault contracts permitted to register their pools
+    SetCodeIds(Vec<u64>),
+    /// Approves the registration of the vault at `address` for `denom`, replacing any existing vault
+    Approve { denom: String, address: String },
+    /// Removes the vault registered for `denom`
+    Deregister { denom: String },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(ConfigResponse)]
+    Config {},
+
+    /// The vault registered for `denom`
+    #[returns(VaultResponse)]
+    Vault { denom: String },
+
+    /// Paginate registered vaults by denom. Upper limit of 100 per page
+    #[returns(VaultsResponse)]
+    Vaults { page: PageRequest<String> },
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub code_ids: Vec<u64>,
+}
+
+#[cw_serde]
+pub struct VaultResponse {
+    /// The denom lent by the pool
+    pub denom: String,
+    /// The ghost-vault contract holding the pool
+    pub address: Addr,
+    /// The receipt token issued for deposits into the pool
+    pub receipt: String,
+}
+
+#[cw_serde]
+pub struct VaultsResponse {
+    pub vaults: Vec<VaultResponse>,
+    pub page: PageResponse<String>,
+}
@@ -1,5 +1,7 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Timestamp, Uint128};
+use cosmwasm_std::{Decimal, Timestamp, Uint128};
+
+use crate::{PageRequest, PageResponse};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -20,6 +22,34 @@ pub struct InstantiateMsg {
 
     /// The end of the merge period, when deposit ratio = `0`
     pub decay_ends_at: Timestamp,
+
+    /// Optional caps on the amount of `merge_denom` deposited in each window
+    #[serde(default)]
+    pub deposit_caps: Option<DepositCaps>,
+}
+
+/// Caps on the amount of `[InstantiateMsg::merge_denom]` deposited in each `window` seconds,
+/// counted from `start`. Deposits before `start` count towards the first window
+#[cw_serde]
+pub struct DepositCaps {
+    pub start: Timestamp,
+    pub window: u64,
+    /// The maximum deposited by a single account in a window
+    pub account: Option<Uint128>,
+    /// The maximum deposited by all accounts in a window
+    pub global: Option<Uint128>,
+}
+
+impl DepositCaps {
+    /// The index of the window that `now` falls in
+    pub fn window_at(&self, now: &Timestamp) -> u64 {
+        now.seconds().saturating_sub(self.start.seconds()) / self.window
+    }
+
+    /// The time at which `window` ends, and the caps reset
+    pub fn window_end(&self, window: u64) -> Timestamp {
+        self.start.plus_seconds((window + 1) * self.window)
+    }
 }
 
 #[cw_serde]
@@ -36,7 +66,37 @@ pub enum ExecuteMsg {
 
     /// Withdraws the amount `[InstantiateMsg::ruji_denom]` allocated to the `share_amount`, and decreases the Share Account of `info.sender`
     ///
-    Withdraw { share_amount: Uint128 },
+    /// When `payout` is provided, the withdrawn amount is sent according to it instead of to `info.sender`
+    Withdraw {
+        share_amount: Uint128,
+        payout: Option<Payout>,
+    },
+}
+
+#[cw_serde]
+pub enum Payout {
+    /// Swaps the withdrawn $RUJI to `to` on THORChain, eg `BTC.BTC`, sending the output to `destination`.
+    /// The memo is built by the contract, and refunds `info.sender` if the swap fails or returns less than `min_return`
+    Swap {
+        to: String,
+        destination: String,
+        min_return: Option<Uint128>,
+    },
+}
+
+#[cw_serde]
+pub enum SudoMsg {
+    /// Sets or removes the deposit caps. Amounts already deposited in the current window are retained
+    SetDepositCaps(Option<DepositCaps>),
+
+    /// Moves the decay schedule, and pauses or resumes it, eg while an outage prevents merging.
+    /// The schedule can only be moved before its decay starts. While paused the decay factor is held,
+    /// and on resuming the schedule is delayed by the time spent paused
+    UpdateSchedule {
+        decay_starts_at: Timestamp,
+        decay_ends_at: Timestamp,
+        paused: bool,
+    },
 }
 
 #[cw_serde]
@@ -55,6 +115,14 @@ pub enum QueryMsg {
 
     #[returns(AccountResponse)]
     Account { addr: String },
+
+    /// Paginate all accounts by address. Upper limit of 100 per page
+    #[returns(AccountsResponse)]
+    Accounts { page: PageRequest<String> },
+
+    /// The remaining amount that `addr` can deposit in the current deposit cap window
+    #[returns(CapacityResponse)]
+    Capacity { addr: String },
 }
 
 #[cw_serde]
@@ -65,6 +133,8 @@ pub struct ConfigResponse {
     pub ruji_allocation: Uint128,
     pub decay_starts_at: Timestamp,
     pub decay_ends_at: Timestamp,
+    #[serde(default)]
+    pub deposit_caps: Option<DepositCaps>,
 }
 
 #[cw_serde]
@@ -77,6 +147,20 @@ pub struct StatusResponse {
 
     /// Total `[InstantiateMsg::ruji_denom]` allocated to shareholders
     pub size: Uint128,
+
+    pub schedule: ScheduleResponse,
+}
+
+/// The decay schedule, see [SudoMsg::UpdateSchedule]
+#[cw_serde]
+pub struct ScheduleResponse {
+    pub decay_starts_at: Timestamp,
+    pub decay_ends_at: Timestamp,
+    pub paused: bool,
+    /// The seconds the schedule has spent paused, which its decay is delayed by
+    pub paused_for: u64,
+    /// The current `decay_factor`, from `1` until `decay_starts_at` to `0` at `decay_ends_at`
+    pub decay_factor: Decimal,
 }
 
 #[cw_serde]
@@ -92,3 +176,21 @@ pub struct AccountResponse {
     /// Total `[InstantiateMsg::ruji_denom]` allocation that `shares` represents
     pub size: Uint128,
 }
+
+#[cw_serde]
+pub struct AccountsResponse {
+    pub accounts: Vec<AccountResponse>,
+    pub page: PageResponse<String>,
+}
+
+#[cw_serde]
+pub struct CapacityResponse {
+    /// The remaining amount the account can deposit in the window. `None` if uncapped
+    pub account: Option<Uint128>,
+
+    /// The remaining amount all accounts can deposit in the window. `None` if uncapped
+    pub global: Option<Uint128>,
+
+    /// The end of the current window. `None` if no caps are set
+    pub resets_at: Option<Timestamp>,
+}
@@ -6,12 +6,16 @@ pub mod bow;
 pub mod demo;
 #[cfg(feature = "interface-fin")]
 pub mod fin;
+#[cfg(feature = "interface-fin-router")]
+pub mod fin_router;
 #[cfg(feature = "interface-ghost")]
 pub mod ghost;
 #[cfg(feature = "interface-merge")]
 pub mod merge;
 #[cfg(feature = "interface-mint")]
 pub mod mint;
+#[cfg(feature = "interface-orca")]
+pub mod orca;
 #[cfg(feature = "interface-pilot")]
 pub mod pilot;
 #[cfg(feature = "interface-revenue")]
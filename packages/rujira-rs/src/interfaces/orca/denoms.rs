@@ -0,0 +1,20 @@
+use cosmwasm_schema::cw_serde;
+
+#[cw_serde]
+pub struct Denoms([String; 2]);
+
+impl Denoms {
+    pub fn new(collateral: &str, bid: &str) -> Self {
+        Self([collateral.to_string(), bid.to_string()])
+    }
+
+    /// The denom that is liquidated into the queue, and claimed by bidders
+    pub fn collateral(&self) -> &str {
+        self.0[0].as_str()
+    }
+
+    /// The denom that bidders deposit, and liquidations are paid in
+    pub fn bid(&self) -> &str {
+        self.0[1].as_str()
+    }
+}
@@ -0,0 +1,55 @@
+use crate::CallbackData;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal, Uint128};
+
+use super::Denoms;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The denoms of the queue. The first is the collateral denom, the second is the bid denom.
+    /// Both must be secured assets, valued by the oracle
+    pub denoms: Denoms,
+
+    /// The contracts that are allowed to liquidate collateral into the queue
+    pub markets: Vec<String>,
+
+    /// The maximum premium that can be bid (in %)
+    pub max_premium: u8,
+
+    /// The fee charged on collateral claimed by bidders
+    pub fee: Decimal,
+
+    /// The destination address for fees collected
+    pub fee_address: String,
+}
+
+/// Callable interfaces
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Manage all bids
+    /// Submit a list of premium and bid amounts
+    /// 0. All filled bids will be claimed
+    /// For each entry:
+    /// 1. If no bid exists at that premium, one will be created
+    /// 2. If a bid exists, and its amount is greater than the target amount, it will be retracted
+    /// 3. If its amount is less than the target amount, it will be increased
+    ///
+    /// Funds sent must be equal to the net change of balances. Collateral claimed in step 0 is returned,
+    /// and bid denom retracted in 2 can be reused to fund bids in 1 and 3
+    Bid(Vec<(u8, Uint128)>),
+
+    /// Claims the filled collateral of all of the sender's bids
+    Claim {},
+
+    /// Sells the collateral sent to the bids in the queue, from the lowest premium upwards, at the
+    /// oracle price less each premium. Only callable by a configured market
+    Liquidate {
+        min_return: Option<Uint128>,
+        to: Option<String>,
+
+        /// An optional callback that ORCA will execute with the funds from the liquidation.
+        /// The callback is executed on the sender's address.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        callback: Option<CallbackData>,
+    },
+}
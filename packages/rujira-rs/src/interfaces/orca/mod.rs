@@ -0,0 +1,9 @@
+mod denoms;
+mod execute;
+mod query;
+mod sudo;
+
+pub use denoms::Denoms;
+pub use execute::{ExecuteMsg, InstantiateMsg};
+pub use query::*;
+pub use sudo::SudoMsg;
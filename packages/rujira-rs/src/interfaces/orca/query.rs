@@ -0,0 +1,103 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Coin, Decimal, Timestamp, Uint128};
+
+use super::Denoms;
+use crate::{PageRequest, PageResponse};
+
+/// Standard interface to query contract state
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(ConfigResponse)]
+    Config {},
+
+    /// Simulates the liquidation of an amount of collateral
+    #[returns(SimulationResponse)]
+    Simulate(Coin),
+
+    /// Find a specific bid for a user at a premium
+    #[returns(BidResponse)]
+    Bid((String, u8)),
+
+    /// Paginate user bids. Upper limit of 31 per page
+    #[returns(BidsResponse)]
+    Bids {
+        owner: String,
+        offset: Option<u8>,
+        limit: Option<u8>,
+    },
+
+    /// Paginate bid pools by premium. Upper limit of 31 per page
+    #[returns(PoolsResponse)]
+    Pools { page: PageRequest<u8> },
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    /// See [InstantiateMsg::denoms]
+    pub denoms: Denoms,
+
+    /// See [InstantiateMsg::markets]
+    pub markets: Vec<String>,
+
+    /// See [InstantiateMsg::max_premium]
+    pub max_premium: u8,
+
+    /// See [InstantiateMsg::fee]
+    pub fee: Decimal,
+
+    /// See [InstantiateMsg::fee_address]
+    pub fee_address: String,
+}
+
+#[cw_serde]
+pub struct BidResponse {
+    /// The account which placed the bid
+    pub owner: String,
+
+    /// The premium of this bid, in %
+    pub premium: u8,
+
+    /// The rate at which this bid would be filled at the current moment in time
+    pub rate: Decimal,
+
+    /// The last time this bid was touched (created, incremented or reduced) in a Bid execution
+    pub updated_at: Timestamp,
+
+    /// Bid amount at updated_at time
+    pub offer: Uint128,
+
+    /// The remaining bid amount
+    pub remaining: Uint128,
+
+    /// Amount of collateral awaiting claim
+    pub filled: Uint128,
+}
+
+#[cw_serde]
+pub struct BidsResponse {
+    pub bids: Vec<BidResponse>,
+}
+
+#[cw_serde]
+pub struct PoolsResponse {
+    pub pools: Vec<PoolResponse>,
+    pub page: PageResponse<u8>,
+}
+
+#[cw_serde]
+pub struct PoolResponse {
+    pub premium: u8,
+    pub epoch: u32,
+    pub price: Decimal,
+    pub total: Uint128,
+}
+
+#[cw_serde]
+pub struct SimulationResponse {
+    /// The amount of bid denom returned
+    pub returned: Uint128,
+
+    /// The amount of collateral that could not be filled by the queue
+    pub remaining: Uint128,
+}
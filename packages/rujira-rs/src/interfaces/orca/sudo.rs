@@ -0,0 +1,11 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Decimal;
+
+#[cw_serde]
+pub enum SudoMsg {
+    UpdateConfig {
+        markets: Option<Vec<String>>,
+        fee: Option<Decimal>,
+        fee_address: Option<String>,
+    },
+}
@@ -108,7 +108,7 @@ impl From<Addr> for Vault {
 impl Vault {
     pub fn borrow_msg(
         &self,
-        amount: Uint128,
+        amount: Coin,
         to: String,
         callback: Option<CallbackData>,
     ) -> StdResult<WasmMsg> {
@@ -116,7 +116,8 @@ impl Vault {
             contract_addr: self.0.to_string(),
             msg: to_json_binary(&ghost::vault::ExecuteMsg::Market(
                 ghost::vault::MarketMsg::Borrow {
-                    amount,
+                    denom: amount.denom,
+                    amount: amount.amount,
                     callback: Some(to_json_binary(&Callback { to, callback })?.into()),
                     delegate: None,
                 },
@@ -135,20 +136,22 @@ impl Vault {
         })
     }
 
-    pub fn debt(&self, q: QuerierWrapper, owner: &Addr) -> StdResult<Uint128> {
+    pub fn debt(&self, q: QuerierWrapper, denom: &str, owner: &Addr) -> StdResult<Uint128> {
         let res: ghost::vault::BorrowerResponse = q.query_wasm_smart(
             self.0.clone(),
             &ghost::vault::QueryMsg::Borrower {
+                denom: denom.to_string(),
                 addr: owner.to_string(),
             },
         )?;
         Ok(res.current)
     }
 
-    pub fn available(&self, q: QuerierWrapper, owner: &Addr) -> StdResult<Uint128> {
+    pub fn available(&self, q: QuerierWrapper, denom: &str, owner: &Addr) -> StdResult<Uint128> {
         let res: ghost::vault::BorrowerResponse = q.query_wasm_smart(
             self.0.clone(),
             &ghost::vault::QueryMsg::Borrower {
+                denom: denom.to_string(),
                 addr: owner.to_string(),
             },
         )?;
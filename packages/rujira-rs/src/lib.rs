@@ -20,6 +20,7 @@ mod msg;
 mod native_balance_plus;
 #[cfg(feature = "oracle")]
 mod oracle;
+mod page;
 #[cfg(feature = "premium")]
 mod premium;
 #[cfg(feature = "proto")]
@@ -38,20 +39,26 @@ mod token_factory;
 pub use account_pool::{AccountPool, AccountPoolAccount};
 #[cfg(feature = "asset")]
 pub use asset::{
-    Asset, AssetError, Layer1Asset, Layer1AssetError, SecuredAsset, SecuredAssetError,
+    Asset, AssetError, AssetKind, AssetNotation, Layer1Asset, Layer1AssetError, SecuredAsset,
+    SecuredAssetError,
 };
 
 #[cfg(feature = "callback")]
 pub use callback::{CallbackData, CallbackMsg};
+#[cfg(feature = "coin")]
+pub use coin::Coin;
 #[cfg(feature = "decimal-scaled")]
 pub use decimal_scaled::DecimalScaled;
 pub use interfaces::*;
 #[cfg(feature = "merge-n-by-iter")]
 pub use merge_n_by_iter::MergeNByIter;
+#[cfg(feature = "coin")]
+pub use msg::swap::{Destination, MsgSwap, Slip};
 #[cfg(feature = "native-balance-plus")]
 pub use native_balance_plus::NativeBalancePlus;
 #[cfg(feature = "oracle")]
 pub use oracle::{Oracle, OracleError, OracleValue};
+pub use page::{PageRequest, PageResponse};
 #[cfg(feature = "premium")]
 pub use premium::Premiumable;
 #[cfg(feature = "share-pool")]
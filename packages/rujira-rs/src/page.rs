@@ -0,0 +1,89 @@
+use cosmwasm_schema::cw_serde;
+
+/// Pagination parameters shared by list queries.
+/// Results are returned in key order, starting after the `start_after` key
+#[cw_serde]
+pub struct PageRequest<T> {
+    /// The key to start after, typically [PageResponse::next] of the previous page
+    pub start_after: Option<T>,
+    /// Number of results to return. Capped at the maximum page size of each query
+    pub limit: Option<u8>,
+}
+
+impl<T> Default for PageRequest<T> {
+    fn default() -> Self {
+        Self {
+            start_after: None,
+            limit: None,
+        }
+    }
+}
+
+impl<T> PageRequest<T> {
+    pub fn new(start_after: Option<T>, limit: Option<u8>) -> Self {
+        Self { start_after, limit }
+    }
+
+    /// The number of results to return, defaulting to `default` and capped at `max`
+    pub fn limit(&self, default: u8, max: u8) -> usize {
+        self.limit.unwrap_or(default).min(max) as usize
+    }
+
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<PageRequest<U>, E> {
+        Ok(PageRequest {
+            start_after: self.start_after.map(f).transpose()?,
+            limit: self.limit,
+        })
+    }
+}
+
+#[cw_serde]
+pub struct PageResponse<T> {
+    /// The `start_after` key for the next page. `None` when there are no more results
+    pub next: Option<T>,
+}
+
+impl<T> Default for PageResponse<T> {
+    fn default() -> Self {
+        Self { next: None }
+    }
+}
+
+impl<T> PageResponse<T> {
+    /// Builds the response for a page of `items` requested with `limit`.
+    /// A full page sets the cursor to the key of the last item
+    pub fn new<I>(items: &[I], limit: usize, key: impl FnOnce(&I) -> T) -> Self {
+        if items.len() < limit {
+            return Self::default();
+        }
+        Self {
+            next: items.last().map(key),
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> PageResponse<U> {
+        PageResponse {
+            next: self.next.map(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit() {
+        assert_eq!(PageRequest::<u64>::default().limit(10, 30), 10);
+        assert_eq!(PageRequest::<u64>::new(None, Some(20)).limit(10, 30), 20);
+        assert_eq!(PageRequest::<u64>::new(None, Some(100)).limit(10, 30), 30);
+    }
+
+    #[test]
+    fn next() {
+        let items = vec![1u64, 2, 3];
+        assert_eq!(PageResponse::new(&items, 3, |x| *x).next, Some(3));
+        assert_eq!(PageResponse::new(&items, 4, |x| *x).next, None);
+        assert_eq!(PageResponse::new(&items[..0], 0, |x| *x).next, None);
+    }
+}
@@ -1,8 +1,24 @@
-use cosmwasm_std::Decimal;
+use cosmwasm_std::{Decimal, Timestamp};
 
 // Provided as a Trait so that tests can mock an oracle price source
 pub trait Premiumable {
     fn adjust(&self, bps: &i16) -> Decimal;
+
+    /// Converts a rate quoted in human units to atomic units. Identity unless wrapped with a decimal delta
+    fn scale(&self, rate: Decimal) -> Decimal {
+        rate
+    }
+
+    /// The time that time-dependent premiums are evaluated at. Premiums are quoted at their
+    /// starting value unless overridden
+    fn time(&self) -> Timestamp {
+        Timestamp::default()
+    }
+
+    /// Whether a price is available to [Premiumable::adjust]
+    fn available(&self) -> bool {
+        true
+    }
 }
 
 impl Premiumable for Decimal {
@@ -22,4 +38,8 @@ impl<T: Premiumable> Premiumable for Option<T> {
             None => panic!("No price to adjust for Premium"),
         }
     }
+
+    fn available(&self) -> bool {
+        self.as_ref().is_some_and(|x| x.available())
+    }
 }
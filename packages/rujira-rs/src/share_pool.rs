@@ -33,6 +33,55 @@ impl SharePool {
         Ok(issuance.to_uint_floor())
     }
 
+    /// Adds a deposit to the pool, pricing the issuance as though `offset` shares and assets were
+    /// already in the pool, so that an early member can't inflate the share price to round down the
+    /// issuance of later members. Shares issued this way must be valued and redeemed with the same
+    /// `offset`, see [SharePool::ownership_virtual] and [SharePool::leave_virtual]
+    pub fn join_virtual(
+        &mut self,
+        amount: Uint128,
+        offset: Uint128,
+    ) -> Result<Uint128, SharePoolError> {
+        if amount.is_zero() {
+            return Err(SharePoolError::Zero("Amount".to_string()));
+        }
+
+        let issuance = amount.multiply_ratio(self.shares() + offset, self.size + offset);
+        if issuance.is_zero() {
+            return Err(SharePoolError::Zero("Shares".to_string()));
+        }
+
+        self.shares.add_assign(Decimal::from_ratio(issuance, 1u128));
+        self.size.add_assign(amount);
+        Ok(issuance)
+    }
+
+    /// Removes shares issued with [SharePool::join_virtual], returning the amount removed from the
+    /// pool. The share of the pool owned by the virtual shares stays in the pool
+    pub fn leave_virtual(
+        &mut self,
+        amount: Uint128,
+        offset: Uint128,
+    ) -> Result<Uint128, SharePoolError> {
+        if amount.is_zero() {
+            return Err(SharePoolError::Zero("Amount".to_string()));
+        }
+
+        if amount.gt(&self.shares()) {
+            return Err(SharePoolError::ShareOverflow {});
+        }
+
+        let claim = self.ownership_virtual(amount, offset);
+        self.size.sub_assign(claim);
+        self.shares.sub_assign(Decimal::from_ratio(amount, 1u128));
+        Ok(claim)
+    }
+
+    /// The value of shares issued with [SharePool::join_virtual]
+    pub fn ownership_virtual(&self, shares: Uint128, offset: Uint128) -> Uint128 {
+        shares.multiply_ratio(self.size + offset, self.shares() + offset)
+    }
+
     /// Removes a share from the pool, returning the amount removed from the pool and deducted
     pub fn leave(&mut self, amount: Uint128) -> Result<Uint128, SharePoolError> {
         if amount.is_zero() {
@@ -78,6 +127,15 @@ impl SharePool {
         self.size.multiply_ratio(shares, self.shares())
     }
 
+    /// Removes an amount from the pool without burning shares, reducing the value of every share
+    pub fn withdraw(&mut self, amount: Uint128) -> Result<(), SharePoolError> {
+        if amount.gt(&self.size) {
+            return Err(SharePoolError::InvalidWithdraw {});
+        }
+        self.size.sub_assign(amount);
+        Ok(())
+    }
+
     pub fn deposit(&mut self, amount: Uint128) -> Result<(), SharePoolError> {
         let mut checked = self.clone();
         checked.size.add_assign(amount);
@@ -104,6 +162,8 @@ pub enum SharePoolError {
     Zero(String),
     #[error("InvalidDeposit")]
     InvalidDeposit {},
+    #[error("InvalidWithdraw")]
+    InvalidWithdraw {},
 }
 
 #[cfg(test)]
@@ -161,6 +221,39 @@ mod tests {
         assert_eq!(pool.size, Uint128::zero());
     }
 
+    #[test]
+    fn virtual_offset() {
+        let offset = Uint128::from(1000u128);
+        let mut pool = SharePool::default();
+        let shares = pool.join_virtual(Uint128::from(1000u128), offset).unwrap();
+        assert_eq!(shares, Uint128::from(1000u128));
+        pool.deposit(Uint128::from(200u128)).unwrap();
+
+        // The virtual shares own their part of the growth
+        assert_eq!(
+            pool.ownership_virtual(shares, offset),
+            Uint128::from(1100u128)
+        );
+
+        // Shares are redeemed at the price they are issued at, so a deposit can't be withdrawn
+        // for more than it put in
+        let joined = pool.join_virtual(Uint128::from(1100u128), offset).unwrap();
+        assert_eq!(joined, Uint128::from(1000u128));
+        assert_eq!(
+            pool.leave_virtual(joined, offset).unwrap(),
+            Uint128::from(1100u128)
+        );
+
+        pool.leave_virtual(Uint128::from(1001u128), offset)
+            .unwrap_err();
+        assert_eq!(
+            pool.leave_virtual(shares, offset).unwrap(),
+            Uint128::from(1100u128)
+        );
+        assert_eq!(pool.shares(), Uint128::zero());
+        assert_eq!(pool.size(), Uint128::from(100u128));
+    }
+
     #[test]
     fn overflow() {
         let mut pool = SharePool::default();